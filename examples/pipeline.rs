@@ -0,0 +1,19 @@
+extern crate subparse;
+
+use std::path::Path;
+use subparse::pipeline::Pipeline;
+
+fn main() {
+    // runs the jobs of a pipeline file like `cargo run --example pipeline -- jobs.yaml`
+    let path = std::env::args().nth(1).expect("usage: pipeline <file.yaml>");
+    let pipeline = Pipeline::load(Path::new(&path)).unwrap();
+    for report in pipeline.run().unwrap() {
+        println!("{} -> {}", report.input.display(), report.output.display());
+        for warning in &report.qc {
+            println!("  qc: {}", warning);
+        }
+        for warning in &report.conversion {
+            println!("  conversion: {}", warning);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::ffi::OsStr;
+use subparse::{get_subtitle_format, parse_bytes, SubtitleFormat};
+
+// Detects the format of arbitrary bytes, decodes them and writes the parsed file again.
+//
+// Binary VobSub data is skipped: it is decoded by the `vobsub` crate, which makes no panic-free guarantee.
+fuzz_target!(|data: &[u8]| {
+    for extension in ["srt", "ssa", "ass", "idx", "sub", "stl", "pac", "sup"].iter() {
+        match get_subtitle_format(Some(OsStr::new(extension)), data) {
+            None | Some(SubtitleFormat::VobSubSub) => {}
+            Some(format) => {
+                if let Ok(file) = parse_bytes(format, data, None, 23.976) {
+                    let _ = file.get_subtitle_entries();
+                    let _ = file.to_data();
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,45 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use subparse::{parse_str, SubtitleFormat};
+
+// Parses the input with every text format, and modifies and writes every file that could be parsed.
+fuzz_target!(|data: &str| {
+    let formats = [
+        SubtitleFormat::SubRip,
+        SubtitleFormat::SubStationAlpha,
+        SubtitleFormat::VobSubIdx,
+        SubtitleFormat::MicroDVD,
+        SubtitleFormat::WebVtt,
+        SubtitleFormat::Ttml,
+        SubtitleFormat::Sbv,
+        SubtitleFormat::Lrc,
+        SubtitleFormat::Scc,
+        SubtitleFormat::Mpl2,
+        SubtitleFormat::JacoSub,
+        SubtitleFormat::SubViewer,
+        SubtitleFormat::RealText,
+        SubtitleFormat::Itt,
+        SubtitleFormat::Bdn,
+        SubtitleFormat::SpruceStl,
+        SubtitleFormat::AqTitle,
+        SubtitleFormat::VPlayer,
+        SubtitleFormat::PowerDivX,
+        SubtitleFormat::Srv3,
+        SubtitleFormat::Sami,
+    ];
+    for &format in formats.iter() {
+        if let Ok(mut file) = parse_str(format, data, 25.0) {
+            let mut entries = file.get_subtitle_entries().unwrap();
+            for entry in &mut entries {
+                entry.timespan = entry.timespan - entry.timespan.len();
+            }
+            file.update_subtitle_entries(&entries).unwrap();
+            file.to_data().unwrap();
+
+            let keep: Vec<bool> = (0..entries.len()).map(|i| i % 2 == 0).collect();
+            file.retain_entries_by_mask(&keep).unwrap();
+            file.to_data().unwrap();
+        }
+    }
+});
@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Importers for the output of forced aligners like [Gentle](https://github.com/lowerquality/gentle)
+//! and [aeneas](https://github.com/readbeyond/aeneas).
+//!
+//! A forced aligner takes a plain text script and an audio file, and computes when each word or fragment
+//! is spoken. These functions turn that output into `SubtitleEntry`s, which can then be written into any
+//! format that can be created from scratch (like `.srt` with `alignment_to_srt()`).
+//!
+//! Speech recognizers like [Whisper](https://github.com/openai/whisper) also output word timings with a
+//! confidence, which `parse_whisper_words()` reads for `retiming::WordSnapping`.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::json::{self, Value};
+use crate::timetypes::{TimePoint, TimeSpan};
+use crate::{SrtFile, SubtitleEntry};
+use failure::ResultExt;
+
+/// Errors specific to reading alignment files.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "invalid JSON: {}", msg)]
+        InvalidJson { msg: String },
+
+        #[fail(display = "the alignment data has no `{}` field", f)]
+        MissingField { f: &'static str },
+
+        #[fail(display = "the alignment entry {} has an invalid `{}` value", index, f)]
+        InvalidField { index: usize, f: &'static str },
+
+        #[fail(display = "expected `id,begin,end,text` in CSV line {}, found '{}'", line_num, line)]
+        InvalidCsvLine { line_num: usize, line: String },
+    }
+}
+
+/// The supported output formats of forced aligners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlignmentFormat {
+    /// The JSON output of Gentle (`{"transcript": ..., "words": [...]}`) with word-level timings.
+    GentleJson,
+
+    /// The JSON output of aeneas (`{"fragments": [...]}`).
+    AeneasJson,
+
+    /// The CSV output of aeneas (`id,begin,end,"text"`).
+    AeneasCsv,
+}
+
+/// Reads the aligner output and returns one `SubtitleEntry` per aligned fragment.
+///
+/// Gentle only aligns single words, so the words are grouped by the line of the transcript they appear in.
+/// Words which could not be found in the audio are skipped, as are transcript lines without any aligned word.
+pub fn parse_alignment(format: AlignmentFormat, s: &str) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+    let result = match format {
+        AlignmentFormat::GentleJson => parse_gentle_json(s),
+        AlignmentFormat::AeneasJson => parse_aeneas_json(s),
+        AlignmentFormat::AeneasCsv => parse_aeneas_csv(s),
+    };
+    Ok(result.with_context(|_| crate::ErrorKind::ParsingError)?)
+}
+
+/// A recognized word with its timing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedWord {
+    /// When the word is spoken.
+    pub timespan: TimeSpan,
+
+    /// The word (without surrounding spaces).
+    pub text: String,
+
+    /// How sure the recognizer is about the word, from `0.0` to `1.0`.
+    pub confidence: f64,
+}
+
+/// Reads the words of the JSON output of Whisper (`{"segments": [{"words": [...]}]}`, created with word
+/// timestamps).
+///
+/// The confidence is read from `probability` (or `score`, as written by WhisperX); words without timing are
+/// skipped.
+pub fn parse_whisper_words(s: &str) -> SubtitleParserResult<Vec<TimedWord>> {
+    Ok(parse_whisper_json(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+}
+
+fn parse_whisper_json(s: &str) -> Result<Vec<TimedWord>> {
+    let root = read_json(s)?;
+    let segments = root.get("segments").and_then(Value::as_array).ok_or(MissingField { f: "segments" })?;
+
+    let mut result = Vec::new();
+    for word in segments
+        .iter()
+        .filter_map(|segment| segment.get("words").and_then(Value::as_array))
+        .flatten()
+    {
+        let index = result.len();
+        let (start, end) = match (word.get("start").and_then(Value::as_f64), word.get("end").and_then(Value::as_f64)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        let text = word.get("word").and_then(Value::as_str).ok_or(InvalidField { index, f: "word" })?;
+        let confidence = word
+            .get("probability")
+            .or_else(|| word.get("score"))
+            .and_then(Value::as_f64)
+            .unwrap_or(1.0);
+        result.push(TimedWord {
+            timespan: TimeSpan::new(secs_to_timepoint(start), secs_to_timepoint(end)),
+            text: text.trim().to_string(),
+            confidence,
+        });
+    }
+    Ok(result)
+}
+
+/// Reads the aligner output and creates a `.srt` file from it.
+pub fn alignment_to_srt(format: AlignmentFormat, s: &str) -> SubtitleParserResult<SrtFile> {
+    let entries = parse_alignment(format, s)?;
+    SrtFile::create(entries.into_iter().map(|e| (e.timespan, e.line.unwrap_or_default())).collect())
+}
+
+fn secs_to_timepoint(secs: f64) -> TimePoint {
+    TimePoint::from_msecs((secs * 1000.0).round() as i64)
+}
+
+fn read_json(s: &str) -> Result<Value> {
+    json::parse(s).map_err(|e| InvalidJson { msg: e.to_string() }.into())
+}
+
+fn parse_gentle_json(s: &str) -> Result<Vec<SubtitleEntry>> {
+    let root = read_json(s)?;
+    let words = root.get("words").and_then(Value::as_array).ok_or(MissingField { f: "words" })?;
+    let transcript: Vec<char> = root.get("transcript").and_then(Value::as_str).unwrap_or("").chars().collect();
+
+    // (transcript line, start, end) of every aligned word
+    let mut aligned = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        if word.get("case").and_then(Value::as_str).is_some_and(|c| c != "success") {
+            continue;
+        }
+        let (start, end) = match (word.get("start").and_then(Value::as_f64), word.get("end").and_then(Value::as_f64)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => continue,
+        };
+        let line = match word.get("startOffset").and_then(Value::as_f64) {
+            Some(offset) if !transcript.is_empty() => {
+                let preceding = transcript.get(..offset as usize).ok_or(InvalidField { index, f: "startOffset" })?;
+                GentleLine::Transcript(preceding.iter().filter(|&&c| c == '\n').count())
+            }
+            _ => {
+                let text = word.get("word").and_then(Value::as_str).ok_or(InvalidField { index, f: "word" })?;
+                GentleLine::Word(text.to_string())
+            }
+        };
+        aligned.push((line, secs_to_timepoint(start), secs_to_timepoint(end)));
+    }
+
+    let transcript_lines: Vec<String> = transcript.iter().collect::<String>().split('\n').map(str::to_string).collect();
+    let mut result: Vec<(GentleLine, TimeSpan)> = Vec::new();
+    for (line, start, end) in aligned {
+        match result.last_mut() {
+            Some((last_line, timespan)) if *last_line == line && matches!(line, GentleLine::Transcript(_)) => timespan.end = end,
+            _ => result.push((line, TimeSpan::new(start, end))),
+        }
+    }
+
+    Ok(result
+        .into_iter()
+        .map(|(line, timespan)| {
+            let text = match line {
+                GentleLine::Transcript(i) => transcript_lines.get(i).map_or("", |l| l.trim()).to_string(),
+                GentleLine::Word(w) => w,
+            };
+            SubtitleEntry::new(timespan, text)
+        })
+        .collect())
+}
+
+/// Where the text of an aligned Gentle word comes from.
+#[derive(PartialEq)]
+enum GentleLine {
+    /// Index of the transcript line
+    Transcript(usize),
+
+    /// The word itself (no transcript offsets available)
+    Word(String),
+}
+
+fn parse_aeneas_json(s: &str) -> Result<Vec<SubtitleEntry>> {
+    let root = read_json(s)?;
+    let fragments = root.get("fragments").and_then(Value::as_array).ok_or(MissingField { f: "fragments" })?;
+
+    let mut result = Vec::new();
+    for (index, fragment) in fragments.iter().enumerate() {
+        let begin = fragment.get("begin").and_then(Value::as_f64).ok_or(InvalidField { index, f: "begin" })?;
+        let end = fragment.get("end").and_then(Value::as_f64).ok_or(InvalidField { index, f: "end" })?;
+        let lines = fragment
+            .get("lines")
+            .and_then(Value::as_array)
+            .ok_or(InvalidField { index, f: "lines" })?;
+        let text = lines.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n");
+
+        // aeneas can emit head/tail fragments without any text
+        if text.trim().is_empty() {
+            continue;
+        }
+        result.push(SubtitleEntry::new(TimeSpan::new(secs_to_timepoint(begin), secs_to_timepoint(end)), text));
+    }
+    Ok(result)
+}
+
+fn parse_aeneas_csv(s: &str) -> Result<Vec<SubtitleEntry>> {
+    let mut result = Vec::new();
+    for (line_num, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = || InvalidCsvLine {
+            line_num,
+            line: line.to_string(),
+        };
+        let mut fields = line.splitn(4, ',');
+        let (_id, begin, end, text) = match (fields.next(), fields.next(), fields.next(), fields.next()) {
+            (Some(id), Some(begin), Some(end), Some(text)) => (id, begin, end, text),
+            _ => return Err(invalid().into()),
+        };
+        let begin: f64 = begin.trim().parse().map_err(|_| invalid())?;
+        let end: f64 = end.trim().parse().map_err(|_| invalid())?;
+
+        result.push(SubtitleEntry::new(
+            TimeSpan::new(secs_to_timepoint(begin), secs_to_timepoint(end)),
+            unquote_csv_field(text.trim()),
+        ));
+    }
+    Ok(result)
+}
+
+/// Removes the quotes around a CSV field and replaces the `""` escape sequences.
+fn unquote_csv_field(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        s[1..s.len() - 1].replace("\"\"", "\"")
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts_and_times(entries: &[SubtitleEntry]) -> Vec<(i64, i64, &str)> {
+        entries
+            .iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.line.as_ref().unwrap().as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn gentle_groups_words_by_transcript_line() {
+        let input = r#"{"transcript": "Hello world.\nBye!", "words": [
+            {"alignedWord": "hello", "case": "success", "end": 0.9, "endOffset": 5, "start": 0.5, "startOffset": 0, "word": "Hello"},
+            {"alignedWord": "world", "case": "success", "end": 1.5, "endOffset": 11, "start": 1.0, "startOffset": 6, "word": "world"},
+            {"case": "not-found-in-audio", "endOffset": 16, "startOffset": 13, "word": "Bye"}
+        ]}"#;
+        let entries = parse_alignment(AlignmentFormat::GentleJson, input).unwrap();
+        assert_eq!(texts_and_times(&entries), vec![(500, 1500, "Hello world.")]);
+    }
+
+    #[test]
+    fn aeneas_json_and_csv() {
+        let input = r#"{"fragments": [
+            {"begin": "0.000", "end": "0.280", "id": "f000000", "language": "eng", "lines": [], "children": []},
+            {"begin": "0.280", "end": "2.640", "id": "f000001", "language": "eng", "lines": ["1", "From fairest creatures"], "children": []}
+        ]}"#;
+        let entries = parse_alignment(AlignmentFormat::AeneasJson, input).unwrap();
+        assert_eq!(texts_and_times(&entries), vec![(280, 2640, "1\nFrom fairest creatures")]);
+
+        let input = "f000001,0.000,1.320,\"Hello, \"\"world\"\"\"\nf000002,1.320,2.000,Bye\n";
+        let entries = parse_alignment(AlignmentFormat::AeneasCsv, input).unwrap();
+        assert_eq!(texts_and_times(&entries), vec![(0, 1320, "Hello, \"world\""), (1320, 2000, "Bye")]);
+
+        assert!(parse_alignment(AlignmentFormat::AeneasCsv, "f1,abc,1.0,x").is_err());
+    }
+}
@@ -0,0 +1,173 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Notes about subtitle entries that are kept next to the file instead of in it.
+//!
+//! Review comments and QC flags must not end up in the delivered subtitles, so they are stored in an
+//! `Annotations` map: every `CueId` has typed values by label. An `EditSession` (see `journal`) gives every
+//! entry a `CueId` that stays the same when other entries are removed, so the notes stay with their entry.
+//! The map can be exported as JSON like
+//!
+//! ```text
+//! {"0":{"comment":"typo in the second line","qc.checked":true},"4":{"cps":23.5}}
+//! ```
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::json::{self, Value};
+use failure::ResultExt;
+use std::collections::BTreeMap;
+
+/// Errors specific to reading annotations.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "invalid JSON: {}", msg)]
+        InvalidJson { msg: String },
+
+        #[fail(display = "the annotations of cue `{}` are invalid", cue)]
+        InvalidAnnotation { cue: String },
+    }
+}
+
+/// Identifies an entry of an `EditSession` (its position when the session was started).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CueId(pub u64);
+
+/// The value of a note.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationValue {
+    /// A flag like "checked" or "needs review".
+    Flag(bool),
+
+    /// A measurement like characters per second.
+    Number(f64),
+
+    /// A comment.
+    Text(String),
+}
+
+impl AnnotationValue {
+    fn to_json(&self) -> Value {
+        match *self {
+            AnnotationValue::Flag(flag) => Value::Bool(flag),
+            AnnotationValue::Number(n) => Value::Number(n),
+            AnnotationValue::Text(ref text) => Value::String(text.clone()),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<AnnotationValue> {
+        match *value {
+            Value::Bool(flag) => Some(AnnotationValue::Flag(flag)),
+            Value::Number(n) => Some(AnnotationValue::Number(n)),
+            Value::String(ref text) => Some(AnnotationValue::Text(text.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Notes by label for every annotated cue (see the module documentation).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Annotations {
+    cues: BTreeMap<CueId, BTreeMap<String, AnnotationValue>>,
+}
+
+impl Annotations {
+    /// Creates an empty map.
+    pub fn new() -> Annotations {
+        Annotations::default()
+    }
+
+    /// Returns `true` if no cue has notes.
+    pub fn is_empty(&self) -> bool {
+        self.cues.is_empty()
+    }
+
+    /// Sets the note `label` of a cue and returns the old value.
+    pub fn set(&mut self, cue: CueId, label: &str, value: AnnotationValue) -> Option<AnnotationValue> {
+        self.cues.entry(cue).or_default().insert(label.to_string(), value)
+    }
+
+    /// Returns the note `label` of a cue.
+    pub fn get(&self, cue: CueId, label: &str) -> Option<&AnnotationValue> {
+        self.cues.get(&cue).and_then(|notes| notes.get(label))
+    }
+
+    /// Removes the note `label` of a cue and returns its value.
+    pub fn remove(&mut self, cue: CueId, label: &str) -> Option<AnnotationValue> {
+        let notes = self.cues.get_mut(&cue)?;
+        let value = notes.remove(label);
+        if notes.is_empty() {
+            self.cues.remove(&cue);
+        }
+        value
+    }
+
+    /// Removes all notes of a cue.
+    pub fn remove_cue(&mut self, cue: CueId) {
+        self.cues.remove(&cue);
+    }
+
+    /// All notes of a cue, sorted by label.
+    pub fn notes(&self, cue: CueId) -> impl Iterator<Item = (&str, &AnnotationValue)> {
+        self.cues.get(&cue).into_iter().flatten().map(|(label, value)| (label.as_str(), value))
+    }
+
+    /// All cues with notes, in order.
+    pub fn cues(&self) -> impl Iterator<Item = CueId> + '_ {
+        self.cues.keys().copied()
+    }
+
+    /// Writes the map as compact JSON (see the module documentation).
+    pub fn to_json(&self) -> String {
+        let cues = self.cues.iter().map(|(cue, notes)| {
+            let notes = notes.iter().map(|(label, value)| (label.clone(), value.to_json()));
+            (cue.0.to_string(), Value::Object(notes.collect()))
+        });
+        Value::Object(cues.collect()).to_string()
+    }
+
+    /// Reads a map that was written by `to_json()`.
+    pub fn from_json(s: &str) -> SubtitleParserResult<Annotations> {
+        Ok(Self::from_json_inner(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn from_json_inner(s: &str) -> Result<Annotations> {
+        let value = json::parse(s).map_err(|e| Error::from(InvalidJson { msg: e.to_string() }))?;
+        let cues = match value {
+            Value::Object(cues) => cues,
+            _ => {
+                return Err(InvalidJson {
+                    msg: "expected an object".to_string(),
+                }
+                .into())
+            }
+        };
+
+        let mut result = Annotations::new();
+        for (cue, notes) in cues {
+            let parse_notes = || -> Option<(CueId, BTreeMap<String, AnnotationValue>)> {
+                let notes = match notes {
+                    Value::Object(ref notes) => notes,
+                    _ => return None,
+                };
+                let notes = notes
+                    .iter()
+                    .map(|(label, value)| Some((label.clone(), AnnotationValue::from_json(value)?)));
+                Some((CueId(cue.parse().ok()?), notes.collect::<Option<_>>()?))
+            };
+            let (id, notes) = parse_notes().ok_or_else(|| Error::from(InvalidAnnotation { cue: cue.clone() }))?;
+            if !notes.is_empty() {
+                result.cues.insert(id, notes);
+            }
+        }
+        Ok(result)
+    }
+}
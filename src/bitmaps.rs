@@ -0,0 +1,172 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Where the images of bitmap subtitles (Blu-ray `.sup`, VobSub `.sub`) are shown on the screen.
+//!
+//! `PgsFile::bitmap_placements()` and `VobFile::bitmap_placements()` return the images of every entry with their
+//! position, size and (for `.sup` files) cropping rectangle and window. The positions can be changed and written
+//! back with `set_bitmap_placements()`, for example to move subtitles out of a letterbox which was cropped from the
+//! video:
+//!
+//! ```no_run
+//! # let mut file = subparse::PgsFile::parse(&[]).unwrap();
+//! let placements: Vec<Vec<_>> = file
+//!     .bitmap_placements()
+//!     .into_iter()
+//!     .map(|images| images.into_iter().map(|image| image.moved_by(0, -140)).collect())
+//!     .collect();
+//! file.set_bitmap_placements(&placements).unwrap();
+//! ```
+//!
+//! The images themselves are not re-encoded, so their size can't be changed.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+
+/// Errors of invalid bitmap placements.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Clone, Copy, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "entry {} has {} images, but {} placements were given", entry_idx, expected, found)]
+        ImageCountMismatch { entry_idx: usize, expected: usize, found: usize },
+
+        #[fail(display = "the size of the images of entry {} can't be changed", entry_idx)]
+        SizeChanged { entry_idx: usize },
+
+        #[fail(display = "the cropping rectangle of entry {} is outside of its image", entry_idx)]
+        InvalidCrop { entry_idx: usize },
+
+        #[fail(display = "an image of entry {} is outside of the screen", entry_idx)]
+        OutsideScreen { entry_idx: usize },
+
+        #[fail(display = "an image of entry {} is outside of its window", entry_idx)]
+        OutsideWindow { entry_idx: usize },
+
+        #[fail(display = "the window of entry {} is defined by an earlier entry and can't be changed", entry_idx)]
+        WindowNotEditable { entry_idx: usize },
+    }
+}
+
+/// A rectangle in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapRect {
+    /// The left edge.
+    pub x: u16,
+
+    /// The top edge.
+    pub y: u16,
+
+    /// The width.
+    pub width: u16,
+
+    /// The height.
+    pub height: u16,
+}
+
+impl BitmapRect {
+    /// Returns `true` if the other rectangle is completely inside of this one.
+    pub fn contains(&self, other: &BitmapRect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && u32::from(other.x) + u32::from(other.width) <= u32::from(self.x) + u32::from(self.width)
+            && u32::from(other.y) + u32::from(other.height) <= u32::from(self.y) + u32::from(self.height)
+    }
+
+    /// Returns the rectangle moved by `dx` pixels to the right and `dy` pixels down (stopping at the screen edges).
+    pub fn moved_by(&self, dx: i32, dy: i32) -> BitmapRect {
+        let add = |v: u16, d: i32| (i32::from(v) + d).clamp(0, i32::from(u16::MAX)) as u16;
+        BitmapRect {
+            x: add(self.x, dx),
+            y: add(self.y, dy),
+            ..*self
+        }
+    }
+}
+
+/// An image of a bitmap subtitle entry and where it is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapPlacement {
+    /// The top left corner of the shown image (or the shown part of it) on the screen.
+    pub x: u16,
+
+    /// See `x`.
+    pub y: u16,
+
+    /// The size of the image, which can't be changed.
+    pub width: u16,
+
+    /// See `width`.
+    pub height: u16,
+
+    /// The part of the image which is shown, relative to the image (only `.sup` files).
+    pub crop: Option<BitmapRect>,
+
+    /// The window on the screen which the image is drawn into (only `.sup` files).
+    pub window: Option<BitmapRect>,
+}
+
+impl BitmapPlacement {
+    /// Returns the rectangle on the screen which is covered by the image.
+    pub fn screen_rect(&self) -> BitmapRect {
+        let (width, height) = self.crop.map_or((self.width, self.height), |crop| (crop.width, crop.height));
+        BitmapRect {
+            x: self.x,
+            y: self.y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the placement with the image and its window moved by `dx` pixels to the right and `dy` pixels down.
+    pub fn moved_by(&self, dx: i32, dy: i32) -> BitmapPlacement {
+        let rect = self.screen_rect().moved_by(dx, dy);
+        BitmapPlacement {
+            x: rect.x,
+            y: rect.y,
+            window: self.window.map(|window| window.moved_by(dx, dy)),
+            ..*self
+        }
+    }
+}
+
+/// Checks that the new placements of an entry are valid replacements of the old ones (`screen` is the video size).
+pub(crate) fn check_placements(entry_idx: usize, old: &[BitmapPlacement], new: &[BitmapPlacement], screen: Option<(u16, u16)>) -> Result<()> {
+    if old.len() != new.len() {
+        return Err(ImageCountMismatch {
+            entry_idx,
+            expected: old.len(),
+            found: new.len(),
+        }
+        .into());
+    }
+    let screen = screen.map(|(width, height)| BitmapRect { x: 0, y: 0, width, height });
+    for (old, new) in old.iter().zip(new) {
+        if (old.width, old.height) != (new.width, new.height) {
+            return Err(SizeChanged { entry_idx }.into());
+        }
+        let image = BitmapRect {
+            x: 0,
+            y: 0,
+            width: new.width,
+            height: new.height,
+        };
+        if new.crop.is_some_and(|crop| !image.contains(&crop)) {
+            return Err(InvalidCrop { entry_idx }.into());
+        }
+        let rect = new.screen_rect();
+        let outside = |area: &Option<BitmapRect>, rect: &BitmapRect| area.is_some_and(|area| !area.contains(rect));
+        if outside(&screen, &rect) || new.window.is_some_and(|window| outside(&screen, &window)) {
+            return Err(OutsideScreen { entry_idx }.into());
+        }
+        if outside(&new.window, &rect) {
+            return Err(OutsideWindow { entry_idx }.into());
+        }
+    }
+    Ok(())
+}
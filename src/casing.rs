@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Conversion between all caps and sentence case.
+//!
+//! Broadcast captions (like CEA-608) are usually written in all caps, which is unusual for streaming subtitles.
+//! `SubtitleFile::to_sentence_case()` converts them, keeping acronyms and names from a protection list, and
+//! `SubtitleFile::to_upper_case()` does the reverse. Tags like `<i>` or `{\an8}` and escapes like `\N` are never changed.
+
+use crate::errors::Result;
+use crate::SubtitleFile;
+
+/// Calls `f` for every word of the text outside of tags and copies everything else unchanged.
+///
+/// A word is a run of alphanumeric characters and apostrophes. `f` also gets the text between the
+/// previous word and this one. The `.ssa` escapes `\N`, `\n` and `\h` are markup like tags.
+fn map_words<F>(text: &str, mut f: F) -> String
+where
+    F: FnMut(&str, &str) -> String,
+{
+    let mut result = String::with_capacity(text.len());
+    let mut word = String::new();
+    let mut between = String::new();
+    let mut tag_end: Option<char> = None;
+    let mut after_backslash = false;
+    for c in text.chars() {
+        if std::mem::replace(&mut after_backslash, false) && matches!(c, 'N' | 'n' | 'h') {
+            between.push(' ');
+            result.push(c);
+            continue;
+        }
+        let in_word = tag_end.is_none() && (c.is_alphanumeric() || (c == '\'' && !word.is_empty()));
+        if in_word {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            result.push_str(&f(&word, &between));
+            word.clear();
+            between.clear();
+        }
+        match tag_end {
+            Some(end) if c == end => tag_end = None,
+            Some(_) => {}
+            None if c == '<' => tag_end = Some('>'),
+            None if c == '{' => tag_end = Some('}'),
+            None => {
+                after_backslash = c == '\\';
+                between.push(c);
+            }
+        }
+        result.push(c);
+    }
+    if !word.is_empty() {
+        result.push_str(&f(&word, &between));
+    }
+    result
+}
+
+/// Returns the text in sentence case; `sentence_start` tells (and is updated with) whether the next word starts
+/// a sentence.
+fn sentence_case(text: &str, protected: &[&str], sentence_start: &mut bool) -> String {
+    map_words(text, |word, between| {
+        if between.chars().any(|c| matches!(c, '.' | '!' | '?' | '…')) {
+            *sentence_start = true;
+        }
+        let lower = word.to_lowercase();
+        let mut converted = if let Some(protected) = protected.iter().find(|p| p.to_lowercase() == lower) {
+            protected.to_string()
+        } else if lower == "i" || lower.starts_with("i'") {
+            // "I", "I'm", "I'll", ...
+            let mut chars = lower.chars();
+            chars.next();
+            format!("I{}", chars.as_str())
+        } else {
+            lower
+        };
+        if std::mem::replace(sentence_start, false) {
+            let mut chars = converted.chars();
+            if let Some(first) = chars.next() {
+                converted = first.to_uppercase().chain(chars).collect();
+            }
+        }
+        converted
+    })
+}
+
+impl SubtitleFile {
+    /// Converts all texts in all caps to sentence case.
+    ///
+    /// The first word of every sentence is capitalized (sentences can span multiple entries), words in `protected`
+    /// (like `"FBI"` or `"London"`) are written like in the list (case-insensitive match), and "I" stays uppercase.
+    /// Entries which already contain lowercase letters are not changed.
+    pub fn to_sentence_case(&mut self, protected: &[&str]) -> Result<()> {
+        let mut entries = self.get_subtitle_entries()?;
+        let mut sentence_start = true;
+        for entry in &mut entries {
+            if let Some(ref mut text) = entry.line {
+                let converted = sentence_case(text, protected, &mut sentence_start);
+                let mut has_lowercase = false;
+                map_words(text, |word, _| {
+                    has_lowercase |= word.chars().any(char::is_lowercase);
+                    String::new()
+                });
+                if !has_lowercase {
+                    *text = converted;
+                }
+                if text.trim_end().ends_with(['.', '!', '?', '…']) {
+                    sentence_start = true;
+                }
+            }
+        }
+        self.update_subtitle_entries(&entries)
+    }
+
+    /// Converts all texts to all caps.
+    pub fn to_upper_case(&mut self) -> Result<()> {
+        let mut entries = self.get_subtitle_entries()?;
+        for entry in &mut entries {
+            if let Some(ref mut text) = entry.line {
+                *text = map_words(text, |word, _| word.to_uppercase());
+            }
+        }
+        self.update_subtitle_entries(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn all_caps_to_sentence_case() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>I'M WITH THE FBI.</i> WE\n\n\
+                   2\n00:00:03,000 --> 00:00:04,000\n{\\an8}CAME FROM LONDON!\n\n\
+                   3\n00:00:05,000 --> 00:00:06,000\nAlready Mixed\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let texts = |file: &SubtitleFile| -> Vec<String> { file.get_subtitle_entries().unwrap().into_iter().map(|e| e.line.unwrap()).collect() };
+
+        file.to_sentence_case(&["FBI", "London"]).unwrap();
+        assert_eq!(
+            texts(&file),
+            vec!["<i>I'm with the FBI.</i> We", "{\\an8}came from London!", "Already Mixed"]
+        );
+
+        file.to_upper_case().unwrap();
+        assert_eq!(
+            texts(&file),
+            vec!["<i>I'M WITH THE FBI.</i> WE", "{\\an8}CAME FROM LONDON!", "ALREADY MIXED"]
+        );
+    }
+
+    #[test]
+    fn ass_escapes_are_markup() {
+        let ass = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\i1}HELLO\\hTHERE.\\NNOW\\nGO\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubStationAlpha, ass, 25.0).unwrap();
+        let text = |file: &SubtitleFile| file.get_subtitle_entries().unwrap().remove(0).line.unwrap();
+
+        file.to_sentence_case(&[]).unwrap();
+        assert_eq!(text(&file), "{\\i1}Hello\\hthere.\\NNow\\ngo");
+
+        file.to_upper_case().unwrap();
+        assert_eq!(text(&file), "{\\i1}HELLO\\hTHERE.\\NNOW\\nGO");
+    }
+}
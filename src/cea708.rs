@@ -0,0 +1,492 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Decoding digital TV captions (CEA-708 / DTVCC) into subtitle entries.
+//!
+//! MPEG-2 and H.264 streams carry the captions of every picture as `cc_data()` (in ATSC A/53 user data or
+//! SEI messages). A demuxer hands these bytes with the presentation time of the picture to a `Cea708Decoder`,
+//! which assembles the caption channel packets, runs the window commands of one caption service and creates an
+//! entry for every change of the visible text:
+//!
+//! ```
+//! use subparse::cea708::Cea708Decoder;
+//! use subparse::timetypes::TimePoint;
+//!
+//! let mut decoder = Cea708Decoder::new(1);
+//! // a packet with a visible window (service 1) that shows "Hi"
+//! let cc_data = [0xff, 0x06, 0x29, 0xfe, 0x98, 0x20, 0xfe, 0x00, 0x00, 0xfe, 0x01, 0x1f, 0xfe, 0x00, b'H', 0xfe, b'i', 0x00];
+//! decoder.push_cc_data(TimePoint::from_msecs(1000), &cc_data);
+//! let entries = decoder.finish(TimePoint::from_msecs(3000));
+//! assert_eq!(entries[0].line.as_deref(), Some("Hi"));
+//! ```
+//!
+//! The CEA-608 compatibility bytes of the `cc_data()` (`cc_type` 0 and 1) are skipped, the `.scc` reader
+//! decodes those. Delays (`DLY`) are ignored and pen columns, colors and window positions are not kept.
+
+use crate::timetypes::{TimePoint, TimeSpan};
+use crate::SubtitleEntry;
+
+/// The text of a caption window.
+#[derive(Debug, Clone, Default)]
+struct Window {
+    visible: bool,
+
+    /// The rows of text, at least one
+    rows: Vec<String>,
+
+    /// The row of the pen
+    pen_row: usize,
+}
+
+impl Window {
+    fn new(row_count: usize) -> Window {
+        Window {
+            visible: false,
+            rows: vec![String::new(); row_count.max(1)],
+            pen_row: 0,
+        }
+    }
+
+    /// Changes the number of rows (the last rows are kept).
+    fn set_row_count(&mut self, row_count: usize) {
+        let row_count = row_count.max(1);
+        while self.rows.len() > row_count {
+            self.rows.remove(0);
+            self.pen_row = self.pen_row.saturating_sub(1);
+        }
+        self.rows.resize(row_count, String::new());
+    }
+
+    /// The row of the pen (the pen stays within the window).
+    fn row(&mut self) -> Option<&mut String> {
+        self.pen_row = self.pen_row.min(self.rows.len().saturating_sub(1));
+        self.rows.get_mut(self.pen_row)
+    }
+
+    fn clear(&mut self) {
+        self.rows.iter_mut().for_each(String::clear);
+        self.pen_row = 0;
+    }
+
+    /// Moves the pen to the next row and scrolls the text up at the last row.
+    fn carriage_return(&mut self) {
+        if self.pen_row + 1 < self.rows.len() {
+            self.pen_row += 1;
+        } else if !self.rows.is_empty() {
+            self.rows.remove(0);
+            self.rows.push(String::new());
+        }
+    }
+
+    fn text(&self) -> String {
+        let rows: Vec<&str> = self.rows.iter().map(|row| row.trim()).filter(|row| !row.is_empty()).collect();
+        rows.join("\n")
+    }
+}
+
+/// Decodes one caption service of a DTVCC stream (see the module documentation).
+#[derive(Debug, Clone)]
+pub struct Cea708Decoder {
+    /// The service number (1 is the primary caption service)
+    service: u8,
+
+    /// The caption channel packet that is assembled
+    packet: Vec<u8>,
+
+    windows: [Option<Window>; 8],
+    current_window: usize,
+
+    /// The time of the last `cc_data()`
+    time: TimePoint,
+
+    /// The visible text and its start time
+    displayed: Option<(TimePoint, String)>,
+
+    entries: Vec<SubtitleEntry>,
+}
+
+impl Cea708Decoder {
+    /// Creates a decoder for a caption service (`1` to `63`, `1` is the primary caption service).
+    pub fn new(service: u8) -> Cea708Decoder {
+        Cea708Decoder {
+            service,
+            packet: Vec::new(),
+            windows: Default::default(),
+            current_window: 0,
+            time: TimePoint::from_msecs(0),
+            displayed: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Processes the `cc_data()` triplets of a picture that is shown at `time` (three bytes per triplet:
+    /// marker bits with `cc_valid` and `cc_type`, then two data bytes).
+    pub fn push_cc_data(&mut self, time: TimePoint, cc_data: &[u8]) {
+        self.time = time;
+        for triplet in cc_data.chunks_exact(3) {
+            let (flags, data) = triplet.split_at(1);
+            let flags = flags.first().copied().unwrap_or(0);
+            let cc_valid = flags & 0x04 != 0;
+            if !cc_valid {
+                continue;
+            }
+            match flags & 0x03 {
+                // DTVCC_PACKET_START
+                3 => {
+                    self.process_packet();
+                    self.packet = data.to_vec();
+                }
+                // DTVCC_PACKET_DATA
+                2 if !self.packet.is_empty() => self.packet.extend_from_slice(data),
+                _ => {}
+            }
+            if self.packet.first().is_some_and(|&header| self.packet.len() >= packet_size(header)) {
+                self.process_packet();
+            }
+        }
+        self.update_displayed();
+    }
+
+    /// Returns all captions; a caption that is still visible ends at `end`.
+    pub fn finish(mut self, end: TimePoint) -> Vec<SubtitleEntry> {
+        self.process_packet();
+        self.update_displayed();
+        if let Some((start, text)) = self.displayed.take() {
+            self.entries.push(SubtitleEntry::new(TimeSpan::new(start, end.max(start)), text));
+        }
+        self.entries
+    }
+
+    /// Creates an entry when the visible text changed.
+    fn update_displayed(&mut self) {
+        let text: Vec<String> = self
+            .windows
+            .iter()
+            .flatten()
+            .filter(|window| window.visible)
+            .map(Window::text)
+            .filter(|text| !text.is_empty())
+            .collect();
+        let text = text.join("\n");
+        if self.displayed.as_ref().map_or("", |(_, displayed)| displayed.as_str()) == text {
+            return;
+        }
+        if let Some((start, old)) = self.displayed.take() {
+            self.entries.push(SubtitleEntry::new(TimeSpan::new(start, self.time), old));
+        }
+        if !text.is_empty() {
+            self.displayed = Some((self.time, text));
+        }
+    }
+
+    /// Runs the service blocks of the assembled packet.
+    fn process_packet(&mut self) {
+        let packet = std::mem::take(&mut self.packet);
+        let size = match packet.first() {
+            Some(&header) => packet_size(header).min(packet.len()),
+            None => return,
+        };
+        let mut rest = packet.get(1..size).unwrap_or_default();
+
+        while let Some((&header, tail)) = rest.split_first() {
+            let (mut service, block_size) = (header >> 5, usize::from(header & 0x1f));
+            let mut tail = tail;
+            if service == 7 {
+                // extended service number
+                match tail.split_first() {
+                    Some((&extended, after)) => {
+                        service = extended & 0x3f;
+                        tail = after;
+                    }
+                    None => return,
+                }
+            }
+            if service == 0 || block_size == 0 {
+                return;
+            }
+            let (block, after) = tail.split_at(block_size.min(tail.len()));
+            if service == self.service {
+                self.process_block(block);
+            }
+            rest = after;
+        }
+    }
+
+    fn process_block(&mut self, mut block: &[u8]) {
+        while let Some((&code, rest)) = block.split_first() {
+            let take = |n: usize| rest.get(..n).unwrap_or(rest);
+            let consumed = match code {
+                // EXT1: extended characters and codes
+                0x10 => self.extended(rest),
+                // 16-bit character
+                0x18 => {
+                    let bytes = take(2);
+                    if let [high, low] = *bytes {
+                        if let Some(c) = char::from_u32(u32::from(high) << 8 | u32::from(low)) {
+                            self.write(c);
+                        }
+                    }
+                    2
+                }
+                0x00..=0x1f => {
+                    self.c0(code);
+                    match code {
+                        0x11..=0x17 => 1,
+                        0x18..=0x1f => 2,
+                        _ => 0,
+                    }
+                }
+                0x7f => {
+                    self.write('♪');
+                    0
+                }
+                0x20..=0x7e => {
+                    self.write(char::from(code));
+                    0
+                }
+                0x80..=0x9f => self.c1(code, rest),
+                // G1: Latin-1
+                0xa0..=0xff => {
+                    self.write(char::from(code));
+                    0
+                }
+            };
+            block = rest.get(consumed..).unwrap_or_default();
+        }
+    }
+
+    /// Runs a C0 code.
+    fn c0(&mut self, code: u8) {
+        let window = match self.window() {
+            Some(window) => window,
+            None => return,
+        };
+        match code {
+            // BS
+            0x08 => {
+                window.row().map(String::pop);
+            }
+            // FF: clears the window
+            0x0c => window.clear(),
+            // CR
+            0x0d => window.carriage_return(),
+            // HCR: clears the row of the pen
+            0x0e => window.row().map_or((), String::clear),
+            _ => {}
+        }
+    }
+
+    /// Runs a C1 code and returns the number of parameter bytes.
+    fn c1(&mut self, code: u8, params: &[u8]) -> usize {
+        let param = params.first().copied().unwrap_or(0);
+        let windows_of = |bitmap: u8| (0..8).filter(move |idx| bitmap & (1 << idx) != 0);
+        match code {
+            // CW0-CW7: set current window
+            0x80..=0x87 => {
+                self.current_window = usize::from(code - 0x80);
+                0
+            }
+            // CLW, DSW, HDW, TGW, DLW
+            0x88..=0x8c => {
+                for idx in windows_of(param) {
+                    let slot = self.windows.get_mut(idx);
+                    match (code, slot) {
+                        (0x8c, Some(slot)) => *slot = None,
+                        (_, Some(Some(window))) => match code {
+                            0x88 => window.clear(),
+                            0x89 => window.visible = true,
+                            0x8a => window.visible = false,
+                            _ => window.visible = !window.visible,
+                        },
+                        _ => {}
+                    }
+                }
+                1
+            }
+            // DLY
+            0x8d => 1,
+            // RST: deletes all windows
+            0x8f => {
+                self.windows = Default::default();
+                0
+            }
+            // SPA
+            0x90 => 2,
+            // SPC
+            0x91 => 3,
+            // SPL: set pen location
+            0x92 => {
+                if let Some(window) = self.window() {
+                    let row = usize::from(param & 0x0f);
+                    if row != window.pen_row {
+                        window.pen_row = row;
+                    } else if let Some(text) = window.row().filter(|text| !text.is_empty() && !text.ends_with(' ')) {
+                        // a new column in the same row
+                        text.push(' ');
+                    }
+                }
+                2
+            }
+            // SWA
+            0x97 => 4,
+            // DF0-DF7: define window
+            0x98..=0x9f => {
+                let idx = usize::from(code - 0x98);
+                if let [attributes, _, _, rows, _, _] = *params.get(..6).unwrap_or_default() {
+                    let row_count = usize::from(rows & 0x0f) + 1;
+                    if let Some(slot) = self.windows.get_mut(idx) {
+                        let window = slot.get_or_insert_with(|| Window::new(row_count));
+                        window.set_row_count(row_count);
+                        window.visible = attributes & 0x20 != 0;
+                    }
+                    self.current_window = idx;
+                }
+                6
+            }
+            // DLC and the reserved codes have no parameters
+            _ => 0,
+        }
+    }
+
+    /// Runs the code after `EXT1` and returns the number of consumed bytes.
+    fn extended(&mut self, rest: &[u8]) -> usize {
+        let code = match rest.first() {
+            Some(&code) => code,
+            None => return 0,
+        };
+        let params = match code {
+            // C2
+            0x00..=0x07 => 0,
+            0x08..=0x0f => 1,
+            0x10..=0x17 => 2,
+            0x18..=0x1f => 3,
+            // G2
+            0x20..=0x7f => {
+                if let Some(c) = g2_char(code) {
+                    self.write(c);
+                }
+                0
+            }
+            // C3
+            0x80..=0x87 => 4,
+            0x88..=0x8f => 5,
+            // variable length commands
+            0x90..=0x9f => 1 + rest.get(1).map_or(0, |&header| usize::from(header & 0x3f)),
+            // G3: only the [CC] icon is defined
+            0xa0..=0xff => 0,
+        };
+        1 + params
+    }
+
+    fn window(&mut self) -> Option<&mut Window> {
+        self.windows.get_mut(self.current_window).and_then(Option::as_mut)
+    }
+
+    fn write(&mut self, c: char) {
+        if let Some(row) = self.window().and_then(Window::row) {
+            row.push(c);
+        }
+    }
+}
+
+/// The size of a caption channel packet (with header) from its header byte.
+fn packet_size(header: u8) -> usize {
+    match header & 0x3f {
+        0 => 128,
+        code => usize::from(code) * 2,
+    }
+}
+
+/// Maps a G2 character (after `EXT1`).
+fn g2_char(code: u8) -> Option<char> {
+    Some(match code {
+        0x20 | 0x21 => ' ',
+        0x25 => '…',
+        0x2a => 'Š',
+        0x2c => 'Œ',
+        0x30 => '█',
+        0x31 => '‘',
+        0x32 => '’',
+        0x33 => '“',
+        0x34 => '”',
+        0x35 => '•',
+        0x39 => '™',
+        0x3a => 'š',
+        0x3c => 'œ',
+        0x3d => '℠',
+        0x3f => 'Ÿ',
+        0x76 => '⅛',
+        0x77 => '⅜',
+        0x78 => '⅝',
+        0x79 => '⅞',
+        0x7a => '│',
+        0x7b => '┐',
+        0x7c => '└',
+        0x7d => '─',
+        0x7e => '┘',
+        0x7f => '┌',
+        _ => return None,
+    })
+}
+
+/// Returns the `cc_data()` triplets of ATSC A/53 picture user data (starting with the identifier `GA94`).
+pub fn cc_data_from_a53(user_data: &[u8]) -> Option<&[u8]> {
+    let rest = user_data.strip_prefix(b"GA94")?;
+    // user_data_type_code 3 is cc_data()
+    let (&type_code, rest) = rest.split_first()?;
+    let (&flags, rest) = rest.split_first()?;
+    if type_code != 3 || flags & 0x40 == 0 {
+        return None;
+    }
+    let cc_count = usize::from(flags & 0x1f);
+    // the byte after the flags is reserved (em_data)
+    rest.get(1..1 + cc_count * 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps a service block of service 1 into a caption channel packet and `cc_data()` triplets.
+    fn cc_data(block: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0, 0x20 | block.len() as u8];
+        packet.extend_from_slice(block);
+        if packet.len() % 2 == 1 {
+            packet.push(0);
+        }
+        packet[0] = (packet.len() / 2) as u8;
+        let triplets = packet.chunks(2).enumerate();
+        triplets
+            .flat_map(|(i, pair)| vec![if i == 0 { 0xff } else { 0xfe }, pair[0], pair[1]])
+            .collect()
+    }
+
+    #[test]
+    fn decode_dtvcc_service() {
+        let mut decoder = Cea708Decoder::new(1);
+        // a hidden window with two rows gets its text
+        let mut block = vec![0x98, 0x00, 0x00, 0x00, 0x01, 0x1f, 0x00];
+        block.extend_from_slice(b"Hello");
+        block.extend_from_slice(&[0x0d, b'w', 0xe9, b'r', b'l', b'd', 0x10, 0x25, 0x7f]);
+        decoder.push_cc_data(TimePoint::from_msecs(1000), &cc_data(&block));
+        // other services and CEA-608 bytes are ignored
+        decoder.push_cc_data(TimePoint::from_msecs(1500), &[0xfc, 0x94, 0x2c, 0xff, 0x02, 0x41, 0xfe, b'x', 0x00]);
+        // display, then hide the window
+        decoder.push_cc_data(TimePoint::from_msecs(2000), &cc_data(&[0x89, 0x01]));
+        decoder.push_cc_data(TimePoint::from_msecs(4000), &cc_data(&[0x8a, 0x01]));
+        // a visible window with a 16-bit character, still shown at the end
+        decoder.push_cc_data(TimePoint::from_msecs(5000), &cc_data(&[0x99, 0x20, 0, 0, 0, 0x1f, 0, 0x18, 0x20, 0xac]));
+
+        let entries = decoder.finish(TimePoint::from_msecs(7000));
+        let entries: Vec<(i64, i64, &str)> = entries
+            .iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.line.as_deref().unwrap()))
+            .collect();
+        assert_eq!(entries, vec![(2000, 4000, "Hello\nwérld…♪"), (5000, 7000, "€")]);
+
+        let user_data = [b'G', b'A', b'9', b'4', 0x03, 0x41, 0xff, 0xfc, 0x94, 0x20, 0xff];
+        assert_eq!(cc_data_from_a53(&user_data), Some(&[0xfc, 0x94, 0x20][..]));
+        assert_eq!(cc_data_from_a53(b"DTG1"), None);
+    }
+}
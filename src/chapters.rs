@@ -0,0 +1,166 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Derive chapter markers from subtitle entries and export them as Matroska chapter XML or
+//! FFmpeg metadata.
+//!
+//! Chapters can be placed at cues whose text matches a user supplied rule (like `"CHAPTER 2: The Storm"`),
+//! or after silent gaps between two cues that are longer than a threshold.
+
+use crate::formats::common::escape_xml;
+use crate::timetypes::{TimeDelta, TimePoint};
+use crate::SubtitleEntry;
+
+/// A chapter marker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// The time at which the chapter begins.
+    pub start: TimePoint,
+
+    /// The display name of the chapter.
+    pub title: String,
+}
+
+/// Creates a chapter for every entry for which `title_fn` returns a title.
+///
+/// The chapter starts together with the entry. Entries without text (like in VobSub files) are skipped.
+///
+/// ```
+/// use subparse::chapters::chapters_from_matching_entries;
+/// # let entries: Vec<subparse::SubtitleEntry> = Vec::new();
+/// let chapters = chapters_from_matching_entries(&entries, |text| {
+///     if text.starts_with("CHAPTER") { Some(text.to_string()) } else { None }
+/// });
+/// ```
+pub fn chapters_from_matching_entries<F>(entries: &[SubtitleEntry], mut title_fn: F) -> Vec<Chapter>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut result: Vec<Chapter> = entries
+        .iter()
+        .filter_map(|entry| {
+            let text = entry.line.as_ref()?;
+            title_fn(text).map(|title| Chapter {
+                start: entry.timespan.start,
+                title,
+            })
+        })
+        .collect();
+    result.sort_by_key(|c| c.start);
+    result
+}
+
+/// Creates a chapter at the beginning of the file and after every gap between two entries which is longer than `min_gap`.
+///
+/// The chapters are named `"Chapter 1"`, `"Chapter 2"`, ...
+pub fn chapters_from_gaps(entries: &[SubtitleEntry], min_gap: TimeDelta) -> Vec<Chapter> {
+    let mut spans: Vec<_> = entries.iter().map(|e| e.timespan).collect();
+    spans.sort();
+
+    let mut starts = vec![TimePoint::from_msecs(0)];
+    let mut last_end: Option<TimePoint> = None;
+    for span in spans {
+        if let Some(end) = last_end {
+            if span.start - end > min_gap {
+                starts.push(span.start);
+            }
+        }
+        last_end = Some(last_end.map_or(span.end, |end| end.max(span.end)));
+    }
+
+    starts
+        .into_iter()
+        .enumerate()
+        .map(|(i, start)| Chapter {
+            start,
+            title: format!("Chapter {}", i + 1),
+        })
+        .collect()
+}
+
+/// Formats a time like `"00:01:02.500000000"` (the precision Matroska uses).
+fn matroska_time(t: TimePoint) -> String {
+    let t = if t.is_negative() { TimePoint::from_msecs(0) } else { t };
+    format!("{:02}:{:02}:{:02}.{:03}000000", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp())
+}
+
+/// Exports the chapters as Matroska chapter XML (as understood by `mkvmerge --chapters`).
+///
+/// `language` is the ISO 639-2 code of the chapter names (like `"eng"`).
+pub fn to_matroska_xml(chapters: &[Chapter], language: &str) -> String {
+    let mut result = String::new();
+    result.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    result.push_str("<!DOCTYPE Chapters SYSTEM \"matroskachapters.dtd\">\n");
+    result.push_str("<Chapters>\n  <EditionEntry>\n");
+    for chapter in chapters {
+        result.push_str("    <ChapterAtom>\n");
+        result.push_str(&format!("      <ChapterTimeStart>{}</ChapterTimeStart>\n", matroska_time(chapter.start)));
+        result.push_str("      <ChapterDisplay>\n");
+        result.push_str(&format!("        <ChapterString>{}</ChapterString>\n", escape_xml(&chapter.title)));
+        result.push_str(&format!("        <ChapterLanguage>{}</ChapterLanguage>\n", escape_xml(language)));
+        result.push_str("      </ChapterDisplay>\n");
+        result.push_str("    </ChapterAtom>\n");
+    }
+    result.push_str("  </EditionEntry>\n</Chapters>\n");
+    result
+}
+
+/// Escapes the characters with special meaning in FFmpeg metadata files.
+fn escape_ffmetadata(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "=;#\\\n".contains(c) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Exports the chapters in the FFmpeg metadata format (`;FFMETADATA1`, as used by `ffmpeg -i metadata.txt -map_metadata`).
+///
+/// This format requires an end time for each chapter: a chapter ends where the next one begins, and the
+/// last chapter ends at `end` (e.g. the duration of the video).
+pub fn to_ffmetadata(chapters: &[Chapter], end: TimePoint) -> String {
+    let mut result = String::from(";FFMETADATA1\n");
+    let ends = chapters.iter().skip(1).map(|c| c.start).chain(std::iter::once(end));
+    for (chapter, chapter_end) in chapters.iter().zip(ends) {
+        result.push_str("\n[CHAPTER]\nTIMEBASE=1/1000\n");
+        result.push_str(&format!("START={}\n", chapter.start.msecs()));
+        result.push_str(&format!("END={}\n", chapter_end.max(chapter.start).msecs()));
+        result.push_str(&format!("title={}\n", escape_ffmetadata(&chapter.title)));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeSpan;
+
+    fn entry(start: i64, end: i64, text: &str) -> SubtitleEntry {
+        SubtitleEntry::new(TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end)), text.to_string())
+    }
+
+    #[test]
+    fn chapters_from_text_and_gaps() {
+        let entries = vec![
+            entry(1000, 2000, "CHAPTER 1"),
+            entry(2000, 3000, "text"),
+            entry(63000, 64000, "CHAPTER 2; the end"),
+        ];
+
+        let by_text = chapters_from_matching_entries(&entries, |t| if t.starts_with("CHAPTER") { Some(t.to_string()) } else { None });
+        assert_eq!(by_text.iter().map(|c| c.start.msecs()).collect::<Vec<_>>(), vec![1000, 63000]);
+
+        let by_gap = chapters_from_gaps(&entries, TimeDelta::from_secs(30));
+        assert_eq!(by_gap.iter().map(|c| c.start.msecs()).collect::<Vec<_>>(), vec![0, 63000]);
+
+        let ffmeta = to_ffmetadata(&by_text, TimePoint::from_secs(100));
+        assert!(ffmeta.contains("START=63000\nEND=100000\ntitle=CHAPTER 2\\; the end\n"));
+
+        let xml = to_matroska_xml(&by_text, "eng");
+        assert!(xml.contains("<ChapterTimeStart>00:01:03.000000000</ChapterTimeStart>"));
+    }
+}
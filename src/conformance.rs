@@ -0,0 +1,414 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Conformance checks against a corpus of sample files.
+//!
+//! Before adopting this library, integrators can run their own archive of subtitle files through it:
+//! `check_directory()` detects the format of every file and checks that it can be parsed, that writing it and
+//! parsing the result gives the same entries ("round trip"), and that the entries can be converted into a
+//! `.srt` file. The `ConformanceReport` summarizes the results per format and can be written as JSON.
+//!
+//! ```no_run
+//! use subparse::conformance::{check_directory, ConformanceOptions};
+//!
+//! let report = check_directory("samples".as_ref(), &ConformanceOptions::default()).unwrap();
+//! for summary in report.summaries() {
+//!     let name = summary.format.map_or("unknown format", |format| format.get_name());
+//!     println!("{}: {} of {} files passed", name, summary.passed, summary.samples);
+//! }
+//! std::fs::write("conformance.json", report.to_json()).unwrap();
+//! ```
+
+use crate::errors::Error;
+use crate::formats::{decode_bytes_to_string, get_subtitle_format, parse_bytes, parse_str_with_warnings};
+use crate::json::Value;
+use crate::{SrtFile, SubtitleEntry, SubtitleFile, SubtitleFileInterface, SubtitleFormat};
+use encoding_rs::Encoding;
+use failure::Fail;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for the conformance checks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConformanceOptions {
+    /// The frame rate of frame-based formats (see `parse_bytes()`).
+    pub fps: f64,
+
+    /// The character encoding of text files (`None` for automatic detection).
+    pub encoding: Option<&'static Encoding>,
+
+    /// Also check the files in subdirectories.
+    pub recursive: bool,
+}
+
+impl Default for ConformanceOptions {
+    fn default() -> ConformanceOptions {
+        ConformanceOptions {
+            fps: 25.0,
+            encoding: None,
+            recursive: true,
+        }
+    }
+}
+
+/// The result of a single check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The check succeeded.
+    Passed,
+
+    /// The check failed (with the reason).
+    Failed(String),
+
+    /// The check was not done (with the reason, for example because parsing already failed).
+    Skipped(String),
+}
+
+impl CheckOutcome {
+    /// Returns `true` if the check failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(self, CheckOutcome::Failed(_))
+    }
+
+    fn from_result(result: Result<(), String>) -> CheckOutcome {
+        match result {
+            Ok(()) => CheckOutcome::Passed,
+            Err(reason) => CheckOutcome::Failed(reason),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let (result, message) = match self {
+            CheckOutcome::Passed => ("passed", None),
+            CheckOutcome::Failed(message) => ("failed", Some(message)),
+            CheckOutcome::Skipped(message) => ("skipped", Some(message)),
+        };
+        let mut members = vec![("result".to_string(), Value::String(result.to_string()))];
+        members.extend(message.map(|message| ("message".to_string(), Value::String(message.clone()))));
+        Value::Object(members)
+    }
+}
+
+/// The results of all checks of one sample file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleReport {
+    /// The path (or name) of the file.
+    pub path: PathBuf,
+
+    /// The detected format (`None` if the file is not a subtitle file that this library knows).
+    pub format: Option<SubtitleFormat>,
+
+    /// The file can be parsed.
+    pub parse: CheckOutcome,
+
+    /// Writing the file and parsing the result gives the same entries.
+    pub round_trip: CheckOutcome,
+
+    /// The entries can be written as `.srt` file, which has the same entries when it is parsed.
+    pub convert: CheckOutcome,
+
+    /// The written file is identical to the input.
+    pub identical: bool,
+
+    /// The number of entries.
+    pub entries: usize,
+
+    /// The number of warnings while parsing (only available for text formats).
+    pub warnings: usize,
+}
+
+impl SampleReport {
+    /// Returns `true` if no check failed.
+    pub fn passed(&self) -> bool {
+        self.format.is_some() && ![&self.parse, &self.round_trip, &self.convert].iter().any(|outcome| outcome.is_failed())
+    }
+
+    fn to_json(&self) -> Value {
+        let number = |n: usize| Value::Number(n as f64);
+        Value::Object(vec![
+            ("file".to_string(), Value::String(self.path.to_string_lossy().into_owned())),
+            (
+                "format".to_string(),
+                self.format.map_or(Value::Null, |format| Value::String(format.get_name().to_string())),
+            ),
+            ("parse".to_string(), self.parse.to_json()),
+            ("round_trip".to_string(), self.round_trip.to_json()),
+            ("convert".to_string(), self.convert.to_json()),
+            ("identical".to_string(), Value::Bool(self.identical)),
+            ("entries".to_string(), number(self.entries)),
+            ("warnings".to_string(), number(self.warnings)),
+        ])
+    }
+}
+
+/// The number of passed checks of all samples of one format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatSummary {
+    /// The format (`None` for files with an unknown format).
+    pub format: Option<SubtitleFormat>,
+
+    /// The number of sample files.
+    pub samples: usize,
+
+    /// The number of files without failed checks.
+    pub passed: usize,
+
+    /// The number of files which could be parsed.
+    pub parsed: usize,
+
+    /// The number of files with a successful round trip.
+    pub round_trip: usize,
+
+    /// The number of files which were written byte by byte identical.
+    pub identical: usize,
+
+    /// The number of files which could be converted.
+    pub converted: usize,
+}
+
+/// The results of all sample files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// The results of the files in the order they were checked.
+    pub samples: Vec<SampleReport>,
+}
+
+impl ConformanceReport {
+    /// Returns a summary for every format (in the order of `SubtitleFormat::ALL`, unknown files last).
+    pub fn summaries(&self) -> Vec<FormatSummary> {
+        let formats = SubtitleFormat::ALL.iter().map(|&format| Some(format)).chain(Some(None));
+        formats
+            .filter_map(|format| {
+                let samples: Vec<&SampleReport> = self.samples.iter().filter(|sample| sample.format == format).collect();
+                if samples.is_empty() {
+                    return None;
+                }
+                let count = |f: &dyn Fn(&SampleReport) -> bool| samples.iter().filter(|sample| f(sample)).count();
+                Some(FormatSummary {
+                    format,
+                    samples: samples.len(),
+                    passed: count(&|s| s.passed()),
+                    parsed: count(&|s| s.parse == CheckOutcome::Passed),
+                    round_trip: count(&|s| s.round_trip == CheckOutcome::Passed),
+                    identical: count(&|s| s.identical),
+                    converted: count(&|s| s.convert == CheckOutcome::Passed),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the report as JSON object with the summaries in `"formats"` and the results of every file in `"samples"`:
+    ///
+    /// ```text
+    /// {"formats":[{"format":".srt (SubRip)","samples":1,"passed":1,"parsed":1,"round_trip":1,"identical":1,"converted":1}],
+    ///  "samples":[{"file":"a.srt","format":".srt (SubRip)","parse":{"result":"passed"},...,"entries":3,"warnings":0}]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let number = |n: usize| Value::Number(n as f64);
+        let summaries = self
+            .summaries()
+            .into_iter()
+            .map(|summary| {
+                Value::Object(vec![
+                    (
+                        "format".to_string(),
+                        summary.format.map_or(Value::Null, |format| Value::String(format.get_name().to_string())),
+                    ),
+                    ("samples".to_string(), number(summary.samples)),
+                    ("passed".to_string(), number(summary.passed)),
+                    ("parsed".to_string(), number(summary.parsed)),
+                    ("round_trip".to_string(), number(summary.round_trip)),
+                    ("identical".to_string(), number(summary.identical)),
+                    ("converted".to_string(), number(summary.converted)),
+                ])
+            })
+            .collect();
+        Value::Object(vec![
+            ("formats".to_string(), Value::Array(summaries)),
+            (
+                "samples".to_string(),
+                Value::Array(self.samples.iter().map(SampleReport::to_json).collect()),
+            ),
+        ])
+        .to_string()
+    }
+}
+
+/// Returns the messages of the error and all its causes.
+fn error_message(error: &Error) -> String {
+    let error: &dyn Fail = error;
+    error.iter_chain().map(|cause| cause.to_string()).collect::<Vec<_>>().join(": ")
+}
+
+/// Compares the timing and the texts of two lists of entries.
+fn compare_entries(expected: &[SubtitleEntry], found: &[SubtitleEntry]) -> Result<(), String> {
+    if expected.len() != found.len() {
+        return Err(format!("expected {} entries, found {}", expected.len(), found.len()));
+    }
+    for (idx, (a, b)) in expected.iter().zip(found).enumerate() {
+        if a.timespan != b.timespan {
+            let (from, to) = (
+                (a.timespan.start.msecs(), a.timespan.end.msecs()),
+                (b.timespan.start.msecs(), b.timespan.end.msecs()),
+            );
+            return Err(format!("entry {}: the timespan changed from {:?} to {:?} (milliseconds)", idx, from, to));
+        }
+        if a.line != b.line {
+            return Err(format!("entry {}: the text changed from {:?} to {:?}", idx, a.line, b.line));
+        }
+    }
+    Ok(())
+}
+
+/// Parses a file and also returns the number of warnings (which are only available for text formats).
+fn parse_counting_warnings(format: SubtitleFormat, content: &[u8], options: &ConformanceOptions) -> crate::Result<(SubtitleFile, usize)> {
+    if format.capabilities().text {
+        let text = decode_bytes_to_string(content, options.encoding)?;
+        let (file, warnings) = parse_str_with_warnings(format, &text, options.fps)?;
+        Ok((file, warnings.len()))
+    } else {
+        Ok((parse_bytes(format, content, options.encoding, options.fps)?, 0))
+    }
+}
+
+/// Runs all checks on the content of a file (the extension of `path` is used to detect the format).
+pub fn check_sample(path: &Path, content: &[u8], options: &ConformanceOptions) -> SampleReport {
+    let mut report = SampleReport {
+        path: path.to_path_buf(),
+        format: get_subtitle_format(path.extension(), content),
+        parse: CheckOutcome::Skipped("unknown format".to_string()),
+        round_trip: CheckOutcome::Skipped("unknown format".to_string()),
+        convert: CheckOutcome::Skipped("unknown format".to_string()),
+        identical: false,
+        entries: 0,
+        warnings: 0,
+    };
+    let format = match report.format {
+        Some(format) => format,
+        None => return report,
+    };
+
+    let parsed = parse_counting_warnings(format, content, options).and_then(|(file, warnings)| Ok((file.get_subtitle_entries()?, file, warnings)));
+    let (entries, file) = match parsed {
+        Ok((entries, file, warnings)) => {
+            report.parse = CheckOutcome::Passed;
+            report.entries = entries.len();
+            report.warnings = warnings;
+            (entries, file)
+        }
+        Err(error) => {
+            report.parse = CheckOutcome::Failed(error_message(&error));
+            report.round_trip = CheckOutcome::Skipped("parsing failed".to_string());
+            report.convert = CheckOutcome::Skipped("parsing failed".to_string());
+            return report;
+        }
+    };
+
+    report.round_trip = CheckOutcome::from_result(file.to_data().map_err(|e| error_message(&e)).and_then(|data| {
+        report.identical = data == content;
+        let file = parse_bytes(
+            format,
+            &data,
+            Some(encoding_rs::UTF_8).filter(|_| format.capabilities().text),
+            options.fps,
+        )
+        .map_err(|e| format!("the written file can't be parsed: {}", error_message(&e)))?;
+        compare_entries(&entries, &file.get_subtitle_entries().map_err(|e| error_message(&e))?)
+    }));
+
+    let texts: Option<Vec<(_, String)>> = entries
+        .iter()
+        .map(|entry| entry.line.clone().map(|line| (entry.timespan, line)))
+        .collect();
+    report.convert = match texts {
+        None => CheckOutcome::Skipped("the entries have no text".to_string()),
+        Some(texts) => CheckOutcome::from_result(
+            SrtFile::create(texts)
+                .and_then(|srt| SrtFile::parse(&String::from_utf8_lossy(&srt.to_data()?)))
+                .and_then(|srt| srt.get_subtitle_entries())
+                .map_err(|e| error_message(&e))
+                .and_then(|converted| compare_entries(&entries, &converted)),
+        ),
+    };
+    report
+}
+
+/// Collects the files of a directory (sorted by name, so the report is deterministic).
+pub(crate) fn collect_files(dir: &Path, recursive: bool, result: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            if recursive {
+                collect_files(&path, recursive, result)?;
+            }
+        } else {
+            result.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Runs all checks on every file in the directory.
+///
+/// Files which don't look like subtitles (by extension) are skipped, files with a subtitle extension whose format
+/// is not detected are reported with the format `None`.
+pub fn check_directory(dir: &Path, options: &ConformanceOptions) -> io::Result<ConformanceReport> {
+    let mut files = Vec::new();
+    collect_files(dir, options.recursive, &mut files)?;
+
+    let mut report = ConformanceReport::default();
+    for path in files {
+        let is_subtitle_extension = SubtitleFormat::ALL
+            .iter()
+            .any(|format| format.extensions().iter().any(|&ext| path.extension() == Some(OsStr::new(ext))));
+        if !is_subtitle_extension {
+            continue;
+        }
+        let content = std::fs::read(&path)?;
+        report.samples.push(check_sample(&path, &content, options));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conformance_report() {
+        let options = ConformanceOptions::default();
+        let srt = check_sample(
+            "a.srt".as_ref(),
+            b"1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n\n",
+            &options,
+        );
+        assert!(srt.passed());
+        assert_eq!((srt.identical, srt.entries), (true, 2));
+
+        let broken = check_sample("b.srt".as_ref(), b"1\n00:00:01,000 -> 00:00:02,000\nHello\n", &options);
+        assert!(broken.parse.is_failed());
+        assert_eq!(broken.round_trip, CheckOutcome::Skipped("parsing failed".to_string()));
+
+        let unknown = check_sample("c.txt".as_ref(), b"just some notes\n", &options);
+        assert_eq!(unknown.format, None);
+        assert!(!unknown.passed());
+
+        let report = ConformanceReport {
+            samples: vec![srt, broken, unknown],
+        };
+        let summaries = report.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].format, Some(SubtitleFormat::SubRip));
+        assert_eq!((summaries[0].samples, summaries[0].passed, summaries[0].identical), (2, 1, 1));
+        let json = report.to_json();
+        assert!(json.starts_with("{\"formats\":[{\"format\":\".srt (SubRip)\",\"samples\":2,\"passed\":1,"));
+        assert!(json.contains("\"format\":null"));
+
+        assert!(check_directory("/nonexistent/subparse/samples".as_ref(), &options).is_err());
+    }
+}
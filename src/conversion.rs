@@ -0,0 +1,459 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! What survives a conversion between two formats.
+//!
+//! `Feature::support()` describes what a format can store. It is a `const fn` with an exhaustive match, so a new
+//! format can't be added without deciding its support for every feature (the support of end times and bitmaps
+//! comes from `SubtitleFormat::capabilities()`). The conversion matrix is derived from it:
+//! `preservation()` tells whether a feature survives a conversion lossless, degrades or is dropped, and
+//! `SubtitleFile::conversion_warnings()` checks which features a file actually uses, so users know before converting
+//! that, for example, the karaoke timing of an `.ass` file is lost in a `.srt` file. `SubtitleFile::analyze_conversion()`
+//! also lists the entries and tags of every loss and the styles which are dropped, for a confirmation before converting:
+//!
+//! ```
+//! use subparse::conversion::{Feature, Preservation};
+//! use subparse::{parse_str, SubtitleFormat};
+//!
+//! let ass = "[Events]\nFormat: Layer, Start, End, Style, Name, Text\nDialogue: 0,0:00:01.00,0:00:03.00,Default,,{\\k50}Hel{\\k50}lo\n";
+//! let file = parse_str(SubtitleFormat::SubStationAlpha, ass, 25.0).unwrap();
+//! let warnings = file.conversion_warnings(SubtitleFormat::SubRip).unwrap();
+//! assert_eq!((warnings[0].feature, warnings[0].preservation), (Feature::Karaoke, Preservation::Dropped));
+//!
+//! let report = file.analyze_conversion(SubtitleFormat::SubRip).unwrap();
+//! assert_eq!((report.losses[0].entry_indices.as_slice(), report.losses[0].tags.as_slice()), (&[0][..], &["\\k"][..]));
+//! assert_eq!(report.styles, vec!["Default".to_string()]);
+//! ```
+
+use crate::errors::Result;
+use crate::{SubtitleEntry, SubtitleFile, SubtitleFormat};
+use std::fmt;
+
+/// Tags (by their start or prefix) with their names.
+type TagPatterns = &'static [(&'static str, &'static str)];
+
+/// Something a subtitle can contain besides its times and plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// Bold, italic and underlined text.
+    BasicStyling,
+
+    /// Text colors.
+    Colors,
+
+    /// Font names and sizes.
+    Fonts,
+
+    /// Alignment and positions on the screen.
+    Positioning,
+
+    /// Timing of syllables or words inside of a subtitle.
+    Karaoke,
+
+    /// An end time for every subtitle (`.idx` files only store start times).
+    EndTimes,
+
+    /// Subtitles as images.
+    Bitmaps,
+}
+
+/// How well a format supports a feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Support {
+    /// The feature can't be stored.
+    None,
+
+    /// Only a part of the feature can be stored (like only italics, or only a few colors).
+    Partial,
+
+    /// The feature can be stored completely.
+    Full,
+}
+
+/// What happens to a feature in a conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Preservation {
+    /// The feature is kept completely.
+    Lossless,
+
+    /// The feature is kept partially.
+    Degraded,
+
+    /// The feature is lost.
+    Dropped,
+}
+
+impl Feature {
+    /// All features, for example to print the whole matrix.
+    pub const ALL: [Feature; 7] = [
+        Feature::BasicStyling,
+        Feature::Colors,
+        Feature::Fonts,
+        Feature::Positioning,
+        Feature::Karaoke,
+        Feature::EndTimes,
+        Feature::Bitmaps,
+    ];
+
+    /// Returns how well a format supports the feature.
+    pub const fn support(self, format: SubtitleFormat) -> Support {
+        use self::Feature::*;
+        use self::Support::{Full, Partial};
+        use crate::SubtitleFormat::*;
+        // end times and bitmaps are decided by the capabilities of the format
+        let capabilities = format.capabilities();
+        match (format, self) {
+            (_, EndTimes) | (_, Bitmaps) => {
+                if matches!(self, EndTimes) && capabilities.end_times || matches!(self, Bitmaps) && capabilities.bitmap {
+                    Full
+                } else {
+                    Support::None
+                }
+            }
+            (VobSubIdx, _) | (VobSubSub, _) | (Pgs, _) | (Bdn, _) | (Dvb, _) => Support::None,
+
+            (SubStationAlpha, Karaoke) => Full,
+            (Lrc, Karaoke) => Partial,
+            (_, Karaoke) => Support::None,
+
+            (SubStationAlpha, _) | (Ttml, _) | (Itt, _) => Full,
+            (SubRip, BasicStyling) | (WebVtt, BasicStyling) | (JacoSub, BasicStyling) | (RealText, BasicStyling) => Full,
+            (SpruceStl, BasicStyling) | (Srv3, BasicStyling) | (Sami, BasicStyling) => Full,
+            (SubRip, _) | (RealText, _) => Partial,
+            (WebVtt, Fonts) => Support::None,
+            (WebVtt, _) => Partial,
+            (MicroDVD, _) => Partial,
+            (Scc, Fonts) | (Pac, Colors) | (Pac, Fonts) => Support::None,
+            (Scc, _) | (EbuStl, _) | (Pac, _) => Partial,
+            (Mpl2, BasicStyling) | (JacoSub, Positioning) => Partial,
+            (Mpl2, _)
+            | (JacoSub, _)
+            | (Sbv, _)
+            | (Lrc, _)
+            | (SubViewer, _)
+            | (SpruceStl, _)
+            | (AqTitle, _)
+            | (VPlayer, _)
+            | (PowerDivX, _)
+            | (Srv3, _)
+            | (Sami, _) => Support::None,
+        }
+    }
+
+    /// Returns a short description of the loss in a conversion.
+    fn loss(self) -> &'static str {
+        match self {
+            Feature::BasicStyling => "bold/italic/underline styling",
+            Feature::Colors => "text colors",
+            Feature::Fonts => "font names and sizes",
+            Feature::Positioning => "alignment and positions",
+            Feature::Karaoke => "karaoke timing (flattened to whole subtitles)",
+            Feature::EndTimes => "end times (subtitles last until the next one)",
+            Feature::Bitmaps => "subtitle images (there is no text to convert)",
+        }
+    }
+
+    /// Returns the HTML-like tags (by their start) and the override tags (by their prefix) of the feature with
+    /// their names (the first matching prefix wins).
+    fn tag_patterns(self) -> (TagPatterns, TagPatterns) {
+        match self {
+            Feature::BasicStyling => (
+                &[("<i>", "<i>"), ("<b>", "<b>"), ("<u>", "<u>")],
+                &[("i1", "\\i"), ("b1", "\\b"), ("u1", "\\u")],
+            ),
+            Feature::Colors => (&[("<font color", "<font color>")], &[("c&", "\\c"), ("1c", "\\1c"), ("3c", "\\3c")]),
+            Feature::Fonts => (
+                &[("<font face", "<font face>"), ("<font size", "<font size>")],
+                &[("fn", "\\fn"), ("fs", "\\fs")],
+            ),
+            Feature::Positioning => (&[], &[("an", "\\an"), ("pos(", "\\pos"), ("move(", "\\move")]),
+            Feature::Karaoke => (&[], &[("kf", "\\kf"), ("ko", "\\ko"), ("k", "\\k")]),
+            Feature::EndTimes | Feature::Bitmaps => (&[], &[]),
+        }
+    }
+
+    /// Returns the names of the tags of the feature in a text (with duplicates).
+    fn tags_in(self, text: &str) -> Vec<&'static str> {
+        let lower = text.to_lowercase();
+        let (html, overrides) = self.tag_patterns();
+        let mut tags: Vec<&'static str> = html.iter().filter(|(start, _)| lower.contains(start)).map(|&(_, name)| name).collect();
+        for block in lower.split('{').skip(1).filter_map(|block| block.split('}').next()) {
+            for tag in block.split('\\').skip(1) {
+                tags.extend(overrides.iter().find(|(prefix, _)| tag.starts_with(prefix)).map(|&(_, name)| name));
+            }
+        }
+        tags
+    }
+
+    /// Returns `true` if the entry uses the feature.
+    fn is_used_by(self, entry: &SubtitleEntry) -> bool {
+        match (self, entry.line.as_deref()) {
+            (Feature::Bitmaps, line) => line.is_none(),
+            (Feature::EndTimes, _) => true,
+            (_, Some(text)) => !self.tags_in(text).is_empty() || (self == Feature::BasicStyling && entry.flags.italic),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Returns what happens to a feature in a conversion between two formats (`None` if the source can't store it).
+pub const fn preservation(from: SubtitleFormat, to: SubtitleFormat, feature: Feature) -> Option<Preservation> {
+    match (feature.support(from), feature.support(to)) {
+        (Support::None, _) => None,
+        (_, Support::Full) | (Support::Partial, Support::Partial) => Some(Preservation::Lossless),
+        (Support::Full, Support::Partial) => Some(Preservation::Degraded),
+        (_, Support::None) => Some(Preservation::Dropped),
+    }
+}
+
+/// A feature which is used by a file and does not survive a conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionWarning {
+    /// The feature.
+    pub feature: Feature,
+
+    /// Whether the feature is degraded or dropped.
+    pub preservation: Preservation,
+
+    /// The number of entries which use the feature.
+    pub entries: usize,
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self.preservation {
+            Preservation::Lossless => "keeps",
+            Preservation::Degraded => "degrades",
+            Preservation::Dropped => "drops",
+        };
+        write!(f, "the conversion {} {} in {} entries", verb, self.feature.loss(), self.entries)
+    }
+}
+
+/// A feature which is used by a file and does not survive a conversion, with where it is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loss {
+    /// The feature.
+    pub feature: Feature,
+
+    /// Whether the feature is degraded or dropped.
+    pub preservation: Preservation,
+
+    /// The positions of the entries in `get_subtitle_entries()` which use the feature.
+    pub entry_indices: Vec<usize>,
+
+    /// The tags which use the feature, in the order of their first use (like `"<font color>"`, `"\\pos"` or
+    /// `"<mm:ss.xx>"` for the word timings of `.lrc` files).
+    pub tags: Vec<&'static str>,
+}
+
+impl Loss {
+    /// Returns the summary of the loss.
+    pub fn warning(&self) -> ConversionWarning {
+        ConversionWarning {
+            feature: self.feature,
+            preservation: self.preservation,
+            entries: self.entry_indices.len(),
+        }
+    }
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.warning())?;
+        if !self.tags.is_empty() {
+            write!(f, " ({})", self.tags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything of a file which is lost or degraded in a conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LossReport {
+    /// The format of the file (`None` for files of other crates, which never have losses).
+    pub from: Option<SubtitleFormat>,
+
+    /// The target format.
+    pub to: SubtitleFormat,
+
+    /// The features which are used by the file and don't survive the conversion (in the order of `Feature::ALL`).
+    pub losses: Vec<Loss>,
+
+    /// The names of the styles of a `.ssa`/`.ass` file which are used by its events, but are dropped because the
+    /// target is another format (in the order of their first use).
+    pub styles: Vec<String>,
+}
+
+impl LossReport {
+    /// Returns `true` if nothing of the file is lost.
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty() && self.styles.is_empty()
+    }
+
+    /// Returns the summaries of the losses (see `SubtitleFile::conversion_warnings()`).
+    pub fn warnings(&self) -> Vec<ConversionWarning> {
+        self.losses.iter().map(Loss::warning).collect()
+    }
+}
+
+impl fmt::Display for LossReport {
+    /// Writes every loss on its own line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for loss in &self.losses {
+            writeln!(f, "{}", loss)?;
+        }
+        if !self.styles.is_empty() {
+            writeln!(f, "the conversion drops the styles {}", self.styles.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl SubtitleFile {
+    /// Returns the features of this file which are degraded or dropped when it is converted to `to`.
+    ///
+    /// Files of other crates (see `registry`) have no known format and never get warnings.
+    pub fn conversion_warnings(&self, to: SubtitleFormat) -> Result<Vec<ConversionWarning>> {
+        Ok(self.analyze_conversion(to)?.warnings())
+    }
+
+    /// Returns everything of this file which is degraded or dropped when it is converted to `to`, with the entries
+    /// and tags of every loss (nothing is converted).
+    pub fn analyze_conversion(&self, to: SubtitleFormat) -> Result<LossReport> {
+        let mut report = LossReport {
+            from: self.format(),
+            to,
+            losses: Vec::new(),
+            styles: Vec::new(),
+        };
+        let from = match report.from {
+            Some(from) => from,
+            None => return Ok(report),
+        };
+        let entries = self.get_subtitle_entries()?;
+        let words = match self {
+            SubtitleFile::LrcFile(f) => f.words()?,
+            _ => Vec::new(),
+        };
+
+        for &feature in Feature::ALL.iter() {
+            let preservation = match preservation(from, to, feature) {
+                Some(preservation) if preservation != Preservation::Lossless => preservation,
+                _ => continue,
+            };
+            let mut loss = Loss {
+                feature,
+                preservation,
+                entry_indices: Vec::new(),
+                tags: Vec::new(),
+            };
+            for (entry_idx, entry) in entries.iter().enumerate() {
+                let mut tags = entry.line.as_deref().map(|text| feature.tags_in(text)).unwrap_or_default();
+                // the word timings of `.lrc` files are not part of the entry texts
+                let has_words = feature == Feature::Karaoke && words.get(entry_idx).is_some_and(|words| !words.is_empty());
+                if has_words {
+                    tags.push("<mm:ss.xx>");
+                }
+                if has_words || feature.is_used_by(entry) {
+                    loss.entry_indices.push(entry_idx);
+                    for tag in tags {
+                        if !loss.tags.contains(&tag) {
+                            loss.tags.push(tag);
+                        }
+                    }
+                }
+            }
+            if !loss.entry_indices.is_empty() {
+                report.losses.push(loss);
+            }
+        }
+
+        if let (SubtitleFile::SubStationAlpha(f), false) = (self, to == SubtitleFormat::SubStationAlpha) {
+            for event in f.get_events() {
+                let style = event.style.trim();
+                if !style.is_empty() && !report.styles.iter().any(|s| s == style) {
+                    report.styles.push(style.to_string());
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_matrix() {
+        use crate::SubtitleFormat::*;
+        assert_eq!(preservation(SubStationAlpha, SubRip, Feature::Karaoke), Some(Preservation::Dropped));
+        assert_eq!(preservation(SubStationAlpha, SubRip, Feature::Colors), Some(Preservation::Degraded));
+        assert_eq!(preservation(SubRip, SubStationAlpha, Feature::Colors), Some(Preservation::Lossless));
+        assert_eq!(preservation(SubRip, VobSubIdx, Feature::EndTimes), Some(Preservation::Dropped));
+        assert_eq!(preservation(Sbv, SubRip, Feature::Colors), None);
+        for &from in SubtitleFormat::ALL.iter() {
+            for &feature in Feature::ALL.iter() {
+                assert!(preservation(from, from, feature).is_none_or(|p| p == Preservation::Lossless));
+            }
+        }
+
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<font color=\"red\">Red</font>\n\n2\n00:00:03,000 --> 00:00:04,000\n{\\an8}<i>Top</i>\n";
+        let file = crate::parse_str(SubRip, srt, 25.0).unwrap();
+        let warnings = file.conversion_warnings(Mpl2).unwrap();
+        let features: Vec<Feature> = warnings.iter().map(|w| w.feature).collect();
+        assert_eq!(features, vec![Feature::BasicStyling, Feature::Colors, Feature::Positioning]);
+        assert_eq!(warnings[0].preservation, Preservation::Degraded);
+        assert_eq!(warnings[1].to_string(), "the conversion drops text colors in 1 entries");
+        assert!(file.conversion_warnings(SubStationAlpha).unwrap().is_empty());
+    }
+
+    #[test]
+    fn support_matches_capabilities() {
+        let full = |yes: bool| if yes { Support::Full } else { Support::None };
+        for &format in SubtitleFormat::ALL.iter() {
+            let capabilities = format.capabilities();
+            assert_eq!(Feature::EndTimes.support(format), full(capabilities.end_times), "{:?}", format);
+            assert_eq!(Feature::Bitmaps.support(format), full(capabilities.bitmap), "{:?}", format);
+            if capabilities.bitmap {
+                assert_eq!(Feature::BasicStyling.support(format), Support::None, "{:?}", format);
+            }
+        }
+    }
+
+    #[test]
+    fn conversion_loss_report() {
+        use crate::SubtitleFormat::*;
+        let ass = "[Events]\nFormat: Layer, Start, End, Style, Name, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Default,,{\\an8\\1c&H0000FF&}Top\n\
+                   Dialogue: 0,0:00:03.00,0:00:04.00,Sign,,{\\pos(10,10)\\kf20}Sign\n\
+                   Dialogue: 0,0:00:05.00,0:00:06.00,Default,,plain";
+        let file = crate::parse_str(SubStationAlpha, ass, 25.0).unwrap();
+        let report = file.analyze_conversion(WebVtt).unwrap();
+        let losses: Vec<(Feature, Vec<usize>, Vec<&str>)> = report
+            .losses
+            .iter()
+            .map(|loss| (loss.feature, loss.entry_indices.clone(), loss.tags.clone()))
+            .collect();
+        assert_eq!(
+            losses,
+            vec![
+                (Feature::Colors, vec![0], vec!["\\1c"]),
+                (Feature::Positioning, vec![0, 1], vec!["\\an", "\\pos"]),
+                (Feature::Karaoke, vec![1], vec!["\\kf"]),
+            ]
+        );
+        assert_eq!(report.styles, vec!["Default".to_string(), "Sign".to_string()]);
+        assert_eq!(report.losses[0].to_string(), "the conversion degrades text colors in 1 entries (\\1c)");
+        assert!(file.analyze_conversion(SubStationAlpha).unwrap().is_lossless());
+
+        let lrc = crate::parse_str(Lrc, "[00:01.00]<00:01.00>Hel<00:01.50>lo\n[00:03.00]plain\n", 25.0).unwrap();
+        let report = lrc.analyze_conversion(SubRip).unwrap();
+        assert_eq!(
+            (report.losses[0].feature, report.losses[0].entry_indices.clone()),
+            (Feature::Karaoke, vec![0])
+        );
+    }
+}
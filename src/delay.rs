@@ -0,0 +1,34 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Delays which are stored as metadata of a file.
+//!
+//! Some formats can shift timestamps by a delay (`delay:` lines in `.idx` files, the `[offset:...]` tag of `.lrc`
+//! files). The delay is always
+//! applied by `get_subtitle_entries()`, so the entries have the times at which the subtitles are shown, and
+//! `update_subtitle_entries()` subtracts it again. A `DelayOutput` decides whether the written file still contains
+//! the delay:
+//!
+//! ```
+//! use subparse::delay::DelayOutput;
+//! use subparse::IdxFile;
+//! use subparse::SubtitleFileInterface;
+//!
+//! let mut file = IdxFile::parse("delay: 00:00:01:000\ntimestamp: 00:00:02:000, filepos: 000000000\n").unwrap();
+//! assert_eq!(file.get_subtitle_entries().unwrap()[0].timespan.start.msecs(), 3000);
+//!
+//! file.set_delay_output(DelayOutput::Bake);
+//! assert_eq!(file.to_data().unwrap(), b"timestamp: 00:00:03:000, filepos: 000000000\n");
+//! ```
+
+/// Whether a delay of a file is kept when the file is written (see the module documentation).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DelayOutput {
+    /// The delay lines are written unchanged and the timestamps without the delay.
+    #[default]
+    KeepMetadata,
+
+    /// The delay is added to the timestamps and the delay lines are removed.
+    Bake,
+}
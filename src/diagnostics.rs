@@ -0,0 +1,516 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Readable reports of parse errors and warnings for command line tools (requires the `diagnostics` feature).
+//!
+//! A `Diagnostic` collects the location of an issue from the error chain (the line numbers of the format errors)
+//! and renders it with the offending source line, underlined and with a hint how to fix it:
+//!
+//! ```
+//! use subparse::diagnostics::render_error;
+//! use subparse::SrtFile;
+//!
+//! let source = "1\n00:00:01,000 -> 00:00:02,000\nHello\n";
+//! let error = SrtFile::parse(source).unwrap_err();
+//! println!("{}", render_error(&error, source, "movie.srt"));
+//! // error: parsing the subtitle data failed
+//! //   --> movie.srt:2:1
+//! //    |
+//! //  2 | 00:00:01,000 -> 00:00:02,000
+//! //    | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^ expected SubRip timespan line, found '00:00:01,000 -> 00:00:02,000'
+//! //    |
+//! //    = hint: the timing line has to look like `00:00:01,000 --> 00:00:02,500`
+//! ```
+
+use crate::errors::{
+    aqtitle_errors, bdn_errors, idx_errors, jacosub_errors, mdvd_errors, mpl2_errors, powerdivx_errors, realtext_errors, sami_errors, sbv_errors,
+    scc_errors, spruce_errors, srt_errors, srv3_errors, ssa_errors, subviewer_errors, ttml_errors, vplayer_errors, vtt_errors,
+};
+use crate::errors::{Error, ErrorKind};
+use crate::timetypes::TimestampOutOfRange;
+use crate::warnings::{TimestampIssue, Warning, WarningKind, Warnings};
+use failure::Fail;
+use std::fmt::Write;
+
+/// How serious an issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The file could not be parsed.
+    Error,
+
+    /// The file was parsed, but something is suspicious.
+    Warning,
+}
+
+/// An issue with its location in the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How serious the issue is.
+    pub severity: Severity,
+
+    /// The headline.
+    pub message: String,
+
+    /// The line of the issue (starting at 0).
+    pub line_num: Option<usize>,
+
+    /// The byte position of the issue in the source (used if there is no line number).
+    pub byte_offset: Option<usize>,
+
+    /// The text which is underlined in the line (the whole line is underlined if it is not found).
+    pub highlight: Option<String>,
+
+    /// The message next to the underline.
+    pub label: Option<String>,
+
+    /// How the issue can be fixed.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: String) -> Diagnostic {
+        Diagnostic {
+            severity,
+            message,
+            line_num: None,
+            byte_offset: None,
+            highlight: None,
+            label: None,
+            hint: None,
+        }
+    }
+
+    /// Collects the location and a hint from all causes of the error.
+    pub fn from_error(error: &Error) -> Diagnostic {
+        let mut result = Diagnostic::new(Severity::Error, error.to_string());
+        result.hint = match error.kind() {
+            ErrorKind::DecodingError | ErrorKind::EncodingDetectionError => {
+                Some("pass the character encoding of the file to `parse_bytes()`".to_string())
+            }
+            _ => None,
+        };
+
+        let error_fail: &dyn Fail = error;
+        let chain: Vec<&dyn Fail> = error_fail.iter_chain().collect();
+        for cause in chain.iter().skip(1) {
+            result.apply_cause(*cause);
+        }
+        if chain.len() > 1 {
+            result.label = chain.last().map(|cause| cause.to_string());
+        }
+        result
+    }
+
+    /// Takes the location and hint of a format error (the innermost causes are applied last and have the most specific hints).
+    fn apply_cause(&mut self, cause: &dyn Fail) {
+        let mut hint = None;
+        let mut line_num = None;
+        if let Some(error) = cause.downcast_ref::<srt_errors::Error>() {
+            use srt_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedIndexLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every entry starts with its number, like `1`");
+                }
+                ExpectedTimestampLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("the timing line has to look like `00:00:01,000 --> 00:00:02,500`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<ssa_errors::Error>() {
+            use ssa_errors::ErrorKind::*;
+            match error.kind() {
+                SsaFieldsInfoNotFound => hint = Some("add a line like `Format: Layer, Start, End, Style, Text` to the `[Events]` section"),
+                SsaMissingField { line_num: l, .. } => {
+                    line_num = Some(*l);
+                    hint = Some("the `Format:` line needs the fields `Start`, `End` and `Text`");
+                }
+                SsaDuplicateField { line_num: l, f } => {
+                    line_num = Some(*l);
+                    self.highlight = Some(f.to_string());
+                }
+                SsaTextFieldNotLast { line_num: l } => {
+                    line_num = Some(*l);
+                    hint = Some("move `Text` to the end of the `Format:` line");
+                }
+                SsaIncorrectNumberOfFields { line_num: l } => {
+                    line_num = Some(*l);
+                    hint = Some("a line needs all fields of the `Format:` line of its section");
+                }
+                SsaWrongTimepointFormat { line_num: l, string } => {
+                    line_num = Some(*l);
+                    self.highlight = Some(string.trim().to_string());
+                    hint = Some("times look like `0:00:01.50` (hours, minutes, seconds and centiseconds)");
+                }
+                SsaDialogLineParseError { line_num: l, .. } | SsaLineParseError { line_num: l, .. } => line_num = Some(*l),
+                SsaUnknownStyle { name } | SsaDuplicateStyle { name } => self.highlight = Some(name.clone()),
+                SsaInvalidFieldValue { .. } => hint = Some("remove the commas and line breaks from the value"),
+            }
+        } else if let Some(error) = cause.downcast_ref::<idx_errors::Error>() {
+            let idx_errors::ErrorKind::IdxLineParseError { line_num: l, .. } = error.kind();
+            line_num = Some(*l);
+            hint = Some("timestamps look like `timestamp: 00:00:01:000, filepos: 000000000`");
+        } else if let Some(error) = cause.downcast_ref::<mdvd_errors::Error>() {
+            use mdvd_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedSubtitleLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every line has to look like `{25}{50}Text` (start and end frame)");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<sbv_errors::Error>() {
+            use sbv_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedTimingLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every caption starts with a timing line like `0:00:01.000,0:00:04.000`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<subviewer_errors::Error>() {
+            use subviewer_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedTimingLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("the header has `[KEY]value` lines and subtitles start with a line like `00:00:01.00,00:00:04.00`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<realtext_errors::Error>() {
+            use realtext_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedWindowElement => hint = Some("RealText subtitles are `<time begin=\"..\"/>` tags inside of a `<window>` element"),
+                UnclosedTag => hint = Some("every tag has to end with `>`"),
+                MissingBegin => hint = Some("every `<time>` tag needs a `begin` attribute"),
+                InvalidTime { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("times look like `00:01:02.50`, `1:02.5` or `62.5`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<spruce_errors::Error>() {
+            use spruce_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedSubtitleLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("settings look like `$FontName = Arial` and subtitles like `00:00:01:12 , 00:00:03:20 , Text`");
+                }
+                InvalidTimecode { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("the frames of a timecode have to be smaller than the frame rate (pass 29.97 for 30 frame timecodes)");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<powerdivx_errors::Error>() {
+            use powerdivx_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedPowerDivXLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every subtitle is a line like `{0:00:01}{0:00:03}Hello|world`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<vplayer_errors::Error>() {
+            use vplayer_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedVPlayerLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every subtitle is a line like `00:00:12:Hello|world`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<aqtitle_errors::Error>() {
+            use aqtitle_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedFrameLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every subtitle starts with a frame line like `-->> 000025`");
+                }
+                InvalidFrameNumber { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("the frame number after `-->>` has to be a positive number");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<bdn_errors::Error>() {
+            use bdn_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedBdnElement => hint = Some("BDN files have a `<BDN>` element with `<Format>` and `<Events>`"),
+                MissingFrameRate => hint = Some("the `<Format>` element needs a `FrameRate` attribute like `23.976`"),
+                InvalidFrameRate { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("the frame rate has to be 23.976, 24, 25, 29.97, 30, 50, 59.94 or 60");
+                }
+                DropFrameNotSupported => hint = Some("convert the timecodes to non-drop-frame timecodes (`DropFrame=\"False\"`)"),
+                UnclosedTag => hint = Some("every tag has to end with `>`"),
+                UnclosedEvent => hint = Some("every `<Event>` element has to end with `</Event>`"),
+                MissingTimecode { attribute } => {
+                    self.highlight = Some(attribute.to_string());
+                    hint = Some("every `<Event>` needs an `InTC` and an `OutTC` attribute");
+                }
+                InvalidTimecode { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("timecodes look like `00:00:01:12` (hours, minutes, seconds and frames)");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<mpl2_errors::Error>() {
+            use mpl2_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedMpl2Line { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every line has to look like `[10][25]Hello|world` (times in deciseconds)");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<jacosub_errors::Error>() {
+            use jacosub_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedJacoLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("every line has to be a `#` directive or look like `0:00:01.00 0:00:02.15 D Hello`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<scc_errors::Error>() {
+            use scc_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedHeader => hint = Some("the first line has to be `Scenarist_SCC V1.0`"),
+                ExpectedCaptionLine { line } => {
+                    self.highlight = Some(line.clone());
+                    hint = Some("caption lines are a time code and hex words, like `00:00:01;15<TAB>9420 9420`");
+                }
+                ErrorAtLine { line_num: l } => line_num = Some(*l),
+            }
+        } else if let Some(error) = cause.downcast_ref::<vtt_errors::Error>() {
+            use vtt_errors::ErrorKind::*;
+            match error.kind() {
+                ExpectedHeader => {
+                    line_num = Some(0);
+                    hint = Some("the first line has to be `WEBVTT`");
+                }
+                ExpectedTimingLine { line_num: l, line } => {
+                    line_num = Some(*l);
+                    self.highlight = Some(line.clone());
+                    hint = Some("cue timings look like `00:01.000 --> 00:02.500`");
+                }
+            }
+        } else if let Some(error) = cause.downcast_ref::<ttml_errors::Error>() {
+            use ttml_errors::ErrorKind::*;
+            match error.kind() {
+                MalformedXml { position, .. } => self.byte_offset = Some(*position),
+                InvalidTime { value } => {
+                    self.highlight = Some(format!("\"{}\"", value));
+                    hint = Some("TTML times look like `00:00:01.500`, `00:00:01:12` (frames), `1.5s`, `1500ms`, `36f` or `15000t`");
+                }
+                InvalidTimeParameter { attribute, .. } => {
+                    self.highlight = Some(attribute.clone());
+                    hint = Some("frame and tick rates have to be positive integers");
+                }
+                ExpectedTtElement => hint = Some("a TTML document has a `<tt xmlns=\"http://www.w3.org/ns/ttml\">` root element"),
+            }
+        } else if let Some(error) = cause.downcast_ref::<srv3_errors::Error>() {
+            use srv3_errors::ErrorKind::*;
+            match error.kind() {
+                MalformedXml { position, .. } => self.byte_offset = Some(*position),
+                InvalidTime { value } => {
+                    self.highlight = Some(format!("\"{}\"", value));
+                    hint = Some("the `t` and `d` attributes are whole milliseconds like `t=\"1500\" d=\"2000\"`");
+                }
+                ExpectedTimedText => hint = Some("a srv3 file has a `<timedtext format=\"3\">` root element"),
+            }
+        } else if let Some(error) = cause.downcast_ref::<sami_errors::Error>() {
+            use sami_errors::ErrorKind::*;
+            match error.kind() {
+                InvalidStart { value } => {
+                    self.highlight = Some(value.clone());
+                    hint = Some("the `Start` attribute of `<SYNC>` is whole milliseconds like `<SYNC Start=1500>`");
+                }
+                ExpectedSami => hint = Some("a SAMI file has a `<SAMI>` root element"),
+            }
+        } else if cause.downcast_ref::<TimestampOutOfRange>().is_some() {
+            hint = Some("the hours of the timestamp are too large for any video");
+        }
+
+        if self.line_num.is_none() {
+            self.line_num = line_num;
+        }
+        if let Some(hint) = hint {
+            self.hint = Some(hint.to_string());
+        }
+    }
+
+    /// Converts a warning of `parse_with_warnings()`.
+    pub fn from_warning(warning: &Warning) -> Diagnostic {
+        let mut result = Diagnostic::new(Severity::Warning, String::new());
+        result.line_num = warning.line_num;
+        match warning.kind {
+            WarningKind::UnknownHeaderKey { ref section, ref key } => {
+                result.message = format!("unknown key `{}` in section `[{}]`", key, section);
+                result.highlight = Some(key.clone());
+                result.hint = Some("the line is kept, but players probably ignore it".to_string());
+            }
+            WarningKind::IgnoredLine { ref line } => {
+                result.message = "ignored a line".to_string();
+                result.highlight = Some(line.clone());
+                result.label = Some("this line does not belong to any subtitle".to_string());
+            }
+            WarningKind::SuspiciousTimestamp { entry_idx, issue } => {
+                result.message = match issue {
+                    TimestampIssue::EndBeforeStart => format!("subtitle entry {} ends before it starts", entry_idx),
+                    TimestampIssue::Negative => format!("subtitle entry {} starts at a negative time", entry_idx),
+                };
+                result.hint = Some("`repair()` fixes the timing".to_string());
+            }
+            WarningKind::UnexpectedIndex { expected, found } => {
+                result.message = format!("expected index {}, found {}", expected, found);
+                result.highlight = Some(found.to_string());
+                result.hint = Some("`SrtNumbering::StartAt(1)` writes continuous indices".to_string());
+            }
+        }
+        result
+    }
+
+    /// Renders the diagnostic with the line of `source` it refers to (`file_name` is only shown in the location).
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let line_num = self
+            .line_num
+            .or_else(|| {
+                let offset = self.byte_offset?;
+                Some(source.get(..offset)?.matches('\n').count())
+            })
+            .or_else(|| {
+                let highlight = self.highlight.as_ref().filter(|highlight| !highlight.is_empty())?;
+                lines.iter().position(|line| line.contains(highlight.as_str()))
+            })
+            .filter(|&line_num| line_num < lines.len());
+
+        let mut result = String::new();
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(result, "{}: {}", severity, self.message);
+
+        match line_num.and_then(|line_num| Some((line_num, *lines.get(line_num)?))) {
+            Some((line_num, line)) => {
+                let line = line.trim_start_matches('\u{feff}');
+                let (start, len) = self.underline(source, line_num, line);
+                let number = (line_num + 1).to_string();
+                let gutter = " ".repeat(number.len() + 1);
+                let column = line.get(..start).map_or(0, |before| before.chars().count()) + 1;
+                let _ = writeln!(result, "{}--> {}:{}:{}", gutter, file_name, number, column);
+                let _ = writeln!(result, "{} |", gutter);
+                let _ = writeln!(result, " {} | {}", number, expand_tabs(line));
+                let indentation = line.get(..start).map_or(0, display_width);
+                let width = line.get(start..start + len).map_or(1, display_width).max(1);
+                let _ = write!(result, "{} | {}{}", gutter, " ".repeat(indentation), "^".repeat(width));
+                if let Some(ref label) = self.label {
+                    let _ = write!(result, " {}", label);
+                }
+                result.push('\n');
+                if let Some(ref hint) = self.hint {
+                    let _ = writeln!(result, "{} |", gutter);
+                    let _ = writeln!(result, "{} = hint: {}", gutter, hint);
+                }
+            }
+            None => {
+                let _ = writeln!(result, " --> {}", file_name);
+                if let Some(ref label) = self.label {
+                    let _ = writeln!(result, "  = {}", label);
+                }
+                if let Some(ref hint) = self.hint {
+                    let _ = writeln!(result, "  = hint: {}", hint);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns the byte range in `line` which is underlined.
+    fn underline(&self, source: &str, line_num: usize, line: &str) -> (usize, usize) {
+        if let Some(offset) = self.byte_offset.filter(|_| self.line_num.is_none()) {
+            let line_start = source.get(..offset).and_then(|before| before.rfind('\n')).map_or(0, |idx| idx + 1);
+            let start = offset - line_start;
+            let len = line.get(start..).map_or(0, |rest| rest.find('>').map_or(rest.len(), |idx| idx + 1));
+            if start < line.len() || line_num == 0 {
+                return (start.min(line.len()), len);
+            }
+        }
+        let highlight = self
+            .highlight
+            .as_ref()
+            .map(|highlight| highlight.trim())
+            .filter(|highlight| !highlight.is_empty());
+        if let Some(start) = highlight.and_then(|highlight| line.find(highlight)) {
+            return (start, highlight.map_or(0, str::len));
+        }
+        let trimmed = line.trim_start();
+        (line.len() - trimmed.len(), trimmed.trim_end().len())
+    }
+}
+
+/// Returns the width of a text in the terminal (tabs are shown as four spaces).
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+fn expand_tabs(s: &str) -> String {
+    s.replace('\t', "    ")
+}
+
+/// Renders an error of a parser (see `Diagnostic::render()`).
+pub fn render_error(error: &Error, source: &str, file_name: &str) -> String {
+    Diagnostic::from_error(error).render(source, file_name)
+}
+
+/// Renders all warnings of `parse_with_warnings()`, separated by empty lines.
+pub fn render_warnings(warnings: &Warnings, source: &str, file_name: &str) -> String {
+    warnings
+        .iter()
+        .map(|warning| Diagnostic::from_warning(warning).render(source, file_name))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SrtFile, TtmlFile};
+
+    #[test]
+    fn render_diagnostics() {
+        let source = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 -> 00:00:04,000\nWorld\n";
+        let rendered = render_error(&SrtFile::parse(source).unwrap_err(), source, "movie.srt");
+        assert_eq!(
+            rendered,
+            "error: parsing the subtitle data failed\n\
+             \x20 --> movie.srt:6:1\n\
+             \x20  |\n\
+             \x206 | 00:00:03,000 -> 00:00:04,000\n\
+             \x20  | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^ expected SubRip timespan line, found '00:00:03,000 -> 00:00:04,000'\n\
+             \x20  |\n\
+             \x20  = hint: the timing line has to look like `00:00:01,000 --> 00:00:02,500`\n"
+        );
+
+        let ttml = "<tt>\n  <body>\n    <p begin=\"1x\" end=\"2s\">x</p>\n  </body>\n</tt>\n";
+        let rendered = render_error(&TtmlFile::parse(ttml).unwrap_err(), ttml, "a.ttml");
+        assert!(rendered.contains(" --> a.ttml:3:14\n"), "{}", rendered);
+        assert!(
+            rendered.contains("  |              ^^^^ the time expression `1x` is invalid or not supported\n"),
+            "{}",
+            rendered
+        );
+
+        let (_, warnings) = SrtFile::parse_with_warnings("1\n00:00:01,000 --> 00:00:02,000\na\n\n5\n00:00:03,000 --> 00:00:04,000\nb\n").unwrap();
+        let rendered = render_warnings(&warnings, "", "x.srt");
+        assert_eq!(
+            rendered,
+            "warning: expected index 2, found 5\n --> x.srt\n  = hint: `SrtNumbering::StartAt(1)` writes continuous indices\n"
+        );
+    }
+}
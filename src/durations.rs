@@ -0,0 +1,102 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! End times for formats which only store when a subtitle starts.
+//!
+//! Formats like `.idx`, MicroDVD lines without end frame (`{100}{0}Text`) or transcripts with one timestamp per
+//! line have no durations, so their end times have to be made up. A `DurationSynthesis` describes how.
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+
+/// How the end times of subtitles without durations are created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DurationSynthesis {
+    /// Every subtitle is shown until the next subtitle starts; the last one is shown for `last`.
+    NextCue {
+        /// The duration of the last subtitle.
+        last: TimeDelta,
+    },
+
+    /// Every subtitle is shown for the same duration (even if it then overlaps the next one).
+    Fixed(TimeDelta),
+
+    /// The duration depends on the length of the text, but a subtitle never overlaps the next one.
+    ReadingSpeed {
+        /// The number of characters (without line breaks) that can be read per second.
+        chars_per_sec: f64,
+
+        /// The minimum duration.
+        min: TimeDelta,
+
+        /// The maximum duration.
+        max: TimeDelta,
+    },
+}
+
+impl DurationSynthesis {
+    /// Shows the last subtitle for one minute and all others until the next one starts (like mpv does for `.idx`).
+    pub const IDX: DurationSynthesis = DurationSynthesis::NextCue {
+        last: TimeDelta::from_msecs(60_000),
+    };
+
+    /// Shows every line of lyrics until the next one starts and the last one for five seconds.
+    pub const LRC: DurationSynthesis = DurationSynthesis::NextCue {
+        last: TimeDelta::from_msecs(5000),
+    };
+
+    /// A common reading speed of 17 characters per second, with a duration between one and seven seconds.
+    pub const READING_SPEED: DurationSynthesis = DurationSynthesis::ReadingSpeed {
+        chars_per_sec: 17.0,
+        min: TimeDelta::from_msecs(1000),
+        max: TimeDelta::from_msecs(7000),
+    };
+
+    /// Returns a timespan for every start time and (optional) text.
+    ///
+    /// The cues don't have to be sorted: the "next" cue is the one with the next greater start time.
+    pub fn timespans(&self, cues: &[(TimePoint, Option<&str>)]) -> Vec<TimeSpan> {
+        let mut starts: Vec<TimePoint> = cues.iter().map(|&(start, _)| start).collect();
+        starts.sort_unstable();
+        let next_start = |start: TimePoint| starts.get(starts.partition_point(|&s| s <= start)).copied();
+
+        cues.iter()
+            .map(|&(start, text)| {
+                let end = match *self {
+                    DurationSynthesis::NextCue { last } => next_start(start).unwrap_or(start + last),
+                    DurationSynthesis::Fixed(duration) => start + duration,
+                    DurationSynthesis::ReadingSpeed { chars_per_sec, min, max } => {
+                        let chars = text.map_or(0, |text| text.chars().filter(|&c| c != '\n' && c != '\r').count());
+                        let msecs = if chars_per_sec > 0.0 {
+                            chars as f64 / chars_per_sec * 1000.0
+                        } else {
+                            0.0
+                        };
+                        let duration = TimeDelta::from_msecs(msecs.round() as i64).max(min).min(max);
+                        next_start(start).map_or(start + duration, |next| next.min(start + duration))
+                    }
+                };
+                TimeSpan::new(start, end)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthesize_durations() {
+        let cues = [
+            (TimePoint::from_secs(1), Some("short")),
+            (TimePoint::from_secs(10), Some("a much longer line which takes a while to read")),
+            (TimePoint::from_secs(3), None),
+        ];
+        let ends = |synthesis: DurationSynthesis| -> Vec<i64> { synthesis.timespans(&cues).iter().map(|t| t.end.msecs()).collect() };
+
+        assert_eq!(ends(DurationSynthesis::IDX), vec![3000, 70000, 10000]);
+        assert_eq!(ends(DurationSynthesis::Fixed(TimeDelta::from_secs(2))), vec![3000, 12000, 5000]);
+        assert_eq!(ends(DurationSynthesis::READING_SPEED), vec![2000, 12706, 4000]);
+    }
+}
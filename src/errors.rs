@@ -8,12 +8,39 @@ use failure::Context;
 use failure::Fail;
 use std::fmt;
 
+pub use crate::alignment::errors as alignment_errors;
+pub use crate::annotations::errors as annotation_errors;
+pub use crate::bitmaps::errors as bitmap_errors;
 pub use crate::formats::idx::errors as idx_errors;
 pub use crate::formats::microdvd::errors as mdvd_errors;
-
+pub use crate::hls::errors as hls_errors;
+pub use crate::journal::errors as journal_errors;
+pub use crate::markers::errors as marker_errors;
+pub use crate::pipeline::errors as pipeline_errors;
+
+pub use crate::formats::aqtitle::errors as aqtitle_errors;
+pub use crate::formats::bdn::errors as bdn_errors;
+pub use crate::formats::dvb::errors as dvb_errors;
+pub use crate::formats::jacosub::errors as jacosub_errors;
+pub use crate::formats::mpl2::errors as mpl2_errors;
+pub use crate::formats::pac::errors as pac_errors;
+pub use crate::formats::pgs::errors as pgs_errors;
+pub use crate::formats::powerdivx::errors as powerdivx_errors;
+pub use crate::formats::realtext::errors as realtext_errors;
+pub use crate::formats::sami::errors as sami_errors;
+pub use crate::formats::sbv::errors as sbv_errors;
+pub use crate::formats::scc::errors as scc_errors;
+pub use crate::formats::spruce::errors as spruce_errors;
 pub use crate::formats::srt::errors as srt_errors;
+pub use crate::formats::srv3::errors as srv3_errors;
 pub use crate::formats::ssa::errors as ssa_errors;
+pub use crate::formats::stl::errors as stl_errors;
+pub use crate::formats::subviewer::errors as subviewer_errors;
+pub use crate::formats::ttml::errors as ttml_errors;
 pub use crate::formats::vobsub::errors as vob_errors;
+pub use crate::formats::vplayer::errors as vplayer_errors;
+pub use crate::formats::vtt::errors as vtt_errors;
+pub use crate::remap::errors as remap_errors;
 
 /// A result type that can be used wide for error handling.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -30,6 +57,9 @@ pub enum ErrorKind {
     /// Parsing error
     ParsingError,
 
+    /// Creating a subtitle file from the given data failed.
+    CreationError,
+
     /// The file format is not supported by this library.
     UnknownFileFormat,
 
@@ -42,29 +72,71 @@ pub enum ErrorKind {
     /// The attempted operation does not work on binary subtitle formats.
     TextFormatOnly,
 
+    /// The number of given entries (or mask values) does not match the number of entries in the file.
+    EntryCountMismatch {
+        /// The number of entries in the file.
+        expected: usize,
+
+        /// The number of given entries.
+        found: usize,
+    },
+
     /// The attempted operation does not work on this format (not supported in this version of this library).
     UpdatingEntriesNotSupported {
         /// The format for which updating the subtitle entries is not supported.
         format: SubtitleFormat,
     },
+
+    /// New entries can't be inserted into files of this format.
+    InsertingEntriesNotSupported,
+
+    /// The text contains characters which can't be written in the chosen character encoding.
+    EncodingError,
+
+    /// Writing the subtitle file to the disk failed.
+    WritingFileError,
+
+    /// Reading a file from the disk failed.
+    ReadingFileError,
+
+    /// The file does not implement the attempted operation (like a format of another crate which can't remove
+    /// entries).
+    UnsupportedOperation,
+
+    /// The colors (palette indices) of the subtitle images could not be changed.
+    InvalidPalette,
+
+    /// The subtitle entries (or their images) could not be changed to the given values.
+    UpdatingEntriesError,
 }
 
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ErrorKind::ParsingError => write!(f, "parsing the subtitle data failed"),
+            ErrorKind::CreationError => write!(f, "creating the subtitle file from the given data failed"),
             ErrorKind::UnknownFileFormat => write!(
                 f,
-                "unknown file format, only SubRip (.srt), SubStationAlpha (.ssa/.ass) and VobSub (.idx and .sub) are supported at the moment"
+                "unknown file format, only SubRip (.srt), SubStationAlpha (.ssa/.ass), VobSub (.idx and .sub), MicroDVD (.sub), WebVTT (.vtt), TTML (.ttml/.dfxp), YouTube (.sbv), lyrics (.lrc), Scenarist (.scc), EBU-STL (.stl), Screen PAC (.pac), MPL2 (.txt), Blu-ray PGS (.sup), JACOsub (.jss), SubViewer (.sub), RealText (.rt), iTunes Timed Text (.itt), BDN XML (.xml), Spruce STL (.stl), AQTitle (.aqt), VPlayer (.txt), PowerDivX (.psb), YouTube timed text (.srv3), SAMI (.smi) and DVB subtitles (.dvbsub) are supported at the moment"
             ),
             ErrorKind::DecodingError => write!(f, "error while decoding subtitle from bytes to string (wrong charset encoding?)"),
             ErrorKind::EncodingDetectionError => write!(f, "could not determine character encoding from byte array (manually supply character encoding?)"),
             ErrorKind::TextFormatOnly => write!(f, "operation does not work on binary subtitle formats (only text formats)"),
+            ErrorKind::EntryCountMismatch { expected, found } => {
+                write!(f, "the file has {} subtitle entries, but {} were given", expected, found)
+            }
             ErrorKind::UpdatingEntriesNotSupported { format } => write!(
                 f,
                 "updating subtitles is not implemented or supported by the `subparse` library for this format: {}",
                 format.get_name()
             ),
+            ErrorKind::InsertingEntriesNotSupported => write!(f, "inserting subtitles is not supported for this format"),
+            ErrorKind::EncodingError => write!(f, "the text can't be represented in the chosen character encoding"),
+            ErrorKind::WritingFileError => write!(f, "writing the subtitle file failed"),
+            ErrorKind::ReadingFileError => write!(f, "reading the file failed"),
+            ErrorKind::UnsupportedOperation => write!(f, "the operation is not supported for this file"),
+            ErrorKind::InvalidPalette => write!(f, "changing the colors of the subtitle images failed"),
+            ErrorKind::UpdatingEntriesError => write!(f, "updating the subtitle entries with the given values failed"),
         }
     }
 }
@@ -100,7 +172,7 @@ impl From<ErrorKind> for Error {
 
 impl From<Context<ErrorKind>> for Error {
     fn from(inner: Context<ErrorKind>) -> Error {
-        Error { inner: inner }
+        Error { inner }
     }
 }
 
@@ -139,7 +211,7 @@ error_chain! {
     errors {
         /// The file format is not supported by this library.
         UnknownFileFormat {
-            description("unknown file format, only SubRip (.srt), SubStationAlpha (.ssa/.ass) and VobSub (.idx and .sub) are supported at the moment")
+            description("unknown file format, only SubRip (.srt), SubStationAlpha (.ssa/.ass), VobSub (.idx and .sub), MicroDVD (.sub), WebVTT (.vtt), TTML (.ttml/.dfxp), YouTube (.sbv), lyrics (.lrc), Scenarist (.scc), EBU-STL (.stl), Screen PAC (.pac), MPL2 (.txt), Blu-ray PGS (.sup), JACOsub (.jss), SubViewer (.sub), RealText (.rt), iTunes Timed Text (.itt), BDN XML (.xml), Spruce STL (.stl), AQTitle (.aqt), VPlayer (.txt), PowerDivX (.psb), YouTube timed text (.srv3), SAMI (.smi) and DVB subtitles (.dvbsub) are supported at the moment")
         }
 
         /// The file format is not supported by this library.
@@ -160,7 +232,6 @@ error_chain! {
     }
 }*/
 
-#[macro_use]
 /// Creates the `Error`-context type for an ErrorKind and associated conversion methods.
 macro_rules! define_error {
     ($error:ident, $kind:ident) => {
@@ -205,7 +276,7 @@ macro_rules! define_error {
 
         impl From<Context<$kind>> for $error {
             fn from(inner: Context<$kind>) -> $error {
-                $error { inner: inner }
+                $error { inner }
             }
         }
     };
@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Escaping of plain text for the markup of each format.
+//!
+//! The texts of a `SubtitleEntry` are written in the markup of their format (`{\i1}` and `\N` in `.ssa` files, `<i>`
+//! in `.srt` files). The `escape_*` functions convert plain text (like user input) into markup which is displayed
+//! literally, and the `unescape_*` functions convert it back:
+//!
+//! ```
+//! use subparse::escaping::{escape_ass, unescape_ass};
+//!
+//! assert_eq!(escape_ass("{not a tag}\nline 2"), "\\{not a tag\\}\\Nline 2");
+//! assert_eq!(unescape_ass("\\{not a tag\\}\\Nline 2"), "{not a tag}\nline 2");
+//! ```
+//!
+//! Independently of that, every format makes sure that texts given to `update_subtitle_entries()` can't break the
+//! structure of the file: line breaks become `\N` in `.ssa` events and `|` in MicroDVD lines, and empty lines (which
+//! would end the entry) are removed from `.srt` and `.vtt` texts (where `-->` is also escaped, because it would start a
+//! new cue).
+
+/// Used to separate characters which would otherwise start a tag or an escape sequence.
+const WORD_JOINER: char = '\u{2060}';
+
+/// Escapes plain text for `.ssa`/`.ass` events.
+///
+/// Line breaks become `\N`, non-breaking spaces `\h` and braces `\{`/`\}` (so they don't start override blocks). A
+/// backslash which would start an escape sequence is followed by an invisible word joiner (U+2060).
+pub fn escape_ass(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => result.push_str("\\N"),
+            '\u{a0}' => result.push_str("\\h"),
+            '{' => result.push_str("\\{"),
+            '}' => result.push_str("\\}"),
+            '\\' => {
+                result.push('\\');
+                if chars
+                    .peek()
+                    .is_some_and(|next| matches!(next, 'N' | 'n' | 'h' | '{' | '}' | '\n' | '\r' | '\u{a0}'))
+                {
+                    result.push(WORD_JOINER);
+                }
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Reverses `escape_ass()`: `\N` and `\n` become line breaks, `\h` a non-breaking space and `\{`/`\}` braces.
+///
+/// Override blocks like `{\i1}` are not changed.
+pub fn unescape_ass(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('N') | Some('n') => result.push('\n'),
+            Some('h') => result.push('\u{a0}'),
+            Some('{') => result.push('{'),
+            Some('}') => result.push('}'),
+            Some(&WORD_JOINER) => result.push('\\'),
+            _ => {
+                result.push('\\');
+                continue;
+            }
+        }
+        chars.next();
+    }
+    result
+}
+
+/// Escapes plain text for `.srt` files.
+///
+/// Tag-like text (`<` followed by a letter or `/`, and the SubStation Alpha override tags `{\`) is separated by an
+/// invisible word joiner (U+2060), because `.srt` has no escape sequences that all players understand.
+pub fn escape_srt(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        let is_tag_start = match (c, chars.peek()) {
+            ('<', Some(&next)) => next.is_alphabetic() || next == '/',
+            ('{', Some(&next)) => next == '\\',
+            _ => false,
+        };
+        if is_tag_start {
+            result.push(WORD_JOINER);
+        }
+    }
+    result
+}
+
+/// Reverses `escape_srt()`.
+pub fn unescape_srt(text: &str) -> String {
+    text.replace(&format!("<{}", WORD_JOINER), "<")
+        .replace(&format!("{{{}", WORD_JOINER), "{")
+}
+
+/// Escapes plain text for WebVTT cues: `&`, `<` and `>` become `&amp;`, `&lt;` and `&gt;`.
+pub fn escape_webvtt(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Reverses `escape_webvtt()` and also replaces the other named character references of WebVTT (`&nbsp;`,
+/// `&lrm;` and `&rlm;`).
+///
+/// Tags like `<i>` are not changed.
+pub fn unescape_webvtt(text: &str) -> String {
+    const REFERENCES: &[(&str, &str)] = &[
+        ("&lt;", "<"),
+        ("&gt;", ">"),
+        ("&nbsp;", "\u{a0}"),
+        ("&lrm;", "\u{200e}"),
+        ("&rlm;", "\u{200f}"),
+        ("&amp;", "&"),
+    ];
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('&') {
+        let (before, reference) = rest.split_at(pos);
+        result.push_str(before);
+        match REFERENCES.iter().find(|(name, _)| reference.starts_with(name)) {
+            Some((name, replacement)) => {
+                result.push_str(replacement);
+                rest = reference.get(name.len()..).unwrap_or("");
+            }
+            None => {
+                result.push('&');
+                rest = reference.get(1..).unwrap_or("");
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the lines of a `.srt` text without empty lines (which would end the entry).
+pub(crate) fn srt_text_lines(text: &str) -> Vec<String> {
+    text.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect()
+}
+
+/// Returns the lines of a WebVTT cue text without empty lines and with `-->` escaped (both would end the cue).
+pub(crate) fn vtt_text_lines(text: &str) -> Vec<String> {
+    srt_text_lines(text).into_iter().map(|line| line.replace("-->", "--&gt;")).collect()
+}
+
+/// Returns the text of a `.ssa` event with line breaks written as `\N` (a line break would end the event).
+pub(crate) fn ass_event_text(text: &str) -> String {
+    text.replace("\r\n", "\\N").replace(['\n', '\r'], "\\N")
+}
+
+/// Returns a field of a `.ssa` event (like the style) without commas and line breaks, which would break the line.
+pub(crate) fn ass_event_field(value: &str) -> String {
+    value.replace([',', '\n', '\r'], "")
+}
+
+/// Returns the text of a MicroDVD line with line breaks written as `|` (without empty lines).
+pub(crate) fn mdvd_text(text: &str) -> String {
+    srt_text_lines(text).join("|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::{TimePoint, TimeSpan};
+    use crate::{SubtitleEntry, SubtitleFormat};
+
+    #[test]
+    fn escape_and_unescape() {
+        for text in ["{\\i1} \\N\nline\u{a0}2 \\", "plain"] {
+            assert_eq!(unescape_ass(&escape_ass(text)), text);
+        }
+        assert_eq!(escape_srt("<i> a < b {\\an8}"), "<\u{2060}i> a < b {\u{2060}\\an8}");
+        assert_eq!(unescape_srt(&escape_srt("</i> {\\an8}")), "</i> {\\an8}");
+        assert_eq!(escape_webvtt("a < b & c"), "a &lt; b &amp; c");
+        assert_eq!(unescape_webvtt("&amp;lt; &nbsp;& <b>"), "&lt; \u{a0}& <b>");
+
+        // texts with line breaks can't break the structure of the files
+        let text = "first\n\nsecond";
+        let timespan = TimeSpan::new(TimePoint::from_secs(1), TimePoint::from_secs(2));
+        let files = [
+            (SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nold\n", "first\nsecond"),
+            (
+                SubtitleFormat::SubStationAlpha,
+                "[Events]\nFormat: Layer, Start, End, Style, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,old\n",
+                "first\\N\\Nsecond",
+            ),
+        ];
+        for &(format, data, expected) in &files {
+            let mut file = crate::parse_str(format, data, 25.0).unwrap();
+            file.update_subtitle_entries(&[SubtitleEntry::new(timespan, text.to_string())]).unwrap();
+            let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+            let reparsed = crate::parse_str(format, &data, 25.0).unwrap();
+            assert_eq!(reparsed.get_subtitle_entries().unwrap()[0].line.as_deref(), Some(expected));
+        }
+    }
+}
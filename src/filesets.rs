@@ -0,0 +1,428 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Grouping the subtitle files of one video by language and variant.
+//!
+//! Releases often come with a directory of subtitle files like `movie.en.srt`, `movie.en.sdh.srt`,
+//! `movie.pt-BR.forced.ass` or just `movie.srt`. `group_directory()` reads all subtitle files of a directory and
+//! decides the language and variant of each: tags at the end of the file name win, otherwise the language is
+//! detected from the text (see `language`) and forced or SDH files are recognized by the flags of their entries.
+//! The resulting `SubtitleSet` lists the files per language and can be written as a JSON manifest for packaging
+//! tools:
+//!
+//! ```no_run
+//! use subparse::filesets::{group_directory, SetOptions};
+//!
+//! let mut set = group_directory("release/subs".as_ref(), &SetOptions::default()).unwrap();
+//! set.sort_for_locale("de-AT");
+//! std::fs::write("subtitles.json", set.to_json()).unwrap();
+//! ```
+
+use crate::conformance::collect_files;
+use crate::json::Value;
+use crate::language::{entry_language, language_matches};
+use crate::{get_subtitle_format, parse_bytes, SubtitleFormat};
+use encoding_rs::Encoding;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The ISO 639-1 codes and the ISO 639-2 (bibliographic and terminology) codes of the languages which are
+/// recognized in file names.
+const LANGUAGE_CODES: &[(&str, &str, &str)] = &[
+    ("ar", "ara", "ara"),
+    ("cs", "cze", "ces"),
+    ("da", "dan", "dan"),
+    ("de", "ger", "deu"),
+    ("el", "gre", "ell"),
+    ("en", "eng", "eng"),
+    ("es", "spa", "spa"),
+    ("fi", "fin", "fin"),
+    ("fr", "fre", "fra"),
+    ("he", "heb", "heb"),
+    ("hi", "hin", "hin"),
+    ("hu", "hun", "hun"),
+    ("it", "ita", "ita"),
+    ("ja", "jpn", "jpn"),
+    ("ko", "kor", "kor"),
+    ("nl", "dut", "nld"),
+    ("no", "nor", "nor"),
+    ("pl", "pol", "pol"),
+    ("pt", "por", "por"),
+    ("ro", "rum", "ron"),
+    ("ru", "rus", "rus"),
+    ("sv", "swe", "swe"),
+    ("th", "tha", "tha"),
+    ("tr", "tur", "tur"),
+    ("uk", "ukr", "ukr"),
+    ("zh", "chi", "zho"),
+];
+
+/// The share of entries with SDH annotations from which a file counts as SDH file.
+const SDH_SHARE: f64 = 0.1;
+
+/// The kind of a subtitle file for a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SubtitleVariant {
+    /// The normal subtitles of all dialogue.
+    Full,
+
+    /// Subtitles for the deaf and hard of hearing (with descriptions of sounds and speaker names).
+    Sdh,
+
+    /// Only the forced narrative (signs and foreign dialogue, see `forced`).
+    Forced,
+}
+
+impl SubtitleVariant {
+    /// Returns the name of the variant in manifests and file names (`"full"`, `"sdh"` or `"forced"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            SubtitleVariant::Full => "full",
+            SubtitleVariant::Sdh => "sdh",
+            SubtitleVariant::Forced => "forced",
+        }
+    }
+}
+
+/// Options for reading the files of a set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetOptions {
+    /// The frame rate of frame-based formats (see `parse_bytes()`).
+    pub fps: f64,
+
+    /// The character encoding of text files (`None` for automatic detection).
+    pub encoding: Option<&'static Encoding>,
+}
+
+impl Default for SetOptions {
+    fn default() -> SetOptions {
+        SetOptions { fps: 25.0, encoding: None }
+    }
+}
+
+/// A subtitle file of a set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetFile {
+    /// The path (or name) of the file.
+    pub path: PathBuf,
+
+    /// The detected format.
+    pub format: SubtitleFormat,
+
+    /// The language as BCP 47 tag (like `"pt-BR"`), `None` if it is neither in the file name nor detected.
+    pub language: Option<String>,
+
+    /// The kind of subtitles.
+    pub variant: SubtitleVariant,
+
+    /// The number of entries.
+    pub entries: usize,
+}
+
+/// Turns a language tag of a file name (like `"eng"` or `"pt_br"`) into a BCP 47 tag.
+fn normalize_language_tag(tag: &str) -> Option<String> {
+    let lower = tag.to_lowercase().replace('_', "-");
+    let mut subtags = lower.split('-');
+    let primary = subtags.next()?;
+    let &(code, _, _) = LANGUAGE_CODES
+        .iter()
+        .find(|&&(code, bibliographic, terminology)| primary == code || primary == bibliographic || primary == terminology)?;
+
+    let mut result = code.to_string();
+    for subtag in subtags {
+        let is_alphanumeric = subtag.chars().all(|c| c.is_ascii_alphanumeric());
+        let subtag = match subtag.len() {
+            // regions are upper case and scripts title case (like `zh-Hant-TW`)
+            2 | 3 if is_alphanumeric => subtag.to_uppercase(),
+            4 if is_alphanumeric => subtag.get(..1)?.to_uppercase() + subtag.get(1..)?,
+            _ => return None,
+        };
+        result.push('-');
+        result.push_str(&subtag);
+    }
+    Some(result)
+}
+
+/// Returns the language and the variant from tags at the end of a file name (like `movie.en.sdh.srt`).
+///
+/// Tags are only taken from the end, so words of the title (like `of` in `rise.of.the.planet.srt`) are never
+/// mistaken for them.
+fn file_name_tags(path: &Path) -> (Option<String>, Option<SubtitleVariant>) {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    let (mut language, mut variant) = (None, None);
+    for segment in stem.split('.').skip(1).collect::<Vec<&str>>().into_iter().rev() {
+        match segment.to_lowercase().as_str() {
+            "forced" if variant.is_none() => variant = Some(SubtitleVariant::Forced),
+            "sdh" | "cc" | "hoh" if variant.is_none() => variant = Some(SubtitleVariant::Sdh),
+            _ => match normalize_language_tag(segment).filter(|_| language.is_none()) {
+                Some(tag) => language = Some(tag),
+                None => break,
+            },
+        }
+    }
+    (language, variant)
+}
+
+impl SetFile {
+    /// Parses the content of a file and detects its language and variant.
+    ///
+    /// Returns `None` if the content is not a subtitle file which this library can parse.
+    pub fn detect(path: &Path, content: &[u8], options: &SetOptions) -> Option<SetFile> {
+        let format = get_subtitle_format(path.extension(), content)?;
+        let entries = parse_bytes(format, content, options.encoding, options.fps)
+            .and_then(|file| file.get_subtitle_entries())
+            .ok()?;
+        let (tagged_language, tagged_variant) = file_name_tags(path);
+
+        // the most common language of the entries (undetermined languages like `und-Latn` only count if no
+        // language is recognized, and the first one wins a tie)
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for language in entries.iter().filter_map(entry_language) {
+            match counts.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((language, 1)),
+            }
+        }
+        let detected_language = counts
+            .into_iter()
+            .rev()
+            .max_by_key(|(language, count)| (!language.starts_with("und"), *count))
+            .map(|(language, _)| language);
+
+        let with_text: Vec<_> = entries.iter().filter(|entry| entry.line.is_some()).collect();
+        let sdh = with_text.iter().filter(|entry| entry.flags.sdh).count();
+        let detected_variant = if !entries.is_empty() && entries.iter().all(|entry| entry.flags.forced) {
+            SubtitleVariant::Forced
+        } else if sdh > 0 && sdh as f64 >= with_text.len() as f64 * SDH_SHARE {
+            SubtitleVariant::Sdh
+        } else {
+            SubtitleVariant::Full
+        };
+
+        Some(SetFile {
+            path: path.to_path_buf(),
+            format,
+            language: tagged_language.or(detected_language),
+            variant: tagged_variant.unwrap_or(detected_variant),
+            entries: entries.len(),
+        })
+    }
+}
+
+/// The files of a set with the same language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageGroup {
+    /// The language of the files (`None` for the files whose language is unknown).
+    pub language: Option<String>,
+
+    /// The files, ordered by variant and path.
+    pub files: Vec<SetFile>,
+}
+
+/// The subtitle files of one video grouped by language.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SubtitleSet {
+    /// The groups, ordered by language tag (with the unknown language last) until `sort_for_locale()` is called.
+    pub groups: Vec<LanguageGroup>,
+
+    /// The files with a subtitle extension which couldn't be parsed.
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Compares language tags (without case), with the unknown language last.
+fn compare_languages(a: &Option<String>, b: &Option<String>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        _ => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+impl SubtitleSet {
+    /// Groups files by their language (tags are compared without case).
+    pub fn from_files(files: Vec<SetFile>) -> SubtitleSet {
+        let mut groups: Vec<LanguageGroup> = Vec::new();
+        for file in files {
+            let key = file.language.as_ref().map(|language| language.to_lowercase());
+            match groups
+                .iter_mut()
+                .find(|group| group.language.as_ref().map(|language| language.to_lowercase()) == key)
+            {
+                Some(group) => group.files.push(file),
+                None => groups.push(LanguageGroup {
+                    language: file.language.clone(),
+                    files: vec![file],
+                }),
+            }
+        }
+        for group in &mut groups {
+            group.files.sort_by(|a, b| (a.variant, &a.path).cmp(&(b.variant, &b.path)));
+        }
+        groups.sort_by(|a, b| compare_languages(&a.language, &b.language));
+        SubtitleSet { groups, skipped: Vec::new() }
+    }
+
+    /// Orders the groups for a user with the `locale` (a BCP 47 tag like `"pt-BR"`): the language of the locale
+    /// comes first, then its other regional variants, then all other languages by tag and the unknown language last.
+    pub fn sort_for_locale(&mut self, locale: &str) {
+        let base = locale.split(['-', '_']).next().unwrap_or(locale);
+        let rank = |language: &Option<String>| match language {
+            Some(tag) if language_matches(tag, locale) => 0,
+            Some(tag) if language_matches(tag, base) => 1,
+            Some(_) => 2,
+            None => 3,
+        };
+        self.groups.sort_by(|a, b| {
+            rank(&a.language)
+                .cmp(&rank(&b.language))
+                .then_with(|| compare_languages(&a.language, &b.language))
+        });
+    }
+
+    /// Returns the groups as JSON manifest:
+    ///
+    /// ```text
+    /// {"languages":[{"language":"en","files":[{"file":"movie.en.srt","format":".srt (SubRip)","variant":"full","entries":312},
+    ///  ...]}],"skipped":["broken.srt"]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let path = |path: &PathBuf| Value::String(path.to_string_lossy().into_owned());
+        let groups = self
+            .groups
+            .iter()
+            .map(|group| {
+                let files = group
+                    .files
+                    .iter()
+                    .map(|file| {
+                        Value::Object(vec![
+                            ("file".to_string(), path(&file.path)),
+                            ("format".to_string(), Value::String(file.format.get_name().to_string())),
+                            ("variant".to_string(), Value::String(file.variant.name().to_string())),
+                            ("entries".to_string(), Value::Number(file.entries as f64)),
+                        ])
+                    })
+                    .collect();
+                Value::Object(vec![
+                    ("language".to_string(), group.language.clone().map_or(Value::Null, Value::String)),
+                    ("files".to_string(), Value::Array(files)),
+                ])
+            })
+            .collect();
+        Value::Object(vec![
+            ("languages".to_string(), Value::Array(groups)),
+            ("skipped".to_string(), Value::Array(self.skipped.iter().map(path).collect())),
+        ])
+        .to_string()
+    }
+}
+
+/// Reads the subtitle files of a directory (not of its subdirectories) and groups them.
+///
+/// Files which don't look like subtitles (by extension) are ignored.
+pub fn group_directory(dir: &Path, options: &SetOptions) -> io::Result<SubtitleSet> {
+    let mut paths = Vec::new();
+    collect_files(dir, false, &mut paths)?;
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for path in paths {
+        let is_subtitle_extension = SubtitleFormat::ALL
+            .iter()
+            .any(|format| format.extensions().iter().any(|&ext| path.extension() == Some(OsStr::new(ext))));
+        if !is_subtitle_extension {
+            continue;
+        }
+        let content = std::fs::read(&path)?;
+        match SetFile::detect(&path, &content, options) {
+            Some(file) => files.push(file),
+            None => skipped.push(path),
+        }
+    }
+    Ok(SubtitleSet {
+        skipped,
+        ..SubtitleSet::from_files(files)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srt(text: &str) -> String {
+        format!("1\n00:00:01,000 --> 00:00:02,000\n{}\n\n2\n00:00:03,000 --> 00:00:04,000\nOk.\n", text)
+    }
+
+    fn subtitle_set() -> SubtitleSet {
+        let options = SetOptions::default();
+        let files: Vec<SetFile> = vec![
+            ("Rise.of.the.Planet.srt", srt("What is this? I don't know.")),
+            ("Rise.of.the.Planet.en.srt", srt("[DOOR SLAMS]")),
+            ("movie.pt_br.FORCED.srt", srt("Olá")),
+            ("movie.ger.srt", srt("Das ist nicht gut.")),
+            ("movie.srt", srt("…")),
+        ]
+        .into_iter()
+        .map(|(name, content)| SetFile::detect(name.as_ref(), content.as_bytes(), &options).unwrap())
+        .collect();
+        SubtitleSet::from_files(files)
+    }
+
+    #[test]
+    fn detect_invalid_file() {
+        assert_eq!(
+            SetFile::detect("x.srt".as_ref(), b"1\n00:00:01,000 -> 00:00:02,000\na\n", &SetOptions::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn language_and_variant_from_file_name() {
+        assert_eq!(
+            file_name_tags("a.b.zh_hant_tw.cc.srt".as_ref()),
+            (Some("zh-Hant-TW".to_string()), Some(SubtitleVariant::Sdh))
+        );
+        assert_eq!(file_name_tags("movie.srt".as_ref()), (None, None));
+    }
+
+    #[test]
+    fn group_subtitle_set() {
+        // files without a language tag are grouped by the detected language
+        let summary: Vec<_> = subtitle_set()
+            .groups
+            .iter()
+            .map(|group| {
+                let variants: Vec<_> = group.files.iter().map(|file| file.variant).collect();
+                (group.language.clone().unwrap_or_default(), variants)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("de".to_string(), vec![SubtitleVariant::Full]),
+                ("en".to_string(), vec![SubtitleVariant::Full, SubtitleVariant::Sdh]),
+                ("pt-BR".to_string(), vec![SubtitleVariant::Forced]),
+                ("und-Latn".to_string(), vec![SubtitleVariant::Full]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_subtitle_set_for_locale() {
+        let mut set = subtitle_set();
+        set.sort_for_locale("pt-PT");
+        assert_eq!(set.groups[0].language.as_deref(), Some("pt-BR"));
+    }
+
+    #[test]
+    fn subtitle_set_json() {
+        let mut set = subtitle_set();
+        set.sort_for_locale("pt-PT");
+        assert!(set.to_json().starts_with(
+            "{\"languages\":[{\"language\":\"pt-BR\",\"files\":[{\"file\":\"movie.pt_br.FORCED.srt\",\"format\":\".srt (SubRip)\",\
+             \"variant\":\"forced\",\"entries\":2}]}"
+        ));
+    }
+}
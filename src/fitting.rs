@@ -0,0 +1,143 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Fitting texts into the duration of their cues at a maximum reading speed.
+//!
+//! The timing of a cue is synchronized to the speech, so a text which is too long for the reading speed has to be
+//! condensed instead. `fit_worklist()` computes how many characters have to be removed from each cue,
+//! `SubtitleFile::condense_to_cps()` first applies the condensations which don't change the text's meaning, and
+//! `worklist_to_csv()` exports the remaining cues for human editors.
+
+use crate::errors::Result;
+use crate::music::tag_mask;
+use crate::timetypes::TimeSpan;
+use crate::{SubtitleEntry, SubtitleFile};
+
+/// A cue whose text is too long for its duration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FitItem {
+    /// The position of the entry in `get_subtitle_entries()`.
+    pub entry_idx: usize,
+
+    /// The (unchanged) timing of the entry.
+    pub timespan: TimeSpan,
+
+    /// The text of the entry.
+    pub text: String,
+
+    /// The number of visible characters (without tags and line breaks).
+    pub chars: usize,
+
+    /// The number of characters that can be read during the cue.
+    pub max_chars: usize,
+}
+
+impl FitItem {
+    /// The number of characters that have to be removed so the text fits.
+    pub fn excess(&self) -> usize {
+        self.chars.saturating_sub(self.max_chars)
+    }
+}
+
+/// Returns the number of characters outside of tags like `<i>` or `{\an8}`, without line breaks.
+fn visible_chars(text: &str) -> usize {
+    text.chars()
+        .zip(tag_mask(text))
+        .filter(|&(c, is_tag)| !is_tag && c != '\n' && c != '\r')
+        .count()
+}
+
+/// Returns the number of characters that can be read during `timespan` at `max_cps` characters per second.
+fn max_chars(timespan: TimeSpan, max_cps: f64) -> usize {
+    let msecs = (timespan.end - timespan.start).msecs().max(0);
+    (msecs as f64 * max_cps.max(0.0) / 1000.0).floor() as usize
+}
+
+/// Shortens the text without changing its meaning: repeated spaces are collapsed, lines are trimmed and `...`
+/// becomes `…`.
+fn condense(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" ").replace("...", "…"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns all entries with a text that can't be read in its duration at `max_cps` characters per second.
+pub fn fit_worklist(entries: &[SubtitleEntry], max_cps: f64) -> Vec<FitItem> {
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(entry_idx, entry)| {
+            let text = entry.line.as_ref()?;
+            let item = FitItem {
+                entry_idx,
+                timespan: entry.timespan,
+                text: text.clone(),
+                chars: visible_chars(text),
+                max_chars: max_chars(entry.timespan, max_cps),
+            };
+            Some(item).filter(|item| item.excess() > 0)
+        })
+        .collect()
+}
+
+/// Returns the worklist as CSV with the header `entry,start,end,chars,max_chars,excess,text`.
+///
+/// Entries are numbered from 1 and the texts are always quoted (they can contain commas and line breaks).
+pub fn worklist_to_csv(items: &[FitItem]) -> String {
+    let mut result = "entry,start,end,chars,max_chars,excess,text\n".to_string();
+    for item in items {
+        result.push_str(&format!(
+            "{},{},{},{},{},{},\"{}\"\n",
+            item.entry_idx + 1,
+            item.timespan.start,
+            item.timespan.end,
+            item.chars,
+            item.max_chars,
+            item.excess(),
+            item.text.replace('"', "\"\"")
+        ));
+    }
+    result
+}
+
+impl SubtitleFile {
+    /// Condenses all texts which are too long for `max_cps` characters per second (by collapsing repeated spaces
+    /// and replacing `...` by `…`) and returns the cues which still don't fit.
+    ///
+    /// The timings are never changed and texts which already fit are left alone.
+    pub fn condense_to_cps(&mut self, max_cps: f64) -> Result<Vec<FitItem>> {
+        let mut entries = self.get_subtitle_entries()?;
+        for item in fit_worklist(&entries, max_cps) {
+            if let Some(entry) = entries.get_mut(item.entry_idx) {
+                entry.line = Some(condense(&item.text));
+            }
+        }
+        self.update_subtitle_entries(&entries)?;
+        Ok(fit_worklist(&entries, max_cps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn condense_and_export_worklist() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>Fits.</i>\n\n\
+                   2\n00:00:03,000 --> 00:00:04,000\nWait...   what?\n\n\
+                   3\n00:00:05,000 --> 00:00:05,500\nMuch too long, \"really\"\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        assert_eq!(fit_worklist(&file.get_subtitle_entries().unwrap(), 12.0).len(), 2);
+
+        let worklist = file.condense_to_cps(12.0).unwrap();
+        let texts: Vec<String> = file.get_subtitle_entries().unwrap().into_iter().map(|e| e.line.unwrap()).collect();
+        assert_eq!(texts, vec!["<i>Fits.</i>", "Wait… what?", "Much too long, \"really\""]);
+        assert_eq!(
+            worklist_to_csv(&worklist),
+            "entry,start,end,chars,max_chars,excess,text\n3,0:00:05.000,0:00:05.500,23,6,17,\"Much too long, \"\"really\"\"\"\n"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Flags for common properties of subtitle entries.
+//!
+//! The flags are set by `get_subtitle_entries()`, so filters don't have to inspect the text themselves:
+//!
+//! ```
+//! # let mut file = subparse::parse_str(subparse::SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\n[DOOR SLAMS]\n", 25.0).unwrap();
+//! // remove the annotations for the deaf and hard of hearing
+//! file.retain_entries(|entry| !entry.flags.sdh).unwrap();
+//! # assert!(file.get_subtitle_entries().unwrap().is_empty());
+//! ```
+
+use crate::music::is_music_only;
+
+/// Properties of a subtitle entry (see `SubtitleEntry::flags`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EntryFlags {
+    /// The whole text is in italics (`<i>...</i>`, `{\i1}...` or MicroDVD `{y:i}`).
+    pub italic: bool,
+
+    /// The subtitle is forced (it has to be shown even if subtitles are disabled, like translations of signs).
+    ///
+    /// This is set for forced VobSub images and `.ssa` events whose style or name contains "forced".
+    pub forced: bool,
+
+    /// The text contains annotations for the deaf and hard of hearing (like `[DOOR SLAMS]` or `JOHN: Hi`).
+    pub sdh: bool,
+
+    /// The text contains music notes or only marks music (see `music::is_music_only()`).
+    pub music: bool,
+}
+
+impl EntryFlags {
+    /// Detects the flags which can be seen in the text (everything except `forced`).
+    pub fn from_text(text: &str) -> EntryFlags {
+        EntryFlags {
+            italic: is_styled(text, 'i'),
+            forced: false,
+            sdh: has_sdh_annotation(text),
+            music: text.contains(['♪', '♫', '♬', '♩']) || is_music_only(text),
+        }
+    }
+}
+
+/// Removes override blocks like `{\an8}` from the start of a line.
+fn skip_override_blocks(mut line: &str) -> &str {
+    while let Some(rest) = line.strip_prefix('{') {
+        match rest.split_once('}') {
+            Some((_, rest)) => line = rest.trim_start(),
+            None => break,
+        }
+    }
+    line
+}
+
+/// Returns `true` if the whole text has a style like `<b>...</b>` or `{\b1}...` (`tag` is `i`, `b` or `u`).
+pub(crate) fn is_styled(text: &str, tag: char) -> bool {
+    let trimmed = text.trim();
+    let lower = trimmed.to_lowercase();
+    let (on, off) = (format!("\\{}1", tag), format!("\\{}0", tag));
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+
+    // `{\i1}` at the start without `{\i0}` (apart from a block at the end)
+    let leading_blocks = lower.get(..lower.len() - skip_override_blocks(&lower).len()).unwrap_or("");
+    if leading_blocks.contains(on.as_str()) {
+        let body = match lower.rfind('{') {
+            Some(last_block) if lower.ends_with('}') => lower.get(..last_block).unwrap_or(""),
+            _ => &lower,
+        };
+        return !body.contains(off.as_str());
+    }
+
+    let lines: Vec<&str> = lower.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    !lines.is_empty()
+        && lines.iter().all(|line| {
+            let line = skip_override_blocks(line);
+            line.starts_with(open.as_str()) && line.ends_with(close.as_str()) && line.matches(close.as_str()).count() == 1
+        })
+}
+
+/// Returns `true` if the text contains descriptions in brackets or speaker labels like `JOHN:`.
+fn has_sdh_annotation(text: &str) -> bool {
+    let has_bracket_description = |open: char, close: char| {
+        text.split(open)
+            .skip(1)
+            .filter_map(|rest| rest.split(close).next())
+            .any(|description| description.chars().any(char::is_alphabetic))
+    };
+    let has_speaker_label = text.lines().any(|line| {
+        let line = skip_override_blocks(line.trim()).trim_start_matches(['-', ' ']);
+        match line.split_once(':') {
+            Some((label, _)) => {
+                label.chars().filter(|c| c.is_alphabetic()).count() >= 2
+                    && label.chars().all(|c| c.is_uppercase() || c == ' ' || c == '.' || c == '\'' || c == '-')
+            }
+            None => false,
+        }
+    });
+    has_bracket_description('[', ']')
+        || has_speaker_label
+        || text
+            .lines()
+            .any(|line| line.trim_start().starts_with('(') && has_bracket_description('(', ')'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn detect_entry_flags() {
+        assert!(EntryFlags::from_text("<i>Hello</i>\n<i>world</i>").italic);
+        assert!(EntryFlags::from_text("{\\an8}{\\i1}Hello{\\i0}").italic);
+        assert!(!EntryFlags::from_text("<i>Hello</i> world").italic);
+        assert!(EntryFlags::from_text("- JOHN: Hi!").sdh);
+        assert!(EntryFlags::from_text("(sighs) Fine.").sdh);
+        assert!(!EntryFlags::from_text("It's 10:30 (maybe).").sdh);
+        assert!(EntryFlags::from_text("♪ la la ♪").music);
+
+        let ssa = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Signs Forced,,0,0,0,,EXIT\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\i1}Hi";
+        let file = crate::parse_str(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        let flags: Vec<EntryFlags> = file.get_subtitle_entries().unwrap().into_iter().map(|e| e.flags).collect();
+        assert_eq!((flags[0].forced, flags[0].italic), (true, false));
+        assert_eq!((flags[1].forced, flags[1].italic), (false, true));
+
+        let mdvd = crate::parse_str(SubtitleFormat::MicroDVD, "{0}{25}{Y:i}Hello|world", 25.0).unwrap();
+        assert!(mdvd.get_subtitle_entries().unwrap().iter().all(|e| e.flags.italic));
+    }
+}
@@ -0,0 +1,203 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Extraction of the forced narrative, the subtitles that are shown even if subtitles are disabled.
+//!
+//! Localization packages usually contain a full subtitle file and a "forced" file which only translates
+//! signs and foreign dialogue. `SubtitleFile::forced_only()` creates the second from the first: it keeps the
+//! entries with the `forced` flag, entries with a note like `[in French]` and, if the language of the main
+//! dialogue is known, entries which are detected as another language.
+//!
+//! ```
+//! use subparse::forced::ForcedOptions;
+//! # let file = subparse::parse_str(subparse::SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nHello.\n\n2\n00:00:03,000 --> 00:00:04,000\n[in Spanish] Where is he?\n", 25.0).unwrap();
+//! let forced = file.forced_only(&ForcedOptions::default()).unwrap();
+//! # assert_eq!(forced.get_subtitle_entries().unwrap().len(), 1);
+//! ```
+
+use crate::errors::Result;
+use crate::language::{entry_language, language_matches};
+use crate::{SubtitleEntry, SubtitleFile};
+
+/// Language names (and words for an unknown language) which are recognized in notes like `[in French]`.
+const LANGUAGE_NAMES: &[&str] = &[
+    "arabic",
+    "cantonese",
+    "chinese",
+    "czech",
+    "danish",
+    "dutch",
+    "english",
+    "farsi",
+    "finnish",
+    "foreign",
+    "french",
+    "german",
+    "greek",
+    "hebrew",
+    "hindi",
+    "hungarian",
+    "indonesian",
+    "italian",
+    "japanese",
+    "korean",
+    "latin",
+    "mandarin",
+    "norwegian",
+    "persian",
+    "polish",
+    "portuguese",
+    "romanian",
+    "russian",
+    "spanish",
+    "swahili",
+    "swedish",
+    "tagalog",
+    "thai",
+    "turkish",
+    "ukrainian",
+    "urdu",
+    "vietnamese",
+];
+
+/// How `SubtitleFile::forced_only()` decides which entries belong to the forced narrative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForcedOptions {
+    /// Keep entries with a note like `[in French]`, `(speaking Spanish)` or `[FOREIGN]`.
+    pub language_tags: bool,
+
+    /// The language of the main dialogue (a BCP 47 tag like `"en"`): entries in other languages are kept.
+    ///
+    /// The language of an entry is its stored language or the one detected by `language::detect_language()`,
+    /// entries of an undetermined language (like `"und-Latn"`) are not kept.
+    pub main_language: Option<String>,
+}
+
+impl Default for ForcedOptions {
+    fn default() -> ForcedOptions {
+        ForcedOptions {
+            language_tags: true,
+            main_language: None,
+        }
+    }
+}
+
+/// Returns `true` if the text has a note about the spoken language like `[in French]` or `(speaking Spanish)`.
+pub fn has_language_tag(text: &str) -> bool {
+    let is_language_note = |note: &str| {
+        let note = note.trim().to_lowercase();
+        let note = ["speaking in ", "speaking ", "speaks ", "in "]
+            .iter()
+            .find_map(|prefix| note.strip_prefix(prefix))
+            .unwrap_or(&note);
+        let mut words = note.split_whitespace().filter(|&word| word != "and" && word != "language");
+        words.next().is_some_and(|word| LANGUAGE_NAMES.contains(&word)) && words.all(|word| LANGUAGE_NAMES.contains(&word))
+    };
+    let has_note = |open: char, close: char| text.split(open).skip(1).filter_map(|rest| rest.split(close).next()).any(is_language_note);
+    has_note('[', ']') || has_note('(', ')')
+}
+
+/// Returns `true` if the entry belongs to the forced narrative (see the module documentation).
+pub fn is_forced_narrative(entry: &SubtitleEntry, options: &ForcedOptions) -> bool {
+    if entry.flags.forced {
+        return true;
+    }
+    if options.language_tags && entry.line.as_deref().is_some_and(has_language_tag) {
+        return true;
+    }
+    match options.main_language {
+        Some(ref main_language) => {
+            entry_language(entry).is_some_and(|tag| !tag.to_lowercase().starts_with("und") && !language_matches(&tag, main_language))
+        }
+        None => false,
+    }
+}
+
+impl SubtitleFile {
+    /// Returns a copy of the file which only contains the forced narrative (see the module documentation).
+    ///
+    /// Formats which can't remove entries (like VobSub `.sub` files) return an error.
+    pub fn forced_only(&self, options: &ForcedOptions) -> Result<SubtitleFile> {
+        let mut file = self.clone();
+        file.retain_entries(|entry| is_forced_narrative(entry, options))?;
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    const SCRIPT: &str =
+        "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\nStyle: Forced,Arial\n\n[Events]\n\
+         Format: Layer, Start, End, Style, Name, Text\n\
+         Dialogue: 0,0:00:01.00,0:00:02.00,Default,,I don't know what this is.\n\
+         Dialogue: 0,0:00:03.00,0:00:04.00,Forced,,SIGN: Exit\n\
+         Dialogue: 0,0:00:05.00,0:00:06.00,Default,,(in German) Wo ist er?\n\
+         Dialogue: 0,0:00:07.00,0:00:08.00,Default,,Das ist nicht gut.\n";
+
+    fn texts(file: &SubtitleFile) -> Vec<String> {
+        file.get_subtitle_entries().unwrap().into_iter().filter_map(|e| e.line).collect()
+    }
+
+    fn forced_texts(options: &ForcedOptions) -> Vec<String> {
+        let file = crate::parse_str(SubtitleFormat::SubStationAlpha, SCRIPT, 25.0).unwrap();
+        texts(&file.forced_only(options).unwrap())
+    }
+
+    #[test]
+    fn language_tags() {
+        assert!(has_language_tag("[in French] Bonjour."));
+        assert!(has_language_tag("(SPEAKING SPANISH AND PORTUGUESE)"));
+        assert!(has_language_tag("[Foreign language]"));
+    }
+
+    #[test]
+    fn notes_without_language() {
+        assert!(!has_language_tag("[in silence]"));
+        assert!(!has_language_tag("(French horn playing)"));
+        assert!(!has_language_tag("in French"));
+        assert!(!has_language_tag("[]"));
+    }
+
+    #[test]
+    fn forced_flags_and_language_tags() {
+        assert_eq!(forced_texts(&ForcedOptions::default()), vec!["SIGN: Exit", "(in German) Wo ist er?"]);
+
+        let options = ForcedOptions {
+            language_tags: false,
+            ..ForcedOptions::default()
+        };
+        assert_eq!(forced_texts(&options), vec!["SIGN: Exit"]);
+    }
+
+    #[test]
+    fn forced_other_languages() {
+        let options = ForcedOptions {
+            main_language: Some("en".to_string()),
+            ..ForcedOptions::default()
+        };
+        assert_eq!(forced_texts(&options), vec!["SIGN: Exit", "(in German) Wo ist er?", "Das ist nicht gut."]);
+    }
+
+    #[test]
+    fn forced_only_keeps_the_original() {
+        let file = crate::parse_str(SubtitleFormat::SubStationAlpha, SCRIPT, 25.0).unwrap();
+        file.forced_only(&ForcedOptions::default()).unwrap();
+        assert_eq!(texts(&file).len(), 4);
+    }
+
+    #[test]
+    fn forced_only_unsupported_format() {
+        let file = SubtitleFile::VobSubSubFile(crate::VobFile::parse(&[]).unwrap());
+        let error = file.forced_only(&ForcedOptions::default()).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            crate::ErrorKind::UpdatingEntriesNotSupported {
+                format: SubtitleFormat::VobSubSub
+            }
+        );
+    }
+}
@@ -0,0 +1,299 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::Warnings;
+
+use failure::ResultExt;
+
+use crate::durations::DurationSynthesis;
+use crate::timetypes::{RoundingMode, TimeDelta, TimePoint, TimeSpan};
+
+/// Errors specific to `.aqt`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected AQTitle frame line like `-->> 000025`, found '{}'", line)]
+        ExpectedFrameLine { line: String },
+
+        #[fail(display = "the frame number `{}` is invalid", value)]
+        InvalidFrameNumber { value: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// Holds one subtitle of an `.aqt` file.
+#[derive(Debug, Clone)]
+struct AqtEntry {
+    /// The frame of the `-->>` line before the text.
+    start_frame: i64,
+
+    /// The frame of the following `-->>` line without text; `None` if the next subtitle follows directly.
+    end_frame: Option<i64>,
+
+    /// The text lines.
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+/// Represents an AQTitle (`.aqt`) file.
+///
+/// The file consists of blocks which start with a frame line like `-->> 000025`. A block with text lines starts a
+/// subtitle and the following block without text ends it; without one, the subtitle is shown until the next one
+/// starts. Like in a `MdvdFile`, the frames are converted to times with the frame rate given to `parse()`.
+pub struct AqTitleFile {
+    /// Number of frames per second of the associated video.
+    fps: f64,
+
+    /// How times are converted to and from frames
+    rounding: RoundingMode,
+
+    /// How the end times of subtitles without end frame are created
+    open_ends: DurationSynthesis,
+
+    v: Vec<AqtEntry>,
+}
+
+impl AqTitleFile {
+    /// Parse an AQTitle `.aqt` subtitle string to `AqTitleFile`.
+    pub fn parse(s: &str, fps: f64) -> SubtitleParserResult<AqTitleFile> {
+        Ok(Self::parse_with_warnings(s, fps)?.0)
+    }
+
+    /// Parse an AQTitle `.aqt` subtitle string to `AqTitleFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str, fps: f64) -> SubtitleParserResult<(AqTitleFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, fps).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates an `.aqt` file from scratch.
+    pub fn create(v: Vec<(TimeSpan, String)>, fps: f64) -> SubtitleParserResult<AqTitleFile> {
+        let mut file = Self::new(fps, Vec::new());
+        file.v = v
+            .into_iter()
+            .map(|(timespan, text)| AqtEntry {
+                start_frame: timespan.start.to_frame(fps, file.rounding),
+                end_frame: Some(timespan.end.to_frame(fps, file.rounding)),
+                lines: text_lines(&text),
+            })
+            .collect();
+        Ok(file)
+    }
+
+    /// Sets how times are converted to and from frames (default: `RoundingMode::Nearest`).
+    pub fn set_rounding_mode(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    /// Sets how the end times of subtitles without end frame are created.
+    ///
+    /// By default these subtitles are shown until the next subtitle starts, and the last one for three seconds.
+    pub fn set_duration_synthesis(&mut self, open_ends: DurationSynthesis) {
+        self.open_ends = open_ends;
+    }
+
+    fn new(fps: f64, v: Vec<AqtEntry>) -> AqTitleFile {
+        AqTitleFile {
+            fps,
+            rounding: RoundingMode::default(),
+            open_ends: DurationSynthesis::NextCue {
+                last: TimeDelta::from_secs(3),
+            },
+            v,
+        }
+    }
+
+    fn parse_file(i: &str, fps: f64) -> Result<AqTitleFile> {
+        let (_, s) = split_bom(i);
+
+        // the frame and the text lines of every block
+        let mut blocks: Vec<(i64, Vec<String>)> = Vec::new();
+        for (line_num, line) in s.lines().enumerate() {
+            match (parse_frame_line(line).with_context(|_| ErrorAtLine { line_num })?, blocks.last_mut()) {
+                (Some(frame), _) => blocks.push((frame, Vec::new())),
+                (None, Some((_, lines))) => lines.push(line.to_string()),
+                (None, None) if line.trim().is_empty() => {}
+                (None, None) => {
+                    return Err(Error::from(ExpectedFrameLine { line: line.to_string() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+            }
+        }
+
+        let mut result: Vec<AqtEntry> = Vec::new();
+        for (frame, lines) in blocks {
+            let lines = text_lines(&lines.join("\n"));
+            if !lines.is_empty() {
+                result.push(AqtEntry {
+                    start_frame: frame,
+                    end_frame: None,
+                    lines,
+                });
+            } else if let Some(entry) = result.last_mut().filter(|entry| entry.end_frame.is_none()) {
+                entry.end_frame = Some(frame);
+            }
+        }
+
+        Ok(Self::new(fps, result))
+    }
+
+    /// Returns the end frame of every subtitle (see `set_duration_synthesis()` for subtitles without end frame).
+    fn end_frames(&self) -> Vec<i64> {
+        let cues: Vec<(TimePoint, Option<String>)> = self
+            .v
+            .iter()
+            .map(|entry| {
+                (
+                    TimePoint::from_frame(entry.start_frame, self.fps, self.rounding),
+                    Some(entry.lines.join("\n")),
+                )
+            })
+            .collect();
+        let cues: Vec<(TimePoint, Option<&str>)> = cues.iter().map(|(start, text)| (*start, text.as_deref())).collect();
+        self.v
+            .iter()
+            .zip(self.open_ends.timespans(&cues))
+            .map(|(entry, timespan)| entry.end_frame.unwrap_or_else(|| timespan.end.to_frame(self.fps, self.rounding)))
+            .collect()
+    }
+}
+
+/// Returns `true` if the text looks like an `.aqt` file (and not like a `.srt` file).
+pub(crate) fn looks_like_aqtitle(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    let first_line = text.lines().find(|line| !line.trim().is_empty());
+    first_line.is_some_and(|line| parse_frame_line(line).ok().flatten().is_some())
+}
+
+/// Parses a frame line like `-->> 000025` (`None` for text lines).
+fn parse_frame_line(line: &str) -> Result<Option<i64>> {
+    let frame = match line.trim().strip_prefix("-->>") {
+        Some(frame) => frame.trim(),
+        None => return Ok(None),
+    };
+    match frame.parse::<i64>() {
+        Ok(number) if frame.bytes().all(|b| b.is_ascii_digit()) => Ok(Some(number)),
+        _ => Err(InvalidFrameNumber { value: frame.to_string() }.into()),
+    }
+}
+
+/// Splits an entry text into its non-empty lines.
+fn text_lines(text: &str) -> Vec<String> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect()
+}
+
+impl SubtitleFileInterface for AqTitleFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .zip(self.end_frames())
+            .map(|(entry, end_frame)| {
+                let timespan = TimeSpan::new(
+                    TimePoint::from_frame(entry.start_frame, self.fps, self.rounding),
+                    TimePoint::from_frame(end_frame, self.fps, self.rounding),
+                );
+                SubtitleEntry::new(timespan, entry.lines.join("\n"))
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        let old_end_frames = self.end_frames();
+        for ((entry, new_entry), old_end_frame) in self.v.iter_mut().zip(new_subtitle_entries).zip(old_end_frames) {
+            entry.start_frame = new_entry.timespan.start.to_frame(self.fps, self.rounding);
+
+            // subtitles stay open-ended as long as their end is not changed
+            let end_frame = new_entry.timespan.end.to_frame(self.fps, self.rounding);
+            if entry.end_frame.is_some() || end_frame != old_end_frame {
+                entry.end_frame = Some(end_frame);
+            }
+
+            if let Some(ref text) = new_entry.line {
+                entry.lines = text_lines(text);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = String::new();
+        for entry in &self.v {
+            result.push_str(&format!("-->> {:06}\n", entry.start_frame));
+            for line in &entry.lines {
+                result.push_str(line);
+                result.push('\n');
+            }
+            if let Some(end_frame) = entry.end_frame {
+                result.push_str(&format!("-->> {:06}\n\n", end_frame));
+            }
+        }
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        // removed subtitles must not extend open-ended subtitles before them
+        let end_frames = self.end_frames();
+        for (idx, &keep) in keep.iter().enumerate() {
+            if let (Some(previous), false) = (idx.checked_sub(1).and_then(|previous| self.v.get_mut(previous)), keep) {
+                if previous.end_frame.is_none() {
+                    previous.end_frame = end_frames.get(idx - 1).cloned();
+                }
+            }
+        }
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aqtitle_frames() {
+        let input = "-->> 000025\r\nHello\r\nworld\r\n-->> 000100\r\n\r\n-->> 000150\r\nOpen\r\n\r\n-->> 000200\r\nLast\r\n-->> 000250\r\n\r\n";
+        let mut file = AqTitleFile::parse(input, 25.0).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1000, 4000), (6000, 8000), (8000, 10000)]);
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\nworld"));
+
+        entries[0].timespan += TimeDelta::from_msecs(400);
+        entries[2].line = Some("Changed".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "-->> 000035\nHello\nworld\n-->> 000110\n\n-->> 000150\nOpen\n-->> 000200\nChanged\n-->> 000250\n\n"
+        );
+
+        // removing a subtitle keeps the end of the open-ended subtitle before it
+        file.retain_entries_by_mask(&[true, true, false]).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries[1].timespan.end.msecs(), 8000);
+        assert!(looks_like_aqtitle("\u{feff}-->> 1\nHi\n"));
+        assert!(!looks_like_aqtitle("1\n00:00:01,000 --> 00:00:02,000\nHi\n"));
+        assert!(AqTitleFile::parse("Hi\n-->> 1\n", 25.0).is_err());
+        assert!(AqTitleFile::parse("-->> x1\n", 25.0).is_err());
+    }
+}
@@ -0,0 +1,612 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::warnings::Warnings;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+
+use crate::timetypes::{RoundingMode, TimePoint, TimeSpan};
+use std::path::{Path, PathBuf};
+
+/// BDN XML-parser-specific errors
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the file has no `<BDN>` element")]
+        ExpectedBdnElement,
+
+        #[fail(display = "the `<Format>` element has no `FrameRate` attribute")]
+        MissingFrameRate,
+
+        #[fail(display = "the frame rate `{}` is not supported", value)]
+        InvalidFrameRate { value: String },
+
+        #[fail(display = "drop-frame timecodes are not supported")]
+        DropFrameNotSupported,
+
+        #[fail(display = "the tag is not closed with `>`")]
+        UnclosedTag,
+
+        #[fail(display = "the `<Event>` element is not closed with `</Event>`")]
+        UnclosedEvent,
+
+        #[fail(display = "the `<Event>` element has no `{}` attribute", attribute)]
+        MissingTimecode { attribute: &'static str },
+
+        #[fail(display = "the timecode `{}` is invalid", value)]
+        InvalidTimecode { value: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// The frame rates of BDN files as `FrameRate` value, the frames per second of the timecodes and the actual frame
+/// rate.
+const FRAME_RATES: &[(&str, i64, f64)] = &[
+    ("23.976", 24, 24000.0 / 1001.0),
+    ("24", 24, 24.0),
+    ("25", 25, 25.0),
+    ("29.97", 30, 30000.0 / 1001.0),
+    ("30", 30, 30.0),
+    ("50", 50, 50.0),
+    ("59.94", 60, 60000.0 / 1001.0),
+    ("60", 60, 60.0),
+];
+
+/// An image of a BDN event (the `<Graphic>` element).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BdnGraphic {
+    /// The path of the PNG file as it is written in the file (relative to the directory of the `.xml` file).
+    pub path: String,
+
+    /// The horizontal position of the image on the screen.
+    pub x: u32,
+
+    /// The vertical position of the image on the screen.
+    pub y: u32,
+
+    /// The width of the image.
+    pub width: u32,
+
+    /// The height of the image.
+    pub height: u32,
+}
+
+impl BdnGraphic {
+    /// Returns the path of the PNG file for a `.xml` file in the directory `base_dir`.
+    pub fn resolve(&self, base_dir: &Path) -> PathBuf {
+        base_dir.join(&self.path)
+    }
+}
+
+/// A subtitle of a BDN file with its images (the entries of `get_subtitle_entries()` have no text).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BdnEvent {
+    /// The `InTC` and `OutTC` of the event.
+    pub timespan: TimeSpan,
+
+    /// The `Forced` attribute of the event.
+    pub forced: bool,
+
+    /// The images of the event (usually one; two if there are two windows).
+    pub graphics: Vec<BdnGraphic>,
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// BDN file parts
+
+/// A part of an `<Event>` tag.
+#[derive(Debug, Clone)]
+enum TagPiece {
+    /// Everything apart from the timecode values.
+    Text(String),
+
+    /// The value of the `InTC` attribute.
+    InTc,
+
+    /// The value of the `OutTC` attribute.
+    OutTc,
+}
+
+/// An `<Event>` element.
+#[derive(Debug, Clone)]
+struct BdnEventPart {
+    /// The line break and the indentation before the tag.
+    indent: String,
+
+    /// The tag split at the values of the timecodes.
+    tag: Vec<TagPiece>,
+
+    /// The `InTC` value as it is written.
+    in_tc: String,
+
+    /// The `OutTC` value as it is written.
+    out_tc: String,
+
+    /// Everything after the tag up to the indentation of the next event (or `</Event>` for the last event).
+    rest: String,
+
+    event: BdnEvent,
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// BDN file
+
+/// Represents a reconstructable BDN XML file, the input of Blu-ray authoring tools for graphic subtitles.
+///
+/// Every `<Event InTC=".." OutTC="..">` references the PNG files of the subtitle in `<Graphic>` elements (see
+/// `events()`). The timecodes count frames of the `FrameRate` of the `<Format>` element, drop-frame timecodes are
+/// not supported. The document is kept as it is; changed times are written as timecodes again and the
+/// `FirstEventInTC`, `LastEventOutTC` and `NumberofEvents` summary of the `<Events>` description is updated after
+/// changes.
+#[derive(Debug, Clone)]
+pub struct BdnFile {
+    /// Everything before the first event.
+    header: String,
+
+    v: Vec<BdnEventPart>,
+
+    /// Everything after the last event.
+    trailer: String,
+
+    /// The frames per second of the timecodes.
+    timecode_rate: i64,
+
+    /// The actual frame rate.
+    fps: f64,
+
+    /// The times or events were changed, so the summary has to be rewritten.
+    modified: bool,
+}
+
+impl BdnFile {
+    /// Parse a BDN XML subtitle string to `BdnFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<BdnFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a BDN XML subtitle string to `BdnFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(BdnFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Returns the events with their images.
+    pub fn events(&self) -> Vec<BdnEvent> {
+        self.v.iter().map(|part| part.event.clone()).collect()
+    }
+
+    /// Returns the frame rate of the timecodes (like 23.976).
+    pub fn frame_rate(&self) -> f64 {
+        self.fps
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        let events = self.v.iter().map(|_| None);
+        Some(Some(self.header.as_str()))
+            .into_iter()
+            .chain(events)
+            .chain(Some(Some(self.trailer.as_str())))
+            .collect()
+    }
+
+    fn parse_file(s: &str) -> Result<BdnFile> {
+        find_element(s, 0, "BDN").ok_or(ExpectedBdnElement)?;
+        let line_num = |position: usize| s.get(..position).unwrap_or("").matches('\n').count() + 1;
+
+        let format_position = find_element(s, 0, "Format").ok_or(MissingFrameRate)?;
+        let format = tag_at(s, format_position).with_context(|_| ErrorAtLine {
+            line_num: line_num(format_position),
+        })?;
+        if attribute(format, "DropFrame").is_some_and(|value| value.trim().eq_ignore_ascii_case("true")) {
+            return Err(DropFrameNotSupported.into());
+        }
+        let frame_rate = attribute(format, "FrameRate").ok_or(MissingFrameRate)?.trim();
+        let &(_, timecode_rate, fps) = FRAME_RATES
+            .iter()
+            .find(|(value, ..)| *value == frame_rate)
+            .ok_or_else(|| InvalidFrameRate {
+                value: frame_rate.to_string(),
+            })?;
+
+        let mut positions = Vec::new();
+        let mut position = 0;
+        while let Some(found) = find_element(s, position, "Event") {
+            positions.push(found);
+            position = found + 1;
+        }
+
+        let mut result: Vec<BdnEventPart> = Vec::new();
+        let mut header_end = s.len();
+        let mut previous_end = 0;
+        for (i, &tag_start) in positions.iter().enumerate() {
+            let mut part = Self::parse_event(s, tag_start, positions.get(i + 1).cloned(), timecode_rate, fps).with_context(|_| ErrorAtLine {
+                line_num: line_num(tag_start),
+            })?;
+
+            // the line break before the tag belongs to the event, so removed events leave no empty lines
+            let gap = s.get(previous_end..tag_start).unwrap_or("");
+            let indent = match gap.rfind('\n') {
+                Some(newline) if gap.get(newline..).is_some_and(|indent| indent.trim().is_empty()) => newline,
+                _ => gap.len(),
+            };
+            let indent_start = previous_end + indent;
+            match result.last_mut() {
+                Some(previous) => previous.rest.push_str(s.get(previous_end..indent_start).unwrap_or("")),
+                None => header_end = indent_start,
+            }
+            part.indent = s.get(indent_start..tag_start).unwrap_or("").to_string();
+            previous_end = tag_start + part.tag_len() + part.rest.len();
+            result.push(part);
+        }
+
+        Ok(BdnFile {
+            header: s.get(..header_end).unwrap_or("").to_string(),
+            v: result,
+            trailer: s.get(header_end.max(previous_end)..).unwrap_or("").to_string(),
+            timecode_rate,
+            fps,
+            modified: false,
+        })
+    }
+
+    /// Parses the `<Event>` element at `tag_start` (the `rest` ends with `</Event>`; the indentation is empty).
+    fn parse_event(s: &str, tag_start: usize, next: Option<usize>, timecode_rate: i64, fps: f64) -> Result<BdnEventPart> {
+        let tag = tag_at(s, tag_start)?;
+        let rest_start = tag_start + tag.len();
+        let rest = if tag.ends_with("/>") {
+            ""
+        } else {
+            let content = s.get(rest_start..next.unwrap_or(s.len())).unwrap_or("");
+            let close = content.find("</Event>").ok_or(UnclosedEvent)?;
+            content.get(..close + "</Event>".len()).unwrap_or(content)
+        };
+
+        let in_range = attribute_range(tag, "InTC").ok_or(MissingTimecode { attribute: "InTC" })?;
+        let out_range = attribute_range(tag, "OutTC").ok_or(MissingTimecode { attribute: "OutTC" })?;
+        let in_tc = tag.get(in_range.clone()).unwrap_or("").to_string();
+        let out_tc = tag.get(out_range.clone()).unwrap_or("").to_string();
+        let timespan = TimeSpan::new(parse_timecode(&in_tc, timecode_rate, fps)?, parse_timecode(&out_tc, timecode_rate, fps)?);
+
+        let mut ranges = vec![(in_range, TagPiece::InTc), (out_range, TagPiece::OutTc)];
+        ranges.sort_by_key(|(range, _)| range.start);
+        let mut pieces = Vec::new();
+        let mut position = 0;
+        for (range, piece) in ranges {
+            pieces.push(TagPiece::Text(tag.get(position..range.start).unwrap_or("").to_string()));
+            pieces.push(piece);
+            position = range.end;
+        }
+        pieces.push(TagPiece::Text(tag.get(position..).unwrap_or("").to_string()));
+
+        Ok(BdnEventPart {
+            indent: String::new(),
+            tag: pieces,
+            in_tc,
+            out_tc,
+            rest: rest.to_string(),
+            event: BdnEvent {
+                timespan,
+                forced: attribute(tag, "Forced").is_some_and(|value| value.trim().eq_ignore_ascii_case("true")),
+                graphics: parse_graphics(rest)?,
+            },
+        })
+    }
+
+    /// Returns the header with the updated summary of the `<Events>` description.
+    fn header_with_summary(&self) -> String {
+        let mut header = self.header.clone();
+        let summary_start = match find_element(&header, 0, "Events") {
+            Some(start) => start,
+            None => return header,
+        };
+        let summary = match tag_at(&header, summary_start) {
+            Ok(tag) => tag.to_string(),
+            Err(_) => return header,
+        };
+        let first = self.v.first().map(|part| part.in_tc.clone());
+        let last = self.v.last().map(|part| part.out_tc.clone());
+        let values = [
+            ("FirstEventInTC", first.unwrap_or_else(|| self.format_timecode(TimePoint::from_msecs(0)))),
+            ("LastEventOutTC", last.unwrap_or_else(|| self.format_timecode(TimePoint::from_msecs(0)))),
+            ("NumberofEvents", self.v.len().to_string()),
+        ];
+        let mut ranges: Vec<_> = values
+            .iter()
+            .filter_map(|(name, value)| Some((attribute_range(&summary, name)?, value)))
+            .collect();
+        // replace from the end, so the earlier ranges stay valid
+        ranges.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, value) in ranges {
+            header.replace_range(summary_start + range.start..summary_start + range.end, value);
+        }
+        header
+    }
+
+    fn format_timecode(&self, t: TimePoint) -> String {
+        format_timecode(t, self.timecode_rate, self.fps)
+    }
+}
+
+impl BdnEventPart {
+    /// The length of the tag in the file.
+    fn tag_len(&self) -> usize {
+        self.tag
+            .iter()
+            .map(|piece| match piece {
+                TagPiece::Text(text) => text.len(),
+                TagPiece::InTc => self.in_tc.len(),
+                TagPiece::OutTc => self.out_tc.len(),
+            })
+            .sum()
+    }
+}
+
+/// Returns `true` if the text looks like a BDN file (and not like a TTML file).
+pub(crate) fn looks_like_bdn(text: &str) -> bool {
+    find_element(text, 0, "BDN").is_some()
+}
+
+/// Returns the position of the next tag `<name ...>` (and not of `<names>`).
+fn find_element(s: &str, from: usize, name: &str) -> Option<usize> {
+    let opening = format!("<{}", name);
+    let mut position = from;
+    loop {
+        let found = position + s.get(position..)?.find(&opening)?;
+        let next = s.get(found + opening.len()..)?.chars().next();
+        if next.is_some_and(|c| c.is_whitespace() || c == '>' || c == '/') {
+            return Some(found);
+        }
+        position = found + opening.len();
+    }
+}
+
+/// Returns the tag which starts at `position`.
+fn tag_at(s: &str, position: usize) -> Result<&str> {
+    let rest = s.get(position..).unwrap_or("");
+    let len = rest.find('>').ok_or(UnclosedTag)?;
+    Ok(rest.get(..len + 1).unwrap_or(rest))
+}
+
+/// Parses the `<Graphic>` elements of an event.
+fn parse_graphics(content: &str) -> Result<Vec<BdnGraphic>> {
+    let mut result = Vec::new();
+    let mut position = 0;
+    while let Some(start) = find_element(content, position, "Graphic") {
+        let tag = tag_at(content, start)?;
+        let path_start = start + tag.len();
+        let path_len = content.get(path_start..).and_then(|rest| rest.find("</Graphic>")).ok_or(UnclosedTag)?;
+        let number = |name: &str| attribute(tag, name).and_then(|value| value.trim().parse().ok()).unwrap_or(0);
+        result.push(BdnGraphic {
+            path: decode_entities(content.get(path_start..path_start + path_len).unwrap_or("").trim()),
+            x: number("X"),
+            y: number("Y"),
+            width: number("Width"),
+            height: number("Height"),
+        });
+        position = path_start + path_len;
+    }
+    Ok(result)
+}
+
+/// Parses a timecode like `00:00:01:12`.
+fn parse_timecode(value: &str, timecode_rate: i64, fps: f64) -> Result<TimePoint> {
+    let invalid = || InvalidTimecode { value: value.to_string() };
+    let components = value
+        .trim()
+        .split(':')
+        .map(|part| part.parse::<i64>().ok().filter(|_| part.bytes().all(|b| b.is_ascii_digit())))
+        .collect::<Option<Vec<i64>>>()
+        .ok_or_else(invalid)?;
+    let (hours, mins, secs, frames) = match components.as_slice() {
+        &[hours, mins, secs, frames] if mins < 60 && secs < 60 && frames < timecode_rate => (hours, mins, secs, frames),
+        _ => return Err(invalid().into()),
+    };
+    let frame = hours
+        .checked_mul(3600)
+        .and_then(|secs_of_hours| secs_of_hours.checked_add(mins * 60 + secs))
+        .and_then(|all_secs| all_secs.checked_mul(timecode_rate))
+        .and_then(|all_frames| all_frames.checked_add(frames))
+        .ok_or_else(invalid)?;
+    Ok(TimePoint::from_frame(frame, fps, RoundingMode::Nearest))
+}
+
+/// Formats a timecode like `00:00:01:12` (negative times are written as zero).
+fn format_timecode(t: TimePoint, timecode_rate: i64, fps: f64) -> String {
+    let frame = t.to_frame(fps, RoundingMode::Nearest).max(0);
+    let secs = frame / timecode_rate;
+    format!("{:02}:{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60, frame % timecode_rate)
+}
+
+impl SubtitleFileInterface for BdnFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .map(|part| {
+                let mut entry = SubtitleEntry::from(part.event.timespan);
+                entry.flags.forced = part.event.forced;
+                entry
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        let (timecode_rate, fps) = (self.timecode_rate, self.fps);
+        for (part, new_entry) in self.v.iter_mut().zip(new_subtitle_entries) {
+            let timespan = new_entry.timespan;
+            if part.event.timespan.start != timespan.start {
+                part.in_tc = format_timecode(timespan.start, timecode_rate, fps);
+                self.modified = true;
+            }
+            if part.event.timespan.end != timespan.end {
+                part.out_tc = format_timecode(timespan.end, timecode_rate, fps);
+                self.modified = true;
+            }
+            part.event.timespan = timespan;
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = if self.modified {
+            self.header_with_summary()
+        } else {
+            self.header.clone()
+        };
+        for part in &self.v {
+            result.push_str(&part.indent);
+            for piece in &part.tag {
+                result.push_str(match piece {
+                    TagPiece::Text(text) => text,
+                    TagPiece::InTc => &part.in_tc,
+                    TagPiece::OutTc => &part.out_tc,
+                });
+            }
+            result.push_str(&part.rest);
+        }
+        result.push_str(&self.trailer);
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        let len = self.v.len();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        self.modified |= self.v.len() != len;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+    use crate::timetypes::TimeDelta;
+
+    const FILE: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <BDN Version=\"0.93\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n\
+                     \x20 <Description>\n\
+                     \x20   <Name Title=\"Movie\" Content=\"\"/>\n\
+                     \x20   <Format VideoFormat=\"1080p\" FrameRate=\"23.976\" DropFrame=\"False\"/>\n\
+                     \x20   <Events LastEventOutTC=\"00:00:06:23\" FirstEventInTC=\"00:00:01:12\" ContentInTC=\"00:00:00:00\" \
+                     ContentOutTC=\"00:00:06:23\" NumberofEvents=\"2\" Type=\"Graphic\"/>\n\
+                     \x20 </Description>\n\
+                     \x20 <Events>\n\
+                     \x20   <Event InTC=\"00:00:01:12\" OutTC=\"00:00:03:00\" Forced=\"False\">\n\
+                     \x20     <Graphic Width=\"600\" Height=\"80\" X=\"660\" Y=\"960\">00000001.png</Graphic>\n\
+                     \x20   </Event>\n\
+                     \x20   <Event Forced=\"True\" InTC=\"00:00:05:00\" OutTC=\"00:00:06:23\">\n\
+                     \x20     <Graphic Width=\"500\" Height=\"80\" X=\"710\" Y=\"100\">images/00000002.png</Graphic>\n\
+                     \x20   </Event>\n\
+                     \x20 </Events>\n\
+                     </BDN>\n";
+
+    #[test]
+    fn bdn_unchanged_file_is_kept() {
+        // the attributes keep their order (`Forced` before `InTC` in the second event)
+        let mut file = BdnFile::parse(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+    }
+
+    #[test]
+    fn bdn_events() {
+        let file = BdnFile::parse(FILE).unwrap();
+        let events = file.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].timespan.start.msecs(), events[0].timespan.end.msecs()), (1502, 3003));
+        assert!(!events[0].forced && events[1].forced);
+        assert_eq!(
+            events[1].graphics,
+            vec![BdnGraphic {
+                path: "images/00000002.png".to_string(),
+                x: 710,
+                y: 100,
+                width: 500,
+                height: 80,
+            }]
+        );
+        assert_eq!(
+            events[1].graphics[0].resolve(Path::new("/discs/movie")),
+            Path::new("/discs/movie/images/00000002.png")
+        );
+
+        let entries = file.get_subtitle_entries().unwrap();
+        assert!(entries[0].line.is_none() && entries[1].flags.forced);
+    }
+
+    #[test]
+    fn bdn_retime_and_retain() {
+        let mut file = BdnFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[1].timespan += TimeDelta::from_msecs(1001);
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+
+        // the summary in the description follows the events
+        let data = to_string(&file);
+        assert!(data.contains(
+            "<Events LastEventOutTC=\"00:00:07:23\" FirstEventInTC=\"00:00:06:00\" ContentInTC=\"00:00:00:00\" \
+             ContentOutTC=\"00:00:06:23\" NumberofEvents=\"1\" Type=\"Graphic\"/>"
+        ));
+        assert!(data.contains("  <Events>\n    <Event Forced=\"True\" InTC=\"00:00:06:00\" OutTC=\"00:00:07:23\">\n      <Graphic"));
+        assert!(data.ends_with("</Graphic>\n    </Event>\n  </Events>\n</BDN>\n"));
+    }
+
+    #[test]
+    fn bdn_frame_rates() {
+        assert_eq!(parse_error(BdnFile::parse_file, "<Subtitles/>").kind(), &ExpectedBdnElement);
+        assert_eq!(
+            parse_error(BdnFile::parse_file, &FILE.replace("DropFrame=\"False\"", "DropFrame=\"True\"")).kind(),
+            &DropFrameNotSupported
+        );
+        assert_eq!(
+            parse_error(BdnFile::parse_file, &FILE.replace(" FrameRate=\"23.976\"", "")).kind(),
+            &MissingFrameRate
+        );
+        assert_eq!(
+            parse_error(BdnFile::parse_file, &FILE.replace("\"23.976\"", "\"12\"")).kind(),
+            &InvalidFrameRate { value: "12".to_string() }
+        );
+    }
+
+    #[test]
+    fn bdn_invalid_timecodes() {
+        // 24 frames per second: the frames of a timecode go up to 23
+        assert_eq!(
+            parse_error(BdnFile::parse_file, &FILE.replace("00:00:03:00", "00:00:03:24")).kind(),
+            &ErrorAtLine { line_num: 9 }
+        );
+        assert_eq!(
+            parse_error(BdnFile::parse_file, &FILE.replace(" OutTC=\"00:00:03:00\"", "")).kind(),
+            &ErrorAtLine { line_num: 9 }
+        );
+        for value in ["00:00:03:24", "00:60:00:00", "00:00:01", "-1:00:00:00", "2562047788015216:00:00:00"] {
+            assert_eq!(
+                parse_timecode(value, 24, 24.0).unwrap_err().kind(),
+                &InvalidTimecode { value: value.to_string() }
+            );
+        }
+        assert_eq!(parse_timecode("00:00:01:12", 24, 24.0).unwrap(), TimePoint::from_msecs(1500));
+    }
+}
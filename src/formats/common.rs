@@ -6,6 +6,8 @@ use combine::char::*;
 use combine::combinator::*;
 use combine::primitives::{ParseError, ParseResult, Parser, Stream};
 use std::fmt::Display;
+use std::num::ParseIntError;
+use std::ops::Range;
 use std::str::FromStr;
 
 type CustomCharParser<I> = Expected<Satisfy<I, fn(char) -> bool>>;
@@ -22,26 +24,169 @@ pub fn split_bom(s: &str) -> (&str, &str) {
 }
 
 #[test]
-#[allow(unsafe_code)]
 fn test_split_bom() {
-    let bom1_vec = &[0xEF, 0xBB, 0xBF];
-    let bom2_vec = &[0xFE, 0xFF];
-    let bom1 = unsafe { ::std::str::from_utf8_unchecked(bom1_vec) };
-    let bom2 = unsafe { ::std::str::from_utf8_unchecked(bom2_vec) };
-
-    // Rust doesn't seem to let us create a BOM as str in a safe way.
-    assert_eq!(
-        split_bom(unsafe { ::std::str::from_utf8_unchecked(&[0xEF, 0xBB, 0xBF, 'a' as u8, 'b' as u8, 'c' as u8]) }),
-        (bom1, "abc")
-    );
-    assert_eq!(
-        split_bom(unsafe { ::std::str::from_utf8_unchecked(&[0xFE, 0xFF, 'd' as u8, 'e' as u8, 'g' as u8]) }),
-        (bom2, "deg")
-    );
+    // the UTF-16 BOM `[0xFE, 0xFF]` is not valid UTF-8, so only the UTF-8 BOM can appear in a `str`
+    assert_eq!(split_bom("\u{FEFF}abc"), ("\u{FEFF}", "abc"));
     assert_eq!(split_bom("bla"), ("", "bla"));
     assert_eq!(split_bom(""), ("", ""));
 }
 
+/// Replaces the characters with special meaning in XML (`&`, `<`, `>`, `"`, `'`) by entities.
+pub fn escape_xml(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Replaces the predefined and numeric character references of XML.
+pub fn decode_entities(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(idx) = rest.find('&') {
+        let (before, reference) = rest.split_at(idx);
+        result.push_str(before);
+        let decoded = reference.find(';').and_then(|end| {
+            let name = reference.get(1..end)?;
+            let c = match name {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                    Some(hex) => std::char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+                    None => std::char::from_u32(name.strip_prefix('#')?.parse().ok()?)?,
+                },
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = reference.get(len..).unwrap_or("");
+            }
+            None => {
+                result.push('&');
+                rest = reference.get(1..).unwrap_or("");
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the byte range of the value of an attribute in a tag like `<time begin="0:01.5" end=3>`.
+pub fn attribute_range(tag: &str, name: &str) -> Option<Range<usize>> {
+    let bytes = tag.as_bytes();
+    let is_space = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_whitespace);
+    // the element name
+    let mut i = tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>')?;
+    loop {
+        while is_space(i) || bytes.get(i) == Some(&b'/') {
+            i += 1;
+        }
+        let name_start = i;
+        while bytes.get(i).is_some_and(|b| !b.is_ascii_whitespace() && !b"=/>".contains(b)) {
+            i += 1;
+        }
+        let attribute = tag.get(name_start..i).filter(|attribute| !attribute.is_empty())?;
+        while is_space(i) {
+            i += 1;
+        }
+        if bytes.get(i) != Some(&b'=') {
+            continue;
+        }
+        i += 1;
+        while is_space(i) {
+            i += 1;
+        }
+        let range = match bytes.get(i) {
+            Some(&quote) if quote == b'"' || quote == b'\'' => {
+                let end = i + 1 + tag.get(i + 1..)?.find(char::from(quote))?;
+                let range = i + 1..end;
+                i = end + 1;
+                range
+            }
+            _ => {
+                let start = i;
+                while bytes.get(i).is_some_and(|b| !b.is_ascii_whitespace() && *b != b'>') && tag.get(i..) != Some("/>") {
+                    i += 1;
+                }
+                start..i
+            }
+        };
+        if attribute.eq_ignore_ascii_case(name) {
+            return Some(range);
+        }
+    }
+}
+
+/// Returns the value of an attribute in a tag (the names are compared case-insensitively).
+pub fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.get(attribute_range(tag, name)?)
+}
+
+/// Returns an `EntryCountMismatch` error if the number of given entries does not match the file.
+pub fn check_entry_count(expected: usize, found: usize) -> crate::errors::Result<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(crate::ErrorKind::EntryCountMismatch { expected, found }.into())
+    }
+}
+
+/// Normalizes the line endings of a text file for deterministic output: removes the BOM, uses `\n` line
+/// endings and ends the file with exactly one newline. The text of the lines is kept as it is.
+pub fn normalize_line_endings(s: &str) -> String {
+    let (_, s) = split_bom(s);
+    let mut result = String::with_capacity(s.len());
+    for (line, _) in get_lines_non_destructive(s) {
+        result.push_str(&line);
+        result.push('\n');
+    }
+    while result.ends_with("\n\n") {
+        result.pop();
+    }
+    if result == "\n" {
+        result.clear();
+    }
+    result
+}
+
+/// Normalizes the whitespace of a whole text file: removes the BOM, uses `\n` line endings, removes trailing
+/// whitespace, leading empty lines and repeated empty lines, and ends the file with exactly one newline.
+///
+/// This also changes the text of entries with trailing whitespace or empty lines.
+pub fn normalize_whitespace(s: &str) -> String {
+    let (_, s) = split_bom(s);
+    let mut result = String::with_capacity(s.len());
+    let mut previous_empty = true;
+    for (line, _) in get_lines_non_destructive(s) {
+        let line = line.trim_end();
+        if line.is_empty() && previous_empty {
+            continue;
+        }
+        previous_empty = line.is_empty();
+        result.push_str(line);
+        result.push('\n');
+    }
+    let trimmed_len = result.trim_end().len();
+    result.truncate(trimmed_len);
+    if !result.is_empty() {
+        result.push('\n');
+    }
+    result
+}
+
 /// Parses whitespaces and tabs.
 #[inline]
 #[allow(trivial_casts)]
@@ -61,13 +206,13 @@ where
     I: Stream<Item = char>,
 {
     (optional(char('-')), many1(digit()))
-        .map(|(a, c): (Option<_>, String)| {
-            // we provide a string that only contains digits: this unwrap should never fail
-            let i: i64 = FromStr::from_str(&c).unwrap();
-            match a {
+        .and_then(|(a, c): (Option<_>, String)| {
+            // the string only contains digits, so this only fails if the number is too large
+            let i: i64 = FromStr::from_str(&c)?;
+            Ok::<_, ParseIntError>(match a {
                 Some(_) => -i,
                 None => i,
-            }
+            })
         })
         .expected("positive or negative number")
         .parse_stream(input)
@@ -115,6 +260,34 @@ where
     result
 }
 
+/// Removes the parts `first..=last` of a non-destructive part list together with the rest of their line.
+///
+/// The filler before `first` is cut after its last newline and the filler after `last` loses everything up to
+/// and including its first newline, so both neighboring lines stay intact.
+pub fn remove_line_parts<T, F>(v: &mut Vec<T>, first: usize, last: usize, mut extract_fn: F)
+where
+    F: FnMut(&mut T) -> Option<&mut String>,
+{
+    if let Some(next) = v.get_mut(last + 1).and_then(&mut extract_fn) {
+        match next.find('\n') {
+            Some(idx) => {
+                next.drain(..=idx);
+            }
+            None => next.clear(),
+        }
+    }
+
+    if let Some(prev) = first.checked_sub(1).and_then(|i| v.get_mut(i)).and_then(&mut extract_fn) {
+        match prev.rfind('\n') {
+            Some(idx) => prev.truncate(idx + 1),
+            // the line is the first one in the file: only keep the BOM
+            None => prev.truncate(split_bom(prev).0.len()),
+        }
+    }
+
+    v.drain(first..=last);
+}
+
 // used in `get_lines_non_destructive()`
 type SplittedLine = (String /* string */, String /* newline string like \n or \r\n */);
 
@@ -156,6 +329,14 @@ pub fn get_lines_non_destructive(s: &str) -> Vec<SplittedLine> {
     }
 }
 
+#[test]
+fn normalize_line_endings_test() {
+    assert_eq!(normalize_line_endings("\u{FEFF}a  \r\n\r\n\r\nb \rc\n\n\n"), "a  \n\n\nb \nc\n");
+    assert_eq!(normalize_line_endings("a"), "a\n");
+    assert_eq!(normalize_line_endings("\r\n\n"), "");
+    assert_eq!(normalize_whitespace("\n\na  \r\n\r\n\r\nb \rc\n\n"), "a\n\nb\nc\n");
+}
+
 #[test]
 fn get_lines_non_destructive_test0() {
     let lines = ["", "aaabb", "aaabb\r\nbcccc\n\r\n ", "aaabb\r\nbcccc"];
@@ -178,9 +359,7 @@ pub fn trim_non_destructive(s: &str) -> (String, String, String) {
 
 /// Splits a string in whitespace string and the rest "   hello " -> ("   ", "hello ").
 fn trim_left(s: &str) -> (String, String) {
-    (many(ws()), many(r#try(any())), eof())
-        .map(|t| (t.0, t.1))
-        .parse(s)
-        .expect("the trim parser should accept any input")
-        .0
+    let rest = s.trim_start_matches([' ', '\t']);
+    let (begin, rest) = s.split_at(s.len() - rest.len());
+    (begin.to_string(), rest.to_string())
 }
@@ -0,0 +1,516 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use super::vobsub::{read_pts, write_pts};
+use crate::errors::Result as SubtitleParserResult;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use std::convert::TryFrom;
+
+/// Errors specific to DVB subtitle parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Clone, Copy, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected the start code of a PES packet at byte {}", offset)]
+        ExpectedPesPacket { offset: usize },
+
+        #[fail(display = "the PES packet at byte {} is truncated", offset)]
+        TruncatedPesPacket { offset: usize },
+
+        #[fail(display = "expected a transport stream packet of 188 bytes starting with `47` at byte {}", offset)]
+        InvalidTsPacket { offset: usize },
+    }
+}
+
+/// The start code of a PES packet (followed by the stream id).
+const PES_START_CODE: [u8; 3] = [0x00, 0x00, 0x01];
+
+/// The size of the PES header before the optional fields (start code, stream id, length and two flag bytes,
+/// and the length of the optional fields).
+const PES_HEADER_SIZE: usize = 9;
+
+/// The position of the presentation time stamp in the PES header.
+const PES_PTS: usize = 9;
+
+/// The first byte of every subtitling segment.
+const SEGMENT_SYNC_BYTE: u8 = 0x0f;
+
+/// The size of the segment header (sync byte, type, page id and length).
+const SEGMENT_HEADER_SIZE: usize = 6;
+
+/// The segment type which defines the shown regions.
+const PAGE_COMPOSITION: u8 = 0x10;
+
+/// The size of a region in the page composition segment (region id, reserved byte and position).
+const PAGE_REGION_SIZE: usize = 6;
+
+const TS_PACKET_SIZE: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+
+/// The clock of the time stamps.
+const TICKS_PER_MSEC: u64 = 90;
+
+#[derive(Debug, Clone)]
+/// A PES packet with subtitling segments (kept byte by byte).
+struct DvbPacket {
+    data: Vec<u8>,
+}
+
+/// The page composition segment of a packet.
+#[derive(Debug, Clone, Copy)]
+struct DvbPage {
+    /// The position of the segment data in the packet
+    pos: usize,
+
+    /// The length of the segment data
+    len: usize,
+
+    /// The time after which the page is removed (in seconds)
+    timeout: u8,
+
+    /// The number of shown regions (`0` clears the screen)
+    regions: usize,
+}
+
+impl DvbPacket {
+    fn pts(&self) -> Option<u64> {
+        match self.data.get(7) {
+            Some(flags) if flags & 0x80 != 0 => read_pts(&self.data, PES_PTS),
+            _ => None,
+        }
+    }
+
+    fn time(&self) -> Option<TimePoint> {
+        self.pts()
+            .map(|pts| TimePoint::from_msecs(i64::try_from(pts / TICKS_PER_MSEC).unwrap_or(i64::MAX)))
+    }
+
+    fn set_time(&mut self, t: TimePoint) {
+        let pts = u64::try_from(t.msecs().max(0))
+            .unwrap_or_default()
+            .saturating_mul(TICKS_PER_MSEC)
+            .min((1 << 33) - 1);
+        write_pts(&mut self.data, PES_PTS, pts);
+    }
+
+    fn page(&self) -> Option<DvbPage> {
+        // the PES data starts with the data identifier and the subtitle stream id
+        let mut pos = PES_HEADER_SIZE + usize::from(*self.data.get(8)?) + 2;
+        while self.data.get(pos) == Some(&SEGMENT_SYNC_BYTE) {
+            let kind = *self.data.get(pos + 1)?;
+            let len = usize::from(read_u16(&self.data, pos + 4));
+            let data_pos = pos + SEGMENT_HEADER_SIZE;
+            if kind == PAGE_COMPOSITION {
+                return Some(DvbPage {
+                    pos: data_pos,
+                    len,
+                    timeout: self.data.get(data_pos).copied().unwrap_or_default(),
+                    regions: len.saturating_sub(2) / PAGE_REGION_SIZE,
+                });
+            }
+            pos = data_pos + len;
+        }
+        None
+    }
+
+    /// Removes all regions from the page composition segment, so nothing is shown.
+    fn clear_regions(&mut self, page: DvbPage) {
+        let removed = page.len.saturating_sub(2);
+        if removed == 0 || self.data.len() < page.pos + page.len {
+            return;
+        }
+        self.data.drain(page.pos + 2..page.pos + page.len);
+        if let Some(dest) = self.data.get_mut(page.pos - 2..page.pos) {
+            dest.copy_from_slice(&2u16.to_be_bytes());
+        }
+        // a length of `0` (unbounded) stays
+        let pes_len = read_u16(&self.data, 4);
+        if pes_len != 0 {
+            let pes_len = pes_len.saturating_sub(u16::try_from(removed).unwrap_or(u16::MAX));
+            if let Some(dest) = self.data.get_mut(4..6) {
+                dest.copy_from_slice(&pes_len.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// A shown page with the index of its packet and of the packet with the next page.
+#[derive(Debug, Clone, Copy)]
+struct DvbDisplay {
+    packet: usize,
+    page: DvbPage,
+    next: Option<usize>,
+}
+
+/// Represents a DVB subtitle stream (ETSI EN 300 743), the bitmap subtitles of digital television.
+///
+/// The stream consists of PES packets with page composition, region composition, CLUT and object data segments.
+/// Every page composition which shows regions is an entry, which ends with the next page composition or after
+/// the time-out of the page. `parse()` reads a stream of PES packets (like it is written by demuxers) and
+/// `parse_ts()` extracts the packets of one PID from a transport stream capture. `to_data()` always writes the
+/// PES packets, unchanged packets are written byte by byte.
+///
+/// Changing the start time changes the time stamp of the packet, changing the end time moves the packet which
+/// clears the screen (or changes the time-out if the page is removed by it). Removed entries stay in the stream,
+/// but their page shows no regions (later pages may use their objects).
+///
+/// There is no common file extension for DVB subtitles, so `SubtitleFormat::Dvb` uses `.dvbsub` for streams of PES
+/// packets (transport streams have to be read with `parse_ts()`).
+#[derive(Debug, Clone)]
+pub struct DvbFile {
+    packets: Vec<DvbPacket>,
+}
+
+impl DvbFile {
+    /// Parse a stream of PES packets to `DvbFile`.
+    pub fn parse(b: &[u8]) -> SubtitleParserResult<DvbFile> {
+        Ok(Self::parse_pes(b).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    /// Parse the subtitle PES packets with the PID `pid` in a MPEG transport stream to `DvbFile`.
+    pub fn parse_ts(b: &[u8], pid: u16) -> SubtitleParserResult<DvbFile> {
+        Ok(Self::parse_transport_stream(b, pid).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn parse_pes(b: &[u8]) -> Result<DvbFile> {
+        let mut packets = Vec::new();
+        let mut rest = b;
+        while !rest.is_empty() {
+            let offset = b.len() - rest.len();
+            if !rest.starts_with(&PES_START_CODE) {
+                return Err(ExpectedPesPacket { offset }.into());
+            }
+            let len = match read_u16(rest, 4) {
+                0 => rest.len(),
+                len => 6 + usize::from(len),
+            };
+            let header_len = rest.get(8).map_or(usize::MAX, |&n| PES_HEADER_SIZE + usize::from(n));
+            if rest.len() < len || len < header_len {
+                return Err(TruncatedPesPacket { offset }.into());
+            }
+            let (packet, next) = rest.split_at(len);
+            packets.push(DvbPacket { data: packet.to_vec() });
+            rest = next;
+        }
+        Ok(DvbFile { packets })
+    }
+
+    fn parse_transport_stream(b: &[u8], pid: u16) -> Result<DvbFile> {
+        let mut buffers: Vec<Vec<u8>> = Vec::new();
+        for (idx, packet) in b.chunks(TS_PACKET_SIZE).enumerate() {
+            let header = match *packet {
+                [TS_SYNC_BYTE, b1, b2, b3, ..] if packet.len() == TS_PACKET_SIZE => (b1, b2, b3),
+                _ => {
+                    return Err(InvalidTsPacket {
+                        offset: idx * TS_PACKET_SIZE,
+                    }
+                    .into())
+                }
+            };
+            let (b1, b2, b3) = header;
+            if (u16::from(b1 & 0x1f) << 8 | u16::from(b2)) != pid || b3 & 0x10 == 0 {
+                continue;
+            }
+            // skip the adaptation field
+            let payload_start = if b3 & 0x20 != 0 {
+                5 + usize::from(packet.get(4).copied().unwrap_or_default())
+            } else {
+                4
+            };
+            let payload = packet.get(payload_start..).unwrap_or_default();
+            if b1 & 0x40 != 0 {
+                buffers.push(payload.to_vec());
+            } else if let Some(buffer) = buffers.last_mut() {
+                buffer.extend_from_slice(payload);
+            }
+        }
+
+        // the last transport stream packet of a PES packet is filled with stuffing bytes
+        let mut pes = Vec::new();
+        for mut buffer in buffers {
+            let len = read_u16(&buffer, 4);
+            if len != 0 {
+                buffer.truncate(6 + usize::from(len));
+            }
+            pes.extend(buffer);
+        }
+        Self::parse_pes(&pes)
+    }
+
+    fn displays(&self) -> Vec<DvbDisplay> {
+        let pages: Vec<(usize, DvbPage)> = self
+            .packets
+            .iter()
+            .enumerate()
+            .filter(|(_, packet)| packet.pts().is_some())
+            .filter_map(|(idx, packet)| packet.page().map(|page| (idx, page)))
+            .collect();
+        pages
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, page))| page.regions > 0)
+            .map(|(i, &(packet, page))| DvbDisplay {
+                packet,
+                page,
+                next: pages.get(i + 1).map(|&(next, _)| next),
+            })
+            .collect()
+    }
+
+    fn packet_time(&self, idx: usize) -> TimePoint {
+        self.packets
+            .get(idx)
+            .and_then(DvbPacket::time)
+            .unwrap_or_else(|| TimePoint::from_msecs(0))
+    }
+
+    /// Returns the timespan of a page and whether it is ended by the time-out.
+    fn display_timespan(&self, display: &DvbDisplay) -> (TimeSpan, bool) {
+        let start = self.packet_time(display.packet);
+        let timeout = start + TimeDelta::from_secs(i64::from(display.page.timeout));
+        match display.next.map(|next| self.packet_time(next)) {
+            Some(next) if next <= timeout || display.page.timeout == 0 => (TimeSpan::new(start, next), false),
+            _ => (TimeSpan::new(start, timeout), true),
+        }
+    }
+}
+
+impl SubtitleFileInterface for DvbFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .displays()
+            .iter()
+            .map(|display| SubtitleEntry::from(self.display_timespan(display).0))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let displays = self.displays();
+        check_entry_count(displays.len(), new_subtitle_entries.len())?;
+
+        for (display, new) in displays.iter().zip(new_subtitle_entries) {
+            let (old, by_timeout) = self.display_timespan(display);
+            if old.start != new.timespan.start {
+                if let Some(packet) = self.packets.get_mut(display.packet) {
+                    packet.set_time(new.timespan.start);
+                }
+            }
+            if old.end == new.timespan.end && old.start == new.timespan.start {
+                continue;
+            }
+            let clearing = display
+                .next
+                .filter(|&next| self.packets.get(next).and_then(DvbPacket::page).is_some_and(|page| page.regions == 0));
+            match clearing {
+                Some(next) if !by_timeout => {
+                    if let Some(packet) = self.packets.get_mut(next) {
+                        packet.set_time(new.timespan.end);
+                    }
+                }
+                _ if by_timeout => {
+                    let secs = (new.timespan.end - new.timespan.start).msecs().max(0).saturating_add(999) / 1000;
+                    if let Some(timeout) = self
+                        .packets
+                        .get_mut(display.packet)
+                        .and_then(|packet| packet.data.get_mut(display.page.pos))
+                    {
+                        *timeout = u8::try_from(secs.max(1)).unwrap_or(u8::MAX);
+                    }
+                }
+                // the end is the start of the next page
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        Ok(self.packets.iter().flat_map(|packet| packet.data.iter().copied()).collect())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let displays = self.displays();
+        check_entry_count(displays.len(), keep.len())?;
+
+        for (display, _) in displays.iter().zip(keep).filter(|&(_, &keep)| !keep) {
+            if let Some(packet) = self.packets.get_mut(display.packet) {
+                packet.clear_regions(display.page);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    match data.get(pos..pos + 2) {
+        Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PES packet with a page composition segment (with a region for every position) and an object segment.
+    fn packet(pts_ms: u64, timeout: u8, regions: &[(u16, u16)]) -> Vec<u8> {
+        let mut page = vec![timeout, 0x04];
+        for &(x, y) in regions {
+            page.extend_from_slice(&[0, 0xff]);
+            page.extend_from_slice(&x.to_be_bytes());
+            page.extend_from_slice(&y.to_be_bytes());
+        }
+        let mut payload = vec![0x20, 0x00];
+        for (kind, data) in [(PAGE_COMPOSITION, page), (0x13, vec![0, 1, 0, 0, 0, 0, 0])] {
+            payload.extend_from_slice(&[SEGMENT_SYNC_BYTE, kind, 0, 1]);
+            payload.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            payload.extend(data);
+        }
+        payload.push(0xff);
+
+        let mut result = vec![0, 0, 1, 0xbd, 0, 0, 0x81, 0x80, 5, 0x21, 0, 0, 0, 0];
+        write_pts(&mut result, PES_PTS, pts_ms * TICKS_PER_MSEC);
+        result.extend(payload);
+        let len = (result.len() - 6) as u16;
+        result[4..6].copy_from_slice(&len.to_be_bytes());
+        result
+    }
+
+    /// Three packets: a page from 1 s (10 s timeout), an empty page at 3 s and a page with two regions at 4 s.
+    fn stream() -> Vec<u8> {
+        let mut input = packet(1000, 10, &[(100, 500)]);
+        input.extend(packet(3000, 10, &[]));
+        input.extend(packet(4000, 2, &[(100, 500), (100, 550)]));
+        input
+    }
+
+    fn spans(file: &DvbFile) -> Vec<(i64, i64)> {
+        let entries = file.get_subtitle_entries().unwrap();
+        entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect()
+    }
+
+    /// Packs the PES packets into transport stream packets of `pid`, with a packet of another PID after each of them.
+    fn transport_stream(pes_packets: &[Vec<u8>], pid: u16) -> Vec<u8> {
+        let mut ts = Vec::new();
+        for pes in pes_packets {
+            for (i, chunk) in pes.chunks(100).enumerate() {
+                let start = if i == 0 { 0x40 } else { 0x00 };
+                let mut ts_packet = vec![TS_SYNC_BYTE, start | (pid >> 8) as u8, pid as u8, 0x10];
+                ts_packet.extend_from_slice(chunk);
+                ts_packet.resize(TS_PACKET_SIZE, 0xff);
+                ts.extend(ts_packet);
+            }
+            let mut other = vec![TS_SYNC_BYTE, 0x40, 0x11, 0x10];
+            other.resize(TS_PACKET_SIZE, 0);
+            ts.extend(other);
+        }
+        ts
+    }
+
+    #[test]
+    fn dvb_round_trip() {
+        let input = stream();
+        assert_eq!(DvbFile::parse(&input).unwrap().to_data().unwrap(), input);
+    }
+
+    #[test]
+    fn dvb_page_timeouts() {
+        // the empty page ends the first one, the last one ends after its timeout
+        let file = DvbFile::parse(&stream()).unwrap();
+        assert_eq!(spans(&file), vec![(1000, 3000), (4000, 6000)]);
+    }
+
+    #[test]
+    fn dvb_update_timespans() {
+        let mut file = DvbFile::parse(&stream()).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].timespan = TimeSpan::new(TimePoint::from_msecs(1500), TimePoint::from_msecs(3500));
+        entries[1].timespan.end = TimePoint::from_msecs(7000);
+        file.update_subtitle_entries(&entries).unwrap();
+        let file = DvbFile::parse(&file.to_data().unwrap()).unwrap();
+        assert_eq!(spans(&file), vec![(1500, 3500), (4000, 7000)]);
+    }
+
+    #[test]
+    fn dvb_update_entry_count_mismatch() {
+        let mut file = DvbFile::parse(&stream()).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        let error = file.update_subtitle_entries(&entries[..1]).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::EntryCountMismatch { expected: 2, found: 1 });
+    }
+
+    #[test]
+    fn dvb_retain_entries() {
+        let mut file = DvbFile::parse(&stream()).unwrap();
+        file.retain_entries_by_mask(&[true, false]).unwrap();
+        let file = DvbFile::parse(&file.to_data().unwrap()).unwrap();
+        assert_eq!(spans(&file), vec![(1000, 3000)]);
+    }
+
+    #[test]
+    fn dvb_transport_stream() {
+        let ts = transport_stream(&[packet(1000, 10, &[(100, 500)]), packet(3000, 10, &[])], 0x123);
+        let file = DvbFile::parse_ts(&ts, 0x123).unwrap();
+        assert_eq!(spans(&file), vec![(1000, 3000)]);
+        assert!(DvbFile::parse_ts(&ts, 0x124).unwrap().get_subtitle_entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn dvb_truncated_pes_packet() {
+        let input = stream();
+        let offset = input.len() - packet(4000, 2, &[(100, 500), (100, 550)]).len();
+        let error = DvbFile::parse_pes(&input[..input.len() - 1]).unwrap_err();
+        assert_eq!(*error.kind(), TruncatedPesPacket { offset });
+        assert_eq!(
+            DvbFile::parse(&input[..input.len() - 1]).unwrap_err().kind(),
+            crate::ErrorKind::ParsingError
+        );
+    }
+
+    #[test]
+    fn dvb_expected_pes_packet() {
+        let mut input = stream();
+        let offset = input.len();
+        input.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(*DvbFile::parse_pes(&input).unwrap_err().kind(), ExpectedPesPacket { offset });
+    }
+
+    #[test]
+    fn dvb_invalid_ts_packet() {
+        let ts = transport_stream(&[packet(1000, 10, &[(100, 500)])], 0x123);
+        let error = DvbFile::parse_transport_stream(&ts[..TS_PACKET_SIZE + 100], 0x123).unwrap_err();
+        assert_eq!(*error.kind(), InvalidTsPacket { offset: TS_PACKET_SIZE });
+
+        let mut ts = ts;
+        ts[0] = 0;
+        assert_eq!(
+            *DvbFile::parse_transport_stream(&ts, 0x123).unwrap_err().kind(),
+            InvalidTsPacket { offset: 0 }
+        );
+    }
+
+    #[test]
+    fn dvb_subtitle_format() {
+        let input = packet(1000, 10, &[(100, 500)]);
+        let format = crate::get_subtitle_format(Some(std::ffi::OsStr::new("dvbsub")), &input).unwrap();
+        assert_eq!(format, crate::SubtitleFormat::Dvb);
+        assert!(format.capabilities().bitmap);
+
+        let file = crate::parse_bytes(format, &input, None, 25.0).unwrap();
+        assert_eq!(file.get_subtitle_entries().unwrap().len(), 1);
+        assert_eq!(file.to_data().unwrap(), input);
+        assert!(crate::parse_str(format, "", 25.0).is_err());
+    }
+}
@@ -5,6 +5,7 @@
 use self::errors::ErrorKind::*; // the crate wide error type (we use a custom error type here)
 use self::errors::*;
 use super::common::*;
+use crate::warnings::Warnings;
 use crate::{SubtitleEntry, SubtitleFileInterface};
 
 use crate::errors::Result as SubtitleParserResult;
@@ -14,8 +15,9 @@ use combine::primitives::Parser;
 
 use failure::ResultExt;
 
-use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
-use std::iter::once;
+use crate::delay::DelayOutput;
+use crate::durations::DurationSynthesis;
+use crate::timetypes::{TimeDelta, TimePoint};
 
 /// `.idx`-parser-specific errors
 #[allow(missing_docs)]
@@ -41,6 +43,25 @@ enum IdxFilePart {
 
     /// Represents a parsed time string like "00:42:20:204".
     Timestamp(TimePoint),
+
+    /// The delay of a `delay: 00:00:01:000` line, which shifts all following timestamps.
+    Delay(TimeDelta),
+}
+
+/// A `timestamp:` line as it is written in the file (see `IdxFile::index_entries()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IdxIndexEntry {
+    /// The line of the entry (starting at 0).
+    pub line_num: usize,
+
+    /// The `index:` of the last `id:` line (the subpicture stream `0x20 + index` in the `.sub` file).
+    pub stream: u8,
+
+    /// The timestamp without the delay.
+    pub timestamp: TimePoint,
+
+    /// The hexadecimal `filepos:` of the first packet of the subtitle in the `.sub` file.
+    pub filepos: Option<usize>,
 }
 
 // ////////////////////////////////////////////////////////////////////////////////////////////////
@@ -50,9 +71,19 @@ enum IdxFilePart {
 ///
 /// All (for this project) unimportant information are saved into `IdxFilePart::Filler(...)`, so
 /// a timespan-altered file still has the same meta-information.
+///
+/// Comment lines (starting with `#`) directly above a `timestamp:` line belong to that entry: they are removed
+/// together with it and new entries are inserted above them. All other lines (like `id:` or `delay:` lines and
+/// blank lines) stay where they are.
 #[derive(Debug, Clone)]
 pub struct IdxFile {
     v: Vec<IdxFilePart>,
+
+    /// How the end times of the subtitles are created
+    durations: DurationSynthesis,
+
+    /// Whether the `delay:` lines are kept in `to_data()`
+    delay_output: DelayOutput,
 }
 
 impl IdxFile {
@@ -62,58 +93,234 @@ impl IdxFile {
             IdxFilePart::Filler(ref mut text) => Some(text),
             _ => None,
         });
-        IdxFile { v: new_file_parts }
+        IdxFile {
+            v: new_file_parts,
+            durations: DurationSynthesis::IDX,
+            delay_output: DelayOutput::default(),
+        }
+    }
+
+    /// Sets how the end times of the subtitles are created (default: `DurationSynthesis::IDX`).
+    pub fn set_duration_synthesis(&mut self, durations: DurationSynthesis) {
+        self.durations = durations;
+    }
+
+    /// Sets whether the `delay:` lines are kept when writing the file (default: `DelayOutput::KeepMetadata`).
+    pub fn set_delay_output(&mut self, delay_output: DelayOutput) {
+        self.delay_output = delay_output;
+    }
+
+    /// Returns the timestamps together with the delay which applies to them (the one of the last `delay:` line before).
+    fn timestamps_with_delay(&mut self) -> Vec<(&mut TimePoint, TimeDelta)> {
+        let mut delay = TimeDelta::from_msecs(0);
+        self.v
+            .iter_mut()
+            .filter_map(|part| match *part {
+                IdxFilePart::Filler(_) => None,
+                IdxFilePart::Timestamp(ref mut t) => Some((t, delay)),
+                IdxFilePart::Delay(d) => {
+                    delay = d;
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the `timestamp:` lines with the stream and the packet position they refer to.
+    pub(crate) fn index_entries(&self) -> Vec<IdxIndexEntry> {
+        let mut result: Vec<IdxIndexEntry> = Vec::new();
+        let mut line_num = 0;
+        let mut stream = 0;
+        let mut at_line_start = true;
+        for part in &self.v {
+            let text = match *part {
+                IdxFilePart::Filler(ref text) => text,
+                IdxFilePart::Timestamp(timestamp) => {
+                    result.push(IdxIndexEntry {
+                        line_num,
+                        stream,
+                        timestamp,
+                        filepos: None,
+                    });
+                    at_line_start = false;
+                    continue;
+                }
+                IdxFilePart::Delay(_) => {
+                    at_line_start = false;
+                    continue;
+                }
+            };
+
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    line_num += 1;
+                }
+                if i == 0 && !at_line_start {
+                    // the rest of a `timestamp:` line like `, filepos: 000000800`
+                    if let Some(entry) = result.last_mut().filter(|entry| entry.line_num == line_num) {
+                        let filepos = line.find("filepos:").map(|pos| line.get(pos + "filepos:".len()..).unwrap_or("").trim());
+                        entry.filepos = filepos.and_then(|filepos| usize::from_str_radix(filepos, 16).ok());
+                    }
+                } else if line.trim_start().starts_with("id:") {
+                    let index = line.find("index:").and_then(|pos| line.get(pos + "index:".len()..)).map(str::trim);
+                    stream = index.and_then(|index| index.parse().ok()).unwrap_or(stream);
+                }
+            }
+            at_line_start = (at_line_start && text.is_empty()) || text.ends_with('\n');
+        }
+        result
+    }
+
+    /// Adds the delays to the timestamps and removes the `delay:` lines.
+    fn bake_delays(&mut self) {
+        for (t, delay) in self.timestamps_with_delay() {
+            *t += delay;
+        }
+        let delay_idxs: Vec<usize> = (0..self.v.len())
+            .filter(|&i| matches!(self.v.get(i), Some(IdxFilePart::Delay(_))))
+            .collect();
+        for &part_idx in delay_idxs.iter().rev() {
+            remove_line_parts(&mut self.v, part_idx, part_idx, |part| match *part {
+                IdxFilePart::Filler(ref mut text) => Some(text),
+                _ => None,
+            });
+        }
+        self.v = IdxFile::new(std::mem::take(&mut self.v)).v;
+    }
+}
+
+impl IdxFile {
+    /// Inserts a `timestamp:` line for a subtitle which starts at `timestamp` and whose first packet is at `filepos`
+    /// in the `.sub` file, as entry `idx` (or at the end).
+    ///
+    /// The line is inserted above the comments of the current entry `idx`, so it ends up in the same `id:` block as
+    /// this entry (or the last entry). The `delay:` which applies at this position is subtracted from `timestamp`.
+    pub fn insert_entry(&mut self, idx: usize, timestamp: TimePoint, filepos: usize) {
+        let newline = if self.filler_parts().into_iter().flatten().any(|text| text.contains("\r\n")) {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let timestamp_idxs: Vec<usize> = self
+            .v
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| matches!(part, IdxFilePart::Timestamp(_)))
+            .map(|(i, _)| i)
+            .collect();
+
+        // the filler part and the position in it where the new line starts
+        let (filler_idx, split_at) = match (timestamp_idxs.get(idx), timestamp_idxs.last()) {
+            (Some(&part_idx), _) => {
+                let filler_idx = part_idx - 1;
+                let line_start = match self.v.get(filler_idx) {
+                    Some(IdxFilePart::Filler(text)) => match text.rfind('\n') {
+                        Some(i) => attached_comments_start(text.get(..=i).unwrap_or("")),
+                        None => split_bom(text).0.len(),
+                    },
+                    _ => 0,
+                };
+                (filler_idx, line_start)
+            }
+            (None, Some(&last_idx)) => (last_idx + 1, self.line_end(last_idx + 1, false, newline)),
+            (None, None) => {
+                let filler_idx = self.v.len().saturating_sub(1);
+                (filler_idx, self.line_end(filler_idx, true, newline))
+            }
+        };
+
+        let delay = self
+            .v
+            .iter()
+            .take(filler_idx)
+            .rev()
+            .find_map(|part| match *part {
+                IdxFilePart::Delay(d) => Some(d),
+                _ => None,
+            })
+            .unwrap_or_else(|| TimeDelta::from_msecs(0));
+        let rest = match self.v.get_mut(filler_idx) {
+            Some(IdxFilePart::Filler(text)) => text.split_off(split_at.min(text.len())),
+            _ => String::new(),
+        };
+        let parts = vec![
+            IdxFilePart::Filler("timestamp: ".to_string()),
+            IdxFilePart::Timestamp(timestamp - delay),
+            IdxFilePart::Filler(format!(", filepos: {:09x}{}", filepos, newline)),
+            IdxFilePart::Filler(rest),
+        ];
+        self.v.splice(filler_idx + 1..filler_idx + 1, parts);
+        self.v = IdxFile::new(std::mem::take(&mut self.v)).v;
+    }
+
+    /// Returns the position after the newline which ends the first line of the filler part `filler_idx` (or its
+    /// last line for `last_line`); the line is ended with `newline` if the file ends without one.
+    fn line_end(&mut self, filler_idx: usize, last_line: bool, newline: &str) -> usize {
+        let text = match self.v.get_mut(filler_idx) {
+            Some(IdxFilePart::Filler(text)) => text,
+            _ => return 0,
+        };
+        match text.find('\n').filter(|_| !last_line) {
+            Some(i) => i + 1,
+            None => {
+                if !(text.ends_with('\n') || filler_idx == 0 && split_bom(text).1.is_empty()) {
+                    text.push_str(newline);
+                }
+                text.len()
+            }
+        }
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                IdxFilePart::Filler(ref text) => Some(text.as_str()),
+                IdxFilePart::Timestamp(_) | IdxFilePart::Delay(_) => None,
+            })
+            .collect()
     }
 }
 
 impl SubtitleFileInterface for IdxFile {
     fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let mut delay = TimeDelta::from_msecs(0);
         let timings: Vec<_> = self
             .v
             .iter()
             .filter_map(|file_part| match *file_part {
                 IdxFilePart::Filler(_) => None,
-                IdxFilePart::Timestamp(t) => Some(t),
+                IdxFilePart::Timestamp(t) => Some((t + delay, None)),
+                IdxFilePart::Delay(d) => {
+                    delay = d;
+                    None
+                }
             })
             .collect();
 
-        Ok(match timings.last() {
-            Some(&last_timing) => {
-                // .idx files do not store timespans. Every subtitle is shown until the next subtitle
-                // starts. Mpv shows the last subtitle for exactly one minute.
-                let next_timings = timings.iter().cloned().skip(1).chain(once(last_timing + TimeDelta::from_mins(1)));
-                timings
-                    .iter()
-                    .cloned()
-                    .zip(next_timings)
-                    .map(|time_tuple| TimeSpan::new(time_tuple.0, time_tuple.1))
-                    .map(SubtitleEntry::from)
-                    .collect()
-            }
-            None => {
-                // no timings
-                Vec::new()
-            }
-        })
+        // .idx files do not store timespans (by default every subtitle is shown until the next subtitle starts)
+        Ok(self.durations.timespans(&timings).into_iter().map(SubtitleEntry::from).collect())
     }
 
     fn update_subtitle_entries(&mut self, ts: &[SubtitleEntry]) -> SubtitleParserResult<()> {
-        let mut count = 0;
-        for file_part_ref in &mut self.v {
-            match *file_part_ref {
-                IdxFilePart::Filler(_) => {}
-                IdxFilePart::Timestamp(ref mut this_ts_ref) => {
-                    *this_ts_ref = ts[count - 1].timespan.start;
-                    count += 1;
-                }
-            }
+        let count = self.v.iter().filter(|part| matches!(part, IdxFilePart::Timestamp(_))).count();
+        check_entry_count(count, ts.len())?;
+
+        for ((this_ts_ref, delay), entry) in self.timestamps_with_delay().into_iter().zip(ts) {
+            *this_ts_ref = entry.timespan.start - delay;
         }
 
-        assert_eq!(count, ts.len()); // required by specification of this function
         Ok(())
     }
 
     fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        if self.delay_output == DelayOutput::Bake && self.v.iter().any(|part| matches!(part, IdxFilePart::Delay(_))) {
+            let mut baked = self.clone();
+            baked.bake_delays();
+            return baked.to_data();
+        }
+
         // timing to string like "00:03:28:308"
         let fn_timing_to_string = |t: TimePoint| {
             let p = if t.msecs() < 0 { -t } else { t };
@@ -132,6 +339,7 @@ impl SubtitleFileInterface for IdxFile {
             match *part {
                 Filler(ref t) => t.clone(),
                 Timestamp(t) => fn_timing_to_string(t),
+                Delay(d) => fn_timing_to_string(TimePoint::from_msecs(d.msecs())),
             }
         };
 
@@ -139,6 +347,51 @@ impl SubtitleFileInterface for IdxFile {
 
         Ok(result.into_bytes())
     }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let timestamp_idxs: Vec<usize> = self
+            .v
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| matches!(part, IdxFilePart::Timestamp(_)))
+            .map(|(i, _)| i)
+            .collect();
+        check_entry_count(timestamp_idxs.len(), keep.len())?;
+
+        // removing from the back keeps the indices of the front valid
+        for (&part_idx, _) in timestamp_idxs.iter().zip(keep).rev().filter(|(_, &keep)| !keep) {
+            remove_line_parts(&mut self.v, part_idx, part_idx, |part| match *part {
+                IdxFilePart::Filler(ref mut text) => Some(text),
+                _ => None,
+            });
+
+            // the comments above the `timestamp:` line are removed with it
+            if let Some(IdxFilePart::Filler(text)) = self.v.get_mut(part_idx - 1) {
+                let start = attached_comments_start(text);
+                text.truncate(start);
+            }
+        }
+
+        self.v = IdxFile::new(std::mem::take(&mut self.v)).v;
+        Ok(())
+    }
+}
+
+/// Returns where the comment lines at the end of `text` start, which belong to the `timestamp:` line after them.
+///
+/// `text` has to end with the newline before the `timestamp:` line. Its first line is never a comment of the entry:
+/// it is either the rest of a line with a timestamp or the first line of the file (the `# VobSub index file` header).
+fn attached_comments_start(text: &str) -> usize {
+    let first_line_end = text.find('\n').map_or(text.len(), |i| i + 1);
+    let mut start = text.len();
+    while start > first_line_end {
+        let line_begin = text.get(..start - 1).and_then(|before| before.rfind('\n')).map_or(0, |i| i + 1);
+        if !text.get(line_begin..start).unwrap_or("").trim_start().starts_with('#') {
+            break;
+        }
+        start = line_begin;
+    }
+    start
 }
 
 // ////////////////////////////////////////////////////////////////////////////////////////////////
@@ -149,6 +402,14 @@ impl IdxFile {
     pub fn parse(s: &str) -> SubtitleParserResult<IdxFile> {
         Ok(Self::parse_inner(s).with_context(|_| crate::ErrorKind::ParsingError)?)
     }
+
+    /// Parse a `.idx` subtitle string to `IdxFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(IdxFile, Warnings)> {
+        let file = Self::parse(s)?;
+        let mut warnings = Warnings::new();
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
 }
 
 // implement parsing functions
@@ -169,6 +430,9 @@ impl IdxFile {
     }
 
     fn parse_line(line_num: usize, s: String) -> Result<Vec<IdxFilePart>> {
+        if s.trim_start().starts_with("delay:") {
+            return Self::parse_delay_line(line_num, s);
+        }
         if !s.trim_start().starts_with("timestamp:") {
             return Ok(vec![IdxFilePart::Filler(s)]);
         }
@@ -183,13 +447,13 @@ impl IdxFile {
         )
             .map(
                 |(ws1, s1, ws2, timestamp_str, s2, _): (String, &str, String, String, String, ())| -> Result<Vec<IdxFilePart>> {
-                    let mut result = Vec::<IdxFilePart>::new();
-                    result.push(IdxFilePart::Filler(ws1));
-                    result.push(IdxFilePart::Filler(s1.to_string()));
-                    result.push(IdxFilePart::Filler(ws2));
-                    result.push(IdxFilePart::Timestamp(Self::parse_timestamp(line_num, timestamp_str.as_str())?));
-                    result.push(IdxFilePart::Filler(s2.to_string()));
-                    Ok(result)
+                    Ok(vec![
+                        IdxFilePart::Filler(ws1),
+                        IdxFilePart::Filler(s1.to_string()),
+                        IdxFilePart::Filler(ws2),
+                        IdxFilePart::Timestamp(Self::parse_timestamp(line_num, timestamp_str.as_str())?),
+                        IdxFilePart::Filler(s2.to_string()),
+                    ])
                 },
             )
             .parse(s.as_str())
@@ -200,6 +464,29 @@ impl IdxFile {
             .0
     }
 
+    /// Parses a line like `delay: -00:00:01:500` (lines with other values are kept as filler without effect).
+    fn parse_delay_line(line_num: usize, s: String) -> Result<Vec<IdxFilePart>> {
+        let (ws1, rest) = s.split_at(s.len() - s.trim_start().len());
+        let rest = rest.get("delay:".len()..).unwrap_or("");
+        let (ws2, rest) = rest.split_at(rest.len() - rest.trim_start().len());
+        let (delay_str, s2) = rest.split_at(rest.find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '-')).unwrap_or(rest.len()));
+        let (negative, timestamp_str) = match delay_str.strip_prefix('-') {
+            Some(timestamp_str) => (true, timestamp_str),
+            None => (false, delay_str),
+        };
+        let delay = match Self::parse_timestamp(line_num, timestamp_str) {
+            Ok(delay) => delay,
+            Err(_) => return Ok(vec![IdxFilePart::Filler(s)]),
+        };
+        Ok(vec![
+            IdxFilePart::Filler(ws1.to_string()),
+            IdxFilePart::Filler("delay:".to_string()),
+            IdxFilePart::Filler(ws2.to_string()),
+            IdxFilePart::Delay(TimeDelta::from_msecs(if negative { -delay.msecs() } else { delay.msecs() })),
+            IdxFilePart::Filler(s2.to_string()),
+        ])
+    }
+
     /// Parse an .idx timestamp like `00:41:36:961`.
     fn parse_timestamp(line_num: usize, s: &str) -> Result<TimePoint> {
         (
@@ -212,15 +499,93 @@ impl IdxFile {
             parser(number_i64),
             eof(),
         )
-            .map(|(hours, _, mins, _, secs, _, msecs, _)| TimePoint::from_components(hours, mins, secs, msecs))
-            .parse(s) // <- return type is ParseResult<(Timing, &str)>
-            .map(|(file_part, _)| file_part)
+            .map(|(hours, _, mins, _, secs, _, msecs, _)| TimePoint::try_from_components(hours, mins, secs, msecs))
+            .parse(s) // <- return type is ParseResult<(Result<TimePoint, TimestampOutOfRange>, &str)>
             .map_err(|e| {
-                IdxLineParseError {
+                Error::from(IdxLineParseError {
                     line_num,
                     msg: parse_error_to_string(e),
-                }
-                .into()
+                })
+            })?
+            .0
+            .with_context(|e| IdxLineParseError {
+                line_num,
+                msg: e.to_string(),
             })
+            .map_err(Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idx_delay() {
+        let input =
+            "delay: -00:00:00:500\ntimestamp: 00:00:01:000, filepos: 000000000\ndelay: 00:00:01:000\ntimestamp: 00:00:02:000, filepos: 000000800\n";
+        let mut file = IdxFile::parse(input).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        let starts: Vec<i64> = entries.iter().map(|e| e.timespan.start.msecs()).collect();
+        assert_eq!(starts, vec![500, 3000]);
+
+        entries[0].timespan.start = TimePoint::from_msecs(1500);
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            input.replace("00:00:01:000, filepos: 000000000", "00:00:02:000, filepos: 000000000")
+        );
+
+        file.set_delay_output(DelayOutput::Bake);
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "timestamp: 00:00:01:500, filepos: 000000000\ntimestamp: 00:00:03:000, filepos: 000000800\n"
+        );
+        assert!(IdxFile::parse("delay: 0\n").is_ok());
+    }
+
+    #[test]
+    fn idx_retain_entries() {
+        let input = "# VobSub index file, v7\n\nid: en, index: 0\n# first\ntimestamp: 00:00:01:000, filepos: 000000000\n# second\ntimestamp: 00:00:02:000, filepos: 000000800\ntimestamp: 00:00:03:000, filepos: 000001000\n";
+        let mut file = IdxFile::parse(input).unwrap();
+        file.retain_entries_by_mask(&[true, false, true]).unwrap();
+
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert_eq!(
+            data,
+            "# VobSub index file, v7\n\nid: en, index: 0\n# first\ntimestamp: 00:00:01:000, filepos: 000000000\ntimestamp: 00:00:03:000, filepos: 000001000\n"
+        );
+        assert_eq!(file.get_subtitle_entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn idx_insert_entries() {
+        let input = "# VobSub index file, v7\r\n# first\r\ntimestamp: 00:00:01:000, filepos: 000000000\r\n\r\nid: de, index: 1\r\ndelay: 00:00:01:000\r\n# second\r\n# (two lines)\r\ntimestamp: 00:00:02:000, filepos: 000000800";
+        let mut file = IdxFile::parse(input).unwrap();
+        file.insert_entry(1, TimePoint::from_msecs(2500), 0x400);
+        file.insert_entry(9, TimePoint::from_msecs(4000), 0x1000);
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "# VobSub index file, v7\r\n# first\r\ntimestamp: 00:00:01:000, filepos: 000000000\r\n\r\nid: de, index: 1\r\ndelay: 00:00:01:000\r\n\
+             timestamp: 00:00:01:500, filepos: 000000400\r\n# second\r\n# (two lines)\r\ntimestamp: 00:00:02:000, filepos: 000000800\r\n\
+             timestamp: 00:00:03:000, filepos: 000001000\r\n"
+        );
+        let starts: Vec<i64> = file.get_subtitle_entries().unwrap().iter().map(|e| e.timespan.start.msecs()).collect();
+        assert_eq!(starts, vec![1000, 2500, 3000, 4000]);
+
+        // removing an entry keeps the comments of its neighbors
+        file.retain_entries_by_mask(&[false, true, false, true]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "# VobSub index file, v7\r\n\r\nid: de, index: 1\r\ndelay: 00:00:01:000\r\ntimestamp: 00:00:01:500, filepos: 000000400\r\n\
+             timestamp: 00:00:03:000, filepos: 000001000\r\n"
+        );
+
+        let mut empty = IdxFile::parse("# VobSub index file, v7\n").unwrap();
+        empty.insert_entry(0, TimePoint::from_msecs(1000), 0);
+        assert_eq!(
+            String::from_utf8(empty.to_data().unwrap()).unwrap(),
+            "# VobSub index file, v7\ntimestamp: 00:00:01:000, filepos: 000000000\n"
+        );
     }
 }
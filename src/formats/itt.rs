@@ -0,0 +1,198 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::common::escape_xml;
+use super::ttml::TtmlFile;
+use crate::errors::Result as SubtitleParserResult;
+use crate::timetypes::TimeSpan;
+use crate::warnings::Warnings;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+/// The frame rate of an `.itt` file (the `ttp:frameRate`, `ttp:frameRateMultiplier` and `ttp:dropMode` attributes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IttFrameRate {
+    /// The number of frames per second of the timecodes (like 30 for 29.97 fps).
+    pub frame_rate: u32,
+
+    /// The factor of the actual frame rate as numerator and denominator (`(1000, 1001)` for 23.976 and 29.97 fps).
+    pub multiplier: (u32, u32),
+
+    /// The timecodes skip the frame numbers 0 and 1 at the start of every minute, except for every tenth minute
+    /// (only for 29.97 fps).
+    pub drop_frame: bool,
+}
+
+impl IttFrameRate {
+    /// 23.976 fps (24 fps timecodes).
+    pub const FPS_23_976: IttFrameRate = IttFrameRate::new(24, (1000, 1001), false);
+
+    /// 24 fps.
+    pub const FPS_24: IttFrameRate = IttFrameRate::new(24, (1, 1), false);
+
+    /// 25 fps.
+    pub const FPS_25: IttFrameRate = IttFrameRate::new(25, (1, 1), false);
+
+    /// 29.97 fps with non-drop timecodes.
+    pub const FPS_29_97: IttFrameRate = IttFrameRate::new(30, (1000, 1001), false);
+
+    /// 29.97 fps with drop-frame timecodes.
+    pub const FPS_29_97_DROP: IttFrameRate = IttFrameRate::new(30, (1000, 1001), true);
+
+    /// 30 fps.
+    pub const FPS_30: IttFrameRate = IttFrameRate::new(30, (1, 1), false);
+
+    const fn new(frame_rate: u32, multiplier: (u32, u32), drop_frame: bool) -> IttFrameRate {
+        IttFrameRate {
+            frame_rate,
+            multiplier,
+            drop_frame,
+        }
+    }
+}
+
+impl Default for IttFrameRate {
+    /// 23.976 fps, the usual frame rate of movie deliveries.
+    fn default() -> IttFrameRate {
+        IttFrameRate::FPS_23_976
+    }
+}
+
+/// Represents an iTunes Timed Text (`.itt`) file, the TTML dialect of iTunes and Apple TV deliveries.
+///
+/// ITT files use the SMPTE time base (`ttp:timeBase="smpte"`), so the times are timecodes like `00:00:01:12`,
+/// which count frames of the `ttp:frameRate`. Everything else works like in a `TtmlFile`: the document is kept as
+/// it is and changed times are written as timecodes again.
+#[derive(Debug, Clone)]
+pub struct IttFile {
+    ttml: TtmlFile,
+}
+
+impl IttFile {
+    /// Parse an `.itt` subtitle string to `IttFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<IttFile> {
+        Ok(IttFile { ttml: TtmlFile::parse(s)? })
+    }
+
+    /// Parse an `.itt` subtitle string to `IttFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(IttFile, Warnings)> {
+        let (ttml, warnings) = TtmlFile::parse_with_warnings(s)?;
+        Ok((IttFile { ttml }, warnings))
+    }
+
+    /// Creates an `.itt` file with 23.976 fps and English as language.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<IttFile> {
+        Self::create_with(v, IttFrameRate::default(), "en")
+    }
+
+    /// Creates an `.itt` file with the given frame rate and language (like `en` or `de-DE`).
+    ///
+    /// The document has the default style and bottom region of the files which are exported by Apple's tools.
+    pub fn create_with(v: Vec<(TimeSpan, String)>, frame_rate: IttFrameRate, language: &str) -> SubtitleParserResult<IttFile> {
+        let (numerator, denominator) = frame_rate.multiplier;
+        let document = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+             <tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" \
+             xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" \
+             xml:lang=\"{}\" ttp:timeBase=\"smpte\" ttp:frameRate=\"{}\" ttp:frameRateMultiplier=\"{} {}\" ttp:dropMode=\"{}\">\n\
+             \x20 <head>\n\
+             \x20   <styling>\n\
+             \x20     <style xml:id=\"normal\" tts:fontFamily=\"sansSerif\" tts:fontWeight=\"normal\" tts:fontStyle=\"normal\" \
+             tts:color=\"white\" tts:fontSize=\"100%\"/>\n\
+             \x20   </styling>\n\
+             \x20   <layout>\n\
+             \x20     <region xml:id=\"bottom\" tts:origin=\"0% 85%\" tts:extent=\"100% 15%\" tts:textAlign=\"center\" \
+             tts:displayAlign=\"after\"/>\n\
+             \x20   </layout>\n\
+             \x20 </head>\n\
+             \x20 <body style=\"normal\" region=\"bottom\">\n\
+             \x20   <div>\n\
+             \x20   </div>\n\
+             \x20 </body>\n\
+             </tt>\n",
+            escape_xml(language),
+            frame_rate.frame_rate,
+            numerator,
+            denominator,
+            if frame_rate.drop_frame { "dropNTSC" } else { "nonDrop" }
+        );
+        Ok(IttFile {
+            ttml: TtmlFile::create_in(&document, v)?,
+        })
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.ttml.filler_parts()
+    }
+}
+
+impl SubtitleFileInterface for IttFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        self.ttml.get_subtitle_entries()
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        self.ttml.update_subtitle_entries(new_subtitle_entries)
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        self.ttml.to_data()
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        self.ttml.retain_entries_by_mask(keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::{TimeDelta, TimePoint};
+
+    #[test]
+    fn itt_timecodes() {
+        let input = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+                     <tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" xml:lang=\"en\" \
+                     ttp:timeBase=\"smpte\" ttp:frameRate=\"24\" ttp:frameRateMultiplier=\"1000 1001\" ttp:dropMode=\"nonDrop\">\n\
+                     <body><div>\n\
+                     <p begin=\"00:00:01:12\" end=\"00:00:03:00\">Hello</p>\n\
+                     <p begin=\"00:00:05:00\" end=\"00:00:06:23\">World</p>\n\
+                     </div></body>\n\
+                     </tt>\n";
+        let mut file = IttFile::parse(input).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), input);
+
+        let mut entries = file.get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1502, 3003), (5005, 6965)]);
+
+        entries[1].timespan += TimeDelta::from_msecs(1000);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("<p begin=\"00:00:06:00\" end=\"00:00:07:23\">World</p>"));
+
+        // 29.97 fps drop-frame timecodes skip the frame numbers 0 and 1 after the first minute
+        let start = TimePoint::from_msecs(60_060);
+        let created = IttFile::create_with(
+            vec![(TimeSpan::new(start, start + TimeDelta::from_msecs(1001)), "Drop".to_string())],
+            IttFrameRate::FPS_29_97_DROP,
+            "de-DE",
+        )
+        .unwrap();
+        let data = String::from_utf8(created.to_data().unwrap()).unwrap();
+        assert!(data.contains(
+            "xml:lang=\"de-DE\" ttp:timeBase=\"smpte\" ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"1000 1001\" ttp:dropMode=\"dropNTSC\""
+        ));
+        assert!(data.contains("<p begin=\"00:01:00:02\" end=\"00:01:01:02\">Drop</p>"));
+        let reparsed = IttFile::parse(&data).unwrap().get_subtitle_entries().unwrap();
+        assert_eq!(reparsed[0].timespan, TimeSpan::new(start, start + TimeDelta::from_msecs(1001)));
+    }
+
+    #[test]
+    fn itt_language() {
+        let span = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        let file = IttFile::create_with(vec![(span, "Hallo".to_string())], IttFrameRate::default(), "de").unwrap();
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].language.as_deref(), Some("de"));
+    }
+}
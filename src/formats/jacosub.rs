@@ -0,0 +1,501 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::timetypes::{RoundingMode, TimePoint, TimeSpan};
+
+/// Errors specific to JACOsub-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected JACOsub line like `0:00:01.00 0:00:02.15 D Hello`, found '{}'", line)]
+        ExpectedJacoLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// The number of time units per second if the file has no `#TIMERES` directive.
+const DEFAULT_TIMERES: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How the times of a subtitle are written.
+enum TimeSyntax {
+    /// `H:MM:SS.FF` where `FF` counts in units of the time resolution.
+    Clock,
+
+    /// `@123` in units of the time resolution.
+    Units,
+}
+
+#[derive(Debug, Clone)]
+/// A line of a JACOsub file.
+enum JacoLine {
+    /// An empty line, a directive like `#TIMERES 30`, a comment or a line which is not a subtitle (kept as it is).
+    Filler(String),
+
+    /// A subtitle like `0:00:01.00 0:00:02.15 VB Hello\nworld`.
+    Entry {
+        timespan: TimeSpan,
+        syntax: TimeSyntax,
+
+        /// The time units per second which were valid for this line.
+        timeres: i64,
+
+        /// Everything between the end time and the text (the directive codes with the surrounding whitespace).
+        directive: String,
+
+        /// The text as it is written in the file.
+        raw: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Represents a JACOsub file (`.jss`).
+///
+/// Every subtitle is a line like `0:00:01.00 0:00:02.15 D Hello\nworld`, where the fraction of the times counts
+/// in units of the `#TIMERES` directive (30 per second by default). Directive lines, comments and the
+/// directive codes in front of the texts are kept as they are, so retimed files keep their styling. The text
+/// codes `\n`, `\I`, `\B`, `\U` and `\N` are converted into line breaks and `<i>`/`<b>`/`<u>` tags and `{comments}`
+/// are removed from the entry texts. Texts are only rewritten if they change.
+pub struct JacoFile {
+    v: Vec<(JacoLine, String)>,
+}
+
+impl JacoFile {
+    /// Parse a JACOsub subtitle string to `JacoFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<JacoFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a JACOsub subtitle string to `JacoFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(JacoFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a JACOsub file from scratch (with the default directive `D`).
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<JacoFile> {
+        let lines = v
+            .into_iter()
+            .map(|(timespan, text)| {
+                (
+                    JacoLine::Entry {
+                        timespan,
+                        syntax: TimeSyntax::Clock,
+                        timeres: DEFAULT_TIMERES,
+                        directive: " D ".to_string(),
+                        raw: encode_text(&text),
+                    },
+                    "\n".to_string(),
+                )
+            })
+            .collect();
+        Ok(JacoFile { v: lines })
+    }
+
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<JacoFile> {
+        let (bom, s) = split_bom(i);
+
+        let mut result = Vec::new();
+        if !bom.is_empty() {
+            result.push((JacoLine::Filler(bom.to_string()), String::new()));
+        }
+        let mut timeres = DEFAULT_TIMERES;
+        let mut found_entry = false;
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            if line.trim_start().starts_with('#') {
+                timeres = parse_timeres(&line).unwrap_or(timeres);
+                result.push((JacoLine::Filler(line), newline));
+                continue;
+            }
+            match parse_line(&line, timeres) {
+                Some(entry) => {
+                    found_entry = true;
+                    result.push((entry, newline));
+                }
+                None if line.trim().is_empty() => result.push((JacoLine::Filler(line), newline)),
+                // a file whose first content isn't a subtitle or a directive is not a JACOsub file
+                None if !found_entry => {
+                    return Err(Error::from(ExpectedJacoLine { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                None => {
+                    warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+                    result.push((JacoLine::Filler(line), newline));
+                }
+            }
+        }
+        Ok(JacoFile { v: result })
+    }
+}
+
+/// Returns `true` if the first line which is not empty or a directive looks like a JACOsub subtitle.
+pub(crate) fn looks_like_jacosub(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    text.lines()
+        .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .is_some_and(|line| parse_line(line, DEFAULT_TIMERES).is_some())
+}
+
+/// Parses the time resolution of a directive line like `#TIMERES 25` or `#T25`.
+fn parse_timeres(line: &str) -> Option<i64> {
+    let directive = line.trim().trim_start_matches('#');
+    let upper = directive.to_ascii_uppercase();
+    let value = upper.strip_prefix("TIMERES").or_else(|| upper.strip_prefix('T'))?;
+    value.trim().parse::<i64>().ok().filter(|timeres| *timeres > 0)
+}
+
+/// Parses a time like `1:02:03.15` or `@1234` with the given time resolution.
+fn parse_time(s: &str, timeres: i64) -> Option<(TimePoint, TimeSyntax)> {
+    let units = |s: &str| {
+        Some(s)
+            .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))?
+            .parse::<i64>()
+            .ok()
+    };
+    if let Some(count) = s.strip_prefix('@') {
+        let msecs = units(count)?.checked_mul(1000)? / timeres;
+        return Some((TimePoint::from_msecs(msecs), TimeSyntax::Units));
+    }
+    let (clock, fraction) = s.split_once('.')?;
+    let mut secs: i64 = 0;
+    for part in clock.split(':') {
+        secs = secs.checked_mul(60)?.checked_add(units(part)?)?;
+    }
+    let fraction = units(fraction)?;
+    let msecs = secs.checked_mul(1000)?.checked_add(fraction.checked_mul(1000)? / timeres)?;
+    Some((TimePoint::from_msecs(msecs), TimeSyntax::Clock))
+}
+
+/// Formats a time with the given syntax and time resolution (negative times are written as zero).
+fn format_time(t: TimePoint, syntax: TimeSyntax, timeres: i64) -> String {
+    let msecs = t.msecs().max(0);
+    match syntax {
+        TimeSyntax::Units => format!("@{}", RoundingMode::Nearest.div(msecs.saturating_mul(timeres), 1000)),
+        TimeSyntax::Clock => {
+            let units = RoundingMode::Nearest.div(msecs.saturating_mul(timeres), 1000);
+            let (secs, fraction) = (units / timeres, units % timeres);
+            let width = timeres.saturating_sub(1).to_string().len().max(2);
+            format!(
+                "{}:{:02}:{:02}.{:0width$}",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                fraction,
+                width = width
+            )
+        }
+    }
+}
+
+/// Returns `true` if the word is a directive like `D`, `VB` or `JC` in front of the text.
+fn is_directive(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+/// Splits off the first word (after leading whitespace) and returns it with the rest of the string.
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_at(s.find(char::is_whitespace).unwrap_or(s.len()))
+}
+
+/// Parses a subtitle line like `0:00:01.00 0:00:02.15 D Hello`.
+fn parse_line(line: &str, timeres: i64) -> Option<JacoLine> {
+    let (start_word, rest) = split_word(line);
+    let (end_word, rest) = split_word(rest);
+    let (start, syntax) = parse_time(start_word, timeres)?;
+    let (end, _) = parse_time(end_word, timeres)?;
+
+    let (word, after_word) = split_word(rest);
+    let directive_len = if is_directive(word) {
+        rest.len() - after_word.trim_start().len()
+    } else {
+        rest.len() - rest.trim_start().len()
+    };
+    let (directive, raw) = rest.split_at(directive_len);
+    Some(JacoLine::Entry {
+        timespan: TimeSpan::new(start, end),
+        syntax,
+        timeres,
+        directive: directive.to_string(),
+        raw: raw.to_string(),
+    })
+}
+
+/// Converts the raw text of a subtitle into an entry text.
+fn decode_text(raw: &str) -> String {
+    let mut result = String::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut comment = false;
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => comment = true,
+            '}' if comment => comment = false,
+            _ if comment => {}
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some(code) if "IBU".contains(code) => {
+                    let tag = match code {
+                        'I' => "i",
+                        'B' => "b",
+                        _ => "u",
+                    };
+                    if open.contains(&tag) {
+                        open.retain(|open_tag| *open_tag != tag);
+                        result.push_str(&format!("</{}>", tag));
+                    } else {
+                        open.push(tag);
+                        result.push_str(&format!("<{}>", tag));
+                    }
+                }
+                Some('N') => {
+                    for tag in open.drain(..).rev() {
+                        result.push_str(&format!("</{}>", tag));
+                    }
+                }
+                Some(other) => result.push(other),
+                None => {}
+            },
+            '~' => result.push(' '),
+            c => result.push(c),
+        }
+    }
+    for tag in open.into_iter().rev() {
+        result.push_str(&format!("</{}>", tag));
+    }
+    result
+}
+
+/// Converts an entry text into the raw text of a subtitle.
+fn encode_text(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let code = [
+            ("<i>", "\\I"),
+            ("</i>", "\\I"),
+            ("<b>", "\\B"),
+            ("</b>", "\\B"),
+            ("<u>", "\\U"),
+            ("</u>", "\\U"),
+        ]
+        .iter()
+        .find(|(tag, _)| rest.starts_with(tag));
+        let len = match code {
+            Some((tag, code)) => {
+                result.push_str(code);
+                tag.len()
+            }
+            None => {
+                match c {
+                    '\n' => result.push_str("\\n"),
+                    '\\' => result.push_str("\\\\"),
+                    '{' | '}' | '~' => result.push_str(&format!("\\{}", c)),
+                    c => result.push(c),
+                }
+                c.len_utf8()
+            }
+        };
+        rest = rest.get(len..).unwrap_or("");
+    }
+    result
+}
+
+impl JacoFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .flat_map(|(line, newline)| {
+                let line = match *line {
+                    JacoLine::Filler(ref text) => Some(text.as_str()),
+                    JacoLine::Entry { .. } => None,
+                };
+                vec![line, Some(newline.as_str())]
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for JacoFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .filter_map(|(line, _)| match *line {
+                JacoLine::Filler(_) => None,
+                JacoLine::Entry { timespan, ref raw, .. } => Some(SubtitleEntry::new(timespan, decode_text(raw))),
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, JacoLine::Entry { .. })).count();
+        check_entry_count(count, new_subtitle_entries.len())?;
+
+        let entries = self.v.iter_mut().filter_map(|(line, _)| match *line {
+            JacoLine::Filler(_) => None,
+            JacoLine::Entry {
+                ref mut timespan,
+                ref mut raw,
+                ..
+            } => Some((timespan, raw)),
+        });
+        for ((timespan, raw), new_entry) in entries.zip(new_subtitle_entries) {
+            *timespan = new_entry.timespan;
+            if let Some(ref text) = new_entry.line {
+                if decode_text(raw) != *text {
+                    *raw = encode_text(text);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let result: String = self
+            .v
+            .iter()
+            .map(|(line, newline)| match *line {
+                JacoLine::Filler(ref text) => format!("{}{}", text, newline),
+                JacoLine::Entry {
+                    timespan,
+                    syntax,
+                    timeres,
+                    ref directive,
+                    ref raw,
+                } => format!(
+                    "{} {}{}{}{}",
+                    format_time(timespan.start, syntax, timeres),
+                    format_time(timespan.end, syntax, timeres),
+                    directive,
+                    raw,
+                    newline
+                ),
+            })
+            .collect();
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, JacoLine::Entry { .. })).count();
+        check_entry_count(count, keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|(line, _)| match *line {
+            JacoLine::Filler(_) => true,
+            JacoLine::Entry { .. } => keep_iter.next().cloned().unwrap_or(true),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+    use crate::timetypes::TimeDelta;
+
+    const FILE: &str = "#TITLE Test\r\n# a comment\r\n0:00:01.00 0:00:02.15 VB Hello\\nworld\r\n\r\n#TIMERES 100\r\n\
+                        0:00:03.50 0:00:04.75 D \\IItalic\\I {note}text\r\n@600 @700 JC Third\r\nbroken line\r\n";
+
+    #[test]
+    fn jacosub_directives_and_comments_are_kept() {
+        let (mut file, warnings) = JacoFile::parse_with_warnings(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(messages, vec!["line 7: ignored line 'broken line'"]);
+    }
+
+    #[test]
+    fn jacosub_time_resolution() {
+        // the fractions and `@` frames use the default time resolution (30) until the `#TIMERES` directive
+        let entries = JacoFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1000, 2500), (3500, 4750), (6000, 7000)]);
+
+        let entries = JacoFile::parse("@15 @45 a\n#TIMERES 10\n@15 @45 b\n")
+            .unwrap()
+            .get_subtitle_entries()
+            .unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(500, 1500), (1500, 4500)]);
+    }
+
+    #[test]
+    fn jacosub_formatting_codes() {
+        // `\n` is a line break, `\I` toggles italics and `{...}` comments are left out
+        let entries = JacoFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let texts: Vec<&str> = entries.iter().filter_map(|entry| entry.line.as_deref()).collect();
+        assert_eq!(texts, vec!["Hello\nworld", "<i>Italic</i> text", "Third"]);
+    }
+
+    #[test]
+    fn jacosub_times_keep_their_notation() {
+        let mut file = JacoFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        for entry in &mut entries {
+            entry.timespan += TimeDelta::from_msecs(1000);
+        }
+        entries[2].line = Some("<b>Bold</b>\nnew".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "#TITLE Test\r\n# a comment\r\n0:00:02.00 0:00:03.15 VB Hello\\nworld\r\n\r\n#TIMERES 100\r\n\
+             0:00:04.50 0:00:05.75 D \\IItalic\\I {note}text\r\n@700 @800 JC \\BBold\\B\\nnew\r\nbroken line\r\n"
+        );
+    }
+
+    #[test]
+    fn jacosub_removed_lines_keep_the_directives() {
+        let mut file = JacoFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[false, true, true]).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "#TITLE Test\r\n# a comment\r\n\r\n#TIMERES 100\r\n0:00:03.50 0:00:04.75 D \\IItalic\\I {note}text\r\n@600 @700 JC Third\r\nbroken line\r\n"
+        );
+    }
+
+    #[test]
+    fn jacosub_create() {
+        let span = TimeSpan::new(TimePoint::from_msecs(2000), TimePoint::from_msecs(3500));
+        let created = JacoFile::create(vec![(span, "Hi".to_string())]).unwrap();
+        assert_eq!(to_string(&created), "0:00:02.00 0:00:03.15 D Hi\n");
+    }
+
+    #[test]
+    fn jacosub_detection() {
+        assert!(looks_like_jacosub("#TITLE x\n@1 @2 Hi\n"));
+        assert!(!looks_like_jacosub("0:00:01.000,0:00:02.000\nHi\n"));
+        assert_eq!(parse_timeres("#T25"), Some(25));
+        assert_eq!(parse_timeres("#TIMERES 0"), None);
+    }
+
+    #[test]
+    fn jacosub_parse_errors() {
+        let parse = |s: &str| JacoFile::parse_file(s, &mut Warnings::new());
+        assert_eq!(parse_error(parse, "#TITLE x\nnot a subtitle\n").kind(), &ErrorAtLine { line_num: 1 });
+        // the hours overflow
+        assert_eq!(parse_error(parse, "99999999999999999:0.0 @1 x\n").kind(), &ErrorAtLine { line_num: 0 });
+    }
+}
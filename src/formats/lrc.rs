@@ -0,0 +1,644 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::common::*;
+use crate::delay::DelayOutput;
+use crate::durations::DurationSynthesis;
+use crate::errors::Result as SubtitleParserResult;
+use crate::warnings::{WarningKind, Warnings};
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+
+/// A time tag like `[01:23.45]` at the beginning of a line (or `<01:23.45>` before a word).
+#[derive(Debug, Clone, Copy)]
+struct LrcTag {
+    time: TimePoint,
+
+    /// The number of digits of the fraction (written like in the parsed file).
+    fraction_digits: usize,
+
+    /// The separator before the fraction (`.` or `:`).
+    separator: char,
+}
+
+impl Default for LrcTag {
+    fn default() -> LrcTag {
+        LrcTag {
+            time: TimePoint::from_msecs(0),
+            fraction_digits: 2,
+            separator: '.',
+        }
+    }
+}
+
+impl LrcTag {
+    /// Parses the content of a tag like `01:23.45`, `01:23:45`, `01:23.456` or `01:23`.
+    fn parse(s: &str) -> Option<LrcTag> {
+        let is_digits = |s: &str, min: usize, max: usize| (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+        let (mins, rest) = s.trim().split_once(':')?;
+        let (secs, separator, fraction) = match rest.find(['.', ':']) {
+            Some(idx) => {
+                let (secs, fraction) = rest.split_at(idx);
+                let mut chars = fraction.chars();
+                (secs, chars.next().unwrap_or('.'), chars.as_str())
+            }
+            None => (rest, '.', ""),
+        };
+        if !is_digits(mins, 1, 9) || !is_digits(secs, 2, 2) || !(fraction.is_empty() || is_digits(fraction, 1, 3)) {
+            return None;
+        }
+        let secs: i64 = secs.parse().ok().filter(|&secs| secs < 60)?;
+        let msecs = if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse::<i64>().ok()? * 10i64.pow(3 - fraction.len() as u32)
+        };
+        let time = TimePoint::from_components(0, mins.parse().ok()?, secs, msecs);
+        Some(LrcTag {
+            time,
+            fraction_digits: fraction.len(),
+            separator,
+        })
+    }
+
+    /// Formats the time with the precision of the parsed tag (without brackets), negative times are written as zero.
+    fn format(&self, time: TimePoint) -> String {
+        let msecs = time.msecs().max(0);
+        // a tag without fraction gets one if the time was changed to a fraction of a second
+        let digits = if self.fraction_digits == 0 && msecs % 1000 != 0 {
+            2
+        } else {
+            self.fraction_digits.min(3)
+        };
+        let scale = 10i64.pow(digits as u32);
+        let units = (msecs * scale + 500) / 1000;
+        let (secs, fraction) = (units / scale, units % scale);
+        let mut result = format!("{:02}:{:02}", secs / 60, secs % 60);
+        if digits > 0 {
+            result.push_str(&format!("{}{:0width$}", self.separator, fraction, width = digits));
+        }
+        result
+    }
+}
+
+/// A line with one or more time tags (`[00:12.00][00:45.00]Chorus` is shown twice).
+#[derive(Debug, Clone)]
+struct LrcLine {
+    tags: Vec<LrcTag>,
+
+    /// The lyrics after the tags, with the word tags of enhanced LRC files.
+    text: String,
+
+    newline: String,
+}
+
+#[derive(Debug, Clone)]
+enum LrcFilePart {
+    /// Metadata like `[ar:Artist]`, empty lines and lines without tags (with their line endings).
+    Filler(String),
+
+    /// An `[offset:+500]` line (with the line ending); positive offsets let the lyrics appear sooner.
+    Offset(TimeDelta, String),
+
+    /// A line with lyrics.
+    Line(LrcLine),
+}
+
+/// A word of an enhanced LRC file (`[00:12.00]<00:12.00>Never <00:12.50>gonna`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrcWord {
+    /// When the word is sung (the end is the start of the next word).
+    pub timespan: TimeSpan,
+
+    /// The word with the following whitespace.
+    pub text: String,
+}
+
+/// Represents a reconstructable `.lrc` (lyrics) file.
+///
+/// Every time tag of a line becomes a `SubtitleEntry`. LRC files only store when a line starts, so the end
+/// times are created by a `DurationSynthesis` (by default every line is shown until the next one starts) and are
+/// ignored by `update_subtitle_entries()`. The `[offset:...]` tag is applied to all times (see `delay::DelayOutput`).
+#[derive(Debug, Clone)]
+pub struct LrcFile {
+    v: Vec<LrcFilePart>,
+
+    /// How the end times of the lines are created
+    durations: DurationSynthesis,
+
+    /// Whether the `[offset:...]` line is kept in `to_data()`
+    delay_output: DelayOutput,
+}
+
+impl LrcFile {
+    fn new(v: Vec<LrcFilePart>) -> LrcFile {
+        // cleans up multiple fillers after another
+        let new_file_parts = dedup_string_parts(v, |part: &mut LrcFilePart| match *part {
+            LrcFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        LrcFile {
+            v: new_file_parts,
+            durations: DurationSynthesis::LRC,
+            delay_output: DelayOutput::default(),
+        }
+    }
+
+    /// Parse a `.lrc` subtitle string to `LrcFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<LrcFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.lrc` subtitle string to `LrcFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(LrcFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_inner(s, &mut warnings);
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates .lrc file from scratch (the end times are lost).
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<LrcFile> {
+        let lines = v
+            .into_iter()
+            .map(|(timespan, text)| {
+                LrcFilePart::Line(LrcLine {
+                    tags: vec![LrcTag {
+                        time: timespan.start,
+                        ..LrcTag::default()
+                    }],
+                    text: lrc_text(&text),
+                    newline: "\n".to_string(),
+                })
+            })
+            .collect();
+        Ok(LrcFile::new(lines))
+    }
+
+    /// Sets how the end times of the lines are created (default: `DurationSynthesis::LRC`).
+    pub fn set_duration_synthesis(&mut self, durations: DurationSynthesis) {
+        self.durations = durations;
+    }
+
+    /// Sets whether the `[offset:...]` line is kept when writing the file (default: `DelayOutput::KeepMetadata`).
+    pub fn set_delay_output(&mut self, delay_output: DelayOutput) {
+        self.delay_output = delay_output;
+    }
+
+    /// Returns the value of a metadata tag like `[ar:Artist]` (`metadata("ar")`).
+    pub fn metadata(&self, key: &str) -> Option<String> {
+        self.v.iter().find_map(|part| match *part {
+            LrcFilePart::Filler(ref text) => text.lines().find_map(|line| {
+                let (tag_key, value) = line.trim().strip_prefix('[')?.strip_suffix(']')?.split_once(':')?;
+                Some(value.trim().to_string()).filter(|_| tag_key.trim().eq_ignore_ascii_case(key))
+            }),
+            _ => None,
+        })
+    }
+
+    /// Returns the words of every entry (empty for lines without word tags), in the order of `get_subtitle_entries()`.
+    pub fn words(&self) -> SubtitleParserResult<Vec<Vec<LrcWord>>> {
+        let offset = self.offset();
+        let entries = self.get_subtitle_entries()?;
+        let lines = self.lines().flat_map(|line| line.tags.iter().map(move |_| line));
+        Ok(lines
+            .zip(entries)
+            .map(|(line, entry)| {
+                let segments = split_word_tags(&line.text);
+                let mut words: Vec<LrcWord> = Vec::new();
+                for (i, (start, text)) in segments.iter().enumerate() {
+                    let start = match *start {
+                        Some(start) => start - offset,
+                        None => continue,
+                    };
+                    if text.is_empty() && i + 1 == segments.len() {
+                        // a tag after the last word is the end of the last word
+                        if let Some(last) = words.last_mut() {
+                            last.timespan.end = start;
+                        }
+                        continue;
+                    }
+                    if let Some(last) = words.last_mut() {
+                        last.timespan.end = start;
+                    }
+                    words.push(LrcWord {
+                        timespan: TimeSpan::new(start, entry.timespan.end.max(start)),
+                        text: text.to_string(),
+                    });
+                }
+                words
+            })
+            .collect())
+    }
+
+    /// Replaces the text of an entry by words with word tags (does nothing if there is no entry `entry_idx`).
+    ///
+    /// The end of the last word is written as a tag after it. If the line is shown several times (like
+    /// `[00:12.00][00:45.00]Chorus`), the entry gets its own line.
+    pub fn set_words(&mut self, entry_idx: usize, words: &[LrcWord]) {
+        let offset = self.offset();
+        if let Some(line) = self.detach(entry_idx) {
+            let tag = line.tags.first().copied().unwrap_or_default();
+            let mut text: String = words
+                .iter()
+                .map(|word| format!("<{}>{}", tag.format(word.timespan.start + offset), word.text.replace(['\n', '\r'], " ")))
+                .collect();
+            if let Some(last) = words.last() {
+                text.push_str(&format!("<{}>", tag.format(last.timespan.end + offset)));
+            }
+            line.text = text;
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &LrcLine> {
+        self.v.iter().filter_map(|part| match *part {
+            LrcFilePart::Line(ref line) => Some(line),
+            _ => None,
+        })
+    }
+
+    /// Returns the offset of the `[offset:...]` line (the first one if there are several).
+    fn offset(&self) -> TimeDelta {
+        self.v
+            .iter()
+            .find_map(|part| match *part {
+                LrcFilePart::Offset(offset, _) => Some(offset),
+                _ => None,
+            })
+            .unwrap_or_else(|| TimeDelta::from_msecs(0))
+    }
+
+    /// Moves the tag of an entry into its own line (if the line has other tags) and returns that line.
+    ///
+    /// The other tags stay in lines before and after it, so the order of the entries does not change.
+    fn detach(&mut self, entry_idx: usize) -> Option<&mut LrcLine> {
+        let mut remaining = entry_idx;
+        let (part_idx, tag_idx) = self.v.iter().enumerate().find_map(|(part_idx, part)| match *part {
+            LrcFilePart::Line(ref line) if remaining < line.tags.len() => Some((part_idx, remaining)),
+            LrcFilePart::Line(ref line) => {
+                remaining -= line.tags.len();
+                None
+            }
+            _ => None,
+        })?;
+
+        let mut line_idx = part_idx;
+        if let Some(LrcFilePart::Line(line)) = self.v.get(part_idx).cloned() {
+            if line.tags.len() > 1 {
+                let (before, rest) = line.tags.split_at(tag_idx);
+                let (tag, after) = rest.split_at(1.min(rest.len()));
+                let inner_newline = if line.newline.is_empty() {
+                    "\n".to_string()
+                } else {
+                    line.newline.clone()
+                };
+                let groups: Vec<&[LrcTag]> = vec![before, tag, after].into_iter().filter(|tags| !tags.is_empty()).collect();
+                let count = groups.len();
+                let lines = groups.into_iter().enumerate().map(|(i, tags)| {
+                    LrcFilePart::Line(LrcLine {
+                        tags: tags.to_vec(),
+                        text: line.text.clone(),
+                        newline: if i + 1 == count { line.newline.clone() } else { inner_newline.clone() },
+                    })
+                });
+                self.v.splice(part_idx..=part_idx, lines);
+                line_idx += usize::from(!before.is_empty());
+            }
+        }
+        match self.v.get_mut(line_idx) {
+            Some(LrcFilePart::Line(ref mut line)) => Some(line),
+            _ => None,
+        }
+    }
+
+    /// Adds the offset to the tags and removes the `[offset:...]` lines.
+    fn bake_offset(&mut self) {
+        let offset = self.offset();
+        for part in &mut self.v {
+            if let LrcFilePart::Line(ref mut line) = *part {
+                for tag in &mut line.tags {
+                    tag.time -= offset;
+                }
+                let tag = line.tags.first().copied().unwrap_or_default();
+                line.text = split_word_tags(&line.text)
+                    .into_iter()
+                    .map(|(start, text)| match start {
+                        Some(start) => format!("<{}>{}", tag.format(start - offset), text),
+                        None => text.to_string(),
+                    })
+                    .collect();
+            }
+        }
+        self.v.retain(|part| !matches!(part, LrcFilePart::Offset(..)));
+        self.v = LrcFile::new(std::mem::take(&mut self.v)).v;
+    }
+}
+
+// implement parsing functions
+impl LrcFile {
+    fn parse_inner(i: &str, warnings: &mut Warnings) -> LrcFile {
+        let (bom, s) = split_bom(i);
+        let mut result = vec![LrcFilePart::Filler(bom.to_string())];
+
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            let trimmed = line.trim();
+            let mut rest = line.trim_start();
+            let mut tags = Vec::new();
+            while let Some((content, after)) = rest.strip_prefix('[').and_then(|inner| inner.split_once(']')) {
+                match LrcTag::parse(content) {
+                    Some(tag) => tags.push(tag),
+                    None => break,
+                }
+                rest = after;
+            }
+
+            if !tags.is_empty() {
+                result.push(LrcFilePart::Line(LrcLine {
+                    tags,
+                    text: rest.to_string(),
+                    newline,
+                }));
+                continue;
+            }
+
+            let metadata = trimmed
+                .strip_prefix('[')
+                .and_then(|inner| inner.strip_suffix(']'))
+                .and_then(|inner| inner.split_once(':'));
+            let offset = metadata
+                .filter(|(key, _)| key.trim().eq_ignore_ascii_case("offset"))
+                .and_then(|(_, value)| value.trim().trim_start_matches('+').parse::<i64>().ok());
+            match offset {
+                Some(offset) => result.push(LrcFilePart::Offset(TimeDelta::from_msecs(offset), format!("{}{}", line, newline))),
+                None => {
+                    if metadata.is_none() && !trimmed.is_empty() {
+                        warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+                    }
+                    result.push(LrcFilePart::Filler(format!("{}{}", line, newline)));
+                }
+            }
+        }
+
+        LrcFile::new(result)
+    }
+}
+
+/// Returns `true` if the line starts with a time tag like `[00:12.00]`.
+pub(crate) fn starts_with_time_tag(line: &str) -> bool {
+    let content = line.trim_start().strip_prefix('[').and_then(|inner| inner.split_once(']'));
+    content.is_some_and(|(content, _)| LrcTag::parse(content).is_some())
+}
+
+/// Splits a text at the word tags like `<00:12.50>` (the first part has no time if the text does not start with a tag).
+fn split_word_tags(text: &str) -> Vec<(Option<TimePoint>, &str)> {
+    let mut result = Vec::new();
+    let mut start = None;
+    let mut rest = text;
+    let mut segment_start = 0;
+    let mut pos = 0;
+    while let Some(idx) = rest.find('<') {
+        let tag = rest
+            .get(idx + 1..)
+            .and_then(|after| after.split_once('>'))
+            .and_then(|(content, _)| Some((LrcTag::parse(content)?, content.len())));
+        match tag {
+            Some((tag, len)) => {
+                let segment = text.get(segment_start..pos + idx).unwrap_or("");
+                if start.is_some() || !segment.is_empty() {
+                    result.push((start, segment));
+                }
+                start = Some(tag.time);
+                pos += idx + len + 2;
+                segment_start = pos;
+            }
+            None => pos += idx + 1,
+        }
+        rest = text.get(pos..).unwrap_or("");
+    }
+    result.push((start, text.get(segment_start..).unwrap_or("")));
+    result
+}
+
+/// Returns the text without word tags.
+fn plain_text(text: &str) -> String {
+    split_word_tags(text)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Returns an entry text as a single line of lyrics.
+fn lrc_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+impl LrcFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                LrcFilePart::Filler(ref text) => Some(text.as_str()),
+                LrcFilePart::Offset(..) | LrcFilePart::Line(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for LrcFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let offset = self.offset();
+        let texts: Vec<String> = self
+            .lines()
+            .flat_map(|line| line.tags.iter().map(move |_| plain_text(&line.text)))
+            .collect();
+        let starts = self.lines().flat_map(|line| line.tags.iter().map(|tag| tag.time - offset));
+        let cues: Vec<(TimePoint, Option<&str>)> = starts.zip(texts.iter().map(|text| Some(text.as_str()))).collect();
+
+        // .lrc files do not store timespans (by default every line is shown until the next line starts)
+        let timespans = self.durations.timespans(&cues);
+        Ok(timespans
+            .into_iter()
+            .zip(texts)
+            .map(|(timespan, text)| SubtitleEntry::new(timespan, text))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, ts: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.lines().map(|line| line.tags.len()).sum(), ts.len())?;
+
+        // lines with a changed text are detached first (from the back, so the entry indices stay valid)
+        let changed: Vec<usize> = self
+            .lines()
+            .flat_map(|line| line.tags.iter().map(move |_| plain_text(&line.text)))
+            .zip(ts)
+            .enumerate()
+            .filter(|(_, (text, entry))| entry.line.as_ref().is_some_and(|line| *line != *text))
+            .map(|(entry_idx, _)| entry_idx)
+            .collect();
+        for &entry_idx in changed.iter().rev() {
+            if let Some(line) = self.detach(entry_idx) {
+                line.text = ts
+                    .get(entry_idx)
+                    .and_then(|entry| entry.line.as_ref())
+                    .map(|text| lrc_text(text))
+                    .unwrap_or_default();
+            }
+        }
+
+        let offset = self.offset();
+        let tags = self.v.iter_mut().flat_map(|part| match *part {
+            LrcFilePart::Line(ref mut line) => line.tags.iter_mut(),
+            _ => [].iter_mut(),
+        });
+        for (tag, entry) in tags.zip(ts) {
+            tag.time = entry.timespan.start + offset;
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        if self.delay_output == DelayOutput::Bake && self.v.iter().any(|part| matches!(part, LrcFilePart::Offset(..))) {
+            let mut baked = self.clone();
+            baked.bake_offset();
+            return baked.to_data();
+        }
+
+        let mut result = String::new();
+        for part in &self.v {
+            match *part {
+                LrcFilePart::Filler(ref text) | LrcFilePart::Offset(_, ref text) => result.push_str(text),
+                LrcFilePart::Line(ref line) => {
+                    for tag in &line.tags {
+                        result.push_str(&format!("[{}]", tag.format(tag.time)));
+                    }
+                    result.push_str(&line.text);
+                    result.push_str(&line.newline);
+                }
+            }
+        }
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.lines().map(|line| line.tags.len()).sum(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        for part in &mut self.v {
+            if let LrcFilePart::Line(ref mut line) = *part {
+                line.tags.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+            }
+        }
+        self.v.retain(|part| !matches!(part, LrcFilePart::Line(line) if line.tags.is_empty()));
+        self.v = LrcFile::new(std::mem::take(&mut self.v)).v;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::to_string;
+
+    const FILE: &str = "[ar:Someone]\n[offset:+500]\n\n[00:12.00][00:45.00]Chorus\n[00:20.50]<00:20.50>Never <00:21.00>gonna<00:21.75>\n[01:00]\n";
+
+    #[test]
+    fn lrc_metadata() {
+        let file = LrcFile::parse(FILE).unwrap();
+        assert_eq!(file.metadata("AR").as_deref(), Some("Someone"));
+        assert_eq!(file.metadata("ti"), None);
+    }
+
+    #[test]
+    fn lrc_lines_with_offset() {
+        // a line with two time tags is two entries, and every line ends when the next one starts
+        let entries = LrcFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(11500, 20000), (44500, 59500), (20000, 44500), (59500, 64500)]);
+        assert_eq!(entries[1].line.as_deref(), Some("Chorus"));
+        assert_eq!(entries[2].line.as_deref(), Some("Never gonna"));
+    }
+
+    #[test]
+    fn lrc_word_tags() {
+        let words = LrcFile::parse(FILE).unwrap().words().unwrap();
+        assert_eq!(
+            words[2]
+                .iter()
+                .map(|w| (w.text.as_str(), w.timespan.start.msecs(), w.timespan.end.msecs()))
+                .collect::<Vec<_>>(),
+            vec![("Never ", 20000, 20500), ("gonna", 20500, 21250)]
+        );
+        assert!(words[0].is_empty());
+    }
+
+    #[test]
+    fn lrc_update_entries() {
+        // the unchanged line keeps its word tags, the changed chorus gets its own line
+        let mut file = LrcFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[1].line = Some("Last\nchorus".to_string());
+        entries[2].timespan.start = TimePoint::from_msecs(20010);
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "[ar:Someone]\n[offset:+500]\n\n[00:12.00]Chorus\n[00:45.00]Last chorus\n[00:20.51]<00:20.50>Never <00:21.00>gonna<00:21.75>\n[01:00]\n"
+        );
+    }
+
+    #[test]
+    fn lrc_entry_count_mismatch() {
+        let mut file = LrcFile::parse(FILE).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        let error = file.update_subtitle_entries(&entries[..3]).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::EntryCountMismatch { expected: 4, found: 3 });
+        let error = file.retain_entries_by_mask(&[true]).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::EntryCountMismatch { expected: 4, found: 1 });
+        assert_eq!(to_string(&file), FILE);
+    }
+
+    #[test]
+    fn lrc_retain_entries() {
+        let mut file = LrcFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[true, false, true, true]).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "[ar:Someone]\n[offset:+500]\n\n[00:12.00]Chorus\n[00:20.50]<00:20.50>Never <00:21.00>gonna<00:21.75>\n[01:00]\n"
+        );
+    }
+
+    #[test]
+    fn lrc_set_words_and_bake_delay() {
+        let mut file = LrcFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[true, false, true, true]).unwrap();
+        file.set_words(
+            0,
+            &[LrcWord {
+                timespan: TimeSpan::new(TimePoint::from_msecs(11500), TimePoint::from_msecs(12000)),
+                text: "Chorus".to_string(),
+            }],
+        );
+        file.set_delay_output(DelayOutput::Bake);
+        assert_eq!(
+            to_string(&file),
+            "[ar:Someone]\n\n[00:11.50]<00:11.50>Chorus<00:12.00>\n[00:20.00]<00:20.00>Never <00:20.50>gonna<00:21.25>\n[00:59.50]\n"
+        );
+    }
+
+    #[test]
+    fn lrc_unknown_lines_are_kept() {
+        let input = "no tag\n[xx:yy]not a time\n[00:01.00]a\n";
+        let file = LrcFile::parse(input).unwrap();
+        assert_eq!(to_string(&file), input);
+        assert_eq!(file.get_subtitle_entries().unwrap().len(), 1);
+    }
+}
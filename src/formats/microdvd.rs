@@ -7,7 +7,9 @@ use self::errors::*;
 use crate::{SubtitleEntry, SubtitleFileInterface};
 
 use crate::errors::Result as SubtitleParserResult;
+use crate::escaping::mdvd_text;
 use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
 use combine::char::char;
 use combine::combinator::{eof, many, parser as p, satisfy, sep_by};
 use combine::primitives::Parser;
@@ -18,7 +20,8 @@ use std::collections::HashSet;
 
 use failure::ResultExt;
 
-use crate::timetypes::{TimePoint, TimeSpan};
+use crate::durations::DurationSynthesis;
+use crate::timetypes::{RoundingMode, TimeDelta, TimePoint, TimeSpan};
 use std::collections::LinkedList;
 
 /// Errors specific to `.sub`(`MicroDVD`)-parsing.
@@ -55,7 +58,7 @@ impl From<String> for MdvdFormatting {
 impl MdvdFormatting {
     /// Is this a single line formatting (e.g. `y:i`) or a multi-line formatting (e.g `Y:i`)?
     fn is_container_line_formatting(f: &str) -> bool {
-        f.chars().next().and_then(|c| Some(c.is_uppercase())).unwrap_or(false)
+        f.chars().next().map(|c| c.is_uppercase()).unwrap_or(false)
     }
 
     /// Applies `to_lowercase()` to first char, leaves the rest of the characters untouched.
@@ -100,6 +103,12 @@ pub struct MdvdFile {
     /// -> start/end frames can be coverted to timestamps
     fps: f64,
 
+    /// How times are converted to and from frames
+    rounding: RoundingMode,
+
+    /// How the end times of lines without end frame are created
+    open_ends: DurationSynthesis,
+
     /// all lines and multilines
     v: Vec<MdvdLine>,
 }
@@ -110,8 +119,8 @@ struct MdvdLine {
     /// The start frame.
     start_frame: i64,
 
-    /// The end frame.
-    end_frame: i64,
+    /// The end frame; `None` for lines like `{100}{0}Text` which are shown until the next subtitle.
+    end_frame: Option<i64>,
 
     /// Formatting that affects all contained single lines.
     formatting: Vec<MdvdFormatting>,
@@ -121,40 +130,99 @@ struct MdvdLine {
 }
 
 impl MdvdLine {
-    fn to_subtitle_entry(&self, fps: f64) -> SubtitleEntry {
-        SubtitleEntry {
-            timespan: TimeSpan::new(
-                TimePoint::from_msecs((self.start_frame as f64 * 1000.0 / fps) as i64),
-                TimePoint::from_msecs((self.end_frame as f64 * 1000.0 / fps) as i64),
-            ),
-            line: Some(self.text.clone()),
-        }
+    fn to_subtitle_entry(&self, end_frame: i64, fps: f64, rounding: RoundingMode) -> SubtitleEntry {
+        let timespan = TimeSpan::new(
+            TimePoint::from_frame(self.start_frame, fps, rounding),
+            TimePoint::from_frame(end_frame, fps, rounding),
+        );
+        let mut entry = SubtitleEntry::new(timespan, self.text.clone());
+        entry.flags.italic |= self.formatting.iter().any(|f| match f {
+            MdvdFormatting::Unknown(f) => f.strip_prefix("y:").is_some_and(|styles| styles.split(',').any(|s| s.trim() == "i")),
+        });
+        entry
     }
 }
 
 impl MdvdFile {
     /// Parse a `MicroDVD` `.sub` subtitle string to `MdvdFile`.
     pub fn parse(s: &str, fps: f64) -> SubtitleParserResult<MdvdFile> {
-        Ok(Self::parse_file(s, fps).with_context(|_| crate::ErrorKind::ParsingError)?)
+        Ok(Self::parse_with_warnings(s, fps)?.0)
+    }
+
+    /// Parse a `MicroDVD` `.sub` subtitle string to `MdvdFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str, fps: f64) -> SubtitleParserResult<(MdvdFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, fps, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Sets how times are converted to and from frames (default: `RoundingMode::Nearest`).
+    pub fn set_rounding_mode(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    /// Sets how the end times of lines without end frame (`{100}{0}Text`) are created.
+    ///
+    /// By default these lines are shown until the next subtitle starts, and the last one for three seconds.
+    pub fn set_duration_synthesis(&mut self, open_ends: DurationSynthesis) {
+        self.open_ends = open_ends;
+    }
+
+    /// Inserts a new line without formatting at position `idx` (or at the end).
+    pub(crate) fn insert_entry(&mut self, idx: usize, timespan: TimeSpan, text: &str) {
+        let line = MdvdLine {
+            start_frame: timespan.start.to_frame(self.fps, self.rounding),
+            end_frame: Some(timespan.end.to_frame(self.fps, self.rounding)),
+            formatting: Vec::new(),
+            text: mdvd_text(text),
+        };
+        self.v.insert(idx.min(self.v.len()), line);
+    }
+
+    /// Returns the end frame of every line (see `set_duration_synthesis()` for lines without end frame).
+    fn end_frames(&self) -> Vec<i64> {
+        let cues: Vec<(TimePoint, Option<&str>)> = self
+            .v
+            .iter()
+            .map(|line| (TimePoint::from_frame(line.start_frame, self.fps, self.rounding), Some(line.text.as_str())))
+            .collect();
+        self.v
+            .iter()
+            .zip(self.open_ends.timespans(&cues))
+            .map(|(line, timespan)| line.end_frame.unwrap_or_else(|| timespan.end.to_frame(self.fps, self.rounding)))
+            .collect()
     }
 }
 
 /// Implements parse functions.
 impl MdvdFile {
-    fn parse_file(i: &str, fps: f64) -> Result<MdvdFile> {
+    fn parse_file(i: &str, fps: f64, warnings: &mut Warnings) -> Result<MdvdFile> {
         let mut result: Vec<MdvdLine> = Vec::new();
 
         // remove utf-8 bom
         let (_, s) = split_bom(i);
 
         for (line_num, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.to_string() });
+                continue;
+            }
+
             // a line looks like "{0}{25}{c:$0000ff}{y:b,u}{f:DeJaVuSans}{s:12}Hello!|{y:i}Hello2!" where
             // 0 and 25 are the start and end frames and the other information is the formatting.
             let mut lines: Vec<MdvdLine> = Self::parse_line(line_num, line)?;
             result.append(&mut lines);
         }
 
-        Ok(MdvdFile { fps: fps, v: result })
+        Ok(MdvdFile {
+            fps,
+            rounding: RoundingMode::default(),
+            open_ends: DurationSynthesis::NextCue {
+                last: TimeDelta::from_secs(3),
+            },
+            v: result,
+        })
     }
 
     // Parses something like "{0}{25}{C:$0000ff}{y:b,u}{f:DeJaVuSans}{s:12}Hello!|{s:15}Hello2!"
@@ -181,6 +249,8 @@ impl MdvdFile {
         )
             .map(|(_, start_frame, _, _, end_frame, _, fmt_strs_and_lines, ())| (start_frame, end_frame, fmt_strs_and_lines))
             .map(|(start_frame, end_frame, fmt_strs_and_lines): (i64, i64, Vec<(Vec<String>, String)>)| {
+                // `{100}{0}` means "until the next subtitle"
+                let end_frame = Some(end_frame).filter(|&end_frame| end_frame != 0);
                 Self::construct_mdvd_lines(start_frame, end_frame, fmt_strs_and_lines)
             })
             .parse(line)
@@ -195,7 +265,7 @@ impl MdvdFile {
     ///
     /// The third parameter is for the example
     /// like `[(["C:$0000ff", "y:b,u", "f:DeJaVuSans", "s:12"], "Hello!"), (["s:15"], "Hello2!")].
-    fn construct_mdvd_lines(start_frame: i64, end_frame: i64, fmt_strs_and_lines: Vec<(Vec<String>, String)>) -> Vec<MdvdLine> {
+    fn construct_mdvd_lines(start_frame: i64, end_frame: Option<i64>, fmt_strs_and_lines: Vec<(Vec<String>, String)>) -> Vec<MdvdLine> {
         // saves all multiline formatting
         let mut cline_fmts: Vec<MdvdFormatting> = Vec::new();
 
@@ -211,10 +281,10 @@ impl MdvdFile {
         fmts_and_lines
             .into_iter()
             .map(|(sline_fmts, text)| MdvdLine {
-                start_frame: start_frame,
-                end_frame: end_frame,
-                text: text,
-                formatting: cline_fmts.clone().into_iter().chain(sline_fmts.into_iter()).collect(),
+                start_frame,
+                end_frame,
+                text,
+                formatting: cline_fmts.clone().into_iter().chain(sline_fmts).collect(),
             })
             .collect()
     }
@@ -235,21 +305,37 @@ impl MdvdFile {
 
 impl SubtitleFileInterface for MdvdFile {
     fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
-        Ok(self.v.iter().map(|line| line.to_subtitle_entry(self.fps)).collect())
+        Ok(self
+            .v
+            .iter()
+            .zip(self.end_frames())
+            .map(|(line, end_frame)| line.to_subtitle_entry(end_frame, self.fps, self.rounding))
+            .collect())
     }
 
     fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
-        assert_eq!(new_subtitle_entries.len(), self.v.len());
-
-        let mut iter = new_subtitle_entries.iter().peekable();
-        for line in &mut self.v {
-            let peeked = iter.next().unwrap();
-
-            line.start_frame = (peeked.timespan.start.secs_f64() * self.fps) as i64;
-            line.end_frame = (peeked.timespan.end.secs_f64() * self.fps) as i64;
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
 
+        let old_end_frames = self.end_frames();
+        for (line, peeked) in self.v.iter_mut().zip(new_subtitle_entries) {
+            line.start_frame = peeked.timespan.start.to_frame(self.fps, self.rounding);
             if let Some(ref text) = peeked.line {
-                line.text = text.clone();
+                line.text = mdvd_text(text);
+            }
+        }
+
+        // lines stay open-ended as long as their end is not changed or moves along with the new start times
+        let synthesized_end_frames = self.end_frames();
+        for (((line, peeked), old_end_frame), synthesized_end_frame) in self
+            .v
+            .iter_mut()
+            .zip(new_subtitle_entries)
+            .zip(old_end_frames)
+            .zip(synthesized_end_frames)
+        {
+            let end_frame = peeked.timespan.end.to_frame(self.fps, self.rounding);
+            if line.end_frame.is_some() || (end_frame != old_end_frame && end_frame != synthesized_end_frame) {
+                line.end_frame = Some(end_frame);
             }
         }
 
@@ -293,7 +379,7 @@ impl SubtitleFileInterface for MdvdFile {
                         None => Some(set.clone()),
                         Some(acc_set) => Some(acc_set.intersection(set).cloned().collect()),
                     })
-                    .unwrap()
+                    .unwrap_or_default()
             };
 
             let individual_formattings = formattings
@@ -306,7 +392,7 @@ impl SubtitleFileInterface for MdvdFile {
             result.push_back("}".into());
 
             result.push_back("{".into());
-            result.push_back(end_frame.to_string().into());
+            result.push_back(end_frame.unwrap_or(0).to_string().into());
             result.push_back("}".into());
 
             for formatting in &common_formatting {
@@ -315,7 +401,7 @@ impl SubtitleFileInterface for MdvdFile {
                 result.push_back("}".into());
             }
 
-            for (i, (individual_formatting, text)) in individual_formattings.into_iter().zip(texts.into_iter()).enumerate() {
+            for (i, (individual_formatting, text)) in individual_formattings.into_iter().zip(texts).enumerate() {
                 if i != 0 {
                     result.push_back("|".into());
                 }
@@ -334,6 +420,14 @@ impl SubtitleFileInterface for MdvdFile {
 
         Ok(result.into_iter().map(|cow| cow.to_string()).collect::<String>().into_bytes())
     }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -374,4 +468,60 @@ mod tests {
         // these can't be condensed, because the lines have different times
         test_mdvd("{0}{25}{y:i}Text1\n{0}{26}{y:i}Text2", "{0}{25}{y:i}Text1\n{0}{26}{y:i}Text2");
     }
+
+    #[test]
+    fn mdvd_update_keeps_frames() {
+        let mut file = MdvdFile::parse("{1}{2}a\n{1001}{1049}b", 23.976).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), "{1}{2}a\n{1001}{1049}b");
+    }
+
+    #[test]
+    fn mdvd_until_next_subtitle() {
+        let mut file = MdvdFile::parse("{10}{0}a|b\n{50}{75}c\n{100}{0}d", 25.0).unwrap();
+        let frames = |file: &MdvdFile| -> Vec<(i64, i64)> {
+            let entries = file.get_subtitle_entries().unwrap();
+            entries
+                .iter()
+                .map(|e| {
+                    (
+                        e.timespan.start.to_frame(25.0, RoundingMode::Nearest),
+                        e.timespan.end.to_frame(25.0, RoundingMode::Nearest),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(frames(&file), vec![(10, 50), (10, 50), (50, 75), (100, 175)]);
+
+        // unchanged ends are written as `{0}` again
+        let entries = file.get_subtitle_entries().unwrap();
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), "{10}{0}a|b\n{50}{75}c\n{100}{0}d");
+
+        let srt = crate::SrtFile::create(entries.into_iter().map(|e| (e.timespan, e.line.unwrap())).collect()).unwrap();
+        assert!(String::from_utf8(srt.to_data().unwrap())
+            .unwrap()
+            .starts_with("1\n00:00:00,400 --> 00:00:02,000\na"));
+    }
+
+    #[test]
+    fn mdvd_shift_keeps_open_ends() {
+        let mut file = MdvdFile::parse("{10}{0}a|b\n{50}{75}c\n{100}{0}d", 25.0).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        for entry in &mut entries {
+            entry.timespan += TimeDelta::from_secs(1);
+        }
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), "{35}{0}a|b\n{75}{100}c\n{125}{0}d");
+    }
+
+    #[test]
+    fn mdvd_changed_open_end() {
+        let mut file = MdvdFile::parse("{10}{0}a\n{50}{75}b", 25.0).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].timespan.end = TimePoint::from_frame(40, 25.0, RoundingMode::Nearest);
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), "{10}{40}a\n{50}{75}b");
+    }
 }
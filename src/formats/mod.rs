@@ -2,19 +2,47 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod aqtitle;
+pub mod bdn;
 pub mod common;
+pub mod dvb;
 pub mod idx;
+pub mod itt;
+pub mod jacosub;
+pub mod lrc;
 pub mod microdvd;
+pub mod mpl2;
+pub mod pac;
+pub mod pgs;
+pub mod powerdivx;
+pub mod realtext;
+pub mod sami;
+pub mod sbv;
+pub mod scc;
+pub mod spruce;
 pub mod srt;
+pub mod srv3;
 pub mod ssa;
+pub mod ssa_color;
+pub mod ssa_override;
+pub mod stl;
+pub mod subviewer;
+pub mod ttml;
 pub mod vobsub;
+pub mod vplayer;
+pub mod vtt;
 
 use crate::errors::*;
+use crate::language::{entry_language, language_matches};
+use crate::registry::{registered_formats, ExternalSubtitleFile};
+use crate::retiming::Retiming;
+use crate::timetypes::TimeSpan;
+use crate::warnings::Warnings;
 use crate::SubtitleEntry;
 use crate::SubtitleFileInterface;
+use chardet::{charset2encoding, detect};
 use encoding_rs::Encoding;
 use std::ffi::OsStr;
-use chardet::{charset2encoding, detect};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// All formats which are supported by this library.
@@ -33,6 +61,68 @@ pub enum SubtitleFormat {
 
     /// .sub file (`MicroDVD`/text)
     MicroDVD,
+
+    /// .vtt file (WebVTT)
+    WebVtt,
+
+    /// .ttml/.dfxp file (Timed Text Markup Language)
+    Ttml,
+
+    /// .sbv file (YouTube captions)
+    Sbv,
+
+    /// .lrc file (lyrics)
+    Lrc,
+
+    /// .scc file (Scenarist Closed Captions)
+    Scc,
+
+    /// .stl file (EBU-STL)
+    EbuStl,
+
+    /// .pac file (Screen Electronics PAC)
+    Pac,
+
+    /// .txt file (MPL2)
+    Mpl2,
+
+    /// .sup file (Blu-ray PGS)
+    Pgs,
+
+    /// .jss file (JACOsub)
+    JacoSub,
+
+    /// .sub file (SubViewer 2.0/text)
+    SubViewer,
+
+    /// .rt file (RealText)
+    RealText,
+
+    /// .itt file (iTunes Timed Text)
+    Itt,
+
+    /// .xml file (BDN XML for Blu-ray authoring)
+    Bdn,
+
+    /// .stl file (Spruce STL text format)
+    SpruceStl,
+
+    /// .aqt file (AQTitle)
+    AqTitle,
+
+    /// .txt file (VPlayer)
+    VPlayer,
+
+    /// .psb file (PowerDivX)
+    PowerDivX,
+
+    /// .srv3/.ytt file (YouTube timed text)
+    Srv3,
+
+    /// .smi/.sami file (Microsoft SAMI)
+    Sami,
+    /// .dvbsub file (DVB subtitle stream)
+    Dvb,
 }
 
 #[derive(Clone, Debug)]
@@ -52,6 +142,71 @@ pub enum SubtitleFile {
 
     /// .sub file (`MicroDVD`/text)
     MicroDVDFile(microdvd::MdvdFile),
+
+    /// .vtt file (WebVTT)
+    WebVttFile(vtt::VttFile),
+
+    /// .ttml/.dfxp file (Timed Text Markup Language)
+    TtmlFile(ttml::TtmlFile),
+
+    /// .sbv file (YouTube captions)
+    SbvFile(sbv::SbvFile),
+
+    /// .lrc file (lyrics)
+    LrcFile(lrc::LrcFile),
+
+    /// .scc file (Scenarist Closed Captions)
+    SccFile(scc::SccFile),
+
+    /// .stl file (EBU-STL)
+    StlFile(stl::StlFile),
+
+    /// .pac file (Screen Electronics PAC)
+    PacFile(pac::PacFile),
+
+    /// .txt file (MPL2)
+    Mpl2File(mpl2::Mpl2File),
+
+    /// .sup file (Blu-ray PGS)
+    PgsFile(pgs::PgsFile),
+
+    /// .jss file (JACOsub)
+    JacoSubFile(jacosub::JacoFile),
+
+    /// .sub file (SubViewer 2.0/text)
+    SubViewerFile(subviewer::SubViewerFile),
+
+    /// .rt file (RealText)
+    RealTextFile(realtext::RealTextFile),
+
+    /// .itt file (iTunes Timed Text)
+    IttFile(itt::IttFile),
+
+    /// .xml file (BDN XML for Blu-ray authoring)
+    BdnFile(bdn::BdnFile),
+
+    /// .stl file (Spruce STL text format)
+    SpruceStlFile(spruce::SpruceStlFile),
+
+    /// .aqt file (AQTitle)
+    AqTitleFile(aqtitle::AqTitleFile),
+
+    /// .txt file (VPlayer)
+    VPlayerFile(vplayer::VPlayerFile),
+
+    /// .psb file (PowerDivX)
+    PowerDivXFile(powerdivx::PowerDivXFile),
+
+    /// .srv3/.ytt file (YouTube timed text)
+    Srv3File(srv3::Srv3File),
+
+    /// .smi/.sami file (Microsoft SAMI)
+    SamiFile(sami::SamiFile),
+    /// .dvbsub file (DVB subtitle stream)
+    DvbFile(dvb::DvbFile),
+
+    /// A file of a format that is provided by another crate (see `registry`).
+    External(Box<dyn ExternalSubtitleFile>),
 }
 
 impl SubtitleFile {
@@ -63,6 +218,28 @@ impl SubtitleFile {
             SubtitleFile::VobSubIdxFile(f) => f.get_subtitle_entries(),
             SubtitleFile::VobSubSubFile(f) => f.get_subtitle_entries(),
             SubtitleFile::MicroDVDFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::WebVttFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::TtmlFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::SbvFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::LrcFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::SccFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::StlFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::PacFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::Mpl2File(f) => f.get_subtitle_entries(),
+            SubtitleFile::PgsFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::JacoSubFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::SubViewerFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::RealTextFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::IttFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::BdnFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::SpruceStlFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::AqTitleFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::VPlayerFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::PowerDivXFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::Srv3File(f) => f.get_subtitle_entries(),
+            SubtitleFile::SamiFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::DvbFile(f) => f.get_subtitle_entries(),
+            SubtitleFile::External(f) => f.get_subtitle_entries(),
         }
     }
 
@@ -84,6 +261,28 @@ impl SubtitleFile {
             SubtitleFile::VobSubIdxFile(f) => f.update_subtitle_entries(i),
             SubtitleFile::VobSubSubFile(f) => f.update_subtitle_entries(i),
             SubtitleFile::MicroDVDFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::WebVttFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::TtmlFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::SbvFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::LrcFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::SccFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::StlFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::PacFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::Mpl2File(f) => f.update_subtitle_entries(i),
+            SubtitleFile::PgsFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::JacoSubFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::SubViewerFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::RealTextFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::IttFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::BdnFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::SpruceStlFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::AqTitleFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::VPlayerFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::PowerDivXFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::Srv3File(f) => f.update_subtitle_entries(i),
+            SubtitleFile::SamiFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::DvbFile(f) => f.update_subtitle_entries(i),
+            SubtitleFile::External(f) => f.update_subtitle_entries(i),
         }
     }
 
@@ -96,8 +295,216 @@ impl SubtitleFile {
             SubtitleFile::VobSubIdxFile(f) => f.to_data(),
             SubtitleFile::VobSubSubFile(f) => f.to_data(),
             SubtitleFile::MicroDVDFile(f) => f.to_data(),
+            SubtitleFile::WebVttFile(f) => f.to_data(),
+            SubtitleFile::TtmlFile(f) => f.to_data(),
+            SubtitleFile::SbvFile(f) => f.to_data(),
+            SubtitleFile::LrcFile(f) => f.to_data(),
+            SubtitleFile::SccFile(f) => f.to_data(),
+            SubtitleFile::StlFile(f) => f.to_data(),
+            SubtitleFile::PacFile(f) => f.to_data(),
+            SubtitleFile::Mpl2File(f) => f.to_data(),
+            SubtitleFile::PgsFile(f) => f.to_data(),
+            SubtitleFile::JacoSubFile(f) => f.to_data(),
+            SubtitleFile::SubViewerFile(f) => f.to_data(),
+            SubtitleFile::RealTextFile(f) => f.to_data(),
+            SubtitleFile::IttFile(f) => f.to_data(),
+            SubtitleFile::BdnFile(f) => f.to_data(),
+            SubtitleFile::SpruceStlFile(f) => f.to_data(),
+            SubtitleFile::AqTitleFile(f) => f.to_data(),
+            SubtitleFile::VPlayerFile(f) => f.to_data(),
+            SubtitleFile::PowerDivXFile(f) => f.to_data(),
+            SubtitleFile::Srv3File(f) => f.to_data(),
+            SubtitleFile::SamiFile(f) => f.to_data(),
+            SubtitleFile::DvbFile(f) => f.to_data(),
+            SubtitleFile::External(f) => f.to_data(),
+        }
+    }
+
+    /// Returns a byte-stream like `to_data()`, but normalized so that files with the same content always
+    /// produce the same bytes (for reproducible pipelines and content-addressed caches).
+    ///
+    /// Text formats get `\n` line endings without a BOM, `.srt` files are renumbered and `.ssa` files get a fixed
+    /// key order, number notation and whitespace between the fields (see `SsaFile::to_deterministic_data()`).
+    /// The text of the entries stays the same. Binary formats and external formats are returned unchanged.
+    pub fn to_deterministic_data(&self) -> Result<Vec<u8>> {
+        let normalize = |data: Vec<u8>| common::normalize_line_endings(&String::from_utf8_lossy(&data)).into_bytes();
+        match self {
+            SubtitleFile::SubRipFile(f) => f.to_deterministic_data(),
+            SubtitleFile::SubStationAlpha(f) => f.to_deterministic_data(),
+            SubtitleFile::VobSubIdxFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::VobSubSubFile(f) => f.to_data(),
+            SubtitleFile::MicroDVDFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::WebVttFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::TtmlFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::SbvFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::LrcFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::SccFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::StlFile(f) => f.to_data(),
+            SubtitleFile::PacFile(f) => f.to_data(),
+            SubtitleFile::Mpl2File(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::PgsFile(f) => f.to_data(),
+            SubtitleFile::JacoSubFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::SubViewerFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::RealTextFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::IttFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::BdnFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::SpruceStlFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::AqTitleFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::VPlayerFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::PowerDivXFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::Srv3File(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::SamiFile(f) => Ok(normalize(f.to_data()?)),
+            SubtitleFile::DvbFile(f) => f.to_data(),
+            SubtitleFile::External(f) => f.to_data(),
+        }
+    }
+
+    /// Returns `to_deterministic_data()` with normalized whitespace in text formats: trailing whitespace,
+    /// leading empty lines and repeated empty lines are removed from the whole file.
+    ///
+    /// Unlike `to_deterministic_data()`, this can change the text of entries (trailing spaces, or empty lines in
+    /// the paragraphs of XML formats).
+    pub fn to_normalized_data(&self) -> Result<Vec<u8>> {
+        let data = self.to_deterministic_data()?;
+        if self.format().is_some_and(|format| format.capabilities().text) {
+            Ok(common::normalize_whitespace(&String::from_utf8_lossy(&data)).into_bytes())
+        } else {
+            Ok(data)
         }
     }
+
+    /// Removes every entry for which `keep[i]` is `false` (see `SubtitleFileInterface::retain_entries_by_mask()`).
+    pub fn retain_entries_by_mask(&mut self, keep: &[bool]) -> Result<()> {
+        match self {
+            SubtitleFile::SubRipFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SubStationAlpha(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::VobSubIdxFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::VobSubSubFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::MicroDVDFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::WebVttFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::TtmlFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SbvFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::LrcFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SccFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::StlFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::PacFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::Mpl2File(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::PgsFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::JacoSubFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SubViewerFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::RealTextFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::IttFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::BdnFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SpruceStlFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::AqTitleFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::VPlayerFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::PowerDivXFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::Srv3File(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::SamiFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::DvbFile(f) => f.retain_entries_by_mask(keep),
+            SubtitleFile::External(f) => f.retain_entries_by_mask(keep),
+        }
+    }
+
+    /// Removes every entry for which `f` returns `false`.
+    pub fn retain_entries<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnMut(&SubtitleEntry) -> bool,
+    {
+        let keep: Vec<bool> = self.get_subtitle_entries()?.iter().map(f).collect();
+        self.retain_entries_by_mask(&keep)
+    }
+
+    /// Removes every entry which lies completely within `timespan`.
+    pub fn drop_entries_in(&mut self, timespan: TimeSpan) -> Result<()> {
+        self.retain_entries(|entry| !timespan.contains(entry.timespan))
+    }
+
+    /// Returns the entries like they would be after `transform.retime_file()`, without changing the file.
+    pub fn preview_transform<R: Retiming + ?Sized>(&self, transform: &R) -> Result<Vec<SubtitleEntry>> {
+        let mut entries = self.get_subtitle_entries()?;
+        transform.retime(&mut entries);
+        Ok(entries)
+    }
+
+    /// Returns the format of the file (`None` for formats of other crates, see `registry`).
+    pub fn format(&self) -> Option<SubtitleFormat> {
+        match self {
+            SubtitleFile::SubRipFile(_) => Some(SubtitleFormat::SubRip),
+            SubtitleFile::SubStationAlpha(_) => Some(SubtitleFormat::SubStationAlpha),
+            SubtitleFile::VobSubIdxFile(_) => Some(SubtitleFormat::VobSubIdx),
+            SubtitleFile::VobSubSubFile(_) => Some(SubtitleFormat::VobSubSub),
+            SubtitleFile::MicroDVDFile(_) => Some(SubtitleFormat::MicroDVD),
+            SubtitleFile::WebVttFile(_) => Some(SubtitleFormat::WebVtt),
+            SubtitleFile::TtmlFile(_) => Some(SubtitleFormat::Ttml),
+            SubtitleFile::SbvFile(_) => Some(SubtitleFormat::Sbv),
+            SubtitleFile::LrcFile(_) => Some(SubtitleFormat::Lrc),
+            SubtitleFile::SccFile(_) => Some(SubtitleFormat::Scc),
+            SubtitleFile::StlFile(_) => Some(SubtitleFormat::EbuStl),
+            SubtitleFile::PacFile(_) => Some(SubtitleFormat::Pac),
+            SubtitleFile::Mpl2File(_) => Some(SubtitleFormat::Mpl2),
+            SubtitleFile::PgsFile(_) => Some(SubtitleFormat::Pgs),
+            SubtitleFile::JacoSubFile(_) => Some(SubtitleFormat::JacoSub),
+            SubtitleFile::SubViewerFile(_) => Some(SubtitleFormat::SubViewer),
+            SubtitleFile::RealTextFile(_) => Some(SubtitleFormat::RealText),
+            SubtitleFile::IttFile(_) => Some(SubtitleFormat::Itt),
+            SubtitleFile::BdnFile(_) => Some(SubtitleFormat::Bdn),
+            SubtitleFile::SpruceStlFile(_) => Some(SubtitleFormat::SpruceStl),
+            SubtitleFile::AqTitleFile(_) => Some(SubtitleFormat::AqTitle),
+            SubtitleFile::VPlayerFile(_) => Some(SubtitleFormat::VPlayer),
+            SubtitleFile::PowerDivXFile(_) => Some(SubtitleFormat::PowerDivX),
+            SubtitleFile::Srv3File(_) => Some(SubtitleFormat::Srv3),
+            SubtitleFile::SamiFile(_) => Some(SubtitleFormat::Sami),
+            SubtitleFile::DvbFile(_) => Some(SubtitleFormat::Dvb),
+            SubtitleFile::External(_) => None,
+        }
+    }
+
+    /// Returns the text of every part which is kept without being interpreted, and `None` for the other parts
+    /// (in the order of the file).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        match self {
+            SubtitleFile::SubStationAlpha(f) => f.filler_parts(),
+            SubtitleFile::VobSubIdxFile(f) => f.filler_parts(),
+            SubtitleFile::WebVttFile(f) => f.filler_parts(),
+            SubtitleFile::TtmlFile(f) => f.filler_parts(),
+            SubtitleFile::LrcFile(f) => f.filler_parts(),
+            SubtitleFile::SccFile(f) => f.filler_parts(),
+            SubtitleFile::Mpl2File(f) => f.filler_parts(),
+            SubtitleFile::JacoSubFile(f) => f.filler_parts(),
+            SubtitleFile::SubViewerFile(f) => f.filler_parts(),
+            SubtitleFile::RealTextFile(f) => f.filler_parts(),
+            SubtitleFile::IttFile(f) => f.filler_parts(),
+            SubtitleFile::BdnFile(f) => f.filler_parts(),
+            SubtitleFile::SpruceStlFile(f) => f.filler_parts(),
+            SubtitleFile::VPlayerFile(f) => f.filler_parts(),
+            SubtitleFile::PowerDivXFile(f) => f.filler_parts(),
+            SubtitleFile::Srv3File(f) => f.filler_parts(),
+            SubtitleFile::SamiFile(f) => f.filler_parts(),
+            // the other formats interpret (or reject) everything
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns `true` if new entries can be inserted with `insert_entry()` (only `.srt` and MicroDVD files).
+    pub(crate) fn can_insert_entries(&self) -> bool {
+        matches!(self, SubtitleFile::SubRipFile(_) | SubtitleFile::MicroDVDFile(_))
+    }
+
+    /// Inserts a new entry at position `idx` of `get_subtitle_entries()` (or at the end).
+    pub(crate) fn insert_entry(&mut self, idx: usize, timespan: TimeSpan, text: &str) -> Result<()> {
+        match self {
+            SubtitleFile::SubRipFile(f) => f.insert_entry(idx, timespan, text),
+            SubtitleFile::MicroDVDFile(f) => f.insert_entry(idx, timespan, text),
+            _ => return Err(ErrorKind::InsertingEntriesNotSupported.into()),
+        }
+        Ok(())
+    }
+
+    /// Removes every entry which is not in `language` (see `language::entry_language()` on how the language is determined).
+    pub fn filter_by_language(&mut self, language: &str) -> Result<()> {
+        self.retain_entries(|entry| entry_language(entry).is_some_and(|tag| language_matches(&tag, language)))
+    }
 }
 
 impl From<srt::SrtFile> for SubtitleFile {
@@ -130,7 +537,352 @@ impl From<microdvd::MdvdFile> for SubtitleFile {
     }
 }
 
+impl From<vtt::VttFile> for SubtitleFile {
+    fn from(f: vtt::VttFile) -> SubtitleFile {
+        SubtitleFile::WebVttFile(f)
+    }
+}
+
+impl From<ttml::TtmlFile> for SubtitleFile {
+    fn from(f: ttml::TtmlFile) -> SubtitleFile {
+        SubtitleFile::TtmlFile(f)
+    }
+}
+
+impl From<sbv::SbvFile> for SubtitleFile {
+    fn from(f: sbv::SbvFile) -> SubtitleFile {
+        SubtitleFile::SbvFile(f)
+    }
+}
+
+impl From<lrc::LrcFile> for SubtitleFile {
+    fn from(f: lrc::LrcFile) -> SubtitleFile {
+        SubtitleFile::LrcFile(f)
+    }
+}
+
+impl From<scc::SccFile> for SubtitleFile {
+    fn from(f: scc::SccFile) -> SubtitleFile {
+        SubtitleFile::SccFile(f)
+    }
+}
+
+impl From<stl::StlFile> for SubtitleFile {
+    fn from(f: stl::StlFile) -> SubtitleFile {
+        SubtitleFile::StlFile(f)
+    }
+}
+
+impl From<pac::PacFile> for SubtitleFile {
+    fn from(f: pac::PacFile) -> SubtitleFile {
+        SubtitleFile::PacFile(f)
+    }
+}
+
+impl From<mpl2::Mpl2File> for SubtitleFile {
+    fn from(f: mpl2::Mpl2File) -> SubtitleFile {
+        SubtitleFile::Mpl2File(f)
+    }
+}
+
+impl From<pgs::PgsFile> for SubtitleFile {
+    fn from(f: pgs::PgsFile) -> SubtitleFile {
+        SubtitleFile::PgsFile(f)
+    }
+}
+
+impl From<jacosub::JacoFile> for SubtitleFile {
+    fn from(f: jacosub::JacoFile) -> SubtitleFile {
+        SubtitleFile::JacoSubFile(f)
+    }
+}
+
+impl From<subviewer::SubViewerFile> for SubtitleFile {
+    fn from(f: subviewer::SubViewerFile) -> SubtitleFile {
+        SubtitleFile::SubViewerFile(f)
+    }
+}
+
+impl From<realtext::RealTextFile> for SubtitleFile {
+    fn from(f: realtext::RealTextFile) -> SubtitleFile {
+        SubtitleFile::RealTextFile(f)
+    }
+}
+
+impl From<itt::IttFile> for SubtitleFile {
+    fn from(f: itt::IttFile) -> SubtitleFile {
+        SubtitleFile::IttFile(f)
+    }
+}
+
+impl From<bdn::BdnFile> for SubtitleFile {
+    fn from(f: bdn::BdnFile) -> SubtitleFile {
+        SubtitleFile::BdnFile(f)
+    }
+}
+
+impl From<spruce::SpruceStlFile> for SubtitleFile {
+    fn from(f: spruce::SpruceStlFile) -> SubtitleFile {
+        SubtitleFile::SpruceStlFile(f)
+    }
+}
+
+impl From<aqtitle::AqTitleFile> for SubtitleFile {
+    fn from(f: aqtitle::AqTitleFile) -> SubtitleFile {
+        SubtitleFile::AqTitleFile(f)
+    }
+}
+
+impl From<vplayer::VPlayerFile> for SubtitleFile {
+    fn from(f: vplayer::VPlayerFile) -> SubtitleFile {
+        SubtitleFile::VPlayerFile(f)
+    }
+}
+
+impl From<powerdivx::PowerDivXFile> for SubtitleFile {
+    fn from(f: powerdivx::PowerDivXFile) -> SubtitleFile {
+        SubtitleFile::PowerDivXFile(f)
+    }
+}
+
+impl From<srv3::Srv3File> for SubtitleFile {
+    fn from(f: srv3::Srv3File) -> SubtitleFile {
+        SubtitleFile::Srv3File(f)
+    }
+}
+
+impl From<sami::SamiFile> for SubtitleFile {
+    fn from(f: sami::SamiFile) -> SubtitleFile {
+        SubtitleFile::SamiFile(f)
+    }
+}
+
+impl From<dvb::DvbFile> for SubtitleFile {
+    fn from(f: dvb::DvbFile) -> SubtitleFile {
+        SubtitleFile::DvbFile(f)
+    }
+}
+
+/// What a subtitle format can store and what this library can do with it (see `SubtitleFormat::capabilities()`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatCapabilities {
+    /// The format is text-based and can be parsed with `parse_str()`.
+    pub text: bool,
+
+    /// The subtitles are images instead of text.
+    pub bitmap: bool,
+
+    /// Times are stored as frame numbers, so a frame rate is needed for parsing.
+    pub frame_based: bool,
+
+    /// Every subtitle has its own end time (`.idx` files only store start times).
+    pub end_times: bool,
+
+    /// Texts can be styled beyond bold/italic/underline (fonts, colors, positions, ...).
+    pub styling: bool,
+
+    /// An empty file can be created with `SubtitleFormat::empty_file()`.
+    pub create: bool,
+
+    /// New entries can be inserted (for example by `SubtitleFile::fill_gaps()`).
+    pub insert_entries: bool,
+}
+
 impl SubtitleFormat {
+    /// All formats which are supported by this library, for example to fill a menu of target formats.
+    pub const ALL: [SubtitleFormat; 26] = [
+        SubtitleFormat::SubRip,
+        SubtitleFormat::SubStationAlpha,
+        SubtitleFormat::VobSubIdx,
+        SubtitleFormat::VobSubSub,
+        SubtitleFormat::MicroDVD,
+        SubtitleFormat::WebVtt,
+        SubtitleFormat::Ttml,
+        SubtitleFormat::Sbv,
+        SubtitleFormat::Lrc,
+        SubtitleFormat::Scc,
+        SubtitleFormat::EbuStl,
+        SubtitleFormat::Pac,
+        SubtitleFormat::Mpl2,
+        SubtitleFormat::Pgs,
+        SubtitleFormat::JacoSub,
+        SubtitleFormat::SubViewer,
+        SubtitleFormat::RealText,
+        SubtitleFormat::Itt,
+        SubtitleFormat::Bdn,
+        SubtitleFormat::SpruceStl,
+        SubtitleFormat::AqTitle,
+        SubtitleFormat::VPlayer,
+        SubtitleFormat::PowerDivX,
+        SubtitleFormat::Srv3,
+        SubtitleFormat::Sami,
+        SubtitleFormat::Dvb,
+    ];
+
+    /// Returns the file extensions of the format (without dot), the usual one first.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match *self {
+            SubtitleFormat::SubRip => &["srt"],
+            SubtitleFormat::SubStationAlpha => &["ass", "ssa"],
+            SubtitleFormat::VobSubIdx => &["idx"],
+            SubtitleFormat::VobSubSub | SubtitleFormat::MicroDVD => &["sub"],
+            SubtitleFormat::WebVtt => &["vtt"],
+            SubtitleFormat::Ttml => &["ttml", "dfxp"],
+            SubtitleFormat::Sbv => &["sbv"],
+            SubtitleFormat::Lrc => &["lrc"],
+            SubtitleFormat::Scc => &["scc"],
+            SubtitleFormat::EbuStl => &["stl"],
+            SubtitleFormat::Pac => &["pac"],
+            SubtitleFormat::Mpl2 => &["txt", "mpl"],
+            SubtitleFormat::Pgs => &["sup"],
+            SubtitleFormat::JacoSub => &["jss"],
+            SubtitleFormat::SubViewer => &["sub"],
+            SubtitleFormat::RealText => &["rt"],
+            SubtitleFormat::Itt => &["itt"],
+            SubtitleFormat::Bdn => &["xml"],
+            SubtitleFormat::SpruceStl => &["stl"],
+            SubtitleFormat::AqTitle => &["aqt"],
+            SubtitleFormat::VPlayer => &["txt"],
+            SubtitleFormat::PowerDivX => &["psb"],
+            SubtitleFormat::Srv3 => &["srv3", "ytt"],
+            SubtitleFormat::Sami => &["smi", "sami"],
+            SubtitleFormat::Dvb => &["dvbsub"],
+        }
+    }
+
+    /// Returns what the format can store and what this library can do with it.
+    pub const fn capabilities(&self) -> FormatCapabilities {
+        let text = FormatCapabilities {
+            text: true,
+            bitmap: false,
+            frame_based: false,
+            end_times: true,
+            styling: false,
+            create: true,
+            insert_entries: false,
+        };
+        match *self {
+            SubtitleFormat::SubRip => FormatCapabilities {
+                insert_entries: true,
+                ..text
+            },
+            SubtitleFormat::SubStationAlpha => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::VobSubIdx => FormatCapabilities {
+                bitmap: true,
+                end_times: false,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::VobSubSub => FormatCapabilities {
+                text: false,
+                bitmap: true,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::MicroDVD => FormatCapabilities {
+                frame_based: true,
+                insert_entries: true,
+                ..text
+            },
+            SubtitleFormat::WebVtt | SubtitleFormat::Ttml | SubtitleFormat::Sbv => text,
+            SubtitleFormat::Lrc => FormatCapabilities { end_times: false, ..text },
+            SubtitleFormat::Scc => FormatCapabilities { create: false, ..text },
+            SubtitleFormat::EbuStl => FormatCapabilities {
+                text: false,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::Pac => FormatCapabilities {
+                text: false,
+                frame_based: true,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::Mpl2 => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::Pgs => FormatCapabilities {
+                text: false,
+                bitmap: true,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::JacoSub => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::SubViewer => FormatCapabilities { styling: false, ..text },
+            SubtitleFormat::RealText => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::Itt => text,
+            SubtitleFormat::Bdn => FormatCapabilities {
+                bitmap: true,
+                create: false,
+                ..text
+            },
+            SubtitleFormat::SpruceStl => FormatCapabilities { frame_based: true, ..text },
+            SubtitleFormat::AqTitle => FormatCapabilities { frame_based: true, ..text },
+            SubtitleFormat::VPlayer => text,
+            SubtitleFormat::PowerDivX => text,
+            SubtitleFormat::Srv3 => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::Sami => FormatCapabilities { styling: true, ..text },
+            SubtitleFormat::Dvb => FormatCapabilities {
+                text: false,
+                bitmap: true,
+                create: false,
+                ..text
+            },
+        }
+    }
+
+    /// Creates a file without entries (`fps` is only used by `MicroDVD` files, see `parse_bytes()`).
+    ///
+    /// `.ssa` files get a `Default` style. VobSub files can't be created, because they need images.
+    pub fn empty_file(&self, fps: f64) -> Result<SubtitleFile> {
+        self.create_file(Vec::new(), fps)
+    }
+
+    /// Creates a file with the given entries (`fps` is only used by frame-based formats, see `parse_bytes()`).
+    ///
+    /// The events of `.ssa` files get the `Default` style. Formats which need images or binary metadata (see
+    /// `FormatCapabilities::create`) return an error.
+    pub fn create_file(&self, v: Vec<(TimeSpan, String)>, fps: f64) -> Result<SubtitleFile> {
+        match *self {
+            SubtitleFormat::SubRip => Ok(srt::SrtFile::create(v)?.into()),
+            SubtitleFormat::SubStationAlpha => {
+                let mut template = ssa::AssTemplate::new(vec![ssa::SsaStyle::default()]);
+                for (timespan, text) in &v {
+                    template.add_event(*timespan, text, "Default", None);
+                }
+                Ok(template.to_ssa_file()?.into())
+            }
+            SubtitleFormat::VobSubIdx => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::VobSubSub => Err(ErrorKind::TextFormatOnly.into()),
+            SubtitleFormat::MicroDVD => {
+                let mut file = microdvd::MdvdFile::parse("", fps)?;
+                for (idx, (timespan, text)) in v.iter().enumerate() {
+                    file.insert_entry(idx, *timespan, text);
+                }
+                Ok(file.into())
+            }
+            SubtitleFormat::WebVtt => Ok(vtt::VttFile::create(v)?.into()),
+            SubtitleFormat::Ttml => Ok(ttml::TtmlFile::create(v)?.into()),
+            SubtitleFormat::Sbv => Ok(sbv::SbvFile::create(v)?.into()),
+            SubtitleFormat::Lrc => Ok(lrc::LrcFile::create(v)?.into()),
+            SubtitleFormat::Scc => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::EbuStl => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::Pac => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::Mpl2 => Ok(mpl2::Mpl2File::create(v)?.into()),
+            SubtitleFormat::Pgs => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::JacoSub => Ok(jacosub::JacoFile::create(v)?.into()),
+            SubtitleFormat::SubViewer => Ok(subviewer::SubViewerFile::create(v)?.into()),
+            SubtitleFormat::RealText => Ok(realtext::RealTextFile::create(v)?.into()),
+            SubtitleFormat::Itt => Ok(itt::IttFile::create(v)?.into()),
+            SubtitleFormat::Bdn => Err(ErrorKind::CreationError.into()),
+            SubtitleFormat::SpruceStl => Ok(spruce::SpruceStlFile::create(v, fps)?.into()),
+            SubtitleFormat::AqTitle => Ok(aqtitle::AqTitleFile::create(v, fps)?.into()),
+            SubtitleFormat::VPlayer => Ok(vplayer::VPlayerFile::create(v)?.into()),
+            SubtitleFormat::PowerDivX => Ok(powerdivx::PowerDivXFile::create(v)?.into()),
+            SubtitleFormat::Srv3 => Ok(srv3::Srv3File::create(v)?.into()),
+            SubtitleFormat::Sami => Ok(sami::SamiFile::create(v)?.into()),
+            SubtitleFormat::Dvb => Err(ErrorKind::CreationError.into()),
+        }
+    }
+
     /// Get a descriptive string for the format like `".srt (SubRip)"`.
     pub fn get_name(&self) -> &'static str {
         match *self {
@@ -139,14 +891,245 @@ impl SubtitleFormat {
             SubtitleFormat::VobSubIdx => ".idx (VobSub)",
             SubtitleFormat::VobSubSub => ".sub (VobSub)",
             SubtitleFormat::MicroDVD => ".sub (MicroDVD)",
+            SubtitleFormat::WebVtt => ".vtt (WebVTT)",
+            SubtitleFormat::Ttml => ".ttml (Timed Text Markup Language)",
+            SubtitleFormat::Sbv => ".sbv (YouTube)",
+            SubtitleFormat::Lrc => ".lrc (lyrics)",
+            SubtitleFormat::Scc => ".scc (Scenarist Closed Captions)",
+            SubtitleFormat::EbuStl => ".stl (EBU-STL)",
+            SubtitleFormat::Pac => ".pac (Screen Electronics PAC)",
+            SubtitleFormat::Mpl2 => ".txt (MPL2)",
+            SubtitleFormat::Pgs => ".sup (Blu-ray PGS)",
+            SubtitleFormat::JacoSub => ".jss (JACOsub)",
+            SubtitleFormat::SubViewer => ".sub (SubViewer 2.0)",
+            SubtitleFormat::RealText => ".rt (RealText)",
+            SubtitleFormat::Itt => ".itt (iTunes Timed Text)",
+            SubtitleFormat::Bdn => ".xml (BDN XML)",
+            SubtitleFormat::SpruceStl => ".stl (Spruce STL)",
+            SubtitleFormat::AqTitle => ".aqt (AQTitle)",
+            SubtitleFormat::VPlayer => ".txt (VPlayer)",
+            SubtitleFormat::PowerDivX => ".psb (PowerDivX)",
+            SubtitleFormat::Srv3 => ".srv3 (YouTube timed text)",
+            SubtitleFormat::Sami => ".smi (SAMI)",
+            SubtitleFormat::Dvb => ".dvbsub (DVB subtitles)",
         }
     }
 }
 
+#[test]
+fn test_malformed_input_does_not_panic() {
+    let inputs = [
+        "",
+        "\u{FEFF}",
+        "1\n99999999999999999999:00:00,000 --> 00:00:01,000\nx\n",
+        "1\n9223372036854775:00:00,000 --> 00:00:01,000\nx\n",
+        "[Events]\nFormat: Start, End, Text\nDialogue: 9999999999999999:00:00.00,0:00:01.00,x\nDialogue: 0:00",
+        "[Events]\nFormat: Layer, Layer, Start, End, Text\n",
+        "timestamp: 99999999999999:00:00:000, filepos: 0\ntimestamp: ",
+        "delay: -99999999999999:00:00:000\ntimestamp: 00:00:00:000, filepos: 0\ndelay: -",
+        "{99999999999999999999}{1}x\n{-9223372036854775807}{0}{y:i}|",
+        "WEBVTT\n\n99999999999999999999:00:00.000 --> 00:01.000\nx\n\nid\n00:00.000 -->",
+        "WEBVTT\n\n-->\n\n00:00.000 --> 00:01.000",
+        "<tt><body><div begin=\"99999999999999999999h\"><p begin=\"1s\" dur=\"1s\"><span>x</p></div></body></tt>",
+        "<tt><body><p begin='0s' end='1s'><br/>&#x110000;&#99999999999;&</p><!--",
+        "999999999:00:00.000,0:00:01.000\nx\n0:00:00.0,0:00:00.00\n\n9:99:99.999,",
+        "[offset:-9223372036854775807]\n[999999999:59.999][00:00]<999999999:00.00><x\n[00:61.00]\n[",
+        "Scenarist_SCC V1.0\n99:59:59;29\t9425 9425 94ad 1020 9121 9220 1370 942f 942c\n00:00:00:00\t9429 9721 2080 94a1 94a1 ffff\n",
+        "[99999999999999999999][1]x\n[0][99999999999999999]/|/\n[",
+        "#T0\n@99999999999999999999 @1 x\n0:0.99999999999999999999 1.0 \\\n",
+        "[INFORMATION]\n99:99:99.99,00:00:00.00\n[br][BR]\n\nx\n00:00:00.00,",
+        "<window duration=99999999999999999999><time begin=\"99999999999999:99\" end='1.99999999999'/><clear/><b></b><br/>\n<time begin=1 /><time begin='",
+        "<tt ttp:timeBase=\"smpte\" ttp:frameRate=\"30\" ttp:dropMode=\"dropNTSC\"><body><p begin=\"99999999999999999999:59:59:29\" end=\"00:00:00:00\">x</p></body></tt>",
+        "<BDN><Format FrameRate=\"25\"/><Events><Event InTC=\"99999999999999999999:00:00:00\" OutTC=\"00:00:00:00\"></Event></Events></BDN>",
+        "1\n3000000000000:00:00,000 --> 00:00:01,000\nx\n",
+        "[Events]\nFormat: Start, End, Text\nDialogue: 0:00:00.9223372036854775807,0:00:01.00,x\n",
+        "timestamp: 3000000000000:00:00:000, filepos: 0\n",
+        "WEBVTT\n\n3000000000000:00:00.000 --> 00:01.000\nx\n",
+        "$FontName = Arial\n99999999999999999999:00:00:00 , 00:00:00:99 , x|^I^B\n00:00:00:00 ,\n",
+        "-->> 99999999999999999999\nHi\n",
+        "99:99:99:x\n",
+        "{0:99:00}{0:00:01}x\n",
+        "<p t=\"0\" d=\"1\">x</p>\n",
+    ];
+    let formats = [
+        SubtitleFormat::SubRip,
+        SubtitleFormat::SubStationAlpha,
+        SubtitleFormat::VobSubIdx,
+        SubtitleFormat::MicroDVD,
+        SubtitleFormat::WebVtt,
+        SubtitleFormat::Ttml,
+        SubtitleFormat::Sbv,
+        SubtitleFormat::Lrc,
+        SubtitleFormat::Scc,
+        SubtitleFormat::Mpl2,
+        SubtitleFormat::JacoSub,
+        SubtitleFormat::SubViewer,
+        SubtitleFormat::RealText,
+        SubtitleFormat::Itt,
+        SubtitleFormat::Bdn,
+        SubtitleFormat::SpruceStl,
+        SubtitleFormat::AqTitle,
+        SubtitleFormat::VPlayer,
+        SubtitleFormat::PowerDivX,
+        SubtitleFormat::Srv3,
+        SubtitleFormat::Sami,
+        SubtitleFormat::Dvb,
+    ];
+    for input in inputs.iter() {
+        for &format in formats.iter() {
+            for &fps in [25.0, 0.0, f64::NAN].iter() {
+                if let Ok(mut file) = parse_str(format, input, fps) {
+                    let mut entries = file.get_subtitle_entries().unwrap();
+                    for entry in &mut entries {
+                        entry.timespan = TimeSpan::new(-entry.timespan.end, entry.timespan.start + entry.timespan.len());
+                    }
+                    file.update_subtitle_entries(&entries).unwrap();
+                    file.to_data().unwrap();
+                    if !entries.is_empty() {
+                        assert!(file.update_subtitle_entries(&entries[1..]).is_err());
+                        assert!(file.retain_entries_by_mask(&[]).is_err());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_out_of_range_timestamps() {
+    use crate::timetypes::TimestampOutOfRange;
+    use failure::Fail;
+
+    let inputs = [
+        (SubtitleFormat::SubRip, "1\n3000000000000:00:00,000 --> 00:00:01,000\nx\n"),
+        (
+            SubtitleFormat::SubStationAlpha,
+            "[Events]\nFormat: Start, End, Text\nDialogue: 0:00:00.9223372036854775807,0:00:01.00,x\n",
+        ),
+        (SubtitleFormat::VobSubIdx, "timestamp: 3000000000000:00:00:000, filepos: 0\n"),
+        (SubtitleFormat::WebVtt, "WEBVTT\n\n3000000000000:00:00.000 --> 00:01.000\nx\n"),
+    ];
+    for &(format, input) in inputs.iter() {
+        let error = parse_str(format, input, 25.0)
+            .err()
+            .unwrap_or_else(|| panic!("{:?} accepted the timestamp", format));
+        let error_fail: &dyn Fail = &error;
+        assert!(
+            error_fail
+                .iter_causes()
+                .any(|cause| cause.downcast_ref::<TimestampOutOfRange>().is_some()),
+            "{:?}: {}",
+            format,
+            error
+        );
+    }
+}
+
+#[test]
+fn test_saturated_timestamps() {
+    // MicroDVD frame numbers are converted without a typed error, the times saturate
+    let file = parse_str(SubtitleFormat::MicroDVD, "{900000000000000000}{900000000000000001}x\n", 25.0).unwrap();
+    let entries = file.get_subtitle_entries().unwrap();
+    let max = crate::timetypes::TimePoint::from_msecs(i64::MAX);
+    assert_eq!(entries.first().map(|entry| entry.timespan), Some(TimeSpan::new(max, max)));
+}
+
+#[test]
+fn test_all_formats() {
+    for format in SubtitleFormat::ALL.iter() {
+        let capabilities = format.capabilities();
+        let extension = format.extensions().first().map(OsStr::new);
+        assert!(is_valid_extension_for_subtitle_format(extension, *format));
+        match format.empty_file(25.0) {
+            Ok(file) => {
+                assert!(capabilities.create);
+                assert_eq!(file.format(), Some(*format));
+                assert_eq!(file.can_insert_entries(), capabilities.insert_entries);
+                assert!(file.get_subtitle_entries().unwrap().is_empty());
+                assert!(parse_str(*format, &String::from_utf8(file.to_data().unwrap()).unwrap(), 25.0).is_ok());
+            }
+            Err(_) => assert!(!capabilities.create),
+        }
+        // every format has its own variant of `SubtitleFile`
+        if let Ok(file) = parse_bytes(*format, &[], Some(encoding_rs::UTF_8), 25.0) {
+            assert_eq!(file.format(), Some(*format));
+        }
+    }
+}
+
+#[test]
+fn test_deterministic_data_keeps_texts() {
+    let files = [
+        (SubtitleFormat::SubRip, "1\r\n00:00:01,000 --> 00:00:02,000\r\nHello  \r\n\r\n\r\n5\r\n00:00:03,000 --> 00:00:04,000\r\nWorld\r\n"),
+        (SubtitleFormat::WebVtt, "\u{FEFF}WEBVTT\r\n\r\n\r\n00:01.000 --> 00:02.000\r\nHello  \r\n  world\r\n\r\n\r\n"),
+        (
+            SubtitleFormat::Ttml,
+            "<tt xmlns=\"http://www.w3.org/ns/ttml\" xml:space=\"preserve\">  \r\n\r\n\r\n<body><div><p begin=\"1s\" end=\"2s\">a  \n\n\n  b</p></div></body></tt>\n",
+        ),
+        (
+            SubtitleFormat::SubStationAlpha,
+            "[Events]\r\nFormat: Layer, Start, End, Text\r\n\r\n\r\nDialogue: 0,0:00:01.00,0:00:02.00,  a  \r\n",
+        ),
+    ];
+    for &(format, content) in &files {
+        let file = parse_str(format, content, 25.0).unwrap();
+        let data = String::from_utf8(file.to_deterministic_data().unwrap()).unwrap();
+        assert!(!data.contains('\r'));
+        let deterministic = parse_str(format, &data, 25.0).unwrap();
+        assert_eq!(deterministic.get_subtitle_entries().unwrap(), file.get_subtitle_entries().unwrap());
+        assert_eq!(deterministic.to_deterministic_data().unwrap(), data.as_bytes());
+
+        // only the normalized data is allowed to change the text of the entries
+        let normalized = String::from_utf8(file.to_normalized_data().unwrap()).unwrap();
+        assert!(!normalized.contains(" \n") && !normalized.contains("\n\n\n"));
+    }
+}
+
 #[test]
 fn test_subtitle_format_by_extension() {
     // this shows how the input paramter can be crated from scratch
     assert_eq!(get_subtitle_format_by_extension(Some(OsStr::new("srt"))), Some(SubtitleFormat::SubRip));
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("txt")), b"[10][25]Hello\n"),
+        Some(SubtitleFormat::Mpl2)
+    );
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("txt")), b"00:00:12:Hello\n"),
+        Some(SubtitleFormat::VPlayer)
+    );
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("txt")), b"{0:00:01}{0:00:03}Hello\n"),
+        Some(SubtitleFormat::PowerDivX)
+    );
+    assert_eq!(get_subtitle_format(Some(OsStr::new("psb")), b""), Some(SubtitleFormat::PowerDivX));
+    assert_eq!(get_subtitle_format(Some(OsStr::new("txt")), b"Hello\n"), None);
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("sub")), b"{1}{25}Hello\n"),
+        Some(SubtitleFormat::MicroDVD)
+    );
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("sub")), b"[INFORMATION]\n[END INFORMATION]\n"),
+        Some(SubtitleFormat::SubViewer)
+    );
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("xml")), b"<BDN Version=\"0.93\">"),
+        Some(SubtitleFormat::Bdn)
+    );
+    assert_eq!(get_subtitle_format(Some(OsStr::new("smi")), b"<SAMI>"), Some(SubtitleFormat::Sami));
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("xml")), b"<tt xml:lang=\"en\">"),
+        Some(SubtitleFormat::Ttml)
+    );
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("xml")), b"<timedtext format=\"3\">"),
+        Some(SubtitleFormat::Srv3)
+    );
+    assert_eq!(get_subtitle_format(Some(OsStr::new("dvbsub")), b""), Some(SubtitleFormat::Dvb));
+    assert_eq!(get_subtitle_format(Some(OsStr::new("stl")), b"850STL25.01"), Some(SubtitleFormat::EbuStl));
+    assert_eq!(
+        get_subtitle_format(Some(OsStr::new("stl")), b"$FontName = Arial\n"),
+        Some(SubtitleFormat::SpruceStl)
+    );
 }
 
 /// Returns the subtitle format by the file extension.
@@ -154,13 +1137,13 @@ fn test_subtitle_format_by_extension() {
 /// Calling the function with the full file path or simply a `get_subtitle_format_by_extension(Some(OsStr::new("srt")))`
 /// both work. Returns `None` if subtitle format could not be recognized.
 ///
-/// Because the `.sub` file extension is ambiguous (both `MicroDVD` and `VobSub` use that extension) the
-/// function will return `None` in that case. Instead, use the content-aware `get_subtitle_format`
+/// Because the `.sub` file extension is ambiguous (`MicroDVD`, `VobSub` and SubViewer use that extension) the
+/// function will return `None` in that case (as for `.txt`, `.xml` and `.stl`). Instead, use the content-aware `get_subtitle_format`
 /// to handle this case correctly.
 ///
 /// `Option` is used to simplify handling with `PathBuf::extension()`.
 pub fn get_subtitle_format_by_extension(extension: Option<&OsStr>) -> Option<SubtitleFormat> {
-    let _ext_opt: Option<&OsStr> = extension.into();
+    let _ext_opt: Option<&OsStr> = extension;
 
     if _ext_opt == Some(OsStr::new("srt")) {
         Some(SubtitleFormat::SubRip)
@@ -168,6 +1151,38 @@ pub fn get_subtitle_format_by_extension(extension: Option<&OsStr>) -> Option<Sub
         Some(SubtitleFormat::SubStationAlpha)
     } else if _ext_opt == Some(OsStr::new("idx")) {
         Some(SubtitleFormat::VobSubIdx)
+    } else if _ext_opt == Some(OsStr::new("vtt")) {
+        Some(SubtitleFormat::WebVtt)
+    } else if _ext_opt == Some(OsStr::new("ttml")) || _ext_opt == Some(OsStr::new("dfxp")) {
+        Some(SubtitleFormat::Ttml)
+    } else if _ext_opt == Some(OsStr::new("sbv")) {
+        Some(SubtitleFormat::Sbv)
+    } else if _ext_opt == Some(OsStr::new("lrc")) {
+        Some(SubtitleFormat::Lrc)
+    } else if _ext_opt == Some(OsStr::new("scc")) {
+        Some(SubtitleFormat::Scc)
+    } else if _ext_opt == Some(OsStr::new("pac")) {
+        Some(SubtitleFormat::Pac)
+    } else if _ext_opt == Some(OsStr::new("mpl")) {
+        Some(SubtitleFormat::Mpl2)
+    } else if _ext_opt == Some(OsStr::new("sup")) {
+        Some(SubtitleFormat::Pgs)
+    } else if _ext_opt == Some(OsStr::new("jss")) {
+        Some(SubtitleFormat::JacoSub)
+    } else if _ext_opt == Some(OsStr::new("rt")) {
+        Some(SubtitleFormat::RealText)
+    } else if _ext_opt == Some(OsStr::new("itt")) {
+        Some(SubtitleFormat::Itt)
+    } else if _ext_opt == Some(OsStr::new("aqt")) {
+        Some(SubtitleFormat::AqTitle)
+    } else if _ext_opt == Some(OsStr::new("psb")) {
+        Some(SubtitleFormat::PowerDivX)
+    } else if _ext_opt == Some(OsStr::new("srv3")) || _ext_opt == Some(OsStr::new("ytt")) {
+        Some(SubtitleFormat::Srv3)
+    } else if _ext_opt == Some(OsStr::new("smi")) || _ext_opt == Some(OsStr::new("sami")) {
+        Some(SubtitleFormat::Sami)
+    } else if _ext_opt == Some(OsStr::new("dvbsub")) {
+        Some(SubtitleFormat::Dvb)
     } else {
         None
     }
@@ -183,6 +1198,27 @@ pub fn is_valid_extension_for_subtitle_format(extension: Option<&OsStr>, format:
         SubtitleFormat::VobSubIdx => extension == Some(OsStr::new("idx")),
         SubtitleFormat::VobSubSub => extension == Some(OsStr::new("sub")),
         SubtitleFormat::MicroDVD => extension == Some(OsStr::new("sub")),
+        SubtitleFormat::WebVtt => extension == Some(OsStr::new("vtt")),
+        SubtitleFormat::Ttml => extension == Some(OsStr::new("ttml")) || extension == Some(OsStr::new("dfxp")),
+        SubtitleFormat::Sbv => extension == Some(OsStr::new("sbv")),
+        SubtitleFormat::Lrc => extension == Some(OsStr::new("lrc")),
+        SubtitleFormat::Scc => extension == Some(OsStr::new("scc")),
+        SubtitleFormat::EbuStl => extension == Some(OsStr::new("stl")),
+        SubtitleFormat::Pac => extension == Some(OsStr::new("pac")),
+        SubtitleFormat::Mpl2 => extension == Some(OsStr::new("txt")) || extension == Some(OsStr::new("mpl")),
+        SubtitleFormat::Pgs => extension == Some(OsStr::new("sup")),
+        SubtitleFormat::JacoSub => extension == Some(OsStr::new("jss")),
+        SubtitleFormat::SubViewer => extension == Some(OsStr::new("sub")),
+        SubtitleFormat::RealText => extension == Some(OsStr::new("rt")),
+        SubtitleFormat::Itt => extension == Some(OsStr::new("itt")),
+        SubtitleFormat::Bdn => extension == Some(OsStr::new("xml")),
+        SubtitleFormat::SpruceStl => extension == Some(OsStr::new("stl")),
+        SubtitleFormat::AqTitle => extension == Some(OsStr::new("aqt")),
+        SubtitleFormat::VPlayer => extension == Some(OsStr::new("txt")),
+        SubtitleFormat::PowerDivX => extension == Some(OsStr::new("psb")),
+        SubtitleFormat::Srv3 => extension == Some(OsStr::new("srv3")) || extension == Some(OsStr::new("ytt")),
+        SubtitleFormat::Sami => extension == Some(OsStr::new("smi")) || extension == Some(OsStr::new("sami")),
+        SubtitleFormat::Dvb => extension == Some(OsStr::new("dvbsub")),
     }
 }
 
@@ -201,7 +1237,7 @@ pub fn get_subtitle_format_by_extension_err(extension: Option<&OsStr>) -> Result
 /// Calling the function with the full file path or simply a `get_subtitle_format(".sub", content)`
 /// both work. Returns `None` if subtitle format could not be recognized.
 ///
-/// It works exactly the same as `get_subtitle_format_by_extension` (see documentation), but also handles the  `.sub` cases
+/// It works exactly the same as `get_subtitle_format_by_extension` (see documentation), but also handles the  `.sub`, `.txt`, `.xml` and `.stl` cases
 /// correctly by using the provided content of the file as secondary info.
 ///
 /// `Option` is used to simplify handling with `PathBuf::extension()`.
@@ -210,9 +1246,44 @@ pub fn get_subtitle_format(extension: Option<&OsStr>, content: &[u8]) -> Option<
         // test for VobSub .sub magic number
         if content.iter().take(4).cloned().eq([0x00, 0x00, 0x01, 0xba].iter().cloned()) {
             Some(SubtitleFormat::VobSubSub)
+        } else if subviewer::looks_like_subviewer(&String::from_utf8_lossy(content)) {
+            Some(SubtitleFormat::SubViewer)
         } else {
             Some(SubtitleFormat::MicroDVD)
         }
+    } else if extension == Some(OsStr::new("txt")) {
+        // `.txt` is used by several formats
+        let text = String::from_utf8_lossy(content);
+        if mpl2::looks_like_mpl2(&text) {
+            Some(SubtitleFormat::Mpl2)
+        } else if vplayer::looks_like_vplayer(&text) {
+            Some(SubtitleFormat::VPlayer)
+        } else if powerdivx::looks_like_powerdivx(&text) {
+            Some(SubtitleFormat::PowerDivX)
+        } else if text.trim_start_matches('\u{feff}').trim_start().starts_with('{') {
+            Some(SubtitleFormat::MicroDVD)
+        } else {
+            None
+        }
+    } else if extension == Some(OsStr::new("stl")) {
+        // the binary EBU-STL and the Spruce STL text format use `.stl`
+        if spruce::looks_like_spruce(&String::from_utf8_lossy(content)) {
+            Some(SubtitleFormat::SpruceStl)
+        } else {
+            Some(SubtitleFormat::EbuStl)
+        }
+    } else if extension == Some(OsStr::new("xml")) {
+        // `.xml` is used by BDN, srv3 and TTML files
+        let text = String::from_utf8_lossy(content);
+        if bdn::looks_like_bdn(&text) {
+            Some(SubtitleFormat::Bdn)
+        } else if srv3::looks_like_srv3(&text) {
+            Some(SubtitleFormat::Srv3)
+        } else if text.contains("<tt") {
+            Some(SubtitleFormat::Ttml)
+        } else {
+            None
+        }
     } else {
         get_subtitle_format_by_extension(extension)
     }
@@ -240,18 +1311,78 @@ pub fn parse_str(format: SubtitleFormat, content: &str, fps: f64) -> Result<Subt
         SubtitleFormat::VobSubIdx => Ok(idx::IdxFile::parse(content)?.into()),
         SubtitleFormat::VobSubSub => Err(ErrorKind::TextFormatOnly.into()),
         SubtitleFormat::MicroDVD => Ok(microdvd::MdvdFile::parse(content, fps)?.into()),
+        SubtitleFormat::WebVtt => Ok(vtt::VttFile::parse(content)?.into()),
+        SubtitleFormat::Ttml => Ok(ttml::TtmlFile::parse(content)?.into()),
+        SubtitleFormat::Sbv => Ok(sbv::SbvFile::parse(content)?.into()),
+        SubtitleFormat::Lrc => Ok(lrc::LrcFile::parse(content)?.into()),
+        SubtitleFormat::Scc => Ok(scc::SccFile::parse(content)?.into()),
+        SubtitleFormat::EbuStl => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::Pac => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::Mpl2 => Ok(mpl2::Mpl2File::parse(content)?.into()),
+        SubtitleFormat::Pgs => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::JacoSub => Ok(jacosub::JacoFile::parse(content)?.into()),
+        SubtitleFormat::SubViewer => Ok(subviewer::SubViewerFile::parse(content)?.into()),
+        SubtitleFormat::RealText => Ok(realtext::RealTextFile::parse(content)?.into()),
+        SubtitleFormat::Itt => Ok(itt::IttFile::parse(content)?.into()),
+        SubtitleFormat::Bdn => Ok(bdn::BdnFile::parse(content)?.into()),
+        SubtitleFormat::SpruceStl => Ok(spruce::SpruceStlFile::parse(content, fps)?.into()),
+        SubtitleFormat::AqTitle => Ok(aqtitle::AqTitleFile::parse(content, fps)?.into()),
+        SubtitleFormat::VPlayer => Ok(vplayer::VPlayerFile::parse(content)?.into()),
+        SubtitleFormat::PowerDivX => Ok(powerdivx::PowerDivXFile::parse(content)?.into()),
+        SubtitleFormat::Srv3 => Ok(srv3::Srv3File::parse(content)?.into()),
+        SubtitleFormat::Sami => Ok(sami::SamiFile::parse(content)?.into()),
+        SubtitleFormat::Dvb => Err(ErrorKind::TextFormatOnly.into()),
+    }
+}
+
+/// Works exactly like `parse_str`, but also returns all non-fatal issues that were found in the file.
+pub fn parse_str_with_warnings(format: SubtitleFormat, content: &str, fps: f64) -> Result<(SubtitleFile, Warnings)> {
+    fn convert<T: Into<SubtitleFile>>((file, warnings): (T, Warnings)) -> (SubtitleFile, Warnings) {
+        (file.into(), warnings)
     }
+
+    match format {
+        SubtitleFormat::SubRip => Ok(convert(srt::SrtFile::parse_with_warnings(content)?)),
+        SubtitleFormat::SubStationAlpha => Ok(convert(ssa::SsaFile::parse_with_warnings(content)?)),
+        SubtitleFormat::VobSubIdx => Ok(convert(idx::IdxFile::parse_with_warnings(content)?)),
+        SubtitleFormat::VobSubSub => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::MicroDVD => Ok(convert(microdvd::MdvdFile::parse_with_warnings(content, fps)?)),
+        SubtitleFormat::WebVtt => Ok(convert(vtt::VttFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Ttml => Ok(convert(ttml::TtmlFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Sbv => Ok(convert(sbv::SbvFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Lrc => Ok(convert(lrc::LrcFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Scc => Ok((scc::SccFile::parse(content)?.into(), Warnings::new())),
+        SubtitleFormat::EbuStl => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::Pac => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::Mpl2 => Ok(convert(mpl2::Mpl2File::parse_with_warnings(content)?)),
+        SubtitleFormat::Pgs => Err(ErrorKind::TextFormatOnly.into()),
+        SubtitleFormat::JacoSub => Ok(convert(jacosub::JacoFile::parse_with_warnings(content)?)),
+        SubtitleFormat::SubViewer => Ok(convert(subviewer::SubViewerFile::parse_with_warnings(content)?)),
+        SubtitleFormat::RealText => Ok(convert(realtext::RealTextFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Itt => Ok(convert(itt::IttFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Bdn => Ok(convert(bdn::BdnFile::parse_with_warnings(content)?)),
+        SubtitleFormat::SpruceStl => Ok(convert(spruce::SpruceStlFile::parse_with_warnings(content, fps)?)),
+        SubtitleFormat::AqTitle => Ok(convert(aqtitle::AqTitleFile::parse_with_warnings(content, fps)?)),
+        SubtitleFormat::VPlayer => Ok(convert(vplayer::VPlayerFile::parse_with_warnings(content)?)),
+        SubtitleFormat::PowerDivX => Ok(convert(powerdivx::PowerDivXFile::parse_with_warnings(content)?)),
+        SubtitleFormat::Srv3 => Ok(convert(srv3::Srv3File::parse_with_warnings(content)?)),
+        SubtitleFormat::Sami => Ok((sami::SamiFile::parse(content)?.into(), Warnings::new())),
+        SubtitleFormat::Dvb => Err(ErrorKind::TextFormatOnly.into()),
+    }
+}
+
+/// Detects the character encoding of text subtitles with `chardet`.
+pub(crate) fn detect_encoding(content: &[u8]) -> Result<&'static Encoding> {
+    let (charset, _, _) = detect(content);
+    let encoding_name = charset2encoding(&charset);
+    Ok(Encoding::for_label_no_replacement(encoding_name.as_bytes()).ok_or(ErrorKind::EncodingDetectionError)?)
 }
 
 /// Helper function for text subtitles for byte-to-text decoding (use `None` for automatic detection).
-fn decode_bytes_to_string(content: &[u8], encoding: Option<&'static Encoding>) -> Result<String> {
+pub(crate) fn decode_bytes_to_string(content: &[u8], encoding: Option<&'static Encoding>) -> Result<String> {
     let det_encoding = match encoding {
         Some(encoding) => encoding,
-        None => {
-            let (charset, _, _) = detect(content);
-            let encoding_name = charset2encoding(&charset);
-            Encoding::for_label_no_replacement(encoding_name.as_bytes()).ok_or(ErrorKind::EncodingDetectionError)?
-        }
+        None => detect_encoding(content)?,
     };
 
     let (decoded, _, replaced) = det_encoding.decode(content);
@@ -283,5 +1414,40 @@ pub fn parse_bytes(format: SubtitleFormat, content: &[u8], encoding: Option<&'st
         SubtitleFormat::VobSubIdx => Ok(idx::IdxFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
         SubtitleFormat::VobSubSub => Ok(vobsub::VobFile::parse(content)?.into()),
         SubtitleFormat::MicroDVD => Ok(microdvd::MdvdFile::parse(&decode_bytes_to_string(content, encoding)?, fps)?.into()),
+        SubtitleFormat::WebVtt => Ok(vtt::VttFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Ttml => Ok(ttml::TtmlFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Sbv => Ok(sbv::SbvFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Lrc => Ok(lrc::LrcFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Scc => Ok(scc::SccFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::EbuStl => Ok(stl::StlFile::parse(content)?.into()),
+        SubtitleFormat::Pac => Ok(pac::PacFile::parse(content, fps)?.into()),
+        SubtitleFormat::Mpl2 => Ok(mpl2::Mpl2File::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Pgs => Ok(pgs::PgsFile::parse(content)?.into()),
+        SubtitleFormat::JacoSub => Ok(jacosub::JacoFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::SubViewer => Ok(subviewer::SubViewerFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::RealText => Ok(realtext::RealTextFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Itt => Ok(itt::IttFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Bdn => Ok(bdn::BdnFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::SpruceStl => Ok(spruce::SpruceStlFile::parse(&decode_bytes_to_string(content, encoding)?, fps)?.into()),
+        SubtitleFormat::AqTitle => Ok(aqtitle::AqTitleFile::parse(&decode_bytes_to_string(content, encoding)?, fps)?.into()),
+        SubtitleFormat::VPlayer => Ok(vplayer::VPlayerFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::PowerDivX => Ok(powerdivx::PowerDivXFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Srv3 => Ok(srv3::Srv3File::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Sami => Ok(sami::SamiFile::parse(&decode_bytes_to_string(content, encoding)?)?.into()),
+        SubtitleFormat::Dvb => Ok(dvb::DvbFile::parse(content)?.into()),
+    }
+}
+
+/// Detects the format by the file extension and the content and parses the file.
+///
+/// The built-in formats are tried first (like in `get_subtitle_format`), then the providers which were
+/// registered by `registry::register_format_provider()`. See `parse_bytes` for the other parameters.
+pub fn parse_auto(extension: Option<&OsStr>, content: &[u8], encoding: Option<&'static Encoding>, fps: f64) -> Result<SubtitleFile> {
+    if let Some(format) = get_subtitle_format(extension, content) {
+        return parse_bytes(format, content, encoding, fps);
+    }
+    match registered_formats().detect(extension, content) {
+        Some(provider) => Ok(SubtitleFile::External(provider.parse(content, encoding, fps)?)),
+        None => Err(ErrorKind::UnknownFileFormat.into()),
     }
 }
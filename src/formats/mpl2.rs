@@ -0,0 +1,292 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+
+/// Errors specific to MPL2-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected MPL2 line like `[10][25]Hello|world`, found '{}'", line)]
+        ExpectedMpl2Line { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A line of a MPL2 file.
+enum Mpl2Line {
+    /// An empty line or a line which is not a subtitle (kept as it is).
+    Filler(String),
+
+    /// A subtitle like `[10][25]Hello|/world`.
+    Entry {
+        timespan: TimeSpan,
+
+        /// The text as it is written in the file.
+        raw: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Represents a MPL2 file (usually with the extension `.txt`), which is still common for Polish subtitles.
+///
+/// Every line is a subtitle like `[10][25]Hello|/world` with the start and end in deciseconds. A `|`
+/// separates the lines of the text and a `/` at the start of a line makes it italic (which is converted to
+/// `<i>...</i>` in the entry text). Lines which aren't subtitles are kept, and texts are only rewritten if
+/// they change.
+pub struct Mpl2File {
+    v: Vec<(Mpl2Line, String)>,
+}
+
+impl Mpl2File {
+    /// Parse a MPL2 subtitle string to `Mpl2File`.
+    pub fn parse(s: &str) -> SubtitleParserResult<Mpl2File> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a MPL2 subtitle string to `Mpl2File` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(Mpl2File, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a MPL2 file from scratch.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<Mpl2File> {
+        let lines = v
+            .into_iter()
+            .map(|(timespan, text)| {
+                (
+                    Mpl2Line::Entry {
+                        timespan,
+                        raw: encode_text(&text),
+                    },
+                    "\n".to_string(),
+                )
+            })
+            .collect();
+        Ok(Mpl2File { v: lines })
+    }
+
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<Mpl2File> {
+        let (bom, s) = split_bom(i);
+
+        let mut result = Vec::new();
+        if !bom.is_empty() {
+            result.push((Mpl2Line::Filler(bom.to_string()), String::new()));
+        }
+        let mut found_entry = false;
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            match parse_line(&line) {
+                Some((timespan, raw)) => {
+                    found_entry = true;
+                    result.push((
+                        Mpl2Line::Entry {
+                            timespan,
+                            raw: raw.to_string(),
+                        },
+                        newline,
+                    ));
+                }
+                None if line.trim().is_empty() => result.push((Mpl2Line::Filler(line), newline)),
+                // a file whose first content isn't a subtitle is not a MPL2 file
+                None if !found_entry => {
+                    return Err(Error::from(ExpectedMpl2Line { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                None => {
+                    warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+                    result.push((Mpl2Line::Filler(line), newline));
+                }
+            }
+        }
+        Ok(Mpl2File { v: result })
+    }
+}
+
+/// Returns `true` if the first non-empty line of the text looks like a MPL2 subtitle.
+pub(crate) fn looks_like_mpl2(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    text.lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| parse_line(line).is_some())
+}
+
+/// Parses a time like `[25]` at the start of the string and returns the rest.
+fn parse_time(s: &str) -> Option<(TimePoint, &str)> {
+    let rest = s.trim_start().strip_prefix('[')?;
+    let (number, rest) = rest.split_once(']')?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((TimePoint::from_msecs(number.parse::<i64>().ok()?.checked_mul(100)?), rest))
+}
+
+/// Parses a line like `[10][25]Hello` into the timespan and the raw text.
+fn parse_line(line: &str) -> Option<(TimeSpan, &str)> {
+    let (start, rest) = parse_time(line)?;
+    let (end, text) = parse_time(rest)?;
+    Some((TimeSpan::new(start, end), text))
+}
+
+/// Converts the raw text of a subtitle into an entry text.
+fn decode_text(raw: &str) -> String {
+    let lines: Vec<String> = raw
+        .split('|')
+        .map(|line| match line.strip_prefix('/') {
+            Some(italic) => format!("<i>{}</i>", italic),
+            None => line.to_string(),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Converts an entry text into the raw text of a subtitle.
+fn encode_text(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let italic = line.strip_prefix("<i>").and_then(|line| line.strip_suffix("</i>"));
+            match italic {
+                Some(italic) if !italic.contains("<i>") => format!("/{}", italic),
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    lines.join("|")
+}
+
+/// Formats a time in deciseconds (negative times are written as zero).
+fn format_time(t: TimePoint) -> String {
+    ((t.msecs().max(0) + 50) / 100).to_string()
+}
+
+impl Mpl2File {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .flat_map(|(line, newline)| {
+                let line = match *line {
+                    Mpl2Line::Filler(ref text) => Some(text.as_str()),
+                    Mpl2Line::Entry { .. } => None,
+                };
+                vec![line, Some(newline.as_str())]
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for Mpl2File {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .filter_map(|(line, _)| match *line {
+                Mpl2Line::Filler(_) => None,
+                Mpl2Line::Entry { timespan, ref raw } => Some(SubtitleEntry::new(timespan, decode_text(raw))),
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, Mpl2Line::Entry { .. })).count();
+        check_entry_count(count, new_subtitle_entries.len())?;
+
+        let entries = self.v.iter_mut().filter_map(|(line, _)| match *line {
+            Mpl2Line::Filler(_) => None,
+            Mpl2Line::Entry {
+                ref mut timespan,
+                ref mut raw,
+            } => Some((timespan, raw)),
+        });
+        for ((timespan, raw), new_entry) in entries.zip(new_subtitle_entries) {
+            *timespan = new_entry.timespan;
+            if let Some(ref text) = new_entry.line {
+                if decode_text(raw) != *text {
+                    *raw = encode_text(text);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let result: String = self
+            .v
+            .iter()
+            .map(|(line, newline)| match *line {
+                Mpl2Line::Filler(ref text) => format!("{}{}", text, newline),
+                Mpl2Line::Entry { timespan, ref raw } => {
+                    format!("[{}][{}]{}{}", format_time(timespan.start), format_time(timespan.end), raw, newline)
+                }
+            })
+            .collect();
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, Mpl2Line::Entry { .. })).count();
+        check_entry_count(count, keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|(line, _)| match *line {
+            Mpl2Line::Filler(_) => true,
+            Mpl2Line::Entry { .. } => keep_iter.next().cloned().unwrap_or(true),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn mpl2_round_trip_and_edit() {
+        let input = "\u{feff}[10][25]Hello|/world\r\n[30][42]Second\r\n\r\nnot a subtitle\r\n[50][61]/Third|line\r\n";
+        let (mut file, warnings) = Mpl2File::parse_with_warnings(input).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), input);
+        assert_eq!(warnings.len(), 1);
+
+        let mut entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\n<i>world</i>"));
+        assert_eq!((entries[1].timespan.start.msecs(), entries[1].timespan.end.msecs()), (3000, 4200));
+
+        entries[0].timespan.end += TimeDelta::from_msecs(260);
+        entries[1].line = Some("<i>Changed</i>\nand more".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[true, true, false]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "\u{feff}[10][28]Hello|/world\r\n[30][42]/Changed|and more\r\n\r\nnot a subtitle\r\n"
+        );
+
+        assert!(looks_like_mpl2("\n[1][2]x\n"));
+        assert!(!looks_like_mpl2("{1}{2}x\n"));
+        assert!(Mpl2File::parse("{1}{2}x\n").is_err());
+        assert!(Mpl2File::parse("[99999999999999999999][1]x\n").is_err());
+    }
+}
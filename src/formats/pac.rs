@@ -0,0 +1,263 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+use std::ops::Range;
+
+/// Errors specific to `.pac`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Clone, Copy, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "a PAC file starts with a 23 byte header with the first byte `01`")]
+        InvalidHeader,
+
+        #[fail(display = "the subtitle block at byte {} is truncated", offset)]
+        TruncatedBlock { offset: usize },
+    }
+}
+
+/// The size of the file header.
+const HEADER_SIZE: usize = 23;
+
+/// The size of the fixed part of a subtitle block (before the text).
+const BLOCK_HEADER_SIZE: usize = 13;
+
+/// Fields of a subtitle block.
+const BLOCK_NUMBER: Range<usize> = 1..3;
+const BLOCK_TIME_IN: Range<usize> = 3..7;
+const BLOCK_TIME_OUT: Range<usize> = 7..11;
+const BLOCK_LENGTH: Range<usize> = 11..13;
+
+/// Starts a row of the text (followed by the alignment and a format byte).
+const ROW_START: u8 = 0xfe;
+
+#[derive(Debug, Clone)]
+/// Represents a Screen Electronics `.pac` file (the binary exchange format of Cavena/Screen subtitling systems).
+///
+/// The header and all subtitle blocks are kept byte by byte, so only the changed time codes differ in the
+/// output. A time code is stored as two little-endian numbers that read as `HHMM` and `SSFF` in decimal.
+/// Rows of the text start with `FE`, the alignment and a format byte, and `<`/`>` switch italics on and
+/// off. Bytes outside of ASCII are read as ISO 8859-1, the other code pages of PAC (like Arabic, Hebrew or
+/// Chinese) are not supported. Text changes can't be written.
+pub struct PacFile {
+    header: Vec<u8>,
+
+    /// The subtitle blocks including their fixed part
+    blocks: Vec<Vec<u8>>,
+
+    /// Everything after the last complete block
+    trailer: Vec<u8>,
+
+    /// The frame rate of the time codes
+    fps: f64,
+}
+
+impl PacFile {
+    /// Parse the content of a `.pac` file to `PacFile` (with the frame rate of the time codes).
+    pub fn parse(b: &[u8], fps: f64) -> SubtitleParserResult<PacFile> {
+        Ok(Self::parse_file(b, fps).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn parse_file(b: &[u8], fps: f64) -> Result<PacFile> {
+        let (header, mut rest) = match b.get(..HEADER_SIZE) {
+            Some(header) if header.first() == Some(&1) => b.split_at(HEADER_SIZE),
+            _ => return Err(InvalidHeader.into()),
+        };
+
+        let mut blocks = Vec::new();
+        // every block starts with a zero byte, the end of the file often has other padding
+        while rest.len() >= BLOCK_HEADER_SIZE && rest.first() == Some(&0) {
+            let len = BLOCK_HEADER_SIZE + usize::from(read_u16(rest, BLOCK_LENGTH.start));
+            if rest.len() < len {
+                return Err(TruncatedBlock {
+                    offset: b.len() - rest.len(),
+                }
+                .into());
+            }
+            let (block, next) = rest.split_at(len);
+            blocks.push(block.to_vec());
+            rest = next;
+        }
+
+        Ok(PacFile {
+            header: header.to_vec(),
+            blocks,
+            trailer: rest.to_vec(),
+            fps,
+        })
+    }
+
+    fn timecode(&self, block: &[u8], range: Range<usize>) -> TimePoint {
+        let (hhmm, ssff) = (i64::from(read_u16(block, range.start)), i64::from(read_u16(block, range.start + 2)));
+        let secs = (hhmm / 100 * 60 + hhmm % 100) * 60 + ssff / 100;
+        TimePoint::from_msecs(secs * 1000 + ((ssff % 100) as f64 * 1000.0 / self.fps).round() as i64)
+    }
+
+    /// Returns the time code bytes of a time (negative times are written as zero).
+    fn timecode_bytes(&self, t: TimePoint) -> [u8; 4] {
+        let fps = self.fps.round().max(1.0) as i64;
+        let frames = (t.msecs().max(0) as f64 * self.fps / 1000.0).round() as i64;
+        let (secs, f) = (frames / fps, frames % fps);
+        let hhmm = ((secs / 3600).min(99) * 100 + secs / 60 % 60) as u16;
+        let ssff = (secs % 60 * 100 + f.min(99)) as u16;
+        let (hhmm, ssff) = (hhmm.to_le_bytes(), ssff.to_le_bytes());
+        [hhmm[0], hhmm[1], ssff[0], ssff[1]]
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    match data.get(pos..pos + 2) {
+        Some(&[lo, hi]) => u16::from_le_bytes([lo, hi]),
+        _ => 0,
+    }
+}
+
+/// Decodes the text of a block into an entry text with `<i>` tags.
+fn decode_text(text: &[u8]) -> String {
+    let lines: Vec<String> = text
+        .split(|&b| b == ROW_START)
+        .skip(1)
+        .map(|row| {
+            // skip the alignment and the format byte
+            let row = row.get(2..).unwrap_or_default();
+            let mut line = String::new();
+            let mut italic = false;
+            for &b in row {
+                match b {
+                    b'<' => {
+                        line.push_str("<i>");
+                        italic = true;
+                    }
+                    b'>' => {
+                        line.push_str("</i>");
+                        italic = false;
+                    }
+                    0x00..=0x1f | 0x7f => {}
+                    b => line.push(char::from(b)),
+                }
+            }
+            if italic {
+                line.push_str("</i>");
+            }
+            line.trim_end().to_string()
+        })
+        .collect();
+    lines.join("\n")
+}
+
+impl SubtitleFileInterface for PacFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .blocks
+            .iter()
+            .map(|block| {
+                let timespan = TimeSpan::new(self.timecode(block, BLOCK_TIME_IN), self.timecode(block, BLOCK_TIME_OUT));
+                SubtitleEntry::new(timespan, decode_text(block.get(BLOCK_HEADER_SIZE..).unwrap_or_default()))
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.blocks.len(), new_subtitle_entries.len())?;
+        let old_entries = self.get_subtitle_entries()?;
+
+        for (idx, (old, new)) in old_entries.iter().zip(new_subtitle_entries).enumerate() {
+            for (range, old_time, new_time) in [
+                (BLOCK_TIME_IN, old.timespan.start, new.timespan.start),
+                (BLOCK_TIME_OUT, old.timespan.end, new.timespan.end),
+            ] {
+                // keep the original frames of times that didn't change
+                if old_time == new_time {
+                    continue;
+                }
+                let bytes = self.timecode_bytes(new_time);
+                if let Some(dest) = self.blocks.get_mut(idx).and_then(|block| block.get_mut(range)) {
+                    dest.copy_from_slice(&bytes);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = self.header.clone();
+        result.extend(self.blocks.iter().flatten());
+        result.extend(&self.trailer);
+        Ok(result)
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.blocks.len(), keep.len())?;
+
+        let first_number = self.blocks.first().map_or(0, |block| read_u16(block, BLOCK_NUMBER.start));
+        let mut keep = keep.iter();
+        self.blocks.retain(|_| keep.next().copied().unwrap_or(true));
+        for (number, block) in (first_number..).zip(self.blocks.iter_mut()) {
+            if let Some(dest) = block.get_mut(BLOCK_NUMBER) {
+                dest.copy_from_slice(&number.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u16, time_in: [u16; 2], time_out: [u16; 2], text: &[u8]) -> Vec<u8> {
+        let mut block = vec![0];
+        for n in [number, time_in[0], time_in[1], time_out[0], time_out[1], text.len() as u16] {
+            block.extend_from_slice(&n.to_le_bytes());
+        }
+        block.extend_from_slice(text);
+        block
+    }
+
+    #[test]
+    fn pac_timing_and_text() {
+        let mut input = vec![1];
+        input.resize(HEADER_SIZE, 0);
+        input.extend(block(1, [1000, 112], [1000, 300], b"\x0a\x00\xfe\x02\x03Hello\xfe\x02\x03<Caf\xe9>"));
+        let second = input.len();
+        input.extend(block(2, [1000, 410], [1000, 600], b"\x0a\x00\xfe\x02\x03World"));
+        input.extend_from_slice(&[0xff; 4]);
+
+        let mut file = PacFile::parse(&input, 25.0).unwrap();
+        assert_eq!(file.to_data().unwrap(), input);
+        let mut entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\n<i>Café</i>"));
+        assert_eq!(
+            (entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()),
+            (36_001_480, 36_003_000)
+        );
+
+        entries[1].timespan.start = TimePoint::from_msecs(36_004_040);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = file.to_data().unwrap();
+        assert_eq!(&data[second + 3..second + 7], &[0xe8, 0x03, 0x91, 0x01]);
+        assert_eq!(data.len(), input.len());
+
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        let data = file.to_data().unwrap();
+        assert_eq!(&data[HEADER_SIZE + 1..HEADER_SIZE + 3], &[1, 0]);
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("World"));
+
+        assert!(PacFile::parse(&input[..HEADER_SIZE + 20], 25.0).is_err());
+        assert!(PacFile::parse(b"not a pac file at all..", 25.0).is_err());
+    }
+}
@@ -0,0 +1,636 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::bitmaps::{check_placements, BitmapPlacement, BitmapRect};
+use crate::errors::Result as SubtitleParserResult;
+use crate::{SubtitleEntry, SubtitleFileInterface, SubtitleFormat};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+use std::convert::TryFrom;
+
+/// Errors specific to `.sup`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Clone, Copy, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected the magic number `PG` of a segment at byte {}", offset)]
+        ExpectedSegment { offset: usize },
+
+        #[fail(display = "the segment at byte {} is truncated", offset)]
+        TruncatedSegment { offset: usize },
+
+        #[fail(display = "unknown segment type `{:02X}` at byte {}", kind, offset)]
+        UnknownSegmentType { offset: usize, kind: u8 },
+    }
+}
+
+/// The size of the segment header (`PG`, PTS, DTS, type and size).
+const SEGMENT_HEADER_SIZE: usize = 13;
+
+/// The segment types.
+const PALETTE_DEFINITION: u8 = 0x14;
+const OBJECT_DEFINITION: u8 = 0x15;
+const PRESENTATION_COMPOSITION: u8 = 0x16;
+const WINDOW_DEFINITION: u8 = 0x17;
+const END_OF_DISPLAY_SET: u8 = 0x80;
+const SEGMENT_TYPES: [u8; 5] = [
+    PALETTE_DEFINITION,
+    OBJECT_DEFINITION,
+    PRESENTATION_COMPOSITION,
+    WINDOW_DEFINITION,
+    END_OF_DISPLAY_SET,
+];
+
+/// The size of the fixed part of the presentation composition segment (before the composition objects).
+const PCS_HEADER_SIZE: usize = 11;
+
+/// The flag of a composition object which is shown even if subtitles are disabled.
+const OBJECT_FORCED: u8 = 0x40;
+
+/// The flag of a composition object with a cropping rectangle.
+const OBJECT_CROPPED: u8 = 0x80;
+
+/// The size of a window in a window definition segment (id, x, y, width and height).
+const WINDOW_SIZE: usize = 9;
+
+/// The flag of the first fragment of an object definition segment (which contains the size of the object).
+const FIRST_IN_SEQUENCE: u8 = 0x80;
+
+/// The clock of the time stamps.
+const TICKS_PER_MSEC: i64 = 90;
+
+#[derive(Debug, Clone)]
+/// A segment of the Presentation Graphic Stream.
+struct PgsSegment {
+    /// The presentation time stamp (in 90 kHz ticks)
+    pts: u32,
+
+    /// The decoding time stamp (in 90 kHz ticks, usually `0`)
+    dts: u32,
+
+    /// The segment type (like `PRESENTATION_COMPOSITION`)
+    kind: u8,
+
+    data: Vec<u8>,
+}
+
+/// A composition object of a presentation composition segment: an object which is shown in a window.
+#[derive(Debug, Clone, Copy)]
+struct CompositionObject {
+    /// The position in the data of the segment
+    pos: usize,
+
+    object_id: u16,
+    window_id: u8,
+    flags: u8,
+    x: u16,
+    y: u16,
+    crop: Option<BitmapRect>,
+}
+
+impl CompositionObject {
+    /// Returns the composition objects of the data of a presentation composition segment.
+    fn parse_all(data: &[u8]) -> Vec<CompositionObject> {
+        let count = data.get(PCS_HEADER_SIZE - 1).copied().unwrap_or_default();
+        let mut pos = PCS_HEADER_SIZE;
+        let mut result = Vec::new();
+        for _ in 0..count {
+            // object id (2 bytes), window id, flags, x and y (2 bytes each) and an optional cropping rectangle
+            let flags = data.get(pos + 3).copied().unwrap_or_default();
+            let crop = if flags & OBJECT_CROPPED != 0 {
+                Some(read_rect(data, pos + 8))
+            } else {
+                None
+            };
+            result.push(CompositionObject {
+                pos,
+                object_id: read_u16(data, pos),
+                window_id: data.get(pos + 2).copied().unwrap_or_default(),
+                flags,
+                x: read_u16(data, pos + 4),
+                y: read_u16(data, pos + 6),
+                crop,
+            });
+            pos += if crop.is_some() { 16 } else { 8 };
+        }
+        result
+    }
+}
+
+/// A display set: the segments from one presentation composition segment to the next.
+#[derive(Debug, Clone, Copy)]
+struct DisplaySet {
+    /// The index of the presentation composition segment
+    first: usize,
+
+    /// The index after the last segment
+    end: usize,
+
+    /// The number of composition objects (`0` clears the screen)
+    objects: usize,
+
+    forced: bool,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a Blu-ray `.sup` file (a Presentation Graphic Stream, also called PGS or HDMV subtitles).
+///
+/// The file is a sequence of segments which define palettes (PDS), bitmaps (ODS), windows (WDS) and which
+/// images are shown (PCS). Every presentation composition segment starts a display set, a display set with
+/// composition objects is an entry that is shown until the next display set. The entries have no text, their
+/// `forced` flag is set if an object is marked as forced.
+///
+/// All segments are kept, so an unchanged file is written identically. Changing the timing moves all segments
+/// of the display set (keeping the distance between the decoding and the presentation time), and the end time
+/// moves the display set which clears the screen. If the next entry directly follows an entry, its start can't
+/// be moved independently from the end of the entry, so the end time is only applied to display sets without
+/// objects. Entries can't be removed, because later display sets may depend on the palettes and objects of
+/// earlier ones. The positions of the images can be changed with `set_bitmap_placements()`.
+pub struct PgsFile {
+    segments: Vec<PgsSegment>,
+}
+
+impl PgsFile {
+    /// Parse the content of a `.sup` file to `PgsFile`.
+    pub fn parse(b: &[u8]) -> SubtitleParserResult<PgsFile> {
+        Ok(Self::parse_file(b).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn parse_file(b: &[u8]) -> Result<PgsFile> {
+        let mut segments = Vec::new();
+        let mut rest = b;
+        while !rest.is_empty() {
+            let offset = b.len() - rest.len();
+            let header = match rest.get(..SEGMENT_HEADER_SIZE) {
+                Some(header) if header.starts_with(b"PG") => header,
+                Some(_) => return Err(ExpectedSegment { offset }.into()),
+                None => return Err(TruncatedSegment { offset }.into()),
+            };
+            let len = SEGMENT_HEADER_SIZE + usize::from(read_u16(header, 11));
+            if rest.len() < len {
+                return Err(TruncatedSegment { offset }.into());
+            }
+            let (segment, next) = rest.split_at(len);
+            let kind = segment.get(10).copied().unwrap_or_default();
+            if !SEGMENT_TYPES.contains(&kind) {
+                return Err(UnknownSegmentType { offset, kind }.into());
+            }
+            segments.push(PgsSegment {
+                pts: read_u32(segment, 2),
+                dts: read_u32(segment, 6),
+                kind,
+                data: segment.get(SEGMENT_HEADER_SIZE..).unwrap_or_default().to_vec(),
+            });
+            rest = next;
+        }
+        Ok(PgsFile { segments })
+    }
+
+    fn display_sets(&self) -> Vec<DisplaySet> {
+        let mut result: Vec<DisplaySet> = Vec::new();
+        for (idx, segment) in self.segments.iter().enumerate() {
+            if segment.kind != PRESENTATION_COMPOSITION {
+                continue;
+            }
+            if let Some(last) = result.last_mut() {
+                last.end = idx;
+            }
+            let objects = CompositionObject::parse_all(&segment.data);
+            result.push(DisplaySet {
+                first: idx,
+                end: self.segments.len(),
+                objects: objects.len(),
+                forced: objects.iter().any(|object| object.flags & OBJECT_FORCED != 0),
+            });
+        }
+        result
+    }
+
+    fn set_time(&self, set: &DisplaySet) -> TimePoint {
+        let pts = self.segments.get(set.first).map_or(0, |segment| segment.pts);
+        TimePoint::from_msecs(i64::from(pts) / TICKS_PER_MSEC)
+    }
+
+    /// Returns the display sets of every entry, with the display set that follows it.
+    fn entry_sets(&self) -> Vec<(DisplaySet, Option<DisplaySet>)> {
+        let sets = self.display_sets();
+        sets.iter()
+            .enumerate()
+            .filter(|(_, set)| set.objects > 0)
+            .map(|(idx, &set)| (set, sets.get(idx + 1).copied()))
+            .collect()
+    }
+
+    /// Moves all segments of a display set to a new presentation time.
+    fn move_set(&mut self, set: &DisplaySet, t: TimePoint) {
+        let pts = u32::try_from(t.msecs().max(0).saturating_mul(TICKS_PER_MSEC)).unwrap_or(u32::MAX);
+        for segment in self.segments.iter_mut().take(set.end).skip(set.first) {
+            let delta = i64::from(pts) - i64::from(segment.pts);
+            segment.dts = u32::try_from((i64::from(segment.dts) + delta).max(0)).unwrap_or(u32::MAX);
+            segment.pts = pts;
+        }
+    }
+}
+
+impl PgsFile {
+    /// Returns the images of every entry with their position, cropping rectangle and window (see `bitmaps`).
+    pub fn bitmap_placements(&self) -> Vec<Vec<BitmapPlacement>> {
+        self.entry_sets()
+            .iter()
+            .map(|(set, _)| {
+                let data = self.segments.get(set.first).map(|segment| segment.data.as_slice()).unwrap_or_default();
+                CompositionObject::parse_all(data)
+                    .iter()
+                    .map(|object| {
+                        let (width, height) = self.object_size(object.object_id, set.end);
+                        BitmapPlacement {
+                            x: object.x,
+                            y: object.y,
+                            width,
+                            height,
+                            crop: object.crop,
+                            window: self.find_window(object.window_id, set.end).map(|(_, _, window)| window),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Changes the positions, cropping rectangles and windows of the images of every entry (see `bitmaps`).
+    ///
+    /// The sizes of the images can't be changed, and the images have to be inside of their windows and the
+    /// screen. A window can only be changed by the entry whose display set defines it.
+    pub fn set_bitmap_placements(&mut self, placements: &[Vec<BitmapPlacement>]) -> SubtitleParserResult<()> {
+        let entry_sets = self.entry_sets();
+        check_entry_count(entry_sets.len(), placements.len())?;
+        let old_placements = self.bitmap_placements();
+
+        for (entry_idx, (((set, _), old), new)) in entry_sets.iter().zip(&old_placements).zip(placements).enumerate() {
+            if old == new {
+                continue;
+            }
+            let data = self.segments.get(set.first).map(|segment| segment.data.clone()).unwrap_or_default();
+            let screen = (read_u16(&data, 0), read_u16(&data, 2));
+            check_placements(entry_idx, old, new, Some(screen)).with_context(|_| crate::ErrorKind::UpdatingEntriesError)?;
+
+            let objects = CompositionObject::parse_all(&data);
+            // from the last object, because adding or removing a cropping rectangle moves the following objects
+            for (object, (old, new)) in objects.iter().zip(old.iter().zip(new)).rev() {
+                if old.window != new.window {
+                    self.set_window(entry_idx, set, object.window_id, new.window)?;
+                }
+                if let Some(pcs) = self.segments.get_mut(set.first) {
+                    set_composition_object(&mut pcs.data, object, new);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the size of the last definition of an object before the segment `end`.
+    fn object_size(&self, object_id: u16, end: usize) -> (u16, u16) {
+        self.segments
+            .iter()
+            .take(end)
+            .rev()
+            .find(|segment| {
+                let first = segment.data.get(3).is_some_and(|flags| flags & FIRST_IN_SEQUENCE != 0);
+                segment.kind == OBJECT_DEFINITION && first && read_u16(&segment.data, 0) == object_id
+            })
+            // object id (2 bytes), version, sequence flags, data length (3 bytes), width and height
+            .map_or((0, 0), |segment| (read_u16(&segment.data, 7), read_u16(&segment.data, 9)))
+    }
+
+    /// Returns the segment index, the position in the segment and the rectangle of the last definition of a window
+    /// before the segment `end`.
+    fn find_window(&self, window_id: u8, end: usize) -> Option<(usize, usize, BitmapRect)> {
+        self.segments.iter().enumerate().take(end).rev().find_map(|(idx, segment)| {
+            if segment.kind != WINDOW_DEFINITION {
+                return None;
+            }
+            let count = usize::from(segment.data.first().copied().unwrap_or_default());
+            (0..count)
+                .map(|i| 1 + i * WINDOW_SIZE)
+                .find(|&pos| segment.data.get(pos) == Some(&window_id))
+                .map(|pos| (idx, pos, read_rect(&segment.data, pos + 1)))
+        })
+    }
+
+    fn set_window(&mut self, entry_idx: usize, set: &DisplaySet, window_id: u8, window: Option<BitmapRect>) -> SubtitleParserResult<()> {
+        let not_editable = || crate::bitmaps::errors::Error::from(crate::bitmaps::errors::ErrorKind::WindowNotEditable { entry_idx });
+        match (self.find_window(window_id, set.end), window) {
+            (Some((idx, pos, _)), Some(window)) if idx >= set.first => {
+                if let Some(segment) = self.segments.get_mut(idx) {
+                    write_rect(&mut segment.data, pos + 1, window);
+                }
+                Ok(())
+            }
+            _ => Err(not_editable()).with_context(|_| crate::ErrorKind::UpdatingEntriesError)?,
+        }
+    }
+}
+
+/// Writes the position and the cropping rectangle of a composition object into the data of its segment.
+fn set_composition_object(data: &mut Vec<u8>, object: &CompositionObject, placement: &BitmapPlacement) {
+    write_u16(data, object.pos + 4, placement.x);
+    write_u16(data, object.pos + 6, placement.y);
+    let crop_pos = object.pos + 8;
+    match (object.crop, placement.crop) {
+        (Some(_), Some(crop)) => write_rect(data, crop_pos, crop),
+        (None, Some(crop)) if crop_pos <= data.len() => {
+            data.splice(crop_pos..crop_pos, [0; 8]);
+            write_rect(data, crop_pos, crop);
+        }
+        (Some(_), None) if crop_pos + 8 <= data.len() => {
+            data.drain(crop_pos..crop_pos + 8);
+        }
+        _ => {}
+    }
+    let flags = if placement.crop.is_some() {
+        object.flags | OBJECT_CROPPED
+    } else {
+        object.flags & !OBJECT_CROPPED
+    };
+    if let Some(b) = data.get_mut(object.pos + 3) {
+        *b = flags;
+    }
+}
+
+/// Reads a rectangle (x, y, width and height as 16 bit values).
+fn read_rect(data: &[u8], pos: usize) -> BitmapRect {
+    BitmapRect {
+        x: read_u16(data, pos),
+        y: read_u16(data, pos + 2),
+        width: read_u16(data, pos + 4),
+        height: read_u16(data, pos + 6),
+    }
+}
+
+fn write_rect(data: &mut [u8], pos: usize, rect: BitmapRect) {
+    for (i, value) in [rect.x, rect.y, rect.width, rect.height].iter().enumerate() {
+        write_u16(data, pos + 2 * i, *value);
+    }
+}
+
+fn write_u16(data: &mut [u8], pos: usize, value: u16) {
+    if let Some(dest) = data.get_mut(pos..pos + 2) {
+        dest.copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    match data.get(pos..pos + 2) {
+        Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]),
+        _ => 0,
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    match data.get(pos..pos + 4) {
+        Some(&[a, b, c, d]) => u32::from_be_bytes([a, b, c, d]),
+        _ => 0,
+    }
+}
+
+impl SubtitleFileInterface for PgsFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .entry_sets()
+            .iter()
+            .map(|(set, next)| {
+                let start = self.set_time(set);
+                let end = next.map_or(start, |next| self.set_time(&next));
+                let mut entry = SubtitleEntry::from(TimeSpan::new(start, end));
+                entry.flags.forced = set.forced;
+                entry
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let entry_sets = self.entry_sets();
+        check_entry_count(entry_sets.len(), new_subtitle_entries.len())?;
+        let old_entries = self.get_subtitle_entries()?;
+
+        for (((set, next), old), new) in entry_sets.iter().zip(&old_entries).zip(new_subtitle_entries) {
+            if old.timespan.start != new.timespan.start {
+                self.move_set(set, new.timespan.start);
+            }
+            match *next {
+                Some(ref next) if next.objects == 0 && old.timespan.end != new.timespan.end => self.move_set(next, new.timespan.end),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = Vec::new();
+        for segment in &self.segments {
+            result.extend_from_slice(b"PG");
+            result.extend_from_slice(&segment.pts.to_be_bytes());
+            result.extend_from_slice(&segment.dts.to_be_bytes());
+            result.push(segment.kind);
+            result.extend_from_slice(&(segment.data.len() as u16).to_be_bytes());
+            result.extend_from_slice(&segment.data);
+        }
+        Ok(result)
+    }
+
+    fn retain_entries_by_mask(&mut self, _: &[bool]) -> SubtitleParserResult<()> {
+        Err(crate::errors::ErrorKind::UpdatingEntriesNotSupported { format: SubtitleFormat::Pgs }.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error};
+
+    fn segment(pts: u32, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut result = b"PG".to_vec();
+        result.extend_from_slice(&pts.to_be_bytes());
+        result.extend_from_slice(&0u32.to_be_bytes());
+        result.push(kind);
+        result.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        result.extend_from_slice(data);
+        result
+    }
+
+    fn pcs(objects: &[u8]) -> Vec<u8> {
+        // 1920x1080, 23.976 fps, composition 0, epoch start, no palette update, palette 0
+        let mut data = vec![0x07, 0x80, 0x04, 0x38, 0x10, 0, 0, 0x80, 0, 0, objects.len() as u8];
+        for &flags in objects {
+            data.extend_from_slice(&[0, 0, 0, flags, 0x01, 0x00, 0x03, 0x00]);
+        }
+        data
+    }
+
+    /// A display set with an image, one which clears the screen and a forced one without objects.
+    fn file() -> Vec<u8> {
+        let mut input = Vec::new();
+        input.extend(segment(90_000, PRESENTATION_COMPOSITION, &pcs(&[0])));
+        input.extend(segment(
+            90_000,
+            WINDOW_DEFINITION,
+            &[1, 0, 0x01, 0x00, 0x03, 0x00, 0x00, 0x10, 0x00, 0x10],
+        ));
+        input.extend(segment(90_000, PALETTE_DEFINITION, &[0, 0, 1, 0x10, 0x80, 0x80, 0xff]));
+        input.extend(segment(90_000, OBJECT_DEFINITION, &[0, 0, 0, 0xc0, 0, 0, 4, 0, 1, 0, 1]));
+        input.extend(segment(90_000, END_OF_DISPLAY_SET, &[]));
+        input.extend(segment(270_000, PRESENTATION_COMPOSITION, &pcs(&[])));
+        input.extend(segment(270_000, END_OF_DISPLAY_SET, &[]));
+        input.extend(segment(360_000, PRESENTATION_COMPOSITION, &pcs(&[OBJECT_FORCED])));
+        input.extend(segment(360_000, END_OF_DISPLAY_SET, &[]));
+        input
+    }
+
+    fn spans(file: &PgsFile) -> Vec<(i64, i64, bool)> {
+        file.get_subtitle_entries()
+            .unwrap()
+            .iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.flags.forced))
+            .collect()
+    }
+
+    #[test]
+    fn pgs_segments_are_kept() {
+        let input = file();
+        assert_round_trip(&mut PgsFile::parse(&input).unwrap(), &input);
+    }
+
+    #[test]
+    fn pgs_display_sets() {
+        // the set without objects ends the first entry
+        let file = PgsFile::parse(&file()).unwrap();
+        assert_eq!(spans(&file), vec![(1000, 3000, false), (4000, 4000, true)]);
+    }
+
+    #[test]
+    fn pgs_changed_times_move_the_display_sets() {
+        // every segment of a display set gets the new presentation timestamp (in 90 kHz ticks)
+        let mut file = PgsFile::parse(&file()).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].timespan.start = TimePoint::from_msecs(1500);
+        entries[0].timespan.end = TimePoint::from_msecs(3500);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = file.to_data().unwrap();
+        assert_eq!(&data[2..6], &135_000u32.to_be_bytes());
+        let file = PgsFile::parse(&data).unwrap();
+        assert_eq!(spans(&file), vec![(1500, 3500, false), (4000, 4000, true)]);
+    }
+
+    #[test]
+    fn pgs_parse_errors() {
+        let input = file();
+        let last = input.len() - SEGMENT_HEADER_SIZE;
+        assert_eq!(
+            parse_error(PgsFile::parse_file, &input[..input.len() - 1]).kind(),
+            &TruncatedSegment { offset: last }
+        );
+        assert_eq!(parse_error(PgsFile::parse_file, b"not a sup file").kind(), &ExpectedSegment { offset: 0 });
+
+        let mut input = segment(0, END_OF_DISPLAY_SET, &[]);
+        input[10] = 0x42;
+        assert_eq!(
+            parse_error(PgsFile::parse_file, &input).kind(),
+            &UnknownSegmentType { offset: 0, kind: 0x42 }
+        );
+    }
+
+    /// Two display sets with the same 8x4 image, the first one defines a 16x16 window.
+    fn placement_file() -> Vec<u8> {
+        let mut input = Vec::new();
+        input.extend(segment(90_000, PRESENTATION_COMPOSITION, &pcs(&[0])));
+        input.extend(segment(
+            90_000,
+            WINDOW_DEFINITION,
+            &[1, 0, 0x01, 0x00, 0x03, 0x00, 0x00, 0x10, 0x00, 0x10],
+        ));
+        input.extend(segment(90_000, OBJECT_DEFINITION, &[0, 0, 0, 0xc0, 0, 0, 4, 0, 8, 0, 4]));
+        input.extend(segment(90_000, END_OF_DISPLAY_SET, &[]));
+        input.extend(segment(270_000, PRESENTATION_COMPOSITION, &pcs(&[0])));
+        input.extend(segment(270_000, END_OF_DISPLAY_SET, &[]));
+        input
+    }
+
+    const PLACEMENT: BitmapPlacement = BitmapPlacement {
+        x: 256,
+        y: 768,
+        width: 8,
+        height: 4,
+        crop: None,
+        window: Some(BitmapRect {
+            x: 256,
+            y: 768,
+            width: 16,
+            height: 16,
+        }),
+    };
+
+    #[test]
+    fn pgs_bitmap_placements() {
+        let file = PgsFile::parse(&placement_file()).unwrap();
+        assert_eq!(file.bitmap_placements(), vec![vec![PLACEMENT], vec![PLACEMENT]]);
+    }
+
+    #[test]
+    fn pgs_set_bitmap_placements() {
+        let input = placement_file();
+        let mut file = PgsFile::parse(&input).unwrap();
+        let crop = BitmapRect {
+            x: 0,
+            y: 2,
+            width: 8,
+            height: 2,
+        };
+        let moved = BitmapPlacement {
+            crop: Some(crop),
+            ..PLACEMENT.moved_by(0, -100)
+        };
+        file.set_bitmap_placements(&[vec![moved], vec![PLACEMENT]]).unwrap();
+
+        // the second entry uses the moved window, and the cropping rectangle adds 8 bytes
+        let file = PgsFile::parse(&file.to_data().unwrap()).unwrap();
+        assert_eq!(file.bitmap_placements()[0], vec![moved]);
+        assert_eq!(file.bitmap_placements()[1][0].window, moved.window);
+        assert_eq!(file.to_data().unwrap().len(), input.len() + 8);
+    }
+
+    #[test]
+    fn pgs_invalid_bitmap_placements() {
+        let input = placement_file();
+        let mut file = PgsFile::parse(&input).unwrap();
+        let set = |file: &mut PgsFile, placements: &[Vec<BitmapPlacement>]| file.set_bitmap_placements(placements).unwrap_err().kind();
+
+        // the window of the second entry is defined by the first one
+        assert_eq!(
+            set(&mut file, &[vec![PLACEMENT], vec![PLACEMENT.moved_by(10, 0)]]),
+            crate::ErrorKind::UpdatingEntriesError
+        );
+        // the size of an image can't be changed
+        let resized = BitmapPlacement { width: 9, ..PLACEMENT };
+        assert_eq!(set(&mut file, &[vec![resized], vec![PLACEMENT]]), crate::ErrorKind::UpdatingEntriesError);
+        let outside_window = BitmapPlacement { y: 782, ..PLACEMENT };
+        assert_eq!(
+            set(&mut file, &[vec![outside_window], vec![PLACEMENT]]),
+            crate::ErrorKind::UpdatingEntriesError
+        );
+        assert_eq!(
+            set(&mut file, &[vec![PLACEMENT]]),
+            crate::ErrorKind::EntryCountMismatch { expected: 2, found: 1 }
+        );
+        assert_eq!(file.to_data().unwrap(), input);
+    }
+}
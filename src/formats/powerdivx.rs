@@ -0,0 +1,312 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+
+/// Errors specific to PowerDivX-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected PowerDivX line like `{{0:00:01}}{{0:00:03}}Hello|world`, found '{}'", line)]
+        ExpectedPowerDivXLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// A time of a PowerDivX line like `{0:00:01}`, with the text in the braces as it is written in the file.
+#[derive(Debug, Clone)]
+struct PsbTime {
+    stamp: String,
+    time: TimePoint,
+}
+
+impl PsbTime {
+    fn new(time: TimePoint) -> PsbTime {
+        PsbTime {
+            stamp: format_time(time),
+            time,
+        }
+    }
+
+    /// Sets the time (the text is only rewritten if the time changes in the file).
+    fn set(&mut self, time: TimePoint) {
+        if format_time(self.time) != format_time(time) {
+            self.stamp = format_time(time);
+        }
+        self.time = time;
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A line of a PowerDivX file.
+enum PsbLine {
+    /// An empty line or a line which is not a subtitle (kept as it is).
+    Filler(String),
+
+    /// A subtitle like `{0:00:01}{0:00:03}Hello|world`.
+    Entry {
+        start: PsbTime,
+        end: PsbTime,
+
+        /// The text as it is written in the file.
+        raw: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Represents a PowerDivX (`.psb`) file.
+///
+/// Every line is a subtitle like `{0:00:01}{0:00:03}Hello|world` with the start and end in seconds; a `|`
+/// separates the lines of the text. Lines which aren't subtitles are kept, and times and texts are only rewritten
+/// if they change.
+pub struct PowerDivXFile {
+    v: Vec<(PsbLine, String)>,
+}
+
+impl PowerDivXFile {
+    /// Parse a PowerDivX subtitle string to `PowerDivXFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<PowerDivXFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a PowerDivX subtitle string to `PowerDivXFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(PowerDivXFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a PowerDivX file from scratch.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<PowerDivXFile> {
+        let lines = v
+            .into_iter()
+            .map(|(timespan, text)| {
+                (
+                    PsbLine::Entry {
+                        start: PsbTime::new(timespan.start),
+                        end: PsbTime::new(timespan.end),
+                        raw: encode_text(&text),
+                    },
+                    "\n".to_string(),
+                )
+            })
+            .collect();
+        Ok(PowerDivXFile { v: lines })
+    }
+
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<PowerDivXFile> {
+        let (bom, s) = split_bom(i);
+
+        let mut result = Vec::new();
+        if !bom.is_empty() {
+            result.push((PsbLine::Filler(bom.to_string()), String::new()));
+        }
+        let mut found_entry = false;
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            match parse_line(&line) {
+                Some((start, end, raw)) => {
+                    found_entry = true;
+                    result.push((
+                        PsbLine::Entry {
+                            start,
+                            end,
+                            raw: raw.to_string(),
+                        },
+                        newline,
+                    ));
+                }
+                None if line.trim().is_empty() => result.push((PsbLine::Filler(line), newline)),
+                // a file whose first content isn't a subtitle is not a PowerDivX file
+                None if !found_entry => {
+                    return Err(Error::from(ExpectedPowerDivXLine { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                None => {
+                    warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+                    result.push((PsbLine::Filler(line), newline));
+                }
+            }
+        }
+        Ok(PowerDivXFile { v: result })
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .flat_map(|(line, newline)| {
+                let line = match *line {
+                    PsbLine::Filler(ref text) => Some(text.as_str()),
+                    PsbLine::Entry { .. } => None,
+                };
+                vec![line, Some(newline.as_str())]
+            })
+            .collect()
+    }
+}
+
+/// Returns `true` if the first non-empty line of the text looks like a PowerDivX subtitle.
+pub(crate) fn looks_like_powerdivx(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    text.lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| parse_line(line).is_some())
+}
+
+/// Parses a time like `{0:00:01}` at the start of the string and returns the rest.
+fn parse_time(s: &str) -> Option<(PsbTime, &str)> {
+    let (stamp, rest) = s.strip_prefix('{')?.split_once('}')?;
+    let mut numbers = stamp.split(':');
+    let (hours, mins, secs) = (numbers.next()?, numbers.next()?, numbers.next()?);
+    let valid = |number: &str, max_len: usize| !number.is_empty() && number.len() <= max_len && number.bytes().all(|b| b.is_ascii_digit());
+    if numbers.next().is_some() || !valid(hours, 2) || mins.len() != 2 || !valid(mins, 2) || secs.len() != 2 || !valid(secs, 2) {
+        return None;
+    }
+    let (mins, secs): (i64, i64) = (mins.parse().ok()?, secs.parse().ok()?);
+    if mins >= 60 || secs >= 60 {
+        return None;
+    }
+    let time = TimePoint::from_components(hours.parse().ok()?, mins, secs, 0);
+    Some((
+        PsbTime {
+            stamp: stamp.to_string(),
+            time,
+        },
+        rest,
+    ))
+}
+
+/// Parses a line like `{0:00:01}{0:00:03}Hello` into the start, the end and the raw text.
+fn parse_line(line: &str) -> Option<(PsbTime, PsbTime, &str)> {
+    let (start, rest) = parse_time(line)?;
+    let (end, text) = parse_time(rest)?;
+    Some((start, end, text))
+}
+
+/// Formats a time like `0:00:01` (rounded to seconds, negative times are written as zero).
+fn format_time(t: TimePoint) -> String {
+    let secs = (t.msecs().max(0) + 500) / 1000;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Converts the raw text of a subtitle into an entry text.
+fn decode_text(raw: &str) -> String {
+    raw.replace('|', "\n")
+}
+
+/// Converts an entry text into the raw text of a subtitle.
+fn encode_text(text: &str) -> String {
+    text.lines().collect::<Vec<&str>>().join("|")
+}
+
+impl SubtitleFileInterface for PowerDivXFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .filter_map(|(line, _)| match *line {
+                PsbLine::Filler(_) => None,
+                PsbLine::Entry { ref start, ref end, ref raw } => Some(SubtitleEntry::new(TimeSpan::new(start.time, end.time), decode_text(raw))),
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, PsbLine::Entry { .. })).count();
+        check_entry_count(count, new_subtitle_entries.len())?;
+
+        let entries = self.v.iter_mut().filter_map(|(line, _)| match *line {
+            PsbLine::Filler(_) => None,
+            PsbLine::Entry {
+                ref mut start,
+                ref mut end,
+                ref mut raw,
+            } => Some((start, end, raw)),
+        });
+        for ((start, end, raw), new_entry) in entries.zip(new_subtitle_entries) {
+            start.set(new_entry.timespan.start);
+            end.set(new_entry.timespan.end);
+            if let Some(ref text) = new_entry.line {
+                if decode_text(raw) != *text {
+                    *raw = encode_text(text);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let result: String = self
+            .v
+            .iter()
+            .map(|(line, newline)| match *line {
+                PsbLine::Filler(ref text) => format!("{}{}", text, newline),
+                PsbLine::Entry { ref start, ref end, ref raw } => format!("{{{}}}{{{}}}{}{}", start.stamp, end.stamp, raw, newline),
+            })
+            .collect();
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|(line, _)| matches!(line, PsbLine::Entry { .. })).count();
+        check_entry_count(count, keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|(line, _)| match *line {
+            PsbLine::Filler(_) => true,
+            PsbLine::Entry { .. } => keep_iter.next().cloned().unwrap_or(true),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn powerdivx_round_trip_and_edit() {
+        let input = "\u{feff}{0:00:01}{0:00:03}Hello|world\r\n{00:00:05}{00:00:07}Second\r\n\r\nnot a subtitle\r\n{0:01:00}{0:01:02}Third\r\n";
+        let (mut file, warnings) = PowerDivXFile::parse_with_warnings(input).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), input);
+        assert_eq!(warnings.len(), 1);
+
+        let mut entries = file.get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1000, 3000), (5000, 7000), (60000, 62000)]);
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\nworld"));
+
+        entries[1].timespan += TimeDelta::from_msecs(3_600_000);
+        entries[2].line = Some("Changed\nline".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[false, true, true]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "\u{feff}{1:00:05}{1:00:07}Second\r\n\r\nnot a subtitle\r\n{0:01:00}{0:01:02}Changed|line\r\n"
+        );
+
+        assert!(looks_like_powerdivx("\n{1:02:03}{1:02:04}x\n"));
+        assert!(!looks_like_powerdivx("{1}{2}x\n"));
+        assert!(PowerDivXFile::parse("{0:00:61}{0:01:00}x\n").is_err());
+    }
+}
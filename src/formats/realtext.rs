@@ -0,0 +1,660 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::warnings::Warnings;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+
+/// `.rt`-parser-specific errors
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the file has no `<window>` element")]
+        ExpectedWindowElement,
+
+        #[fail(display = "the tag is not closed with `>`")]
+        UnclosedTag,
+
+        #[fail(display = "the `<time>` tag has no `begin` attribute")]
+        MissingBegin,
+
+        #[fail(display = "the time value `{}` is invalid", value)]
+        InvalidTime { value: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// The tags which are kept in the entry texts (in the same syntax as in `.srt` files).
+const STYLE_TAGS: &[&str] = &["b", "i", "u", "font", "/b", "/i", "/u", "/font"];
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .rt file parts
+
+/// A part of a `<time>` tag.
+#[derive(Debug, Clone)]
+enum TagPiece {
+    /// Everything apart from the timing attribute values.
+    Text(String),
+
+    /// The value of the `begin` attribute.
+    Begin,
+
+    /// The value of the `end` attribute.
+    End,
+}
+
+/// A `<time>` tag and the markup up to the next one (one subtitle).
+#[derive(Debug, Clone)]
+struct RealTextCue {
+    /// The tag split at the values of the timing attributes (the last piece is the closing `>` or `/>`).
+    tag: Vec<TagPiece>,
+
+    /// The value of the `begin` attribute as it is written.
+    begin: String,
+
+    /// The value of the `end` attribute as it is written (without one, the cue ends with the next one).
+    end: Option<String>,
+
+    timespan: TimeSpan,
+
+    /// The markup after the tag, like `<clear/>Hello<br/>world` and the line break.
+    content: String,
+}
+
+impl RealTextCue {
+    /// Creates a cue with a `<time begin=".." end=".."/>` tag.
+    fn new(timespan: TimeSpan, content: String) -> RealTextCue {
+        RealTextCue {
+            tag: vec![
+                TagPiece::Text("<time begin=\"".to_string()),
+                TagPiece::Begin,
+                TagPiece::Text("\" end=\"".to_string()),
+                TagPiece::End,
+                TagPiece::Text("\"".to_string()),
+                TagPiece::Text("/>".to_string()),
+            ],
+            begin: format_time(timespan.start),
+            end: Some(format_time(timespan.end)),
+            timespan,
+            content,
+        }
+    }
+
+    /// Writes an end time (and adds an `end` attribute if the tag has none).
+    fn set_end(&mut self, end: TimePoint) {
+        if self.end.is_none() {
+            let closing = self.tag.len().saturating_sub(1);
+            let attribute = vec![TagPiece::Text(" end=\"".to_string()), TagPiece::End, TagPiece::Text("\"".to_string())];
+            self.tag.splice(closing..closing, attribute);
+        }
+        self.end = Some(format_time(end));
+        self.timespan.end = end;
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .rt file
+
+/// Represents a reconstructable RealText (`.rt`) file.
+///
+/// The subtitles are the `<time begin=".." end=".."/>` tags inside of the `<window>` element, and the markup up
+/// to the next `<time>` tag is the text. A missing `end` lasts until the next `<time>` tag (or the `duration` of
+/// the window). Everything before the first and after the last subtitle is kept as it is, as are unchanged times
+/// and texts.
+///
+/// The entry texts have `\n` as line break (`<br/>` in the file) and keep `<b>`, `<i>`, `<u>` and `<font>` tags.
+/// The other markup (like `<clear/>` or `<center>`) is removed from the entry text, but all markup in front of and
+/// behind the text stays in the file if the text is changed.
+#[derive(Debug, Clone)]
+pub struct RealTextFile {
+    /// everything before the first `<time>` tag (with the `<window>` tag)
+    header: String,
+
+    v: Vec<RealTextCue>,
+
+    /// the `</window>` tag and everything after it
+    trailer: String,
+}
+
+impl RealTextFile {
+    /// Parse a RealText string to `RealTextFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<RealTextFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a RealText string to `RealTextFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(RealTextFile, Warnings)> {
+        let file = Self::parse_file(s).with_context(|_| crate::ErrorKind::ParsingError)?;
+        let mut warnings = Warnings::new();
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a RealText file with a `<window>` element around the subtitles.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<RealTextFile> {
+        let duration = v.iter().map(|(timespan, _)| timespan.end).max();
+        let header = match duration {
+            Some(duration) => format!("<window type=\"generic\" duration=\"{}\">\n", format_time(duration)),
+            None => "<window type=\"generic\">\n".to_string(),
+        };
+        Ok(RealTextFile {
+            header,
+            v: v.into_iter()
+                .map(|(timespan, text)| RealTextCue::new(timespan, format!("<clear/>{}\n", encode_content(&text))))
+                .collect(),
+            trailer: "</window>\n".to_string(),
+        })
+    }
+
+    fn parse_file(s: &str) -> Result<RealTextFile> {
+        let error_at = |position: usize| ErrorAtLine {
+            line_num: s.get(..position).unwrap_or(s).matches('\n').count(),
+        };
+        // all tags are case-insensitive (and an ASCII lowercase string has the same byte offsets)
+        let lower = s.to_ascii_lowercase();
+        let window = lower.find("<window").ok_or(ExpectedWindowElement)?;
+        let window_tag = tag_at(s, window).with_context(|_| error_at(window))?;
+        let duration = match attribute(window_tag, "duration") {
+            Some(value) => Some(parse_time(value).with_context(|_| error_at(window))?),
+            None => None,
+        };
+
+        let body_end = lower
+            .get(window..)
+            .and_then(|rest| rest.find("</window"))
+            .map_or(s.len(), |idx| window + idx);
+        let mut starts = Vec::new();
+        let mut search = window;
+        while let Some(idx) = lower.get(search..body_end).and_then(|rest| rest.find("<time")) {
+            let start = search + idx;
+            let next = lower.get(start + 5..).and_then(|rest| rest.chars().next());
+            if next.is_some_and(|c| c.is_whitespace() || c == '/' || c == '>') {
+                starts.push(start);
+            }
+            search = start + 5;
+        }
+
+        let mut cues: Vec<RealTextCue> = Vec::new();
+        let mut content_ends = starts.iter().skip(1).cloned().chain(Some(body_end));
+        for &start in &starts {
+            let tag = tag_at(s, start).with_context(|_| error_at(start))?;
+            let content_end = content_ends.next().unwrap_or(body_end);
+            let (cue, begin, end) = parse_time_tag(tag).with_context(|_| error_at(start))?;
+            let content = s.get(start + tag.len()..content_end).unwrap_or("").to_string();
+            cues.push(RealTextCue {
+                timespan: TimeSpan::new(begin, end.unwrap_or(begin)),
+                content,
+                ..cue
+            });
+        }
+
+        // cues without end last until the next cue (or the end of the window)
+        let next_begins: Vec<Option<TimePoint>> = cues.iter().skip(1).map(|cue| Some(cue.timespan.start)).chain(Some(duration)).collect();
+        for (cue, next_begin) in cues.iter_mut().zip(next_begins) {
+            if let (None, Some(next_begin)) = (&cue.end, next_begin) {
+                cue.timespan.end = next_begin.max(cue.timespan.start);
+            }
+        }
+
+        // end tags of the header (like `</font>`) belong to the trailer and not to the last cue
+        let mut trailer = s.get(body_end..).unwrap_or("").to_string();
+        if let Some(last) = cues.last_mut() {
+            let end = content_end_of_last_cue(&last.content);
+            trailer.insert_str(0, last.content.get(end..).unwrap_or(""));
+            last.content.truncate(end);
+        }
+
+        let header_end = starts.first().cloned().unwrap_or(body_end);
+        Ok(RealTextFile {
+            header: s.get(..header_end).unwrap_or("").to_string(),
+            v: cues,
+            trailer,
+        })
+    }
+}
+
+/// Returns `true` if the text looks like a RealText file.
+pub(crate) fn looks_like_realtext(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    lower.contains("<window") && lower.contains("<time")
+}
+
+/// Returns the tag which starts at `position`.
+fn tag_at(s: &str, position: usize) -> Result<&str> {
+    let rest = s.get(position..).unwrap_or("");
+    let len = rest.find('>').ok_or(UnclosedTag)?;
+    Ok(rest.get(..len + 1).unwrap_or(rest))
+}
+
+/// Splits a `<time>` tag into its pieces and returns the cue (without content) and the times of the attributes.
+fn parse_time_tag(tag: &str) -> Result<(RealTextCue, TimePoint, Option<TimePoint>)> {
+    let begin_range = attribute_range(tag, "begin").ok_or(MissingBegin)?;
+    let end_range = attribute_range(tag, "end");
+    let closing_len = if tag.ends_with("/>") { 2 } else { 1 };
+
+    let mut ranges = vec![(begin_range.clone(), TagPiece::Begin)];
+    ranges.extend(end_range.clone().map(|range| (range, TagPiece::End)));
+    ranges.sort_by_key(|(range, _)| range.start);
+    let mut pieces = Vec::new();
+    let mut position = 0;
+    for (range, piece) in ranges {
+        pieces.push(TagPiece::Text(tag.get(position..range.start).unwrap_or("").to_string()));
+        pieces.push(piece);
+        position = range.end;
+    }
+    let closing = tag.len().saturating_sub(closing_len).max(position);
+    pieces.push(TagPiece::Text(tag.get(position..closing).unwrap_or("").to_string()));
+    pieces.push(TagPiece::Text(tag.get(closing..).unwrap_or("").to_string()));
+
+    let begin = tag.get(begin_range).unwrap_or("");
+    let end = end_range.and_then(|range| tag.get(range));
+    let cue = RealTextCue {
+        tag: pieces,
+        begin: begin.to_string(),
+        end: end.map(str::to_string),
+        timespan: TimeSpan::new(TimePoint::from_msecs(0), TimePoint::from_msecs(0)),
+        content: String::new(),
+    };
+    Ok((cue, parse_time(begin)?, end.map(parse_time).transpose()?))
+}
+
+/// Parses a time like `1:02:03.5`, `02:03.50` or `3.5` (`[[[days:]hours:]minutes:]seconds[.fraction]`).
+fn parse_time(value: &str) -> Result<TimePoint> {
+    let invalid = || Error::from(InvalidTime { value: value.to_string() });
+    let trimmed = value.trim();
+    let (clock, fraction) = trimmed.split_once('.').unwrap_or((trimmed, ""));
+    let parts: Vec<&str> = clock.split(':').collect();
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if parts.len() > 4 || !parts.iter().all(|part| is_number(part)) || !(fraction.is_empty() || is_number(fraction)) {
+        return Err(invalid());
+    }
+
+    let mut secs: i64 = 0;
+    for (part, factor) in parts.iter().rev().zip(&[1, 60, 60 * 60, 24 * 60 * 60]) {
+        let value = part.parse::<i64>().ok().and_then(|value| value.checked_mul(*factor));
+        secs = value.and_then(|value| secs.checked_add(value)).ok_or_else(invalid)?;
+    }
+    // the first three digits of the fraction are the milliseconds
+    let msecs = format!("{:0<3}", fraction.get(..3).unwrap_or(fraction))
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+    let msecs = secs.checked_mul(1000).and_then(|secs| secs.checked_add(msecs)).ok_or_else(invalid)?;
+    Ok(TimePoint::from_msecs(msecs))
+}
+
+/// Formats a time like `00:00:01.500` (negative times are written as zero).
+fn format_time(t: TimePoint) -> String {
+    let t = if t.is_negative() { TimePoint::from_msecs(0) } else { t };
+    format!("{:02}:{:02}:{:02}.{:03}", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp())
+}
+
+/// Returns where the content of the last cue ends: in front of the end tags of elements which are not opened in it.
+fn content_end_of_last_cue(content: &str) -> usize {
+    let mut end = content.len();
+    loop {
+        let trimmed = content.get(..end).unwrap_or("").trim_end();
+        let tag_start = match trimmed.rfind('<') {
+            Some(tag_start) if trimmed.ends_with('>') => tag_start,
+            _ => return end,
+        };
+        let name = tag_name(trimmed.get(tag_start..).unwrap_or(""));
+        let opened = match name.strip_prefix('/') {
+            Some(opened) => opened,
+            None => return end,
+        };
+        let names: Vec<String> = trimmed
+            .get(..tag_start)
+            .unwrap_or("")
+            .split('<')
+            .skip(1)
+            .map(|tag| tag_name(&format!("<{}", tag)))
+            .collect();
+        let count = |name: &str| names.iter().filter(|n| *n == name).count();
+        if count(opened) > count(&name) {
+            return end;
+        }
+        end = tag_start;
+    }
+}
+
+/// Returns the lowercase name of a tag like `<br/>` (`br`) or `</b>` (`/b`).
+fn tag_name(tag: &str) -> String {
+    let name = tag
+        .trim_start_matches('<')
+        .split(|c: char| c.is_whitespace() || c == '>')
+        .next()
+        .unwrap_or("");
+    let name = if name.len() > 1 { name.trim_end_matches('/') } else { name };
+    name.to_ascii_lowercase()
+}
+
+/// Returns the length of the tag at the start of `s`, if it is kept in the entry texts.
+fn style_tag_len(s: &str) -> Option<usize> {
+    let len = s.find('>')? + 1;
+    let tag = s.get(..len)?;
+    Some(len).filter(|_| tag.starts_with('<') && STYLE_TAGS.contains(&tag_name(tag).as_str()))
+}
+
+/// Converts the markup of a cue into the entry text (see `RealTextFile`).
+fn decode_content(content: &str) -> String {
+    let mut text = String::new();
+    let mut rest = content;
+    while let Some(idx) = rest.find('<') {
+        let (before, tag_start) = rest.split_at(idx);
+        text.push_str(&decode_entities(&before.replace(['\n', '\r', '\t'], " ")));
+        // a `<` without a closing `>` is text
+        let tag_len = match tag_start.find('>') {
+            Some(idx) => idx + 1,
+            None => {
+                text.push('<');
+                rest = tag_start.get(1..).unwrap_or("");
+                continue;
+            }
+        };
+        let tag = tag_start.get(..tag_len).unwrap_or("");
+        rest = tag_start.get(tag_len..).unwrap_or("");
+
+        if tag_name(tag) == "br" {
+            text.push('\n');
+        } else if style_tag_len(tag).is_some() {
+            text.push_str(tag);
+        }
+    }
+    text.push_str(&decode_entities(&rest.replace(['\n', '\r', '\t'], " ")));
+
+    // whitespace in the markup is collapsed, line breaks are only created by `<br/>`
+    text.split('\n')
+        .map(|line| line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<&str>>().join(" "))
+        .collect::<Vec<String>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Converts an entry text into the markup of a cue (see `RealTextFile`).
+fn encode_content(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = match (c, style_tag_len(rest)) {
+            ('<', Some(len)) => {
+                result.push_str(rest.get(..len).unwrap_or(""));
+                len
+            }
+            ('\n', _) => {
+                result.push_str("<br/>");
+                1
+            }
+            ('\r', _) => 1,
+            ('&', _) => {
+                result.push_str("&amp;");
+                1
+            }
+            ('<', None) => {
+                result.push_str("&lt;");
+                1
+            }
+            ('>', _) => {
+                result.push_str("&gt;");
+                1
+            }
+            (c, _) => {
+                result.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = rest.get(len..).unwrap_or("");
+    }
+    result
+}
+
+/// Splits the markup of a cue into the markup in front of the text (like `<clear/>`), the text and the markup
+/// behind it (like a line break).
+fn split_content(content: &str) -> (&str, &str) {
+    let is_markup = |tag: &str| tag_name(tag) != "br" && style_tag_len(tag).is_none();
+    let mut start = 0;
+    loop {
+        let rest = content.get(start..).unwrap_or("");
+        let trimmed = rest.trim_start();
+        // a `<` without a closing `>` is text
+        let tag_len = match trimmed.find('>') {
+            Some(idx) => idx + 1,
+            None => break,
+        };
+        match trimmed.get(..tag_len) {
+            Some(tag) if trimmed.starts_with('<') && is_markup(tag) => start += rest.len() - trimmed.len() + tag_len,
+            _ => break,
+        }
+    }
+    let mut end = content.len();
+    loop {
+        let rest = content.get(start..end).unwrap_or("");
+        let trimmed = rest.trim_end();
+        let tag_start = match trimmed.rfind('<') {
+            Some(idx) => idx,
+            None => break,
+        };
+        match trimmed.get(tag_start..) {
+            Some(tag) if trimmed.ends_with('>') && is_markup(tag) => end = start + tag_start,
+            _ => break,
+        }
+    }
+    let end = start + content.get(start..end).unwrap_or("").trim_end().len();
+    (content.get(..start).unwrap_or(""), content.get(end..).unwrap_or(""))
+}
+
+impl RealTextFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        let cues = self.v.iter().map(|_| None);
+        Some(Some(self.header.as_str()))
+            .into_iter()
+            .chain(cues)
+            .chain(Some(Some(self.trailer.as_str())))
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for RealTextFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .map(|cue| SubtitleEntry::new(cue.timespan, decode_content(&cue.content)))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        for (cue, entry) in self.v.iter_mut().zip(new_subtitle_entries) {
+            if entry.timespan.start != cue.timespan.start {
+                cue.begin = format_time(entry.timespan.start);
+                cue.timespan.start = entry.timespan.start;
+            }
+            if entry.timespan.end != cue.timespan.end {
+                cue.set_end(entry.timespan.end);
+            }
+            if let Some(ref text) = entry.line {
+                // unchanged texts keep all markup
+                if *text != decode_content(&cue.content) {
+                    let (before, after) = split_content(&cue.content);
+                    cue.content = format!("{}{}{}", before, encode_content(text), after);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = self.header.clone();
+        for cue in &self.v {
+            for piece in &cue.tag {
+                match *piece {
+                    TagPiece::Text(ref text) => result.push_str(text),
+                    TagPiece::Begin => result.push_str(&cue.begin),
+                    TagPiece::End => result.push_str(cue.end.as_deref().unwrap_or("")),
+                }
+            }
+            result.push_str(&cue.content);
+        }
+        result.push_str(&self.trailer);
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        // a cue without end would last until the next kept cue
+        for (idx, cue) in self.v.iter_mut().enumerate() {
+            let next_removed = keep.get(idx + 1) == Some(&false);
+            if cue.end.is_none() && next_removed {
+                let end = cue.timespan.end;
+                cue.set_end(end);
+            }
+        }
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+    use crate::timetypes::TimeDelta;
+
+    const FILE: &str = "<window type=\"generic\" duration=\"0:10\" bgcolor=\"black\">\r\n\
+                        <font color=\"white\">\r\n\
+                        <time begin=\"1.5\" end='0:03.25'/><clear/><center>One &amp; <b>two</b><br/>three</center>\r\n\
+                        <Time Begin=4/><clear/>Four\r\n\
+                        <time begin=\"00:00:06.0\"/><clear/>Six\r\n\
+                        </font>\r\n\
+                        </window>\r\n";
+
+    #[test]
+    fn realtext_markup_is_kept() {
+        // the surrounding elements, the case and quoting of the attributes and the entities are written again
+        let mut file = RealTextFile::parse(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        assert!(looks_like_realtext(FILE));
+    }
+
+    #[test]
+    fn realtext_texts_keep_srt_tags() {
+        // `<b>` is kept, `<br/>` becomes a line break and the layout tag `<center>` is left out
+        let entries = RealTextFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let texts: Vec<&str> = entries.iter().filter_map(|entry| entry.line.as_deref()).collect();
+        assert_eq!(texts, vec!["One & <b>two</b>\nthree", "Four", "Six"]);
+    }
+
+    #[test]
+    fn realtext_cues_last_until_the_next_cue() {
+        // cues without end last until the next cue and the last one until the window duration
+        let entries = RealTextFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1500, 3250), (4000, 6000), (6000, 10_000)]);
+    }
+
+    #[test]
+    fn realtext_changed_ends_get_an_end_attribute() {
+        let mut file = RealTextFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].line = Some("<i>One</i> < two".to_string());
+        entries[1].timespan.end -= TimeDelta::from_msecs(500);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("<time begin=\"1.5\" end='0:03.25'/><clear/><center><i>One</i> &lt; two</center>\r\n"));
+        assert!(data.contains("<Time Begin=4 end=\"00:00:05.500\"/><clear/>Four\r\n"));
+        assert!(data.contains("<time begin=\"00:00:06.0\"/><clear/>Six\r\n"));
+    }
+
+    #[test]
+    fn realtext_removed_cues_take_their_line() {
+        let mut file = RealTextFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[true, false, true]).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "<window type=\"generic\" duration=\"0:10\" bgcolor=\"black\">\r\n\
+             <font color=\"white\">\r\n\
+             <time begin=\"1.5\" end='0:03.25'/><clear/><center>One &amp; <b>two</b><br/>three</center>\r\n\
+             <time begin=\"00:00:06.0\"/><clear/>Six\r\n\
+             </font>\r\n\
+             </window>\r\n"
+        );
+    }
+
+    #[test]
+    fn realtext_create() {
+        let created = RealTextFile::create(vec![(
+            TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2500)),
+            "a\nb".to_string(),
+        )])
+        .unwrap();
+        assert_eq!(
+            to_string(&created),
+            "<window type=\"generic\" duration=\"00:00:02.500\">\n<time begin=\"00:00:01.000\" end=\"00:00:02.500\"/><clear/>a<br/>b\n</window>\n"
+        );
+    }
+
+    #[test]
+    fn realtext_truncated_tags() {
+        let input = "<window>\n<time begin=\"1\"/><clear/>a <b\n<time begin=\"2\"/>b > c>\n<time begin=\"3\"/><clear/\n</window>\n";
+        let mut file = RealTextFile::parse(input).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        let texts: Vec<&str> = entries.iter().filter_map(|entry| entry.line.as_deref()).collect();
+        assert_eq!(texts, vec!["a <b", "b > c>", "<clear/"]);
+
+        // unchanged texts keep their markup, new texts with a `<` are escaped
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(to_string(&file), input);
+        let mut entries = entries;
+        entries[0].line = Some("x <y".to_string());
+        entries[2].line = Some("z".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("<clear/>x &lt;y\n") && data.contains("<time begin=\"3\"/>z\n"));
+    }
+
+    #[test]
+    fn realtext_parse_errors() {
+        assert_eq!(
+            parse_error(RealTextFile::parse_file, "<time begin=\"1\"/>x").kind(),
+            &ExpectedWindowElement
+        );
+        assert_eq!(
+            parse_error(RealTextFile::parse_file, "<window>\n<time end=\"1\"/>x").kind(),
+            &ErrorAtLine { line_num: 1 }
+        );
+        assert_eq!(
+            parse_error(RealTextFile::parse_file, "<window>\n\n<time begin=\"1:x\"/>x").kind(),
+            &ErrorAtLine { line_num: 2 }
+        );
+        assert_eq!(
+            parse_error(RealTextFile::parse_file, "<window duration=\"x\">").kind(),
+            &ErrorAtLine { line_num: 0 }
+        );
+        assert_eq!(
+            parse_error(RealTextFile::parse_file, "<window>\n<time begin=\"1\"").kind(),
+            &ErrorAtLine { line_num: 1 }
+        );
+        assert_eq!(parse_time("1:x").unwrap_err().kind(), &InvalidTime { value: "1:x".to_string() });
+    }
+}
@@ -0,0 +1,717 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::{SubtitleEntry, SubtitleFile, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+
+/// Errors specific to SAMI-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the file has no `<SAMI>` element")]
+        ExpectedSami,
+
+        #[fail(display = "the `Start` value `{}` is not a number of milliseconds", value)]
+        InvalidStart { value: String },
+    }
+}
+
+/// How long the last subtitle of a class is shown if no later `<SYNC>` ends it.
+const LAST_DURATION: TimeDelta = TimeDelta::from_msecs(5000);
+
+/// The tags which end the text of a `<P>` element (lowercase, without `<`).
+const PARAGRAPH_END_TAGS: &[&str] = &["sync", "p", "/p", "/sync", "/body"];
+
+/// A language of a SAMI file: a `P` class of the `STYLE` block like `.ENCC { Name: English; lang: en-US; }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamiLanguage {
+    /// The class name (used like `<P Class=ENCC>`).
+    pub class: String,
+
+    /// The name of the language (the `Name` property), if it is given.
+    pub name: Option<String>,
+
+    /// The language tag (the `lang` property) like `en-US`, if it is given.
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum SamiFilePart {
+    /// The head, end tags, line breaks, ...
+    Filler(String),
+
+    /// A `<SYNC Start=1000>` tag: the text before the start value, the start and the text after it.
+    Sync(String, TimePoint, String),
+
+    /// A `<P Class=ENCC>` tag with the content up to the next `<SYNC>` or `<P>` tag.
+    Paragraph(SamiParagraph),
+}
+
+#[derive(Debug, Clone)]
+struct SamiParagraph {
+    /// The start tag.
+    tag: String,
+
+    /// The value of the `Class` attribute.
+    class: Option<String>,
+
+    /// The HTML content.
+    content: String,
+}
+
+/// A `<SYNC>` tag and its paragraphs.
+#[derive(Debug, Clone)]
+struct SyncInfo {
+    /// The index of the `Sync` part.
+    part_idx: usize,
+
+    start: TimePoint,
+
+    /// The part indices and classes of the paragraphs and whether they have text.
+    paragraphs: Vec<(usize, Option<String>, bool)>,
+}
+
+/// Represents a reconstructable `.smi` (SAMI) file.
+///
+/// Every `<P>` with text after a `<SYNC Start=..>` tag is an entry, with the language of its class (see
+/// `SamiFile::languages()`). A subtitle ends with the next `<SYNC>` which has a `<P>` of the same class (an empty one
+/// like `<P Class=ENCC>&nbsp;` if nothing follows) or no `<P>` at all; the last one is shown for five seconds. The
+/// texts keep `<i>`, `<b>` and `<u>` tags and `<br>` line breaks; other markup is only kept if the text is not changed.
+///
+/// The entries of one `<SYNC>` share its start, so changing the start of one of them moves them all. Changed end
+/// times are written into the empty `<P>` which ends the subtitle (if there is one).
+#[derive(Debug, Clone)]
+pub struct SamiFile {
+    v: Vec<SamiFilePart>,
+
+    languages: Vec<SamiLanguage>,
+}
+
+impl SamiFile {
+    /// Parse a `.smi` subtitle string to `SamiFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<SamiFile> {
+        Ok(Self::parse_inner(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    /// Creates a `.smi` file with one class `SUBTTL` (without a language).
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<SamiFile> {
+        let head = "<SAMI>\n<HEAD>\n<STYLE TYPE=\"text/css\"><!--\nP { font-family: Arial; }\n.SUBTTL { Name: Subtitles; }\n--></STYLE>\n</HEAD>\n";
+        Self::parse(&build_sami(head, &v, "SUBTTL"))
+    }
+
+    /// Returns the languages of the `STYLE` block (every `P` class with a `Name` or a `lang` property).
+    pub fn languages(&self) -> &[SamiLanguage] {
+        &self.languages
+    }
+
+    /// Returns one file per language with only the subtitles of its class.
+    ///
+    /// The files keep the head of this file (with all classes).
+    pub fn split_languages(&self) -> SubtitleParserResult<Vec<(SamiLanguage, SubtitleFile)>> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let head = data.get(..data.to_ascii_lowercase().find("<body").unwrap_or(data.len())).unwrap_or("");
+        let classes = self.entry_classes();
+        let entries = self.get_subtitle_entries()?;
+
+        let mut result = Vec::new();
+        for language in &self.languages {
+            let v: Vec<(TimeSpan, String)> = (entries.iter().zip(&classes))
+                .filter(|(_, class)| class.as_deref().is_some_and(|class| class.eq_ignore_ascii_case(&language.class)))
+                .map(|(entry, _)| (entry.timespan, entry.line.clone().unwrap_or_default()))
+                .collect();
+            result.push((language.clone(), SamiFile::parse(&build_sami(head, &v, &language.class))?.into()));
+        }
+        Ok(result)
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                SamiFilePart::Filler(ref text) => Some(text.as_str()),
+                SamiFilePart::Sync(..) | SamiFilePart::Paragraph(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the `<SYNC>` tags with their paragraphs.
+    fn syncs(&self) -> Vec<SyncInfo> {
+        let mut result: Vec<SyncInfo> = Vec::new();
+        for (part_idx, part) in self.v.iter().enumerate() {
+            match *part {
+                SamiFilePart::Sync(_, start, _) => result.push(SyncInfo {
+                    part_idx,
+                    start,
+                    paragraphs: Vec::new(),
+                }),
+                SamiFilePart::Paragraph(ref paragraph) => {
+                    if let Some(sync) = result.last_mut() {
+                        let has_text = !decode_content(&paragraph.content).is_empty();
+                        sync.paragraphs.push((part_idx, paragraph.class.clone(), has_text));
+                    }
+                }
+                SamiFilePart::Filler(_) => {}
+            }
+        }
+        result
+    }
+
+    /// Returns the class of every entry.
+    fn entry_classes(&self) -> Vec<Option<String>> {
+        let syncs = self.syncs();
+        syncs
+            .iter()
+            .flat_map(|sync| sync.paragraphs.iter().filter(|p| p.2).map(|p| p.1.clone()))
+            .collect()
+    }
+
+    /// Returns for every entry the index of its sync, the part index of its paragraph and the index of the sync which
+    /// ends it.
+    fn entry_positions(syncs: &[SyncInfo]) -> Vec<(usize, usize, Option<usize>)> {
+        let mut result = Vec::new();
+        for (sync_idx, sync) in syncs.iter().enumerate() {
+            for (part_idx, class, _) in sync.paragraphs.iter().filter(|p| p.2) {
+                let same_class = |other: &Option<String>| match (class, other) {
+                    (Some(class), Some(other)) => class.eq_ignore_ascii_case(other),
+                    (None, None) => true,
+                    _ => false,
+                };
+                let end_idx = syncs.iter().enumerate().skip(sync_idx + 1).find_map(|(idx, other)| {
+                    let ends = other.start > sync.start && (other.paragraphs.is_empty() || other.paragraphs.iter().any(|p| same_class(&p.1)));
+                    Some(idx).filter(|_| ends)
+                });
+                result.push((sync_idx, *part_idx, end_idx));
+            }
+        }
+        result
+    }
+
+    fn parse_inner(s: &str) -> Result<SamiFile> {
+        // ASCII lowercase keeps the byte positions
+        let lower = s.to_ascii_lowercase();
+        if !lower.contains("<sami") {
+            return Err(ExpectedSami.into());
+        }
+        let languages = lower
+            .find("<style")
+            .and_then(|start| Some(start..start + lower.get(start..)?.find("</style")?))
+            .and_then(|range| s.get(range))
+            .map(parse_languages)
+            .unwrap_or_default();
+
+        let mut result = Vec::new();
+        let mut filler_start = 0;
+        let mut pos = lower.find("<body").unwrap_or(0);
+        while let Some(offset) = lower.get(pos..).and_then(|rest| rest.find('<')) {
+            pos += offset;
+            let tag = match s.get(pos..).and_then(|rest| rest.get(..rest.find('>')? + 1)) {
+                Some(tag) => tag,
+                None => break,
+            };
+            let tag_end = pos + tag.len();
+            match tag_name(tag).as_str() {
+                "sync" => {
+                    let (value_start, value) = attribute(tag, "start").unwrap_or((tag.len() - 1, ""));
+                    let start = value.trim().parse::<i64>().map_err(|_| InvalidStart { value: value.to_string() })?;
+                    result.push(SamiFilePart::Filler(s.get(filler_start..pos).unwrap_or("").to_string()));
+                    result.push(SamiFilePart::Sync(
+                        tag.get(..value_start).unwrap_or("").to_string(),
+                        TimePoint::from_msecs(start),
+                        tag.get(value_start + value.len()..).unwrap_or("").to_string(),
+                    ));
+                    filler_start = tag_end;
+                }
+                "p" => {
+                    let content_len = next_tag(s.get(tag_end..).unwrap_or(""), PARAGRAPH_END_TAGS);
+                    result.push(SamiFilePart::Filler(s.get(filler_start..pos).unwrap_or("").to_string()));
+                    result.push(SamiFilePart::Paragraph(SamiParagraph {
+                        tag: tag.to_string(),
+                        class: attribute(tag, "class").map(|(_, class)| class.to_string()),
+                        content: s.get(tag_end..tag_end + content_len).unwrap_or("").to_string(),
+                    }));
+                    filler_start = tag_end + content_len;
+                    pos = filler_start;
+                    continue;
+                }
+                _ => {}
+            }
+            pos = tag_end;
+        }
+        result.push(SamiFilePart::Filler(s.get(filler_start..).unwrap_or("").to_string()));
+
+        let parts = dedup_string_parts(result, |part: &mut SamiFilePart| match *part {
+            SamiFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        Ok(SamiFile { v: parts, languages })
+    }
+}
+
+/// Returns the lowercase name of an HTML tag like `<SYNC Start=1000>` (with `/` for end tags).
+fn tag_name(tag: &str) -> String {
+    let name = tag.trim_start_matches('<');
+    let (prefix, name) = match name.strip_prefix('/') {
+        Some(name) => ("/", name),
+        None => ("", name),
+    };
+    let len = name.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(name.len());
+    format!("{}{}", prefix, name.get(..len).unwrap_or("").to_ascii_lowercase())
+}
+
+/// Returns the position and the value of an attribute of an HTML start tag (the name is case-insensitive, the
+/// value can be quoted).
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<(usize, &'a str)> {
+    let body_start = tag.find(|c: char| c.is_whitespace())?;
+    let mut pos = body_start;
+    loop {
+        let rest = tag.get(pos..)?;
+        let key_start = pos + rest.len() - rest.trim_start().len();
+        let rest = tag.get(key_start..)?;
+        let key_len = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>')?;
+        if key_len == 0 {
+            return None;
+        }
+        let key = rest.get(..key_len)?;
+        let after_key = rest.get(key_len..)?.trim_start();
+        let mut value_start = key_start + (rest.len() - after_key.len());
+        let (value_start, value) = match after_key.strip_prefix('=') {
+            Some(value) => {
+                let trimmed = value.trim_start();
+                value_start += 1 + value.len() - trimmed.len();
+                match trimmed.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let inner = trimmed.get(1..)?;
+                        (value_start + 1, inner.get(..inner.find(quote)?)?)
+                    }
+                    _ => (value_start, trimmed.get(..trimmed.find(|c: char| c.is_whitespace() || c == '>')?)?),
+                }
+            }
+            None => (value_start, ""),
+        };
+        if key.eq_ignore_ascii_case(name) {
+            return Some((value_start, value));
+        }
+        // skip the closing quote
+        let value_end = value_start + value.len();
+        pos = value_end + usize::from(tag.get(value_end..).is_some_and(|rest| rest.starts_with(['"', '\''])));
+    }
+}
+
+/// Returns the length of the text up to the first of the given tags (or the whole text).
+fn next_tag(s: &str, names: &[&str]) -> usize {
+    let mut pos = 0;
+    while let Some(offset) = s.get(pos..).and_then(|rest| rest.find('<')) {
+        pos += offset;
+        let rest = s.get(pos..).unwrap_or("");
+        let tag = rest.get(..rest.find('>').map_or(rest.len(), |idx| idx + 1)).unwrap_or("");
+        if names.contains(&tag_name(tag).as_str()) {
+            return pos;
+        }
+        pos += 1;
+    }
+    s.len()
+}
+
+/// Reads the classes with a `Name` or `lang` property from the CSS of the `STYLE` block.
+fn parse_languages(css: &str) -> Vec<SamiLanguage> {
+    let mut result = Vec::new();
+    for rule in css.split('}') {
+        let (selector, declarations) = match rule.split_once('{') {
+            Some(rule) => rule,
+            None => continue,
+        };
+        // `.ENCC` or `P.ENCC`
+        let class = match selector.split_whitespace().last().and_then(|s| s.rsplit_once('.')) {
+            Some((_, class)) if !class.is_empty() => class,
+            _ => continue,
+        };
+        let property = |name: &str| {
+            declarations
+                .split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+        };
+        let (name, lang) = (property("name"), property("lang"));
+        if name.is_some() || lang.is_some() {
+            result.push(SamiLanguage {
+                class: class.to_string(),
+                name,
+                lang,
+            });
+        }
+    }
+    result
+}
+
+/// Converts the HTML content of a paragraph into an entry text (keeps `<i>`, `<b>` and `<u>`).
+fn decode_content(content: &str) -> String {
+    let mut text = String::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (before, tag_start) = rest.split_at(rest.find('<').unwrap_or(rest.len()));
+        // line breaks in the HTML are only whitespace
+        text.extend(before.chars().map(|c| if c.is_whitespace() { ' ' } else { c }));
+        let tag_len = match tag_start.find('>') {
+            Some(idx) => idx + 1,
+            None => {
+                text.push_str(tag_start);
+                break;
+            }
+        };
+        let tag = tag_start.get(..tag_len).unwrap_or("");
+        match tag_name(tag).as_str() {
+            "br" => text.push('\n'),
+            name @ ("i" | "b" | "u" | "/i" | "/b" | "/u") => text.push_str(&format!("<{}>", name)),
+            _ => {}
+        }
+        rest = tag_start.get(tag_len..).unwrap_or("");
+    }
+
+    // `&nbsp;` is used for empty paragraphs which end the previous subtitle
+    let text = decode_entities(&text.replace("&nbsp;", " ").replace("&NBSP;", " "));
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|line| !line.is_empty() && !is_only_tags(line))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns `true` if a line only consists of style tags like `<i></i>`.
+fn is_only_tags(line: &str) -> bool {
+    let mut rest = line;
+    while let Some(tag) = rest.strip_prefix('<').and_then(|tag| tag.split_once('>')) {
+        rest = tag.1;
+    }
+    rest.trim().is_empty()
+}
+
+/// Converts an entry text into the HTML content of a paragraph.
+fn encode_content(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let tag = ["<i>", "<b>", "<u>", "</i>", "</b>", "</u>"]
+            .iter()
+            .find(|tag| rest.get(..tag.len()).is_some_and(|s| s.eq_ignore_ascii_case(tag)));
+        if let Some(tag) = tag {
+            result.push_str(tag);
+            rest = rest.get(tag.len()..).unwrap_or("");
+            continue;
+        }
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '\n' => result.push_str("<br>"),
+            '\r' => {}
+            c => result.push(c),
+        }
+        rest = rest.get(c.len_utf8()..).unwrap_or("");
+    }
+    result
+}
+
+/// Creates the text of a SAMI file with the given head (everything up to `<BODY>`) and subtitles of one class.
+fn build_sami(head: &str, v: &[(TimeSpan, String)], class: &str) -> String {
+    let mut result = head.to_string();
+    result.push_str("<BODY>\n");
+    let mut starts: Vec<TimePoint> = v.iter().map(|(timespan, _)| timespan.start).collect();
+    starts.sort_unstable();
+    for (timespan, text) in v {
+        result.push_str(&format!(
+            "<SYNC Start={}><P Class={}>{}\n",
+            timespan.start.msecs().max(0),
+            class,
+            encode_content(text)
+        ));
+        // the next subtitle ends this one if it starts at the end
+        let next_start = starts.get(starts.partition_point(|&start| start <= timespan.start)).copied();
+        if next_start != Some(timespan.end) {
+            result.push_str(&format!("<SYNC Start={}><P Class={}>&nbsp;\n", timespan.end.msecs().max(0), class));
+        }
+    }
+    result.push_str("</BODY>\n</SAMI>\n");
+    result
+}
+
+/// Returns `true` if the text looks like a SAMI file.
+pub(crate) fn looks_like_sami(text: &str) -> bool {
+    text.trim_start_matches('\u{feff}')
+        .trim_start()
+        .get(..5)
+        .is_some_and(|start| start.eq_ignore_ascii_case("<sami"))
+}
+
+impl SubtitleFileInterface for SamiFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let syncs = self.syncs();
+        let mut result = Vec::new();
+        for (sync_idx, part_idx, end_idx) in Self::entry_positions(&syncs) {
+            let (start, paragraph) = match (syncs.get(sync_idx), self.v.get(part_idx)) {
+                (Some(sync), Some(SamiFilePart::Paragraph(paragraph))) => (sync.start, paragraph),
+                _ => continue,
+            };
+            let end = end_idx.and_then(|idx| syncs.get(idx)).map_or(start + LAST_DURATION, |sync| sync.start);
+            let mut entry = SubtitleEntry::new(TimeSpan::new(start, end), decode_content(&paragraph.content));
+            entry.language = (self.languages.iter())
+                .find(|language| {
+                    paragraph
+                        .class
+                        .as_deref()
+                        .is_some_and(|class| class.eq_ignore_ascii_case(&language.class))
+                })
+                .and_then(|language| language.lang.clone());
+            result.push(entry);
+        }
+        Ok(result)
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let syncs = self.syncs();
+        let positions = Self::entry_positions(&syncs);
+        check_entry_count(positions.len(), new_subtitle_entries.len())?;
+
+        // several entries share a `<SYNC>`, so only changed times are written
+        let set_sync_start = |v: &mut Vec<SamiFilePart>, sync_idx: usize, time: TimePoint| {
+            let sync = syncs.get(sync_idx).filter(|sync| sync.start != time);
+            if let Some(SamiFilePart::Sync(_, ref mut start, _)) = sync.and_then(|sync| v.get_mut(sync.part_idx)) {
+                *start = time;
+            }
+        };
+        for (&(sync_idx, part_idx, end_idx), entry) in positions.iter().zip(new_subtitle_entries) {
+            set_sync_start(&mut self.v, sync_idx, entry.timespan.start);
+
+            // only a `<SYNC>` without subtitles belongs to the end of this subtitle
+            let ends_only = end_idx.filter(|&idx| !positions.iter().any(|&(other_sync, _, _)| other_sync == idx));
+            if let Some(end_idx) = ends_only {
+                set_sync_start(&mut self.v, end_idx, entry.timespan.end);
+            }
+
+            if let (Some(SamiFilePart::Paragraph(ref mut paragraph)), Some(text)) = (self.v.get_mut(part_idx), entry.line.as_ref()) {
+                // unchanged texts keep all markup
+                if *text != decode_content(&paragraph.content) {
+                    let trailing_ws = paragraph.content.len() - paragraph.content.trim_end().len();
+                    let trailing = paragraph.content.get(paragraph.content.len() - trailing_ws..).unwrap_or("").to_string();
+                    paragraph.content = encode_content(text) + &trailing;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = String::new();
+        for part in &self.v {
+            match *part {
+                SamiFilePart::Filler(ref text) => result.push_str(text),
+                SamiFilePart::Sync(ref before, start, ref after) => result.push_str(&format!("{}{}{}", before, start.msecs().max(0), after)),
+                SamiFilePart::Paragraph(ref paragraph) => {
+                    result.push_str(&paragraph.tag);
+                    result.push_str(&paragraph.content);
+                }
+            }
+        }
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let syncs = self.syncs();
+        let positions = Self::entry_positions(&syncs);
+        check_entry_count(positions.len(), keep.len())?;
+
+        // a removed paragraph is removed with its end tag, and a `<SYNC>` without paragraphs with its line
+        let mut removed: Vec<usize> = Vec::new();
+        for sync in &syncs {
+            let kept = |part_idx: usize| {
+                positions
+                    .iter()
+                    .zip(keep)
+                    .find(|((_, idx, _), _)| *idx == part_idx)
+                    .map_or(true, |(_, &keep)| keep)
+            };
+            let is_removed = |&(part_idx, _, has_text): &(usize, Option<String>, bool)| has_text && !kept(part_idx);
+            removed.extend(sync.paragraphs.iter().filter(|p| is_removed(p)).map(|p| p.0));
+            if !sync.paragraphs.is_empty() && sync.paragraphs.iter().all(is_removed) {
+                removed.push(sync.part_idx);
+            }
+        }
+        removed.sort_unstable();
+
+        // removing from the back keeps the indices of the front valid
+        for &part_idx in removed.iter().rev() {
+            let is_sync = matches!(self.v.get(part_idx), Some(SamiFilePart::Sync(..)));
+            let end_tag = if is_sync { "</sync>" } else { "</p>" };
+            if let Some(SamiFilePart::Filler(ref mut next)) = self.v.get_mut(part_idx + 1) {
+                let ws = next.len() - next.trim_start().len();
+                if next.get(ws..ws + end_tag.len()).is_some_and(|tag| tag.eq_ignore_ascii_case(end_tag)) {
+                    next.drain(..ws + end_tag.len());
+                }
+                if is_sync {
+                    let line_end = next.find('\n').map_or(0, |idx| idx + 1);
+                    if next.get(..line_end).is_some_and(|line| line.trim().is_empty()) {
+                        next.drain(..line_end);
+                    }
+                }
+            }
+            self.v.remove(part_idx);
+        }
+
+        self.v = dedup_string_parts(std::mem::take(&mut self.v), |part: &mut SamiFilePart| match *part {
+            SamiFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::to_string;
+
+    const SAMI: &str = "<SAMI>\n<HEAD>\n<STYLE TYPE=\"text/css\"><!--\n\
+                        .ENCC { Name: English; lang: en-US; }\n.FRCC { Name: Français; lang: fr-FR; }\n\
+                        --></STYLE>\n</HEAD>\n<BODY>\n\
+                        <SYNC Start=1000><P Class=ENCC>Hello <i>world</i><br>second<P Class=FRCC>Bonjour &amp; salut\n\
+                        <SYNC Start=3000><P Class=ENCC>&nbsp;\n\
+                        <SYNC Start=4000><P Class=FRCC>Au revoir\n\
+                        </BODY>\n</SAMI>\n";
+
+    fn span(start: i64, end: i64) -> TimeSpan {
+        TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end))
+    }
+
+    fn entries(file: &impl SubtitleFileInterface) -> Vec<(TimeSpan, String, Option<String>)> {
+        let entries = file.get_subtitle_entries().unwrap();
+        entries.into_iter().map(|e| (e.timespan, e.line.unwrap(), e.language)).collect()
+    }
+
+    #[test]
+    fn sami_round_trip() {
+        assert_eq!(to_string(&SamiFile::parse(SAMI).unwrap()), SAMI);
+    }
+
+    #[test]
+    fn sami_entries() {
+        let file = SamiFile::parse(SAMI).unwrap();
+        let lang = |lang: &str| Some(lang.to_string());
+        assert_eq!(
+            entries(&file),
+            vec![
+                (span(1000, 3000), "Hello <i>world</i>\nsecond".to_string(), lang("en-US")),
+                (span(1000, 4000), "Bonjour & salut".to_string(), lang("fr-FR")),
+                (span(4000, 9000), "Au revoir".to_string(), lang("fr-FR")),
+            ]
+        );
+    }
+
+    #[test]
+    fn sami_languages() {
+        let file = SamiFile::parse(SAMI).unwrap();
+        let classes: Vec<(&str, Option<&str>)> = file.languages().iter().map(|l| (l.class.as_str(), l.name.as_deref())).collect();
+        assert_eq!(classes, vec![("ENCC", Some("English")), ("FRCC", Some("Français"))]);
+    }
+
+    #[test]
+    fn sami_update_entries() {
+        let mut file = SamiFile::parse(SAMI).unwrap();
+        let mut new_entries = file.get_subtitle_entries().unwrap();
+        new_entries[0].timespan = span(1500, 3500);
+        new_entries[2].line = Some("À <b>bientôt</b> <3".to_string());
+        file.update_subtitle_entries(&new_entries).unwrap();
+
+        let data = to_string(&file);
+        assert!(data.contains("<SYNC Start=1500><P Class=ENCC>Hello <i>world</i><br>second<P Class=FRCC>"));
+        assert!(data.contains("<SYNC Start=3500><P Class=ENCC>&nbsp;\n"));
+        assert!(data.contains("<SYNC Start=4000><P Class=FRCC>À <b>bientôt</b> &lt;3\n"));
+        assert_eq!(entries(&SamiFile::parse(&data).unwrap())[2].1, "À <b>bientôt</b> <3");
+    }
+
+    #[test]
+    fn sami_update_needs_all_entries() {
+        let mut file = SamiFile::parse(SAMI).unwrap();
+        let new_entries = file.get_subtitle_entries().unwrap();
+        assert!(file.update_subtitle_entries(&new_entries[..2]).is_err());
+    }
+
+    #[test]
+    fn sami_retain_entries() {
+        let mut file = SamiFile::parse(SAMI).unwrap();
+        file.retain_entries_by_mask(&[true, false, false]).unwrap();
+        let data = to_string(&file);
+        assert!(!data.contains("Bonjour") && !data.contains("Au revoir"));
+        assert!(!data.contains("Start=4000"));
+        assert_eq!(
+            entries(&file),
+            vec![(span(1000, 3000), "Hello <i>world</i>\nsecond".to_string(), Some("en-US".to_string()))]
+        );
+    }
+
+    #[test]
+    fn sami_split_languages() {
+        let files = SamiFile::parse(SAMI).unwrap().split_languages().unwrap();
+        let split: Vec<(String, Vec<(TimeSpan, String)>)> = (files.iter())
+            .map(|(language, file)| {
+                let entries = file.get_subtitle_entries().unwrap();
+                (
+                    language.class.clone(),
+                    entries.into_iter().map(|e| (e.timespan, e.line.unwrap())).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            split,
+            vec![
+                ("ENCC".to_string(), vec![(span(1000, 3000), "Hello <i>world</i>\nsecond".to_string())]),
+                (
+                    "FRCC".to_string(),
+                    vec![
+                        (span(1000, 4000), "Bonjour & salut".to_string()),
+                        (span(4000, 9000), "Au revoir".to_string())
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn sami_create() {
+        let file = SamiFile::create(vec![(span(1000, 2000), "a & b".to_string()), (span(2000, 2500), "c".to_string())]).unwrap();
+        assert!(to_string(&file).contains("<SYNC Start=1000><P Class=SUBTTL>a &amp; b\n<SYNC Start=2000><P Class=SUBTTL>c\n<SYNC Start=2500>"));
+        assert_eq!(
+            entries(&file).into_iter().map(|e| e.0).collect::<Vec<_>>(),
+            vec![span(1000, 2000), span(2000, 2500)]
+        );
+    }
+
+    #[test]
+    fn sami_quoted_attributes_and_end_tags() {
+        let file = SamiFile::parse("<sami><body><sync start=\"500\"><p class='X'>text</p></sync><sync start=\"900\"></sync></body></sami>").unwrap();
+        assert_eq!(entries(&file), vec![(span(500, 900), "text".to_string(), None)]);
+    }
+
+    #[test]
+    fn sami_errors() {
+        let kind = |s: &str| SamiFile::parse_inner(s).unwrap_err().kind().to_string();
+        assert_eq!(kind("<html><body></body></html>"), ExpectedSami.to_string());
+        assert_eq!(
+            kind("<SAMI><BODY><SYNC Start=abc><P>x</SAMI>"),
+            InvalidStart { value: "abc".to_string() }.to_string()
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::escaping::srt_text_lines;
+use crate::formats::common::*;
+use crate::warnings::Warnings;
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+use std::iter::once;
+
+/// Errors specific to `.sbv`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected `.sbv` timing line like `0:00:01.000,0:00:04.000`, found '{}'", line)]
+        ExpectedTimingLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+#[derive(Debug, Clone)]
+/// One caption of a `.sbv` file.
+struct SbvEntry {
+    /// start and end time of the caption
+    timespan: TimeSpan,
+
+    /// the text lines
+    texts: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a `.sbv` file (the caption format of YouTube).
+///
+/// Every caption is a timing line like `0:00:01.000,0:00:04.000` followed by the text lines, and captions are
+/// separated by empty lines.
+pub struct SbvFile {
+    v: Vec<SbvEntry>,
+}
+
+impl SbvFile {
+    /// Parse a `.sbv` subtitle string to `SbvFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<SbvFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.sbv` subtitle string to `SbvFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(SbvFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates .sbv file from scratch.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<SbvFile> {
+        let entries = v
+            .into_iter()
+            .map(|(timespan, text)| SbvEntry {
+                timespan,
+                texts: srt_text_lines(&text),
+            })
+            .collect();
+        Ok(SbvFile { v: entries })
+    }
+}
+
+/// Implements parse functions.
+impl SbvFile {
+    fn parse_file(i: &str) -> Result<SbvFile> {
+        let (_, s) = split_bom(i);
+
+        let mut result: Vec<SbvEntry> = Vec::new();
+        let mut current: Option<SbvEntry> = None;
+
+        // the `once("")` is there so no last entry gets ignored
+        for (line_num, line) in s.lines().chain(once("")).enumerate() {
+            let line = line.trim();
+            match current {
+                None if line.is_empty() => {}
+                None => {
+                    let timespan = parse_timing_line(line)
+                        .ok_or_else(|| Error::from(ExpectedTimingLine { line: line.to_string() }))
+                        .with_context(|_| ErrorAtLine { line_num })?;
+                    current = Some(SbvEntry { timespan, texts: Vec::new() });
+                }
+                Some(ref mut entry) if !line.is_empty() => {
+                    // a caption without text and without an empty line before the next timing line
+                    if let Some(timespan) = parse_timing_line(line).filter(|_| entry.texts.is_empty()) {
+                        result.extend(current.replace(SbvEntry { timespan, texts: Vec::new() }));
+                    } else {
+                        entry.texts.push(line.to_string());
+                    }
+                }
+                Some(_) => result.extend(current.take()),
+            }
+        }
+
+        Ok(SbvFile { v: result })
+    }
+}
+
+/// Parses a timestamp like `0:00:01.000` (the fraction can have one to three digits).
+fn parse_timestamp(s: &str) -> Option<TimePoint> {
+    let is_digits = |s: &str, min: usize, max: usize| (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+    let mut parts = s.split(':');
+    let (hours, mins, secs) = (parts.next()?, parts.next()?, parts.next()?);
+    let (secs, fraction) = secs.split_once('.')?;
+    if parts.next().is_some() || !is_digits(hours, 1, 9) || !is_digits(mins, 2, 2) || !is_digits(secs, 2, 2) || !is_digits(fraction, 1, 3) {
+        return None;
+    }
+    let msecs = fraction.parse::<i64>().ok()? * 10i64.pow(3 - fraction.len() as u32);
+    Some(TimePoint::from_components(
+        hours.parse().ok()?,
+        mins.parse().ok()?,
+        secs.parse().ok()?,
+        msecs,
+    ))
+}
+
+/// Parses a timing line like `0:00:01.000,0:00:04.000`.
+pub(crate) fn parse_timing_line(line: &str) -> Option<TimeSpan> {
+    let (start, end) = line.trim().split_once(',')?;
+    Some(TimeSpan::new(parse_timestamp(start.trim())?, parse_timestamp(end.trim())?))
+}
+
+/// Formats a timestamp like `0:00:01.000` (negative times are written as zero).
+fn format_timestamp(t: TimePoint) -> String {
+    let t = if t.is_negative() { TimePoint::from_msecs(0) } else { t };
+    format!("{}:{:02}:{:02}.{:03}", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp())
+}
+
+impl SubtitleFileInterface for SbvFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .map(|entry| SubtitleEntry::new(entry.timespan, entry.texts.join("\n")))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        for (entry, new_entry) in self.v.iter_mut().zip(new_subtitle_entries) {
+            entry.timespan = new_entry.timespan;
+            if let Some(ref text) = new_entry.line {
+                entry.texts = srt_text_lines(text);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let entries: Vec<String> = self
+            .v
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{},{}\n{}\n",
+                    format_timestamp(entry.timespan.start),
+                    format_timestamp(entry.timespan.end),
+                    entry.texts.join("\n")
+                )
+            })
+            .collect();
+        Ok(entries.join("\n").into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn sbv_round_trip_and_edit() {
+        let input = "\u{feff}0:00:01.000,0:00:04.000\nHello\nworld\n\n0:00:05.5,0:00:06.000\r\n\r\n1:02:03.004,1:02:05.000\n>> Second speaker\n";
+        let mut file = SbvFile::parse(input).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\nworld"));
+        assert_eq!((entries[1].timespan.start.msecs(), entries[1].line.as_deref()), (5500, Some("")));
+        assert_eq!(entries[2].timespan.start.msecs(), 3_723_004);
+
+        entries[0].timespan.end += TimeDelta::from_msecs(250);
+        entries[2].line = Some("One\n\nTwo".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[true, false, true]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "0:00:01.000,0:00:04.250\nHello\nworld\n\n1:02:03.004,1:02:05.000\nOne\nTwo\n"
+        );
+
+        assert!(SbvFile::parse("0:00:01.000 --> 0:00:02.000\nx\n").is_err());
+        assert!(SbvFile::parse("0:0:01.000,0:00:02.000\nx\n").is_err());
+    }
+}
@@ -0,0 +1,759 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::durations::DurationSynthesis;
+use crate::errors::Result as SubtitleParserResult;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// Errors specific to `.scc`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected `Scenarist_SCC V1.0` header")]
+        ExpectedHeader,
+
+        #[fail(display = "expected caption line like `00:00:01:15<TAB>9420 9420`, found '{}'", line)]
+        ExpectedCaptionLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// The header of every `.scc` file.
+const HEADER: &str = "Scenarist_SCC V1.0";
+
+/// The frame rate of NTSC video: all `.scc` time codes count frames of 30000/1001 fps.
+const FPS_NUMERATOR: i64 = 30_000;
+const FPS_DENOMINATOR: i64 = 1001;
+
+/// The number of frames in ten minutes of drop-frame time code.
+const FRAMES_PER_10_MINUTES: i64 = 17_982;
+
+/// The number of frames in a minute of drop-frame time code (except in every tenth minute).
+const FRAMES_PER_MINUTE: i64 = 1798;
+
+/// A time code like `00:00:01:15` (or `00:00:01;15` for drop-frame time code).
+#[derive(Debug, Clone, Copy)]
+struct SccTimecode {
+    /// The frame number since `00:00:00:00`.
+    frame: i64,
+
+    /// Whether the time code skips the frame labels `00` and `01` in most minutes (written with `;`).
+    drop_frame: bool,
+}
+
+impl SccTimecode {
+    /// Parses a time code like `01:02:03:04` or `01:02:03;04`.
+    fn parse(s: &str) -> Option<SccTimecode> {
+        let is_digits = |s: &str| s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit());
+        let drop_frame = s.contains(';');
+        let mut parts = s.split([':', ';', '.']);
+        let (hours, mins, secs, frames) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || ![hours, mins, secs, frames].iter().all(|s| is_digits(s)) {
+            return None;
+        }
+        let (hours, mins, secs, frames): (i64, i64, i64, i64) = (hours.parse().ok()?, mins.parse().ok()?, secs.parse().ok()?, frames.parse().ok()?);
+        if mins >= 60 || secs >= 60 || frames >= 30 {
+            return None;
+        }
+
+        let mut frame = ((hours * 60 + mins) * 60 + secs) * 30 + frames;
+        if drop_frame {
+            let total_mins = hours * 60 + mins;
+            frame -= 2 * (total_mins - total_mins / 10);
+        }
+        Some(SccTimecode { frame, drop_frame })
+    }
+
+    /// Returns the time of the frame.
+    fn to_time(self) -> TimePoint {
+        TimePoint::from_msecs(div_round(self.frame * 1000 * FPS_DENOMINATOR, FPS_NUMERATOR))
+    }
+
+    /// Returns the time code of the frame at `time` (with the same time code style).
+    fn with_time(self, time: TimePoint) -> SccTimecode {
+        SccTimecode {
+            frame: div_round(time.msecs().max(0) * FPS_NUMERATOR, 1000 * FPS_DENOMINATOR),
+            drop_frame: self.drop_frame,
+        }
+    }
+
+    /// Formats the time code like it was parsed.
+    fn format(self) -> String {
+        let mut frame = self.frame.max(0);
+        if self.drop_frame {
+            // skip the labels of the dropped frames again
+            let (tens, rest) = (frame / FRAMES_PER_10_MINUTES, frame % FRAMES_PER_10_MINUTES);
+            let dropped = if rest < 2 { 0 } else { 2 * ((rest - 2) / FRAMES_PER_MINUTE) };
+            frame += 18 * tens + dropped;
+        }
+        let (secs, frames) = (frame / 30, frame % 30);
+        let separator = if self.drop_frame { ';' } else { ':' };
+        format!("{:02}:{:02}:{:02}{}{:02}", secs / 3600, secs / 60 % 60, secs % 60, separator, frames)
+    }
+}
+
+/// Divides and rounds to the nearest integer (for non-negative numbers).
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    (numerator + denominator / 2) / denominator
+}
+
+/// A line of caption data like `00:00:01:15<TAB>9420 9420 94ae`.
+#[derive(Debug, Clone)]
+struct SccLine {
+    timecode: SccTimecode,
+
+    /// Everything after the time code (the separator and the hex words), written unchanged.
+    payload: String,
+
+    /// The byte pairs of the payload.
+    words: Vec<(u8, u8)>,
+}
+
+#[derive(Debug, Clone)]
+enum SccFilePart {
+    /// Spaces, empty lines and the header
+    Filler(String),
+
+    /// A line with caption data (without the newline)
+    Line(SccLine),
+}
+
+/// A caption as it was shown on the screen.
+#[derive(Debug, Clone)]
+struct SccCaption {
+    text: String,
+
+    /// The index of the line which put the caption on the screen
+    start_line: usize,
+
+    /// The index of the line which removed or replaced the caption
+    end_line: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a `.scc` file (Scenarist Closed Captions with CEA-608 caption data).
+///
+/// The entries are decoded from the caption commands of data channel 1: pop-on, roll-up and paint-on captions
+/// become one entry for every change of the displayed text. Only timing changes are written back; the caption
+/// data is kept byte by byte, so new texts are ignored by `update_subtitle_entries()`.
+pub struct SccFile {
+    v: Vec<SccFilePart>,
+}
+
+impl SccFile {
+    fn new(v: Vec<SccFilePart>) -> SccFile {
+        // cleans up multiple fillers after another
+        let new_file_parts = dedup_string_parts(v, |part: &mut SccFilePart| match *part {
+            SccFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        SccFile { v: new_file_parts }
+    }
+
+    /// Parse a `.scc` subtitle string to `SccFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<SccFile> {
+        Ok(Self::parse_file(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &SccLine> {
+        self.v.iter().filter_map(|part| match *part {
+            SccFilePart::Line(ref line) => Some(line),
+            SccFilePart::Filler(_) => None,
+        })
+    }
+
+    fn lines_mut(&mut self) -> impl Iterator<Item = &mut SccLine> {
+        self.v.iter_mut().filter_map(|part| match *part {
+            SccFilePart::Line(ref mut line) => Some(line),
+            SccFilePart::Filler(_) => None,
+        })
+    }
+
+    /// Runs the caption commands of all lines and returns the displayed captions.
+    fn decode(&self) -> Vec<SccCaption> {
+        let mut decoder = Decoder::default();
+        let mut captions: Vec<SccCaption> = Vec::new();
+        let mut displayed = String::new();
+
+        for (line_idx, line) in self.lines().enumerate() {
+            for &(b1, b2) in &line.words {
+                decoder.process(b1 & 0x7f, b2 & 0x7f);
+            }
+
+            let text = decoder.displayed_text();
+            if text == displayed {
+                continue;
+            }
+            if let Some(caption) = captions.last_mut().filter(|caption| caption.end_line.is_none()) {
+                caption.end_line = Some(line_idx);
+            }
+            if !text.is_empty() {
+                captions.push(SccCaption {
+                    text: text.clone(),
+                    start_line: line_idx,
+                    end_line: None,
+                });
+            }
+            displayed = text;
+        }
+
+        captions
+    }
+}
+
+/// Implements parse functions.
+impl SccFile {
+    fn parse_file(i: &str) -> Result<SccFile> {
+        let mut result: Vec<SccFilePart> = Vec::new();
+        let (bom, s) = split_bom(i);
+        result.push(SccFilePart::Filler(bom.to_string()));
+
+        let mut header_found = false;
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || !header_found {
+                if !trimmed.is_empty() {
+                    if trimmed != HEADER {
+                        return Err(Error::from(ExpectedHeader)).with_context(|_| ErrorAtLine { line_num })?;
+                    }
+                    header_found = true;
+                }
+                result.push(SccFilePart::Filler(line + &newline));
+                continue;
+            }
+
+            let scc_line = Self::parse_line(&line)
+                .ok_or_else(|| Error::from(ExpectedCaptionLine { line: line.clone() }))
+                .with_context(|_| ErrorAtLine { line_num })?;
+            result.push(SccFilePart::Line(scc_line));
+            result.push(SccFilePart::Filler(newline));
+        }
+
+        if !header_found {
+            return Err(ExpectedHeader.into());
+        }
+
+        Ok(SccFile::new(result))
+    }
+
+    /// Parses a line like `00:00:01:15<TAB>9420 9420 94ae`.
+    fn parse_line(line: &str) -> Option<SccLine> {
+        let line = line.trim_end();
+        let idx = line.find(char::is_whitespace)?;
+        let (timecode, payload) = line.split_at(idx);
+        Some(SccLine {
+            timecode: SccTimecode::parse(timecode)?,
+            payload: payload.to_string(),
+            words: parse_words(payload)?,
+        })
+    }
+}
+
+/// Parses hex words like `9420 9420 c8e5` into byte pairs.
+fn parse_words(payload: &str) -> Option<Vec<(u8, u8)>> {
+    payload
+        .split_whitespace()
+        .map(|word| {
+            if word.len() != 4 {
+                return None;
+            }
+            let value = u16::from_str_radix(word, 16).ok()?;
+            Some(((value >> 8) as u8, (value & 0xff) as u8))
+        })
+        .collect()
+}
+
+impl SccLine {
+    /// Changes the caption data so the line doesn't show new text: "end of caption" becomes "erase displayed
+    /// memory" and (for roll-up and paint-on captions) characters become padding.
+    fn hide_caption(&mut self) {
+        let is_eoc = |(b1, b2): (u8, u8)| (b1 & 0x7f == 0x14 || b1 & 0x7f == 0x15) && b2 & 0x7f == 0x2f;
+        let pop_on = self.words.iter().any(|&word| is_eoc(word));
+
+        let rest = self.payload.trim_start();
+        let separator = &self.payload[..self.payload.len() - rest.len()];
+        let words: Vec<String> = rest
+            .split(' ')
+            .map(|token| match parse_words(token).as_deref() {
+                Some(&[word]) if pop_on && is_eoc(word) => {
+                    let (b1, erase) = token.split_at(2);
+                    b1.to_string() + if erase.ends_with('F') { "2C" } else { "2c" }
+                }
+                Some(&[(b1, _)]) if !pop_on && b1 & 0x7f >= 0x20 => "8080".to_string(),
+                _ => token.to_string(),
+            })
+            .collect();
+        self.payload = separator.to_string() + &words.join(" ");
+        self.words = parse_words(&self.payload).unwrap_or_default();
+    }
+}
+
+/// One of the three ways CEA-608 captions are put on the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaptionMode {
+    /// The text is loaded off-screen and shown at once by "end of caption".
+    PopOn,
+
+    /// The text appears at the bottom row, which moves up with every carriage return.
+    RollUp { rows: u8 },
+
+    /// The text appears on the screen character by character.
+    PaintOn,
+}
+
+/// Caption memory: the text of every row (1 to 15).
+#[derive(Debug, Clone, Default)]
+struct Memory {
+    rows: BTreeMap<u8, Row>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Row {
+    text: String,
+
+    /// An `<i>` tag is open at the end of the row
+    italic: bool,
+}
+
+impl Memory {
+    fn text(&self) -> String {
+        let lines: Vec<String> = self
+            .rows
+            .values()
+            .map(|row| {
+                let mut text = row.text.trim_end().to_string();
+                if row.italic {
+                    text.push_str("</i>");
+                }
+                text.replace("<i></i>", "").trim().to_string()
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// Runs the CEA-608 commands of data channel 1.
+#[derive(Debug, Clone)]
+struct Decoder {
+    mode: CaptionMode,
+    displayed: Memory,
+    non_displayed: Memory,
+
+    /// The row of the cursor
+    row: u8,
+
+    /// Whether the last characters belong to data channel 1 (control codes switch the channel)
+    channel_1: bool,
+
+    /// The last control code (control codes are usually sent twice, the repetition is ignored)
+    last_control: Option<(u8, u8)>,
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder {
+            mode: CaptionMode::PopOn,
+            displayed: Memory::default(),
+            non_displayed: Memory::default(),
+            row: 15,
+            channel_1: true,
+            last_control: None,
+        }
+    }
+}
+
+/// The characters of `0x11 0x30` to `0x11 0x3f`.
+const SPECIAL_CHARS: [char; 16] = ['®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û'];
+
+/// The characters of `0x12 0x20` to `0x12 0x3f` (Spanish, French and miscellaneous).
+const EXTENDED_CHARS_1: [char; 32] = [
+    'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '’', '—', '©', '℠', '•', '“', '”', 'À', 'Â', 'Ç', 'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù',
+    'Û', '«', '»',
+];
+
+/// The characters of `0x13 0x20` to `0x13 0x3f` (Portuguese, German and Danish).
+const EXTENDED_CHARS_2: [char; 32] = [
+    'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~', 'Ä', 'ä', 'Ö', 'ö', 'ß', '¥', '¤', '│', 'Å', 'å', 'Ø', 'ø', '┌',
+    '┐', '└', '┘',
+];
+
+/// The rows of the preamble address codes (by the first byte without channel bit).
+const PAC_ROWS: [u8; 8] = [11, 1, 3, 12, 14, 5, 7, 9];
+
+impl Decoder {
+    /// Processes a byte pair (without parity bits).
+    fn process(&mut self, b1: u8, b2: u8) {
+        if (0x10..=0x1f).contains(&b1) {
+            if self.last_control == Some((b1, b2)) {
+                self.last_control = None;
+                return;
+            }
+            self.last_control = Some((b1, b2));
+            self.channel_1 = b1 & 0x08 == 0;
+            if self.channel_1 {
+                self.control(b1, b2);
+            }
+            return;
+        }
+
+        self.last_control = None;
+        if self.channel_1 {
+            for &b in &[b1, b2] {
+                if let Some(c) = basic_char(b) {
+                    self.memory().push(c);
+                }
+            }
+        }
+    }
+
+    /// Processes a control code of data channel 1.
+    fn control(&mut self, b1: u8, b2: u8) {
+        match (b1, b2) {
+            // miscellaneous control codes (for field 1 and field 2)
+            (0x14, 0x20..=0x2f) | (0x15, 0x20..=0x2f) => self.misc_control(b2),
+            // mid-row codes: a space with a new style
+            (0x11, 0x20..=0x2f) => {
+                let row = self.memory();
+                let italic = b2 >= 0x2e;
+                row.text.push(' ');
+                row.set_italic(italic);
+            }
+            (0x11, 0x30..=0x3f) => {
+                if let Some(&c) = SPECIAL_CHARS.get(usize::from(b2 - 0x30)) {
+                    self.memory().push(c);
+                }
+            }
+            // extended characters replace the standard character that was sent before for older decoders
+            (0x12, 0x20..=0x3f) | (0x13, 0x20..=0x3f) => {
+                let table = if b1 == 0x12 { &EXTENDED_CHARS_1 } else { &EXTENDED_CHARS_2 };
+                if let Some(&c) = table.get(usize::from(b2 - 0x20)) {
+                    let row = self.memory();
+                    row.backspace();
+                    row.push(c);
+                }
+            }
+            // tab offsets only move the cursor within the row
+            (0x17, 0x21..=0x23) => {}
+            (0x10..=0x17, 0x40..=0x7f) => self.preamble_address(b1, b2),
+            _ => {}
+        }
+    }
+
+    fn misc_control(&mut self, b2: u8) {
+        match b2 {
+            // resume caption loading
+            0x20 => self.mode = CaptionMode::PopOn,
+            // backspace
+            0x21 => self.memory().backspace(),
+            // delete to end of row
+            0x24 => {}
+            // roll-up captions with 2, 3 or 4 rows
+            0x25..=0x27 => {
+                let rows = b2 - 0x23;
+                if !matches!(self.mode, CaptionMode::RollUp { .. }) {
+                    self.displayed = Memory::default();
+                    self.row = 15;
+                }
+                self.mode = CaptionMode::RollUp { rows };
+            }
+            // resume direct captioning
+            0x29 => self.mode = CaptionMode::PaintOn,
+            // erase displayed memory
+            0x2c => self.displayed = Memory::default(),
+            // carriage return
+            0x2d => {
+                if let CaptionMode::RollUp { rows } = self.mode {
+                    let lowest = self.row.saturating_sub(rows - 1);
+                    let old_rows = std::mem::take(&mut self.displayed.rows);
+                    self.displayed.rows = old_rows
+                        .into_iter()
+                        .filter(|&(row, _)| row > lowest)
+                        .map(|(row, text)| (row - 1, text))
+                        .collect();
+                }
+            }
+            // erase non-displayed memory
+            0x2e => self.non_displayed = Memory::default(),
+            // end of caption: flip memories
+            0x2f => {
+                std::mem::swap(&mut self.displayed, &mut self.non_displayed);
+                self.mode = CaptionMode::PopOn;
+            }
+            _ => {}
+        }
+    }
+
+    /// Moves the cursor to a new row (the indentation is ignored).
+    fn preamble_address(&mut self, b1: u8, b2: u8) {
+        let first_row = PAC_ROWS.get(usize::from(b1 & 0x07)).copied().unwrap_or(15);
+        let row = if b2 >= 0x60 && first_row != 11 { first_row + 1 } else { first_row };
+        let italic = b2 & 0x1e == 0x0e;
+
+        if let CaptionMode::RollUp { .. } = self.mode {
+            // roll-up captions stay at their base row, so the text moves with the window
+            if row != self.row {
+                let rows = std::mem::take(&mut self.displayed.rows);
+                let offset = i16::from(row) - i16::from(self.row);
+                self.displayed.rows = rows
+                    .into_iter()
+                    .filter_map(|(old, text)| {
+                        u8::try_from(i16::from(old) + offset)
+                            .ok()
+                            .filter(|r| (1..=15).contains(r))
+                            .map(|r| (r, text))
+                    })
+                    .collect();
+            }
+        }
+        self.row = row;
+        let row = self.memory();
+        if !row.text.is_empty() && !row.text.ends_with(' ') {
+            row.text.push(' ');
+        }
+        row.set_italic(italic);
+    }
+
+    /// Returns the row of the cursor in the memory that is written by the current mode.
+    fn memory(&mut self) -> &mut Row {
+        let memory = match self.mode {
+            CaptionMode::PopOn => &mut self.non_displayed,
+            CaptionMode::RollUp { .. } | CaptionMode::PaintOn => &mut self.displayed,
+        };
+        memory.rows.entry(self.row).or_default()
+    }
+
+    fn displayed_text(&self) -> String {
+        self.displayed.text()
+    }
+}
+
+impl Row {
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    fn backspace(&mut self) {
+        if self.text.ends_with('>') && (self.text.ends_with("<i>") || self.text.ends_with("</i>")) {
+            // keep the style tags, only remove characters
+            return;
+        }
+        self.text.pop();
+    }
+
+    fn set_italic(&mut self, italic: bool) {
+        if italic != self.italic {
+            self.text.push_str(if italic { "<i>" } else { "</i>" });
+            self.italic = italic;
+        }
+    }
+}
+
+/// Maps a standard character (without parity bit) to a char; `None` for padding.
+fn basic_char(b: u8) -> Option<char> {
+    Some(match b {
+        0x2a => 'á',
+        0x5c => 'é',
+        0x5e => 'í',
+        0x5f => 'ó',
+        0x60 => 'ú',
+        0x7b => 'ç',
+        0x7c => '÷',
+        0x7d => 'Ñ',
+        0x7e => 'ñ',
+        0x7f => '■',
+        0x20..=0x7f => char::from(b),
+        _ => return None,
+    })
+}
+
+/// How a character is encoded in CEA-608 captions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharEncoding {
+    /// A standard character (one byte).
+    Basic,
+
+    /// A special character (one control code pair).
+    Special,
+
+    /// An extended character (a standard replacement character followed by a control code pair).
+    Extended,
+}
+
+/// Returns how a character is encoded in captions, `None` if it isn't in the CEA-608 character set.
+pub(crate) fn char_encoding(c: char) -> Option<CharEncoding> {
+    if (0x20..=0x7f).any(|b| basic_char(b) == Some(c)) {
+        Some(CharEncoding::Basic)
+    } else if SPECIAL_CHARS.contains(&c) {
+        Some(CharEncoding::Special)
+    } else if EXTENDED_CHARS_1.contains(&c) || EXTENDED_CHARS_2.contains(&c) {
+        Some(CharEncoding::Extended)
+    } else {
+        None
+    }
+}
+
+impl SccFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                SccFilePart::Filler(ref text) => Some(text.as_str()),
+                SccFilePart::Line(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for SccFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let times: Vec<TimePoint> = self.lines().map(|line| line.timecode.to_time()).collect();
+        let captions = self.decode();
+
+        // captions that are never removed are shown for a reading time
+        let cues: Vec<(TimePoint, Option<&str>)> = captions
+            .iter()
+            .map(|caption| {
+                let start = times.get(caption.start_line).copied().unwrap_or(TimePoint::from_msecs(0));
+                (start, Some(caption.text.as_str()))
+            })
+            .collect();
+        let synthesized = DurationSynthesis::READING_SPEED.timespans(&cues);
+
+        Ok(captions
+            .iter()
+            .zip(synthesized)
+            .map(|(caption, timespan)| {
+                let end = caption.end_line.and_then(|idx| times.get(idx).copied()).unwrap_or(timespan.end);
+                SubtitleEntry::new(TimeSpan::new(timespan.start, end), caption.text.clone())
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let captions = self.decode();
+        check_entry_count(captions.len(), new_subtitle_entries.len())?;
+
+        let old_times: Vec<TimePoint> = self.lines().map(|line| line.timecode.to_time()).collect();
+        let mut new_times: Vec<Option<TimePoint>> = vec![None; old_times.len()];
+        for (caption, entry) in captions.iter().zip(new_subtitle_entries) {
+            if let Some(time) = caption.end_line.and_then(|idx| new_times.get_mut(idx)) {
+                *time = Some(entry.timespan.end);
+            }
+        }
+        // a line which replaces a caption by the next one is moved with the start of the next one
+        for (caption, entry) in captions.iter().zip(new_subtitle_entries) {
+            if let Some(time) = new_times.get_mut(caption.start_line) {
+                *time = Some(entry.timespan.start);
+            }
+        }
+
+        // the other lines (for example the loading of pop-on captions) keep their distance to the next moved line
+        let mut delta = None;
+        let mut deltas = Vec::new();
+        for (new_time, &old_time) in new_times.iter().zip(&old_times).rev() {
+            if let Some(new_time) = *new_time {
+                delta = Some(new_time - old_time);
+            }
+            deltas.push(delta);
+        }
+        deltas.reverse();
+        // the lines after the last moved line are moved with it
+        let last_delta = deltas.iter().rev().find_map(|&delta| delta).unwrap_or(TimeDelta::from_msecs(0));
+
+        for ((line, &old_time), delta) in self.lines_mut().zip(&old_times).zip(deltas) {
+            let delta = delta.unwrap_or(last_delta);
+            if delta != TimeDelta::from_msecs(0) {
+                line.timecode = line.timecode.with_time(old_time + delta);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let result: String = self
+            .v
+            .iter()
+            .map(|part| match *part {
+                SccFilePart::Filler(ref text) => text.clone(),
+                SccFilePart::Line(ref line) => line.timecode.format() + &line.payload,
+            })
+            .collect();
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let captions = self.decode();
+        check_entry_count(captions.len(), keep.len())?;
+
+        // the caption data stays in the file, but the removed captions are never shown
+        let hidden: Vec<usize> = captions
+            .iter()
+            .zip(keep)
+            .filter(|&(_, &keep)| !keep)
+            .map(|(caption, _)| caption.start_line)
+            .collect();
+        for (idx, line) in self.lines_mut().enumerate() {
+            if hidden.contains(&idx) {
+                line.hide_caption();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scc_decoding_and_timing() {
+        // pop-on "Hello" (with an extended character), erased, then a two-row pop-on caption in italics
+        let input = "Scenarist_SCC V1.0\n\n\
+                     00:00:01;00\t94ae 94ae 9420 9420 94d0 94d0 c8e5 ecec ef20 e6f2 e9e5 6e64 2043 9232 9232 942f 942f\n\n\
+                     00:00:03;00\t942c 942c\n\n\
+                     00:00:04:00\t94ae 94ae 9420 9420 9440 9440 4f6e e580 946e 946e 54f7 ef80 942f 942f\n\n\
+                     00:01:00;02\t942c 942c\n";
+        let mut file = SccFile::parse(input).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line.as_deref(), Some("Hello friend Ç"));
+        assert_eq!((entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()), (1001, 3003));
+        assert_eq!(entries[1].line.as_deref(), Some("One\n<i>Two</i>"));
+        assert_eq!((entries[1].timespan.start.msecs(), entries[1].timespan.end.msecs()), (4004, 60_060));
+
+        let mut edited = entries.clone();
+        edited[0].timespan = TimeSpan::new(TimePoint::from_msecs(2002), TimePoint::from_msecs(4004));
+        file.update_subtitle_entries(&edited).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.starts_with("Scenarist_SCC V1.0\n\n00:00:02;00\t94ae 94ae 9420"));
+        assert!(data.contains("\n\n00:00:04;00\t942c 942c\n\n00:00:04:00\t94ae"));
+
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        let entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line.as_deref(), Some("One\n<i>Two</i>"));
+
+        assert!(SccFile::parse("00:00:01:00\t9420\n").is_err());
+        assert!(SccFile::parse("Scenarist_SCC V1.0\n00:00:01:00\t942\n").is_err());
+    }
+}
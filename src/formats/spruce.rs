@@ -0,0 +1,383 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::timetypes::{RoundingMode, TimePoint, TimeSpan};
+
+/// Errors specific to Spruce STL-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(
+            display = "expected Spruce STL setting or subtitle line like `00:00:01:12 , 00:00:03:20 , Text`, found '{}'",
+            line
+        )]
+        ExpectedSubtitleLine { line: String },
+
+        #[fail(display = "the timecode `{}` is invalid for the frame rate", value)]
+        InvalidTimecode { value: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+/// The style toggles of Spruce STL texts and the tags of the entry texts.
+const STYLE_TOGGLES: &[(&str, &str)] = &[("^B", "b"), ("^I", "i"), ("^U", "u")];
+
+#[derive(Debug, Clone)]
+/// One subtitle line of a Spruce STL file.
+struct SpruceEntry {
+    /// the comment, setting and empty lines before the subtitle (with line breaks)
+    before: String,
+
+    /// the start timecode as it is written
+    start: String,
+
+    /// the end timecode as it is written
+    end: String,
+
+    /// the separators after the start and after the end timecode (like ` , `)
+    separators: [String; 2],
+
+    timespan: TimeSpan,
+
+    /// the text as it is written (with `|` as line break)
+    raw: String,
+
+    /// the line break after the subtitle
+    newline: String,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a Spruce STL file (the text `.stl` format of DVD Studio Pro, not the binary EBU-STL).
+///
+/// Every subtitle is a line like `00:00:01:12 , 00:00:03:20 , Hello|world` with `hh:mm:ss:ff` timecodes, whose
+/// frames count frames of the frame rate given to `parse()`. In the text, `|` is a line break and `^B`, `^I` and
+/// `^U` toggle bold, italic and underlined text (`<b>`, `<i>` and `<u>` in the entry texts). Settings like
+/// `$FontName = Arial` and `//` comments are kept as they are, as are unchanged timecodes and texts.
+pub struct SpruceStlFile {
+    /// everything before the first subtitle (including the line breaks)
+    header: String,
+
+    v: Vec<SpruceEntry>,
+
+    /// everything after the last subtitle
+    trailer: String,
+
+    fps: f64,
+}
+
+impl SpruceStlFile {
+    /// Parse a Spruce STL subtitle string with the given frame rate to `SpruceStlFile`.
+    pub fn parse(s: &str, fps: f64) -> SubtitleParserResult<SpruceStlFile> {
+        Ok(Self::parse_with_warnings(s, fps)?.0)
+    }
+
+    /// Parse a Spruce STL subtitle string with the given frame rate to `SpruceStlFile` and also return all non-fatal
+    /// issues.
+    pub fn parse_with_warnings(s: &str, fps: f64) -> SubtitleParserResult<(SpruceStlFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, fps, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a Spruce STL file from scratch (with the font settings of DVD Studio Pro).
+    pub fn create(v: Vec<(TimeSpan, String)>, fps: f64) -> SubtitleParserResult<SpruceStlFile> {
+        let entries = v
+            .into_iter()
+            .map(|(timespan, text)| SpruceEntry {
+                before: String::new(),
+                start: format_timecode(timespan.start, fps),
+                end: format_timecode(timespan.end, fps),
+                separators: [" , ".to_string(), " , ".to_string()],
+                timespan,
+                raw: encode_text(&text),
+                newline: "\n".to_string(),
+            })
+            .collect();
+        Ok(SpruceStlFile {
+            header: "$FontName = Arial\n$FontSize = 30\n".to_string(),
+            v: entries,
+            trailer: String::new(),
+            fps,
+        })
+    }
+
+    fn parse_file(i: &str, fps: f64, warnings: &mut Warnings) -> Result<SpruceStlFile> {
+        let (bom, s) = split_bom(i);
+
+        let mut header = bom.to_string();
+        let mut before = String::new();
+        let mut result: Vec<SpruceEntry> = Vec::new();
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            let trimmed = line.trim();
+            if let Some(entry) = parse_subtitle_line(&line, fps).with_context(|_| ErrorAtLine { line_num })? {
+                result.push(SpruceEntry {
+                    before: std::mem::take(&mut before),
+                    newline,
+                    ..entry
+                });
+                continue;
+            }
+            if !(trimmed.is_empty() || trimmed.starts_with('$') || trimmed.starts_with("//")) {
+                if result.is_empty() {
+                    return Err(Error::from(ExpectedSubtitleLine { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+            }
+            let text = if result.is_empty() { &mut header } else { &mut before };
+            text.push_str(&line);
+            text.push_str(&newline);
+        }
+
+        Ok(SpruceStlFile {
+            header,
+            v: result,
+            trailer: before,
+            fps,
+        })
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        let entries = self.v.iter().flat_map(|entry| vec![Some(entry.before.as_str()), None]);
+        Some(Some(self.header.as_str()))
+            .into_iter()
+            .chain(entries)
+            .chain(Some(Some(self.trailer.as_str())))
+            .collect()
+    }
+}
+
+/// Returns `true` if the text looks like a Spruce STL file (and not like a binary EBU-STL file).
+pub(crate) fn looks_like_spruce(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    let first_line = text.lines().map(str::trim).find(|line| !line.is_empty());
+    first_line.is_some_and(|line| {
+        let is_subtitle_line = line.split(',').next().is_some_and(|start| is_timecode(start.trim()));
+        line.starts_with('$') || line.starts_with("//") || is_subtitle_line
+    })
+}
+
+/// Returns `true` for strings like `00:00:01:12`.
+fn is_timecode(s: &str) -> bool {
+    let parts: Vec<&str> = s.split(':').collect();
+    parts.len() == 4 && parts.iter().all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The number of frames per second of the timecodes (like 30 for 29.97 fps).
+fn timecode_rate(fps: f64) -> i64 {
+    (fps.round() as i64).max(1)
+}
+
+/// Parses a timecode like `00:00:01:12`.
+fn parse_timecode(s: &str, fps: f64) -> Result<TimePoint> {
+    let invalid = || InvalidTimecode { value: s.to_string() };
+    let parts = s
+        .split(':')
+        .map(|part| part.parse::<i64>().ok())
+        .collect::<Option<Vec<i64>>>()
+        .ok_or_else(invalid)?;
+    let rate = timecode_rate(fps);
+    match *parts.as_slice() {
+        [hours, mins, secs, frames] if mins < 60 && secs < 60 && frames < rate => {
+            let frame = hours
+                .checked_mul(3600)
+                .and_then(|hour_secs| hour_secs.checked_add(mins * 60 + secs))
+                .and_then(|all_secs| all_secs.checked_mul(rate))
+                .and_then(|all_frames| all_frames.checked_add(frames))
+                .ok_or_else(invalid)?;
+            Ok(TimePoint::from_frame(frame, fps, RoundingMode::Nearest))
+        }
+        _ => Err(invalid().into()),
+    }
+}
+
+/// Formats a timecode like `00:00:01:12` (negative times are written as zero).
+fn format_timecode(t: TimePoint, fps: f64) -> String {
+    let rate = timecode_rate(fps);
+    let frame = t.to_frame(fps, RoundingMode::Nearest).max(0);
+    let secs = frame / rate;
+    format!("{:02}:{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60, frame % rate)
+}
+
+/// Parses a subtitle line like `00:00:01:12 , 00:00:03:20 , Text` (`None` for other lines).
+fn parse_subtitle_line(line: &str, fps: f64) -> Result<Option<SpruceEntry>> {
+    let (start, rest) = match line.split_once(',') {
+        Some((start, rest)) if is_timecode(start.trim()) => (start, rest),
+        _ => return Ok(None),
+    };
+    let (end, text) = match rest.split_once(',') {
+        Some((end, text)) if is_timecode(end.trim()) => (end, text),
+        _ => return Err(ExpectedSubtitleLine { line: line.to_string() }.into()),
+    };
+
+    let (start_ws, start, ws1) = trim_non_destructive(start);
+    let (ws2, end, ws3) = trim_non_destructive(end);
+    let raw = text.trim_start();
+    let ws4 = text.get(..text.len() - raw.len()).unwrap_or("");
+    Ok(Some(SpruceEntry {
+        before: String::new(),
+        timespan: TimeSpan::new(parse_timecode(&start, fps)?, parse_timecode(&end, fps)?),
+        start: format!("{}{}", start_ws, start),
+        end,
+        separators: [format!("{},{}", ws1, ws2), format!("{},{}", ws3, ws4)],
+        raw: raw.to_string(),
+        newline: String::new(),
+    }))
+}
+
+/// Converts the raw text of a subtitle into an entry text.
+fn decode_text(raw: &str) -> String {
+    let mut result = raw.replace('|', "\n");
+    for &(toggle, tag) in STYLE_TOGGLES {
+        let mut open = false;
+        while let Some(idx) = result.find(toggle) {
+            let replacement = if open { format!("</{}>", tag) } else { format!("<{}>", tag) };
+            result.replace_range(idx..idx + toggle.len(), &replacement);
+            open = !open;
+        }
+    }
+    result
+}
+
+/// Converts an entry text into a raw subtitle text.
+fn encode_text(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    let mut result = lines.join("|");
+    for &(toggle, tag) in STYLE_TOGGLES {
+        for pattern in [format!("<{}>", tag), format!("</{}>", tag)].iter() {
+            result = result.replace(pattern.as_str(), toggle).replace(pattern.to_uppercase().as_str(), toggle);
+        }
+    }
+    result
+}
+
+impl SubtitleFileInterface for SpruceStlFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .map(|entry| SubtitleEntry::new(entry.timespan, decode_text(&entry.raw)))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        let fps = self.fps;
+        for (entry, new_entry) in self.v.iter_mut().zip(new_subtitle_entries) {
+            if entry.timespan.start != new_entry.timespan.start {
+                let indent = entry.start.len() - entry.start.trim_start().len();
+                entry.start = format!(
+                    "{}{}",
+                    entry.start.get(..indent).unwrap_or(""),
+                    format_timecode(new_entry.timespan.start, fps)
+                );
+            }
+            if entry.timespan.end != new_entry.timespan.end {
+                entry.end = format_timecode(new_entry.timespan.end, fps);
+            }
+            entry.timespan = new_entry.timespan;
+            if let Some(ref text) = new_entry.line {
+                if decode_text(&entry.raw) != *text {
+                    entry.raw = encode_text(text);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = self.header.clone();
+        for entry in &self.v {
+            result.push_str(&entry.before);
+            for part in [
+                &entry.start,
+                &entry.separators[0],
+                &entry.end,
+                &entry.separators[1],
+                &entry.raw,
+                &entry.newline,
+            ]
+            .iter()
+            {
+                result.push_str(part);
+            }
+        }
+        result.push_str(&self.trailer);
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        // the settings before a removed subtitle still apply to the following subtitles
+        let mut result: Vec<SpruceEntry> = Vec::new();
+        let mut before = String::new();
+        for (mut entry, &keep) in std::mem::take(&mut self.v).into_iter().zip(keep) {
+            if keep {
+                entry.before = std::mem::take(&mut before) + &entry.before;
+                result.push(entry);
+            } else {
+                before.push_str(&entry.before);
+            }
+        }
+        self.trailer = before + &self.trailer;
+        self.v = result;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn spruce_round_trip_and_edit() {
+        let input = "//Font select and font size\r\n$FontName       = Arial\r\n$FontSize       = 30\r\n\r\n00:00:01:12 , 00:00:03:20 , ^IHello^I|world\r\n$Bold = TRUE\r\n00:00:05:00 , 00:00:06:24 , Second\r\n";
+        let (mut file, warnings) = SpruceStlFile::parse_with_warnings(input, 25.0).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), input);
+        assert!(warnings.is_empty());
+
+        let mut entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries[0].line.as_deref(), Some("<i>Hello</i>\nworld"));
+        assert_eq!((entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()), (1480, 3800));
+        assert_eq!(entries[1].timespan.end.msecs(), 6960);
+
+        entries[1].timespan.start += TimeDelta::from_msecs(1000);
+        entries[1].line = Some("<b>Changed</b>\ntext".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "//Font select and font size\r\n$FontName       = Arial\r\n$FontSize       = 30\r\n\r\n$Bold = TRUE\r\n00:00:06:00 , 00:00:06:24 , ^BChanged^B|text\r\n"
+        );
+
+        // 29.97 fps files count 30 frames per second, so frame 25 is invalid at 25 fps
+        let ntsc = "00:00:01:25 , 00:00:02:00 , NTSC\n";
+        assert!(SpruceStlFile::parse(ntsc, 29.97).is_ok());
+        assert!(SpruceStlFile::parse(ntsc, 25.0).is_err());
+        assert!(looks_like_spruce(input) && looks_like_spruce(ntsc));
+        assert!(!looks_like_spruce("850STL25.01\u{1}"));
+    }
+}
@@ -7,7 +7,12 @@ use self::errors::*;
 use crate::{SubtitleEntry, SubtitleFileInterface};
 
 use crate::errors::Result as SubtitleParserResult;
+use crate::escaping::srt_text_lines;
 use crate::formats::common::*;
+use crate::formats::ssa::{AssTemplate, SsaStyle};
+use crate::formats::ssa_color::AssColor;
+use crate::positioning::Placement;
+use crate::warnings::{WarningKind, Warnings};
 use combine::char::{char, string};
 use combine::combinator::{eof, parser as p, skip_many};
 use combine::primitives::Parser;
@@ -16,7 +21,7 @@ use failure::ResultExt;
 
 use itertools::Itertools;
 
-use crate::timetypes::{TimePoint, TimeSpan};
+use crate::timetypes::{RoundingMode, TimePoint, TimeSpan};
 use std::iter::once;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -59,6 +64,382 @@ enum SrtParserState {
 /// Represents a `.srt` file.
 pub struct SrtFile {
     v: Vec<SrtLine>,
+
+    /// What happens to embedded SubStation Alpha override tags when the file is written
+    ass_tag_output: SrtAssTagOutput,
+
+    /// How timestamps are written
+    timestamp_format: SrtTimestampFormat,
+
+    /// How the entries are numbered when the file is written
+    numbering: SrtNumbering,
+}
+
+/// How the entries are numbered by `SrtFile::to_data()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtNumbering {
+    /// Keep the numbers of the parsed file (new entries get the number after the entry before them) (default).
+    #[default]
+    Preserve,
+
+    /// Number the entries consecutively, beginning with the given number.
+    StartAt(i64),
+
+    /// Use the start time in milliseconds as number, so the numbers don't change if entries are added or removed
+    /// (entries with the same start time get the same number).
+    StartTime,
+}
+
+/// How timestamps like `00:24:45,670` are written by `SrtFile::to_data()`.
+///
+/// All components are always zero-padded (`00:00:01,500`, not `0:0:1,5`) and negative times are written as
+/// `00:00:00,000`, no matter how sloppy the parsed file was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SrtTimestampFormat {
+    /// The number of digits after the comma (at most 3; `2` writes `00:24:45,67`, `0` writes `00:24:45`).
+    pub digits: usize,
+
+    /// How milliseconds that don't fit into `digits` are rounded (`RoundingMode::Floor` truncates them).
+    pub rounding: RoundingMode,
+}
+
+impl Default for SrtTimestampFormat {
+    /// Three digits and rounding to the nearest value.
+    fn default() -> SrtTimestampFormat {
+        SrtTimestampFormat {
+            digits: 3,
+            rounding: RoundingMode::Nearest,
+        }
+    }
+}
+
+impl SrtTimestampFormat {
+    /// Formats a `TimePoint` like `00:24:45,670`.
+    pub fn format(&self, t: TimePoint) -> String {
+        let digits = self.digits.min(3);
+        let unit = 10i64.pow(3 - digits as u32);
+        let t = TimePoint::from_msecs(self.rounding.div(t.msecs().max(0), unit) * unit);
+        let time = format!("{:02}:{:02}:{:02}", t.hours(), t.mins_comp(), t.secs_comp());
+        if digits == 0 {
+            time
+        } else {
+            format!("{},{:0width$}", time, t.msecs_comp() / unit, width = digits)
+        }
+    }
+}
+
+/// How SubStation Alpha override tags (like `{\an8}`) in `.srt` texts are written by `SrtFile::to_data()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtAssTagOutput {
+    /// Keep all tags like they are (default).
+    #[default]
+    Keep,
+
+    /// Remove all tags.
+    Strip,
+
+    /// Replace the styling tags by their HTML counterparts (`{\i1}` becomes `<i>`), keep the alignment
+    /// (`{\anN}`, which most players understand) and remove everything else.
+    Convert,
+}
+
+/// The SubStation Alpha override tags embedded into the text of a `.srt` entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SrtAssTags {
+    /// The alignment in numpad notation (`1` is bottom left, `8` is top center) given by `\anN` or legacy `\aN`.
+    pub alignment: Option<u8>,
+
+    /// The position given by `\pos(x,y)`.
+    pub position: Option<(f64, f64)>,
+
+    /// All tags in order of appearance, without the backslash (like `"an8"` or `"i1"`).
+    pub tags: Vec<String>,
+
+    /// The text with all override blocks removed.
+    pub text: String,
+}
+
+impl SrtAssTags {
+    /// Parses all `{\...}` override blocks in a text (other curly braces are treated as text).
+    pub fn parse(text: &str) -> SrtAssTags {
+        let mut result = SrtAssTags::default();
+        for part in split_override_blocks(text) {
+            match part {
+                TextPart::Text(t) => result.text.push_str(t),
+                TextPart::Overrides(block) => {
+                    for tag in block.split('\\').map(str::trim).filter(|t| !t.is_empty()) {
+                        if let Some(alignment) = parse_alignment_tag(tag) {
+                            result.alignment = Some(alignment);
+                        } else if let Some(position) = parse_pos_tag(tag) {
+                            result.position = Some(position);
+                        }
+                        result.tags.push(tag.to_string());
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// A part of a text with SubStation Alpha override blocks.
+enum TextPart<'a> {
+    Text(&'a str),
+
+    /// The content of a `{\...}` block (without the braces)
+    Overrides(&'a str),
+}
+
+fn split_override_blocks(mut text: &str) -> Vec<TextPart<'_>> {
+    let mut result = Vec::new();
+    while let Some(begin) = text.find("{\\") {
+        let end = match text[begin..].find('}') {
+            Some(len) => begin + len,
+            None => break,
+        };
+        if begin > 0 {
+            result.push(TextPart::Text(&text[..begin]));
+        }
+        result.push(TextPart::Overrides(&text[begin + 1..end]));
+        text = &text[end + 1..];
+    }
+    if !text.is_empty() {
+        result.push(TextPart::Text(text));
+    }
+    result
+}
+
+/// Parses `anN` and the legacy `aN` tags into numpad notation.
+fn parse_alignment_tag(tag: &str) -> Option<u8> {
+    if let Some(n) = tag.strip_prefix("an") {
+        return n.trim().parse().ok().filter(|n| (1..=9).contains(n));
+    }
+    let legacy = tag.strip_prefix('a')?.trim().parse::<u8>().ok()?;
+    Placement::from_legacy_alignment(legacy).map(|placement| placement.to_an())
+}
+
+fn parse_pos_tag(tag: &str) -> Option<(f64, f64)> {
+    let args = tag.strip_prefix("pos(")?.trim_end().strip_suffix(')')?;
+    let mut iter = args.split(',').map(|a| a.trim().parse::<f64>());
+    match (iter.next(), iter.next(), iter.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// Rewrites the override blocks of a text line according to `mode`.
+fn convert_ass_tags(text: &str, mode: SrtAssTagOutput) -> String {
+    if mode == SrtAssTagOutput::Keep {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    for part in split_override_blocks(text) {
+        match part {
+            TextPart::Text(t) => result.push_str(t),
+            TextPart::Overrides(_) if mode == SrtAssTagOutput::Strip => {}
+            TextPart::Overrides(block) => {
+                for tag in block.split('\\').map(str::trim).filter(|t| !t.is_empty()) {
+                    if let Some(alignment) = parse_alignment_tag(tag) {
+                        result.push_str(&format!("{{\\an{}}}", alignment));
+                        continue;
+                    }
+                    let html = match tag {
+                        "i1" => "<i>",
+                        "i0" => "</i>",
+                        "u1" => "<u>",
+                        "u0" => "</u>",
+                        "s1" => "<s>",
+                        "s0" => "</s>",
+                        "b0" => "</b>",
+                        // `\b1` or font weights like `\b700`
+                        t if t.starts_with('b') && t[1..].parse::<u32>().is_ok() => "<b>",
+                        _ => "",
+                    };
+                    result.push_str(html);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// How `SrtFile::to_ass_template()` converts `<i>`, `<b>`, `<u>` and `<s>` tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtStyleMapping {
+    /// Write them as override tags (`<i>` becomes `{\i1}`, default).
+    #[default]
+    Overrides,
+
+    /// Entries which are completely italic (bold, ...) use a generated style for every combination (like
+    /// `Default Bold Italic`), tags which only apply to a part of an entry are written as override tags.
+    Styles,
+}
+
+/// How `SrtFile::to_ass_template()` converts alignment tags like `{\an8}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SrtAlignmentMapping {
+    /// Keep them as override tags (default).
+    #[default]
+    Keep,
+
+    /// Remove them, so every entry uses the alignment of its style.
+    Strip,
+
+    /// Remove them and use a generated style for every alignment (like `Default an8`).
+    Styles,
+}
+
+/// Options for the conversion of a `.srt` file into a `.ass` script (see `SrtFile::to_ass_template()`).
+///
+/// `<font color="#rrggbb">` tags are always written as color overrides, other font attributes are removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrtToAssOptions {
+    /// The `PlayResX` and `PlayResY` of the script.
+    pub play_res: (u32, u32),
+
+    /// The style of all entries (generated styles are copies of it).
+    pub style: SsaStyle,
+
+    /// How styling tags are converted.
+    pub tags: SrtStyleMapping,
+
+    /// How alignment tags are converted.
+    pub alignment: SrtAlignmentMapping,
+}
+
+impl SrtToAssOptions {
+    /// Options for a script with the resolution `play_res` and the `Default` style of Aegisub, which is meant for
+    /// 288 lines, scaled to it.
+    pub fn new(play_res: (u32, u32)) -> SrtToAssOptions {
+        let scale = f64::from(play_res.1.max(1)) / 288.0;
+        let default = SsaStyle::default();
+        let margin = |m: i32| (f64::from(m) * scale).round() as i32;
+        SrtToAssOptions {
+            play_res,
+            style: SsaStyle {
+                fontsize: (default.fontsize * scale).round(),
+                outline: (default.outline * scale * 10.0).round() / 10.0,
+                shadow: (default.shadow * scale * 10.0).round() / 10.0,
+                margin_l: margin(default.margin_l),
+                margin_r: margin(default.margin_r),
+                margin_v: margin(default.margin_v),
+                ..default
+            },
+            tags: SrtStyleMapping::default(),
+            alignment: SrtAlignmentMapping::default(),
+        }
+    }
+}
+
+impl Default for SrtToAssOptions {
+    /// Options for a 1920x1080 script.
+    fn default() -> SrtToAssOptions {
+        SrtToAssOptions::new((1920, 1080))
+    }
+}
+
+/// The html-like styling tags of `.srt` which have an override tag.
+const SRT_STYLE_TAGS: [&str; 4] = ["b", "i", "u", "s"];
+
+/// A part of a `.srt` text for the conversion to `.ass`.
+enum SrtMarkup<'a> {
+    Text(&'a str),
+
+    /// The content of a `{\...}` block (without the braces)
+    Overrides(&'a str),
+
+    /// A `<b>`, `<i>`, `<u>` or `<s>` tag (the index in `SRT_STYLE_TAGS`)
+    Style {
+        tag: usize,
+        closing: bool,
+    },
+
+    /// A `<font>` tag with its color, or `</font>`
+    Font {
+        color: Option<AssColor>,
+        closing: bool,
+    },
+}
+
+/// Parses an html-like tag like `<i>`, `</b>` or `<font color="#ff0000">` at the start of the text.
+fn parse_srt_tag(text: &str) -> Option<(SrtMarkup<'_>, &str)> {
+    let (tag, rest) = text.strip_prefix('<')?.split_once('>')?;
+    let (closing, tag) = match tag.strip_prefix('/') {
+        Some(tag) => (true, tag),
+        None => (false, tag),
+    };
+    let name = tag.split_whitespace().next()?.to_lowercase();
+    if let Some(idx) = SRT_STYLE_TAGS.iter().position(|&t| t == name) {
+        return Some((SrtMarkup::Style { tag: idx, closing }, rest));
+    }
+    if name != "font" {
+        return None;
+    }
+    let color = tag
+        .split_once("color=")
+        .and_then(|(_, value)| value.trim_start_matches(['"', '\'']).split(['"', '\'', ' ']).next())
+        .and_then(AssColor::from_css_hex);
+    Some((SrtMarkup::Font { color, closing }, rest))
+}
+
+fn split_srt_markup(text: &str) -> Vec<SrtMarkup<'_>> {
+    let mut result = Vec::new();
+    for part in split_override_blocks(text) {
+        let mut text = match part {
+            TextPart::Overrides(block) => {
+                result.push(SrtMarkup::Overrides(block));
+                continue;
+            }
+            TextPart::Text(text) => text,
+        };
+        let mut search_from = 0;
+        while let Some(begin) = text.get(search_from..).and_then(|t| t.find('<')).map(|pos| search_from + pos) {
+            match parse_srt_tag(&text[begin..]) {
+                Some((markup, rest)) => {
+                    if begin > 0 {
+                        result.push(SrtMarkup::Text(&text[..begin]));
+                    }
+                    result.push(markup);
+                    text = rest;
+                    search_from = 0;
+                }
+                None => search_from = begin + 1,
+            }
+        }
+        if !text.is_empty() {
+            result.push(SrtMarkup::Text(text));
+        }
+    }
+    result
+}
+
+/// Returns which of the `SRT_STYLE_TAGS` apply to all visible characters of the text.
+fn whole_text_styles(markup: &[SrtMarkup<'_>]) -> [bool; 4] {
+    let mut active = [0usize; 4];
+    let mut whole = [true; 4];
+    let mut has_text = false;
+    for part in markup {
+        match *part {
+            SrtMarkup::Style { tag, closing } => {
+                if let Some(count) = active.get_mut(tag) {
+                    *count = if closing { count.saturating_sub(1) } else { *count + 1 };
+                }
+            }
+            SrtMarkup::Text(text) if !text.trim().is_empty() => {
+                has_text = true;
+                for (whole, &count) in whole.iter_mut().zip(&active) {
+                    *whole &= count > 0;
+                }
+            }
+            _ => {}
+        }
+    }
+    if has_text {
+        whole
+    } else {
+        [false; 4]
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -77,16 +458,25 @@ struct SrtLine {
 impl SrtFile {
     /// Parse a `.srt` subtitle string to `SrtFile`.
     pub fn parse(s: &str) -> SubtitleParserResult<SrtFile> {
-        Ok(Self::parse_file(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.srt` subtitle string to `SrtFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(SrtFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
     }
 }
 
 /// Implements parse functions.
 impl SrtFile {
-    fn parse_file(i: &str) -> Result<SrtFile> {
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<SrtFile> {
         use self::SrtParserState::*;
 
         let mut result: Vec<SrtLine> = Vec::new();
+        let mut last_index: Option<i64> = None;
 
         // remove utf-8 bom
         let (_, s) = split_bom(i);
@@ -100,7 +490,14 @@ impl SrtFile {
                     if line.trim().is_empty() {
                         Emptyline
                     } else {
-                        Index(Self::parse_index_line(line_num, line)?)
+                        let index = Self::parse_index_line(line_num, line)?;
+                        if let Some(expected) = last_index.map(|i| i.saturating_add(1)) {
+                            if index != expected {
+                                warnings.push(Some(line_num), WarningKind::UnexpectedIndex { expected, found: index });
+                            }
+                        }
+                        last_index = Some(index);
+                        Index(index)
                     }
                 }
                 Index(index) => Timing(index, Self::parse_timespan_line(line_num, line)?),
@@ -109,16 +506,17 @@ impl SrtFile {
             };
         }
 
-        Ok(SrtFile { v: result })
+        Ok(SrtFile {
+            v: result,
+            ass_tag_output: SrtAssTagOutput::default(),
+            timestamp_format: SrtTimestampFormat::default(),
+            numbering: SrtNumbering::default(),
+        })
     }
 
     fn state_expect_dialog(line: &str, result: &mut Vec<SrtLine>, index: i64, timespan: TimeSpan, mut texts: Vec<String>) -> SrtParserState {
         if line.trim().is_empty() {
-            result.push(SrtLine {
-                index: index,
-                timespan: timespan,
-                texts: texts,
-            });
+            result.push(SrtLine { index, timespan, texts });
             SrtParserState::Emptyline
         } else {
             texts.push(line.trim().to_string());
@@ -147,11 +545,11 @@ impl SrtFile {
                 char(','),
                 p(number_i64),
             )
-                .map(|t| TimePoint::from_components(t.0, t.2, t.4, t.6))
+                .map(|t| TimePoint::try_from_components(t.0, t.2, t.4, t.6))
                 .parse_stream(s)
         };
 
-        let result = (
+        let (start, end) = (
             skip_many(ws()),
             p(&timestamp),
             skip_many(ws()),
@@ -161,13 +559,14 @@ impl SrtFile {
             skip_many(ws()),
             eof(),
         )
-            .map(|t| TimeSpan::new(t.1, t.5))
+            .map(|t| (t.1, t.5))
             .parse(line)
             .map(|x| x.0)
             .map_err(|_| Error::from(ExpectedTimestampLine { line: line.to_string() }))
             .with_context(|_| ErrorAtLine { line_num })?;
 
-        Ok(result)
+        let timespan = start.and_then(|start| Ok(TimeSpan::new(start, end?)));
+        Ok(timespan.with_context(|_| ErrorAtLine { line_num })?)
     }
 }
 
@@ -183,12 +582,12 @@ impl SubtitleFileInterface for SrtFile {
     }
 
     fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
-        assert_eq!(self.v.len(), new_subtitle_entries.len()); // required by specification of this function
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
 
         for (line_ref, new_entry_ref) in self.v.iter_mut().zip(new_subtitle_entries) {
             line_ref.timespan = new_entry_ref.timespan;
             if let Some(ref text) = new_entry_ref.line {
-                line_ref.texts = text.lines().map(str::to_string).collect();
+                line_ref.texts = srt_text_lines(text);
             }
         }
 
@@ -196,23 +595,166 @@ impl SubtitleFileInterface for SrtFile {
     }
 
     fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
-        let timepoint_to_str =
-            |t: TimePoint| -> String { format!("{:02}:{:02}:{:02},{:03}", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp()) };
-        let line_to_str = |line: &SrtLine| -> String {
+        let timepoint_to_str = |t: TimePoint| -> String { self.timestamp_format.format(t) };
+        let line_to_str = |(i, line): (usize, &SrtLine)| -> String {
+            let index = match self.numbering {
+                SrtNumbering::Preserve => line.index,
+                SrtNumbering::StartAt(start) => start.saturating_add(i as i64),
+                SrtNumbering::StartTime => line.timespan.start.msecs(),
+            };
             format!(
                 "{}\n{} --> {}\n{}\n\n",
-                line.index,
+                index,
                 timepoint_to_str(line.timespan.start),
                 timepoint_to_str(line.timespan.end),
-                line.texts.join("\n")
+                line.texts.iter().map(|text| convert_ass_tags(text, self.ass_tag_output)).join("\n")
             )
         };
 
-        Ok(self.v.iter().map(line_to_str).collect::<String>().into_bytes())
+        Ok(self.v.iter().enumerate().map(line_to_str).collect::<String>().into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
     }
 }
 
 impl SrtFile {
+    /// Returns the SubStation Alpha override tags (like `{\an8}`) of every entry, in the order of `get_subtitle_entries()`.
+    pub fn get_ass_tags(&self) -> Vec<SrtAssTags> {
+        self.v.iter().map(|line| SrtAssTags::parse(&line.texts.join("\n"))).collect()
+    }
+
+    /// Converts the file into a `.ass` script (see `SrtToAssOptions` for how the tags are converted).
+    ///
+    /// ```
+    /// use subparse::{SrtFile, SrtStyleMapping, SrtToAssOptions};
+    ///
+    /// let file = SrtFile::parse("1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i>\n").unwrap();
+    /// let options = SrtToAssOptions { tags: SrtStyleMapping::Styles, ..SrtToAssOptions::new((1280, 720)) };
+    /// let template = file.to_ass_template(&options);
+    /// assert_eq!(template.events[0].style, "Default Italic");
+    /// assert_eq!(template.events[0].text, "Hello");
+    /// ```
+    pub fn to_ass_template(&self, options: &SrtToAssOptions) -> AssTemplate {
+        let mut template = AssTemplate::new(vec![options.style.clone()]);
+        template.play_res = options.play_res;
+
+        for line in &self.v {
+            let text = line.texts.join("\n");
+            let markup = split_srt_markup(&text);
+            let mut styled = [false; 4];
+            if options.tags == SrtStyleMapping::Styles {
+                styled = whole_text_styles(&markup);
+            }
+            let mut alignment = None;
+
+            let mut result = String::new();
+            for part in markup {
+                match part {
+                    SrtMarkup::Text(t) => result.push_str(t),
+                    SrtMarkup::Style { tag, closing } => {
+                        if !styled.get(tag).copied().unwrap_or(false) {
+                            let name = SRT_STYLE_TAGS.get(tag).unwrap_or(&"");
+                            result.push_str(&format!("{{\\{}{}}}", name, if closing { 0 } else { 1 }));
+                        }
+                    }
+                    SrtMarkup::Font { closing: true, .. } => result.push_str("{\\c}"),
+                    SrtMarkup::Font { color, closing: false } => {
+                        if let Some(color) = color {
+                            result.push_str(&format!("{{\\c{}}}", color.to_override_string()));
+                        }
+                    }
+                    SrtMarkup::Overrides(block) => {
+                        let mut tags = String::new();
+                        for tag in block.split('\\').map(str::trim).filter(|t| !t.is_empty()) {
+                            match parse_alignment_tag(tag) {
+                                Some(an) if options.alignment == SrtAlignmentMapping::Keep => tags.push_str(&format!("\\an{}", an)),
+                                Some(an) => alignment = Some(an),
+                                None => {
+                                    tags.push('\\');
+                                    tags.push_str(tag);
+                                }
+                            }
+                        }
+                        if !tags.is_empty() {
+                            result.push_str(&format!("{{{}}}", tags));
+                        }
+                    }
+                }
+            }
+
+            let mut style = options.style.clone();
+            let mut name = style.name.clone();
+            let mut flags = [&mut style.bold, &mut style.italic, &mut style.underline, &mut style.strike_out];
+            for ((flag, suffix), &styled) in flags.iter_mut().zip([" Bold", " Italic", " Underline", " StrikeOut"]).zip(&styled) {
+                if styled {
+                    **flag = true;
+                    name.push_str(suffix);
+                }
+            }
+            if let Some(an) = alignment.filter(|&an| options.alignment == SrtAlignmentMapping::Styles && i32::from(an) != style.alignment) {
+                style.alignment = i32::from(an);
+                name.push_str(&format!(" an{}", an));
+            }
+            if !template.styles.iter().any(|s| s.name == name) {
+                style.name = name.clone();
+                template.styles.push(style);
+            }
+
+            template.add_event(line.timespan, &result, &name, None);
+        }
+        template
+    }
+
+    /// Sets what happens to SubStation Alpha override tags when the file is written (default: `SrtAssTagOutput::Keep`).
+    pub fn set_ass_tag_output(&mut self, mode: SrtAssTagOutput) {
+        self.ass_tag_output = mode;
+    }
+
+    /// Sets how timestamps are written (default: three digits, rounded to the nearest value).
+    pub fn set_timestamp_format(&mut self, format: SrtTimestampFormat) {
+        self.timestamp_format = format;
+    }
+
+    /// Sets how the entries are numbered when the file is written (default: `SrtNumbering::Preserve`).
+    pub fn set_numbering(&mut self, numbering: SrtNumbering) {
+        self.numbering = numbering;
+    }
+
+    /// Returns the file like `to_data()`, but numbered from 1 and with `\n` line endings, so files with
+    /// the same entries are always written with the same bytes.
+    pub fn to_deterministic_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut file = self.clone();
+        file.numbering = SrtNumbering::Preserve;
+        for (i, line) in file.v.iter_mut().enumerate() {
+            line.index = i as i64 + 1;
+        }
+        Ok(normalize_line_endings(&String::from_utf8_lossy(&file.to_data()?)).into_bytes())
+    }
+
+    /// Inserts a new entry at position `idx` (or at the end).
+    ///
+    /// The other entries keep their numbers; the new entry gets the number after the entry before it (or the
+    /// number before the first entry).
+    pub(crate) fn insert_entry(&mut self, idx: usize, timespan: TimeSpan, text: &str) {
+        let idx = idx.min(self.v.len());
+        let index = match idx.checked_sub(1).and_then(|prev| self.v.get(prev)) {
+            Some(prev) => prev.index.saturating_add(1),
+            None => self.v.first().map_or(1, |next| next.index.saturating_sub(1).max(1)),
+        };
+        let line = SrtLine {
+            index,
+            timespan,
+            texts: srt_text_lines(text),
+        };
+        self.v.insert(idx, line);
+    }
+
     /// Creates .srt file from scratch.
     pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<SrtFile> {
         let file_parts = v
@@ -221,11 +763,16 @@ impl SrtFile {
             .map(|(i, (ts, text))| SrtLine {
                 index: i as i64 + 1,
                 timespan: ts,
-                texts: text.lines().map(str::to_string).collect(),
+                texts: srt_text_lines(&text),
             })
             .collect();
 
-        Ok(SrtFile { v: file_parts })
+        Ok(SrtFile {
+            v: file_parts,
+            ass_tag_output: SrtAssTagOutput::default(),
+            timestamp_format: SrtTimestampFormat::default(),
+            numbering: SrtNumbering::default(),
+        })
     }
 }
 
@@ -254,5 +801,120 @@ mod tests {
         println!("\n{:?}\n{:?}", data_string, expected);
         assert_eq!(data_string, expected);
     }
+
+    #[test]
+    fn srt_ass_tags() {
+        use super::{SrtAssTagOutput, SrtFile};
+        use crate::SubtitleFileInterface;
+
+        let mut file = SrtFile::parse("1\n00:00:01,000 --> 00:00:02,000\n{\\an8}{\\i1}Top{\\i0} {not a tag}\n").unwrap();
+        let tags = file.get_ass_tags();
+        assert_eq!(tags[0].alignment, Some(8));
+        assert_eq!(tags[0].tags, vec!["an8", "i1", "i0"]);
+        assert_eq!(tags[0].text, "Top {not a tag}");
+
+        let output = |file: &SrtFile| String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(output(&file).contains("\n{\\an8}{\\i1}Top{\\i0} {not a tag}\n"));
+        file.set_ass_tag_output(SrtAssTagOutput::Convert);
+        assert!(output(&file).contains("\n{\\an8}<i>Top</i> {not a tag}\n"));
+        file.set_ass_tag_output(SrtAssTagOutput::Strip);
+        assert!(output(&file).contains("\nTop {not a tag}\n"));
+    }
+
+    #[test]
+    fn srt_to_ass_options() {
+        use super::{SrtAlignmentMapping, SrtFile, SrtStyleMapping, SrtToAssOptions};
+
+        let input = "1\n00:00:01,000 --> 00:00:02,000\n{\\an8}<i>Top</i>\n<i>line</i>\n\n\
+                     2\n00:00:03,000 --> 00:00:04,000\n<b>Bold</b> and <font color=\"#ff0000\">red</font> <ruby>\n";
+        let file = SrtFile::parse(input).unwrap();
+
+        let template = file.to_ass_template(&SrtToAssOptions::default());
+        assert_eq!(template.play_res, (1920, 1080));
+        assert_eq!(
+            (template.styles.len(), template.styles[0].fontsize, template.styles[0].margin_v),
+            (1, 75.0, 38)
+        );
+        assert_eq!(template.events[0].text, "{\\an8}{\\i1}Top{\\i0}\n{\\i1}line{\\i0}");
+        assert_eq!(template.events[1].text, "{\\b1}Bold{\\b0} and {\\c&H0000FF&}red{\\c} <ruby>");
+
+        let options = SrtToAssOptions {
+            tags: SrtStyleMapping::Styles,
+            alignment: SrtAlignmentMapping::Styles,
+            ..SrtToAssOptions::new((1280, 720))
+        };
+        let template = file.to_ass_template(&options);
+        let names: Vec<&str> = template.styles.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Default", "Default Italic an8"]);
+        assert_eq!((template.styles[1].italic, template.styles[1].alignment), (true, 8));
+        assert_eq!(template.events[0].text, "Top\nline");
+        assert_eq!(template.events[1].style, "Default");
+        assert!(template.to_ssa_file().is_ok());
+
+        let options = SrtToAssOptions {
+            alignment: SrtAlignmentMapping::Strip,
+            ..SrtToAssOptions::default()
+        };
+        assert_eq!(file.to_ass_template(&options).events[0].text, "{\\i1}Top{\\i0}\n{\\i1}line{\\i0}");
+    }
+
+    #[test]
+    fn srt_numbering() {
+        use super::{SrtFile, SrtNumbering};
+        use crate::SubtitleFileInterface;
+
+        let mut file = SrtFile::parse("7\n00:00:01,000 --> 00:00:02,000\na\n\n9\n00:00:03,500 --> 00:00:04,000\nb\n").unwrap();
+        let numbers = |file: &SrtFile| -> Vec<String> {
+            let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+            data.split("\n\n").filter_map(|block| block.lines().next()).map(str::to_string).collect()
+        };
+        assert_eq!(numbers(&file), vec!["7", "9"]);
+        file.set_numbering(SrtNumbering::StartAt(100));
+        assert_eq!(numbers(&file), vec!["100", "101"]);
+        file.set_numbering(SrtNumbering::StartTime);
+        assert_eq!(numbers(&file), vec!["1000", "3500"]);
+    }
+
+    #[test]
+    fn srt_insert_keeps_numbers() {
+        use super::{SrtFile, SrtNumbering};
+        use crate::timetypes::{TimePoint, TimeSpan};
+        use crate::SubtitleFileInterface;
+
+        let mut file = SrtFile::parse("7\n00:00:01,000 --> 00:00:02,000\na\n\n20\n00:00:03,500 --> 00:00:04,000\nb\n").unwrap();
+        let span = |start: i64, end: i64| TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end));
+        file.insert_entry(1, span(2000, 3000), "between");
+        file.insert_entry(0, span(0, 500), "first");
+        file.insert_entry(9, span(5000, 6000), "last");
+        let numbers = |file: &SrtFile| -> Vec<String> {
+            let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+            data.split("\n\n").filter_map(|block| block.lines().next()).map(str::to_string).collect()
+        };
+        assert_eq!(numbers(&file), vec!["6", "7", "8", "20", "21"]);
+        file.set_numbering(SrtNumbering::StartAt(1));
+        assert_eq!(numbers(&file), vec!["1", "2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn srt_timestamp_format() {
+        use super::{SrtFile, SrtTimestampFormat};
+        use crate::timetypes::RoundingMode;
+        use crate::SubtitleFileInterface;
+
+        let mut file = SrtFile::parse("1\n0:0:1,5 --> 0:59:59,996\nText\n").unwrap();
+        let timestamps = |file: &SrtFile| String::from_utf8(file.to_data().unwrap()).unwrap().lines().nth(1).unwrap().to_string();
+        assert_eq!(timestamps(&file), "00:00:01,005 --> 00:59:59,996");
+
+        file.set_timestamp_format(SrtTimestampFormat {
+            digits: 2,
+            rounding: RoundingMode::Nearest,
+        });
+        assert_eq!(timestamps(&file), "00:00:01,01 --> 01:00:00,00");
+        file.set_timestamp_format(SrtTimestampFormat {
+            digits: 2,
+            rounding: RoundingMode::Floor,
+        });
+        assert_eq!(timestamps(&file), "00:00:01,00 --> 00:59:59,99");
+    }
 }
 // TODO: parser tests
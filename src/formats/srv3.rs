@@ -0,0 +1,623 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use super::ttml::{special_markup_len, StartTag};
+use crate::warnings::{WarningKind, Warnings};
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+
+/// Errors specific to srv3-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the XML is malformed at byte {}: {}", position, msg)]
+        MalformedXml { position: usize, msg: String },
+
+        #[fail(display = "the time `{}` is not a number of milliseconds", value)]
+        InvalidTime { value: String },
+
+        #[fail(display = "the file has no `<timedtext>` element")]
+        ExpectedTimedText,
+    }
+}
+
+/// The styles of a pen which are converted to and from `<i>`, `<b>` and `<u>` tags in the entry texts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct PenStyle {
+    italic: bool,
+    bold: bool,
+    underline: bool,
+}
+
+impl PenStyle {
+    /// The tags of the styles, in the order in which they are opened.
+    fn tags(self) -> Vec<&'static str> {
+        [(self.italic, "i"), (self.bold, "b"), (self.underline, "u")]
+            .iter()
+            .filter(|(active, _)| *active)
+            .map(|&(_, tag)| tag)
+            .collect()
+    }
+
+    fn attributes(self) -> String {
+        [(self.italic, " i=\"1\""), (self.bold, " b=\"1\""), (self.underline, " u=\"1\"")]
+            .iter()
+            .filter(|(active, _)| *active)
+            .map(|&(_, attribute)| attribute)
+            .collect()
+    }
+
+    fn union(self, other: PenStyle) -> PenStyle {
+        PenStyle {
+            italic: self.italic || other.italic,
+            bold: self.bold || other.bold,
+            underline: self.underline || other.underline,
+        }
+    }
+}
+
+/// A part of the start tag of a `<p>` element.
+#[derive(Debug, Clone)]
+enum TagPiece {
+    /// Everything apart from the timing attribute values.
+    Text(String),
+
+    /// The value of the `t` attribute.
+    Start,
+
+    /// The value of the `d` attribute.
+    Duration,
+}
+
+/// A `<p>` element with text (one subtitle).
+#[derive(Debug, Clone)]
+struct Srv3Paragraph {
+    /// The start tag split at the values of the timing attributes.
+    tag: Vec<TagPiece>,
+
+    timespan: TimeSpan,
+
+    /// The pen of the `p` attribute.
+    pen: Option<String>,
+
+    /// The XML content between the start and the end tag.
+    content: String,
+}
+
+#[derive(Debug, Clone)]
+enum Srv3FilePart {
+    /// The XML declaration, the head with pens and positions, paragraphs without text, ...
+    Filler(String),
+
+    /// A timed `<p>` element without its end tag.
+    Paragraph(Srv3Paragraph),
+}
+
+/// Represents a reconstructable YouTube srv3 file (`.srv3`, `.ytt`), the XML caption format of YouTube.
+///
+/// Every `<p t=".." d="..">` with text is an entry, with the start and the duration in milliseconds. The styles of
+/// the pens (`<pen id=".." b="1" i="1" u="1"/>` in the head, used by the `p` attribute of `<p>` and `<s>` elements)
+/// become `<i>`, `<b>` and `<u>` tags in the entry texts. The word timings of automatic captions (`<s t="..">`) and
+/// other markup are only kept if the text of the entry is not changed; changed texts use the pens of the file with
+/// exactly the styles of the text. Paragraphs without text (which automatic captions use for line breaks) are kept
+/// as they are.
+#[derive(Debug, Clone)]
+pub struct Srv3File {
+    v: Vec<Srv3FilePart>,
+
+    /// The ids and styles of the pens in the head.
+    pens: Vec<(String, PenStyle)>,
+}
+
+impl Srv3File {
+    /// Parse a srv3 subtitle string to `Srv3File`.
+    pub fn parse(s: &str) -> SubtitleParserResult<Srv3File> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a srv3 subtitle string to `Srv3File` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(Srv3File, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_inner(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a srv3 file with a pen for every combination of styles which is used by the texts.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<Srv3File> {
+        let mut styles: Vec<PenStyle> = Vec::new();
+        for (_, text) in &v {
+            for (style, _) in styled_segments(text) {
+                if style != PenStyle::default() && !styles.contains(&style) {
+                    styles.push(style);
+                }
+            }
+        }
+        let pens: Vec<(String, PenStyle)> = styles.iter().enumerate().map(|(i, &style)| ((i + 1).to_string(), style)).collect();
+
+        let mut result = String::from("<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n<timedtext format=\"3\">\n<head>\n");
+        for (id, style) in &pens {
+            result.push_str(&format!("<pen id=\"{}\"{}/>\n", id, style.attributes()));
+        }
+        result.push_str("</head>\n<body>\n");
+        for (timespan, text) in &v {
+            result.push_str(&format!(
+                "<p t=\"{}\" d=\"{}\">{}</p>\n",
+                format_time(timespan.start),
+                timespan.len().msecs().max(0),
+                encode_content(text, &pens)
+            ));
+        }
+        result.push_str("</body>\n</timedtext>\n");
+        Self::parse(&result)
+    }
+
+    fn paragraphs(&self) -> impl Iterator<Item = &Srv3Paragraph> {
+        self.v.iter().filter_map(|part| match *part {
+            Srv3FilePart::Paragraph(ref paragraph) => Some(paragraph),
+            Srv3FilePart::Filler(_) => None,
+        })
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                Srv3FilePart::Filler(ref text) => Some(text.as_str()),
+                Srv3FilePart::Paragraph(_) => None,
+            })
+            .collect()
+    }
+
+    fn parse_inner(s: &str, warnings: &mut Warnings) -> Result<Srv3File> {
+        let malformed = |position: usize, msg: &str| {
+            Error::from(MalformedXml {
+                position,
+                msg: msg.to_string(),
+            })
+        };
+        let mut result = Vec::new();
+        let mut pens = Vec::new();
+        let mut has_timedtext = false;
+        let mut filler_start = 0;
+        let mut pos = 0;
+        while let Some(offset) = s.get(pos..).and_then(|rest| rest.find('<')) {
+            pos += offset;
+            let rest = s.get(pos..).unwrap_or("");
+
+            if let Some(len) = special_markup_len(rest, pos).map_err(|_| malformed(pos, "unclosed markup"))? {
+                pos += len;
+                continue;
+            }
+            if rest.starts_with("</") {
+                pos += rest.find('>').ok_or_else(|| malformed(pos, "unclosed end tag"))? + 1;
+                continue;
+            }
+
+            let (tag, tag_len) = StartTag::parse(rest, pos).map_err(|_| malformed(pos, "invalid start tag"))?;
+            let tag_str = rest.get(..tag_len).unwrap_or("");
+            match tag.local_name() {
+                "timedtext" => has_timedtext = true,
+                "pen" => {
+                    let flag = |name: &str| tag.attribute(tag_str, name) == Some("1");
+                    let style = PenStyle {
+                        italic: flag("i"),
+                        bold: flag("b"),
+                        underline: flag("u"),
+                    };
+                    pens.push((tag.attribute(tag_str, "id").unwrap_or("").to_string(), style));
+                }
+                "p" if !tag.self_closing => {
+                    let content_start = pos + tag_len;
+                    let content_len = s
+                        .get(content_start..)
+                        .and_then(|content| content.find("</p"))
+                        .ok_or_else(|| malformed(pos, "unclosed paragraph"))?;
+                    let content = s.get(content_start..content_start + content_len).unwrap_or("");
+                    let pen = tag.attribute(tag_str, "p").map(str::to_string);
+
+                    if !decode_content(content, &pens, pen.as_deref()).is_empty() {
+                        match Self::parse_paragraph(&tag, tag_str)? {
+                            Some((pieces, timespan)) => {
+                                result.push(Srv3FilePart::Filler(s.get(filler_start..pos).unwrap_or("").to_string()));
+                                result.push(Srv3FilePart::Paragraph(Srv3Paragraph {
+                                    tag: pieces,
+                                    timespan,
+                                    pen,
+                                    content: content.to_string(),
+                                }));
+                                filler_start = content_start + content_len;
+                            }
+                            None => warnings.push(None, WarningKind::IgnoredLine { line: tag_str.to_string() }),
+                        }
+                    }
+                    pos = content_start + content_len;
+                    continue;
+                }
+                _ => {}
+            }
+            pos += tag_len;
+        }
+
+        if !has_timedtext {
+            return Err(ExpectedTimedText.into());
+        }
+        result.push(Srv3FilePart::Filler(s.get(filler_start..).unwrap_or("").to_string()));
+        Ok(Srv3File { v: result, pens })
+    }
+
+    /// Returns the tag split at the timing values and the timespan, if the paragraph has a `t` and a `d` attribute.
+    fn parse_paragraph(tag: &StartTag<'_>, tag_str: &str) -> Result<Option<(Vec<TagPiece>, TimeSpan)>> {
+        let time = |name: &str| tag.attribute(tag_str, name).map(parse_time).transpose();
+        let (start, duration) = match (time("t")?, time("d")?) {
+            (Some(start), Some(duration)) => (start, duration),
+            _ => return Ok(None),
+        };
+
+        let mut timing_values: Vec<(std::ops::Range<usize>, TagPiece)> = tag
+            .attributes
+            .iter()
+            .filter_map(|(attribute, range)| match *attribute {
+                "t" => Some((range.clone(), TagPiece::Start)),
+                "d" => Some((range.clone(), TagPiece::Duration)),
+                _ => None,
+            })
+            .collect();
+        timing_values.sort_by_key(|(range, _)| range.start);
+
+        let mut pieces = Vec::new();
+        let mut last_end = 0;
+        for (range, piece) in timing_values {
+            pieces.push(TagPiece::Text(tag_str.get(last_end..range.start).unwrap_or("").to_string()));
+            pieces.push(piece);
+            last_end = range.end;
+        }
+        pieces.push(TagPiece::Text(tag_str.get(last_end..).unwrap_or("").to_string()));
+
+        let start = TimePoint::from_msecs(start);
+        Ok(Some((
+            pieces,
+            TimeSpan::new(start, start + crate::timetypes::TimeDelta::from_msecs(duration)),
+        )))
+    }
+}
+
+/// Parses a time in milliseconds like `1500`.
+fn parse_time(value: &str) -> Result<i64> {
+    match value.trim() {
+        digits if !digits.is_empty() && digits.len() <= 15 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            digits.parse().map_err(|_| InvalidTime { value: value.to_string() }.into())
+        }
+        _ => Err(InvalidTime { value: value.to_string() }.into()),
+    }
+}
+
+/// Formats a time in milliseconds (negative times are written as zero).
+fn format_time(t: TimePoint) -> i64 {
+    t.msecs().max(0)
+}
+
+/// Returns the style of a pen (the default style for unknown pens).
+fn pen_style(pens: &[(String, PenStyle)], id: Option<&str>) -> PenStyle {
+    id.and_then(|id| pens.iter().find(|(pen_id, _)| pen_id == id))
+        .map(|&(_, style)| style)
+        .unwrap_or_default()
+}
+
+/// Converts the XML content of a paragraph into the entry text (see `Srv3File`).
+fn decode_content(content: &str, pens: &[(String, PenStyle)], paragraph_pen: Option<&str>) -> String {
+    let paragraph_style = pen_style(pens, paragraph_pen);
+    let mut segments: Vec<(PenStyle, String)> = Vec::new();
+    let mut style = paragraph_style;
+    let mut rest = content;
+    while !rest.is_empty() {
+        let idx = rest.find('<').unwrap_or(rest.len());
+        let (text, tag_start) = rest.split_at(idx);
+        match segments.last_mut() {
+            Some((last_style, last_text)) if *last_style == style => last_text.push_str(&decode_entities(text)),
+            _ => segments.push((style, decode_entities(text))),
+        }
+        rest = tag_start;
+        if rest.is_empty() {
+            break;
+        }
+
+        if let Ok(Some(len)) = special_markup_len(rest, 0) {
+            rest = rest.get(len..).unwrap_or("");
+            continue;
+        }
+        let tag_len = rest.find('>').map_or(rest.len(), |idx| idx + 1);
+        let tag_str = rest.get(..tag_len).unwrap_or("");
+        rest = rest.get(tag_len..).unwrap_or("");
+        if tag_str.starts_with("</") {
+            style = paragraph_style;
+        } else if let Ok((tag, _)) = StartTag::parse(tag_str, 0) {
+            match tag.local_name() {
+                "s" if !tag.self_closing => style = paragraph_style.union(pen_style(pens, tag.attribute(tag_str, "p"))),
+                "br" => segments.push((style, "\n".to_string())),
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    for (style, segment) in segments.iter().filter(|(_, segment)| !segment.is_empty()) {
+        let tags = style.tags();
+        for tag in &tags {
+            text.push_str(&format!("<{}>", tag));
+        }
+        text.push_str(segment);
+        for tag in tags.iter().rev() {
+            text.push_str(&format!("</{}>", tag));
+        }
+    }
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Splits an entry text at its `<i>`, `<b>` and `<u>` tags.
+fn styled_segments(text: &str) -> Vec<(PenStyle, String)> {
+    let mut segments: Vec<(PenStyle, String)> = Vec::new();
+    let mut style = PenStyle::default();
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        for &(name, open) in &[("i", true), ("b", true), ("u", true), ("i", false), ("b", false), ("u", false)] {
+            let tag = if open { format!("<{}>", name) } else { format!("</{}>", name) };
+            if rest.get(..tag.len()).is_some_and(|s| s.eq_ignore_ascii_case(&tag)) {
+                match name {
+                    "i" => style.italic = open,
+                    "b" => style.bold = open,
+                    _ => style.underline = open,
+                }
+                rest = rest.get(tag.len()..).unwrap_or("");
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        if let Some(c) = chars.next().filter(|&c| c != '\r') {
+            match segments.last_mut() {
+                Some((last_style, text)) if *last_style == style => text.push(c),
+                _ => segments.push((style, c.to_string())),
+            }
+        }
+        rest = chars.as_str();
+    }
+    segments
+}
+
+/// Converts an entry text into the XML content of a paragraph (styles without a pen of the file are dropped).
+fn encode_content(text: &str, pens: &[(String, PenStyle)]) -> String {
+    let mut result = String::new();
+    for (style, segment) in styled_segments(text) {
+        match pens.iter().find(|(_, pen_style)| *pen_style == style) {
+            Some((id, _)) if style != PenStyle::default() => result.push_str(&format!("<s p=\"{}\">{}</s>", escape_xml(id), escape_xml(&segment))),
+            _ => result.push_str(&escape_xml(&segment)),
+        }
+    }
+    result
+}
+
+/// Returns `true` if the text looks like a srv3 file.
+pub(crate) fn looks_like_srv3(text: &str) -> bool {
+    text.find("<timedtext")
+        .and_then(|idx| text.get(idx..))
+        .and_then(|tag| tag.get(..tag.find('>')?))
+        .is_some_and(|tag| tag.contains("format=\"3\"") || tag.contains("format='3'"))
+}
+
+impl SubtitleFileInterface for Srv3File {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .paragraphs()
+            .map(|paragraph| {
+                SubtitleEntry::new(
+                    paragraph.timespan,
+                    decode_content(&paragraph.content, &self.pens, paragraph.pen.as_deref()),
+                )
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.paragraphs().count(), new_subtitle_entries.len())?;
+
+        let pens = &self.pens;
+        let paragraphs = self.v.iter_mut().filter_map(|part| match *part {
+            Srv3FilePart::Paragraph(ref mut paragraph) => Some(paragraph),
+            Srv3FilePart::Filler(_) => None,
+        });
+        for (paragraph, entry) in paragraphs.zip(new_subtitle_entries) {
+            paragraph.timespan = entry.timespan;
+            if let Some(ref text) = entry.line {
+                // unchanged texts keep all markup
+                if *text != decode_content(&paragraph.content, pens, paragraph.pen.as_deref()) {
+                    // the text gets the pens of its own styles
+                    let style = pen_style(pens, paragraph.pen.as_deref());
+                    if style != PenStyle::default() {
+                        for piece in &mut paragraph.tag {
+                            if let TagPiece::Text(ref mut text) = *piece {
+                                *text = remove_attribute(text, "p");
+                            }
+                        }
+                        paragraph.pen = None;
+                    }
+                    paragraph.content = encode_content(text, pens);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = String::new();
+        for part in &self.v {
+            match *part {
+                Srv3FilePart::Filler(ref text) => result.push_str(text),
+                Srv3FilePart::Paragraph(ref paragraph) => {
+                    for piece in &paragraph.tag {
+                        match *piece {
+                            TagPiece::Text(ref text) => result.push_str(text),
+                            TagPiece::Start => result.push_str(&format_time(paragraph.timespan.start).to_string()),
+                            TagPiece::Duration => result.push_str(&paragraph.timespan.len().msecs().max(0).to_string()),
+                        }
+                    }
+                    result.push_str(&paragraph.content);
+                }
+            }
+        }
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let paragraph_idxs: Vec<usize> = (0..self.v.len())
+            .filter(|&i| matches!(self.v.get(i), Some(Srv3FilePart::Paragraph(_))))
+            .collect();
+        check_entry_count(paragraph_idxs.len(), keep.len())?;
+
+        // removing from the back keeps the indices of the front valid
+        for (&part_idx, _) in paragraph_idxs.iter().zip(keep).rev().filter(|(_, &keep)| !keep) {
+            // the end tag `</p>` and the line break after it
+            if let Some(Srv3FilePart::Filler(ref mut next)) = self.v.get_mut(part_idx + 1) {
+                let end = next.find('>').map_or(0, |idx| idx + 1);
+                let rest = next.get(end..).unwrap_or("");
+                let line_break = if rest.starts_with("\r\n") {
+                    2
+                } else if rest.starts_with('\n') {
+                    1
+                } else {
+                    0
+                };
+                next.drain(..end + line_break);
+            }
+            self.v.remove(part_idx);
+        }
+
+        // merge the fillers around the removed paragraphs
+        let mut parts: Vec<Srv3FilePart> = Vec::new();
+        for part in std::mem::take(&mut self.v) {
+            match (parts.last_mut(), part) {
+                (Some(Srv3FilePart::Filler(ref mut last)), Srv3FilePart::Filler(ref text)) => last.push_str(text),
+                (_, part) => parts.push(part),
+            }
+        }
+        self.v = parts;
+        Ok(())
+    }
+}
+
+/// Removes an attribute like ` p="1"` from (a part of) a start tag.
+fn remove_attribute(tag: &str, name: &str) -> String {
+    for quote in &['"', '\''] {
+        let prefix = format!(" {}={}", name, quote);
+        if let Some(start) = tag.find(&prefix) {
+            let value_start = start + prefix.len();
+            if let Some(len) = tag.get(value_start..).and_then(|value| value.find(*quote)) {
+                let mut result = tag.to_string();
+                result.replace_range(start..value_start + len + 1, "");
+                return result;
+            }
+        }
+    }
+    tag.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+
+    const FILE: &str = "<?xml version=\"1.0\" encoding=\"utf-8\" ?><timedtext format=\"3\">\n<head>\n<pen id=\"1\" fc=\"#FEFEFE\"/>\n\
+                     <pen id=\"2\" i=\"1\"/>\n</head>\n<body>\n<p t=\"1000\" d=\"2500\" p=\"2\">Hello &amp;\nworld</p>\n\
+                     <p t=\"2000\" d=\"3000\" w=\"1\"><s ac=\"0\">auto</s><s t=\"480\" ac=\"0\"> caption</s></p>\n\
+                     <p t=\"3500\" d=\"1500\" w=\"1\" a=\"1\">\n</p>\n<p t=\"5000\" d=\"100\">a <s p=\"2\">styled</s> word</p>\n</body>\n</timedtext>\n";
+
+    #[test]
+    fn srv3_pens_and_segments_are_kept() {
+        let mut file = Srv3File::parse(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        assert!(looks_like_srv3(FILE));
+    }
+
+    #[test]
+    fn srv3_pen_styles_become_tags() {
+        // the segments of a paragraph are joined and empty window paragraphs are no entries
+        let entries = Srv3File::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let texts: Vec<(i64, i64, &str)> = entries
+            .iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.line.as_deref().unwrap_or("")))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                (1000, 3500, "<i>Hello &\nworld</i>"),
+                (2000, 5000, "auto caption"),
+                (5000, 5100, "a <i>styled</i> word")
+            ]
+        );
+    }
+
+    #[test]
+    fn srv3_changed_texts_use_the_pens() {
+        // italics get the pen of the head, bold text has no pen and durations are written as `d` attribute
+        let mut file = Srv3File::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].line = Some("<i>Changed</i> <b>text</b>".to_string());
+        entries[1].timespan.end = TimePoint::from_msecs(3500);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("<p t=\"1000\" d=\"2500\"><s p=\"2\">Changed</s> text</p>\n"));
+        assert!(data.contains("<p t=\"2000\" d=\"1500\" w=\"1\"><s ac=\"0\">auto</s><s t=\"480\" ac=\"0\"> caption</s></p>\n"));
+    }
+
+    #[test]
+    fn srv3_window_paragraphs_are_kept() {
+        // the empty paragraph defines the window of the auto captions, so it stays when they are removed
+        let mut file = Srv3File::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[true, false, false]).unwrap();
+        let data = to_string(&file);
+        assert!(data.ends_with("world</p>\n<p t=\"3500\" d=\"1500\" w=\"1\" a=\"1\">\n</p>\n</body>\n</timedtext>\n"));
+        assert!(!data.contains("caption") && !data.contains("styled"));
+    }
+
+    #[test]
+    fn srv3_create() {
+        let span = TimeSpan::new(TimePoint::from_msecs(0), TimePoint::from_msecs(1000));
+        let created = Srv3File::create(vec![(span, "<b><i>x</i></b> & y".to_string())]).unwrap();
+        assert_eq!(created.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("<i><b>x</b></i> & y"));
+    }
+
+    #[test]
+    fn srv3_parse_errors() {
+        let parse = |s: &str| Srv3File::parse_inner(s, &mut Warnings::new());
+        assert_eq!(parse_error(parse, "<tt></tt>").kind(), &ExpectedTimedText);
+        assert_eq!(
+            parse_error(parse, "<timedtext format=\"3\"><body><p t=\"x\" d=\"1\">a</p></body></timedtext>").kind(),
+            &InvalidTime { value: "x".to_string() }
+        );
+        assert_eq!(
+            parse_error(parse, "<timedtext format=\"3\"><body><p t=\"0\" d=\"1\">a").kind(),
+            &MalformedXml {
+                position: 28,
+                msg: "unclosed paragraph".to_string()
+            }
+        );
+        assert!(!looks_like_srv3("<tt></tt>"));
+    }
+}
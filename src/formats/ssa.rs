@@ -5,13 +5,24 @@
 use crate::{SubtitleEntry, SubtitleFileInterface};
 
 use crate::errors::Result as SubtitleParserResult;
+use crate::escaping::{ass_event_field, ass_event_text};
 use crate::formats::common::*;
+use crate::formats::ssa_color::AssColor;
+use crate::formats::ssa_override::{AssKaraoke, AssText};
+use crate::positioning::Placement;
+use crate::retiming::TimeTransform;
+use crate::warnings::{WarningKind, Warnings};
 use combine::char::*;
 use combine::combinator::*;
 use combine::primitives::Parser;
 
-use crate::timetypes::{TimePoint, TimeSpan};
+#[cfg(test)]
+use crate::timetypes::TimeDelta;
+use crate::timetypes::{RoundingMode, TimePoint, TimeSpan, TimestampOutOfRange};
 use failure::ResultExt;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
 use std::iter::once;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -51,6 +62,15 @@ pub mod errors {
 
         #[fail(display = "parsing the line `{}` failed because of `{}`", line_num, msg)]
         SsaLineParseError { line_num: usize, msg: String },
+
+        #[fail(display = "the style `{}` is not defined", name)]
+        SsaUnknownStyle { name: String },
+
+        #[fail(display = "the style `{}` is defined twice", name)]
+        SsaDuplicateStyle { name: String },
+
+        #[fail(display = "the value `{}` of the '{}' field contains a comma or a line break", value, f)]
+        SsaInvalidFieldValue { f: &'static str, value: String },
     }
 }
 /*error_chain! {
@@ -90,23 +110,35 @@ struct SsaFieldsInfo {
     end_field_idx: usize,
     text_field_idx: usize,
     num_fields: usize,
+
+    /// Indices of the other known fields (`Layer`, `Style`, ...).
+    other_field_idxs: Vec<(usize, SsaEventField)>,
 }
 
 impl SsaFieldsInfo {
     /// Parses a format line like "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text".
     fn new_from_fields_info_line(line_num: usize, s: String) -> Result<SsaFieldsInfo> {
-        assert!(s.starts_with("Format:"));
-        let field_info = &s["Format:".len()..];
+        // the caller only passes lines which begin with "Format:"
+        let field_info = s.strip_prefix("Format:").unwrap_or(&s);
         let mut start_field_idx: Option<usize> = None;
         let mut end_field_idx: Option<usize> = None;
         let mut text_field_idx: Option<usize> = None;
+        let mut other_field_idxs: Vec<(usize, SsaEventField)> = Vec::new();
 
         // filter "Start" and "End" and "Text"
         let split_iter = field_info.split(',');
         let num_fields = split_iter.clone().count();
         for (i, field_name) in split_iter.enumerate() {
             let trimmed = field_name.trim();
-            if trimmed == "Start" {
+            if let Some(field) = SsaEventField::from_format_name(trimmed) {
+                if other_field_idxs.iter().any(|&(_, f)| f == field) {
+                    return Err(SsaDuplicateField {
+                        line_num,
+                        f: field.format_name(),
+                    })?;
+                }
+                other_field_idxs.push((i, field));
+            } else if trimmed == "Start" {
                 if start_field_idx.is_some() {
                     return Err(SsaDuplicateField { line_num, f: "Start" })?;
                 }
@@ -133,7 +165,8 @@ impl SsaFieldsInfo {
             start_field_idx: start_field_idx.ok_or_else(|| Error::from(SsaMissingField { line_num, f: "Start" }))?,
             end_field_idx: end_field_idx.ok_or_else(|| Error::from(SsaMissingField { line_num, f: "End" }))?,
             text_field_idx: text_field_idx2,
-            num_fields: num_fields,
+            num_fields,
+            other_field_idxs,
         })
     }
 }
@@ -144,14 +177,22 @@ impl SsaFieldsInfo {
 impl SsaFile {
     /// Parse a `.ssa` subtitle string to `SsaFile`.
     pub fn parse(s: &str) -> SubtitleParserResult<SsaFile> {
-        Ok(Self::parse_inner(s.to_string()).with_context(|_| crate::ErrorKind::ParsingError)?)
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.ssa` subtitle string to `SsaFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(SsaFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_inner(s.to_string(), &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
     }
 }
 
 /// Implement parser helper functions.
 impl SsaFile {
     /// Parses a whole `.ssa` file from string.
-    fn parse_inner(i: String) -> Result<SsaFile> {
+    fn parse_inner(i: String, warnings: &mut Warnings) -> Result<SsaFile> {
         let mut file_parts = Vec::new();
         let (bom, s) = split_bom(&i);
         file_parts.push(SsaFilePart::Filler(bom.to_string()));
@@ -161,8 +202,10 @@ impl SsaFile {
         let fields_info = SsaFieldsInfo::new_from_fields_info_line(line_num, field_info_line)?;
 
         // parse the dialog lines with the given format
-        file_parts.append(&mut Self::parse_dialog_lines(&fields_info, s)?);
-        Ok(SsaFile::new(file_parts))
+        file_parts.append(&mut Self::parse_dialog_lines(&fields_info, s, warnings)?);
+        let mut file = SsaFile::new(file_parts);
+        file.dialect = detect_dialect(s);
+        Ok(file)
     }
 
     /// Searches and parses a format line like "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text".
@@ -193,7 +236,7 @@ impl SsaFile {
     /// ```text
     /// "Dialogue: 1,0:22:43.52,0:22:46.22,ED-Romaji,,0,0,0,,{\fad(150,150)\blur0.5\bord1}some text"
     /// ```
-    fn parse_dialog_lines(fields_info: &SsaFieldsInfo, s: &str) -> Result<Vec<SsaFilePart>> {
+    fn parse_dialog_lines(fields_info: &SsaFieldsInfo, s: &str, warnings: &mut Warnings) -> Result<Vec<SsaFilePart>> {
         let mut result = Vec::new();
         let mut section_opt: Option<String> = None;
 
@@ -209,11 +252,20 @@ impl SsaFile {
             }
 
             if section_opt.is_none() || section_opt.iter().any(|s| s != "Events") || !trimmed_line.starts_with("Dialogue:") {
+                Self::check_non_dialog_line(line_num, section_opt.as_deref(), &trimmed_line, warnings);
                 result.push(SsaFilePart::Filler(line));
                 result.push(SsaFilePart::Filler("\n".to_string()));
                 continue;
             }
 
+            // `Dialogue:` lines with fewer fields than the format line have no timespan, so they are kept as filler
+            if line.matches(',').count() < fields_info.num_fields - 1 {
+                warnings.push(Some(line_num), WarningKind::IgnoredLine { line: trimmed_line });
+                result.push(SsaFilePart::Filler(line));
+                result.push(SsaFilePart::Filler(newl));
+                continue;
+            }
+
             result.append(&mut Self::parse_dialog_line(line_num, line.as_str(), fields_info)?);
             result.push(SsaFilePart::Filler(newl));
         }
@@ -221,6 +273,35 @@ impl SsaFile {
         Ok(result)
     }
 
+    /// Adds warnings for unknown `[Script Info]` keys and unknown lines in the `[Events]` section.
+    fn check_non_dialog_line(line_num: usize, section: Option<&str>, trimmed_line: &str, warnings: &mut Warnings) {
+        // empty lines and comments
+        if trimmed_line.is_empty() || trimmed_line.starts_with(';') || trimmed_line.starts_with("!:") {
+            return;
+        }
+
+        let key = trimmed_line.split(':').next().unwrap_or("").trim();
+        let is_known = match section {
+            Some("Script Info") => SCRIPT_INFO_KEYS.contains(&key),
+            Some("Events") => EVENT_LINE_KEYS.contains(&key),
+            _ => return,
+        };
+        if is_known {
+            return;
+        }
+
+        let kind = match section {
+            Some(section) if trimmed_line.contains(':') && section == "Script Info" => WarningKind::UnknownHeaderKey {
+                section: section.to_string(),
+                key: key.to_string(),
+            },
+            _ => WarningKind::IgnoredLine {
+                line: trimmed_line.to_string(),
+            },
+        };
+        warnings.push(Some(line_num), kind);
+    }
+
     /// Parse lines like:
     ///
     /// ```text
@@ -272,6 +353,8 @@ impl SsaFile {
                 SsaFilePart::TimespanEnd(Self::parse_timepoint(line_num, &field)?)
             } else if i == fields_info.text_field_idx {
                 SsaFilePart::Text(field.to_string())
+            } else if let Some(&(_, event_field)) = fields_info.other_field_idxs.iter().find(|&&(idx, _)| idx == i) {
+                SsaFilePart::Field(event_field, field.to_string())
             } else {
                 SsaFilePart::Filler(field.to_string())
             };
@@ -290,7 +373,7 @@ impl SsaFile {
             .map(extract_file_parts_closure)
             .collect::<Result<Vec<Vec<SsaFilePart>>>>()?
             .into_iter()
-            .flat_map(|part| part)
+            .flatten()
             .collect();
         Ok(result)
     }
@@ -307,10 +390,16 @@ impl SsaFile {
             parser(number_i64),
             eof(),
         )
-            .map(|(h, _, mm, _, ss, _, ms, _)| TimePoint::from_components(h, mm, ss, ms * 10))
+            .map(|(h, _, mm, _, ss, _, cs, _): (i64, _, i64, _, i64, _, i64, _)| {
+                let ms = cs.checked_mul(10).ok_or(TimestampOutOfRange)?;
+                TimePoint::try_from_components(h, mm, ss, ms)
+            })
             .parse(s);
         match parse_res {
-            Ok(res) => Ok(res.0),
+            Ok(res) => Ok(res.0.with_context(|_| SsaWrongTimepointFormat {
+                line_num,
+                string: s.to_string(),
+            })?),
             Err(e) => Err(SsaWrongTimepointFormat {
                 line_num,
                 string: parse_error_to_string(e),
@@ -334,10 +423,295 @@ enum SsaFilePart {
     /// Timespan end of a dialogue line
     TimespanEnd(TimePoint),
 
+    /// One of the other known fields of a dialogue line (stored verbatim)
+    Field(SsaEventField, String),
+
     /// Dialog lines
     Text(String),
 }
 
+/// The fields of a dialogue line (apart from `Start`, `End` and `Text`) which are not stored as filler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SsaEventField {
+    Layer,
+    Style,
+    Name,
+    MarginL,
+    MarginR,
+    MarginV,
+    Effect,
+}
+
+impl SsaEventField {
+    /// Field names like they appear in the `Format:` line.
+    fn from_format_name(s: &str) -> Option<SsaEventField> {
+        match s {
+            "Layer" => Some(SsaEventField::Layer),
+            "Style" => Some(SsaEventField::Style),
+            // older scripts use `Actor` instead of `Name`
+            "Name" | "Actor" => Some(SsaEventField::Name),
+            "MarginL" => Some(SsaEventField::MarginL),
+            "MarginR" => Some(SsaEventField::MarginR),
+            "MarginV" => Some(SsaEventField::MarginV),
+            "Effect" => Some(SsaEventField::Effect),
+            _ => None,
+        }
+    }
+
+    fn format_name(self) -> &'static str {
+        match self {
+            SsaEventField::Layer => "Layer",
+            SsaEventField::Style => "Style",
+            SsaEventField::Name => "Name",
+            SsaEventField::MarginL => "MarginL",
+            SsaEventField::MarginR => "MarginR",
+            SsaEventField::MarginV => "MarginV",
+            SsaEventField::Effect => "Effect",
+        }
+    }
+}
+
+/// A mutable view into a single dialogue line of a `SsaFile`.
+///
+/// All changes are written directly into the file (see `SsaFile::events_mut()`).
+#[derive(Debug)]
+pub struct SsaEventMut<'a> {
+    start: &'a mut TimePoint,
+    end: &'a mut TimePoint,
+    text: &'a mut String,
+    fields: Vec<(SsaEventField, &'a mut String)>,
+}
+
+impl<'a> SsaEventMut<'a> {
+    fn field(&self, field: SsaEventField) -> Option<&str> {
+        self.fields.iter().find(|(f, _)| *f == field).map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the start and end of the event.
+    pub fn timespan(&self) -> TimeSpan {
+        TimeSpan::new(*self.start, *self.end)
+    }
+
+    /// Changes the start and end of the event.
+    pub fn set_timespan(&mut self, timespan: TimeSpan) {
+        *self.start = timespan.start;
+        *self.end = timespan.end;
+    }
+
+    /// Returns the text including all override tags.
+    pub fn text(&self) -> &str {
+        self.text
+    }
+
+    /// Replaces the text of the event.
+    pub fn set_text(&mut self, text: String) {
+        *self.text = ass_event_text(&text);
+    }
+
+    /// Returns the karaoke syllables of the text (see `AssKaraoke`).
+    pub fn karaoke(&self) -> AssKaraoke {
+        AssKaraoke::from_text(&AssText::parse(self.text))
+    }
+
+    /// Writes the kinds and durations of the syllables into the karaoke tags of the text.
+    pub fn set_karaoke(&mut self, karaoke: &AssKaraoke) {
+        let mut text = AssText::parse(self.text);
+        karaoke.write_to(&mut text);
+        *self.text = text.to_string();
+    }
+
+    /// Changes the times of the event and scales the karaoke syllables by the same factor, so they stay in sync.
+    pub fn retime_with_karaoke(&mut self, transform: &TimeTransform) {
+        let mut karaoke = self.karaoke();
+        if !karaoke.syllables.is_empty() {
+            karaoke.scale(transform.scale);
+            self.set_karaoke(&karaoke);
+        }
+        *self.start = transform.apply(*self.start);
+        *self.end = transform.apply(*self.end);
+    }
+
+    fn set_field(&mut self, field: SsaEventField, new_value: &str) {
+        if let Some((_, value)) = self.fields.iter_mut().find(|(f, _)| *f == field) {
+            **value = ass_event_field(new_value);
+        }
+    }
+
+    /// Like `set_field()`, but rejects values which can't be written into an event line.
+    fn set_text_field(&mut self, field: SsaEventField, f: &'static str, new_value: &str) -> SubtitleParserResult<()> {
+        if new_value.contains([',', '\n', '\r']) {
+            let error = SsaInvalidFieldValue {
+                f,
+                value: new_value.to_string(),
+            };
+            return Err(Error::from(error)).with_context(|_| crate::ErrorKind::UpdatingEntriesError)?;
+        }
+        self.set_field(field, new_value);
+        Ok(())
+    }
+
+    fn integer_field(&self, field: SsaEventField) -> i32 {
+        self.field(field).and_then(|value| value.trim().parse().ok()).unwrap_or(0)
+    }
+
+    /// Returns the layer (or `0` if the script has no `Layer` field).
+    pub fn layer(&self) -> i32 {
+        self.integer_field(SsaEventField::Layer)
+    }
+
+    /// Changes the layer (does nothing if the script has no `Layer` field).
+    pub fn set_layer(&mut self, layer: i32) {
+        self.set_field(SsaEventField::Layer, &layer.to_string());
+    }
+
+    /// Returns the style name (or `""` if the script has no `Style` field).
+    pub fn style(&self) -> &str {
+        self.field(SsaEventField::Style).map_or("", str::trim)
+    }
+
+    /// Changes the style name (does nothing if the script has no `Style` field).
+    ///
+    /// Names with commas or line breaks are rejected with `SsaInvalidFieldValue`.
+    pub fn set_style(&mut self, style: &str) -> SubtitleParserResult<()> {
+        self.set_text_field(SsaEventField::Style, "Style", style)
+    }
+
+    /// Returns the character name (or `""` if the script has no `Name`/`Actor` field).
+    pub fn actor(&self) -> &str {
+        self.field(SsaEventField::Name).map_or("", str::trim)
+    }
+
+    /// Changes the character name (does nothing if the script has no `Name`/`Actor` field).
+    ///
+    /// Names with commas or line breaks can't be written into an event line and are rejected with
+    /// `SsaInvalidFieldValue`.
+    pub fn set_actor(&mut self, actor: &str) -> SubtitleParserResult<()> {
+        self.set_text_field(SsaEventField::Name, "Name", actor)
+    }
+
+    /// Returns the left, right and vertical margin (`0` means the margin of the style, which is also returned for
+    /// missing fields).
+    pub fn margins(&self) -> (i32, i32, i32) {
+        (
+            self.integer_field(SsaEventField::MarginL),
+            self.integer_field(SsaEventField::MarginR),
+            self.integer_field(SsaEventField::MarginV),
+        )
+    }
+
+    /// Changes the left, right and vertical margin (only of the fields the script has).
+    ///
+    /// Zero-padded margins like `0010` (of `v4.00` scripts) keep their width.
+    pub fn set_margins(&mut self, margin_l: i32, margin_r: i32, margin_v: i32) {
+        let fields = [
+            (SsaEventField::MarginL, margin_l),
+            (SsaEventField::MarginR, margin_r),
+            (SsaEventField::MarginV, margin_v),
+        ];
+        for &(field, margin) in &fields {
+            let old = self.field(field).map_or("", str::trim);
+            let width = if old.len() > 1 && old.starts_with('0') { old.len() } else { 0 };
+            let value = format!("{:0width$}", margin, width = width);
+            self.set_field(field, &value);
+        }
+    }
+
+    /// Returns the effect (or `""` if the script has no `Effect` field).
+    pub fn effect(&self) -> &str {
+        self.field(SsaEventField::Effect).map_or("", str::trim)
+    }
+
+    /// Changes the effect (does nothing if the script has no `Effect` field).
+    ///
+    /// Effects with commas or line breaks are rejected with `SsaInvalidFieldValue` (the parameters of effects
+    /// like `Banner;5` are separated by semicolons).
+    pub fn set_effect(&mut self, effect: &str) -> SubtitleParserResult<()> {
+        self.set_text_field(SsaEventField::Effect, "Effect", effect)
+    }
+
+    /// Copies the referenced data.
+    pub fn to_event(&self) -> SsaEvent {
+        SsaEvent {
+            timespan: TimeSpan::new(*self.start, *self.end),
+            layer: self.layer(),
+            style: self.field(SsaEventField::Style).unwrap_or("").to_string(),
+            name: self.field(SsaEventField::Name).unwrap_or("").to_string(),
+            margin_l: self.integer_field(SsaEventField::MarginL),
+            margin_r: self.integer_field(SsaEventField::MarginR),
+            margin_v: self.integer_field(SsaEventField::MarginV),
+            effect: self.field(SsaEventField::Effect).unwrap_or("").to_string(),
+            text: self.text.clone(),
+        }
+    }
+}
+
+/// The data of a dialogue line in the `[Events]` section.
+///
+/// New fields can be added in minor versions, so create events with `SsaEvent::new()` instead of struct literals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SsaEvent {
+    /// Start and end of the event.
+    pub timespan: TimeSpan,
+
+    /// The layer (events in higher layers are drawn above lower layers); `0` if the script has no `Layer` field.
+    pub layer: i32,
+
+    /// The style name.
+    pub style: String,
+
+    /// The name of the speaking character (`Name` or `Actor` field).
+    pub name: String,
+
+    /// The left margin in pixels (`0` uses the margin of the style).
+    pub margin_l: i32,
+
+    /// The right margin in pixels (`0` uses the margin of the style).
+    pub margin_r: i32,
+
+    /// The vertical margin in pixels (`0` uses the margin of the style).
+    pub margin_v: i32,
+
+    /// The effect field (like `Banner;...` or `Scroll up;...`).
+    pub effect: String,
+
+    /// The text including all override tags.
+    pub text: String,
+}
+
+impl SsaEvent {
+    /// Creates an event in layer `0` without name, margins and effect.
+    pub fn new(timespan: TimeSpan, style: &str, text: &str) -> SsaEvent {
+        SsaEvent {
+            timespan,
+            layer: 0,
+            style: style.to_string(),
+            name: String::new(),
+            margin_l: 0,
+            margin_r: 0,
+            margin_v: 0,
+            effect: String::new(),
+            text: text.to_string(),
+        }
+    }
+
+    /// Guesses whether this event is typesetting (a sign) instead of dialogue.
+    ///
+    /// Events are considered typesetting if they are positioned (`\pos`, `\move`) or use a custom rotation
+    /// origin or clipping (`\org`, `\clip`, `\iclip`). Signs are usually timed to the video, whereas dialogue
+    /// is timed to the audio.
+    pub fn is_typesetting(&self) -> bool {
+        ["\\pos(", "\\move(", "\\org(", "\\clip(", "\\iclip("]
+            .iter()
+            .any(|tag| self.text.contains(tag))
+    }
+
+    /// Returns the karaoke syllables of the text (see `AssKaraoke`).
+    pub fn karaoke(&self) -> AssKaraoke {
+        AssKaraoke::from_text(&AssText::parse(&self.text))
+    }
+}
+
 // ////////////////////////////////////////////////////////////////////////////////////////////////
 // SSA file
 
@@ -348,6 +722,12 @@ enum SsaFilePart {
 #[derive(Debug, Clone)]
 pub struct SsaFile {
     v: Vec<SsaFilePart>,
+
+    /// How milliseconds are rounded to the centiseconds of the file
+    rounding: RoundingMode,
+
+    /// The format version of the file
+    dialect: SsaDialect,
 }
 
 impl SsaFile {
@@ -358,84 +738,530 @@ impl SsaFile {
             _ => None,
         });
 
-        SsaFile { v: new_file_parts }
+        SsaFile {
+            v: new_file_parts,
+            rounding: RoundingMode::default(),
+            dialect: SsaDialect::default(),
+        }
     }
 
-    /// This function filters out all start times and end times, and returns them ordered
-    /// (="(start, end, dialog)") so they can be easily read or written to.
-    ///
-    /// TODO: implement a single version that takes both `&mut` and `&` (dependent on HKT).
-    fn get_subtitle_entries_mut<'a>(&'a mut self) -> Vec<(&'a mut TimePoint, &'a mut TimePoint, &'a mut String)> {
-        let mut startpoint_buffer: Option<&'a mut TimePoint> = None;
-        let mut endpoint_buffer: Option<&'a mut TimePoint> = None;
-
-        // the extra block satisfies the borrow checker
-        let timings: Vec<_> = {
-            let filter_map_closure = |part: &'a mut SsaFilePart| -> Option<(&'a mut TimePoint, &'a mut TimePoint, &'a mut String)> {
-                use self::SsaFilePart::*;
-                match *part {
-                    TimespanStart(ref mut start) => {
-                        assert_eq!(startpoint_buffer, None); // parser should have ensured that no two consecutive SSA start times exist
-                        startpoint_buffer = Some(start);
-                        None
-                    }
-                    TimespanEnd(ref mut end) => {
-                        assert_eq!(endpoint_buffer, None); // parser should have ensured that no two consecutive SSA end times exist
-                        endpoint_buffer = Some(end);
-                        None
+    /// Returns the indices of the first and the last (the `Text`) typed part of every dialogue line.
+    fn get_entry_part_ranges(&self) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        let mut first_opt: Option<usize> = None;
+        let (mut has_start, mut has_end) = (false, false);
+        for (i, part) in self.v.iter().enumerate() {
+            match *part {
+                SsaFilePart::Filler(_) => {}
+                SsaFilePart::TimespanStart(_) | SsaFilePart::TimespanEnd(_) | SsaFilePart::Field(_, _) => {
+                    has_start |= matches!(*part, SsaFilePart::TimespanStart(_));
+                    has_end |= matches!(*part, SsaFilePart::TimespanEnd(_));
+                    first_opt.get_or_insert(i);
+                }
+                SsaFilePart::Text(_) => {
+                    // only lines with a timespan are entries (like in `get_events_mut()`)
+                    let first = first_opt.take().unwrap_or(i);
+                    let is_entry = has_start && has_end;
+                    has_start = false;
+                    has_end = false;
+                    if is_entry {
+                        result.push((first, i));
                     }
-                    Text(ref mut text) => {
-                        // reset the timepoint buffers
-                        let snatched_startpoint_buffer = startpoint_buffer.take();
-                        let snatched_endpoint_buffer = endpoint_buffer.take();
+                }
+            }
+        }
+        result
+    }
 
-                        let start = snatched_startpoint_buffer.expect("SSA parser should have ensured that every line has a startpoint");
-                        let end = snatched_endpoint_buffer.expect("SSA parser should have ensured that every line has a endpoint");
+    /// This function filters out all start times, end times and fields, and returns them ordered
+    /// (=one `SsaEventMut` per dialogue line) so they can be easily read or written to.
+    ///
+    /// TODO: implement a single version that takes both `&mut` and `&` (dependent on HKT).
+    fn get_events_mut(&mut self) -> Vec<SsaEventMut<'_>> {
+        let mut startpoint_buffer: Option<&mut TimePoint> = None;
+        let mut endpoint_buffer: Option<&mut TimePoint> = None;
+        let mut fields_buffer: Vec<(SsaEventField, &mut String)> = Vec::new();
 
-                        Some((start, end, text))
+        let mut result = Vec::new();
+        for part in self.v.iter_mut() {
+            use self::SsaFilePart::*;
+            match *part {
+                TimespanStart(ref mut start) => startpoint_buffer = Some(start),
+                TimespanEnd(ref mut end) => endpoint_buffer = Some(end),
+                Field(field, ref mut value) => fields_buffer.push((field, value)),
+                Text(ref mut text) => {
+                    // reset the buffers (the parser ensures that every line has a start and an end)
+                    let fields = std::mem::take(&mut fields_buffer);
+                    if let (Some(start), Some(end)) = (startpoint_buffer.take(), endpoint_buffer.take()) {
+                        result.push(SsaEventMut { start, end, text, fields });
                     }
-                    Filler(_) => None,
                 }
-            };
+                Filler(_) => {}
+            }
+        }
+
+        result
+    }
+
+    /// Returns the typed data of all dialogue lines in file order.
+    ///
+    /// The events are in the same order as the entries of `get_subtitle_entries()`.
+    pub fn get_events(&self) -> Vec<SsaEvent> {
+        // see `SubtitleFileInterface::get_subtitle_entries()` for why we clone here
+        let mut new_file = self.clone();
+        new_file.get_events_mut().iter().map(SsaEventMut::to_event).collect()
+    }
+
+    /// Sets how times are rounded to centiseconds when the file is written (default: `RoundingMode::Nearest`).
+    pub fn set_rounding_mode(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    /// Returns the format version of the file (from `ScriptType:` or the name of the styles section).
+    pub fn dialect(&self) -> SsaDialect {
+        self.dialect
+    }
+
+    /// Converts the file to another format version.
+    ///
+    /// This rewrites `ScriptType:`, the name of the styles section and the `Format:`, `Style:` and event lines
+    /// to use the fields of the new version. Fields that don't exist in the new version are dropped, new fields
+    /// get their default values (`Marked` becomes `Layer` and vice versa, `MarginV` is split into/taken from
+    /// `MarginT`/`MarginB`, and the alignment is converted between the legacy and the numpad notation).
+    pub fn set_dialect(&mut self, dialect: SsaDialect) -> SubtitleParserResult<()> {
+        if dialect == self.dialect {
+            return Ok(());
+        }
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let converted = convert_dialect(&data, self.dialect, dialect);
+        let mut file = Self::parse_inner(converted, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        file.dialect = dialect;
+        *self = file;
+        Ok(())
+    }
+
+    /// Returns the file like `to_data()`, but normalized so that files with the same content are always
+    /// written with the same bytes.
+    ///
+    /// Whitespace and line endings are normalized (one empty line between sections, no whitespace around the fields
+    /// except in the text of events), the keys of `[Script Info]`
+    /// are sorted (known keys in the usual order, then unknown keys alphabetically, comments first), and the
+    /// numbers and colors of styles are written in a fixed notation.
+    pub fn to_deterministic_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        Ok(canonicalize_ssa(&data).into_bytes())
+    }
+
+    /// Returns the `CodecPrivate` data (the script without events) and the block data of all events for a
+    /// Matroska `S_TEXT/ASS` track (see `matroska::to_matroska()`).
+    pub(crate) fn to_matroska_blocks(&self) -> SubtitleParserResult<(String, Vec<(TimeSpan, String)>)> {
+        let mut file = self.clone();
+        file.set_dialect(SsaDialect::V4Plus)?;
+
+        let data = String::from_utf8_lossy(&file.to_data()?).into_owned();
+        let mut header = String::new();
+        for line in data.split_inclusive('\n') {
+            if line.trim().eq_ignore_ascii_case("[Events]") {
+                break;
+            }
+            header.push_str(line);
+        }
+        if !header.is_empty() && !header.ends_with("\n\n") && !header.ends_with("\r\n\r\n") {
+            header.push('\n');
+        }
+        header.push_str("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n");
+
+        // the block data is "ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+        let blocks = file
+            .get_events_mut()
+            .iter()
+            .enumerate()
+            .map(|(read_order, event)| {
+                let field = |field: SsaEventField, default: &str| event.field(field).map_or(default, str::trim).to_string();
+                let fields = [
+                    read_order.to_string(),
+                    field(SsaEventField::Layer, "0"),
+                    field(SsaEventField::Style, "Default"),
+                    field(SsaEventField::Name, ""),
+                    field(SsaEventField::MarginL, "0"),
+                    field(SsaEventField::MarginR, "0"),
+                    field(SsaEventField::MarginV, "0"),
+                    field(SsaEventField::Effect, ""),
+                    event.text().to_string(),
+                ];
+                (event.timespan(), fields.join(","))
+            })
+            .collect();
+        Ok((header, blocks))
+    }
+
+    /// Returns mutable views of all dialogue lines in file order.
+    pub fn events_mut(&mut self) -> Vec<SsaEventMut<'_>> {
+        self.get_events_mut()
+    }
+
+    /// Returns mutable views of all dialogue lines with the style `style`.
+    ///
+    /// ```
+    /// # let mut file = subparse::SsaFile::parse("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hi!").unwrap();
+    /// use subparse::timetypes::TimeDelta;
+    ///
+    /// for mut event in file.entries_by_style("Default") {
+    ///     let timespan = event.timespan();
+    ///     event.set_timespan(timespan + TimeDelta::from_msecs(200));
+    /// }
+    /// ```
+    pub fn entries_by_style(&mut self, style: &str) -> Vec<SsaEventMut<'_>> {
+        self.get_events_mut().into_iter().filter(|event| event.style() == style.trim()).collect()
+    }
+
+    /// Returns mutable views of all dialogue lines spoken by `actor` (the `Name` or `Actor` field).
+    pub fn entries_by_actor(&mut self, actor: &str) -> Vec<SsaEventMut<'_>> {
+        self.get_events_mut().into_iter().filter(|event| event.actor() == actor.trim()).collect()
+    }
+
+    /// Returns mutable views of all dialogue lines with the effect `effect`.
+    ///
+    /// Only the effect name has to match, so `"Banner"` also finds events with `"Banner;10;0"`.
+    pub fn entries_by_effect(&mut self, effect: &str) -> Vec<SsaEventMut<'_>> {
+        self.get_events_mut()
+            .into_iter()
+            .filter(|event| event.effect().split(';').next().unwrap_or("").trim() == effect.trim())
+            .collect()
+    }
+
+    /// Changes the timing of all events which are selected by `select` to the result of `transform`.
+    ///
+    /// This is useful if only the audio of a video was resynced: the dialogue has to be shifted, whereas signs and
+    /// other typesetting are still timed correctly to the video frames.
+    ///
+    /// ```
+    /// # let mut file = subparse::SsaFile::parse("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hi!").unwrap();
+    /// use subparse::timetypes::TimeDelta;
+    ///
+    /// // shift dialogue by 1.5 seconds, leave the signs untouched
+    /// file.retime_events(|event| !event.is_typesetting(), |timespan| timespan + TimeDelta::from_msecs(1500));
+    /// ```
+    pub fn retime_events<S, T>(&mut self, mut select: S, mut transform: T)
+    where
+        S: FnMut(&SsaEvent) -> bool,
+        T: FnMut(TimeSpan) -> TimeSpan,
+    {
+        for event_refs in self.get_events_mut() {
+            if select(&event_refs.to_event()) {
+                let new_timespan = transform(TimeSpan::new(*event_refs.start, *event_refs.end));
+                *event_refs.start = new_timespan.start;
+                *event_refs.end = new_timespan.end;
+            }
+        }
+    }
+
+    /// Reorders the dialogue lines by their start time without changing how the file is rendered.
+    ///
+    /// Renderers draw overlapping events of the same layer in the order of the file (or of the `ReadOrder` field of
+    /// scripts extracted from Matroska files), so a naive sort would change the stacking and collisions of typesetting.
+    /// The sort is therefore stable and never swaps two events of the same layer which overlap in time. All other
+    /// lines (like `Comment:` lines) keep their position.
+    pub fn sort_events(&mut self) -> SubtitleParserResult<()> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let sorted = sort_event_lines(&data, &self.get_events());
+        let mut file = Self::parse_inner(sorted, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(())
+    }
+
+    /// Merges identical styles and removes all styles which are not used by any event.
+    ///
+    /// Styles are identical if all fields apart from the name are equal (the colors and numbers are compared in
+    /// the notation of `to_deterministic_data()`); the first of them is kept and all events (including `Comment:`
+    /// lines and `\rStyle` override tags) are changed to use it. A style is used if an event or a `\r` tag refers
+    /// to it; `Default` is also kept if an event refers to an undefined style, because renderers fall back to it.
+    pub fn clean_styles(&mut self) -> SubtitleParserResult<SsaStyleCleanup> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let (cleaned, cleanup) = clean_style_lines(&data);
+        let mut file = Self::parse_inner(cleaned, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(cleanup)
+    }
+
+    /// Copies all styles of `source` into this file and returns the name of every imported style in this file,
+    /// as `(name in source, name in this file)`.
+    ///
+    /// The styles are converted to the fields of this file (see `set_dialect()`) and appended to its styles
+    /// section, which is created if necessary. `conflicts` decides what happens to styles with an existing name.
+    /// The events can then be changed to use the imported styles:
+    ///
+    /// ```
+    /// # let house = "[V4+ Styles]\nFormat: Name, Fontname, Fontsize\nStyle: Default,Open Sans,52\nStyle: Sign,Arial,40\n\n[Events]\nFormat: Layer, Start, End, Style, Text\n";
+    /// # let script = "[V4+ Styles]\nFormat: Name, Fontname, Fontsize\nStyle: Default,Arial,20\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\pos(10,10)}EXIT";
+    /// use subparse::{SsaFile, SsaStyleConflict};
+    ///
+    /// let mut file = SsaFile::parse(script).unwrap();
+    /// let names = file.import_styles(&SsaFile::parse(house).unwrap(), &SsaStyleConflict::Rename("_house".to_string())).unwrap();
+    /// assert_eq!(names[0], ("Default".to_string(), "Default_house".to_string()));
+    ///
+    /// for mut event in file.events_mut() {
+    ///     if event.to_event().is_typesetting() {
+    ///         event.set_style("Sign").unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn import_styles(&mut self, source: &SsaFile, conflicts: &SsaStyleConflict) -> SubtitleParserResult<Vec<(String, String)>> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let source_data = String::from_utf8_lossy(&source.to_data()?).into_owned();
+        let (imported, names) = import_style_lines(&data, &source_data, source.dialect, self.dialect, conflicts);
+        let mut file = Self::parse_inner(imported, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(names)
+    }
+
+    /// Returns the styles of the styles section in the order of the file.
+    ///
+    /// The fields are read by the `Format:` line of the section, so `[V4 Styles]` work too (their `TertiaryColour`
+    /// is the `outline_colour` and their alignment is converted to the numpad layout).
+    ///
+    /// ```
+    /// # let script = "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, Bold\nStyle: Default,Arial,20,0\n\n[Events]\nFormat: Layer, Start, End, Style, Text\n";
+    /// use subparse::{SsaFile, SsaStyle, SubtitleFileInterface};
+    ///
+    /// let mut file = SsaFile::parse(script).unwrap();
+    /// let mut style = file.styles().unwrap().remove(0);
+    /// style.fontsize = 48.0;
+    /// file.set_style(&style).unwrap();
+    /// file.set_style(&SsaStyle { name: "Sign".to_string(), bold: true, ..SsaStyle::default() }).unwrap();
+    /// file.remove_style("Default").unwrap();
+    /// assert!(String::from_utf8(file.to_data().unwrap()).unwrap().contains("\nStyle: Sign,Arial,20,-1\n"));
+    /// ```
+    pub fn styles(&self) -> SubtitleParserResult<Vec<SsaStyle>> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let (_, styles) = find_style_lines(&get_lines_non_destructive(&data));
+        Ok(styles
+            .iter()
+            .map(|line| SsaStyle::from_values(&line.values, &line.fields, self.dialect))
+            .collect())
+    }
+
+    /// Replaces the style with the name of `style` or appends it to the styles section (which is created if
+    /// necessary).
+    ///
+    /// The values are written in the order of the `Format:` line of the section; fields which are not part of
+    /// `SsaStyle` (like `AlphaLevel` of `[V4 Styles]`) keep their value.
+    pub fn set_style(&mut self, style: &SsaStyle) -> SubtitleParserResult<()> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let changed = set_style_line(&data, style, self.dialect);
+        let mut file = Self::parse_inner(changed, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(())
+    }
 
-            self.v.iter_mut().filter_map(filter_map_closure).collect()
+    /// Removes the style with the name `name` and returns `false` if there is no such style.
+    ///
+    /// Events which use the style are not changed, so renderers show them with the `Default` style.
+    pub fn remove_style(&mut self, name: &str) -> SubtitleParserResult<bool> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let changed = match remove_style_line(&data, name) {
+            Some(changed) => changed,
+            None => return Ok(false),
         };
+        let mut file = Self::parse_inner(changed, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(true)
+    }
+
+    /// Returns the common keys of the `[Script Info]` section (see `script_info_value()` for all other keys).
+    ///
+    /// ```
+    /// # let script = "[Script Info]\n; made by hand\nTitle: Demo\nPlayResY: 720\nCustom: kept\n\n[Events]\nFormat: Layer, Start, End, Style, Text\n";
+    /// use subparse::{SsaFile, SubtitleFileInterface};
+    ///
+    /// let mut file = SsaFile::parse(script).unwrap();
+    /// let mut info = file.script_info().unwrap();
+    /// assert_eq!((info.title.as_deref(), info.play_res_y), (Some("Demo"), Some(720)));
+    /// info.play_res_x = Some(1280);
+    /// info.scaled_border_and_shadow = Some(true);
+    /// file.set_script_info(&info).unwrap();
+    /// let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+    /// assert!(data.starts_with("[Script Info]\n; made by hand\nTitle: Demo\nPlayResY: 720\nCustom: kept\nPlayResX: 1280\nScaledBorderAndShadow: yes\n"));
+    /// ```
+    pub fn script_info(&self) -> SubtitleParserResult<SsaScriptInfo> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let (_, values) = find_script_info_lines(&get_lines_non_destructive(&data));
+        let get = |key: &str| values.iter().find(|(_, k, _)| k == key).map(|(_, _, value)| value.clone());
+        let number = |key: &str| get(key).and_then(|value| value.parse().ok());
+        Ok(SsaScriptInfo {
+            title: get("Title"),
+            play_res_x: number("PlayResX"),
+            play_res_y: number("PlayResY"),
+            wrap_style: get("WrapStyle").and_then(|value| value.parse().ok()),
+            scaled_border_and_shadow: get("ScaledBorderAndShadow").and_then(|value| match value.to_lowercase().as_str() {
+                "yes" => Some(true),
+                "no" => Some(false),
+                _ => None,
+            }),
+            ycbcr_matrix: get("YCbCr Matrix"),
+        })
+    }
+
+    /// Writes the keys of `info` into the `[Script Info]` section (which is created if necessary).
+    ///
+    /// Existing keys are changed in place, new keys are appended to the section and keys which are `None` are
+    /// removed. All other keys and the comments are kept.
+    pub fn set_script_info(&mut self, info: &SsaScriptInfo) -> SubtitleParserResult<()> {
+        let values = [
+            ("Title", info.title.clone()),
+            ("PlayResX", info.play_res_x.map(|x| x.to_string())),
+            ("PlayResY", info.play_res_y.map(|y| y.to_string())),
+            ("WrapStyle", info.wrap_style.map(|style| style.to_string())),
+            (
+                "ScaledBorderAndShadow",
+                info.scaled_border_and_shadow.map(|scaled| if scaled { "yes" } else { "no" }.to_string()),
+            ),
+            ("YCbCr Matrix", info.ycbcr_matrix.clone()),
+        ];
+        self.set_script_info_lines(&values)
+    }
+
+    /// Returns the value of any key of the `[Script Info]` section (like `"Original Script"`).
+    pub fn script_info_value(&self, key: &str) -> SubtitleParserResult<Option<String>> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let (_, values) = find_script_info_lines(&get_lines_non_destructive(&data));
+        Ok(values.into_iter().find(|(_, k, _)| k == key.trim()).map(|(_, _, value)| value))
+    }
+
+    /// Sets (or with `None` removes) any key of the `[Script Info]` section, like `set_script_info()`.
+    pub fn set_script_info_value(&mut self, key: &str, value: Option<&str>) -> SubtitleParserResult<()> {
+        self.set_script_info_lines(&[(key.trim(), value.map(str::to_string))])
+    }
+
+    fn set_script_info_lines(&mut self, values: &[(&str, Option<String>)]) -> SubtitleParserResult<()> {
+        let data = String::from_utf8_lossy(&self.to_data()?).into_owned();
+        let changed = set_script_info_values(&data, values);
+        let mut file = Self::parse_inner(changed, &mut Warnings::new()).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.rounding = self.rounding;
+        *self = file;
+        Ok(())
+    }
+}
+
+/// The common keys of the `[Script Info]` section (`None` for missing keys).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SsaScriptInfo {
+    /// The `Title` of the script.
+    pub title: Option<String>,
+
+    /// The `PlayResX` (the width of the coordinate system of positions and margins).
+    pub play_res_x: Option<u32>,
+
+    /// The `PlayResY` (the height of the coordinate system, which also scales the font sizes).
+    pub play_res_y: Option<u32>,
+
+    /// The `WrapStyle` (`0`: smart wrapping with a wider top line, `1`: end-of-line wrapping, `2`: only `\N`
+    /// breaks, `3`: smart wrapping with a wider bottom line).
+    pub wrap_style: Option<u8>,
+
+    /// The `ScaledBorderAndShadow` (`yes` scales outlines and shadows with the video instead of the script
+    /// resolution).
+    pub scaled_border_and_shadow: Option<bool>,
 
-        // every timespan should now consist of a beginning and a end (this should be ensured by parser)
-        assert_eq!(startpoint_buffer, None);
-        assert_eq!(endpoint_buffer, None);
+    /// The `YCbCr Matrix` of the colors (like `TV.709`).
+    pub ycbcr_matrix: Option<String>,
+}
+
+/// The keys of the `[Script Info]` section which are understood by common renderers and editors.
+const SCRIPT_INFO_KEYS: &[&str] = &[
+    "Title",
+    "Original Script",
+    "Original Translation",
+    "Original Editing",
+    "Original Timing",
+    "Synch Point",
+    "Script Updated By",
+    "Update Details",
+    "ScriptType",
+    "Collisions",
+    "PlayResX",
+    "PlayResY",
+    "PlayDepth",
+    "Timer",
+    "WrapStyle",
+    "ScaledBorderAndShadow",
+    "YCbCr Matrix",
+    "LayoutResX",
+    "LayoutResY",
+    "Kerning",
+    "Language",
+];
+
+/// The line types of the `[Events]` section (only `Dialogue` lines are parsed).
+const EVENT_LINE_KEYS: &[&str] = &["Format", "Dialogue", "Comment", "Picture", "Sound", "Movie", "Command"];
+
+/// Timing to string like "0:00:22.21".
+fn timepoint_to_ssa_string(t: TimePoint, rounding: RoundingMode) -> String {
+    let t = TimePoint::from_csecs(t.to_csecs_rounded(rounding));
+    let p = if t.msecs() < 0 { -t } else { t };
+    format!(
+        "{}{}:{:02}:{:02}.{:02}",
+        if t.msecs() < 0 { "-" } else { "" },
+        p.hours(),
+        p.mins_comp(),
+        p.secs_comp(),
+        p.csecs_comp()
+    )
+}
 
-        timings
+impl SsaFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                SsaFilePart::Filler(ref text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
     }
 }
 
 impl SubtitleFileInterface for SsaFile {
     fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
         // it's unfortunate we have to clone the file before using
-        // `get_subtitle_entries_mut()`, but otherwise we'd have to copy the`
-        // `get_subtitle_entries_mut()` and create a non-mut-reference version
+        // `get_events_mut()`, but otherwise we'd have to copy the`
+        // `get_events_mut()` and create a non-mut-reference version
         // of it (much code duplication); I think a `clone` in this
         // not-time-critical code is acceptable, and after HKT become
         // available, this can be solved much nicer.
         let mut new_file = self.clone();
+        // the `Language` of the `[Script Info]` section is the language of all events
+        let language = self.script_info_value("Language")?.filter(|language| !language.is_empty());
         let timings = new_file
-            .get_subtitle_entries_mut()
+            .get_events_mut()
             .into_iter()
-            .map(|(&mut start, &mut end, text)| SubtitleEntry::new(TimeSpan::new(start, end), text.clone()))
+            .map(|event| {
+                let mut entry = SubtitleEntry::new(TimeSpan::new(*event.start, *event.end), event.text.clone());
+                entry.flags.forced = [event.style(), event.actor()].iter().any(|s| s.to_lowercase().contains("forced"));
+                entry.language = language.clone();
+                entry
+            })
             .collect();
 
         Ok(timings)
     }
 
     fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
-        let subtitle_entries = self.get_subtitle_entries_mut();
-        assert_eq!(subtitle_entries.len(), new_subtitle_entries.len()); // required by specification of this function
+        let events = self.get_events_mut();
+        check_entry_count(events.len(), new_subtitle_entries.len())?;
 
-        for ((start_ref, end_ref, text_ref), new_entry_ref) in subtitle_entries.into_iter().zip(new_subtitle_entries) {
-            *start_ref = new_entry_ref.timespan.start;
-            *end_ref = new_entry_ref.timespan.end;
+        for (event, new_entry_ref) in events.into_iter().zip(new_subtitle_entries) {
+            *event.start = new_entry_ref.timespan.start;
+            *event.end = new_entry_ref.timespan.end;
             if let Some(ref text) = new_entry_ref.line {
-                *text_ref = text.clone();
+                *event.text = ass_event_text(text);
             }
         }
 
@@ -443,25 +1269,12 @@ impl SubtitleFileInterface for SsaFile {
     }
 
     fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
-        // timing to string like "0:00:22.21"
-        let fn_timing_to_string = |t: TimePoint| {
-            let p = if t.msecs() < 0 { -t } else { t };
-            format!(
-                "{}{}:{:02}:{:02}.{:02}",
-                if t.msecs() < 0 { "-" } else { "" },
-                p.hours(),
-                p.mins_comp(),
-                p.secs_comp(),
-                p.csecs_comp()
-            )
-        };
-
         let fn_file_part_to_string = |part: &SsaFilePart| {
             use self::SsaFilePart::*;
             match *part {
-                Filler(ref t) | Text(ref t) => t.clone(),
-                TimespanStart(start) => fn_timing_to_string(start),
-                TimespanEnd(end) => fn_timing_to_string(end),
+                Filler(ref t) | Field(_, ref t) | Text(ref t) => t.clone(),
+                TimespanStart(start) => timepoint_to_ssa_string(start, self.rounding),
+                TimespanEnd(end) => timepoint_to_ssa_string(end, self.rounding),
             }
         };
 
@@ -469,4 +1282,1608 @@ impl SubtitleFileInterface for SsaFile {
 
         Ok(result.into_bytes())
     }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let ranges = self.get_entry_part_ranges();
+        check_entry_count(ranges.len(), keep.len())?;
+
+        // removing from the back keeps the indices of the front valid
+        for (&(first, last), _) in ranges.iter().zip(keep).rev().filter(|(_, &keep)| !keep) {
+            remove_line_parts(&mut self.v, first, last, |part| match *part {
+                SsaFilePart::Filler(ref mut text) => Some(text),
+                _ => None,
+            });
+        }
+
+        self.v = SsaFile::new(std::mem::take(&mut self.v)).v;
+        Ok(())
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// SSA dialects
+
+/// The version of the SubStation Alpha format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SsaDialect {
+    /// SubStation Alpha v4 (`ScriptType: v4.00`, `[V4 Styles]`), usually `.ssa` files.
+    V4,
+
+    /// Advanced SubStation Alpha (`ScriptType: v4.00+`, `[V4+ Styles]`), usually `.ass` files.
+    #[default]
+    V4Plus,
+
+    /// Advanced SubStation Alpha v4++ (`ScriptType: v4.00++`, `[V4++ Styles]`) with separate top and bottom margins.
+    V4PlusPlus,
+}
+
+/// The style fields of `[V4 Styles]`.
+const V4_STYLE_FIELDS: &[&str] = &[
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "TertiaryColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "AlphaLevel",
+    "Encoding",
+];
+
+/// The style fields of `[V4+ Styles]` (like `V4PLUS_STYLES_FORMAT`).
+const V4PLUS_STYLE_FIELDS: &[&str] = &[
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "OutlineColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "Underline",
+    "StrikeOut",
+    "ScaleX",
+    "ScaleY",
+    "Spacing",
+    "Angle",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginV",
+    "Encoding",
+];
+
+/// The style fields of `[V4++ Styles]`.
+const V4PLUSPLUS_STYLE_FIELDS: &[&str] = &[
+    "Name",
+    "Fontname",
+    "Fontsize",
+    "PrimaryColour",
+    "SecondaryColour",
+    "OutlineColour",
+    "BackColour",
+    "Bold",
+    "Italic",
+    "Underline",
+    "StrikeOut",
+    "ScaleX",
+    "ScaleY",
+    "Spacing",
+    "Angle",
+    "BorderStyle",
+    "Outline",
+    "Shadow",
+    "Alignment",
+    "MarginL",
+    "MarginR",
+    "MarginT",
+    "MarginB",
+    "Encoding",
+    "RelativeTo",
+];
+
+impl SsaDialect {
+    /// The value of `ScriptType:` in the `[Script Info]` section.
+    pub fn script_type(self) -> &'static str {
+        match self {
+            SsaDialect::V4 => "v4.00",
+            SsaDialect::V4Plus => "v4.00+",
+            SsaDialect::V4PlusPlus => "v4.00++",
+        }
+    }
+
+    /// The name of the styles section (without brackets).
+    pub fn styles_section(self) -> &'static str {
+        match self {
+            SsaDialect::V4 => "V4 Styles",
+            SsaDialect::V4Plus => "V4+ Styles",
+            SsaDialect::V4PlusPlus => "V4++ Styles",
+        }
+    }
+
+    fn from_script_type(s: &str) -> Option<SsaDialect> {
+        [SsaDialect::V4, SsaDialect::V4Plus, SsaDialect::V4PlusPlus]
+            .iter()
+            .cloned()
+            .find(|d| d.script_type().eq_ignore_ascii_case(s.trim()))
+    }
+
+    fn from_styles_section(s: &str) -> Option<SsaDialect> {
+        [SsaDialect::V4, SsaDialect::V4Plus, SsaDialect::V4PlusPlus]
+            .iter()
+            .cloned()
+            .find(|d| d.styles_section().eq_ignore_ascii_case(s.trim()))
+    }
+
+    fn style_fields(self) -> &'static [&'static str] {
+        match self {
+            SsaDialect::V4 => V4_STYLE_FIELDS,
+            SsaDialect::V4Plus => V4PLUS_STYLE_FIELDS,
+            SsaDialect::V4PlusPlus => V4PLUSPLUS_STYLE_FIELDS,
+        }
+    }
+
+    fn event_fields(self) -> &'static [&'static str] {
+        match self {
+            SsaDialect::V4 => &[
+                "Marked", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+            ],
+            SsaDialect::V4Plus => &[
+                "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+            ],
+            SsaDialect::V4PlusPlus => &[
+                "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginT", "MarginB", "Effect", "Text",
+            ],
+        }
+    }
+}
+
+/// Returns the section name of a line like `[Events]`.
+fn section_header(trimmed_line: &str) -> Option<&str> {
+    trimmed_line.strip_prefix('[')?.strip_suffix(']')
+}
+
+/// Determines the dialect by `ScriptType:`, then by the name of the styles section and then by the
+/// `Marked` field of the events (defaults to `V4Plus`).
+fn detect_dialect(s: &str) -> SsaDialect {
+    let mut section = "";
+    let mut section_dialect = None;
+    let mut has_marked_field = false;
+    for line in s.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = section_header(trimmed) {
+            section = name;
+            section_dialect = section_dialect.or_else(|| SsaDialect::from_styles_section(name));
+        } else if section == "Script Info" {
+            if let Some(dialect) = trimmed.strip_prefix("ScriptType:").and_then(SsaDialect::from_script_type) {
+                return dialect;
+            }
+        } else if section == "Events" {
+            if let Some(fields) = trimmed.strip_prefix("Format:") {
+                has_marked_field |= fields.split(',').any(|f| f.trim() == "Marked");
+            }
+        }
+    }
+    section_dialect.unwrap_or(if has_marked_field { SsaDialect::V4 } else { SsaDialect::V4Plus })
+}
+
+/// Rewrites the values of a `Style:` or event line (everything after the colon) from the `source_fields`
+/// to the fields of `to`.
+fn convert_dialect_fields(values: &str, source_fields: &[String], target_fields: &[&str], from: SsaDialect, to: SsaDialect) -> String {
+    let values: Vec<&str> = values.trim_start().splitn(source_fields.len().max(1), ',').collect();
+    let get = |name: &str| {
+        source_fields.iter().position(|f| f == name).and_then(|i| values.get(i)).map(|v| {
+            if name == "Text" {
+                v.to_string()
+            } else {
+                v.trim().to_string()
+            }
+        })
+    };
+
+    let convert_value = |name: &str| -> String {
+        let value = match name {
+            "OutlineColour" => get(name).or_else(|| get("TertiaryColour")),
+            "TertiaryColour" => get(name).or_else(|| get("OutlineColour")),
+            "Name" => get(name).or_else(|| get("Actor")),
+            "MarginV" => get(name).or_else(|| get("MarginB")),
+            "MarginT" | "MarginB" => get(name).or_else(|| get("MarginV")),
+            "Layer" => get(name).or_else(|| get("Marked").map(|m| m.trim_start_matches("Marked=").to_string())),
+            "Marked" => get(name).or_else(|| Some("Marked=0".to_string())),
+            "Alignment" => get(name).map(|a| {
+                let placement = match from {
+                    SsaDialect::V4 => a.parse().ok().and_then(Placement::from_legacy_alignment),
+                    _ => a.parse().ok().and_then(Placement::from_an),
+                };
+                match (placement, to) {
+                    (Some(p), SsaDialect::V4) => p.to_legacy_alignment().to_string(),
+                    (Some(p), _) => p.to_an().to_string(),
+                    (None, _) => a,
+                }
+            }),
+            _ => get(name),
+        };
+        // `.ssa` files often store colors as decimal numbers
+        let value = match value {
+            Some(c) if name.ends_with("Colour") && to != SsaDialect::V4 => Some(AssColor::parse(&c).map(AssColor::to_style_string).unwrap_or(c)),
+            value => value,
+        };
+        value.unwrap_or_else(|| match name {
+            "ScaleX" | "ScaleY" => "100".to_string(),
+            "Encoding" => "1".to_string(),
+            "Text" => String::new(),
+            _ => "0".to_string(),
+        })
+    };
+
+    target_fields.iter().map(|name| convert_value(name)).collect::<Vec<String>>().join(",")
+}
+
+/// Converts the `[Script Info]`, styles and events sections of a file to another dialect.
+fn convert_dialect(s: &str, from: SsaDialect, to: SsaDialect) -> String {
+    let has_script_type = s.lines().any(|line| line.trim().starts_with("ScriptType:"));
+    let mut result = String::with_capacity(s.len());
+    let mut section = String::new();
+    let mut source_fields: Vec<String> = Vec::new();
+
+    for (line, newl) in get_lines_non_destructive(s) {
+        let trimmed = line.trim();
+        let is_styles_section = SsaDialect::from_styles_section(&section).is_some();
+        let (key, values) = trimmed.split_once(':').unwrap_or(("", ""));
+
+        let converted = if let Some(name) = section_header(trimmed) {
+            section = name.to_string();
+            source_fields.clear();
+            if SsaDialect::from_styles_section(name).is_some() {
+                Some(format!("[{}]", to.styles_section()))
+            } else if name == "Script Info" && !has_script_type {
+                Some(format!("{}{}ScriptType: {}", line, newl, to.script_type()))
+            } else {
+                None
+            }
+        } else if section == "Script Info" && key == "ScriptType" {
+            Some(format!("ScriptType: {}", to.script_type()))
+        } else if (is_styles_section || section == "Events") && key == "Format" {
+            source_fields = values.split(',').map(|f| f.trim().to_string()).collect();
+            let target_fields = if is_styles_section { to.style_fields() } else { to.event_fields() };
+            Some(format!("Format: {}", target_fields.join(", ")))
+        } else if is_styles_section && key == "Style" && !source_fields.is_empty() {
+            let values = line.split_once(':').map_or("", |(_, v)| v);
+            Some(format!(
+                "Style: {}",
+                convert_dialect_fields(values, &source_fields, to.style_fields(), from, to)
+            ))
+        } else if section == "Events" && !source_fields.is_empty() && key != "Format" && EVENT_LINE_KEYS.contains(&key) {
+            let (prefix, values) = line.split_once(':').unwrap_or(("", ""));
+            Some(format!(
+                "{}: {}",
+                prefix.trim(),
+                convert_dialect_fields(values, &source_fields, to.event_fields(), from, to)
+            ))
+        } else {
+            None
+        };
+
+        result.push_str(converted.as_deref().unwrap_or(&line));
+        result.push_str(&newl);
+    }
+    result
+}
+
+/// Returns the order in which the events should be written for `SsaFile::sort_events()`.
+///
+/// `read_order` is the original rendering order of each event. An event can only be written once all events
+/// that are drawn before it in the same layer and overlap it in time are written.
+fn sorted_event_order(events: &[SsaEvent], read_order: &[i64]) -> Vec<usize> {
+    let key = |i: usize| (events.get(i).map(|e| e.timespan.start), read_order.get(i).copied().unwrap_or(0), i);
+
+    // `blockers[j]` counts the events which have to be written before `j`, `successors[i]` the events waiting for `i`
+    let mut blockers = vec![0usize; events.len()];
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); events.len()];
+    let mut by_start: Vec<usize> = (0..events.len()).collect();
+    by_start.sort_by_key(|&i| key(i));
+    for (pos, &i) in by_start.iter().enumerate() {
+        let a = match events.get(i) {
+            Some(a) => a,
+            None => continue,
+        };
+        for &j in by_start.iter().skip(pos + 1) {
+            let b = match events.get(j) {
+                Some(b) if b.timespan.start < a.timespan.end => b,
+                Some(_) | None => break,
+            };
+            if a.layer != b.layer || b.timespan.end <= b.timespan.start || a.timespan.end <= a.timespan.start {
+                continue;
+            }
+            let (first, second) = if (read_order.get(i), i) < (read_order.get(j), j) {
+                (i, j)
+            } else {
+                (j, i)
+            };
+            if let (Some(s), Some(b)) = (successors.get_mut(first), blockers.get_mut(second)) {
+                s.push(second);
+                *b += 1;
+            }
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<_>> = (0..events.len())
+        .filter(|&i| blockers.get(i) == Some(&0))
+        .map(|i| Reverse(key(i)))
+        .collect();
+    let mut result = Vec::with_capacity(events.len());
+    while let Some(Reverse((_, _, i))) = ready.pop() {
+        result.push(i);
+        for &j in successors.get(i).map_or(&[][..], Vec::as_slice) {
+            if let Some(b) = blockers.get_mut(j) {
+                *b -= 1;
+                if *b == 0 {
+                    ready.push(Reverse(key(j)));
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Reorders the `Dialogue:` lines of a file (which belong to `events`) for `SsaFile::sort_events()`.
+fn sort_event_lines(s: &str, events: &[SsaEvent]) -> String {
+    let mut lines = get_lines_non_destructive(s);
+    let mut section = String::new();
+    let mut read_order_idx = None;
+    let mut dialogue_lines = Vec::new();
+    for (line_idx, (line, _)) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(name) = section_header(trimmed) {
+            section = name.to_string();
+        } else if section == "Events" && trimmed.starts_with("Format:") {
+            read_order_idx = trimmed.trim_start_matches("Format:").split(',').position(|f| f.trim() == "ReadOrder");
+        } else if section == "Events" && trimmed.starts_with("Dialogue:") {
+            dialogue_lines.push(line_idx);
+        }
+    }
+    if dialogue_lines.len() != events.len() {
+        return s.to_string();
+    }
+
+    let read_order: Vec<i64> = dialogue_lines
+        .iter()
+        .enumerate()
+        .map(|(event_idx, &line_idx)| {
+            let values = lines.get(line_idx).and_then(|(line, _)| line.split_once(':')).map_or("", |(_, v)| v);
+            read_order_idx
+                .and_then(|idx| values.split(',').nth(idx))
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(event_idx as i64)
+        })
+        .collect();
+
+    let sorted: Vec<(String, String)> = sorted_event_order(events, &read_order)
+        .into_iter()
+        .filter_map(|event_idx| dialogue_lines.get(event_idx).and_then(|&line_idx| lines.get(line_idx)).cloned())
+        .collect();
+    for (&line_idx, (line, _)) in dialogue_lines.iter().zip(sorted) {
+        if let Some((target, _)) = lines.get_mut(line_idx) {
+            *target = line;
+        }
+    }
+    lines.into_iter().map(|(line, newl)| line + &newl).collect()
+}
+
+/// Returns the byte ranges of the style names of all `\rStyle` tags in the override blocks of an event text.
+fn reset_style_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+    let mut in_block = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => in_block = true,
+            '}' => in_block = false,
+            '\\' if in_block && text.get(i + 1..).is_some_and(|rest| rest.starts_with('r')) => {
+                let start = i + 2;
+                let len = text.get(start..).map_or(0, |rest| rest.find(['\\', '}']).unwrap_or(rest.len()));
+                if len > 0 {
+                    result.push((start, start + len));
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+/// The style lines and event fields of a file for `SsaFile::clean_styles()`.
+#[derive(Default)]
+struct StyleUsage {
+    /// Line index, name and the other (canonicalized) fields of every `Style:` line
+    styles: Vec<(usize, String, String)>,
+
+    /// Line index and the position of the `Style` field of every event line
+    events: Vec<(usize, usize, usize)>,
+}
+
+impl StyleUsage {
+    fn collect(lines: &[(String, String)]) -> StyleUsage {
+        let mut usage = StyleUsage::default();
+        let mut section = String::new();
+        let mut format_fields: Vec<String> = Vec::new();
+        for (line_idx, (line, _)) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if let Some(name) = section_header(trimmed) {
+                section = name.to_string();
+                format_fields.clear();
+                continue;
+            }
+            let (key, values) = match line.split_once(':') {
+                Some((key, values)) => (key.trim(), values),
+                None => continue,
+            };
+            let is_styles_section = SsaDialect::from_styles_section(&section).is_some();
+            if key == "Format" && (is_styles_section || section == "Events") {
+                format_fields = values.split(',').map(|f| f.trim().to_string()).collect();
+            } else if is_styles_section && key == "Style" {
+                let name_idx = format_fields.iter().position(|f| f == "Name").unwrap_or(0);
+                let fields = values
+                    .split(',')
+                    .zip(format_fields.iter().map(String::as_str).chain(std::iter::repeat("")));
+                let (mut name, mut other) = (String::new(), Vec::new());
+                for (i, (value, field)) in fields.enumerate() {
+                    if i == name_idx {
+                        name = value.trim().to_string();
+                    } else {
+                        other.push(canonicalize_style_value(value.trim(), field));
+                    }
+                }
+                usage.styles.push((line_idx, name, other.join(",")));
+            } else if section == "Events" && key != "Format" && EVENT_LINE_KEYS.contains(&key) {
+                if let Some(style_idx) = format_fields.iter().position(|f| f == "Style") {
+                    usage.events.push((line_idx, style_idx, format_fields.len()));
+                }
+            }
+        }
+        usage
+    }
+}
+
+/// A `Style:` line and the fields of the `Format:` line of its section.
+struct StyleLine {
+    line_idx: usize,
+    name: String,
+
+    /// Everything after the colon
+    values: String,
+    fields: Vec<String>,
+}
+
+/// Returns the `Format:` line of the styles section (its index and fields) and all `Style:` lines.
+fn find_style_lines(lines: &[(String, String)]) -> (Option<(usize, Vec<String>)>, Vec<StyleLine>) {
+    let mut format = None;
+    let mut styles = Vec::new();
+    let mut is_styles_section = false;
+    for (line_idx, (line, _)) in lines.iter().enumerate() {
+        if let Some(name) = section_header(line.trim()) {
+            is_styles_section = SsaDialect::from_styles_section(name).is_some();
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, values)) if is_styles_section && key.trim() == "Format" => {
+                format = Some((line_idx, values.split(',').map(|f| f.trim().to_string()).collect()));
+            }
+            Some((key, values)) if is_styles_section && key.trim() == "Style" => {
+                let fields: Vec<String> = format
+                    .as_ref()
+                    .map(|(_, fields): &(usize, Vec<String>)| fields.clone())
+                    .unwrap_or_default();
+                let name_idx = fields.iter().position(|f| f == "Name").unwrap_or(0);
+                styles.push(StyleLine {
+                    line_idx,
+                    name: values.split(',').nth(name_idx).unwrap_or("").trim().to_string(),
+                    values: values.to_string(),
+                    fields,
+                });
+            }
+            _ => {}
+        }
+    }
+    (format, styles)
+}
+
+/// Returns the line ending, the index for new `Style:` lines and the style fields of a file (the styles section is
+/// created in front of the events if necessary).
+fn style_insert_position(
+    lines: &mut Vec<(String, String)>,
+    format: Option<(usize, Vec<String>)>,
+    styles: &[StyleLine],
+    dialect: SsaDialect,
+) -> (String, usize, Vec<String>) {
+    let newl = format
+        .as_ref()
+        .and_then(|&(idx, _)| lines.get(idx))
+        .map_or("\n", |(_, newl)| if newl.is_empty() { "\n" } else { newl.as_str() })
+        .to_string();
+    match format {
+        Some((format_idx, fields)) => (newl, styles.last().map_or(format_idx, |s| s.line_idx) + 1, fields),
+        None => {
+            let events_idx = lines.iter().position(|(line, _)| line.trim() == "[Events]").unwrap_or(lines.len());
+            let fields: Vec<String> = dialect.style_fields().iter().map(|f| f.to_string()).collect();
+            let section = vec![
+                (format!("[{}]", dialect.styles_section()), newl.clone()),
+                (format!("Format: {}", fields.join(", ")), newl.clone()),
+                (String::new(), newl.clone()),
+            ];
+            lines.splice(events_idx..events_idx, section);
+            (newl, events_idx + 2, fields)
+        }
+    }
+}
+
+/// Inserts a line (and adds a line ending to the previous line if it is the last line of the file).
+fn insert_line(lines: &mut Vec<(String, String)>, idx: usize, line: String, newl: &str) {
+    if let Some((_, previous_newl)) = idx.checked_sub(1).and_then(|i| lines.get_mut(i)) {
+        if previous_newl.is_empty() {
+            *previous_newl = newl.to_string();
+        }
+    }
+    lines.insert(idx, (line, newl.to_string()));
+}
+
+/// Replaces the `Style:` line with the name of `style` or adds it for `SsaFile::set_style()`.
+fn set_style_line(s: &str, style: &SsaStyle, dialect: SsaDialect) -> String {
+    let mut lines = get_lines_non_destructive(s);
+    let (format, styles) = find_style_lines(&lines);
+    match styles.iter().find(|line| line.name == style.name) {
+        Some(existing) => {
+            let values: Vec<&str> = existing.values.split(',').collect();
+            let fields: Vec<&str> = existing.fields.iter().map(String::as_str).collect();
+            if let Some((line, _)) = lines.get_mut(existing.line_idx) {
+                *line = format!("Style: {}", style.to_values(&fields, dialect, &values));
+            }
+        }
+        None => {
+            let (newl, insert_idx, fields) = style_insert_position(&mut lines, format, &styles, dialect);
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            insert_line(
+                &mut lines,
+                insert_idx,
+                format!("Style: {}", style.to_values(&fields, dialect, &[])),
+                &newl,
+            );
+        }
+    }
+    lines.into_iter().map(|(line, newl)| line + &newl).collect()
+}
+
+/// Removes the `Style:` line with the name `name` for `SsaFile::remove_style()`.
+fn remove_style_line(s: &str, name: &str) -> Option<String> {
+    let mut lines = get_lines_non_destructive(s);
+    let (_, styles) = find_style_lines(&lines);
+    let line_idx = styles.iter().find(|line| line.name == name)?.line_idx;
+    lines.remove(line_idx);
+    Some(lines.into_iter().map(|(line, newl)| line + &newl).collect())
+}
+
+/// Returns the index of the `[Script Info]` header and the line index, key and value of every line of the section
+/// (without comments).
+fn find_script_info_lines(lines: &[(String, String)]) -> (Option<usize>, Vec<(usize, String, String)>) {
+    let mut header = None;
+    let mut values = Vec::new();
+    let mut is_script_info = false;
+    for (line_idx, (line, _)) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(name) = section_header(trimmed) {
+            is_script_info = name == "Script Info";
+            if is_script_info && header.is_none() {
+                header = Some(line_idx);
+            }
+            continue;
+        }
+        if !is_script_info || trimmed.starts_with(';') || trimmed.starts_with("!:") {
+            continue;
+        }
+        if let Some((key, value)) = trimmed.split_once(':') {
+            values.push((line_idx, key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (header, values)
+}
+
+/// Sets (or with `None` removes) the values of `[Script Info]` keys for `SsaFile::set_script_info()`.
+fn set_script_info_values(s: &str, values: &[(&str, Option<String>)]) -> String {
+    let mut lines = get_lines_non_destructive(s);
+    let newl = lines
+        .first()
+        .map_or("\n", |(_, newl)| if newl.is_empty() { "\n" } else { newl.as_str() })
+        .to_string();
+    for (key, value) in values {
+        let (header, existing) = find_script_info_lines(&lines);
+        let mut matching: Vec<usize> = existing.iter().filter(|(_, k, _)| k == key).map(|&(line_idx, _, _)| line_idx).collect();
+        let value = value.as_ref().map(|value| value.replace(['\n', '\r'], ""));
+        match (value, matching.first().copied()) {
+            (Some(value), Some(line_idx)) => {
+                if let Some((line, _)) = lines.get_mut(line_idx) {
+                    *line = format!("{}: {}", key, value);
+                }
+                // duplicates of the key would override the new value
+                matching.remove(0);
+            }
+            (Some(value), None) => {
+                let line = format!("{}: {}", key, value);
+                match (existing.last(), header) {
+                    (Some(&(last_idx, _, _)), _) => insert_line(&mut lines, last_idx + 1, line, &newl),
+                    (None, Some(header_idx)) => insert_line(&mut lines, header_idx + 1, line, &newl),
+                    (None, None) => {
+                        let mut section = vec![("[Script Info]".to_string(), newl.clone()), (line, newl.clone())];
+                        if !lines.is_empty() {
+                            section.push((String::new(), newl.clone()));
+                        }
+                        lines.splice(0..0, section);
+                        continue;
+                    }
+                }
+            }
+            (None, _) => {}
+        }
+        for line_idx in matching.into_iter().rev() {
+            lines.remove(line_idx);
+        }
+    }
+    lines.into_iter().map(|(line, newl)| line + &newl).collect()
+}
+
+/// Copies the `Style:` lines of `source` into `target` for `SsaFile::import_styles()`.
+fn import_style_lines(target: &str, source: &str, from: SsaDialect, to: SsaDialect, conflicts: &SsaStyleConflict) -> (String, Vec<(String, String)>) {
+    let mut lines = get_lines_non_destructive(target);
+    let (_, source_styles) = find_style_lines(&get_lines_non_destructive(source));
+    let (format, target_styles) = find_style_lines(&lines);
+
+    let (newl, mut insert_idx, target_fields) = style_insert_position(&mut lines, format, &target_styles, to);
+    let target_field_names: Vec<&str> = target_fields.iter().map(String::as_str).collect();
+
+    let mut existing: Vec<(String, Option<usize>)> = target_styles.iter().map(|s| (s.name.clone(), Some(s.line_idx))).collect();
+    let mut names = Vec::new();
+    for style in source_styles {
+        let existing_idx = existing.iter().find(|(name, _)| *name == style.name).map(|&(_, idx)| idx);
+        let new_name = match (existing_idx, conflicts) {
+            (Some(_), SsaStyleConflict::KeepExisting) => {
+                names.push((style.name.clone(), style.name));
+                continue;
+            }
+            (Some(_), SsaStyleConflict::Rename(suffix)) => (1..)
+                .map(|i| {
+                    if i == 1 {
+                        format!("{}{}", style.name, suffix)
+                    } else {
+                        format!("{}{}{}", style.name, suffix, i)
+                    }
+                })
+                .find(|candidate| !existing.iter().any(|(name, _)| name == candidate))
+                .unwrap_or_default(),
+            (Some(_), SsaStyleConflict::Replace) | (None, _) => style.name.clone(),
+        };
+
+        let name_idx = style.fields.iter().position(|f| f == "Name").unwrap_or(0);
+        let values: Vec<String> = style
+            .values
+            .split(',')
+            .enumerate()
+            .map(|(i, v)| if i == name_idx { new_name.clone() } else { v.to_string() })
+            .collect();
+        let line = format!(
+            "Style: {}",
+            convert_dialect_fields(&values.join(","), &style.fields, &target_field_names, from, to)
+        );
+
+        match (existing_idx, conflicts) {
+            (Some(Some(line_idx)), SsaStyleConflict::Replace) => {
+                if let Some((target, _)) = lines.get_mut(line_idx) {
+                    *target = line;
+                }
+            }
+            _ => {
+                insert_line(&mut lines, insert_idx, line, &newl);
+                insert_idx += 1;
+                existing.push((new_name.clone(), None));
+            }
+        }
+        names.push((style.name, new_name));
+    }
+
+    (lines.into_iter().map(|(line, newl)| line + &newl).collect(), names)
+}
+
+/// Merges identical and removes unused `Style:` lines for `SsaFile::clean_styles()`.
+fn clean_style_lines(s: &str) -> (String, SsaStyleCleanup) {
+    let mut lines = get_lines_non_destructive(s);
+    let usage = StyleUsage::collect(&lines);
+
+    // split the event lines into their fields (the text may contain commas)
+    let event_fields = |line: &str, field_count: usize| -> Option<(String, Vec<String>)> {
+        let (prefix, values) = line.split_once(':')?;
+        Some((prefix.to_string(), values.splitn(field_count.max(1), ',').map(str::to_string).collect()))
+    };
+    let mut used: Vec<String> = Vec::new();
+    for &(line_idx, style_idx, field_count) in &usage.events {
+        if let Some((_, fields)) = lines.get(line_idx).and_then(|(line, _)| event_fields(line, field_count)) {
+            used.extend(fields.get(style_idx).map(|style| style.trim().to_string()));
+            if let Some(text) = fields.last() {
+                used.extend(
+                    reset_style_ranges(text)
+                        .into_iter()
+                        .filter_map(|(start, end)| text.get(start..end))
+                        .map(str::to_string),
+                );
+            }
+        }
+    }
+    if used.iter().any(|name| !usage.styles.iter().any(|(_, style, _)| style == name)) {
+        used.push("Default".to_string());
+    }
+
+    // every style is replaced by the first style with the same fields
+    let kept_name = |fields: &str| usage.styles.iter().find(|(_, _, f)| f == fields).map_or("", |(_, name, _)| name.as_str());
+    let mut cleanup = SsaStyleCleanup::default();
+    let mut renames: Vec<(String, String)> = Vec::new();
+    let mut removed_lines = Vec::new();
+    for (line_idx, name, fields) in &usage.styles {
+        let kept = kept_name(fields);
+        let group_used = usage.styles.iter().any(|(_, n, f)| f == fields && used.contains(n));
+        if !group_used {
+            cleanup.removed.push(name.clone());
+            removed_lines.push(*line_idx);
+        } else if kept != name {
+            cleanup.merged.push((name.clone(), kept.to_string()));
+            renames.push((name.clone(), kept.to_string()));
+            removed_lines.push(*line_idx);
+        }
+    }
+    let rename = |name: &str| renames.iter().find(|(from, _)| from == name.trim()).map(|(_, to)| to.clone());
+
+    for &(line_idx, style_idx, field_count) in &usage.events {
+        let line = match lines.get_mut(line_idx) {
+            Some((line, _)) => line,
+            None => continue,
+        };
+        if let Some((prefix, mut fields)) = event_fields(line, field_count) {
+            if let Some(style) = fields.get_mut(style_idx) {
+                if let Some(new_name) = rename(style) {
+                    *style = new_name;
+                }
+            }
+            if let Some(text) = fields.last_mut() {
+                for (start, end) in reset_style_ranges(text).into_iter().rev() {
+                    if let Some(new_name) = text.get(start..end).and_then(rename) {
+                        text.replace_range(start..end, &new_name);
+                    }
+                }
+            }
+            *line = format!("{}:{}", prefix, fields.join(","));
+        }
+    }
+
+    let result = lines
+        .into_iter()
+        .enumerate()
+        .filter(|(line_idx, _)| !removed_lines.contains(line_idx))
+        .map(|(_, (line, newl))| line + &newl)
+        .collect();
+    (result, cleanup)
+}
+
+/// Normalizes a whole file for `SsaFile::to_deterministic_data()`.
+fn canonicalize_ssa(s: &str) -> String {
+    let (_, s) = split_bom(s);
+    let mut sections: Vec<(String, Vec<String>)> = vec![(String::new(), Vec::new())];
+    let mut format_fields: Vec<String> = Vec::new();
+
+    for line in s.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(name) = section_header(trimmed) {
+            sections.push((name.trim().to_string(), Vec::new()));
+            format_fields.clear();
+            continue;
+        }
+        let section_name = sections.last().map_or("", |(name, _)| name.as_str());
+        let is_styles_section = SsaDialect::from_styles_section(section_name).is_some();
+
+        let normalized = match trimmed.split_once(':') {
+            Some((key, value)) if !trimmed.starts_with(';') && !trimmed.starts_with("!:") => {
+                let key = key.trim();
+                if key == "Format" {
+                    format_fields = value.split(',').map(|f| f.trim().to_string()).collect();
+                    format!("Format: {}", format_fields.join(", "))
+                } else if is_styles_section && key == "Style" {
+                    let values = value
+                        .split(',')
+                        .zip(format_fields.iter().map(String::as_str).chain(std::iter::repeat("")));
+                    let values: Vec<String> = values.map(|(v, field)| canonicalize_style_value(v.trim(), field)).collect();
+                    format!("Style: {}", values.join(","))
+                } else if section_name == "Events" && EVENT_LINE_KEYS.contains(&key) {
+                    // the text (last field) may contain commas and is kept as it is
+                    let value = line.split_once(':').map_or("", |(_, value)| value);
+                    let mut values: Vec<&str> = value.trim_start().splitn(format_fields.len().max(1), ',').collect();
+                    let text = values.pop().unwrap_or("");
+                    let fields = values.iter().map(|v| v.trim()).chain(once(text));
+                    format!("{}: {}", key, fields.collect::<Vec<&str>>().join(","))
+                } else {
+                    format!("{}: {}", key, value.trim())
+                }
+            }
+            _ => trimmed.to_string(),
+        };
+        if let Some((_, lines)) = sections.last_mut() {
+            lines.push(normalized);
+        }
+    }
+
+    for (name, lines) in &mut sections {
+        if name == "Script Info" {
+            lines.sort_by_cached_key(|line| {
+                let key = line.split(':').next().unwrap_or("").to_string();
+                let is_comment = line.starts_with(';') || line.starts_with("!:");
+                let known_idx = SCRIPT_INFO_KEYS.iter().position(|&k| k == key);
+                (
+                    !is_comment,
+                    known_idx.map_or(1, |_| 0),
+                    known_idx.unwrap_or(0),
+                    if known_idx.is_some() { String::new() } else { key },
+                )
+            });
+        }
+    }
+
+    let section_strings = sections
+        .into_iter()
+        .filter(|(name, lines)| !name.is_empty() || !lines.is_empty())
+        .map(|(name, lines)| {
+            let header = if name.is_empty() { String::new() } else { format!("[{}]\n", name) };
+            lines.iter().fold(header, |acc, line| acc + line + "\n")
+        });
+    section_strings.collect::<Vec<String>>().join("\n")
+}
+
+/// Writes a field of a `Style:` line in a fixed notation (colors as `&HAABBGGRR`, numbers without unnecessary decimals).
+fn canonicalize_style_value(value: &str, field: &str) -> String {
+    if field.ends_with("Colour") {
+        if let Some(color) = AssColor::parse(value) {
+            return color.to_style_string();
+        }
+    } else if field != "Name" && field != "Fontname" {
+        if let Ok(n) = value.parse::<f64>() {
+            return format_ssa_number(n);
+        }
+    }
+    value.to_string()
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// SSA styles
+
+/// What `SsaFile::import_styles()` does with a style whose name already exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SsaStyleConflict {
+    /// Keep the existing style and don't import the other one.
+    KeepExisting,
+
+    /// Replace the existing style by the imported one.
+    Replace,
+
+    /// Import the style with the suffix appended to its name (and a number, if that name exists too).
+    Rename(String),
+}
+
+/// The changes made by `SsaFile::clean_styles()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SsaStyleCleanup {
+    /// Styles that were merged into an identical style, as `(removed name, kept name)`.
+    pub merged: Vec<(String, String)>,
+
+    /// Styles that were removed because no event uses them.
+    pub removed: Vec<String>,
+}
+
+/// A style definition (a `Style:` line in the `[V4+ Styles]` section).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsaStyle {
+    /// The name which events use to refer to this style.
+    pub name: String,
+
+    /// Name of the font.
+    pub fontname: String,
+
+    /// Font size.
+    pub fontsize: f64,
+
+    /// Color of the text.
+    pub primary_colour: AssColor,
+
+    /// Color of the not yet highlighted text in karaoke effects.
+    pub secondary_colour: AssColor,
+
+    /// Color of the text outline.
+    pub outline_colour: AssColor,
+
+    /// Color of the shadow (or of the box for `border_style == 3`).
+    pub back_colour: AssColor,
+
+    /// Bold text.
+    pub bold: bool,
+
+    /// Italic text.
+    pub italic: bool,
+
+    /// Underlined text.
+    pub underline: bool,
+
+    /// Struck out text.
+    pub strike_out: bool,
+
+    /// Horizontal font scaling in percent.
+    pub scale_x: f64,
+
+    /// Vertical font scaling in percent.
+    pub scale_y: f64,
+
+    /// Extra space between characters in pixels.
+    pub spacing: f64,
+
+    /// Rotation of the text in degrees.
+    pub angle: f64,
+
+    /// `1` for outline and drop shadow, `3` for an opaque box.
+    pub border_style: i32,
+
+    /// Width of the outline in pixels.
+    pub outline: f64,
+
+    /// Depth of the drop shadow in pixels.
+    pub shadow: f64,
+
+    /// Alignment on the numpad layout (`1` = bottom left, `5` = center, `9` = top right).
+    pub alignment: i32,
+
+    /// Left margin in pixels.
+    pub margin_l: i32,
+
+    /// Right margin in pixels.
+    pub margin_r: i32,
+
+    /// Vertical margin in pixels.
+    pub margin_v: i32,
+
+    /// Font character set (`1` is the default).
+    pub encoding: i32,
+}
+
+impl Default for SsaStyle {
+    /// The `Default` style as generated by Aegisub.
+    fn default() -> SsaStyle {
+        SsaStyle {
+            name: "Default".to_string(),
+            fontname: "Arial".to_string(),
+            fontsize: 20.0,
+            primary_colour: AssColor::from_rgb(255, 255, 255),
+            secondary_colour: AssColor::from_rgb(255, 0, 0),
+            outline_colour: AssColor::from_rgb(0, 0, 0),
+            back_colour: AssColor::from_rgb(0, 0, 0),
+            bold: false,
+            italic: false,
+            underline: false,
+            strike_out: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: 1,
+            outline: 2.0,
+            shadow: 2.0,
+            alignment: 2,
+            margin_l: 10,
+            margin_r: 10,
+            margin_v: 10,
+            encoding: 1,
+        }
+    }
+}
+
+/// The `Format:` line of the `[V4+ Styles]` section which matches `SsaStyle::to_v4plus_line()`.
+const V4PLUS_STYLES_FORMAT: &str = "Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+
+/// Formats a number without a fractional part if possible (`20.0` -> `"20"`).
+pub(crate) fn format_ssa_number(n: f64) -> String {
+    if n.fract() == 0.0 {
+        format!("{}", n as i64)
+    } else {
+        format!("{}", n)
+    }
+}
+
+impl SsaStyle {
+    /// Returns the `Style:` line for the `[V4+ Styles]` section (without newline).
+    pub fn to_v4plus_line(&self) -> String {
+        format!("Style: {}", self.to_values(V4PLUS_STYLE_FIELDS, SsaDialect::V4Plus, &[]))
+    }
+
+    /// Reads the values of a `Style:` line (everything after the colon) in the order of the `Format:` fields.
+    ///
+    /// Missing fields and invalid values keep the values of `SsaStyle::default()`.
+    fn from_values(values: &str, fields: &[String], dialect: SsaDialect) -> SsaStyle {
+        let mut style = SsaStyle::default();
+        for (value, field) in values.split(',').map(str::trim).zip(fields) {
+            let number = |default: f64| value.parse::<f64>().ok().filter(|n| n.is_finite()).unwrap_or(default);
+            let integer = |default: i32| value.parse::<i32>().unwrap_or(default);
+            let flag = |default: bool| value.parse::<i32>().map_or(default, |v| v != 0);
+            let color = |default: AssColor| AssColor::parse(value).unwrap_or(default);
+            match field.as_str() {
+                "Name" => style.name = value.to_string(),
+                "Fontname" => style.fontname = value.to_string(),
+                "Fontsize" => style.fontsize = number(style.fontsize),
+                "PrimaryColour" => style.primary_colour = color(style.primary_colour),
+                "SecondaryColour" => style.secondary_colour = color(style.secondary_colour),
+                "OutlineColour" | "TertiaryColour" => style.outline_colour = color(style.outline_colour),
+                "BackColour" => style.back_colour = color(style.back_colour),
+                "Bold" => style.bold = flag(style.bold),
+                "Italic" => style.italic = flag(style.italic),
+                "Underline" => style.underline = flag(style.underline),
+                "StrikeOut" => style.strike_out = flag(style.strike_out),
+                "ScaleX" => style.scale_x = number(style.scale_x),
+                "ScaleY" => style.scale_y = number(style.scale_y),
+                "Spacing" => style.spacing = number(style.spacing),
+                "Angle" => style.angle = number(style.angle),
+                "BorderStyle" => style.border_style = integer(style.border_style),
+                "Outline" => style.outline = number(style.outline),
+                "Shadow" => style.shadow = number(style.shadow),
+                "Alignment" => {
+                    let placement = match dialect {
+                        SsaDialect::V4 => value.parse().ok().and_then(Placement::from_legacy_alignment),
+                        _ => value.parse().ok().and_then(Placement::from_an),
+                    };
+                    style.alignment = placement.map_or(style.alignment, |p| i32::from(p.to_an()));
+                }
+                "MarginL" => style.margin_l = integer(style.margin_l),
+                "MarginR" => style.margin_r = integer(style.margin_r),
+                "MarginV" | "MarginB" => style.margin_v = integer(style.margin_v),
+                "Encoding" => style.encoding = integer(style.encoding),
+                _ => {}
+            }
+        }
+        style
+    }
+
+    /// Returns the values of a `Style:` line in the order of the `Format:` fields; fields which don't exist in
+    /// `SsaStyle` (like `AlphaLevel`) keep their value in `previous`.
+    fn to_values(&self, fields: &[&str], dialect: SsaDialect, previous: &[&str]) -> String {
+        let flag = |b: bool| if b { "-1" } else { "0" }.to_string();
+        let n = format_ssa_number;
+        let values: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| match *field {
+                "Name" => self.name.clone(),
+                "Fontname" => self.fontname.clone(),
+                "Fontsize" => n(self.fontsize),
+                "PrimaryColour" => self.primary_colour.to_string(),
+                "SecondaryColour" => self.secondary_colour.to_string(),
+                "OutlineColour" | "TertiaryColour" => self.outline_colour.to_string(),
+                "BackColour" => self.back_colour.to_string(),
+                "Bold" => flag(self.bold),
+                "Italic" => flag(self.italic),
+                "Underline" => flag(self.underline),
+                "StrikeOut" => flag(self.strike_out),
+                "ScaleX" => n(self.scale_x),
+                "ScaleY" => n(self.scale_y),
+                "Spacing" => n(self.spacing),
+                "Angle" => n(self.angle),
+                "BorderStyle" => self.border_style.to_string(),
+                "Outline" => n(self.outline),
+                "Shadow" => n(self.shadow),
+                "Alignment" => match (dialect, u8::try_from(self.alignment).ok().and_then(Placement::from_an)) {
+                    (SsaDialect::V4, Some(placement)) => placement.to_legacy_alignment().to_string(),
+                    _ => self.alignment.to_string(),
+                },
+                "MarginL" => self.margin_l.to_string(),
+                "MarginR" => self.margin_r.to_string(),
+                "MarginV" | "MarginT" | "MarginB" => self.margin_v.to_string(),
+                "Encoding" => self.encoding.to_string(),
+                _ => previous.get(i).map_or("0", |value| value.trim()).to_string(),
+            })
+            .collect();
+        values.join(",")
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// ASS templates
+
+/// A single event for `AssTemplate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssTemplateEvent {
+    /// When the event is shown.
+    pub timespan: TimeSpan,
+
+    /// The text of the event; newlines are converted to `\N`.
+    pub text: String,
+
+    /// The name of the style (has to be defined in the template).
+    pub style: String,
+
+    /// An optional position in script pixels, written as `{\pos(x,y)}` override.
+    pub position: Option<(i32, i32)>,
+}
+
+/// Generates complete `.ass` files from a set of styles and events.
+///
+/// This is meant for tools (like speech recognition or translation pipelines) which produce styled subtitles from
+/// scratch.
+///
+/// ```
+/// use subparse::{AssTemplate, SsaStyle, SubtitleFileInterface};
+/// use subparse::timetypes::{TimePoint, TimeSpan};
+///
+/// let mut template = AssTemplate::new(vec![SsaStyle::default()]);
+/// template.add_event(TimeSpan::new(TimePoint::from_secs(1), TimePoint::from_secs(3)), "Hello!", "Default", None);
+/// let file = template.to_ssa_file().unwrap();
+/// assert_eq!(file.get_subtitle_entries().unwrap().len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssTemplate {
+    /// The `Title` in the `[Script Info]` section.
+    pub title: String,
+
+    /// The `PlayResX` and `PlayResY` values (the coordinate system of positions and margins).
+    pub play_res: (u32, u32),
+
+    /// The styles of the `[V4+ Styles]` section.
+    pub styles: Vec<SsaStyle>,
+
+    /// The events of the `[Events]` section.
+    pub events: Vec<AssTemplateEvent>,
+}
+
+impl AssTemplate {
+    /// Creates a template without events for a 1920x1080 script.
+    pub fn new(styles: Vec<SsaStyle>) -> AssTemplate {
+        AssTemplate {
+            title: String::new(),
+            play_res: (1920, 1080),
+            styles,
+            events: Vec::new(),
+        }
+    }
+
+    /// Appends an event.
+    pub fn add_event(&mut self, timespan: TimeSpan, text: &str, style: &str, position: Option<(i32, i32)>) {
+        self.events.push(AssTemplateEvent {
+            timespan,
+            text: text.to_string(),
+            style: style.to_string(),
+            position,
+        });
+    }
+
+    /// Checks that style names are unique and that every event refers to an existing style.
+    fn validate(&self) -> Result<()> {
+        for (i, style) in self.styles.iter().enumerate() {
+            if self.styles.iter().take(i).any(|s| s.name == style.name) {
+                return Err(SsaDuplicateStyle { name: style.name.clone() }.into());
+            }
+        }
+        for event in &self.events {
+            if !self.styles.iter().any(|s| s.name == event.style) {
+                return Err(SsaUnknownStyle { name: event.style.clone() }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the complete text of the `.ass` file.
+    pub fn to_ass_string(&self) -> SubtitleParserResult<String> {
+        self.validate().with_context(|_| crate::ErrorKind::CreationError)?;
+
+        let mut result = String::new();
+        result.push_str("[Script Info]\n; Script generated by subparse\n");
+        result.push_str(&format!("Title: {}\n", self.title));
+        result.push_str("ScriptType: v4.00+\nWrapStyle: 0\nScaledBorderAndShadow: yes\n");
+        result.push_str(&format!("PlayResX: {}\nPlayResY: {}\n", self.play_res.0, self.play_res.1));
+
+        result.push_str("\n[V4+ Styles]\n");
+        result.push_str(V4PLUS_STYLES_FORMAT);
+        result.push('\n');
+        for style in &self.styles {
+            result.push_str(&style.to_v4plus_line());
+            result.push('\n');
+        }
+
+        result.push_str("\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n");
+        for event in &self.events {
+            let position = event.position.map(|(x, y)| format!("{{\\pos({},{})}}", x, y)).unwrap_or_default();
+            result.push_str(&format!(
+                "Dialogue: 0,{},{},{},,0,0,0,,{}{}\n",
+                timepoint_to_ssa_string(event.timespan.start, RoundingMode::default()),
+                timepoint_to_ssa_string(event.timespan.end, RoundingMode::default()),
+                ass_event_field(&event.style),
+                position,
+                ass_event_text(&event.text)
+            ));
+        }
+        Ok(result)
+    }
+
+    /// Creates the `.ass` file.
+    pub fn to_ssa_file(&self) -> SubtitleParserResult<SsaFile> {
+        SsaFile::parse(&self.to_ass_string()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = "[Script Info]\r\nTitle: test\r\n\r\n[Events]\r\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,first\r\nComment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,a comment\r\nDialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,second\r\nDialogue: 0,0:00:05.00,0:00:06.00,Default,,0,0,0,,third";
+
+    #[test]
+    fn ssa_retain_entries() {
+        let mut file = SsaFile::parse(TEST_FILE).unwrap();
+        file.retain_entries(|e| e.line.as_ref().unwrap() != "second").unwrap();
+
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,first"));
+        assert!(data.contains("\nComment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,a comment\n"));
+        assert!(!data.contains("second"));
+        assert!(data.ends_with("\nDialogue: 0,0:00:05.00,0:00:06.00,Default,,0,0,0,,third"));
+
+        let timespan = TimeSpan::new(TimePoint::from_secs(4), TimePoint::from_secs(7));
+        file.drop_entries_in(timespan).unwrap();
+        let texts: Vec<_> = file.get_subtitle_entries().unwrap().into_iter().map(|e| e.line.unwrap()).collect();
+        assert_eq!(texts, vec!["first".to_string()]);
+    }
+
+    #[test]
+    fn ssa_short_dialogue_line() {
+        let input = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,first\nDialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,second";
+        let (mut file, warnings) = SsaFile::parse_with_warnings(input).unwrap();
+        assert_eq!(warnings.iter().map(|w| w.line_num).collect::<Vec<_>>(), vec![Some(2)]);
+        assert_eq!(file.get_subtitle_entries().unwrap().len(), 2);
+
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\nDialogue: 0,\n"));
+        assert!(!data.contains("first"));
+        assert!(data.ends_with("\nDialogue: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,second"));
+    }
+
+    #[test]
+    fn ssa_retime_dialogue_only() {
+        let input = "[Events]\nFormat: Layer, Start, End, Style, Actor, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,Bob,0,0,0,,Hi!\nDialogue: 2,0:00:01.00,0:00:02.00,Sign,,0,0,0,,{\\pos(10,10)}EXIT";
+        let mut file = SsaFile::parse(input).unwrap();
+
+        let events = file.get_events();
+        assert_eq!(
+            (events[0].layer, events[0].style.as_str(), events[0].name.as_str()),
+            (0, "Default", "Bob")
+        );
+        assert_eq!((events[1].layer, events[1].is_typesetting()), (2, true));
+
+        file.retime_events(|e| !e.is_typesetting(), |ts| ts + TimeDelta::from_secs(1));
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\nDialogue: 0,0:00:02.00,0:00:03.00,Default,Bob,0,0,0,,Hi!\n"));
+        assert!(data.ends_with("\nDialogue: 2,0:00:01.00,0:00:02.00,Sign,,0,0,0,,{\\pos(10,10)}EXIT"));
+    }
+
+    #[test]
+    fn ssa_typed_event_fields() {
+        let input =
+            "[Script Info]\nScriptType: v4.00\n\n[Events]\nFormat: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                     Dialogue: Marked=0,0:00:01.00,0:00:02.00,Default,Bob,0000,0010,0000,Banner;5,Hi!";
+        let mut file = SsaFile::parse(input).unwrap();
+        let event = &file.get_events()[0];
+        assert_eq!((event.layer, event.name.as_str(), event.effect.as_str()), (0, "Bob", "Banner;5"));
+        assert_eq!((event.margin_l, event.margin_r, event.margin_v), (0, 10, 0));
+
+        for mut event in file.entries_by_actor("Bob") {
+            event.set_layer(3);
+            event.set_actor("Alice & Bob").unwrap();
+            event.set_margins(20, 10, 5);
+            event.set_effect("").unwrap();
+        }
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.ends_with("\nDialogue: Marked=0,0:00:01.00,0:00:02.00,Default,Alice & Bob,0020,0010,0005,,Hi!"));
+        assert_eq!(file.events_mut()[0].margins(), (20, 10, 5));
+    }
+
+    #[test]
+    fn ssa_set_actor_rejects_commas() {
+        let mut file = SsaFile::parse(TEST_FILE).unwrap();
+        let data = file.to_data().unwrap();
+        for name in ["Alice, Bob", "Alice\nBob"].iter() {
+            let error = file.events_mut()[0].set_actor(name).unwrap_err();
+            assert_eq!(error.kind(), crate::ErrorKind::UpdatingEntriesError);
+        }
+        assert_eq!(file.to_data().unwrap(), data);
+    }
+
+    #[test]
+    fn ssa_set_style_and_effect_reject_commas() {
+        let mut file = SsaFile::parse(TEST_FILE).unwrap();
+        let data = file.to_data().unwrap();
+        let invalid_field = |error: crate::Error| -> &'static str {
+            let error: &dyn failure::Fail = &error;
+            match error.iter_causes().find_map(|cause| cause.downcast_ref::<Error>()).map(Error::kind) {
+                Some(&SsaInvalidFieldValue { f, .. }) => f,
+                other => panic!("unexpected error {:?}", other),
+            }
+        };
+        let mut event = file.events_mut().remove(0);
+        assert_eq!(invalid_field(event.set_style("Sign,Top").unwrap_err()), "Style");
+        assert_eq!(invalid_field(event.set_style("Sign\r\n").unwrap_err()), "Style");
+        assert_eq!(invalid_field(event.set_effect("Scroll up;10,20").unwrap_err()), "Effect");
+        assert_eq!(invalid_field(event.set_effect("Banner\n").unwrap_err()), "Effect");
+        drop(event);
+        assert_eq!(file.to_data().unwrap(), data);
+
+        let mut event = file.events_mut().remove(0);
+        event.set_style("Sign").unwrap();
+        event.set_effect("Banner;5").unwrap();
+        let event = event.to_event();
+        assert_eq!((event.style.as_str(), event.effect.as_str()), ("Sign", "Banner;5"));
+    }
+
+    #[test]
+    fn ssa_karaoke_retiming() {
+        let input = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                     Dialogue: 0,0:00:10.00,0:00:11.00,Default,,0,0,0,,{\\k40}Hel{\\k60}lo\n\
+                     Dialogue: 0,0:00:12.00,0:00:13.00,Default,,0,0,0,,plain";
+        let mut file = SsaFile::parse(input).unwrap();
+        assert_eq!(file.get_events()[0].karaoke().duration(), TimeDelta::from_secs(1));
+
+        let transform = TimeTransform::change_fps(25.0, 50.0).then(TimeTransform::shift(TimeDelta::from_secs(1)));
+        for mut event in file.events_mut() {
+            event.retime_with_karaoke(&transform);
+        }
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\nDialogue: 0,0:00:06.00,0:00:06.50,Default,,0,0,0,,{\\k20}Hel{\\k30}lo\n"));
+        assert!(data.ends_with("\nDialogue: 0,0:00:07.00,0:00:07.50,Default,,0,0,0,,plain"));
+    }
+
+    #[test]
+    fn ssa_sort_events() {
+        let input = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                     Dialogue: 0,0:00:05.00,0:00:10.00,Default,,0,0,0,,a\n\
+                     Dialogue: 0,0:00:01.00,0:00:06.00,Default,,0,0,0,,b\n\
+                     Comment: 0,0:00:00.00,0:00:00.00,Default,,0,0,0,,comment\n\
+                     Dialogue: 1,0:00:02.00,0:00:03.00,Default,,0,0,0,,c\n\
+                     Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,d";
+        let mut file = SsaFile::parse(input).unwrap();
+        file.sort_events().unwrap();
+
+        // `b` overlaps `a` in the same layer, so it has to stay behind `a`
+        let texts: Vec<String> = file.get_events().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["d", "c", "a", "b"]);
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains(",,c\nComment: 0,0:00:00.00,0:00:00.00,Default,,0,0,0,,comment\nDialogue: 0,0:00:05.00"));
+        assert!(data.ends_with(",,b"));
+    }
+
+    #[test]
+    fn ssa_entries_by_style_and_actor() {
+        let input = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,NARRATOR,0,0,0,,a\nDialogue: 0,0:00:03.00,0:00:04.00,Songs,,0,0,0,Banner;10,b\nDialogue: 0,0:00:05.00,0:00:06.00,Default,Bob,0,0,0,,c";
+        let mut file = SsaFile::parse(input).unwrap();
+
+        assert_eq!(file.entries_by_style("Default").len(), 2);
+        assert_eq!(file.entries_by_effect("Banner").len(), 1);
+        for mut event in file.entries_by_actor("NARRATOR") {
+            let text = format!("{{\\i1}}{}", event.text());
+            event.set_text(text);
+        }
+
+        let texts: Vec<String> = file.get_events().into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["{\\i1}a".to_string(), "b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn ass_template() {
+        let sign = SsaStyle {
+            name: "Sign".to_string(),
+            bold: true,
+            ..SsaStyle::default()
+        };
+        let mut template = AssTemplate::new(vec![SsaStyle::default(), sign]);
+        let ts = TimeSpan::new(TimePoint::from_msecs(1500), TimePoint::from_secs(3));
+        template.add_event(ts, "line1\nline2", "Default", None);
+        template.add_event(ts, "sign", "Sign", Some((10, 20)));
+
+        let data = template.to_ass_string().unwrap();
+        assert!(data.contains("\nStyle: Sign,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1\n"));
+        assert!(data.contains("\nDialogue: 0,0:00:01.50,0:00:03.00,Default,,0,0,0,,line1\\Nline2\n"));
+        assert!(data.contains("\nDialogue: 0,0:00:01.50,0:00:03.00,Sign,,0,0,0,,{\\pos(10,20)}sign\n"));
+        assert_eq!(template.to_ssa_file().unwrap().get_subtitle_entries().unwrap().len(), 2);
+
+        template.add_event(ts, "unknown", "Unknown", None);
+        assert!(template.to_ssa_file().is_err());
+    }
+
+    #[test]
+    fn ssa_dialect_conversion() {
+        let ssa = "[Script Info]\nScriptType: v4.00\n\n[V4 Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, TertiaryColour, BackColour, Bold, Italic, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, AlphaLevel, Encoding\nStyle: Default,Arial,20,16777215,65535,0,0,0,0,1,2,0,6,10,10,10,0,0\n\n[Events]\nFormat: Marked, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: Marked=0,0:00:01.00,0:00:02.00,Default,,0000,0000,0000,,Hello, world\n";
+        let mut file = SsaFile::parse(ssa).unwrap();
+        assert_eq!(file.dialect(), SsaDialect::V4);
+        assert_eq!(
+            SsaFile::parse("[V4+ Styles]\n[Events]\nFormat: Start, End, Text\n").unwrap().dialect(),
+            SsaDialect::V4Plus
+        );
+
+        file.set_dialect(SsaDialect::V4Plus).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        let lines: Vec<&str> = data.lines().collect();
+        assert_eq!(lines[1], "ScriptType: v4.00+");
+        assert_eq!(lines[3], "[V4+ Styles]");
+        assert_eq!(lines[4], V4PLUS_STYLES_FORMAT);
+        assert_eq!(
+            lines[5],
+            "Style: Default,Arial,20,&H00FFFFFF,&H0000FFFF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,0,8,10,10,10,0"
+        );
+        assert_eq!(lines[9], "Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0000,0000,0000,,Hello, world");
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Hello, world"));
+    }
+
+    #[test]
+    fn ssa_clean_styles() {
+        let input = "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour\n\
+                     Style: Default,Arial,20,&H00FFFFFF\nStyle: Main,Arial,20.0,&HFFFFFF\n\
+                     Style: Sign,Verdana,30,&H0000FFFF\nStyle: Unused,Times,12,&H00FFFFFF\n\
+                     Style: Alt,Verdana,30,&H0000FFFF\n\n[Events]\n\
+                     Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                     Dialogue: 0,0:00:01.00,0:00:02.00,Main,,0,0,0,,Hi, {\\rAlt}you\n\
+                     Comment: 0,0:00:01.00,0:00:02.00,Sign,,0,0,0,,note\n";
+        let mut file = SsaFile::parse(input).unwrap();
+        let cleanup = file.clean_styles().unwrap();
+        assert_eq!(
+            cleanup.merged,
+            vec![("Main".to_string(), "Default".to_string()), ("Alt".to_string(), "Sign".to_string())]
+        );
+        assert_eq!(cleanup.removed, vec!["Unused".to_string()]);
+
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("PrimaryColour\nStyle: Default,Arial,20,&H00FFFFFF\nStyle: Sign,Verdana,30,&H0000FFFF\n\n[Events]"));
+        assert!(data.contains("\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hi, {\\rSign}you\n"));
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Hi, {\\rSign}you"));
+    }
+
+    #[test]
+    fn ssa_edit_script_info() {
+        let input = "[Script Info]\n; comment\nTitle: Old\nWrapStyle: 0\nWrapStyle: 1\n!: editor note\n\n[Events]\n\
+                     Format: Layer, Start, End, Style, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,Hi";
+        let mut file = SsaFile::parse(input).unwrap();
+        assert_eq!(file.script_info().unwrap().wrap_style, Some(0));
+
+        file.set_script_info_value("WrapStyle", Some("2")).unwrap();
+        file.set_script_info_value("Title", None).unwrap();
+        file.set_script_info_value("Original Script", Some("someone\nelse")).unwrap();
+        assert_eq!(file.script_info_value("Original Script").unwrap().as_deref(), Some("someoneelse"));
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.starts_with("[Script Info]\n; comment\nWrapStyle: 2\nOriginal Script: someoneelse\n!: editor note\n\n[Events]"));
+
+        let mut file = SsaFile::parse("[Events]\nFormat: Layer, Start, End, Style, Text\n").unwrap();
+        let info = SsaScriptInfo {
+            play_res_x: Some(1920),
+            play_res_y: Some(1080),
+            ..SsaScriptInfo::default()
+        };
+        file.set_script_info(&info).unwrap();
+        assert_eq!(file.script_info().unwrap(), info);
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.starts_with("[Script Info]\nPlayResX: 1920\nPlayResY: 1080\n\n[Events]\n"));
+    }
+
+    #[test]
+    fn ssa_edit_styles() {
+        let v4 = "[Script Info]\nScriptType: v4.00\n\n[V4 Styles]\nFormat: Name, Fontname, Fontsize, TertiaryColour, Bold, Alignment, AlphaLevel\n\
+                  Style: Default,Arial,20,&H00FF0000,-1,6,128\n\n[Events]\nFormat: Marked, Start, End, Style, Text\n";
+        let mut file = SsaFile::parse(v4).unwrap();
+        let mut style = file.styles().unwrap().remove(0);
+        assert_eq!((style.fontname.as_str(), style.bold, style.alignment), ("Arial", true, 8));
+        assert_eq!(style.outline_colour, AssColor::from_rgb(0, 0, 255));
+
+        style.alignment = 7;
+        style.bold = false;
+        file.set_style(&style).unwrap();
+        file.set_style(&SsaStyle {
+            name: "Sign".to_string(),
+            ..SsaStyle::default()
+        })
+        .unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\nStyle: Default,Arial,20,&H00FF0000,0,5,128\nStyle: Sign,Arial,20,&H00000000,0,2,0\n\n[Events]"));
+
+        assert!(file.remove_style("Default").unwrap());
+        assert!(!file.remove_style("Default").unwrap());
+        assert_eq!(file.styles().unwrap().len(), 1);
+
+        let mut file = SsaFile::parse("[Events]\nFormat: Layer, Start, End, Style, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,Hi").unwrap();
+        file.set_style(&SsaStyle::default()).unwrap();
+        assert_eq!(file.styles().unwrap(), vec![SsaStyle::default()]);
+    }
+
+    #[test]
+    fn ssa_import_styles() {
+        let house = "[Script Info]\nScriptType: v4.00\n\n[V4 Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, Alignment\n\
+                     Style: Default,Open Sans,52,16777215,2\nStyle: Sign,Arial,40,65535,6\n\n[Events]\nFormat: Marked, Start, End, Style, Text\n";
+        let script = "[V4+ Styles]\nFormat: Name, Fontname, Fontsize, PrimaryColour, Alignment\nStyle: Default,Arial,20,&H00FFFFFF,2\n\n\
+                      [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                      Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hi";
+        let house = SsaFile::parse(house).unwrap();
+        let styles = |file: &SsaFile| -> Vec<String> {
+            let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+            data.lines().filter(|l| l.starts_with("Style:")).map(str::to_string).collect()
+        };
+
+        let mut file = SsaFile::parse(script).unwrap();
+        let names = file.import_styles(&house, &SsaStyleConflict::Rename("_house".to_string())).unwrap();
+        assert_eq!(names[0], ("Default".to_string(), "Default_house".to_string()));
+        assert_eq!(
+            styles(&file),
+            vec![
+                "Style: Default,Arial,20,&H00FFFFFF,2",
+                "Style: Default_house,Open Sans,52,&H00FFFFFF,2",
+                "Style: Sign,Arial,40,&H0000FFFF,8"
+            ]
+        );
+
+        let mut file = SsaFile::parse(script).unwrap();
+        file.import_styles(&house, &SsaStyleConflict::Replace).unwrap();
+        assert_eq!(styles(&file)[0], "Style: Default,Open Sans,52,&H00FFFFFF,2");
+        for mut event in file.events_mut() {
+            event.set_style("Sign").unwrap();
+        }
+        assert_eq!(file.get_events()[0].style, "Sign");
+
+        let mut file = SsaFile::parse("[Events]\nFormat: Layer, Start, End, Style, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Sign,Hi").unwrap();
+        file.import_styles(&house, &SsaStyleConflict::KeepExisting).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.starts_with("[V4+ Styles]\nFormat: Name, Fontname,"));
+        assert_eq!(file.get_subtitle_entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn ssa_deterministic_output() {
+        let a = "\u{FEFF}[Script Info]\r\nPlayResY:  720 \r\nTitle: x\r\n; comment\r\n\r\n\r\n[V4+ Styles]\r\nFormat: Name,Fontsize,PrimaryColour\r\nStyle: Default, 20.0, &HFFFFFF\r\n[Events]\r\nFormat: Layer, Start, End, Text\r\nDialogue: 0, 0:00:01.00,0:00:02.00,a, b  \r\n";
+        let b = "[Script Info]\n; comment\nTitle: x\nPlayResY: 720\n\n[V4+ Styles]\nFormat: Name, Fontsize, PrimaryColour\nStyle: Default,20,&H00FFFFFF\n\n[Events]\nFormat: Layer, Start, End, Text\nDialogue: 0,0:00:01.00,0:00:02.00,a, b  \n";
+        let data = SsaFile::parse(a).unwrap().to_deterministic_data().unwrap();
+        assert_eq!(String::from_utf8(data).unwrap(), b);
+        assert_eq!(SsaFile::parse(b).unwrap().to_deterministic_data().unwrap(), b.as_bytes());
+    }
+
+    #[test]
+    fn ssa_script_info_language() {
+        let file = SsaFile::parse(&TEST_FILE.replace("Title: test", "Title: test\r\nLanguage: ja")).unwrap();
+        let languages: Vec<Option<String>> = file.get_subtitle_entries().unwrap().into_iter().map(|entry| entry.language).collect();
+        assert_eq!(languages, vec![Some("ja".to_string()); 3]);
+        assert!(SsaFile::parse(TEST_FILE).unwrap().get_subtitle_entries().unwrap()[0].language.is_none());
+    }
+
+    #[test]
+    fn ssa_event_new() {
+        let timespan = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        let event = SsaEvent::new(timespan, "Default", "Hello");
+        assert_eq!(
+            (event.layer, event.style.as_str(), event.name.as_str(), event.margin_v),
+            (0, "Default", "", 0)
+        );
+        assert!(!event.is_typesetting());
+        assert!(SsaEvent::new(timespan, "Sign", "{\\pos(10,20)}Exit").is_typesetting());
+    }
 }
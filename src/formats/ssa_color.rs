@@ -0,0 +1,201 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Colors and transparency in the `&HAABBGGRR` notation of `.ssa`/`.ass` files.
+
+use std::fmt;
+
+/// A transparency value like in the `\alpha&HAA&` override tag: `0` is opaque, `255` is fully transparent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AssAlpha(pub u8);
+
+impl AssAlpha {
+    /// Fully opaque.
+    pub const OPAQUE: AssAlpha = AssAlpha(0);
+
+    /// Fully transparent.
+    pub const TRANSPARENT: AssAlpha = AssAlpha(255);
+
+    /// Creates the alpha value from an opacity (like in CSS, where `255` is opaque).
+    pub fn from_opacity(opacity: u8) -> AssAlpha {
+        AssAlpha(255 - opacity)
+    }
+
+    /// Returns the opacity (like in CSS, where `255` is opaque).
+    pub fn opacity(self) -> u8 {
+        255 - self.0
+    }
+
+    /// Parses the value of an alpha override tag like `&H80&` (or `&H80`, `H80`, `80`).
+    pub fn parse(s: &str) -> Option<AssAlpha> {
+        let digits = strip_hex_notation(s)?;
+        if digits.is_empty() || digits.len() > 2 {
+            return None;
+        }
+        u8::from_str_radix(digits, 16).ok().map(AssAlpha)
+    }
+
+    /// Returns the value for an alpha override tag like `&H80&`.
+    pub fn to_override_string(self) -> String {
+        format!("&H{:02X}&", self.0)
+    }
+}
+
+/// A color with transparency, like in the `PrimaryColour` field of a style or the `\1c&HBBGGRR&` override tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AssColor {
+    /// Red component.
+    pub r: u8,
+
+    /// Green component.
+    pub g: u8,
+
+    /// Blue component.
+    pub b: u8,
+
+    /// Transparency (`.ass` colors store transparency, not opacity).
+    pub alpha: AssAlpha,
+}
+
+/// Removes the `&H` prefix and the `&` suffix (both optional) and checks for hex digits.
+fn strip_hex_notation(s: &str) -> Option<&str> {
+    let s = s.trim();
+    let s = s.strip_prefix('&').unwrap_or(s);
+    let s = s.strip_prefix('H').or_else(|| s.strip_prefix('h')).unwrap_or(s);
+    let s = s.strip_suffix('&').unwrap_or(s);
+    if s.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(s)
+    } else {
+        None
+    }
+}
+
+impl AssColor {
+    /// Creates an opaque color.
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> AssColor {
+        AssColor {
+            r,
+            g,
+            b,
+            alpha: AssAlpha::OPAQUE,
+        }
+    }
+
+    /// Creates a color where `a` is the opacity (like in CSS, where `255` is opaque).
+    pub fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> AssColor {
+        AssColor {
+            alpha: AssAlpha::from_opacity(a),
+            ..AssColor::from_rgb(r, g, b)
+        }
+    }
+
+    /// Returns the `(r, g, b)` components.
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+
+    /// Returns the `(r, g, b, opacity)` components (like in CSS, where an opacity of `255` is opaque).
+    pub fn to_rgba(self) -> (u8, u8, u8, u8) {
+        (self.r, self.g, self.b, self.alpha.opacity())
+    }
+
+    /// Creates the color from the `0xAABBGGRR` integer representation.
+    pub fn from_abgr(value: u32) -> AssColor {
+        AssColor {
+            r: value as u8,
+            g: (value >> 8) as u8,
+            b: (value >> 16) as u8,
+            alpha: AssAlpha((value >> 24) as u8),
+        }
+    }
+
+    /// Returns the `0xAABBGGRR` integer representation.
+    pub fn to_abgr(self) -> u32 {
+        u32::from(self.alpha.0) << 24 | u32::from(self.b) << 16 | u32::from(self.g) << 8 | u32::from(self.r)
+    }
+
+    /// Parses colors like `&HAABBGGRR` (styles), `&HBBGGRR&` (override tags) or decimal `AABBGGRR` values (`.ssa` styles).
+    pub fn parse(s: &str) -> Option<AssColor> {
+        let trimmed = s.trim();
+        if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            // `.ssa` files store the colors as (sometimes negative) decimal numbers
+            return trimmed.parse::<i64>().ok().map(|v| AssColor::from_abgr(v as u32));
+        }
+
+        let digits = strip_hex_notation(trimmed)?;
+        if digits.is_empty() || digits.len() > 8 {
+            return None;
+        }
+        u32::from_str_radix(digits, 16).ok().map(AssColor::from_abgr)
+    }
+
+    /// Returns the color like it is written in a style (`&HAABBGGRR`).
+    pub fn to_style_string(self) -> String {
+        format!("&H{:08X}", self.to_abgr())
+    }
+
+    /// Returns the color like it is written in a color override tag (`&HBBGGRR&`, without the transparency).
+    pub fn to_override_string(self) -> String {
+        format!("&H{:02X}{:02X}{:02X}&", self.b, self.g, self.r)
+    }
+
+    /// Returns the override tags for the color number `n` (`1` = primary, `2` = secondary, `3` = outline,
+    /// `4` = shadow) like `\1c&HBBGGRR&\1a&HAA&`.
+    pub fn to_override_tags(self, n: u8) -> String {
+        format!("\\{}c{}\\{}a{}", n, self.to_override_string(), n, self.alpha.to_override_string())
+    }
+
+    /// Parses CSS hex colors like `#RGB`, `#RRGGBB` or `#RRGGBBAA` (where `AA` is the opacity).
+    pub fn from_css_hex(s: &str) -> Option<AssColor> {
+        let digits = s.trim().strip_prefix('#')?;
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let byte = |i: usize| digits.get(i..i + 2).and_then(|d| u8::from_str_radix(d, 16).ok());
+        let nibble = |i: usize| digits.get(i..=i).and_then(|d| u8::from_str_radix(d, 16).ok()).map(|n| n * 17);
+        match digits.len() {
+            3 => Some(AssColor::from_rgb(nibble(0)?, nibble(1)?, nibble(2)?)),
+            6 => Some(AssColor::from_rgb(byte(0)?, byte(2)?, byte(4)?)),
+            8 => Some(AssColor::from_rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+            _ => None,
+        }
+    }
+
+    /// Returns the CSS hex color (`#rrggbb`, or `#rrggbbaa` if the color is not opaque).
+    pub fn to_css_hex(self) -> String {
+        if self.alpha == AssAlpha::OPAQUE {
+            format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.alpha.opacity())
+        }
+    }
+}
+
+impl fmt::Display for AssColor {
+    /// Writes the style notation `&HAABBGGRR`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_style_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ass_color_conversions() {
+        let c = AssColor::parse("&H80FF8000").unwrap();
+        assert_eq!(c.to_rgba(), (0x00, 0x80, 0xFF, 0x7F));
+        assert_eq!(c.to_style_string(), "&H80FF8000");
+        assert_eq!(c.to_override_tags(1), "\\1c&HFF8000&\\1a&H80&");
+        assert_eq!(AssColor::parse("&HFF8000&"), Some(AssColor::from_rgb(0x00, 0x80, 0xFF)));
+        assert_eq!(AssColor::parse("16777215"), Some(AssColor::from_rgb(255, 255, 255)));
+        assert_eq!(AssColor::parse("&Hxyz"), None);
+
+        assert_eq!(AssColor::from_css_hex("#0080ff"), Some(AssColor::from_rgb(0x00, 0x80, 0xFF)));
+        assert_eq!(AssColor::from_css_hex("#f00").map(AssColor::to_css_hex).as_deref(), Some("#ff0000"));
+        assert_eq!(c.to_css_hex(), "#0080ff7f");
+        assert_eq!(AssAlpha::parse("&H80&"), Some(AssAlpha(0x80)));
+    }
+}
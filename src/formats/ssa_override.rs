@@ -0,0 +1,601 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Override tags like `{\pos(10,10)\b1}` in the texts of `.ssa`/`.ass` events.
+//!
+//! `AssText::parse()` splits an event text into plain text and override blocks with typed tags. Writing the text
+//! again gives exactly the parsed text, so tools can rewrite single tags without touching the rest:
+//!
+//! ```
+//! use subparse::{AssTag, AssText};
+//!
+//! let mut text = AssText::parse("{\\pos(10,10)\\b1}Hello{\\b0} world");
+//! for tag in text.tags_mut() {
+//!     if let AssTag::Position { y, .. } = tag {
+//!         *y += 50.0;
+//!     }
+//! }
+//! assert_eq!(text.to_string(), "{\\pos(10,60)\\b1}Hello{\\b0} world");
+//! ```
+//!
+//! `AssKaraoke` is the timing of the karaoke syllables of a text (see `SsaEvent::karaoke()`).
+
+use super::ssa::format_ssa_number;
+use super::ssa_color::{AssAlpha, AssColor};
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use std::fmt;
+
+/// The karaoke tags (`\k`, `\kf`, `\K` and `\ko`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssKaraokeKind {
+    /// `\k`: the syllable is highlighted at once.
+    Instant,
+
+    /// `\kf`: the highlight sweeps from left to right.
+    Fill,
+
+    /// `\K`: the same as `\kf`.
+    FillUpper,
+
+    /// `\ko`: the outline is highlighted at once.
+    Outline,
+}
+
+impl AssKaraokeKind {
+    fn name(self) -> &'static str {
+        match self {
+            AssKaraokeKind::Instant => "k",
+            AssKaraokeKind::Fill => "kf",
+            AssKaraokeKind::FillUpper => "K",
+            AssKaraokeKind::Outline => "ko",
+        }
+    }
+}
+
+/// An override tag (without the backslash).
+///
+/// Tags whose typed form would not be written exactly like in the parsed text (like `\pos(10, 10)` with spaces or
+/// `\fs20.0`) are kept as `Other`, just like unknown tags and tags with nested tags (`\t(...)`). The fields are named
+/// like the arguments of the tags.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssTag {
+    /// `\pos(x,y)`
+    Position { x: f64, y: f64 },
+
+    /// `\move(x1,y1,x2,y2)` or `\move(x1,y1,x2,y2,t1,t2)` (with the times in milliseconds).
+    Move {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        times: Option<(i64, i64)>,
+    },
+
+    /// `\org(x,y)`: the origin of rotations.
+    Origin { x: f64, y: f64 },
+
+    /// `\fad(in,out)` in milliseconds.
+    Fade { fade_in_ms: i64, fade_out_ms: i64 },
+
+    /// `\an1` to `\an9` (numpad layout).
+    Alignment(u8),
+
+    /// `\b0`, `\b1` or a font weight like `\b700`.
+    Bold(u32),
+
+    /// `\i0` or `\i1`.
+    Italic(bool),
+
+    /// `\u0` or `\u1`.
+    Underline(bool),
+
+    /// `\s0` or `\s1`.
+    Strikeout(bool),
+
+    /// `\fnArial`
+    FontName(String),
+
+    /// `\fs20`
+    FontSize(f64),
+
+    /// `\c&HBBGGRR&` (`index` is `None`) or `\1c` to `\4c`.
+    Color { index: Option<u8>, color: AssColor },
+
+    /// `\alpha&HAA&` (`index` is `None`) or `\1a` to `\4a`.
+    Alpha { index: Option<u8>, alpha: AssAlpha },
+
+    /// `\blur2`
+    Blur(f64),
+
+    /// `\bord2`
+    Border(f64),
+
+    /// A karaoke tag with the duration of the syllable in centiseconds.
+    Karaoke { kind: AssKaraokeKind, duration_cs: i64 },
+
+    /// `\r` (back to the style of the event) or `\rStyle`.
+    Reset(Option<String>),
+
+    /// Any other tag, like `\frz30` or `\t(0,500,\fs30)`.
+    Other(String),
+}
+
+impl AssTag {
+    /// Parses a tag without the backslash.
+    pub fn parse(body: &str) -> AssTag {
+        parse_typed_tag(body)
+            .filter(|tag| tag.to_body() == body)
+            .unwrap_or_else(|| AssTag::Other(body.to_string()))
+    }
+
+    /// Returns the tag without the backslash.
+    fn to_body(&self) -> String {
+        let n = format_ssa_number;
+        let index = |index: &Option<u8>| index.map(|i| i.to_string()).unwrap_or_default();
+        let flag = |name: &str, on: bool| format!("{}{}", name, if on { 1 } else { 0 });
+        match self {
+            AssTag::Position { x, y } => format!("pos({},{})", n(*x), n(*y)),
+            AssTag::Move { x1, y1, x2, y2, times } => match times {
+                Some((t1, t2)) => format!("move({},{},{},{},{},{})", n(*x1), n(*y1), n(*x2), n(*y2), t1, t2),
+                None => format!("move({},{},{},{})", n(*x1), n(*y1), n(*x2), n(*y2)),
+            },
+            AssTag::Origin { x, y } => format!("org({},{})", n(*x), n(*y)),
+            AssTag::Fade { fade_in_ms, fade_out_ms } => format!("fad({},{})", fade_in_ms, fade_out_ms),
+            AssTag::Alignment(alignment) => format!("an{}", alignment),
+            AssTag::Bold(weight) => format!("b{}", weight),
+            AssTag::Italic(on) => flag("i", *on),
+            AssTag::Underline(on) => flag("u", *on),
+            AssTag::Strikeout(on) => flag("s", *on),
+            AssTag::FontName(name) => format!("fn{}", name),
+            AssTag::FontSize(size) => format!("fs{}", n(*size)),
+            AssTag::Color { index: i, color } => format!("{}c{}", index(i), color.to_override_string()),
+            AssTag::Alpha { index: None, alpha } => format!("alpha{}", alpha.to_override_string()),
+            AssTag::Alpha { index: i, alpha } => format!("{}a{}", index(i), alpha.to_override_string()),
+            AssTag::Blur(strength) => format!("blur{}", n(*strength)),
+            AssTag::Border(width) => format!("bord{}", n(*width)),
+            AssTag::Karaoke { kind, duration_cs } => format!("{}{}", kind.name(), duration_cs),
+            AssTag::Reset(style) => format!("r{}", style.as_deref().unwrap_or("")),
+            AssTag::Other(body) => body.clone(),
+        }
+    }
+}
+
+impl fmt::Display for AssTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\\{}", self.to_body())
+    }
+}
+
+/// Tries to read a tag as a typed tag (which might not be written the same way, see `AssTag::parse()`).
+fn parse_typed_tag(body: &str) -> Option<AssTag> {
+    let args = |name: &str| -> Option<Vec<f64>> {
+        let inner = body.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')?;
+        inner.split(',').map(|arg| arg.parse().ok()).collect()
+    };
+    let number = |name: &str| body.strip_prefix(name).and_then(|rest| rest.parse::<f64>().ok());
+    let integer = |name: &str| body.strip_prefix(name).and_then(|rest| rest.parse::<i64>().ok());
+    let flag = |name: &str| match body.strip_prefix(name)? {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    };
+    let indexed = |suffix: &str| {
+        let mut chars = body.chars();
+        let index = chars.next()?.to_digit(10).filter(|i| (1..=4).contains(i))? as u8;
+        chars.as_str().strip_prefix(suffix).map(|value| (index, value))
+    };
+
+    if let Some(args) = args("pos") {
+        return match *args.as_slice() {
+            [x, y] => Some(AssTag::Position { x, y }),
+            _ => None,
+        };
+    }
+    if let Some(args) = args("move") {
+        return match *args.as_slice() {
+            [x1, y1, x2, y2] => Some(AssTag::Move { x1, y1, x2, y2, times: None }),
+            [x1, y1, x2, y2, t1, t2] => Some(AssTag::Move {
+                x1,
+                y1,
+                x2,
+                y2,
+                times: Some((t1 as i64, t2 as i64)),
+            }),
+            _ => None,
+        };
+    }
+    if let Some(args) = args("org") {
+        return match *args.as_slice() {
+            [x, y] => Some(AssTag::Origin { x, y }),
+            _ => None,
+        };
+    }
+    if let Some(args) = args("fad") {
+        return match *args.as_slice() {
+            [fade_in, fade_out] => Some(AssTag::Fade {
+                fade_in_ms: fade_in as i64,
+                fade_out_ms: fade_out as i64,
+            }),
+            _ => None,
+        };
+    }
+    if let Some(alignment) = integer("an").filter(|a| (1..=9).contains(a)) {
+        return Some(AssTag::Alignment(alignment as u8));
+    }
+    if let Some(value) = body.strip_prefix("alpha") {
+        return AssAlpha::parse(value).map(|alpha| AssTag::Alpha { index: None, alpha });
+    }
+    if let Some((index, value)) = indexed("a") {
+        return AssAlpha::parse(value).map(|alpha| AssTag::Alpha { index: Some(index), alpha });
+    }
+    if let Some((index, value)) = indexed("c") {
+        return AssColor::parse(value).map(|color| AssTag::Color { index: Some(index), color });
+    }
+    if let Some(value) = body.strip_prefix('c').filter(|value| value.starts_with('&')) {
+        return AssColor::parse(value).map(|color| AssTag::Color { index: None, color });
+    }
+    if let Some(strength) = number("blur") {
+        return Some(AssTag::Blur(strength));
+    }
+    if let Some(width) = number("bord") {
+        return Some(AssTag::Border(width));
+    }
+    if let Some(weight) = body.strip_prefix('b').and_then(|rest| rest.parse::<u32>().ok()) {
+        return Some(AssTag::Bold(weight));
+    }
+    if let Some(on) = flag("i") {
+        return Some(AssTag::Italic(on));
+    }
+    if let Some(on) = flag("u") {
+        return Some(AssTag::Underline(on));
+    }
+    if let Some(on) = flag("s") {
+        return Some(AssTag::Strikeout(on));
+    }
+    if let Some(name) = body.strip_prefix("fn").filter(|name| !name.is_empty()) {
+        return Some(AssTag::FontName(name.to_string()));
+    }
+    if let Some(size) = number("fs") {
+        return Some(AssTag::FontSize(size));
+    }
+    for &kind in &[
+        AssKaraokeKind::Fill,
+        AssKaraokeKind::Outline,
+        AssKaraokeKind::Instant,
+        AssKaraokeKind::FillUpper,
+    ] {
+        if let Some(duration_cs) = integer(kind.name()) {
+            return Some(AssTag::Karaoke { kind, duration_cs });
+        }
+    }
+    body.strip_prefix('r')
+        .map(|style| AssTag::Reset(if style.is_empty() { None } else { Some(style.to_string()) }))
+}
+
+/// A part of an override block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssBlockItem {
+    /// An override tag.
+    Tag(AssTag),
+
+    /// Text in the block that is not a tag (renderers ignore it, so it is used for comments).
+    Comment(String),
+}
+
+/// A part of an event text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssTextPart {
+    /// Text which is shown (including line breaks like `\N`).
+    Text(String),
+
+    /// An override block `{...}`.
+    Block(Vec<AssBlockItem>),
+}
+
+/// An event text split into text and override blocks (see the module documentation).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AssText {
+    /// The parts in the order of the text.
+    pub parts: Vec<AssTextPart>,
+}
+
+impl AssText {
+    /// Parses an event text (an unclosed `{` is kept as text).
+    pub fn parse(text: &str) -> AssText {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let block = rest.find('{').and_then(|start| Some((start, start + rest.get(start..)?.find('}')?)));
+            let (start, end) = match block {
+                Some(block) => block,
+                None => {
+                    parts.push(AssTextPart::Text(rest.to_string()));
+                    break;
+                }
+            };
+            if start > 0 {
+                parts.push(AssTextPart::Text(rest.get(..start).unwrap_or("").to_string()));
+            }
+            parts.push(AssTextPart::Block(parse_block(rest.get(start + 1..end).unwrap_or(""))));
+            rest = rest.get(end + 1..).unwrap_or("");
+        }
+        AssText { parts }
+    }
+
+    /// Returns all tags in the order of the text.
+    pub fn tags(&self) -> impl Iterator<Item = &AssTag> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                AssTextPart::Block(items) => Some(items),
+                AssTextPart::Text(_) => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                AssBlockItem::Tag(tag) => Some(tag),
+                AssBlockItem::Comment(_) => None,
+            })
+    }
+
+    /// Returns all tags in the order of the text for changing them.
+    pub fn tags_mut(&mut self) -> impl Iterator<Item = &mut AssTag> {
+        self.parts
+            .iter_mut()
+            .filter_map(|part| match part {
+                AssTextPart::Block(items) => Some(items),
+                AssTextPart::Text(_) => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                AssBlockItem::Tag(tag) => Some(tag),
+                AssBlockItem::Comment(_) => None,
+            })
+    }
+}
+
+/// Splits the content of an override block at the backslashes which are not inside of parentheses.
+fn parse_block(content: &str) -> Vec<AssBlockItem> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut item_start = 0;
+    for (i, c) in content.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            '\\' if depth == 0 && i > item_start => {
+                items.push(parse_block_item(content.get(item_start..i).unwrap_or("")));
+                item_start = i;
+            }
+            _ => {}
+        }
+    }
+    if item_start < content.len() {
+        items.push(parse_block_item(content.get(item_start..).unwrap_or("")));
+    }
+    items
+}
+
+/// Returns the durations of the karaoke tags in the content of an override block like `\k50\b1` (without braces).
+pub(crate) fn karaoke_durations(content: &str) -> Vec<TimeDelta> {
+    parse_block(content)
+        .into_iter()
+        .filter_map(|item| match item {
+            AssBlockItem::Tag(AssTag::Karaoke { duration_cs, .. }) => Some(TimeDelta::from_csecs(duration_cs)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_block_item(item: &str) -> AssBlockItem {
+    match item.strip_prefix('\\') {
+        Some(body) => AssBlockItem::Tag(AssTag::parse(body)),
+        None => AssBlockItem::Comment(item.to_string()),
+    }
+}
+
+impl fmt::Display for AssText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                AssTextPart::Text(text) => f.write_str(text)?,
+                AssTextPart::Block(items) => {
+                    f.write_str("{")?;
+                    for item in items {
+                        match item {
+                            AssBlockItem::Tag(tag) => write!(f, "{}", tag)?,
+                            AssBlockItem::Comment(comment) => f.write_str(comment)?,
+                        }
+                    }
+                    f.write_str("}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A syllable of a karaoke text: the text after a karaoke tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssSyllable {
+    /// How the syllable is highlighted.
+    pub kind: AssKaraokeKind,
+
+    /// The duration of the syllable (written in centiseconds).
+    pub duration: TimeDelta,
+
+    /// The text up to the next karaoke tag (without override blocks).
+    pub text: String,
+}
+
+/// The karaoke syllables of an event text (tags like `{\k50}`).
+///
+/// The syllables start one after another at the start of the event, so shifting the event moves them along,
+/// whereas scaling the event time also has to scale the syllables (see `SsaEventMut::retime_with_karaoke()`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AssKaraoke {
+    /// The syllables in the order of the text (empty for texts without karaoke tags).
+    pub syllables: Vec<AssSyllable>,
+}
+
+impl AssKaraoke {
+    /// Reads the syllables of a text (the text before the first karaoke tag is not part of a syllable).
+    pub fn from_text(text: &AssText) -> AssKaraoke {
+        let mut syllables: Vec<AssSyllable> = Vec::new();
+        for part in &text.parts {
+            match part {
+                AssTextPart::Text(text) => {
+                    if let Some(syllable) = syllables.last_mut() {
+                        syllable.text.push_str(text);
+                    }
+                }
+                AssTextPart::Block(items) => {
+                    for item in items {
+                        if let AssBlockItem::Tag(AssTag::Karaoke { kind, duration_cs }) = *item {
+                            syllables.push(AssSyllable {
+                                kind,
+                                duration: TimeDelta::from_csecs(duration_cs),
+                                text: String::new(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        AssKaraoke { syllables }
+    }
+
+    /// Returns the sum of the syllable durations.
+    pub fn duration(&self) -> TimeDelta {
+        self.syllables
+            .iter()
+            .fold(TimeDelta::from_msecs(0), |sum, syllable| sum + syllable.duration)
+    }
+
+    /// Returns when the syllables are highlighted if the event starts at `start`.
+    pub fn timespans(&self, start: TimePoint) -> Vec<TimeSpan> {
+        let mut result = Vec::new();
+        let mut syllable_start = start;
+        for syllable in &self.syllables {
+            result.push(TimeSpan::new(syllable_start, syllable_start + syllable.duration));
+            syllable_start += syllable.duration;
+        }
+        result
+    }
+
+    /// Multiplies the durations by `factor`.
+    ///
+    /// The ends of the syllables are rounded to centiseconds, so the total duration has no accumulated rounding error.
+    pub fn scale(&mut self, factor: f64) {
+        let mut end = 0i64;
+        let mut scaled_end = 0i64;
+        for syllable in &mut self.syllables {
+            end += syllable.duration.msecs();
+            let new_end = (end as f64 * factor / 10.0).round() as i64 * 10;
+            syllable.duration = TimeDelta::from_msecs(new_end - scaled_end);
+            scaled_end = new_end;
+        }
+    }
+
+    /// Writes the kinds and durations of the syllables into the karaoke tags of a text (in the order of the text).
+    pub fn write_to(&self, text: &mut AssText) {
+        let tags = text.tags_mut().filter(|tag| matches!(tag, AssTag::Karaoke { .. }));
+        for (tag, syllable) in tags.zip(&self.syllables) {
+            *tag = AssTag::Karaoke {
+                kind: syllable.kind,
+                duration_cs: syllable.duration.csecs(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ass_override_tags() {
+        let input = "{\\an8\\pos(10.5,20)\\b700\\i1\\fnDejaVu Sans\\1c&H0000FF&\\alpha&H80&\\t(0,500,\\fs30)}Hi\\N\
+                     {comment\\K40}you{\\pos(1, 2)\\fs20.0\\rSign}{unclosed";
+        let text = AssText::parse(input);
+        assert_eq!(text.to_string(), input);
+
+        let tags: Vec<&AssTag> = text.tags().collect();
+        assert_eq!(
+            tags,
+            vec![
+                &AssTag::Alignment(8),
+                &AssTag::Position { x: 10.5, y: 20.0 },
+                &AssTag::Bold(700),
+                &AssTag::Italic(true),
+                &AssTag::FontName("DejaVu Sans".to_string()),
+                &AssTag::Color {
+                    index: Some(1),
+                    color: AssColor::from_rgb(255, 0, 0)
+                },
+                &AssTag::Alpha {
+                    index: None,
+                    alpha: AssAlpha(0x80)
+                },
+                &AssTag::Other("t(0,500,\\fs30)".to_string()),
+                &AssTag::Karaoke {
+                    kind: AssKaraokeKind::FillUpper,
+                    duration_cs: 40
+                },
+                &AssTag::Other("pos(1, 2)".to_string()),
+                &AssTag::Other("fs20.0".to_string()),
+                &AssTag::Reset(Some("Sign".to_string())),
+            ]
+        );
+        assert_eq!(text.parts.last(), Some(&AssTextPart::Text("{unclosed".to_string())));
+    }
+
+    #[test]
+    fn ass_edit_override_tags() {
+        let mut text = AssText::parse("{\\fad(200,300)\\move(0,0,100,50,0,1000)}x");
+        for tag in text.tags_mut() {
+            match tag {
+                AssTag::Fade { fade_in_ms, .. } => *fade_in_ms = 0,
+                AssTag::Move { times, .. } => *times = None,
+                _ => {}
+            }
+        }
+        text.parts.push(AssTextPart::Block(vec![AssBlockItem::Tag(AssTag::Underline(false))]));
+        assert_eq!(text.to_string(), "{\\fad(0,300)\\move(0,0,100,50)}x{\\u0}");
+    }
+
+    #[test]
+    fn ass_karaoke_model() {
+        let mut text = AssText::parse("{\\an8}Intro {\\k33}Hel{\\kf20\\b1}lo {\\ko47}world");
+        let mut karaoke = AssKaraoke::from_text(&text);
+        let syllables: Vec<(AssKaraokeKind, i64, &str)> = karaoke.syllables.iter().map(|s| (s.kind, s.duration.msecs(), s.text.as_str())).collect();
+        assert_eq!(
+            syllables,
+            vec![
+                (AssKaraokeKind::Instant, 330, "Hel"),
+                (AssKaraokeKind::Fill, 200, "lo "),
+                (AssKaraokeKind::Outline, 470, "world")
+            ]
+        );
+        assert_eq!(karaoke.duration().msecs(), 1000);
+        let spans = karaoke.timespans(TimePoint::from_msecs(1000));
+        assert_eq!(spans[1], TimeSpan::new(TimePoint::from_msecs(1330), TimePoint::from_msecs(1530)));
+
+        karaoke.scale(0.5);
+        assert_eq!(karaoke.duration().msecs(), 500);
+        karaoke.write_to(&mut text);
+        assert_eq!(text.to_string(), "{\\an8}Intro {\\k17}Hel{\\kf10\\b1}lo {\\ko23}world");
+    }
+
+    #[test]
+    fn karaoke_durations_match_model() {
+        let content = "\\k30\\t(\\k99)\\kf20\\K5\\kx";
+        let durations: Vec<i64> = karaoke_durations(content).iter().map(|d| d.csecs()).collect();
+        assert_eq!(durations, vec![30, 20, 5]);
+
+        let karaoke = AssKaraoke::from_text(&AssText::parse(&format!("{{{}}}x", content)));
+        assert_eq!(karaoke.syllables.iter().map(|s| s.duration.csecs()).collect::<Vec<_>>(), durations);
+        assert!(karaoke_durations("\\b1\\kabc").is_empty());
+    }
+}
@@ -0,0 +1,607 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+use std::ops::Range;
+
+/// Errors specific to `.stl`-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(
+            display = "an EBU-STL file has a 1024 byte GSI block and 128 byte TTI blocks, but the file has {} bytes",
+            len
+        )]
+        InvalidFileSize { len: usize },
+
+        #[fail(display = "unknown disk format code '{}' (expected `STL25.01` or `STL30.01`)", dfc)]
+        UnknownDiskFormat { dfc: String },
+
+        #[fail(display = "the character code table '{}' is not supported (only `00`, Latin)", cct)]
+        UnsupportedCharacterTable { cct: String },
+    }
+}
+
+/// The size of the General Subtitle Information block.
+const GSI_SIZE: usize = 1024;
+
+/// The size of a Text and Timing Information block.
+const TTI_SIZE: usize = 128;
+
+/// The text field of a TTI block.
+const TEXT_FIELD: Range<usize> = 16..128;
+
+/// Fields of the GSI block.
+const GSI_DFC: Range<usize> = 3..11;
+const GSI_CCT: Range<usize> = 12..14;
+const GSI_TNB: Range<usize> = 238..243;
+const GSI_TNS: Range<usize> = 243..248;
+
+/// Fields of a TTI block.
+const TTI_SN: Range<usize> = 1..3;
+const TTI_EBN: usize = 3;
+const TTI_TCI: Range<usize> = 5..9;
+const TTI_TCO: Range<usize> = 9..13;
+const TTI_CF: usize = 15;
+
+/// The extension block number of the last block of a subtitle.
+const EBN_LAST: u8 = 0xff;
+
+/// The extension block number of user data blocks.
+const EBN_USER_DATA: u8 = 0xfe;
+
+/// Control codes of the text field.
+const CR_LF: u8 = 0x8a;
+const UNUSED_SPACE: u8 = 0x8f;
+const ITALICS_ON: u8 = 0x80;
+const ITALICS_OFF: u8 = 0x81;
+const UNDERLINE_ON: u8 = 0x82;
+const UNDERLINE_OFF: u8 = 0x83;
+
+#[derive(Debug, Clone)]
+/// Represents an EBU-STL (`.stl`) file, the binary exchange format of European broadcasters (EBU Tech 3264).
+///
+/// The GSI block and all TTI blocks are kept byte by byte, so a file without changes is written identically.
+/// The times are the time codes of the TTI blocks (without subtracting the start of programme). Teletext
+/// control codes (colors, double height, boxing) are not part of the entry texts, but are kept at the start of
+/// every row when a text is changed. Only the Latin character table (`00`, ISO 6937) is supported.
+pub struct StlFile {
+    gsi: Vec<u8>,
+
+    /// The TTI blocks, `TTI_SIZE` bytes each
+    blocks: Vec<Vec<u8>>,
+
+    /// The frame rate of the time codes
+    fps: i64,
+}
+
+impl StlFile {
+    /// Parse the content of an `.stl` file to `StlFile`.
+    pub fn parse(b: &[u8]) -> SubtitleParserResult<StlFile> {
+        Ok(Self::parse_file(b).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn parse_file(b: &[u8]) -> Result<StlFile> {
+        if b.len() < GSI_SIZE || (b.len() - GSI_SIZE) % TTI_SIZE != 0 {
+            return Err(InvalidFileSize { len: b.len() }.into());
+        }
+        let (gsi, tti) = b.split_at(GSI_SIZE);
+
+        let dfc = String::from_utf8_lossy(field(gsi, GSI_DFC)).to_string();
+        let fps = dfc
+            .strip_prefix("STL")
+            .and_then(|rest| rest.strip_suffix(".01"))
+            .and_then(|fps| fps.parse::<i64>().ok())
+            .filter(|&fps| fps == 25 || fps == 30)
+            .ok_or_else(|| Error::from(UnknownDiskFormat { dfc: dfc.clone() }))?;
+
+        let cct = String::from_utf8_lossy(field(gsi, GSI_CCT)).to_string();
+        // some writers leave the field empty for Latin
+        if cct != "00" && !cct.trim().is_empty() {
+            return Err(UnsupportedCharacterTable { cct }.into());
+        }
+
+        Ok(StlFile {
+            gsi: gsi.to_vec(),
+            blocks: tti.chunks(TTI_SIZE).map(<[u8]>::to_vec).collect(),
+            fps,
+        })
+    }
+
+    /// Returns the blocks of every subtitle (without comments and user data).
+    fn subtitles(&self) -> Vec<Range<usize>> {
+        let mut result: Vec<Range<usize>> = Vec::new();
+        let mut start = 0;
+        for (idx, block) in self.blocks.iter().enumerate() {
+            let (ebn, sn) = (block.get(TTI_EBN).copied().unwrap_or(EBN_LAST), field(block, TTI_SN));
+            // extension blocks continue the subtitle with the same number
+            let continues = idx > start && self.blocks.get(idx - 1).is_some_and(|prev| field(prev, TTI_SN) == sn);
+            if !continues {
+                start = idx;
+            }
+            if ebn == EBN_USER_DATA {
+                start = idx + 1;
+                continue;
+            }
+            if ebn == EBN_LAST || idx + 1 == self.blocks.len() {
+                let first = self.blocks.get(start);
+                if first.and_then(|first| first.get(TTI_CF)).copied().unwrap_or(0) == 0 {
+                    result.push(start..idx + 1);
+                }
+                start = idx + 1;
+            }
+        }
+        result
+    }
+
+    fn timecode(&self, block: &[u8], range: Range<usize>) -> TimePoint {
+        match *field(block, range) {
+            [h, m, s, f] => {
+                let secs = (i64::from(h) * 60 + i64::from(m)) * 60 + i64::from(s);
+                TimePoint::from_msecs(secs * 1000 + (i64::from(f) * 1000 + self.fps / 2) / self.fps)
+            }
+            _ => TimePoint::from_msecs(0),
+        }
+    }
+
+    /// Returns the time code bytes of a time (negative times are written as zero).
+    fn timecode_bytes(&self, t: TimePoint) -> [u8; 4] {
+        let frames = (t.msecs().max(0) * self.fps + 500) / 1000;
+        let (secs, f) = (frames / self.fps, frames % self.fps);
+        let clamp = |v: i64| v.clamp(0, 255) as u8;
+        [clamp(secs / 3600), clamp(secs / 60 % 60), clamp(secs % 60), clamp(f)]
+    }
+
+    /// The text fields of the blocks (without unused space).
+    fn raw_text(&self, blocks: Range<usize>) -> Vec<u8> {
+        let blocks = self.blocks.get(blocks).unwrap_or_default();
+        blocks
+            .iter()
+            .flat_map(|block| field(block, TEXT_FIELD).iter().copied().filter(|&b| b != UNUSED_SPACE))
+            .collect()
+    }
+
+    /// Writes a number into a GSI field like `00042`.
+    fn set_gsi_number(&mut self, range: Range<usize>, n: usize) {
+        let text = format!("{:0width$}", n, width = range.len());
+        if let Some(dest) = self.gsi.get_mut(range) {
+            if text.len() == dest.len() {
+                dest.copy_from_slice(text.as_bytes());
+            }
+        }
+    }
+
+    /// Updates the number of TTI blocks and subtitles in the GSI block.
+    fn update_gsi_counts(&mut self) {
+        let subtitles = self.subtitles().len();
+        self.set_gsi_number(GSI_TNB, self.blocks.len());
+        self.set_gsi_number(GSI_TNS, subtitles);
+    }
+
+    /// Replaces the text of a subtitle, adding or removing extension blocks if the length changed.
+    fn set_text(&mut self, blocks: Range<usize>, text: &str) {
+        let encoded = encode_text(text, &self.raw_text(blocks.clone()));
+        let needed = ((encoded.len() + TEXT_FIELD.len() - 1) / TEXT_FIELD.len()).max(1);
+        let template = match self.blocks.get(blocks.start) {
+            Some(block) => block.clone(),
+            None => return,
+        };
+
+        let mut new_blocks: Vec<Vec<u8>> = self.blocks.get(blocks.clone()).unwrap_or_default().to_vec();
+        new_blocks.resize(needed, template);
+        let chunks = encoded.chunks(TEXT_FIELD.len()).map(<[u8]>::to_vec).chain(std::iter::repeat(Vec::new()));
+        for (idx, (block, mut chunk)) in new_blocks.iter_mut().zip(chunks).enumerate() {
+            chunk.resize(TEXT_FIELD.len(), UNUSED_SPACE);
+            if let Some(text_field) = block.get_mut(TEXT_FIELD) {
+                text_field.copy_from_slice(&chunk);
+            }
+            if let Some(ebn) = block.get_mut(TTI_EBN) {
+                *ebn = if idx + 1 == needed { EBN_LAST } else { idx as u8 };
+            }
+        }
+
+        let old_count = blocks.len();
+        self.blocks.splice(blocks, new_blocks);
+        if old_count != needed {
+            self.update_gsi_counts();
+        }
+    }
+}
+
+/// Returns the bytes of a field (empty if the block is too short).
+fn field(block: &[u8], range: Range<usize>) -> &[u8] {
+    block.get(range).unwrap_or_default()
+}
+
+/// Returns the rows of a text field (separated by one or more `CR_LF`).
+fn rows(raw: &[u8]) -> Vec<&[u8]> {
+    raw.split(|&b| b == CR_LF).filter(|row| !row.is_empty()).collect()
+}
+
+/// Decodes a text field into an entry text with `<i>` and `<u>` tags.
+fn decode_text(raw: &[u8]) -> String {
+    let lines: Vec<String> = rows(raw)
+        .into_iter()
+        .map(|row| {
+            let mut line = String::new();
+            let mut bytes = row.iter().copied();
+            while let Some(b) = bytes.next() {
+                match b {
+                    ITALICS_ON => line.push_str("<i>"),
+                    ITALICS_OFF => line.push_str("</i>"),
+                    UNDERLINE_ON => line.push_str("<u>"),
+                    UNDERLINE_OFF => line.push_str("</u>"),
+                    // a diacritical mark before the letter
+                    0xc1..=0xcf => {
+                        if let Some(letter) = bytes.next() {
+                            line.push(compose(b, char::from(letter)));
+                        }
+                    }
+                    _ => line.extend(latin_char(b)),
+                }
+            }
+            line.trim().to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join("\n")
+}
+
+/// Encodes an entry text and keeps the teletext control codes at the start of the rows of `old`.
+fn encode_text(text: &str, old: &[u8]) -> Vec<u8> {
+    let old_rows = rows(old);
+    let prefix = |idx: usize| -> Vec<u8> {
+        let row = old_rows.get(idx).or_else(|| old_rows.last()).copied().unwrap_or_default();
+        row.iter().copied().take_while(|&b| b < 0x20 || b == 0x84 || b == 0x85).collect()
+    };
+    // double height text uses two `CR_LF` between the rows
+    let separator_len = old.iter().skip_while(|&&b| b != CR_LF).take_while(|&&b| b == CR_LF).count().max(1);
+
+    let mut result = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if idx > 0 {
+            result.extend(std::iter::repeat(CR_LF).take(separator_len));
+        }
+        result.extend(prefix(idx));
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            let tag = [("<i>", ITALICS_ON), ("</i>", ITALICS_OFF), ("<u>", UNDERLINE_ON), ("</u>", UNDERLINE_OFF)]
+                .iter()
+                .find(|(tag, _)| rest.starts_with(tag));
+            if let Some(&(tag, code)) = tag {
+                result.push(code);
+                rest = rest.get(tag.len()..).unwrap_or_default();
+                continue;
+            }
+            result.extend(encode_char(c));
+            rest = rest.get(c.len_utf8()..).unwrap_or_default();
+        }
+    }
+    result
+}
+
+/// The letters with diacritical marks (mark byte, letters without and with the mark).
+const DIACRITICS: [(u8, &str, &str); 9] = [
+    (0xc1, "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+    (0xc2, "AEIOUYCNSZaeiouycnsz", "ÁÉÍÓÚÝĆŃŚŹáéíóúýćńśź"),
+    (0xc3, "AEIOUaeiou", "ÂÊÎÔÛâêîôû"),
+    (0xc4, "ANOano", "ÃÑÕãñõ"),
+    (0xc7, "ZzEeIg", "ŻżĖėİġ"),
+    (0xc8, "AEIOUaeiouy", "ÄËÏÖÜäëïöüÿ"),
+    (0xca, "AUau", "ÅŮåů"),
+    (0xcb, "CSTcst", "ÇŞŢçşţ"),
+    (0xcf, "CDENRSTZcdenrstz", "ČĎĚŇŘŠŤŽčďěňřšťž"),
+];
+
+/// Combines a diacritical mark byte with a letter.
+fn compose(mark: u8, letter: char) -> char {
+    DIACRITICS
+        .iter()
+        .filter(|&&(m, _, _)| m == mark)
+        .find_map(|&(_, plain, marked)| plain.chars().position(|c| c == letter).and_then(|idx| marked.chars().nth(idx)))
+        .unwrap_or(letter)
+}
+
+/// The characters of the upper half of ISO 6937 (without the diacritical marks `0xc1` to `0xcf`).
+const UPPER_HALF: [(u8, char); 72] = [
+    (0xa0, '\u{a0}'),
+    (0xa1, '¡'),
+    (0xa2, '¢'),
+    (0xa3, '£'),
+    (0xa5, '¥'),
+    (0xa7, '§'),
+    (0xa8, '¤'),
+    (0xa9, '‘'),
+    (0xaa, '“'),
+    (0xab, '«'),
+    (0xac, '←'),
+    (0xad, '↑'),
+    (0xae, '→'),
+    (0xaf, '↓'),
+    (0xb0, '°'),
+    (0xb1, '±'),
+    (0xb2, '²'),
+    (0xb3, '³'),
+    (0xb4, '×'),
+    (0xb5, 'µ'),
+    (0xb6, '¶'),
+    (0xb7, '·'),
+    (0xb8, '÷'),
+    (0xb9, '’'),
+    (0xba, '”'),
+    (0xbb, '»'),
+    (0xbc, '¼'),
+    (0xbd, '½'),
+    (0xbe, '¾'),
+    (0xbf, '¿'),
+    (0xd0, '―'),
+    (0xd1, '¹'),
+    (0xd2, '®'),
+    (0xd3, '©'),
+    (0xd4, '™'),
+    (0xd5, '♪'),
+    (0xd6, '¬'),
+    (0xd7, '¦'),
+    (0xdc, '⅛'),
+    (0xdd, '⅜'),
+    (0xde, '⅝'),
+    (0xdf, '⅞'),
+    (0xe0, 'Ω'),
+    (0xe1, 'Æ'),
+    (0xe2, 'Đ'),
+    (0xe3, 'ª'),
+    (0xe4, 'Ħ'),
+    (0xe6, 'Ĳ'),
+    (0xe7, 'Ŀ'),
+    (0xe8, 'Ł'),
+    (0xe9, 'Ø'),
+    (0xea, 'Œ'),
+    (0xeb, 'º'),
+    (0xec, 'Þ'),
+    (0xed, 'Ŧ'),
+    (0xee, 'Ŋ'),
+    (0xef, 'ŉ'),
+    (0xf0, 'ĸ'),
+    (0xf1, 'æ'),
+    (0xf2, 'đ'),
+    (0xf3, 'ð'),
+    (0xf4, 'ħ'),
+    (0xf5, 'ı'),
+    (0xf6, 'ĳ'),
+    (0xf7, 'ŀ'),
+    (0xf8, 'ł'),
+    (0xf9, 'ø'),
+    (0xfa, 'œ'),
+    (0xfb, 'ß'),
+    (0xfc, 'þ'),
+    (0xfd, 'ŧ'),
+    (0xfe, 'ŋ'),
+];
+
+/// Maps a byte of the Latin character table; control codes and unused bytes are `None`.
+fn latin_char(b: u8) -> Option<char> {
+    match b {
+        0x20..=0x7e => Some(char::from(b)),
+        _ => UPPER_HALF.iter().find(|&&(byte, _)| byte == b).map(|&(_, c)| c),
+    }
+}
+
+/// Encodes a character with the Latin character table (`?` if it has no encoding).
+fn encode_char(c: char) -> Vec<u8> {
+    if c.is_ascii() && !c.is_ascii_control() {
+        return vec![c as u8];
+    }
+    if let Some(&(b, _)) = UPPER_HALF.iter().find(|&&(_, upper)| upper == c) {
+        return vec![b];
+    }
+    for &(mark, plain, marked) in DIACRITICS.iter() {
+        if let Some(letter) = marked.chars().position(|m| m == c).and_then(|idx| plain.chars().nth(idx)) {
+            return vec![mark, letter as u8];
+        }
+    }
+    vec![b'?']
+}
+
+impl SubtitleFileInterface for StlFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .subtitles()
+            .into_iter()
+            .map(|blocks| {
+                let first = self.blocks.get(blocks.start).map_or(&[][..], Vec::as_slice);
+                let timespan = TimeSpan::new(self.timecode(first, TTI_TCI), self.timecode(first, TTI_TCO));
+                SubtitleEntry::new(timespan, decode_text(&self.raw_text(blocks)))
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let subtitles = self.subtitles();
+        check_entry_count(subtitles.len(), new_subtitle_entries.len())?;
+        let old_entries = self.get_subtitle_entries()?;
+
+        // from the back, so the block ranges stay valid when extension blocks are added or removed
+        for ((blocks, old), new) in subtitles.into_iter().zip(old_entries).zip(new_subtitle_entries).rev() {
+            for (range, old_time, new_time) in [
+                (TTI_TCI, old.timespan.start, new.timespan.start),
+                (TTI_TCO, old.timespan.end, new.timespan.end),
+            ] {
+                if old_time == new_time {
+                    continue;
+                }
+                let bytes = self.timecode_bytes(new_time);
+                for block in self.blocks.get_mut(blocks.clone()).unwrap_or_default() {
+                    if let Some(dest) = block.get_mut(range.clone()) {
+                        dest.copy_from_slice(&bytes);
+                    }
+                }
+            }
+            if let Some(ref text) = new.line {
+                if old.line.as_ref() != Some(text) {
+                    self.set_text(blocks, text);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = self.gsi.clone();
+        result.extend(self.blocks.iter().flatten());
+        Ok(result)
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let subtitles = self.subtitles();
+        check_entry_count(subtitles.len(), keep.len())?;
+
+        for (blocks, _) in subtitles.into_iter().zip(keep).rev().filter(|&(_, &keep)| !keep) {
+            self.blocks.drain(blocks);
+        }
+        self.update_gsi_counts();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error};
+
+    fn gsi() -> Vec<u8> {
+        let mut gsi = vec![b' '; GSI_SIZE];
+        gsi[0..3].copy_from_slice(b"850");
+        gsi[GSI_DFC].copy_from_slice(b"STL25.01");
+        gsi[GSI_CCT].copy_from_slice(b"00");
+        gsi[GSI_TNB].copy_from_slice(b"00004");
+        gsi[GSI_TNS].copy_from_slice(b"00002");
+        gsi
+    }
+
+    fn tti(sn: u16, ebn: u8, cf: u8, tci: [u8; 4], tco: [u8; 4], text: &[u8]) -> Vec<u8> {
+        let mut block = vec![0u8; 16];
+        block[1..3].copy_from_slice(&sn.to_le_bytes());
+        block[TTI_EBN] = ebn;
+        block[5..9].copy_from_slice(&tci);
+        block[9..13].copy_from_slice(&tco);
+        block[TTI_CF] = cf;
+        block.extend_from_slice(text);
+        block.resize(TTI_SIZE, UNUSED_SPACE);
+        block
+    }
+
+    /// A comment, a subtitle with a double height line and an italic line, and a subtitle in two blocks.
+    fn file() -> Vec<u8> {
+        let mut input = gsi();
+        input.extend(tti(0, EBN_LAST, 1, [0; 4], [0; 4], b"a comment"));
+        input.extend(tti(
+            1,
+            EBN_LAST,
+            0,
+            [10, 0, 1, 5],
+            [10, 0, 3, 0],
+            b"\x0d\x07Hello\x8a\x8a\x0d\x07\x80W\xc2elt \xd5\x81",
+        ));
+        input.extend(tti(2, 0, 0, [10, 0, 4, 0], [10, 0, 5, 12], &[b'x'; 112]));
+        input.extend(tti(2, EBN_LAST, 0, [10, 0, 4, 0], [10, 0, 5, 12], b"yz"));
+        input
+    }
+
+    #[test]
+    fn stl_unchanged_blocks_are_kept() {
+        // unchanged entries are written byte by byte
+        let input = file();
+        assert_round_trip(&mut StlFile::parse(&input).unwrap(), &input);
+    }
+
+    #[test]
+    fn stl_comments_and_extension_blocks() {
+        // comments are no entries and the extension blocks of a subtitle are joined
+        let entries = StlFile::parse(&file()).unwrap().get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].line.as_deref(), Some(format!("{}yz", "x".repeat(112)).as_str()));
+        assert_eq!(
+            (entries[1].timespan.start.msecs(), entries[1].timespan.end.msecs()),
+            (36_004_000, 36_005_480)
+        );
+    }
+
+    #[test]
+    fn stl_control_codes_and_diacritics() {
+        // the timecodes have 25 frames per second, `0x8a 0x8a` is a line break, `0x80`/`0x81` switch italics
+        // and the diacritic `0xc2` is combined with the following letter
+        let entries = StlFile::parse(&file()).unwrap().get_subtitle_entries().unwrap();
+        assert_eq!(entries[0].line.as_deref(), Some("Hello\n<i>Wélt ♪</i>"));
+        assert_eq!(
+            (entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()),
+            (36_001_200, 36_003_000)
+        );
+    }
+
+    #[test]
+    fn stl_changed_texts_are_encoded() {
+        let mut file = StlFile::parse(&file()).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].line = Some("Hi\n<i>Çava?</i>\nthird".to_string());
+        entries[0].timespan.start = TimePoint::from_msecs(36_002_000);
+        entries[1].line = Some("short".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+
+        // the text of the second subtitle fits into a single block now
+        let data = file.to_data().unwrap();
+        assert_eq!(data.len(), GSI_SIZE + 3 * TTI_SIZE);
+        assert_eq!(&data[GSI_TNB], b"00003");
+        let block = &data[GSI_SIZE + TTI_SIZE..GSI_SIZE + 2 * TTI_SIZE];
+        assert_eq!(&block[TTI_TCI], &[10, 0, 2, 0]);
+        assert_eq!(&block[16..42], b"\x0d\x07Hi\x8a\x8a\x0d\x07\x80\xcbCava?\x81\x8a\x8a\x0d\x07third\x8f");
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Hi\n<i>Çava?</i>\nthird"));
+    }
+
+    #[test]
+    fn stl_removed_subtitles_update_the_block_counts() {
+        // the comment stays, so three blocks and one subtitle are left
+        let mut file = StlFile::parse(&file()).unwrap();
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        let data = file.to_data().unwrap();
+        assert_eq!((&data[GSI_TNB], &data[GSI_TNS]), (&b"00003"[..], &b"00001"[..]));
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_ref().map(String::len), Some(114));
+    }
+
+    #[test]
+    fn stl_parse_errors() {
+        let input = file();
+        assert_eq!(parse_error(StlFile::parse_file, &input[..1000]).kind(), &InvalidFileSize { len: 1000 });
+        assert_eq!(
+            parse_error(StlFile::parse_file, &input[..GSI_SIZE + 100]).kind(),
+            &InvalidFileSize { len: GSI_SIZE + 100 }
+        );
+
+        let mut input = gsi();
+        input[GSI_DFC].copy_from_slice(b"STL24.01");
+        assert_eq!(
+            parse_error(StlFile::parse_file, &input).kind(),
+            &UnknownDiskFormat { dfc: "STL24.01".to_string() }
+        );
+
+        let mut input = gsi();
+        input[GSI_CCT].copy_from_slice(b"01");
+        assert_eq!(
+            parse_error(StlFile::parse_file, &input).kind(),
+            &UnsupportedCharacterTable { cct: "01".to_string() }
+        );
+    }
+}
@@ -0,0 +1,276 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::timetypes::{TimePoint, TimeSpan};
+
+/// Errors specific to SubViewer-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected SubViewer header or timing line like `00:00:01.00,00:00:04.00`, found '{}'", line)]
+        ExpectedTimingLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+#[derive(Debug, Clone)]
+/// One subtitle of a SubViewer file.
+struct SubViewerEntry {
+    /// start and end time of the subtitle
+    timespan: TimeSpan,
+
+    /// the text lines as they are written in the file (usually one line with `[br]` line breaks)
+    raw: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+/// Represents a SubViewer 2.0 file (a text variant of `.sub`).
+///
+/// The file starts with a header like `[INFORMATION]` ... `[END INFORMATION]` and `[SUBTITLE]`, which is kept as
+/// it is. Every subtitle is a timing line like `00:00:01.00,00:00:04.00` (in centiseconds) followed by the text,
+/// in which `[br]` is a line break. Subtitles are separated by empty lines. Texts are only rewritten if they change.
+pub struct SubViewerFile {
+    /// everything before the first subtitle (including the line breaks)
+    header: String,
+
+    /// the line break of the file (`\n` or `\r\n`)
+    newline: String,
+
+    v: Vec<SubViewerEntry>,
+}
+
+impl SubViewerFile {
+    /// Parse a SubViewer subtitle string to `SubViewerFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<SubViewerFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a SubViewer subtitle string to `SubViewerFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(SubViewerFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a SubViewer file from scratch (with a minimal header).
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<SubViewerFile> {
+        let entries = v
+            .into_iter()
+            .map(|(timespan, text)| SubViewerEntry {
+                timespan,
+                raw: vec![encode_text(&text)],
+            })
+            .collect();
+        Ok(SubViewerFile {
+            header: "[INFORMATION]\n[END INFORMATION]\n[SUBTITLE]\n".to_string(),
+            newline: "\n".to_string(),
+            v: entries,
+        })
+    }
+
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<SubViewerFile> {
+        let (bom, s) = split_bom(i);
+
+        let lines = get_lines_non_destructive(s);
+        let newline = lines.first().map(|(_, newline)| newline.clone()).filter(|newline| !newline.is_empty());
+        let mut header = bom.to_string();
+        let mut result: Vec<SubViewerEntry> = Vec::new();
+        let mut in_entry = false;
+        for (line_num, (line, newline)) in lines.into_iter().enumerate() {
+            let trimmed = line.trim();
+            if let Some(timespan) = parse_timing_line(trimmed) {
+                result.push(SubViewerEntry { timespan, raw: Vec::new() });
+                in_entry = true;
+            } else if trimmed.is_empty() {
+                in_entry = false;
+                if result.is_empty() {
+                    header.push_str(&line);
+                    header.push_str(&newline);
+                }
+            } else if let (true, Some(entry)) = (in_entry, result.last_mut()) {
+                entry.raw.push(line);
+            } else if result.is_empty() {
+                // the header consists of `[KEY]value` lines
+                if !trimmed.starts_with('[') {
+                    return Err(Error::from(ExpectedTimingLine { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                header.push_str(&line);
+                header.push_str(&newline);
+            } else {
+                warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+            }
+        }
+
+        Ok(SubViewerFile {
+            header,
+            newline: newline.unwrap_or_else(|| "\n".to_string()),
+            v: result,
+        })
+    }
+}
+
+/// Returns `true` if the text looks like a SubViewer file (and not like a MicroDVD file).
+pub(crate) fn looks_like_subviewer(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    text.lines().map(str::trim).find(|line| !line.is_empty()).is_some_and(|line| {
+        let upper = line.to_ascii_uppercase();
+        upper.starts_with("[INFORMATION]") || upper.starts_with("[SUBTITLE]") || parse_timing_line(line).is_some()
+    })
+}
+
+/// Parses a timestamp like `00:00:01.00`.
+fn parse_timestamp(s: &str) -> Option<TimePoint> {
+    let is_digits = |s: &str| s.len() == 2 && s.bytes().all(|b| b.is_ascii_digit());
+    let mut parts = s.split(':');
+    let (hours, mins, secs) = (parts.next()?, parts.next()?, parts.next()?);
+    let (secs, csecs) = secs.split_once('.')?;
+    if parts.next().is_some() || ![hours, mins, secs, csecs].iter().all(|part| is_digits(part)) {
+        return None;
+    }
+    Some(TimePoint::from_components(
+        hours.parse().ok()?,
+        mins.parse().ok()?,
+        secs.parse().ok()?,
+        csecs.parse::<i64>().ok()? * 10,
+    ))
+}
+
+/// Parses a timing line like `00:00:01.00,00:00:04.00`.
+pub(crate) fn parse_timing_line(line: &str) -> Option<TimeSpan> {
+    let (start, end) = line.trim().split_once(',')?;
+    Some(TimeSpan::new(parse_timestamp(start.trim())?, parse_timestamp(end.trim())?))
+}
+
+/// Formats a timestamp like `00:00:01.00` (negative times are written as zero).
+fn format_timestamp(t: TimePoint) -> String {
+    let t = if t.is_negative() { TimePoint::from_msecs(0) } else { t };
+    format!("{:02}:{:02}:{:02}.{:02}", t.hours(), t.mins_comp(), t.secs_comp(), t.csecs_comp())
+}
+
+/// Converts the raw text lines of a subtitle into an entry text.
+fn decode_text(raw: &[String]) -> String {
+    let lines: Vec<String> = raw.iter().map(|line| line.replace("[br]", "\n").replace("[BR]", "\n")).collect();
+    lines.join("\n")
+}
+
+/// Converts an entry text into a single raw text line.
+fn encode_text(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    lines.join("[br]")
+}
+
+impl SubViewerFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        let entries = self.v.iter().map(|_| None);
+        Some(Some(self.header.as_str())).into_iter().chain(entries).collect()
+    }
+}
+
+impl SubtitleFileInterface for SubViewerFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .v
+            .iter()
+            .map(|entry| SubtitleEntry::new(entry.timespan, decode_text(&entry.raw)))
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), new_subtitle_entries.len())?;
+
+        for (entry, new_entry) in self.v.iter_mut().zip(new_subtitle_entries) {
+            entry.timespan = new_entry.timespan;
+            if let Some(ref text) = new_entry.line {
+                if decode_text(&entry.raw) != *text {
+                    entry.raw = vec![encode_text(text)];
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let nl = &self.newline;
+        let entries: Vec<String> = self
+            .v
+            .iter()
+            .map(|entry| {
+                let text: String = entry.raw.iter().map(|line| format!("{}{}", line, nl)).collect();
+                format!(
+                    "{},{}{}{}",
+                    format_timestamp(entry.timespan.start),
+                    format_timestamp(entry.timespan.end),
+                    nl,
+                    text
+                )
+            })
+            .collect();
+        Ok(format!("{}{}", self.header, entries.join(nl)).into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        check_entry_count(self.v.len(), keep.len())?;
+
+        let mut keep_iter = keep.iter();
+        self.v.retain(|_| keep_iter.next().cloned().unwrap_or(true));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn subviewer_round_trip_and_edit() {
+        let input = "[INFORMATION]\r\n[TITLE]Test\r\n[END INFORMATION]\r\n[SUBTITLE]\r\n[COLF]&HFFFFFF,[SIZE]18,[FONT]Arial\r\n00:00:41.00,00:00:44.40\r\nThe Age of Gods was closing.[br]Eternity had come to an end.\r\n\r\n00:00:55.00,00:00:58.40\r\nSecond\r\n";
+        let (mut file, warnings) = SubViewerFile::parse_with_warnings(input).unwrap();
+        assert_eq!(String::from_utf8(file.to_data().unwrap()).unwrap(), input);
+        assert!(warnings.is_empty());
+
+        let mut entries = file.get_subtitle_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].line.as_deref(),
+            Some("The Age of Gods was closing.\nEternity had come to an end.")
+        );
+        assert_eq!((entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()), (41_000, 44_400));
+
+        entries[1].timespan.end += TimeDelta::from_msecs(250);
+        entries[1].line = Some("Changed\ntext".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        assert_eq!(
+            String::from_utf8(file.to_data().unwrap()).unwrap(),
+            "[INFORMATION]\r\n[TITLE]Test\r\n[END INFORMATION]\r\n[SUBTITLE]\r\n[COLF]&HFFFFFF,[SIZE]18,[FONT]Arial\r\n00:00:55.00,00:00:58.65\r\nChanged[br]text\r\n"
+        );
+
+        assert!(looks_like_subviewer("\u{feff}[INFORMATION]\n"));
+        assert!(looks_like_subviewer("00:00:01.00,00:00:02.00\nHi\n"));
+        assert!(!looks_like_subviewer("{1}{25}Hi\n"));
+        assert!(!looks_like_subviewer("0:00:01.000,0:00:02.000\nHi\n"));
+        assert!(SubViewerFile::parse("{1}{25}Hi\n").is_err());
+    }
+}
@@ -0,0 +1,1180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::warnings::{WarningKind, Warnings};
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use std::convert::TryFrom;
+
+/// `.ttml`-parser-specific errors
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the XML is malformed at byte {}: {}", position, msg)]
+        MalformedXml { position: usize, msg: String },
+
+        #[fail(display = "the time expression `{}` is invalid or not supported", value)]
+        InvalidTime { value: String },
+
+        #[fail(display = "the value `{}` of the attribute `{}` is invalid", value, attribute)]
+        InvalidTimeParameter { attribute: String, value: String },
+
+        #[fail(display = "the file has no `<tt>` element")]
+        ExpectedTtElement,
+    }
+}
+
+/// The namespace of the `tts:` styling attributes.
+const STYLING_NAMESPACE: &str = "http://www.w3.org/ns/ttml#styling";
+
+/// The styling attributes which are converted to and from `<i>`, `<b>` and `<u>` tags in the entry texts.
+const STYLE_TAGS: &[(&str, &str, &str)] = &[
+    ("i", "fontStyle", "italic"),
+    ("b", "fontWeight", "bold"),
+    ("u", "textDecoration", "underline"),
+];
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .ttml file parts
+
+/// A part of the start tag of a `<p>` element.
+#[derive(Debug, Clone)]
+enum TagPiece {
+    /// Everything apart from the timing attribute values.
+    Text(String),
+
+    /// The value of the `begin` attribute (written again as long as the start is unchanged).
+    Begin(String),
+
+    /// The value of the `end` attribute (written again as long as the end is unchanged).
+    End(String),
+
+    /// The value of the `dur` attribute (written again as long as the duration is unchanged).
+    Dur(String),
+}
+
+/// A `<p>` element with timing attributes (one subtitle).
+#[derive(Debug, Clone)]
+struct TtmlParagraph {
+    /// The start tag split at the values of the timing attributes.
+    tag: Vec<TagPiece>,
+
+    /// The absolute times of the paragraph.
+    timespan: TimeSpan,
+
+    /// The times of the timing attribute values in `tag`.
+    parsed_timespan: TimeSpan,
+
+    /// The begin of the parent elements, to which the times of the attributes are relative.
+    parent_begin: TimePoint,
+
+    /// The XML content between the start and the end tag.
+    content: String,
+
+    /// The `xml:lang` of the paragraph or the closest element around it (`None` if it is empty).
+    language: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum TtmlFilePart {
+    /// The XML declaration, the head with styles and layout, the `<tt>`/`<body>`/`<div>` elements, ...
+    Filler(String),
+
+    /// A timed `<p>` element without its end tag.
+    Paragraph(TtmlParagraph),
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .ttml file
+
+/// Represents a reconstructable `.ttml`/`.dfxp` (Timed Text Markup Language) file.
+///
+/// Everything apart from the timing and content of `<p>` elements is saved into `TtmlFilePart::Filler(...)`, so
+/// a changed file still has the same styles, regions and metadata.
+///
+/// The content of a `<p>` becomes the entry text with `<br/>` as line break and italic, bold and underlined `<span>`s
+/// as `<i>`, `<b>` and `<u>` tags. Other markup is only kept if the text of the entry is not changed.
+///
+/// All time expressions of TTML are read (frames and ticks with the `ttp:frameRate`, `ttp:subFrameRate`,
+/// `ttp:frameRateMultiplier` and `ttp:tickRate` of the `<tt>` element). Changed files get clock times with milliseconds,
+/// or timecodes like `00:00:01:12` with `ttp:timeBase="smpte"` (also with `ttp:dropMode="dropNTSC"`).
+#[derive(Debug, Clone)]
+pub struct TtmlFile {
+    v: Vec<TtmlFilePart>,
+
+    /// The prefix of the styling namespace (usually `tts`), if the root declares it.
+    styling_prefix: Option<String>,
+
+    /// The frame rate and time base of the `<tt>` element, with which changed times are written.
+    time_parameters: TimeParameters,
+}
+
+impl TtmlFile {
+    fn new(v: Vec<TtmlFilePart>, styling_prefix: Option<String>, time_parameters: TimeParameters) -> TtmlFile {
+        // cleans up multiple fillers after another
+        let new_file_parts = dedup_string_parts(v, |part: &mut TtmlFilePart| match *part {
+            TtmlFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        TtmlFile {
+            v: new_file_parts,
+            styling_prefix,
+            time_parameters,
+        }
+    }
+
+    fn paragraphs(&self) -> impl Iterator<Item = &TtmlParagraph> {
+        self.v.iter().filter_map(|part| match *part {
+            TtmlFilePart::Paragraph(ref paragraph) => Some(paragraph),
+            TtmlFilePart::Filler(_) => None,
+        })
+    }
+
+    /// Creates a TTML document with one `<p>` for every entry.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<TtmlFile> {
+        Self::create_in(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xml:lang=\"\">\n\
+             \x20 <body>\n\
+             \x20   <div>\n\
+             \x20   </div>\n\
+             \x20 </body>\n\
+             </tt>\n",
+            v,
+        )
+    }
+
+    /// Inserts one `<p>` for every entry into the `<div>` of a document (before a line `    </div>`).
+    pub(crate) fn create_in(document: &str, v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<TtmlFile> {
+        let mut file = Self::parse(document)?;
+
+        let paragraphs = v.iter().map(|(timespan, text)| {
+            let zero = TimePoint::from_msecs(0);
+            TtmlFilePart::Paragraph(TtmlParagraph {
+                tag: vec![
+                    TagPiece::Text("      <p begin=\"".to_string()),
+                    TagPiece::Begin(format_time(timespan.start - zero, &file.time_parameters)),
+                    TagPiece::Text("\" end=\"".to_string()),
+                    TagPiece::End(format_time(timespan.end - zero, &file.time_parameters)),
+                    TagPiece::Text("\">".to_string()),
+                ],
+                timespan: *timespan,
+                parsed_timespan: *timespan,
+                parent_begin: TimePoint::from_msecs(0),
+                content: encode_content(text, file.styling_prefix.as_deref()),
+                language: None,
+            })
+        });
+        let mut parts = Vec::new();
+        for paragraph in paragraphs {
+            parts.push(paragraph);
+            parts.push(TtmlFilePart::Filler("</p>\n".to_string()));
+        }
+
+        // insert the paragraphs before `    </div>`
+        let mut result = Vec::new();
+        for part in std::mem::take(&mut file.v) {
+            match part {
+                TtmlFilePart::Filler(text) => match text.find("    </div>") {
+                    Some(idx) => {
+                        let (before, after) = text.split_at(idx);
+                        result.push(TtmlFilePart::Filler(before.to_string()));
+                        result.append(&mut parts);
+                        result.push(TtmlFilePart::Filler(after.to_string()));
+                    }
+                    None => result.push(TtmlFilePart::Filler(text)),
+                },
+                part => result.push(part),
+            }
+        }
+
+        // parsing again gives the paragraphs the inherited `xml:lang`
+        let new_file = TtmlFile::new(result, file.styling_prefix, file.time_parameters);
+        Self::parse(&String::from_utf8_lossy(&new_file.to_data()?))
+    }
+}
+
+impl TtmlFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                TtmlFilePart::Filler(ref text) => Some(text.as_str()),
+                TtmlFilePart::Paragraph(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for TtmlFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        Ok(self
+            .paragraphs()
+            .map(|paragraph| {
+                let mut entry = SubtitleEntry::new(paragraph.timespan, decode_content(&paragraph.content));
+                entry.language = paragraph.language.clone();
+                entry
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, ts: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        check_entry_count(self.paragraphs().count(), ts.len())?;
+
+        let styling_prefix = self.styling_prefix.clone();
+        let paragraphs = self.v.iter_mut().filter_map(|part| match *part {
+            TtmlFilePart::Paragraph(ref mut paragraph) => Some(paragraph),
+            TtmlFilePart::Filler(_) => None,
+        });
+        for (paragraph, entry) in paragraphs.zip(ts) {
+            paragraph.timespan = entry.timespan;
+            if let Some(ref text) = entry.line {
+                // unchanged texts keep all markup
+                if *text != decode_content(&paragraph.content) {
+                    paragraph.content = encode_content(text, styling_prefix.as_deref());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let mut result = String::new();
+        for part in &self.v {
+            match *part {
+                TtmlFilePart::Filler(ref text) => result.push_str(text),
+                TtmlFilePart::Paragraph(ref paragraph) => {
+                    let (timespan, parsed) = (paragraph.timespan, paragraph.parsed_timespan);
+                    // unchanged times keep their time expression (like `30000000t` or `00:00:02:12`)
+                    let time = |value: &str, unchanged: bool, t: TimeDelta| {
+                        if unchanged {
+                            value.to_string()
+                        } else {
+                            format_time(t, &self.time_parameters)
+                        }
+                    };
+                    for piece in &paragraph.tag {
+                        match *piece {
+                            TagPiece::Text(ref text) => result.push_str(text),
+                            TagPiece::Begin(ref value) => {
+                                result.push_str(&time(value, timespan.start == parsed.start, timespan.start - paragraph.parent_begin))
+                            }
+                            TagPiece::End(ref value) => {
+                                result.push_str(&time(value, timespan.end == parsed.end, timespan.end - paragraph.parent_begin))
+                            }
+                            TagPiece::Dur(ref value) => result.push_str(&time(value, timespan.len() == parsed.len(), timespan.len())),
+                        }
+                    }
+                    result.push_str(&paragraph.content);
+                }
+            }
+        }
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let paragraph_idxs: Vec<usize> = (0..self.v.len())
+            .filter(|&i| matches!(self.v.get(i), Some(TtmlFilePart::Paragraph(_))))
+            .collect();
+        check_entry_count(paragraph_idxs.len(), keep.len())?;
+
+        // removing from the back keeps the indices of the front valid
+        for (&part_idx, _) in paragraph_idxs.iter().zip(keep).rev().filter(|(_, &keep)| !keep) {
+            // the end tag `</p>` and the rest of the line
+            if let Some(TtmlFilePart::Filler(ref mut next)) = self.v.get_mut(part_idx + 1) {
+                let end = next.find('>').map_or(0, |idx| idx + 1);
+                let line_end = match next.get(end..).map(|rest| rest.len() - rest.trim_start_matches([' ', '\t']).len()) {
+                    Some(ws) if next.get(end + ws..).is_some_and(|rest| rest.starts_with(['\n', '\r'])) => {
+                        end + ws
+                            + if next.get(end + ws..).is_some_and(|rest| rest.starts_with("\r\n")) {
+                                2
+                            } else {
+                                1
+                            }
+                    }
+                    _ => end,
+                };
+                next.drain(..line_end);
+            }
+            // the indentation before the start tag
+            if let Some(TtmlFilePart::Paragraph(ref mut paragraph)) = self.v.get_mut(part_idx) {
+                if let Some(TagPiece::Text(ref mut text)) = paragraph.tag.first_mut() {
+                    let indentation = text.len() - text.trim_start_matches([' ', '\t']).len();
+                    text.drain(..indentation);
+                }
+            }
+            if let Some(TtmlFilePart::Filler(ref mut prev)) = part_idx.checked_sub(1).and_then(|i| self.v.get_mut(i)) {
+                let len = prev.trim_end_matches([' ', '\t']).len();
+                prev.truncate(len);
+            }
+            self.v.remove(part_idx);
+        }
+
+        self.v = TtmlFile::new(std::mem::take(&mut self.v), self.styling_prefix.clone(), self.time_parameters).v;
+        Ok(())
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .ttml parser
+
+impl TtmlFile {
+    /// Parse a `.ttml`/`.dfxp` subtitle string to `TtmlFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<TtmlFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.ttml`/`.dfxp` subtitle string to `TtmlFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(TtmlFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_inner(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+}
+
+/// A start tag like `<p begin="00:00:01.000" end="00:00:02.000">`.
+pub(crate) struct StartTag<'a> {
+    /// The name with namespace prefix.
+    pub(crate) name: &'a str,
+
+    /// The names of the attributes and the byte ranges of their values (relative to the tag).
+    pub(crate) attributes: Vec<(&'a str, std::ops::Range<usize>)>,
+
+    pub(crate) self_closing: bool,
+}
+
+impl<'a> StartTag<'a> {
+    /// Parses the start tag at the beginning of `s` and returns it with its length.
+    pub(crate) fn parse(s: &'a str, position: usize) -> Result<(StartTag<'a>, usize)> {
+        let malformed = |msg: &str| MalformedXml {
+            position,
+            msg: msg.to_string(),
+        };
+        let name_len = s
+            .get(1..)
+            .and_then(|rest| rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/'))
+            .ok_or_else(|| malformed("unclosed tag"))?;
+        let name = s.get(1..=name_len).unwrap_or("");
+
+        let mut attributes = Vec::new();
+        let mut pos = name_len + 1;
+        loop {
+            let rest = s.get(pos..).ok_or_else(|| malformed("unclosed tag"))?;
+            let trimmed = rest.trim_start();
+            pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("/>") {
+                return Ok((
+                    StartTag {
+                        name,
+                        attributes,
+                        self_closing: true,
+                    },
+                    pos + 2,
+                ));
+            }
+            if trimmed.starts_with('>') {
+                return Ok((
+                    StartTag {
+                        name,
+                        attributes,
+                        self_closing: false,
+                    },
+                    pos + 1,
+                ));
+            }
+
+            let (attribute, value) = trimmed.split_once('=').ok_or_else(|| malformed("expected attribute"))?;
+            let attribute = attribute.trim();
+            if attribute.is_empty() || attribute.contains(['<', '>', '/']) {
+                return Err(malformed("expected attribute").into());
+            }
+            let value_trimmed = value.trim_start();
+            let quote = value_trimmed
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or_else(|| malformed("expected quote"))?;
+            let value_start = pos + (trimmed.len() - value_trimmed.len()) + 1;
+            let value_len = value_trimmed
+                .get(1..)
+                .and_then(|v| v.find(quote))
+                .ok_or_else(|| malformed("unclosed attribute value"))?;
+            attributes.push((attribute, value_start..value_start + value_len));
+            pos = value_start + value_len + 1;
+        }
+    }
+
+    pub(crate) fn local_name(&self) -> &'a str {
+        local_name(self.name)
+    }
+
+    /// Returns the value of an attribute (without decoding entities).
+    pub(crate) fn attribute(&self, tag: &'a str, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| *attribute == name)
+            .and_then(|(_, range)| tag.get(range.clone()))
+    }
+}
+
+/// Returns the name without namespace prefix.
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Returns the length of a comment, processing instruction, CDATA section or doctype at the beginning of `s`.
+pub(crate) fn special_markup_len(s: &str, position: usize) -> Result<Option<usize>> {
+    for &(start, end) in &[("<!--", "-->"), ("<?", "?>"), ("<![CDATA[", "]]>"), ("<!", ">")] {
+        if let Some(rest) = s.strip_prefix(start) {
+            let len = rest.find(end).ok_or_else(|| MalformedXml {
+                position,
+                msg: format!("missing `{}`", end),
+            })?;
+            return Ok(Some(start.len() + len + end.len()));
+        }
+    }
+    Ok(None)
+}
+
+// implement parsing functions
+impl TtmlFile {
+    fn parse_inner(s: &str, warnings: &mut Warnings) -> Result<TtmlFile> {
+        let mut result = Vec::new();
+        let mut styling_prefix = None;
+        let mut has_tt = false;
+
+        // the begin times (times are relative to the parent) and languages of the open elements
+        let mut open_elements: Vec<(&str, Seconds, Option<&str>)> = Vec::new();
+        let mut time_parameters = TimeParameters::default();
+        let mut filler_start = 0;
+        let mut pos = 0;
+        while let Some(offset) = s.get(pos..).and_then(|rest| rest.find('<')) {
+            pos += offset;
+            let rest = s.get(pos..).unwrap_or("");
+
+            if let Some(len) = special_markup_len(rest, pos)? {
+                pos += len;
+                continue;
+            }
+
+            if let Some(end_tag) = rest.strip_prefix("</") {
+                let len = end_tag.find('>').ok_or(MalformedXml {
+                    position: pos,
+                    msg: "unclosed end tag".to_string(),
+                })?;
+                let name = end_tag.get(..len).unwrap_or("").trim();
+                match open_elements.pop() {
+                    Some((open_name, _, _)) if open_name == name => {}
+                    _ => {
+                        return Err(MalformedXml {
+                            position: pos,
+                            msg: format!("unexpected end tag `{}`", name),
+                        }
+                        .into())
+                    }
+                }
+                pos += len + 3;
+                continue;
+            }
+
+            let (tag, tag_len) = StartTag::parse(rest, pos)?;
+            let tag_str = rest.get(..tag_len).unwrap_or("");
+            let parent_begin = open_elements.last().map_or(Seconds::new(0, 1), |&(_, begin, _)| begin);
+            let language = tag
+                .attribute(tag_str, "xml:lang")
+                .or_else(|| open_elements.last().and_then(|&(_, _, language)| language));
+
+            if tag.local_name() == "tt" {
+                has_tt = true;
+                time_parameters = TimeParameters::parse(&tag, tag_str)?;
+                styling_prefix = tag
+                    .attributes
+                    .iter()
+                    .find(|(attribute, range)| attribute.starts_with("xmlns:") && tag_str.get(range.clone()) == Some(STYLING_NAMESPACE))
+                    .map(|(attribute, _)| attribute.trim_start_matches("xmlns:").to_string());
+            }
+
+            if tag.local_name() == "p" && !tag.self_closing {
+                // the content ends with the end tag of the paragraph (paragraphs can't be nested)
+                let content_start = pos + tag_len;
+                let end_tag = format!("</{}", tag.name);
+                let content_len = s.get(content_start..).and_then(|content| content.find(&end_tag)).ok_or(MalformedXml {
+                    position: pos,
+                    msg: "unclosed paragraph".to_string(),
+                })?;
+                let content = s.get(content_start..content_start + content_len).unwrap_or("");
+
+                if let Some(mut paragraph) = Self::parse_paragraph(&tag, tag_str, parent_begin, &time_parameters, content)? {
+                    paragraph.language = language.filter(|language| !language.is_empty()).map(str::to_string);
+                    result.push(TtmlFilePart::Filler(s.get(filler_start..pos).unwrap_or("").to_string()));
+                    result.push(TtmlFilePart::Paragraph(paragraph));
+                    filler_start = content_start + content_len;
+                } else {
+                    warnings.push(None, WarningKind::IgnoredLine { line: tag_str.to_string() });
+                }
+                pos = content_start + content_len;
+                open_elements.push((tag.name, parent_begin, language));
+                continue;
+            }
+
+            if !tag.self_closing {
+                let begin = match tag.attribute(tag_str, "begin") {
+                    Some(begin) => parent_begin
+                        .checked_add(parse_time(begin, &time_parameters)?)
+                        .ok_or_else(|| InvalidTime { value: begin.to_string() })?,
+                    None => parent_begin,
+                };
+                open_elements.push((tag.name, begin, language));
+            }
+            pos += tag_len;
+        }
+
+        if let Some((name, _, _)) = open_elements.last() {
+            return Err(MalformedXml {
+                position: s.len(),
+                msg: format!("unclosed element `{}`", name),
+            }
+            .into());
+        }
+        if !has_tt {
+            return Err(ExpectedTtElement.into());
+        }
+        result.push(TtmlFilePart::Filler(s.get(filler_start..).unwrap_or("").to_string()));
+
+        Ok(TtmlFile::new(result, styling_prefix, time_parameters))
+    }
+
+    /// Returns the paragraph if it has a `begin` and an `end` or `dur` attribute.
+    fn parse_paragraph(
+        tag: &StartTag<'_>,
+        tag_str: &str,
+        parent_begin: Seconds,
+        parameters: &TimeParameters,
+        content: &str,
+    ) -> Result<Option<TtmlParagraph>> {
+        let time = |name: &str| tag.attribute(tag_str, name).map(|value| parse_time(value, parameters)).transpose();
+        let (begin, end, dur) = (time("begin")?, time("end")?, time("dur")?);
+
+        // the times are only rounded after adding them up
+        let to_point = |seconds: Option<Seconds>| {
+            seconds
+                .and_then(Seconds::to_delta)
+                .map(|t| TimePoint::from_msecs(t.msecs()))
+                .ok_or_else(|| InvalidTime { value: tag_str.to_string() })
+        };
+        let begin = match begin {
+            Some(begin) => parent_begin.checked_add(begin),
+            None => return Ok(None),
+        };
+        let end = match (end, dur) {
+            (Some(end), _) => parent_begin.checked_add(end),
+            (None, Some(dur)) => begin.and_then(|begin| begin.checked_add(dur)),
+            (None, None) => return Ok(None),
+        };
+        let (begin, end, parent_begin) = (to_point(begin)?, to_point(end)?, to_point(Some(parent_begin))?);
+
+        // split the tag at the values of the timing attributes
+        let mut timing_values: Vec<(std::ops::Range<usize>, TagPiece)> = tag
+            .attributes
+            .iter()
+            .filter_map(|(attribute, range)| {
+                let value = tag_str.get(range.clone()).unwrap_or("").to_string();
+                match *attribute {
+                    "begin" => Some((range.clone(), TagPiece::Begin(value))),
+                    "end" => Some((range.clone(), TagPiece::End(value))),
+                    // `end` wins if both are given, so `dur` is only written if there is no `end`
+                    "dur" if !tag.attributes.iter().any(|(a, _)| *a == "end") => Some((range.clone(), TagPiece::Dur(value))),
+                    _ => None,
+                }
+            })
+            .collect();
+        timing_values.sort_by_key(|(range, _)| range.start);
+
+        let mut pieces = Vec::new();
+        let mut last_end = 0;
+        for (range, piece) in timing_values {
+            pieces.push(TagPiece::Text(tag_str.get(last_end..range.start).unwrap_or("").to_string()));
+            pieces.push(piece);
+            last_end = range.end;
+        }
+        pieces.push(TagPiece::Text(tag_str.get(last_end..).unwrap_or("").to_string()));
+
+        Ok(Some(TtmlParagraph {
+            tag: pieces,
+            timespan: TimeSpan::new(begin, end),
+            parsed_timespan: TimeSpan::new(begin, end),
+            parent_begin,
+            content: content.to_string(),
+            language: None,
+        }))
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// times and texts
+
+/// How SMPTE timecodes skip frame numbers (`ttp:dropMode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DropMode {
+    /// Every frame number exists.
+    NonDrop,
+
+    /// The frame numbers 0 and 1 are skipped at the start of every minute, except for every tenth minute.
+    DropNtsc,
+}
+
+/// The attributes of the `<tt>` element which define the length of frames and ticks in time expressions.
+#[derive(Debug, Clone, Copy)]
+struct TimeParameters {
+    /// `ttp:timeBase="smpte"`: clock times are timecodes, which count frames.
+    smpte: bool,
+
+    /// `ttp:dropMode` of SMPTE timecodes.
+    drop_mode: DropMode,
+
+    /// `ttp:frameRate` (frames per second).
+    frame_rate: i128,
+
+    /// `ttp:frameRateMultiplier` as numerator and denominator (`1000 1001` for 29.97 fps with a frame rate of 30).
+    frame_rate_multiplier: (i128, i128),
+
+    /// `ttp:subFrameRate` (sub-frames per frame).
+    sub_frame_rate: i128,
+
+    /// `ttp:tickRate` (ticks per second).
+    tick_rate: i128,
+}
+
+impl Default for TimeParameters {
+    fn default() -> TimeParameters {
+        TimeParameters {
+            smpte: false,
+            drop_mode: DropMode::NonDrop,
+            frame_rate: 30,
+            frame_rate_multiplier: (1, 1),
+            sub_frame_rate: 1,
+            tick_rate: 1,
+        }
+    }
+}
+
+impl TimeParameters {
+    /// Reads the parameters from the attributes of the `<tt>` element.
+    fn parse(tag: &StartTag<'_>, tag_str: &str) -> Result<TimeParameters> {
+        let attribute = |name: &str| {
+            tag.attributes
+                .iter()
+                .find(|(attribute, _)| local_name(attribute) == name)
+                .and_then(|(_, range)| tag_str.get(range.clone()))
+        };
+        let positive_integer = |name: &str, value: &str| {
+            let value = value.trim();
+            match value.parse::<i128>() {
+                Ok(number) if number > 0 && value.len() <= 9 && value.bytes().all(|b| b.is_ascii_digit()) => Ok(number),
+                _ => Err(Error::from(InvalidTimeParameter {
+                    attribute: name.to_string(),
+                    value: value.to_string(),
+                })),
+            }
+        };
+
+        let drop_mode = match attribute("dropMode").map(str::trim) {
+            None | Some("nonDrop") => DropMode::NonDrop,
+            Some("dropNTSC") => DropMode::DropNtsc,
+            // `dropPAL` is not supported
+            Some(value) => {
+                return Err(InvalidTimeParameter {
+                    attribute: "dropMode".to_string(),
+                    value: value.to_string(),
+                }
+                .into())
+            }
+        };
+        let mut result = TimeParameters {
+            smpte: attribute("timeBase").is_some_and(|value| value.trim() == "smpte"),
+            drop_mode,
+            ..TimeParameters::default()
+        };
+        if let Some(value) = attribute("frameRate") {
+            result.frame_rate = positive_integer("frameRate", value)?;
+        }
+        if let Some(value) = attribute("frameRateMultiplier") {
+            match *value.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                [numerator, denominator] => {
+                    result.frame_rate_multiplier = (
+                        positive_integer("frameRateMultiplier", numerator)?,
+                        positive_integer("frameRateMultiplier", denominator)?,
+                    )
+                }
+                _ => positive_integer("frameRateMultiplier", "").map(|_| ())?,
+            }
+        }
+        if let Some(value) = attribute("subFrameRate") {
+            result.sub_frame_rate = positive_integer("subFrameRate", value)?;
+        }
+        result.tick_rate = match attribute("tickRate") {
+            Some(value) => positive_integer("tickRate", value)?,
+            // the default tick rate is one tick per sub-frame if a frame rate is given
+            None if attribute("frameRate").is_some() => result.frame_rate * result.sub_frame_rate,
+            None => 1,
+        };
+        Ok(result)
+    }
+
+    /// Returns the length of a frame in seconds.
+    fn frame_length(&self) -> Seconds {
+        let (numerator, denominator) = self.frame_rate_multiplier;
+        Seconds::new(denominator, self.frame_rate * numerator)
+    }
+
+    /// Returns the number of frame numbers which are skipped at the start of a minute and in ten minutes.
+    fn dropped_frames(&self) -> (i128, i128) {
+        match self.drop_mode {
+            DropMode::NonDrop => (0, 0),
+            DropMode::DropNtsc => (2, 18),
+        }
+    }
+
+    /// Returns the number of frames before a timecode like `01:00:00:00` (without the frames of the timecode).
+    fn timecode_frames(&self, hours: i128, mins: i128, secs: i128) -> Option<i128> {
+        let total_mins = hours.checked_mul(60)?.checked_add(mins)?;
+        let (per_minute, _) = self.dropped_frames();
+        let frames = total_mins.checked_mul(60)?.checked_add(secs)?.checked_mul(self.frame_rate)?;
+        frames.checked_sub(per_minute * (total_mins - total_mins / 10))
+    }
+
+    /// Formats a number of frames as a timecode like `01:00:00:00`.
+    fn format_timecode(&self, frames: i128) -> String {
+        let (per_minute, per_ten_minutes) = self.dropped_frames();
+        let mut label = frames;
+        if per_minute > 0 {
+            let frames_per_minute = 60 * self.frame_rate - per_minute;
+            let frames_per_ten_minutes = 600 * self.frame_rate - per_ten_minutes;
+            let rest = frames % frames_per_ten_minutes;
+            let skipped_minutes = if rest > per_minute { (rest - per_minute) / frames_per_minute } else { 0 };
+            label += per_ten_minutes * (frames / frames_per_ten_minutes) + per_minute * skipped_minutes;
+        }
+        let secs = label / self.frame_rate;
+        format!("{:02}:{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60, label % self.frame_rate)
+    }
+}
+
+/// An exact number of seconds as a fraction, so frames and ticks are only rounded once (to milliseconds).
+#[derive(Debug, Clone, Copy)]
+struct Seconds {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Seconds {
+    fn new(numerator: i128, denominator: i128) -> Seconds {
+        Seconds { numerator, denominator }
+    }
+
+    /// Parses a non-negative decimal number like `12` or `1.5` (no sign, no exponent).
+    fn parse_decimal(s: &str) -> Option<Seconds> {
+        let (int, fraction) = match s.split_once('.') {
+            Some((int, fraction)) if !fraction.is_empty() => (int, fraction),
+            Some(_) => return None,
+            None => (s, ""),
+        };
+        let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() || !is_digits(int) || !is_digits(fraction) || int.len() > 20 || fraction.len() > 18 {
+            return None;
+        }
+        let denominator = 10i128.pow(fraction.len() as u32);
+        let numerator = format!("{}{}", int, fraction).parse::<i128>().ok()?;
+        Some(Seconds::new(numerator, denominator))
+    }
+
+    fn checked_add(self, other: Seconds) -> Option<Seconds> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+        Some(Seconds::new(numerator, self.denominator.checked_mul(other.denominator)?))
+    }
+
+    fn checked_mul(self, other: Seconds) -> Option<Seconds> {
+        Some(Seconds::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?,
+        ))
+    }
+
+    /// Rounds to the nearest millisecond.
+    fn to_delta(self) -> Option<TimeDelta> {
+        let msecs = self.numerator.checked_mul(2000)?.checked_add(self.denominator)? / self.denominator.checked_mul(2)?;
+        i64::try_from(msecs).ok().map(TimeDelta::from_msecs)
+    }
+}
+
+/// Parses a time expression with the syntax of TTML:
+///
+/// - clock time: `hh:mm:ss`, `hh:mm:ss.fraction`, `hh:mm:ss:frames` or `hh:mm:ss:frames.sub-frames`
+/// - offset time: a decimal number with one of the metrics `h`, `m`, `s`, `ms`, `f` (frames) or `t` (ticks)
+///
+/// Minutes, seconds, frames and sub-frames out of range are errors, because the file is misread otherwise.
+fn parse_time(value: &str, parameters: &TimeParameters) -> Result<Seconds> {
+    let s = value.trim();
+    let is_digits = |s: &str, len: usize| s.len() >= len && s.bytes().all(|b| b.is_ascii_digit());
+    let less_than = |s: &str, max: i128| s.parse::<i128>().ok().filter(|&n| n < max);
+    let integer = |n: i128| Seconds::new(n, 1);
+    let seconds = if s.contains(':') {
+        let parts: Vec<&str> = s.split(':').collect();
+        let (hours, mins, secs) = match (parts.first(), parts.get(1), parts.get(2)) {
+            (Some(&hours), Some(&mins), Some(&secs)) if parts.len() <= 4 => (hours, mins, secs),
+            _ => ("", "", ""),
+        };
+        let (whole_secs, secs_fraction) = secs.split_once('.').unwrap_or((secs, ""));
+        let valid = is_digits(hours, 2)
+            && hours.len() <= 20
+            && is_digits(mins, 2)
+            && mins.len() == 2
+            && is_digits(whole_secs, 2)
+            && whole_secs.len() == 2
+            && (secs_fraction.is_empty() || parts.len() == 3);
+
+        let frames = match parts.get(3) {
+            Some(frames_str) => {
+                let (frames, sub_frames) = frames_str.split_once('.').unwrap_or((frames_str, "0"));
+                let (multiplier_num, multiplier_den) = parameters.frame_rate_multiplier;
+                // a frame number must be smaller than the (possibly fractional) number of frames per second
+                let frame_number = Some(frames)
+                    .filter(|frames| is_digits(frames, 2) && frames.len() <= 9)
+                    .and_then(|frames| frames.parse::<i128>().ok())
+                    .filter(|&frames| frames * multiplier_den < parameters.frame_rate * multiplier_num);
+                let sub_frame_number = Some(sub_frames)
+                    .filter(|sub_frames| is_digits(sub_frames, 1) && sub_frames.len() <= 9)
+                    .and_then(|sub_frames| less_than(sub_frames, parameters.sub_frame_rate));
+                match (frame_number, sub_frame_number) {
+                    (Some(frames), Some(sub_frames)) => integer(frames)
+                        .checked_add(Seconds::new(sub_frames, parameters.sub_frame_rate))
+                        .and_then(|frame_count| frame_count.checked_mul(parameters.frame_length())),
+                    _ => None,
+                }
+            }
+            None => Some(integer(0)),
+        };
+
+        match (valid, less_than(mins, 60), less_than(whole_secs, 60), frames) {
+            // with the SMPTE time base, the clock time is a timecode which counts frames
+            (true, Some(mins), Some(secs), Some(frames)) if parameters.smpte && secs_fraction.is_empty() => hours
+                .parse::<i128>()
+                .ok()
+                .and_then(|hours| parameters.timecode_frames(hours, mins, secs))
+                .and_then(|count| integer(count).checked_mul(parameters.frame_length()))
+                .and_then(|hms| hms.checked_add(frames)),
+            (true, Some(_), Some(_), Some(frames)) => hours
+                .parse::<i128>()
+                .ok()
+                .and_then(|h| integer(h).checked_mul(integer(3600)))
+                .and_then(|h| h.checked_add(integer(mins.parse::<i128>().ok()? * 60)))
+                .and_then(|hm| hm.checked_add(Seconds::parse_decimal(secs)?))
+                .and_then(|hms| hms.checked_add(frames)),
+            _ => None,
+        }
+    } else {
+        let metric_start = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+        let (number, metric) = s.split_at(metric_start);
+        let unit = match metric {
+            "h" => Some(integer(3600)),
+            "m" => Some(integer(60)),
+            "s" => Some(integer(1)),
+            "ms" => Some(Seconds::new(1, 1000)),
+            "f" => Some(parameters.frame_length()),
+            "t" => Some(Seconds::new(1, parameters.tick_rate)),
+            _ => None,
+        };
+        unit.and_then(|unit| Seconds::parse_decimal(number)?.checked_mul(unit))
+    };
+    seconds.ok_or_else(|| InvalidTime { value: value.to_string() }.into())
+}
+
+/// Formats a time like `00:00:01.500`, or as a timecode like `00:00:01:12` with the SMPTE time base (TTML has no
+/// negative times).
+fn format_time(t: TimeDelta, parameters: &TimeParameters) -> String {
+    let t = if t.is_negative() { TimeDelta::from_msecs(0) } else { t };
+    if parameters.smpte {
+        // rounded to the nearest frame
+        let frame_length = parameters.frame_length();
+        let msecs = i128::from(t.msecs());
+        let frames = (msecs * frame_length.denominator * 2 + 1000 * frame_length.numerator) / (2000 * frame_length.numerator);
+        return parameters.format_timecode(frames);
+    }
+    format!("{:02}:{:02}:{:02}.{:03}", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp())
+}
+
+/// Converts the XML content of a paragraph into the entry text (see `TtmlFile`).
+fn decode_content(content: &str) -> String {
+    let mut text = String::new();
+    // the closing tags of the open `<span>`s
+    let mut open_spans: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(idx) = rest.find('<') {
+        let (before, tag_start) = rest.split_at(idx);
+        text.push_str(&decode_entities(&before.replace(['\n', '\r', '\t'], " ")));
+        rest = tag_start;
+
+        if let Ok(Some(len)) = special_markup_len(rest, 0) {
+            rest = rest.get(len..).unwrap_or("");
+            continue;
+        }
+        let tag_len = rest.find('>').map_or(rest.len(), |idx| idx + 1);
+        let tag_str = rest.get(..tag_len).unwrap_or("");
+        rest = rest.get(tag_len..).unwrap_or("");
+
+        if tag_str.starts_with("</") {
+            if local_name(tag_str.trim_start_matches("</").trim_end_matches('>').trim()) == "span" {
+                text.push_str(&open_spans.pop().unwrap_or_default());
+            }
+            continue;
+        }
+        let tag = match StartTag::parse(tag_str, 0) {
+            Ok((tag, _)) => tag,
+            Err(_) => continue,
+        };
+        match tag.local_name() {
+            "br" => text.push('\n'),
+            "span" if !tag.self_closing => {
+                let mut closing = String::new();
+                for &(name, attribute, value) in STYLE_TAGS {
+                    let has_style = tag
+                        .attributes
+                        .iter()
+                        .any(|(a, range)| local_name(a) == attribute && tag_str.get(range.clone()) == Some(value));
+                    if has_style {
+                        text.push_str(&format!("<{}>", name));
+                        closing.insert_str(0, &format!("</{}>", name));
+                    }
+                }
+                open_spans.push(closing);
+            }
+            _ => {}
+        }
+    }
+    text.push_str(&decode_entities(&rest.replace(['\n', '\r', '\t'], " ")));
+
+    // whitespace in the XML is collapsed, line breaks are only created by `<br/>`
+    text.split('\n')
+        .map(|line| line.split(' ').filter(|word| !word.is_empty()).collect::<Vec<&str>>().join(" "))
+        .collect::<Vec<String>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Converts an entry text into the XML content of a paragraph (see `TtmlFile`).
+fn encode_content(text: &str, styling_prefix: Option<&str>) -> String {
+    let (prefix, declaration) = match styling_prefix {
+        Some(prefix) => (prefix.to_string(), String::new()),
+        None => ("tts".to_string(), format!(" xmlns:tts=\"{}\"", STYLING_NAMESPACE)),
+    };
+
+    let mut result = String::new();
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        for &(name, attribute, value) in STYLE_TAGS {
+            let open = format!("<{}>", name);
+            let close = format!("</{}>", name);
+            if rest.get(..open.len()).is_some_and(|s| s.eq_ignore_ascii_case(&open)) {
+                result.push_str(&format!("<span{} {}:{}=\"{}\">", declaration, prefix, attribute, value));
+                rest = rest.get(open.len()..).unwrap_or("");
+                continue 'outer;
+            }
+            if rest.get(..close.len()).is_some_and(|s| s.eq_ignore_ascii_case(&close)) {
+                result.push_str("</span>");
+                rest = rest.get(close.len()..).unwrap_or("");
+                continue 'outer;
+            }
+        }
+        let mut chars = rest.chars();
+        match chars.next() {
+            Some('\n') => result.push_str("<br/>"),
+            Some('\r') => {}
+            Some(c) => result.push_str(&escape_xml(&c.to_string())),
+            None => {}
+        }
+        rest = chars.as_str();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+
+    const FILE: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\">\n\
+                     <head><styling><style xml:id=\"s1\" tts:color=\"white\"/></styling></head>\n\
+                     <body style=\"s1\">\n\
+                     <!-- a <p> in a comment -->\n\
+                     <div begin=\"10s\">\n\
+                     <p begin=\"00:00:01.000\" end=\"00:00:02.500\" region=\"r1\">Hello <span tts:fontStyle=\"italic\">big</span><br/>\n  world &amp; more</p>\n\
+                     <p dur=\"1.5s\" begin=\"3s\">Second</p>\n\
+                     <p begin=\"5s\" end=\"6s\">Third</p>\n\
+                     </div>\n\
+                     </body>\n\
+                     </tt>\n";
+
+    #[test]
+    fn ttml_unchanged_texts_keep_markup() {
+        // comments, styles, regions and the markup of the texts are written again (a comment is no paragraph)
+        let mut file = TtmlFile::parse(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        let entries = file.get_subtitle_entries().unwrap();
+        let texts: Vec<&str> = entries.iter().map(|e| e.line.as_deref().unwrap()).collect();
+        assert_eq!(texts, vec!["Hello <i>big</i>\nworld & more", "Second", "Third"]);
+    }
+
+    #[test]
+    fn ttml_times_are_relative_to_the_div() {
+        let mut file = TtmlFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(11000, 12500), (13000, 14500), (15000, 16000)]);
+
+        entries[2].timespan = TimeSpan::new(TimePoint::from_msecs(15500), TimePoint::from_msecs(17250));
+        file.update_subtitle_entries(&entries).unwrap();
+        assert!(to_string(&file).contains("<p begin=\"00:00:05.500\" end=\"00:00:07.250\">Third</p>"));
+    }
+
+    #[test]
+    fn ttml_changed_ends_rewrite_the_dur_attribute() {
+        // the `begin` attribute keeps its text, `dur` gets the new duration and the text gets spans for the tags
+        let mut file = TtmlFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[1].line = Some("<b>A</b> < B\nC".to_string());
+        entries[1].timespan.end += TimeDelta::from_msecs(500);
+        file.update_subtitle_entries(&entries).unwrap();
+        assert!(to_string(&file).contains("<p dur=\"00:00:02.000\" begin=\"3s\"><span tts:fontWeight=\"bold\">A</span> &lt; B<br/>C</p>\n"));
+    }
+
+    #[test]
+    fn ttml_removed_paragraphs_keep_the_div() {
+        let mut file = TtmlFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[true, true, false]).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("<p dur=\"1.5s\" begin=\"3s\">Second</p>\n</div>"));
+        assert!(!data.contains("Third"));
+    }
+
+    #[test]
+    fn ttml_unchanged_times_keep_their_expressions() {
+        let input = "<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+                     ttp:tickRate=\"10000000\" ttp:frameRate=\"25\"><body><div>\n\
+                     <p begin=\"30000000t\" dur=\"1.5s\">First</p>\n\
+                     <p begin=\"00:00:02:12\" end=\"4s\">Second</p>\n\
+                     </div></body></tt>\n";
+        let mut file = TtmlFile::parse(input).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(to_string(&file), input);
+
+        // only the changed times are written again
+        entries[0].timespan += TimeDelta::from_msecs(1000);
+        entries[1].timespan.end += TimeDelta::from_msecs(1000);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("<p begin=\"00:00:04.000\" dur=\"1.5s\">First</p>"), "{}", data);
+        assert!(data.contains("<p begin=\"00:00:02:12\" end=\"00:00:05.000\">Second</p>"), "{}", data);
+    }
+
+    #[test]
+    fn ttml_create() {
+        let timespan = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        let created = TtmlFile::create(vec![(timespan, "<i>Hi</i>".to_string())]).unwrap();
+        let reparsed = TtmlFile::parse(&to_string(&created)).unwrap();
+        let entries = reparsed.get_subtitle_entries().unwrap();
+        assert_eq!((entries[0].timespan, entries[0].line.as_deref()), (timespan, Some("<i>Hi</i>")));
+    }
+
+    #[test]
+    fn ttml_parse_errors() {
+        let parse = |s: &str| TtmlFile::parse_inner(s, &mut Warnings::new());
+        assert_eq!(
+            parse_error(parse, "<tt><body><p begin=\"1x\" end=\"2s\">x</p></body></tt>").kind(),
+            &InvalidTime { value: "1x".to_string() }
+        );
+        assert_eq!(
+            parse_error(parse, "<body><p begin=\"1s\" end=\"2s\">x</p></body>").kind(),
+            &ExpectedTtElement
+        );
+        assert!(matches!(parse_error(parse, "<tt><body>").kind(), MalformedXml { .. }));
+        assert_eq!(TtmlFile::parse("<tt><body>").unwrap_err().kind(), crate::ErrorKind::ParsingError);
+    }
+
+    #[test]
+    fn ttml_time_expressions() {
+        let msecs = |value: &str, parameters: &TimeParameters| parse_time(value, parameters).ok().and_then(Seconds::to_delta).map(|t| t.msecs());
+        let default = TimeParameters::default();
+        assert_eq!(msecs("01:02:03", &default), Some(3_723_000));
+        assert_eq!(msecs("00:00:01.0005", &default), Some(1001));
+        assert_eq!(msecs("00:00:01:15", &default), Some(1500));
+        assert_eq!(msecs("2.5h", &default), Some(9_000_000));
+        assert_eq!(msecs("0.75m", &default), Some(45_000));
+        assert_eq!(msecs("250ms", &default), Some(250));
+        assert_eq!(msecs("45f", &default), Some(1500));
+        assert_eq!(msecs("7t", &default), Some(7000));
+        for invalid in &[
+            "00:60:00",
+            "00:00:60",
+            "0:00:01",
+            "00:00:01:30",
+            "00:00:01.5:00",
+            "1.s",
+            "-1s",
+            "1 s",
+            "1x",
+            "00:00:01:00.1",
+        ] {
+            assert_eq!(msecs(invalid, &default), None, "{}", invalid);
+        }
+
+        // 29.97 fps with two fields per frame and the default tick rate of one tick per field
+        let tt = "<tt ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"1000 1001\" ttp:subFrameRate=\"2\">";
+        let (tag, _) = StartTag::parse(tt, 0).unwrap();
+        let ntsc = TimeParameters::parse(&tag, tt).unwrap();
+        assert_eq!(msecs("00:00:01:29.1", &ntsc), Some(1984));
+        assert_eq!(msecs("30000f", &ntsc), Some(1_001_000));
+        assert_eq!(msecs("60t", &ntsc), Some(1000));
+        assert_eq!(msecs("00:00:00:00.2", &ntsc), None);
+
+        let file = "<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" ttp:tickRate=\"10000000\">\
+                    <body><div begin=\"0.3333333s\"><p begin=\"3333334t\" dur=\"1s\">x</p></div></body></tt>";
+        let entries = TtmlFile::parse(file).unwrap().get_subtitle_entries().unwrap();
+        assert_eq!((entries[0].timespan.start.msecs(), entries[0].timespan.end.msecs()), (667, 1667));
+        assert!(TtmlFile::parse("<tt ttp:tickRate=\"0\"><body/></tt>").is_err());
+    }
+
+    #[test]
+    fn ttml_languages_are_inherited() {
+        let input = "<tt xmlns=\"http://www.w3.org/ns/ttml\" xml:lang=\"en\"><body>\
+                     <div xml:lang=\"de\"><p begin=\"1s\" end=\"2s\">Hallo</p><p begin=\"2s\" end=\"3s\" xml:lang=\"fr\">Salut</p></div>\
+                     <div><p begin=\"3s\" end=\"4s\">Hello</p><p begin=\"4s\" end=\"5s\" xml:lang=\"\">?</p></div>\
+                     </body></tt>";
+        let entries = TtmlFile::parse(input).unwrap().get_subtitle_entries().unwrap();
+        let languages: Vec<Option<&str>> = entries.iter().map(|entry| entry.language.as_deref()).collect();
+        assert_eq!(languages, vec![Some("de"), Some("fr"), Some("en"), None]);
+
+        let created = TtmlFile::create(vec![(entries[0].timespan, "text".to_string())]).unwrap();
+        assert_eq!(created.get_subtitle_entries().unwrap()[0].language, None);
+    }
+}
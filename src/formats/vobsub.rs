@@ -3,10 +3,15 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use self::errors::*;
+use crate::bitmaps::{check_placements, BitmapPlacement};
 use crate::errors::Result as SubtitleParserResult;
-use crate::timetypes::{TimePoint, TimeSpan};
+use crate::formats::common::check_entry_count;
+use crate::formats::idx::IdxFile;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
 use crate::{SubtitleEntry, SubtitleFileInterface, SubtitleFormat};
 use failure::ResultExt;
+use std::collections::BTreeMap;
+use std::fmt;
 
 use vobsub;
 
@@ -15,6 +20,8 @@ use vobsub;
 pub mod errors {
     use vobsub;
 
+    pub type Result<T> = std::result::Result<T, Error>;
+
     define_error!(Error, ErrorKind);
 
     #[derive(Debug, Fail)]
@@ -22,12 +29,16 @@ pub mod errors {
         // TODO: Vobsub-ErrorKind display
         /// Since `vobsub::Error` does not implement Sync. We cannot use #[cause] for it.
         VobSubError { cause: vobsub::ErrorKind },
+
+        /// The control sequence of a subtitle packet (SPU) is truncated or points outside of the packet.
+        InvalidSpuPacket { spu_idx: usize },
     }
 
     impl fmt::Display for ErrorKind {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             match self {
                 ErrorKind::VobSubError { cause } => writeln!(f, "VobSub error: {}", cause),
+                ErrorKind::InvalidSpuPacket { spu_idx } => write!(f, "subtitle packet {} is invalid", spu_idx),
             }
         }
     }
@@ -47,6 +58,9 @@ pub struct VobFile {
 /// Represents a line in a `VobSub` `.sub` file.
 struct VobSubSubtitle {
     timespan: TimeSpan,
+
+    /// Whether the subtitle is shown even if subtitles are disabled
+    forced: bool,
 }
 
 impl VobFile {
@@ -62,6 +76,7 @@ impl VobFile {
                         start: TimePoint::from_msecs((sub.start_time() * 1000.0) as i64),
                         end: TimePoint::from_msecs((sub.end_time() * 1000.0) as i64),
                     },
+                    forced: sub.force(),
                 })
             })
             .collect::<vobsub::Result<Vec<VobSubSubtitle>>>()
@@ -70,21 +85,569 @@ impl VobFile {
             })
             .with_context(|_| crate::errors::ErrorKind::ParsingError)?;
 
-        Ok(VobFile {
-            data: b.to_vec(),
-            lines: lines,
+        Ok(VobFile { data: b.to_vec(), lines })
+    }
+}
+
+/// The palette indices (into the 16 color palette of the `.idx` file) of the four pixel types of a subtitle image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpuColors {
+    /// Palette index of the background pixels.
+    pub background: u8,
+
+    /// Palette index of the text fill ("pattern") pixels.
+    pub pattern: u8,
+
+    /// Palette index of the first emphasis pixels (usually the outline).
+    pub emphasis1: u8,
+
+    /// Palette index of the second emphasis pixels (usually anti-aliasing).
+    pub emphasis2: u8,
+}
+
+impl VobFile {
+    /// Changes the palette indices of all subtitle images and rewrites the `SET_COLOR` commands in the `.sub` data.
+    ///
+    /// `f` gets the position of the subtitle packet in the stream and its current colors. All indices
+    /// returned by `f` are truncated to the palette size (`0..16`). The image data itself is not touched,
+    /// so the size of the file stays the same. Returns the number of changed packets.
+    ///
+    /// ```no_run
+    /// # let mut file = subparse::VobFile::parse(&[]).unwrap();
+    /// // fix a film where the text fill and the outline got swapped
+    /// file.remap_colors(|_, mut colors| {
+    ///     std::mem::swap(&mut colors.pattern, &mut colors.emphasis1);
+    ///     colors
+    /// }).unwrap();
+    /// ```
+    pub fn remap_colors<F>(&mut self, f: F) -> SubtitleParserResult<usize>
+    where
+        F: FnMut(usize, SpuColors) -> SpuColors,
+    {
+        Ok(remap_spu_colors(&mut self.data, f).with_context(|_| crate::errors::ErrorKind::InvalidPalette)?)
+    }
+
+    /// Replaces every palette index `i` by `map[i]` in all subtitle images.
+    pub fn remap_palette_indices(&mut self, map: &[u8; 16]) -> SubtitleParserResult<usize> {
+        let lookup = |i: u8| map.get(usize::from(i)).cloned().unwrap_or(i);
+        self.remap_colors(|_, c| SpuColors {
+            background: lookup(c.background),
+            pattern: lookup(c.pattern),
+            emphasis1: lookup(c.emphasis1),
+            emphasis2: lookup(c.emphasis2),
         })
     }
 }
 
+/// An inconsistency between an `.idx` file and its `.sub` file (see `validate_vobsub()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VobSubIssue {
+    /// The `timestamp:` line has no (hexadecimal) `filepos:`.
+    MissingFilePos {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+    },
+
+    /// No subtitle packet starts at the `filepos:` of the line.
+    NoPacketAtFilePos {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+
+        /// The position in the `.sub` file.
+        filepos: usize,
+    },
+
+    /// The subtitle packet belongs to another stream than the `id:` section of the line.
+    WrongStream {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+
+        /// The position in the `.sub` file.
+        filepos: usize,
+    },
+
+    /// The subtitle packet is truncated or its control sequence is broken.
+    InvalidPacket {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+
+        /// The position in the `.sub` file.
+        filepos: usize,
+    },
+
+    /// The timestamp is before the previous timestamp of the same stream.
+    TimestampNotMonotonic {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+
+        /// The timestamp of the line.
+        timestamp: TimePoint,
+
+        /// The previous timestamp.
+        previous: TimePoint,
+    },
+
+    /// The presentation time stamp of the packet doesn't match the timestamp of the line.
+    PtsMismatch {
+        /// The line in the `.idx` file (starting at 0).
+        line_num: usize,
+
+        /// The timestamp of the line.
+        timestamp: TimePoint,
+
+        /// The time stamp of the packet.
+        pts: TimePoint,
+    },
+}
+
+impl fmt::Display for VobSubIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VobSubIssue::MissingFilePos { line_num } => write!(f, "line {}: the timestamp has no `filepos`", line_num),
+            VobSubIssue::NoPacketAtFilePos { line_num, filepos } => write!(f, "line {}: no subtitle packet starts at {:09x}", line_num, filepos),
+            VobSubIssue::WrongStream { line_num, filepos } => {
+                write!(f, "line {}: the subtitle packet at {:09x} belongs to another stream", line_num, filepos)
+            }
+            VobSubIssue::InvalidPacket { line_num, filepos } => write!(f, "line {}: the subtitle packet at {:09x} is invalid", line_num, filepos),
+            VobSubIssue::TimestampNotMonotonic {
+                line_num,
+                timestamp,
+                previous,
+            } => write!(
+                f,
+                "line {}: the timestamp {} is before the previous timestamp {}",
+                line_num, timestamp, previous
+            ),
+            VobSubIssue::PtsMismatch { line_num, timestamp, pts } => {
+                write!(f, "line {}: the timestamp {} differs from the packet time {}", line_num, timestamp, pts)
+            }
+        }
+    }
+}
+
+/// Checks that an `.idx` file fits to its `.sub` file.
+///
+/// Every `filepos:` has to point at the start of a valid subtitle packet of its stream with a presentation time
+/// stamp at most `max_pts_offset` away from the timestamp, and the timestamps of every stream have to be in order. The
+/// timestamps are compared as they are written (without `delay:`). Corrupted rips can still be parsed, but
+/// often fail later with unrelated errors, so this reports all found issues by line of the `.idx` file.
+pub fn validate_vobsub(idx: &IdxFile, sub: &[u8], max_pts_offset: TimeDelta) -> Vec<VobSubIssue> {
+    // the stream, validity and time stamp of all packets by position
+    let mut packets: BTreeMap<usize, (u8, bool, Option<u64>)> = BTreeMap::new();
+    let mut spu_count = 0;
+    walk_spus(sub, |spu| {
+        let size = read_u16(&spu.buffer.data, 0).filter(|_| spu.complete);
+        let valid = size.is_some_and(|size| remap_spu(&spu.buffer.data, size, spu_count, &mut |_, colors| colors).is_ok());
+        packets.entry(spu.buffer.file_pos).or_insert((spu.substream, valid, spu.buffer.pts));
+        spu_count += 1;
+    });
+
+    let mut issues = Vec::new();
+    let mut previous: BTreeMap<u8, TimePoint> = BTreeMap::new();
+    for entry in idx.index_entries() {
+        let line_num = entry.line_num;
+        if let Some(previous) = previous
+            .insert(entry.stream, entry.timestamp)
+            .filter(|&previous| entry.timestamp < previous)
+        {
+            issues.push(VobSubIssue::TimestampNotMonotonic {
+                line_num,
+                timestamp: entry.timestamp,
+                previous,
+            });
+        }
+
+        let filepos = match entry.filepos {
+            Some(filepos) => filepos,
+            None => {
+                issues.push(VobSubIssue::MissingFilePos { line_num });
+                continue;
+            }
+        };
+        let (substream, valid, pts) = match packets.get(&filepos) {
+            Some(&packet) => packet,
+            None => {
+                issues.push(VobSubIssue::NoPacketAtFilePos { line_num, filepos });
+                continue;
+            }
+        };
+        if substream != entry.stream.wrapping_add(0x20) {
+            issues.push(VobSubIssue::WrongStream { line_num, filepos });
+        }
+        if !valid {
+            issues.push(VobSubIssue::InvalidPacket { line_num, filepos });
+        }
+        if let Some(pts) = pts.map(|pts| TimePoint::from_msecs((pts / 90) as i64)) {
+            if (pts - entry.timestamp).abs() > max_pts_offset {
+                issues.push(VobSubIssue::PtsMismatch {
+                    line_num,
+                    timestamp: entry.timestamp,
+                    pts,
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// A subtitle packet (SPU) that is split across multiple PES packets of the MPEG program stream.
+#[derive(Default)]
+struct SpuBuffer {
+    /// The position of the pack header (or the PES packet if there is none) before the first PES packet.
+    file_pos: usize,
+
+    /// The presentation time stamp of the first PES packet (in 90 kHz ticks).
+    pts: Option<u64>,
+
+    /// The reassembled SPU data.
+    data: Vec<u8>,
+
+    /// The file offset of every byte in `data`.
+    file_offsets: Vec<usize>,
+}
+
+/// A reassembled SPU (see `walk_spus()`).
+struct Spu<'a> {
+    buffer: &'a SpuBuffer,
+
+    /// The subpicture stream (`0x20` for the first language).
+    substream: u8,
+
+    /// Whether the SPU has the size of its header (otherwise the stream ended or the next SPU started before).
+    complete: bool,
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<usize> {
+    match data.get(pos..pos + 2)? {
+        &[hi, lo] => Some(usize::from(hi) << 8 | usize::from(lo)),
+        _ => None,
+    }
+}
+
+/// Reads the 33 bit time stamp of a PES header.
+pub(crate) fn read_pts(data: &[u8], pos: usize) -> Option<u64> {
+    match data.get(pos..pos + 5)? {
+        &[b0, b1, b2, b3, b4] => {
+            Some(u64::from(b0 >> 1 & 0x07) << 30 | u64::from(b1) << 22 | u64::from(b2 >> 1) << 15 | u64::from(b3) << 7 | u64::from(b4 >> 1))
+        }
+        _ => None,
+    }
+}
+
+/// Writes the 33 bit time stamp of a PES header (keeping the prefix bits of the first byte).
+pub(crate) fn write_pts(data: &mut [u8], pos: usize, pts: u64) {
+    if let Some(dest) = data.get_mut(pos..pos + 5) {
+        let prefix = dest.first().copied().unwrap_or_default() & 0xf0;
+        let bytes = [
+            prefix | (((pts >> 30) & 0x07) as u8) << 1 | 1,
+            (pts >> 22) as u8,
+            (((pts >> 15) & 0x7f) as u8) << 1 | 1,
+            (pts >> 7) as u8,
+            ((pts & 0x7f) as u8) << 1 | 1,
+        ];
+        dest.copy_from_slice(&bytes);
+    }
+}
+
+/// Walks the MPEG program stream and calls `f` for every reassembled SPU.
+fn walk_spus<F>(data: &[u8], mut f: F)
+where
+    F: FnMut(Spu<'_>),
+{
+    let mut buffers: Vec<(u8, SpuBuffer)> = Vec::new();
+    let mut pack_pos = None;
+
+    let mut pos = 0;
+    while pos + 4 <= data.len() {
+        if data.get(pos..pos + 3) != Some(&[0, 0, 1][..]) {
+            // resynchronize to the next start code
+            pos += 1;
+            continue;
+        }
+        let stream_id = data.get(pos + 3).cloned().unwrap_or(0);
+        let packet_len = match stream_id {
+            // pack header: MPEG-2 has stuffing bytes, MPEG-1 has a fixed size
+            0xBA => match data.get(pos + 4) {
+                Some(b) if b & 0xC0 == 0x40 => 14 + usize::from(data.get(pos + 13).cloned().unwrap_or(0) & 0x07),
+                _ => 12,
+            },
+            0xBB..=0xFF => 6 + read_u16(data, pos + 4).unwrap_or(0),
+            _ => {
+                pos += 1;
+                continue;
+            }
+        };
+        let packet_end = (pos + packet_len).min(data.len());
+
+        // private stream 1 carries the subtitles
+        if stream_id == 0xBD {
+            let has_pts = data.get(pos + 7).is_some_and(|flags| flags & 0x80 != 0);
+            let payload_start = pos + 9 + usize::from(data.get(pos + 8).cloned().unwrap_or(0));
+            if let Some(&substream) = data.get(payload_start) {
+                let spu_start = payload_start + 1;
+                let idx = match buffers.iter().position(|(id, _)| *id == substream) {
+                    Some(idx) => idx,
+                    None => {
+                        buffers.push((substream, SpuBuffer::default()));
+                        buffers.len() - 1
+                    }
+                };
+                if let Some((_, buffer)) = buffers.get_mut(idx) {
+                    if has_pts {
+                        if !buffer.data.is_empty() {
+                            f(Spu {
+                                buffer,
+                                substream,
+                                complete: false,
+                            });
+                        }
+                        buffer.data.clear();
+                        buffer.file_offsets.clear();
+                        buffer.file_pos = pack_pos.unwrap_or(pos);
+                        buffer.pts = read_pts(data, pos + 9);
+                    }
+                    for offset in spu_start..packet_end {
+                        buffer.data.extend(data.get(offset));
+                        buffer.file_offsets.push(offset);
+                    }
+
+                    if let Some(size) = read_u16(&buffer.data, 0) {
+                        if buffer.data.len() >= size {
+                            f(Spu {
+                                buffer,
+                                substream,
+                                complete: true,
+                            });
+                            buffer.data.clear();
+                            buffer.file_offsets.clear();
+                        }
+                    }
+                }
+            }
+        }
+        pack_pos = if stream_id == 0xBA { Some(pos) } else { None };
+
+        pos = packet_end.max(pos + 4);
+    }
+
+    for (substream, buffer) in &buffers {
+        if !buffer.data.is_empty() {
+            f(Spu {
+                buffer,
+                substream: *substream,
+                complete: false,
+            });
+        }
+    }
+}
+
+/// Reassembles all SPUs and rewrites their `SET_COLOR` commands.
+fn remap_spu_colors<F>(data: &mut [u8], mut f: F) -> Result<usize>
+where
+    F: FnMut(usize, SpuColors) -> SpuColors,
+{
+    let mut changes = Vec::new();
+    let mut spu_count = 0;
+    let mut result = Ok(());
+    walk_spus(data, |spu| {
+        let size = match read_u16(&spu.buffer.data, 0) {
+            Some(size) if spu.complete && result.is_ok() => size,
+            _ => return,
+        };
+        match remap_spu(&spu.buffer.data, size, spu_count, &mut f) {
+            Ok(changed) => changes.extend(
+                changed
+                    .into_iter()
+                    .filter_map(|(offset, value)| Some((*spu.buffer.file_offsets.get(offset)?, value))),
+            ),
+            Err(e) => result = Err(e),
+        }
+        spu_count += 1;
+    });
+    result?;
+
+    for &(offset, value) in &changes {
+        if let Some(b) = data.get_mut(offset) {
+            *b = value;
+        }
+    }
+
+    // two bytes per `SET_COLOR` command
+    Ok(changes.len() / 2)
+}
+
+/// Returns the commands of all control sequences of a single SPU with the position of their arguments.
+fn spu_commands(spu: &[u8], size: usize, spu_idx: usize) -> Result<Vec<(u8, usize)>> {
+    let invalid = || Error::from(ErrorKind::InvalidSpuPacket { spu_idx });
+    let mut result = Vec::new();
+    let mut seq_pos = read_u16(spu, 2).ok_or_else(invalid)?;
+
+    // the last control sequence points to itself; the count guards against loops
+    for _ in 0..size {
+        let mut cmd_pos = seq_pos + 4;
+        loop {
+            let cmd = *spu.get(cmd_pos).ok_or_else(invalid)?;
+            cmd_pos += 1;
+            let len = match cmd {
+                0x00..=0x02 => 0,
+                0x03 | 0x04 => 2,
+                0x05 => 6,
+                0x06 => 4,
+                0x07 => read_u16(spu, cmd_pos).ok_or_else(invalid)?,
+                _ => break,
+            };
+            if spu.get(cmd_pos..cmd_pos + len).is_none() {
+                return Err(invalid());
+            }
+            result.push((cmd, cmd_pos));
+            cmd_pos += len;
+        }
+
+        let next = read_u16(spu, seq_pos + 2).ok_or_else(invalid)?;
+        if next == seq_pos || next >= size {
+            break;
+        }
+        seq_pos = next;
+    }
+    Ok(result)
+}
+
+/// Returns the changed bytes (`(offset in SPU, new value)`) of a single SPU.
+fn remap_spu<F>(spu: &[u8], size: usize, spu_idx: usize, f: &mut F) -> Result<Vec<(usize, u8)>>
+where
+    F: FnMut(usize, SpuColors) -> SpuColors,
+{
+    let mut result = Vec::new();
+    for (cmd, pos) in spu_commands(spu, size, spu_idx)? {
+        let (hi, lo) = match spu.get(pos..pos + 2) {
+            Some(&[hi, lo]) if cmd == 0x03 => (hi, lo),
+            _ => continue,
+        };
+        let colors = SpuColors {
+            emphasis2: hi >> 4,
+            emphasis1: hi & 0x0F,
+            pattern: lo >> 4,
+            background: lo & 0x0F,
+        };
+        let new = f(spu_idx, colors);
+        if new != colors {
+            result.push((pos, (new.emphasis2 & 0x0F) << 4 | (new.emphasis1 & 0x0F)));
+            result.push((pos + 1, (new.pattern & 0x0F) << 4 | (new.background & 0x0F)));
+        }
+    }
+    Ok(result)
+}
+
+/// The display area of a complete SPU.
+struct SpuArea {
+    /// The area of the first `SET_DAREA` command (`None` if the SPU has none).
+    placement: Option<BitmapPlacement>,
+
+    /// The file offsets of the arguments of every `SET_DAREA` command.
+    commands: Vec<Vec<usize>>,
+}
+
+/// Reads the display areas of all complete SPUs.
+fn spu_areas(data: &[u8]) -> Result<Vec<SpuArea>> {
+    let mut areas = Vec::new();
+    let mut result = Ok(());
+    walk_spus(data, |spu| {
+        let size = match read_u16(&spu.buffer.data, 0) {
+            Some(size) if spu.complete && result.is_ok() => size,
+            _ => return,
+        };
+        let commands = match spu_commands(&spu.buffer.data, size, areas.len()) {
+            Ok(commands) => commands,
+            Err(e) => {
+                result = Err(e);
+                return;
+            }
+        };
+        let mut area = SpuArea {
+            placement: None,
+            commands: Vec::new(),
+        };
+        for (_, pos) in commands.into_iter().filter(|&(cmd, _)| cmd == 0x05) {
+            if let Some(&[a, b, c, d, e, f]) = spu.buffer.data.get(pos..pos + 6) {
+                // x1, x2, y1 and y2 (inclusive) as 12 bit values
+                let (x1, x2) = (u16::from(a) << 4 | u16::from(b >> 4), u16::from(b & 0x0F) << 8 | u16::from(c));
+                let (y1, y2) = (u16::from(d) << 4 | u16::from(e >> 4), u16::from(e & 0x0F) << 8 | u16::from(f));
+                area.placement.get_or_insert(BitmapPlacement {
+                    x: x1,
+                    y: y1,
+                    width: (x2 + 1).saturating_sub(x1),
+                    height: (y2 + 1).saturating_sub(y1),
+                    crop: None,
+                    window: None,
+                });
+            }
+            area.commands
+                .push((pos..pos + 6).filter_map(|offset| spu.buffer.file_offsets.get(offset).copied()).collect());
+        }
+        areas.push(area);
+    });
+    result?;
+    Ok(areas)
+}
+
+/// The largest coordinate of a `SET_DAREA` command.
+const MAX_SPU_COORDINATE: u32 = 0x0FFF;
+
+impl VobFile {
+    /// Returns the image of every entry with its position (see `bitmaps`; `.sub` files have no cropping
+    /// rectangles and windows).
+    pub fn bitmap_placements(&self) -> SubtitleParserResult<Vec<Vec<BitmapPlacement>>> {
+        let areas = spu_areas(&self.data).with_context(|_| crate::errors::ErrorKind::ParsingError)?;
+        check_entry_count(self.lines.len(), areas.len())?;
+        Ok(areas.into_iter().map(|area| area.placement.into_iter().collect()).collect())
+    }
+
+    /// Moves the images of every entry by rewriting the `SET_DAREA` commands in the `.sub` data (see `bitmaps`).
+    ///
+    /// As with `remap_colors()`, the image data is not touched.
+    pub fn set_bitmap_placements(&mut self, placements: &[Vec<BitmapPlacement>]) -> SubtitleParserResult<()> {
+        let areas = spu_areas(&self.data).with_context(|_| crate::errors::ErrorKind::ParsingError)?;
+        check_entry_count(self.lines.len(), areas.len())?;
+        check_entry_count(areas.len(), placements.len())?;
+
+        let max = MAX_SPU_COORDINATE as u16 + 1;
+        for (entry_idx, (area, new)) in areas.iter().zip(placements).enumerate() {
+            let old: Vec<BitmapPlacement> = area.placement.into_iter().collect();
+            check_placements(entry_idx, &old, new, Some((max, max))).with_context(|_| crate::errors::ErrorKind::UpdatingEntriesError)?;
+            let new = match new.first() {
+                Some(new) if old.first() != Some(new) => new,
+                _ => continue,
+            };
+            let (x2, y2) = (new.x + new.width.saturating_sub(1), new.y + new.height.saturating_sub(1));
+            let bytes = [
+                (new.x >> 4) as u8,
+                ((new.x & 0x0F) << 4 | x2 >> 8) as u8,
+                x2 as u8,
+                (new.y >> 4) as u8,
+                ((new.y & 0x0F) << 4 | y2 >> 8) as u8,
+                y2 as u8,
+            ];
+            for offsets in &area.commands {
+                for (&offset, &value) in offsets.iter().zip(&bytes) {
+                    if let Some(b) = self.data.get_mut(offset) {
+                        *b = value;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl SubtitleFileInterface for VobFile {
     fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
         Ok(self
             .lines
             .iter()
-            .map(|vsub| SubtitleEntry {
-                timespan: vsub.timespan,
-                line: None,
+            .map(|vsub| {
+                let mut entry = SubtitleEntry::from(vsub.timespan);
+                entry.flags.forced = vsub.forced;
+                entry
             })
             .collect())
     }
@@ -99,4 +662,160 @@ impl SubtitleFileInterface for VobFile {
     fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
         Ok(self.data.clone())
     }
+
+    fn retain_entries_by_mask(&mut self, _: &[bool]) -> SubtitleParserResult<()> {
+        Err(crate::errors::ErrorKind::UpdatingEntriesNotSupported {
+            format: SubtitleFormat::VobSubSub,
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vobsub_remap_colors() {
+        // SPU: size 16, control sequence at 4 (two bytes of "image data" are skipped)
+        let spu = [
+            0x00, 0x10, 0x00, 0x06, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x32, 0x10, 0xFF, 0x00,
+        ];
+        let mut pes = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x80, 0x05, 0x21, 0x00, 0x01, 0x00, 0x01, 0x20];
+        pes.extend_from_slice(&spu[..10]);
+        let len = pes.len() - 6;
+        pes[5] = len as u8;
+
+        // the second half of the SPU is in its own PES packet (without PTS)
+        let mut pes2 = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x00, 0x00, 0x20];
+        pes2.extend_from_slice(&spu[10..]);
+        let len = pes2.len() - 6;
+        pes2[5] = len as u8;
+
+        let mut data = vec![0x00, 0x00, 0x01, 0xBA, 0x44, 0x00, 0x04, 0x00, 0x04, 0x01, 0x01, 0x89, 0xC3, 0xF8];
+        data.extend(pes);
+        data.extend(pes2);
+        let expected_pos = data.len() - 4;
+
+        let swapped = remap_spu_colors(&mut data, |_, mut c| {
+            std::mem::swap(&mut c.pattern, &mut c.emphasis1);
+            c
+        })
+        .unwrap();
+        assert_eq!(swapped, 1);
+        assert_eq!(&data[expected_pos..expected_pos + 2], &[0x31, 0x20]);
+    }
+
+    #[test]
+    fn vobsub_validate_index() {
+        let pack = [0x00, 0x00, 0x01, 0xBA, 0x44, 0x00, 0x04, 0x00, 0x04, 0x01, 0x01, 0x89, 0xC3, 0xF8];
+        // a PES packet with the time stamp 0x15f90 (1 second)
+        let pes = |spu: &[u8]| {
+            let mut pes = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x80, 0x05, 0x21, 0x00, 0x05, 0xBF, 0x21, 0x20];
+            pes.extend_from_slice(spu);
+            pes[5] = (pes.len() - 6) as u8;
+            pes
+        };
+        let mut sub = pack.to_vec();
+        sub.extend(pes(&[0x00, 0x0A, 0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x01, 0xFF]));
+        let second = sub.len();
+        sub.extend_from_slice(&pack);
+        // truncated: the size is larger than the data
+        sub.extend(pes(&[0x00, 0x20, 0x00, 0x04]));
+
+        let idx = IdxFile::parse(&format!(
+            "# VobSub index file, v7\nid: en, index: 0\ntimestamp: 00:00:01:000, filepos: 000000000\ntimestamp: 00:00:03:000, filepos: {:09x}\n\
+             timestamp: 00:00:02:000, filepos: 000000010\ntimestamp: 00:00:04:000\nid: de, index: 1\ntimestamp: 00:00:01:000, filepos: 000000000\n",
+            second
+        ))
+        .unwrap();
+        let issues = validate_vobsub(&idx, &sub, TimeDelta::from_msecs(500));
+        let t = TimePoint::from_msecs;
+        assert_eq!(
+            issues,
+            vec![
+                VobSubIssue::InvalidPacket {
+                    line_num: 3,
+                    filepos: second
+                },
+                VobSubIssue::PtsMismatch {
+                    line_num: 3,
+                    timestamp: t(3000),
+                    pts: t(1000)
+                },
+                VobSubIssue::TimestampNotMonotonic {
+                    line_num: 4,
+                    timestamp: t(2000),
+                    previous: t(3000)
+                },
+                VobSubIssue::NoPacketAtFilePos { line_num: 4, filepos: 0x10 },
+                VobSubIssue::MissingFilePos { line_num: 5 },
+                VobSubIssue::WrongStream { line_num: 7, filepos: 0 },
+            ]
+        );
+        assert_eq!(issues[3].to_string(), "line 4: no subtitle packet starts at 000000010");
+    }
+
+    #[test]
+    fn vobsub_bitmap_placements() {
+        // SPU with the display area x = 10..=109, y = 400..=449
+        let spu = [
+            0x00, 0x14, 0x00, 0x06, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0x06, 0x01, 0x05, 0x00, 0xA0, 0x6D, 0x19, 0x01, 0xC1, 0xFF, 0x00,
+        ];
+        let mut data = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x80, 0x05, 0x21, 0x00, 0x01, 0x00, 0x01, 0x20];
+        data.extend_from_slice(&spu);
+        data[5] = (data.len() - 6) as u8;
+        let mut file = VobFile {
+            data,
+            lines: vec![VobSubSubtitle {
+                timespan: TimeSpan::new(TimePoint::from_msecs(0), TimePoint::from_msecs(1000)),
+                forced: false,
+            }],
+        };
+
+        let placement = BitmapPlacement {
+            x: 10,
+            y: 400,
+            width: 100,
+            height: 50,
+            crop: None,
+            window: None,
+        };
+        assert_eq!(file.bitmap_placements().unwrap(), vec![vec![placement]]);
+        file.set_bitmap_placements(&[vec![placement.moved_by(0, -300)]]).unwrap();
+        assert_eq!(file.bitmap_placements().unwrap()[0][0], BitmapPlacement { y: 100, ..placement });
+        assert_eq!(
+            &file.data[file.data.len() - 8..file.data.len() - 2],
+            &[0x00, 0xA0, 0x6D, 0x06, 0x40, 0x95]
+        );
+    }
+
+    #[test]
+    fn vobsub_invalid_placement() {
+        let data = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x80, 0x05, 0x21, 0x00, 0x01, 0x00, 0x01, 0x20];
+        let mut file = VobFile { data, lines: Vec::new() };
+        file.data.extend_from_slice(&[
+            0x00, 0x14, 0x00, 0x06, 0xAA, 0xBB, 0x00, 0x00, 0x00, 0x06, 0x01, 0x05, 0x00, 0xA0, 0x6D, 0x19, 0x01, 0xC1, 0xFF, 0x00,
+        ]);
+        file.data[5] = (file.data.len() - 6) as u8;
+        file.lines.push(VobSubSubtitle {
+            timespan: TimeSpan::new(TimePoint::from_msecs(0), TimePoint::from_msecs(1000)),
+            forced: false,
+        });
+
+        let placement = file.bitmap_placements().unwrap()[0][0];
+        let error = file.set_bitmap_placements(&[vec![placement.moved_by(4000, 0)]]).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::UpdatingEntriesError);
+    }
+
+    #[test]
+    fn vobsub_remap_colors_of_invalid_packet() {
+        // the control sequence offset points outside of the packet
+        let mut data = vec![0x00, 0x00, 0x01, 0xBD, 0x00, 0x00, 0x81, 0x80, 0x05, 0x21, 0x00, 0x01, 0x00, 0x01, 0x20];
+        data.extend_from_slice(&[0x00, 0x06, 0x00, 0x40, 0x00, 0x00]);
+        data[5] = (data.len() - 6) as u8;
+        let mut file = VobFile { data, lines: Vec::new() };
+        let error = file.remap_palette_indices(&[0; 16]).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::InvalidPalette);
+    }
 }
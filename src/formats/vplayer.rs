@@ -0,0 +1,458 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::{SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::common::*;
+use crate::warnings::{WarningKind, Warnings};
+
+use failure::ResultExt;
+
+use crate::durations::DurationSynthesis;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+
+/// Errors specific to VPlayer-parsing.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "expected VPlayer line like `00:00:12:Hello|world`, found '{}'", line)]
+        ExpectedVPlayerLine { line: String },
+
+        #[fail(display = "parse error at line `{}`", line_num)]
+        ErrorAtLine { line_num: usize },
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A line of a VPlayer file.
+enum VPlayerLine {
+    /// An empty line or a line which is not a subtitle (kept as it is).
+    Filler(String),
+
+    /// A line like `00:00:12:Hello|world`, or a line without text like `00:00:15:` which ends the subtitle before.
+    Cue {
+        /// The time as it is written in the file.
+        stamp: String,
+
+        time: TimePoint,
+
+        /// The character between the time and the text (`:`, ` ` or `=`).
+        separator: char,
+
+        /// The text as it is written in the file.
+        raw: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// Represents a VPlayer file (usually with the extension `.txt`).
+///
+/// Every line is a subtitle like `00:00:12:Hello|world` which starts at the given time (in seconds); a `|`
+/// separates the lines of the text. A line without text like `00:00:15:` ends the subtitle before, otherwise it
+/// is shown until the next subtitle starts. Lines which aren't subtitles are kept, and times and texts are only
+/// rewritten if they change.
+pub struct VPlayerFile {
+    v: Vec<(VPlayerLine, String)>,
+
+    /// How the end times of subtitles without a line that ends them are created
+    durations: DurationSynthesis,
+}
+
+/// A subtitle of a VPlayer file: the index of its line and of the line without text which ends it.
+#[derive(Debug, Clone, Copy)]
+struct VPlayerCue {
+    line_idx: usize,
+    end_idx: Option<usize>,
+}
+
+impl VPlayerFile {
+    /// Parse a VPlayer subtitle string to `VPlayerFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<VPlayerFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a VPlayer subtitle string to `VPlayerFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(VPlayerFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_file(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+
+    /// Creates a VPlayer file from scratch.
+    ///
+    /// A line without text is written after every subtitle which does not last until the next one starts.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<VPlayerFile> {
+        let mut lines = Vec::new();
+        for (idx, (timespan, text)) in v.iter().enumerate() {
+            lines.push((cue_line(timespan.start, ':', encode_text(text)), "\n".to_string()));
+            if v.get(idx + 1).map(|(next, _)| format_time(next.start)) != Some(format_time(timespan.end)) {
+                lines.push((cue_line(timespan.end, ':', String::new()), "\n".to_string()));
+            }
+        }
+        Ok(VPlayerFile::new(lines))
+    }
+
+    /// Sets how the end times of subtitles without a line that ends them are created.
+    ///
+    /// By default these subtitles are shown until the next subtitle starts, and the last one for three seconds.
+    pub fn set_duration_synthesis(&mut self, durations: DurationSynthesis) {
+        self.durations = durations;
+    }
+
+    fn new(v: Vec<(VPlayerLine, String)>) -> VPlayerFile {
+        VPlayerFile {
+            v,
+            durations: DurationSynthesis::NextCue {
+                last: TimeDelta::from_secs(3),
+            },
+        }
+    }
+
+    fn parse_file(i: &str, warnings: &mut Warnings) -> Result<VPlayerFile> {
+        let (bom, s) = split_bom(i);
+
+        let mut result = Vec::new();
+        if !bom.is_empty() {
+            result.push((VPlayerLine::Filler(bom.to_string()), String::new()));
+        }
+        let mut found_entry = false;
+        for (line_num, (line, newline)) in get_lines_non_destructive(s).into_iter().enumerate() {
+            match parse_line(&line) {
+                Some(cue) => {
+                    found_entry = true;
+                    result.push((cue, newline));
+                }
+                None if line.trim().is_empty() => result.push((VPlayerLine::Filler(line), newline)),
+                // a file whose first content isn't a subtitle is not a VPlayer file
+                None if !found_entry => {
+                    return Err(Error::from(ExpectedVPlayerLine { line: line.clone() })).with_context(|_| ErrorAtLine { line_num })?;
+                }
+                None => {
+                    warnings.push(Some(line_num), WarningKind::IgnoredLine { line: line.clone() });
+                    result.push((VPlayerLine::Filler(line), newline));
+                }
+            }
+        }
+        Ok(VPlayerFile::new(result))
+    }
+
+    /// Returns the subtitles (the lines with a text) in the order of the file.
+    fn cues(&self) -> Vec<VPlayerCue> {
+        let mut result: Vec<VPlayerCue> = Vec::new();
+        let mut after_text = false;
+        for (idx, (line, _)) in self.v.iter().enumerate() {
+            if let VPlayerLine::Cue { ref raw, .. } = *line {
+                match result.last_mut() {
+                    // only the first line without text after a subtitle ends it
+                    Some(cue) if raw.is_empty() && after_text => cue.end_idx = Some(idx),
+                    _ if raw.is_empty() => {}
+                    _ => result.push(VPlayerCue {
+                        line_idx: idx,
+                        end_idx: None,
+                    }),
+                }
+                after_text = !raw.is_empty();
+            }
+        }
+        result
+    }
+
+    /// Returns the time of the cue line `idx`.
+    fn time(&self, idx: usize) -> Option<TimePoint> {
+        match self.v.get(idx) {
+            Some((VPlayerLine::Cue { time, .. }, _)) => Some(*time),
+            _ => None,
+        }
+    }
+
+    /// Returns the timespan of every subtitle (see `set_duration_synthesis()` for subtitles without end line).
+    fn timespans(&self, cues: &[VPlayerCue]) -> Vec<TimeSpan> {
+        let starts: Vec<(TimePoint, Option<&str>)> = cues
+            .iter()
+            .map(|cue| match self.v.get(cue.line_idx) {
+                Some((VPlayerLine::Cue { time, raw, .. }, _)) => (*time, Some(raw.as_str())),
+                _ => (TimePoint::from_msecs(0), None),
+            })
+            .collect();
+        cues.iter()
+            .zip(self.durations.timespans(&starts))
+            .map(|(cue, timespan)| match cue.end_idx.and_then(|idx| self.time(idx)) {
+                Some(end) => TimeSpan::new(timespan.start, end),
+                None => timespan,
+            })
+            .collect()
+    }
+
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .flat_map(|(line, newline)| {
+                let line = match *line {
+                    VPlayerLine::Filler(ref text) => Some(text.as_str()),
+                    VPlayerLine::Cue { .. } => None,
+                };
+                vec![line, Some(newline.as_str())]
+            })
+            .collect()
+    }
+}
+
+/// Returns `true` if the first non-empty line of the text looks like a VPlayer subtitle.
+pub(crate) fn looks_like_vplayer(text: &str) -> bool {
+    let (_, text) = split_bom(text);
+    text.lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| parse_line(line).is_some())
+}
+
+/// Parses a line like `00:00:12:Hello|world` (the hours may have one digit).
+fn parse_line(line: &str) -> Option<VPlayerLine> {
+    let mut numbers = line.splitn(3, ':');
+    let (hours, mins) = (numbers.next()?, numbers.next()?);
+    let secs = numbers.next()?.get(..2)?;
+    let valid = |number: &str, max_len: usize| !number.is_empty() && number.len() <= max_len && number.bytes().all(|b| b.is_ascii_digit());
+    if !valid(hours, 2) || mins.len() != 2 || !valid(mins, 2) || !valid(secs, 2) {
+        return None;
+    }
+    let (mins, secs): (i64, i64) = (mins.parse().ok()?, secs.parse().ok()?);
+    if mins >= 60 || secs >= 60 {
+        return None;
+    }
+    let time = TimePoint::from_components(hours.parse().ok()?, mins, secs, 0);
+
+    let stamp_len = hours.len() + 6;
+    let rest = line.get(stamp_len..)?;
+    let separator = rest.chars().next()?;
+    if ![':', ' ', '='].contains(&separator) {
+        return None;
+    }
+    Some(VPlayerLine::Cue {
+        stamp: line.get(..stamp_len)?.to_string(),
+        time,
+        separator,
+        raw: rest.get(separator.len_utf8()..)?.to_string(),
+    })
+}
+
+/// Creates a cue line with a new time.
+fn cue_line(time: TimePoint, separator: char, raw: String) -> VPlayerLine {
+    VPlayerLine::Cue {
+        stamp: format_time(time),
+        time,
+        separator,
+        raw,
+    }
+}
+
+/// Formats a time like `00:00:12` (rounded to seconds, negative times are written as zero).
+fn format_time(t: TimePoint) -> String {
+    let secs = (t.msecs().max(0) + 500) / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Converts the raw text of a subtitle into an entry text.
+fn decode_text(raw: &str) -> String {
+    raw.replace('|', "\n")
+}
+
+/// Converts an entry text into the raw text of a subtitle.
+fn encode_text(text: &str) -> String {
+    text.lines().collect::<Vec<&str>>().join("|")
+}
+
+impl SubtitleFileInterface for VPlayerFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let cues = self.cues();
+        Ok(cues
+            .iter()
+            .zip(self.timespans(&cues))
+            .map(|(cue, timespan)| {
+                let text = match self.v.get(cue.line_idx) {
+                    Some((VPlayerLine::Cue { raw, .. }, _)) => decode_text(raw),
+                    _ => String::new(),
+                };
+                SubtitleEntry::new(timespan, text)
+            })
+            .collect())
+    }
+
+    fn update_subtitle_entries(&mut self, new_subtitle_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let cues = self.cues();
+        check_entry_count(cues.len(), new_subtitle_entries.len())?;
+
+        let old_timespans = self.timespans(&cues);
+        let mut new_end_lines: Vec<(usize, TimePoint)> = Vec::new();
+        for ((cue, new_entry), old_timespan) in cues.iter().zip(new_subtitle_entries).zip(old_timespans) {
+            set_time(self.v.get_mut(cue.line_idx), new_entry.timespan.start);
+            if let Some((VPlayerLine::Cue { ref mut raw, .. }, _)) = self.v.get_mut(cue.line_idx) {
+                if let Some(ref text) = new_entry.line {
+                    if decode_text(raw) != *text {
+                        *raw = encode_text(text);
+                    }
+                }
+            }
+
+            // subtitles without end line get one as soon as their end changes
+            match cue.end_idx {
+                Some(end_idx) => set_time(self.v.get_mut(end_idx), new_entry.timespan.end),
+                None if format_time(new_entry.timespan.end) != format_time(old_timespan.end) => {
+                    new_end_lines.push((cue.line_idx + 1, new_entry.timespan.end));
+                }
+                None => {}
+            }
+        }
+
+        let newline = self.v.iter().map(|(_, newline)| newline.clone()).find(|newline| !newline.is_empty());
+        let newline = newline.unwrap_or_else(|| "\n".to_string());
+        for (idx, end) in new_end_lines.into_iter().rev() {
+            self.v.insert(idx, (cue_line(end, ':', String::new()), newline.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        let result: String = self
+            .v
+            .iter()
+            .map(|(line, newline)| match *line {
+                VPlayerLine::Filler(ref text) => format!("{}{}", text, newline),
+                VPlayerLine::Cue {
+                    ref stamp,
+                    separator,
+                    ref raw,
+                    ..
+                } => format!("{}{}{}{}", stamp, separator, raw, newline),
+            })
+            .collect();
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let cues = self.cues();
+        check_entry_count(cues.len(), keep.len())?;
+
+        // the line which ends a subtitle is removed with it
+        let mut removed: Vec<usize> = cues
+            .iter()
+            .zip(keep)
+            .filter(|(_, &keep)| !keep)
+            .flat_map(|(cue, _)| std::iter::once(cue.line_idx).chain(cue.end_idx))
+            .collect();
+        removed.sort_unstable();
+        for idx in removed.into_iter().rev() {
+            self.v.remove(idx);
+        }
+        Ok(())
+    }
+}
+
+/// Sets the time of a cue line (the time is only rewritten if it changes in the file).
+fn set_time(line: Option<&mut (VPlayerLine, String)>, new_time: TimePoint) {
+    if let Some((VPlayerLine::Cue { stamp, time, .. }, _)) = line {
+        if format_time(*time) != format_time(new_time) {
+            *stamp = format_time(new_time);
+        }
+        *time = new_time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+
+    const FILE: &str = "\u{feff}0:00:01:Hello|world\r\n00:00:03:\r\n\r\nnot a subtitle\r\n00:00:05 Second\r\n00:00:08=Third\r\n";
+
+    #[test]
+    fn vplayer_ignored_lines_are_kept() {
+        let (mut file, warnings) = VPlayerFile::parse_with_warnings(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(messages, vec!["line 3: ignored line 'not a subtitle'"]);
+    }
+
+    #[test]
+    fn vplayer_lines_without_text_end_subtitles() {
+        // only the first line without text ends a subtitle, the others last until the next one (the last one
+        // for three seconds)
+        let entries = VPlayerFile::parse("00:00:01:Hello|world\n00:00:02:\n00:00:03:\n00:00:04 Second\n00:00:06=Third\n")
+            .unwrap()
+            .get_subtitle_entries()
+            .unwrap();
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        assert_eq!(spans, vec![(1000, 2000), (4000, 6000), (6000, 9000)]);
+        let texts: Vec<&str> = entries.iter().filter_map(|entry| entry.line.as_deref()).collect();
+        assert_eq!(texts, vec!["Hello\nworld", "Second", "Third"]);
+    }
+
+    #[test]
+    fn vplayer_changed_ends_get_an_end_line() {
+        let mut file = VPlayerFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].timespan.end += TimeDelta::from_msecs(1000);
+        entries[1].timespan.end = TimePoint::from_msecs(7000);
+        entries[2].line = Some("Changed\nline".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(
+            to_string(&file),
+            "\u{feff}0:00:01:Hello|world\r\n00:00:04:\r\n\r\nnot a subtitle\r\n00:00:05 Second\r\n00:00:07:\r\n00:00:08=Changed|line\r\n"
+        );
+    }
+
+    #[test]
+    fn vplayer_times_are_rounded_to_seconds() {
+        // the time is only rewritten if the rounded time changes
+        let mut file = VPlayerFile::parse("0:00:01:a\n0:00:03:b\n").unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].timespan.start += TimeDelta::from_msecs(400);
+        entries[1].timespan.start += TimeDelta::from_msecs(600);
+        file.update_subtitle_entries(&entries).unwrap();
+        assert_eq!(to_string(&file), "0:00:01:a\n00:00:04:b\n");
+    }
+
+    #[test]
+    fn vplayer_removed_subtitles_take_their_end_line() {
+        let mut file = VPlayerFile::parse("00:00:01:a\n00:00:02:\n00:00:04:b\n00:00:05:\n").unwrap();
+        file.retain_entries_by_mask(&[false, true]).unwrap();
+        assert_eq!(to_string(&file), "00:00:04:b\n00:00:05:\n");
+    }
+
+    #[test]
+    fn vplayer_create() {
+        let created = VPlayerFile::create(vec![(
+            TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000)),
+            "A\nB".to_string(),
+        )])
+        .unwrap();
+        assert_eq!(to_string(&created), "00:00:01:A|B\n00:00:02:\n");
+    }
+
+    #[test]
+    fn vplayer_detection() {
+        assert!(looks_like_vplayer("\n01:02:03:x\n"));
+        assert!(!looks_like_vplayer("[1][2]x\n"));
+    }
+
+    #[test]
+    fn vplayer_parse_errors() {
+        // the minutes are out of range, so the first line is no subtitle
+        let parse = |s: &str| VPlayerFile::parse_file(s, &mut Warnings::new());
+        assert_eq!(parse_error(parse, "00:61:00:x\n").kind(), &ErrorAtLine { line_num: 0 });
+        assert_eq!(
+            parse_error(parse, "\n\nnot a subtitle\n00:00:01:x\n").kind(),
+            &ErrorAtLine { line_num: 2 }
+        );
+    }
+}
@@ -0,0 +1,1323 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use super::common::*;
+use crate::escaping::vtt_text_lines;
+use crate::warnings::{WarningKind, Warnings};
+use crate::{AssColor, SsaStyle, SubtitleEntry, SubtitleFileInterface};
+
+use crate::errors::Result as SubtitleParserResult;
+use failure::ResultExt;
+use std::fmt;
+
+use crate::timetypes::{TimePoint, TimeSpan, TimestampOutOfRange};
+
+/// `.vtt`-parser-specific errors
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the file does not start with `WEBVTT`")]
+        ExpectedHeader,
+
+        #[fail(display = "parsing the cue timing `{}` in line {} failed", line, line_num)]
+        ExpectedTimingLine { line_num: usize, line: String },
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt file parts
+
+/// A cue timestamp like "00:01:02.345" or "01:02.345".
+#[derive(Debug, Clone, Copy)]
+struct VttTimestamp {
+    time: TimePoint,
+
+    /// The timestamp was written with hours (they are always written for times of an hour and more).
+    hours: bool,
+}
+
+#[derive(Debug, Clone)]
+enum VttFilePart {
+    /// The header, the `REGION` keyword lines, empty lines, ...
+    Filler(String),
+
+    /// A `NOTE` or `STYLE` block with the original lines and the block they were parsed to (the lines are written
+    /// as long as the block is unchanged).
+    Block(VttBlock, Option<(VttBlock, String)>),
+
+    /// The identifier line of a cue (with its newline, empty if the cue has no identifier).
+    CueId(String),
+
+    /// The start of a cue timing line like "00:00:01.000 --> 00:00:02.000".
+    TimespanStart(VttTimestamp),
+
+    /// The end of a cue timing line.
+    TimespanEnd(VttTimestamp),
+
+    /// The cue settings after the end of the timing line (with the whitespace before them).
+    CueSettings(VttSettings),
+
+    /// The setting lines of a `REGION` block.
+    Region(VttSettings),
+
+    /// The payload lines of a cue with their newlines.
+    Text(String),
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt cue settings and regions
+
+/// The settings of a cue (like `align:start line:0`) or the definition of a `REGION` (like `id:fred width:40%`).
+///
+/// The settings are written unchanged until one of them is changed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VttSettings {
+    /// The names and values in the order of the file.
+    settings: Vec<(String, String)>,
+
+    /// The original text, if no setting was changed.
+    raw: Option<String>,
+}
+
+impl VttSettings {
+    /// Creates empty settings (for example for `VttFile::add_region()`).
+    pub fn new() -> VttSettings {
+        VttSettings::default()
+    }
+
+    /// Parses whitespace-separated settings like `line:0 align:start`.
+    fn parse(raw: &str) -> VttSettings {
+        let settings = raw
+            .split_whitespace()
+            .map(|setting| match setting.split_once(':') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => (setting.to_string(), String::new()),
+            })
+            .collect();
+        VttSettings {
+            settings,
+            raw: Some(raw.to_string()),
+        }
+    }
+
+    /// Returns the value of a setting like `"line"` or `"position"`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.settings.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Changes or adds a setting (whitespace is removed from the value, because it would end the setting).
+    pub fn set(&mut self, name: &str, value: &str) {
+        let value: String = value.split_whitespace().collect();
+        match self.settings.iter_mut().find(|(n, _)| n == name) {
+            Some(setting) => setting.1 = value,
+            None => self.settings.push((name.split_whitespace().collect::<String>().replace(':', ""), value)),
+        }
+        self.raw = None;
+    }
+
+    /// Removes a setting.
+    pub fn remove(&mut self, name: &str) {
+        let len = self.settings.len();
+        self.settings.retain(|(n, _)| n != name);
+        if self.settings.len() != len {
+            self.raw = None;
+        }
+    }
+
+    /// Iterates over the names and values of all settings.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.settings.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the settings as text, each setting starting with `separator` (or followed by it for `REGION` blocks).
+    fn to_string_with(&self, separator: &str, is_region: bool) -> String {
+        if let Some(ref raw) = self.raw {
+            return raw.clone();
+        }
+        let setting_strings = self.settings.iter().map(|(name, value)| match value.as_str() {
+            "" => name.clone(),
+            value => format!("{}:{}", name, value),
+        });
+        if is_region {
+            setting_strings.map(|setting| setting + separator).collect()
+        } else {
+            setting_strings.map(|setting| format!("{}{}", separator, setting)).collect()
+        }
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt comments and style sheets
+
+/// A `NOTE` (comment) or `STYLE` (CSS style sheet) block of a `.vtt` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VttBlock {
+    /// The comment of a `NOTE` block (the text after `NOTE` and the following lines, joined with `\n`).
+    Note(String),
+
+    /// The CSS of a `STYLE` block (the lines after `STYLE`, joined with `\n`).
+    Style(String),
+}
+
+impl VttBlock {
+    /// Parses the lines of a `NOTE` or `STYLE` block (`None` for other blocks).
+    fn parse(lines: &[&str]) -> Option<VttBlock> {
+        let (first, rest) = lines.split_first()?;
+        let keyword_rest = |keyword: &str| {
+            first
+                .strip_prefix(keyword)
+                .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+        };
+        if let Some(text) = keyword_rest("NOTE") {
+            let text = Some(text.trim()).filter(|text| !text.is_empty());
+            Some(VttBlock::Note(
+                text.into_iter().chain(rest.iter().copied()).collect::<Vec<_>>().join("\n"),
+            ))
+        } else if keyword_rest("STYLE").is_some() {
+            Some(VttBlock::Style(rest.join("\n")))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the block with `newline` after every line.
+    ///
+    /// Empty lines and `-->` would end the block, so empty lines are left out and `-->` is written as `--`.
+    fn to_string_with(&self, newline: &str) -> String {
+        let (keyword, text) = match *self {
+            VttBlock::Note(ref text) => ("NOTE", text),
+            VttBlock::Style(ref text) => ("STYLE", text),
+        };
+        let lines: Vec<String> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.replace("-->", "--"))
+            .collect();
+        match (self, lines.as_slice()) {
+            (VttBlock::Note(_), [line]) => format!("{} {}{}", keyword, line, newline),
+            _ => std::iter::once(keyword.to_string()).chain(lines).map(|line| line + newline).collect(),
+        }
+    }
+
+    /// Returns the `::cue` rules of a `STYLE` block as styles (nothing for `NOTE` blocks).
+    ///
+    /// `::cue` is the style `Default` and `::cue(.class)` the style `class` (other selectors keep the text in
+    /// the parentheses as name). Every selector of a rule gets its own style.
+    pub fn cue_styles(&self) -> Vec<VttCueStyle> {
+        let css = match *self {
+            VttBlock::Style(ref css) => remove_css_comments(css),
+            VttBlock::Note(_) => return Vec::new(),
+        };
+
+        let mut result = Vec::new();
+        for rule in css.split('}') {
+            let (selectors, declarations) = match rule.split_once('{') {
+                Some(rule) => rule,
+                None => continue,
+            };
+            for selector in selectors.split(',').map(str::trim) {
+                let name = match selector.strip_prefix("::cue") {
+                    Some("") => "Default",
+                    Some(argument) => match argument.strip_prefix('(').and_then(|a| a.strip_suffix(')')) {
+                        Some(argument) => argument.trim().strip_prefix('.').unwrap_or(argument.trim()),
+                        None => continue,
+                    },
+                    None => continue,
+                };
+                let mut style = VttCueStyle {
+                    name: name.to_string(),
+                    ..VttCueStyle::default()
+                };
+                for (property, value) in declarations.split(';').filter_map(|d| d.split_once(':')) {
+                    let value = value.trim().to_ascii_lowercase();
+                    match property.trim() {
+                        "color" => style.color = Some(value),
+                        "font-family" => style.font_family = Some(value.split(',').next().unwrap_or("").trim().trim_matches(['"', '\'']).to_string()),
+                        "font-style" => style.italic = Some(value == "italic" || value == "oblique"),
+                        "font-weight" => style.bold = Some(value == "bold" || value == "bolder" || value.parse::<u32>().is_ok_and(|w| w >= 600)),
+                        "text-decoration" => {
+                            style.underline = Some(value.contains("underline"));
+                            style.strike_out = Some(value.contains("line-through"));
+                        }
+                        _ => {}
+                    }
+                }
+                result.push(style);
+            }
+        }
+        result
+    }
+}
+
+/// The properties of a `::cue` rule in a `STYLE` block which can be used by other formats.
+///
+/// The properties which the rule doesn't set are `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VttCueStyle {
+    /// `Default` for `::cue`, the class for `::cue(.class)`.
+    pub name: String,
+
+    /// The CSS `color` in lowercase (like `#ff0` or `yellow`).
+    pub color: Option<String>,
+
+    /// The first font of `font-family` (without quotes).
+    pub font_family: Option<String>,
+
+    /// `font-style` is `italic` or `oblique`.
+    pub italic: Option<bool>,
+
+    /// `font-weight` is `bold`, `bolder` or at least 600.
+    pub bold: Option<bool>,
+
+    /// `text-decoration` contains `underline`.
+    pub underline: Option<bool>,
+
+    /// `text-decoration` contains `line-through`.
+    pub strike_out: Option<bool>,
+}
+
+impl VttCueStyle {
+    /// Converts the style to an `.ass` style.
+    ///
+    /// Colors written as hex (`#ff0`, `#ffff00`) or basic color names are mapped. The properties which the rule
+    /// doesn't set (and unknown colors) keep the values of `SsaStyle::default()`.
+    pub fn to_ssa_style(&self) -> SsaStyle {
+        let default = SsaStyle::default();
+        SsaStyle {
+            name: self.name.clone(),
+            fontname: self.font_family.clone().unwrap_or(default.fontname),
+            primary_colour: self.color.as_deref().and_then(css_color).unwrap_or(default.primary_colour),
+            bold: self.bold.unwrap_or(default.bold),
+            italic: self.italic.unwrap_or(default.italic),
+            underline: self.underline.unwrap_or(default.underline),
+            strike_out: self.strike_out.unwrap_or(default.strike_out),
+            ..default
+        }
+    }
+}
+
+/// Parses a CSS color like `#ff0` or `yellow`.
+fn css_color(value: &str) -> Option<AssColor> {
+    const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+        ("black", (0, 0, 0)),
+        ("white", (255, 255, 255)),
+        ("red", (255, 0, 0)),
+        ("lime", (0, 255, 0)),
+        ("blue", (0, 0, 255)),
+        ("yellow", (255, 255, 0)),
+        ("cyan", (0, 255, 255)),
+        ("magenta", (255, 0, 255)),
+    ];
+    AssColor::from_css_hex(value).or_else(|| {
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| *name == value)
+            .map(|&(_, (r, g, b))| AssColor::from_rgb(r, g, b))
+    })
+}
+
+/// Removes all `/* ... */` comments from the CSS.
+fn remove_css_comments(css: &str) -> String {
+    let mut result = String::new();
+    let mut rest = css;
+    while let Some((before, after)) = rest.split_once("/*") {
+        result.push_str(before);
+        rest = after.split_once("*/").map_or("", |(_, rest)| rest);
+    }
+    result.push_str(rest);
+    result
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt cue text
+
+/// The tag names of the cue text spans.
+const SPAN_NAMES: &[&str] = &["c", "i", "b", "u", "v", "ruby", "rt", "lang"];
+
+/// A part of a cue text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VttCueNode {
+    /// Text as it is written in the cue (with escapes like `&amp;`, and unknown tags like `<font>`).
+    Text(String),
+
+    /// A timestamp tag like `<00:01.500>` (without the angle brackets).
+    Timestamp(String),
+
+    /// A span like `<v Bob>...</v>` or `<c.loud>...</c>`.
+    Span(VttSpan),
+}
+
+/// The start tag of a span like `<v.loud Bob>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VttTag {
+    /// The tag name (`c`, `i`, `b`, `u`, `v`, `ruby`, `rt` or `lang`).
+    pub name: String,
+
+    /// The classes (`<c.loud.red>` has the classes `loud` and `red`), which are styled by `::cue(.loud)` rules.
+    pub classes: Vec<String>,
+
+    /// The text after the name and the classes: the speaker of `<v Bob>` or the language of `<lang en>`.
+    pub annotation: Option<String>,
+}
+
+impl VttTag {
+    /// Creates a tag without classes and annotation.
+    pub fn new(name: &str) -> VttTag {
+        VttTag {
+            name: name.to_string(),
+            classes: Vec::new(),
+            annotation: None,
+        }
+    }
+
+    /// Parses the text between the angle brackets of a start tag (`None` for unknown tags).
+    fn parse(body: &str) -> Option<VttTag> {
+        let (head, annotation) = body.split_at(body.find([' ', '\t']).unwrap_or(body.len()));
+        let mut names = head.split('.');
+        let name = names.next().filter(|name| SPAN_NAMES.contains(name))?;
+        Some(VttTag {
+            name: name.to_string(),
+            classes: names.map(str::to_string).collect(),
+            annotation: Some(annotation.trim().to_string()).filter(|annotation| !annotation.is_empty()),
+        })
+    }
+}
+
+impl fmt::Display for VttTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for class in &self.classes {
+            write!(f, ".{}", class.replace(['.', ' ', '\t', '>'], ""))?;
+        }
+        if let Some(ref annotation) = self.annotation {
+            write!(f, " {}", annotation.replace('>', "&gt;"))?;
+        }
+        f.write_str(">")
+    }
+}
+
+/// A span of a cue text: a start tag, the content and (usually) an end tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VttSpan {
+    /// The start tag.
+    pub tag: VttTag,
+
+    /// The content of the span.
+    pub children: Vec<VttCueNode>,
+
+    /// The start tag as written in the text and the tag it was parsed to (written as long as the tag is unchanged).
+    original: Option<(VttTag, String)>,
+
+    /// The span has an end tag (`<v Bob>Hello` without `</v>` is common).
+    has_end_tag: bool,
+}
+
+impl VttSpan {
+    /// Creates a span (with an end tag).
+    pub fn new(tag: VttTag, children: Vec<VttCueNode>) -> VttSpan {
+        VttSpan {
+            tag,
+            children,
+            original: None,
+            has_end_tag: true,
+        }
+    }
+
+    /// Returns the speaker of a voice span like `<v Bob>` (`None` for other spans).
+    pub fn speaker(&self) -> Option<&str> {
+        self.tag.annotation.as_deref().filter(|_| self.tag.name == "v")
+    }
+}
+
+impl fmt::Display for VttSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.original {
+            Some((ref tag, ref raw)) if *tag == self.tag => f.write_str(raw)?,
+            _ => write!(f, "{}", self.tag)?,
+        }
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        if self.has_end_tag {
+            write!(f, "</{}>", self.tag.name)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for VttCueNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VttCueNode::Text(ref text) => f.write_str(text),
+            VttCueNode::Timestamp(ref timestamp) => write!(f, "<{}>", timestamp),
+            VttCueNode::Span(ref span) => write!(f, "{}", span),
+        }
+    }
+}
+
+/// The text of a cue with its voice, class and style spans.
+///
+/// Writing the text again gives exactly the parsed text (until the spans are changed).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VttCueText {
+    /// The text and the top-level spans in the order of the text.
+    pub nodes: Vec<VttCueNode>,
+}
+
+impl VttCueText {
+    /// Parses a cue text like `<v Bob>Hello <c.loud>you</c></v>`.
+    ///
+    /// Unknown tags and end tags without a start tag stay text, and spans without an end tag end with their parent.
+    pub fn parse(text: &str) -> VttCueText {
+        let mut nodes = Vec::new();
+        // the spans which are not closed yet (the innermost one is the last)
+        let mut open: Vec<VttSpan> = Vec::new();
+        let push = |open: &mut Vec<VttSpan>, nodes: &mut Vec<VttCueNode>, node: VttCueNode| {
+            let nodes = open.last_mut().map_or(nodes, |span| &mut span.children);
+            match (nodes.last_mut(), node) {
+                (Some(VttCueNode::Text(ref mut last)), VttCueNode::Text(text)) => last.push_str(&text),
+                (_, node) => nodes.push(node),
+            }
+        };
+
+        let mut rest = text;
+        while !rest.is_empty() {
+            let (before, tag_and_rest) = rest.split_at(rest.find('<').unwrap_or(rest.len()));
+            if !before.is_empty() {
+                push(&mut open, &mut nodes, VttCueNode::Text(before.to_string()));
+            }
+            let (tag, after) = match tag_and_rest.find('>') {
+                Some(idx) => tag_and_rest.split_at(idx + 1),
+                None => (tag_and_rest, ""),
+            };
+            rest = after;
+            let body = match tag.strip_prefix('<').and_then(|tag| tag.strip_suffix('>')) {
+                Some(body) => body,
+                None => {
+                    push(&mut open, &mut nodes, VttCueNode::Text(tag.to_string()));
+                    continue;
+                }
+            };
+
+            if let Some(name) = body.strip_prefix('/') {
+                match open.iter().rposition(|span| span.tag.name == name.trim()) {
+                    Some(idx) => {
+                        // the inner spans without end tag end here too
+                        while open.len() > idx + 1 {
+                            if let Some(span) = open.pop() {
+                                push(&mut open, &mut nodes, VttCueNode::Span(span));
+                            }
+                        }
+                        if let Some(mut span) = open.pop() {
+                            span.has_end_tag = true;
+                            push(&mut open, &mut nodes, VttCueNode::Span(span));
+                        }
+                    }
+                    None => push(&mut open, &mut nodes, VttCueNode::Text(tag.to_string())),
+                }
+            } else if body.starts_with(|c: char| c.is_ascii_digit()) {
+                push(&mut open, &mut nodes, VttCueNode::Timestamp(body.to_string()));
+            } else if let Some(vtt_tag) = VttTag::parse(body) {
+                open.push(VttSpan {
+                    tag: vtt_tag.clone(),
+                    children: Vec::new(),
+                    original: Some((vtt_tag, tag.to_string())),
+                    has_end_tag: false,
+                });
+            } else {
+                push(&mut open, &mut nodes, VttCueNode::Text(tag.to_string()));
+            }
+        }
+        while let Some(span) = open.pop() {
+            push(&mut open, &mut nodes, VttCueNode::Span(span));
+        }
+
+        VttCueText { nodes }
+    }
+
+    /// Iterates over all spans (also the nested ones) in the order of their start tags.
+    pub fn spans(&self) -> impl Iterator<Item = &VttSpan> {
+        fn collect<'a>(nodes: &'a [VttCueNode], result: &mut Vec<&'a VttSpan>) {
+            for node in nodes {
+                if let VttCueNode::Span(ref span) = *node {
+                    result.push(span);
+                    collect(&span.children, result);
+                }
+            }
+        }
+        let mut result = Vec::new();
+        collect(&self.nodes, &mut result);
+        result.into_iter()
+    }
+
+    /// Returns the speakers of all voice spans like `<v Bob>`.
+    pub fn speakers(&self) -> Vec<&str> {
+        self.spans().filter_map(VttSpan::speaker).collect()
+    }
+}
+
+impl fmt::Display for VttCueText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            write!(f, "{}", node)?;
+        }
+        Ok(())
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt file
+
+/// Represents a reconstructable `.vtt` (WebVTT) file.
+///
+/// Everything except the cue timings, texts, settings, regions, comments and style sheets (header, empty lines, ...)
+/// is saved into `VttFilePart::Filler(...)`, so a changed file still has the same meta-information.
+#[derive(Debug, Clone)]
+pub struct VttFile {
+    v: Vec<VttFilePart>,
+
+    /// The line ending for new text lines (the first one of the file).
+    newline: String,
+}
+
+impl VttFile {
+    fn new(v: Vec<VttFilePart>, newline: String) -> VttFile {
+        // cleans up multiple fillers after another
+        let new_file_parts = dedup_string_parts(v, |part: &mut VttFilePart| match *part {
+            VttFilePart::Filler(ref mut text) => Some(text),
+            _ => None,
+        });
+        VttFile { v: new_file_parts, newline }
+    }
+
+    /// Creates a WebVTT file from scratch.
+    pub fn create(v: Vec<(TimeSpan, String)>) -> SubtitleParserResult<VttFile> {
+        let time = |t: TimePoint| {
+            let p = if t.msecs() < 0 { TimePoint::from_msecs(0) } else { t };
+            format!("{:02}:{:02}:{:02}.{:03}", p.hours(), p.mins_comp(), p.secs_comp(), p.msecs_comp())
+        };
+        let mut result = String::from("WEBVTT\n");
+        for (timespan, text) in v {
+            result.push_str(&format!("\n{} --> {}\n", time(timespan.start), time(timespan.end)));
+            for line in vtt_text_lines(&text) {
+                result.push_str(&line);
+                result.push('\n');
+            }
+        }
+        Self::parse(&result)
+    }
+
+    /// Returns the indices of the `CueId` and the `Text` part of every cue.
+    fn cue_bounds(&self) -> Vec<(usize, usize)> {
+        let ids = self.v.iter().enumerate().filter(|(_, part)| matches!(part, VttFilePart::CueId(_)));
+        let texts = self.v.iter().enumerate().filter(|(_, part)| matches!(part, VttFilePart::Text(_)));
+        ids.zip(texts).map(|((id, _), (text, _))| (id, text)).collect()
+    }
+
+    /// Returns the settings of every cue (in the order of `get_subtitle_entries()`).
+    pub fn cue_settings(&self) -> Vec<&VttSettings> {
+        self.v
+            .iter()
+            .filter_map(|part| match *part {
+                VttFilePart::CueSettings(ref settings) => Some(settings),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the settings of every cue for changing them, like `settings.set("line", "0")` to move a cue to the top.
+    pub fn cue_settings_mut(&mut self) -> Vec<&mut VttSettings> {
+        self.v
+            .iter_mut()
+            .filter_map(|part| match *part {
+                VttFilePart::CueSettings(ref mut settings) => Some(settings),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the definitions of all `REGION` blocks.
+    pub fn regions(&self) -> Vec<&VttSettings> {
+        self.v
+            .iter()
+            .filter_map(|part| match *part {
+                VttFilePart::Region(ref settings) => Some(settings),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the definitions of all `REGION` blocks for changing them.
+    pub fn regions_mut(&mut self) -> Vec<&mut VttSettings> {
+        self.v
+            .iter_mut()
+            .filter_map(|part| match *part {
+                VttFilePart::Region(ref mut settings) => Some(settings),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Adds a `REGION` block before the first cue (cues are shown in it with the cue setting `region:<id>`).
+    pub fn add_region(&mut self, mut region: VttSettings) {
+        region.raw = None;
+        let header = VttFilePart::Filler(format!("REGION{}", self.newline));
+        self.insert_before_cues(vec![header, VttFilePart::Region(region)]);
+    }
+
+    /// Returns all `NOTE` and `STYLE` blocks.
+    pub fn blocks(&self) -> Vec<&VttBlock> {
+        self.v
+            .iter()
+            .filter_map(|part| match *part {
+                VttFilePart::Block(ref block, _) => Some(block),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns all `NOTE` and `STYLE` blocks for changing them.
+    pub fn blocks_mut(&mut self) -> Vec<&mut VttBlock> {
+        self.v
+            .iter_mut()
+            .filter_map(|part| match *part {
+                VttFilePart::Block(ref mut block, _) => Some(block),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Adds a `NOTE` or `STYLE` block before the first cue (`STYLE` blocks are only allowed there).
+    pub fn add_block(&mut self, block: VttBlock) {
+        self.insert_before_cues(vec![VttFilePart::Block(block, None)]);
+    }
+
+    /// Removes every `NOTE` and `STYLE` block for which `f` returns `false`.
+    pub fn retain_blocks<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&VttBlock) -> bool,
+    {
+        let removed: Vec<usize> = self
+            .v
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| matches!(part, VttFilePart::Block(block, _) if !f(block)))
+            .map(|(idx, _)| idx)
+            .collect();
+
+        // removing from the back keeps the indices of the front valid
+        for &idx in removed.iter().rev() {
+            self.remove_block_parts(idx, idx);
+        }
+        self.v = VttFile::new(std::mem::take(&mut self.v), self.newline.clone()).v;
+    }
+
+    /// Returns the styles of the `::cue` rules in all `STYLE` blocks (see `VttBlock::cue_styles()`).
+    pub fn cue_styles(&self) -> Vec<VttCueStyle> {
+        self.blocks().into_iter().flat_map(VttBlock::cue_styles).collect()
+    }
+
+    /// Returns the parsed text of every cue (in the order of `get_subtitle_entries()`).
+    ///
+    /// Change the texts with `update_subtitle_entries()`, using `VttCueText::to_string()` as `line`.
+    pub fn cue_texts(&self) -> Vec<VttCueText> {
+        self.v
+            .iter()
+            .filter_map(|part| match *part {
+                VttFilePart::Text(ref text) => Some(VttCueText::parse(text.replace("\r\n", "\n").replace('\r', "\n").trim_end_matches('\n'))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the speakers of the voice spans of every cue (like `Bob` for `<v Bob>Hello</v>`).
+    pub fn speakers(&self) -> Vec<Vec<String>> {
+        self.cue_texts()
+            .iter()
+            .map(|text| text.speakers().into_iter().map(str::to_string).collect())
+            .collect()
+    }
+
+    /// Returns the value of a `Language:` line in the header (like `WEBVTT\nLanguage: en`), the language of all cues.
+    pub fn language(&self) -> Option<String> {
+        let header = match self.v.first() {
+            Some(VttFilePart::Filler(ref header)) => header,
+            _ => return None,
+        };
+        (header.lines().skip(1))
+            .take_while(|line| !line.trim().is_empty())
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("Language"))
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Inserts the parts of a block (and the empty line after it) before the first cue.
+    fn insert_before_cues(&mut self, block_parts: Vec<VttFilePart>) {
+        let newline = self.newline.clone();
+        let mut parts = Vec::new();
+        let idx = match self.cue_bounds().first() {
+            // a cue always follows an empty line
+            Some(&(first, _)) => first,
+            None => {
+                // the last block has to be ended by an empty line
+                let data = self.to_data().unwrap_or_default();
+                let trailing_newlines = data.iter().rev().take_while(|&&b| b == b'\n' || b == b'\r').count();
+                if trailing_newlines < 2 {
+                    parts.push(VttFilePart::Filler(newline.repeat(2 - trailing_newlines)));
+                }
+                self.v.len()
+            }
+        };
+        parts.extend(block_parts);
+        parts.push(VttFilePart::Filler(newline));
+        let tail = self.v.split_off(idx);
+        self.v.extend(parts);
+        self.v.extend(tail);
+        self.v = VttFile::new(std::mem::take(&mut self.v), self.newline.clone()).v;
+    }
+}
+
+impl VttFile {
+    /// Returns the text of every filler part and `None` for the other parts (see `SubtitleFile::parse_metrics()`).
+    pub(crate) fn filler_parts(&self) -> Vec<Option<&str>> {
+        self.v
+            .iter()
+            .map(|part| match *part {
+                VttFilePart::Filler(ref text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl SubtitleFileInterface for VttFile {
+    fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        let mut result = Vec::new();
+        let mut start = TimePoint::from_msecs(0);
+        let mut end = TimePoint::from_msecs(0);
+        let language = self.language();
+        for part in &self.v {
+            match *part {
+                VttFilePart::TimespanStart(t) => start = t.time,
+                VttFilePart::TimespanEnd(t) => end = t.time,
+                VttFilePart::Text(ref text) => {
+                    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                    let mut entry = SubtitleEntry::new(TimeSpan::new(start, end), text.trim_end_matches('\n').to_string());
+                    entry.language = language.clone();
+                    result.push(entry);
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn update_subtitle_entries(&mut self, ts: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let count = self.v.iter().filter(|part| matches!(part, VttFilePart::Text(_))).count();
+        check_entry_count(count, ts.len())?;
+
+        let newline = &self.newline;
+        let mut entries = ts.iter();
+        let mut entry = None;
+        for part in self.v.iter_mut() {
+            match *part {
+                VttFilePart::TimespanStart(ref mut t) => {
+                    entry = entries.next();
+                    if let Some(entry) = entry {
+                        t.time = entry.timespan.start;
+                    }
+                }
+                VttFilePart::TimespanEnd(ref mut t) => {
+                    if let Some(entry) = entry {
+                        t.time = entry.timespan.end;
+                    }
+                }
+                VttFilePart::Text(ref mut text) => {
+                    if let Some(line) = entry.and_then(|entry| entry.line.as_ref()) {
+                        *text = vtt_text_lines(line).into_iter().map(|line| line + newline).collect();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn to_data(&self) -> SubtitleParserResult<Vec<u8>> {
+        // timing to string like "00:03:28.308" (WebVTT has no negative times)
+        let fn_timing_to_string = |t: VttTimestamp| {
+            let p = if t.time.msecs() < 0 { TimePoint::from_msecs(0) } else { t.time };
+            if t.hours || p.hours() > 0 {
+                format!("{:02}:{:02}:{:02}.{:03}", p.hours(), p.mins_comp(), p.secs_comp(), p.msecs_comp())
+            } else {
+                format!("{:02}:{:02}.{:03}", p.mins_comp(), p.secs_comp(), p.msecs_comp())
+            }
+        };
+
+        let fn_file_part_to_string = |part: &VttFilePart| {
+            use self::VttFilePart::*;
+            match *part {
+                Filler(ref t) | CueId(ref t) | Text(ref t) => t.clone(),
+                TimespanStart(t) | TimespanEnd(t) => fn_timing_to_string(t),
+                CueSettings(ref settings) => settings.to_string_with(" ", false),
+                Region(ref settings) => settings.to_string_with(&self.newline, true),
+                Block(ref block, Some((ref original, ref raw))) if block == original => raw.clone(),
+                Block(ref block, _) => block.to_string_with(&self.newline),
+            }
+        };
+
+        let result: String = self.v.iter().map(fn_file_part_to_string).collect();
+
+        Ok(result.into_bytes())
+    }
+
+    fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        let cue_bounds = self.cue_bounds();
+        check_entry_count(cue_bounds.len(), keep.len())?;
+
+        // removing from the back keeps the indices of the front valid
+        for (&(first, last), _) in cue_bounds.iter().zip(keep).rev().filter(|(_, &keep)| !keep) {
+            self.remove_block_parts(first, last);
+        }
+
+        self.v = VttFile::new(std::mem::take(&mut self.v), self.newline.clone()).v;
+        Ok(())
+    }
+}
+
+impl VttFile {
+    /// Removes the parts `first..=last` of a block and the empty line which separates it from the next block (or
+    /// the previous one for the last block).
+    fn remove_block_parts(&mut self, first: usize, last: usize) {
+        let mut removed_separator = false;
+        if let Some(VttFilePart::Filler(ref mut next)) = self.v.get_mut(last + 1) {
+            let (line, newl) = get_lines_non_destructive(next).into_iter().next().unwrap_or_default();
+            if line.trim().is_empty() && !newl.is_empty() {
+                next.drain(..line.len() + newl.len());
+                removed_separator = true;
+            }
+        }
+        if let Some(VttFilePart::Filler(ref mut prev)) = first.checked_sub(1).and_then(|i| self.v.get_mut(i)) {
+            let without_newl = prev.strip_suffix("\r\n").or_else(|| prev.strip_suffix(['\n', '\r']));
+            if let Some(line_start) = without_newl.filter(|_| !removed_separator).map(|s| s.trim_end_matches([' ', '\t']).len()) {
+                prev.truncate(line_start);
+            }
+        }
+        self.v.drain(first..=last);
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////////////////////////////
+// .vtt parser
+
+impl VttFile {
+    /// Parse a `.vtt` subtitle string to `VttFile`.
+    pub fn parse(s: &str) -> SubtitleParserResult<VttFile> {
+        Ok(Self::parse_with_warnings(s)?.0)
+    }
+
+    /// Parse a `.vtt` subtitle string to `VttFile` and also return all non-fatal issues.
+    pub fn parse_with_warnings(s: &str) -> SubtitleParserResult<(VttFile, Warnings)> {
+        let mut warnings = Warnings::new();
+        let file = Self::parse_inner(s, &mut warnings).with_context(|_| crate::ErrorKind::ParsingError)?;
+        warnings.check_timestamps(&file.get_subtitle_entries()?);
+        Ok((file, warnings))
+    }
+}
+
+// implement parsing functions
+impl VttFile {
+    fn parse_inner(i: &str, warnings: &mut Warnings) -> Result<VttFile> {
+        let mut result = Vec::new();
+        let (bom, s) = split_bom(i);
+        result.push(VttFilePart::Filler(bom.to_string()));
+
+        let lines = get_lines_non_destructive(s);
+        let newline = lines.iter().map(|(_, newl)| newl.clone()).find(|newl| !newl.is_empty());
+
+        // the header is "WEBVTT" optionally followed by a space or tab and some text
+        let header = lines.first().map(|(line, _)| line.as_str()).unwrap_or("");
+        let header_rest = header.strip_prefix("WEBVTT").ok_or(ExpectedHeader)?;
+        if !(header_rest.is_empty() || header_rest.starts_with([' ', '\t'])) {
+            return Err(ExpectedHeader.into());
+        }
+
+        // the blocks of lines are separated by empty lines (the header is the first block)
+        let mut block = Vec::new();
+        let mut is_header = true;
+        for (line_num, (line, newl)) in lines.into_iter().enumerate() {
+            if !line.trim().is_empty() {
+                block.push((line_num, line, newl));
+                continue;
+            }
+            if !block.is_empty() {
+                result.append(&mut Self::parse_block(std::mem::take(&mut block), is_header, warnings)?);
+                is_header = false;
+            }
+            result.push(VttFilePart::Filler(line + &newl));
+        }
+        result.append(&mut Self::parse_block(block, is_header, warnings)?);
+
+        Ok(VttFile::new(result, newline.unwrap_or_else(|| "\n".to_string())))
+    }
+
+    /// Converts the lines of a block to file parts.
+    fn parse_block(block: Vec<(usize, String, String)>, is_header: bool, warnings: &mut Warnings) -> Result<Vec<VttFilePart>> {
+        if block.is_empty() {
+            return Ok(Vec::new());
+        }
+        let to_filler = |block: Vec<(usize, String, String)>| block.into_iter().map(|(_, line, newl)| VttFilePart::Filler(line + &newl)).collect();
+
+        // the timing line is the first or (after the cue identifier) the second line of a cue
+        let timing_idx = block.iter().take(2).position(|(_, line, _)| line.contains("-->"));
+        let timing_idx = match timing_idx {
+            Some(timing_idx) if !is_header => timing_idx,
+            _ => {
+                let is_known_block = ["NOTE", "STYLE", "REGION"].iter().any(|keyword| {
+                    block
+                        .first()
+                        .and_then(|(_, line, _)| line.strip_prefix(keyword))
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+                });
+                if let Some((line_num, line, _)) = block.first().filter(|_| !is_header && !is_known_block) {
+                    warnings.push(Some(*line_num), WarningKind::IgnoredLine { line: line.clone() });
+                }
+                if !is_header && block.first().is_some_and(|(_, line, _)| line.trim_end() == "REGION") {
+                    let mut lines = block.into_iter();
+                    let header = lines.next().map(|(_, line, newl)| line + &newl).unwrap_or_default();
+                    let definition: String = lines.map(|(_, line, newl)| line + &newl).collect();
+                    return Ok(vec![VttFilePart::Filler(header), VttFilePart::Region(VttSettings::parse(&definition))]);
+                }
+                let lines: Vec<&str> = block.iter().map(|(_, line, _)| line.as_str()).collect();
+                if let Some(vtt_block) = VttBlock::parse(&lines).filter(|_| !is_header) {
+                    let raw = block.iter().map(|(_, line, newl)| format!("{}{}", line, newl)).collect();
+                    return Ok(vec![VttFilePart::Block(vtt_block.clone(), Some((vtt_block, raw)))]);
+                }
+                return Ok(to_filler(block));
+            }
+        };
+
+        let mut lines = block.into_iter();
+        let mut result = Vec::new();
+        result.push(VttFilePart::CueId(match timing_idx {
+            0 => String::new(),
+            _ => lines.next().map(|(_, line, newl)| line + &newl).unwrap_or_default(),
+        }));
+        if let Some((line_num, line, newl)) = lines.next() {
+            result.append(&mut Self::parse_timing_line(line_num, &line)?);
+            result.push(VttFilePart::Filler(newl));
+        }
+        result.push(VttFilePart::Text(lines.map(|(_, line, newl)| line + &newl).collect()));
+        Ok(result)
+    }
+
+    /// Parses a cue timing line like "00:00:01.000 --> 00:00:02.000 align:start".
+    fn parse_timing_line(line_num: usize, line: &str) -> Result<Vec<VttFilePart>> {
+        let error = || ExpectedTimingLine {
+            line_num,
+            line: line.to_string(),
+        };
+
+        let timestamp = |s: &str| match Self::parse_timestamp(s) {
+            Some(timestamp) => Ok(timestamp.with_context(|_| error())?),
+            None => Err(Error::from(error())),
+        };
+
+        let (start_str, rest) = line.split_once("-->").ok_or_else(error)?;
+        let (ws1, start_str, ws2) = trim_non_destructive(start_str);
+        let (ws3, rest) = trim_left_ws(rest);
+        let (end_str, settings) = rest.split_at(rest.find([' ', '\t']).unwrap_or(rest.len()));
+
+        Ok(vec![
+            VttFilePart::Filler(ws1),
+            VttFilePart::TimespanStart(timestamp(&start_str)?),
+            VttFilePart::Filler(format!("{}-->{}", ws2, ws3)),
+            VttFilePart::TimespanEnd(timestamp(end_str)?),
+            VttFilePart::CueSettings(VttSettings::parse(settings)),
+        ])
+    }
+
+    /// Parses a timestamp like `01:02:03.456` or `02:03.456` (`None` if it has another syntax).
+    fn parse_timestamp(s: &str) -> Option<std::result::Result<VttTimestamp, TimestampOutOfRange>> {
+        let number = |s: &str| {
+            Some(s)
+                .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|s| s.parse::<i64>().ok())
+        };
+
+        let (hms, msecs) = s.split_once('.')?;
+        let msecs = number(msecs).filter(|_| msecs.len() == 3)?;
+        let fields: Vec<&str> = hms.split(':').collect();
+        let (hours, mins, secs) = match *fields.as_slice() {
+            [mins, secs] => (None, mins, secs),
+            [hours, mins, secs] => (Some(number(hours)?), mins, secs),
+            _ => return None,
+        };
+        if mins.len() != 2 || secs.len() != 2 {
+            return None;
+        }
+
+        let time = TimePoint::try_from_components(hours.unwrap_or(0), number(mins)?, number(secs)?, msecs);
+        Some(time.map(|time| VttTimestamp {
+            time,
+            hours: hours.is_some(),
+        }))
+    }
+}
+
+/// Splits a string into the leading spaces and tabs and the rest.
+fn trim_left_ws(s: &str) -> (&str, &str) {
+    let rest = s.trim_start_matches([' ', '\t']);
+    s.split_at(s.len() - rest.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{assert_round_trip, parse_error, to_string};
+    use crate::timetypes::TimeDelta;
+
+    #[test]
+    fn vtt_cue_settings_and_regions() {
+        let input =
+            "WEBVTT\n\nREGION\nid:fred\nwidth:40%\n\n00:01.000 --> 00:02.000  region:fred align:left\nHello\n\n00:03.000 --> 00:04.000\nWorld\n";
+        let mut file = VttFile::parse(input).unwrap();
+        assert_eq!(to_string(&file), input);
+        assert_eq!(file.regions()[0].get("width"), Some("40%"));
+
+        let mut settings = file.cue_settings_mut();
+        settings[0].remove("align");
+        settings[0].set("line", "0");
+        settings[1].set("position", "10% ");
+        file.regions_mut()[0].set("lines", "3");
+        let mut region = VttSettings::new();
+        region.set("id", "bob");
+        file.add_region(region);
+        assert_eq!(
+            to_string(&file),
+            "WEBVTT\n\nREGION\nid:fred\nwidth:40%\nlines:3\n\nREGION\nid:bob\n\n\
+             00:01.000 --> 00:02.000 region:fred line:0\nHello\n\n00:03.000 --> 00:04.000 position:10%\nWorld\n"
+        );
+    }
+
+    #[test]
+    fn vtt_note_and_style_blocks() {
+        let input = "WEBVTT\n\nSTYLE\n::cue { color: yellow }\n\nNOTE a comment\n\nNOTE\ntwo\nlines\n\n00:01.000 --> 00:02.000\nHello\n";
+        let mut file = VttFile::parse(input).unwrap();
+        assert_eq!(to_string(&file), input);
+        assert_eq!(
+            file.blocks(),
+            vec![
+                &VttBlock::Style("::cue { color: yellow }".to_string()),
+                &VttBlock::Note("a comment".to_string()),
+                &VttBlock::Note("two\nlines".to_string()),
+            ]
+        );
+
+        *file.blocks_mut()[1] = VttBlock::Note("changed --> twice\n\nhere".to_string());
+        file.retain_blocks(|block| *block != VttBlock::Note("two\nlines".to_string()));
+        file.add_block(VttBlock::Note("added".to_string()));
+        assert_eq!(
+            to_string(&file),
+            "WEBVTT\n\nSTYLE\n::cue { color: yellow }\n\nNOTE\nchanged -- twice\nhere\n\nNOTE added\n\n00:01.000 --> 00:02.000\nHello\n"
+        );
+
+        let mut file = VttFile::parse("WEBVTT\n").unwrap();
+        file.add_block(VttBlock::Style("::cue { color: red }".to_string()));
+        assert_eq!(to_string(&file), "WEBVTT\n\nSTYLE\n::cue { color: red }\n\n");
+    }
+
+    #[test]
+    fn vtt_blocks_in_header_and_cues() {
+        // `NOTE` in the header or as cue identifier doesn't start a block
+        let input = "WEBVTT\nNOTE header\n\nNOTE\n00:01.000 --> 00:02.000\nHello\n\nREGION\nid:fred\n";
+        let file = VttFile::parse(input).unwrap();
+        assert!(file.blocks().is_empty());
+        assert_eq!(to_string(&file), input);
+    }
+
+    #[test]
+    fn vtt_cue_styles() {
+        let css = "/* comment */ ::cue { color: #FF0; font-style: italic }\n\
+                   ::cue(.loud), ::cue(b) { font-weight: 700; text-decoration: underline; font-family: \"Verdana\", sans-serif }\n\
+                   video { color: red }";
+        let styles = VttBlock::Style(css.to_string()).cue_styles();
+        let names: Vec<&str> = styles.iter().map(|style| style.name.as_str()).collect();
+        assert_eq!(names, vec!["Default", "loud", "b"]);
+        assert_eq!(
+            (styles[0].color.as_deref(), styles[0].italic, styles[0].bold),
+            (Some("#ff0"), Some(true), None)
+        );
+        assert_eq!(
+            (
+                styles[1].bold,
+                styles[1].underline,
+                styles[1].strike_out,
+                styles[1].font_family.as_deref()
+            ),
+            (Some(true), Some(true), Some(false), Some("verdana"))
+        );
+        assert_eq!(
+            styles[2],
+            VttCueStyle {
+                name: "b".to_string(),
+                ..styles[1].clone()
+            }
+        );
+        assert!(VttBlock::Note("::cue { color: red }".to_string()).cue_styles().is_empty());
+
+        let ssa_style = styles[0].to_ssa_style();
+        assert_eq!(
+            (ssa_style.name.as_str(), ssa_style.primary_colour, ssa_style.italic, ssa_style.bold),
+            ("Default", AssColor::from_rgb(255, 255, 0), true, false)
+        );
+        assert_eq!(styles[1].to_ssa_style().fontname, "verdana");
+        let unknown_color = VttCueStyle {
+            color: Some("rgb(1, 2, 3)".to_string()),
+            ..VttCueStyle::default()
+        };
+        assert_eq!(unknown_color.to_ssa_style().primary_colour, SsaStyle::default().primary_colour);
+
+        let file = VttFile::parse(&format!("WEBVTT\n\nSTYLE\n{}\n\nSTYLE\n::cue(.red) {{ color: red }}\n", css)).unwrap();
+        let names: Vec<String> = file.cue_styles().into_iter().map(|style| style.name).collect();
+        assert_eq!(names, vec!["Default", "loud", "b", "red"]);
+    }
+
+    #[test]
+    fn vtt_voice_and_class_spans() {
+        let text = "<v.loud Bob>Hello <c.red.big>you</c></v> &amp; <v Alice>hi <00:01.500><i>there";
+        let mut cue = VttCueText::parse(text);
+        assert_eq!(cue.to_string(), text);
+        assert_eq!(cue.speakers(), vec!["Bob", "Alice"]);
+
+        let spans: Vec<&VttSpan> = cue.spans().collect();
+        assert_eq!(spans[0].tag.classes, vec!["loud".to_string()]);
+        assert_eq!(spans[1].tag.classes, vec!["red".to_string(), "big".to_string()]);
+        assert_eq!(spans[1].children, vec![VttCueNode::Text("you".to_string())]);
+        assert_eq!(cue.nodes[1], VttCueNode::Text(" &amp; ".to_string()));
+
+        if let VttCueNode::Span(ref mut alice) = cue.nodes[2] {
+            assert_eq!(alice.children[1], VttCueNode::Timestamp("00:01.500".to_string()));
+            alice.tag.annotation = Some("Carol".to_string());
+            alice.children.push(VttCueNode::Span(VttSpan::new(VttTag::new("b"), vec![])));
+        }
+        assert_eq!(
+            cue.to_string(),
+            "<v.loud Bob>Hello <c.red.big>you</c></v> &amp; <v Carol>hi <00:01.500><i>there<b></b>"
+        );
+    }
+
+    #[test]
+    fn vtt_invalid_spans_stay_text() {
+        for text in ["a </v> b", "<font color=red>x</font>", "a <b", "1 < 2 > 0"] {
+            let cue = VttCueText::parse(text);
+            assert_eq!(cue.to_string(), text);
+            assert_eq!(cue.spans().count(), 0, "{}", text);
+        }
+    }
+
+    #[test]
+    fn vtt_cue_speakers() {
+        let file = VttFile::parse("WEBVTT\n\n1\n00:01.000 --> 00:02.000\n<v Bob>Hi\n<v Alice>Hey\n\n00:03.000 --> 00:04.000\nnobody\n").unwrap();
+        assert_eq!(file.speakers(), vec![vec!["Bob".to_string(), "Alice".to_string()], vec![]]);
+        assert_eq!(file.cue_texts()[0].to_string(), "<v Bob>Hi\n<v Alice>Hey");
+    }
+
+    const FILE: &str = "WEBVTT - test\r\n\r\nSTYLE\r\n::cue { color: yellow }\r\n\r\nNOTE a comment\r\n\r\n\
+                        intro\r\n00:01.000 --> 00:02.500 align:start\r\n<i>Hello</i>\r\nworld\r\n\r\n\
+                        00:00:03.000 --> 00:00:04.000\r\nSecond\r\n\r\n\
+                        01:00:00.000 --> 01:00:01.000\r\nThird\r\n";
+
+    #[test]
+    fn vtt_blocks_and_cue_settings_are_kept() {
+        // the header, `STYLE` and `NOTE` blocks, cue identifiers and settings are written again
+        let mut file = VttFile::parse(FILE).unwrap();
+        assert_round_trip(&mut file, FILE.as_bytes());
+        assert_eq!(file.cue_settings()[0].get("align"), Some("start"));
+    }
+
+    #[test]
+    fn vtt_timestamps_with_optional_hours() {
+        // the identifier `intro` is not part of the text
+        let entries = VttFile::parse(FILE).unwrap().get_subtitle_entries().unwrap();
+        let lines: Vec<Option<&str>> = entries.iter().map(|entry| entry.line.as_deref()).collect();
+        assert_eq!(lines, vec![Some("<i>Hello</i>\nworld"), Some("Second"), Some("Third")]);
+        assert_eq!(
+            entries[0].timespan,
+            TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2500))
+        );
+        assert_eq!(entries[2].timespan.start, TimePoint::from_msecs(3_600_000));
+    }
+
+    #[test]
+    fn vtt_changed_texts_are_escaped() {
+        let mut file = VttFile::parse(FILE).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        // cue text can't contain `-->` or empty lines
+        entries[1].line = Some("a --> b\n\nc".to_string());
+        entries[1].timespan += TimeDelta::from_msecs(500);
+        file.update_subtitle_entries(&entries).unwrap();
+        let data = to_string(&file);
+        assert!(data.contains("\r\n\r\n00:00:03.500 --> 00:00:04.500\r\na --&gt; b\r\nc\r\n\r\n01:00:00.000"));
+    }
+
+    #[test]
+    fn vtt_removed_cues_take_their_identifier() {
+        // the `STYLE` and `NOTE` blocks before the cue stay
+        let mut file = VttFile::parse(FILE).unwrap();
+        file.retain_entries_by_mask(&[false, true, true]).unwrap();
+        let data = to_string(&file);
+        assert!(
+            data.contains("NOTE a comment\r\n\r\n00:00:03.000 --> 00:00:04.000\r\nSecond\r\n"),
+            "{}",
+            data
+        );
+        assert!(!data.contains("intro") && !data.contains("Hello"));
+    }
+
+    #[test]
+    fn vtt_missing_header() {
+        let parse = |s: &str| VttFile::parse_inner(s, &mut Warnings::new());
+        for input in ["1\n00:00:01,000 --> 00:00:02,000\nSRT\n", "", "WEBVTTX\n"] {
+            assert_eq!(parse_error(parse, input).kind(), &ExpectedHeader);
+        }
+        assert_eq!(VttFile::parse("").unwrap_err().kind(), crate::ErrorKind::ParsingError);
+    }
+
+    #[test]
+    fn vtt_invalid_timing_line() {
+        let parse = |s: &str| VttFile::parse_inner(s, &mut Warnings::new());
+        assert_eq!(
+            parse_error(parse, "WEBVTT\n\n00:01,000 --> 00:02.000\nbad\n").kind(),
+            &ExpectedTimingLine {
+                line_num: 2,
+                line: "00:01,000 --> 00:02.000".to_string()
+            }
+        );
+        assert_eq!(
+            VttFile::parse("WEBVTT\n\n00:01,000 --> 00:02.000\nbad\n").unwrap_err().kind(),
+            crate::ErrorKind::ParsingError
+        );
+    }
+
+    #[test]
+    fn vtt_header_language() {
+        let file = VttFile::parse("WEBVTT\nKind: captions\nLanguage: pt-BR\n\n00:01.000 --> 00:02.000\nOlá\n").unwrap();
+        assert_eq!(file.language().as_deref(), Some("pt-BR"));
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].language.as_deref(), Some("pt-BR"));
+
+        // a `Language:` line in a cue is only text
+        let file = VttFile::parse("WEBVTT\n\n00:01.000 --> 00:02.000\nLanguage: en\n").unwrap();
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].language, None);
+    }
+}
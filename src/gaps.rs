@@ -0,0 +1,147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Placeholder cues for long gaps between subtitles.
+//!
+//! Some broadcast delivery specifications require that silence is annotated (for example with a `♪` cue), or
+//! that there is a (blank) cue at all times. `SubtitleFile::fill_gaps()` inserts such placeholders and
+//! `SubtitleFile::remove_placeholders()` removes them again.
+
+use crate::errors::{ErrorKind, Result};
+use crate::timetypes::{TimeDelta, TimeSpan};
+use crate::{SubtitleEntry, SubtitleFile};
+
+/// Which gaps are filled and how the placeholders look.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GapFill {
+    /// Only gaps which are at least this long are filled.
+    pub min_gap: TimeDelta,
+
+    /// The text of a placeholder (may be empty for blank cues).
+    pub text: String,
+
+    /// The distance between a placeholder and the subtitles around it.
+    pub padding: TimeDelta,
+}
+
+impl Default for GapFill {
+    fn default() -> GapFill {
+        GapFill {
+            min_gap: TimeDelta::from_secs(3),
+            text: "♪".to_string(),
+            padding: TimeDelta::from_msecs(80),
+        }
+    }
+}
+
+/// Returns the gaps between the subtitles which are at least `min_gap` long.
+///
+/// Every gap is returned with the index of the entry that ends right before the gap (the entries don't have to be
+/// sorted). Overlapping entries don't create gaps.
+pub fn find_gaps(entries: &[SubtitleEntry], min_gap: TimeDelta) -> Vec<(usize, TimeSpan)> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries.get(i).map(|e| e.timespan.start));
+
+    let mut result = Vec::new();
+    let mut last_end: Option<(usize, TimeSpan)> = None;
+    for (idx, entry) in order.into_iter().filter_map(|i| entries.get(i).map(|e| (i, e))) {
+        if let Some((last_idx, last)) = last_end {
+            if entry.timespan.start - last.end >= min_gap {
+                result.push((last_idx, TimeSpan::new(last.end, entry.timespan.start)));
+            }
+        }
+        if last_end.map_or(true, |(_, last)| entry.timespan.end > last.end) {
+            last_end = Some((idx, entry.timespan));
+        }
+    }
+    result
+}
+
+impl SubtitleFile {
+    /// Inserts a placeholder into every gap that is at least `options.min_gap` long and returns the number of
+    /// inserted placeholders.
+    ///
+    /// Each placeholder is inserted after the entry that ends before the gap. This only works for `.srt` and
+    /// MicroDVD files (other formats return `ErrorKind::InsertingEntriesNotSupported`).
+    pub fn fill_gaps(&mut self, options: &GapFill) -> Result<usize> {
+        if !self.can_insert_entries() {
+            return Err(ErrorKind::InsertingEntriesNotSupported.into());
+        }
+
+        let gaps = find_gaps(&self.get_subtitle_entries()?, options.min_gap);
+        let mut placeholders: Vec<(usize, TimeSpan)> = gaps
+            .into_iter()
+            .map(|(idx, gap)| (idx, TimeSpan::new(gap.start + options.padding, gap.end - options.padding)))
+            .filter(|(_, timespan)| timespan.start < timespan.end)
+            .collect();
+
+        // inserting from the back keeps the indices of the front valid (the gaps are sorted by time, which is
+        // not the order of the indices if the entries are unsorted)
+        placeholders.sort_by_key(|&(idx, _)| std::cmp::Reverse(idx));
+        for &(idx, timespan) in &placeholders {
+            self.insert_entry(idx + 1, timespan, &options.text)?;
+        }
+        Ok(placeholders.len())
+    }
+
+    /// Removes all entries whose text is `text` (ignoring surrounding whitespace) and returns their number.
+    ///
+    /// This undoes `fill_gaps()`; an empty `text` removes all blank entries.
+    pub fn remove_placeholders(&mut self, text: &str) -> Result<usize> {
+        let keep: Vec<bool> = self
+            .get_subtitle_entries()?
+            .iter()
+            .map(|entry| entry.line.as_deref().map_or(true, |line| line.trim() != text.trim()))
+            .collect();
+        self.retain_entries_by_mask(&keep)?;
+        Ok(keep.iter().filter(|&&keep| !keep).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimePoint;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn fill_and_remove_gaps() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\na\n\n2\n00:00:02,500 --> 00:00:10,000\nb\n\n3\n00:00:04,000 --> 00:00:05,000\nc\n\n\
+                   4\n00:00:20,000 --> 00:00:21,000\nd\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let original = file.get_subtitle_entries().unwrap();
+
+        assert_eq!(file.fill_gaps(&GapFill::default()).unwrap(), 1);
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert!(data.contains("\n\n3\n00:00:10,080 --> 00:00:19,920\n♪\n\n3\n00:00:04,000"));
+
+        assert_eq!(file.remove_placeholders("♪").unwrap(), 1);
+        assert_eq!(file.get_subtitle_entries().unwrap(), original);
+    }
+
+    #[test]
+    fn fill_gaps_of_unsorted_entries() {
+        let srt = "1\n00:00:10,000 --> 00:00:11,000\nb\n\n2\n00:00:01,000 --> 00:00:02,000\na\n\n3\n00:00:20,000 --> 00:00:21,000\nc\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+
+        assert_eq!(file.fill_gaps(&GapFill::default()).unwrap(), 2);
+        let entries: Vec<(String, TimeSpan)> = file
+            .get_subtitle_entries()
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.line.unwrap(), entry.timespan))
+            .collect();
+        let span = |start: i64, end: i64| TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end));
+        assert_eq!(
+            entries,
+            vec![
+                ("b".to_string(), span(10000, 11000)),
+                ("♪".to_string(), span(11080, 19920)),
+                ("a".to_string(), span(1000, 2000)),
+                ("♪".to_string(), span(2080, 9920)),
+                ("c".to_string(), span(20000, 21000)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Rolling WebVTT segments for live HLS streams.
+//!
+//! A live captioning origin gets the cues while the stream is running (from a live transcription or a stream that
+//! is parsed as it arrives). `HlsSegmenter` collects them and cuts the timeline into segments of a fixed
+//! duration: whenever the stream time passes the end of a segment, `advance()` returns it as a WebVTT file with
+//! the `X-TIMESTAMP-MAP` header, which maps the cue times to the MPEG-TS timestamps of the video. A cue which
+//! spans a segment boundary is repeated in every segment it overlaps, and segments without cues are still written,
+//! so the segments line up with the media segments of the stream. `playlist()` returns the current media playlist:
+//!
+//! ```
+//! use subparse::hls::{HlsOptions, HlsSegmenter};
+//! use subparse::timetypes::{TimePoint, TimeSpan};
+//! use subparse::SubtitleEntry;
+//!
+//! let mut segmenter = HlsSegmenter::new(HlsOptions::default()).unwrap();
+//! let mut entry = SubtitleEntry::from(TimeSpan::new(TimePoint::from_msecs(5000), TimePoint::from_msecs(7000)));
+//! entry.line = Some("Hello".to_string());
+//! segmenter.push(&entry);
+//!
+//! let segments = segmenter.advance(TimePoint::from_secs(12));
+//! assert_eq!(segments.len(), 2);
+//! assert!(segments[0].data.starts_with("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:00.000\n"));
+//! assert!(segments[1].data.contains("00:00:05.000 --> 00:00:07.000\nHello\n"));
+//! assert!(segmenter.playlist().contains("#EXTINF:6.000,\nsegment1.vtt\n"));
+//! ```
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::SubtitleEntry;
+use failure::ResultExt;
+use std::collections::VecDeque;
+
+/// Errors specific to HLS segmenting.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(Clone, Copy, PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the segment duration has to be positive")]
+        InvalidSegmentDuration,
+    }
+}
+
+/// The MPEG-TS timestamps wrap around after 33 bits.
+const MPEGTS_WRAP: u64 = 1 << 33;
+
+/// Settings of an `HlsSegmenter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HlsOptions {
+    /// The duration of every segment (default: 6 s, like the usual media segments).
+    pub segment_duration: TimeDelta,
+
+    /// The start of the first segment (default: 0).
+    pub start: TimePoint,
+
+    /// The MPEG-TS timestamp (in 90 kHz ticks) of the cue time 0 (default: 900000, the 10 s offset most
+    /// segmenters give the video).
+    pub mpegts: u64,
+
+    /// The media sequence number of the first segment.
+    pub first_sequence: u64,
+
+    /// The number of segments in the live playlist (0 keeps all segments, like an event playlist).
+    pub playlist_size: usize,
+
+    /// The URI of a segment in the playlist, `{}` is replaced by the sequence number (default: `segment{}.vtt`).
+    pub uri_template: String,
+}
+
+impl Default for HlsOptions {
+    fn default() -> HlsOptions {
+        HlsOptions {
+            segment_duration: TimeDelta::from_secs(6),
+            start: TimePoint::from_msecs(0),
+            mpegts: 900_000,
+            first_sequence: 0,
+            playlist_size: 5,
+            uri_template: "segment{}.vtt".to_string(),
+        }
+    }
+}
+
+/// A finished WebVTT segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VttSegment {
+    /// The media sequence number.
+    pub sequence: u64,
+
+    /// The part of the timeline the segment covers.
+    pub timespan: TimeSpan,
+
+    /// The URI of the segment in the playlist.
+    pub uri: String,
+
+    /// The content of the `.vtt` file.
+    pub data: String,
+}
+
+/// Cuts a live stream of cues into WebVTT segments (see the module documentation).
+#[derive(Debug, Clone)]
+pub struct HlsSegmenter {
+    options: HlsOptions,
+
+    /// The cues which are not finished by the written segments (sorted by start time when a segment is written).
+    pending: Vec<(TimeSpan, String)>,
+
+    /// The start of the next segment.
+    next_start: TimePoint,
+
+    next_sequence: u64,
+
+    /// The sequence numbers, timespans and URIs of the segments in the playlist.
+    playlist: VecDeque<(u64, TimeSpan, String)>,
+
+    /// The number of cues which came after all of their segments were written.
+    late_cues: usize,
+
+    finished: bool,
+}
+
+impl HlsSegmenter {
+    /// Creates a segmenter without cues.
+    pub fn new(options: HlsOptions) -> SubtitleParserResult<HlsSegmenter> {
+        if options.segment_duration.msecs() <= 0 {
+            return Err(Error::from(InvalidSegmentDuration)).with_context(|_| crate::ErrorKind::CreationError)?;
+        }
+        Ok(HlsSegmenter {
+            next_start: options.start,
+            next_sequence: options.first_sequence,
+            options,
+            pending: Vec::new(),
+            playlist: VecDeque::new(),
+            late_cues: 0,
+            finished: false,
+        })
+    }
+
+    /// Adds a cue (entries without text are ignored).
+    ///
+    /// A cue which has ended before the start of the next segment can't be written anymore and is only counted
+    /// (see `late_cues()`); a cue which started in written segments only appears in the following segments.
+    pub fn push(&mut self, entry: &SubtitleEntry) {
+        let text = match entry.line {
+            Some(ref text) => text,
+            None => return,
+        };
+        let timespan = entry.timespan;
+        if self.finished || (timespan.end <= self.next_start && timespan.start < self.next_start) {
+            self.late_cues += 1;
+            return;
+        }
+        self.pending.push((timespan, text.clone()));
+    }
+
+    /// Returns the number of cues which arrived too late to be written.
+    pub fn late_cues(&self) -> usize {
+        self.late_cues
+    }
+
+    /// Writes all segments which end at or before the stream time `now`.
+    pub fn advance(&mut self, now: TimePoint) -> Vec<VttSegment> {
+        let mut segments = Vec::new();
+        while !self.finished && self.next_start + self.options.segment_duration <= now {
+            segments.push(self.write_segment());
+        }
+        segments
+    }
+
+    /// Writes the segments up to the end of the last cue and ends the playlist (with `#EXT-X-ENDLIST`).
+    pub fn finish(&mut self) -> Vec<VttSegment> {
+        let mut segments = Vec::new();
+        while !self.finished && !self.pending.is_empty() {
+            segments.push(self.write_segment());
+        }
+        self.finished = true;
+        segments
+    }
+
+    /// Returns the media playlist with the latest segments.
+    pub fn playlist(&self) -> String {
+        let duration = self.options.segment_duration.msecs();
+        let first_sequence = self.playlist.front().map_or(self.next_sequence, |(sequence, _, _)| *sequence);
+        let mut result = format!(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:{}\n",
+            (duration + 999) / 1000,
+            first_sequence
+        );
+        if self.options.playlist_size == 0 {
+            result.push_str("#EXT-X-PLAYLIST-TYPE:EVENT\n");
+        }
+        for (_, timespan, uri) in &self.playlist {
+            let msecs = timespan.len().msecs();
+            result.push_str(&format!("#EXTINF:{}.{:03},\n{}\n", msecs / 1000, msecs % 1000, uri));
+        }
+        if self.finished {
+            result.push_str("#EXT-X-ENDLIST\n");
+        }
+        result
+    }
+
+    /// Writes the next segment and forgets the cues which end in it.
+    fn write_segment(&mut self) -> VttSegment {
+        let timespan = TimeSpan::new(self.next_start, self.next_start + self.options.segment_duration);
+        self.pending.sort_by_key(|(cue, _)| cue.start);
+
+        let mut data = format!(
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:{},LOCAL:00:00:00.000\n",
+            self.options.mpegts % MPEGTS_WRAP
+        );
+        for (cue, text) in &self.pending {
+            let overlaps = cue.start < timespan.end && (cue.end > timespan.start || (cue.start == cue.end && cue.start >= timespan.start));
+            if overlaps {
+                data.push_str(&format!(
+                    "\n{} --> {}\n{}\n",
+                    format_timestamp(cue.start),
+                    format_timestamp(cue.end),
+                    cue_text(text)
+                ));
+            }
+        }
+        self.pending.retain(|(cue, _)| cue.end > timespan.end || cue.start >= timespan.end);
+
+        let sequence = self.next_sequence;
+        let uri = self.options.uri_template.replace("{}", &sequence.to_string());
+        self.playlist.push_back((sequence, timespan, uri.clone()));
+        while self.options.playlist_size > 0 && self.playlist.len() > self.options.playlist_size {
+            self.playlist.pop_front();
+        }
+        self.next_start = timespan.end;
+        self.next_sequence += 1;
+
+        VttSegment {
+            sequence,
+            timespan,
+            uri,
+            data,
+        }
+    }
+}
+
+/// Formats a timestamp like `00:00:01.500` (negative times are written as zero).
+fn format_timestamp(t: TimePoint) -> String {
+    let t = if t.is_negative() { TimePoint::from_msecs(0) } else { t };
+    format!("{:02}:{:02}:{:02}.{:03}", t.hours(), t.mins_comp(), t.secs_comp(), t.msecs_comp())
+}
+
+/// Removes what would end a cue early: empty lines and `-->`.
+fn cue_text(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    lines.join("\n").replace("-->", "--&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: i64, end: i64, text: &str) -> SubtitleEntry {
+        SubtitleEntry::new(TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end)), text.to_string())
+    }
+
+    /// Returns a segmenter for 4 second segments (starting with number 10 and a wrapped MPEG-TS time).
+    fn segmenter() -> HlsSegmenter {
+        let options = HlsOptions {
+            segment_duration: TimeDelta::from_secs(4),
+            mpegts: MPEGTS_WRAP + 90_000,
+            first_sequence: 10,
+            playlist_size: 2,
+            uri_template: "live/{}.webvtt".to_string(),
+            ..HlsOptions::default()
+        };
+        HlsSegmenter::new(options).unwrap()
+    }
+
+    #[test]
+    fn live_segments() {
+        let mut segmenter = segmenter();
+        segmenter.push(&entry(1000, 5000, "spans\n\ntwo segments"));
+        assert!(segmenter.advance(TimePoint::from_msecs(3999)).is_empty());
+        segmenter.push(&entry(3000, 3500, "a --> b"));
+
+        // the first cue is repeated in both segments, the cue texts are escaped for WebVTT
+        let segments = segmenter.advance(TimePoint::from_msecs(8000));
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].sequence, segments[0].uri.as_str()), (10, "live/10.webvtt"));
+        assert_eq!(
+            segments[0].data,
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:90000,LOCAL:00:00:00.000\n\n\
+             00:00:01.000 --> 00:00:05.000\nspans\ntwo segments\n\n\
+             00:00:03.000 --> 00:00:03.500\na --&gt; b\n"
+        );
+        assert_eq!((segments[1].sequence, segments[1].uri.as_str()), (11, "live/11.webvtt"));
+        assert_eq!(
+            segments[1].data,
+            "WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:90000,LOCAL:00:00:00.000\n\n00:00:01.000 --> 00:00:05.000\nspans\ntwo segments\n"
+        );
+    }
+
+    #[test]
+    fn live_late_cues() {
+        let mut segmenter = segmenter();
+        segmenter.advance(TimePoint::from_msecs(8000));
+        segmenter.push(&entry(6000, 7000, "late"));
+        segmenter.push(&entry(9000, 14_500, "last"));
+        assert_eq!(segmenter.late_cues(), 1);
+    }
+
+    #[test]
+    fn live_playlist() {
+        let mut segmenter = segmenter();
+        segmenter.advance(TimePoint::from_msecs(8000));
+        segmenter.push(&entry(9000, 14_500, "last"));
+        assert_eq!(segmenter.finish().len(), 2);
+
+        // the playlist keeps the last two segments
+        assert_eq!(
+            segmenter.playlist(),
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:12\n\
+             #EXTINF:4.000,\nlive/12.webvtt\n#EXTINF:4.000,\nlive/13.webvtt\n#EXT-X-ENDLIST\n"
+        );
+    }
+
+    #[test]
+    fn invalid_segment_duration() {
+        let invalid = HlsOptions {
+            segment_duration: TimeDelta::from_msecs(0),
+            ..HlsOptions::default()
+        };
+        let error = HlsSegmenter::new(invalid).err().unwrap();
+        assert_eq!(error.kind(), crate::ErrorKind::CreationError);
+    }
+}
@@ -0,0 +1,457 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Recording edits of a subtitle file, so they can be reviewed and applied to another copy of the file.
+//!
+//! An `EditSession` wraps a `SubtitleFile` and records every change made through it as a `Change`. The
+//! recorded `Patch` can be exported as compact JSON like
+//!
+//! ```text
+//! [[3,"start","1000","1200"],[3,"text","Helo","Hello"],[5,"remove"]]
+//! ```
+//!
+//! (entry index, field, old value, new value; times in milliseconds) and applied to another copy of the
+//! file with `Patch::apply()`, which fails if the old values don't match. Entries are identified by their
+//! position in `get_subtitle_entries()` at the time of the change, so the changes have to be applied in order.
+//!
+//! Review notes for the entries are kept in the `Annotations` of the session (see `annotations`), so they are
+//! never written to the file.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::annotations::{AnnotationValue, Annotations, CueId};
+use crate::errors::Result as SubtitleParserResult;
+use crate::json::{self, Value};
+use crate::timetypes::TimePoint;
+use crate::{SubtitleEntry, SubtitleFile};
+use failure::ResultExt;
+
+/// Errors specific to reading and applying patches.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "invalid JSON: {}", msg)]
+        InvalidJson { msg: String },
+
+        #[fail(display = "the change {} of the patch is invalid", index)]
+        InvalidChange { index: usize },
+
+        #[fail(display = "the patch refers to entry {}, but the file has only {} entries", entry_idx, count)]
+        EntryOutOfRange { entry_idx: usize, count: usize },
+
+        #[fail(display = "the `{}` of entry {} is `{}`, but the patch expects `{}`", field, entry_idx, found, expected)]
+        Conflict {
+            entry_idx: usize,
+            field: &'static str,
+            expected: String,
+            found: String,
+        },
+    }
+}
+
+/// The part of a subtitle entry that was changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryField {
+    /// The start time.
+    Start,
+
+    /// The end time.
+    End,
+
+    /// The text.
+    Text,
+}
+
+impl EntryField {
+    /// The name of the field in the patch (`"start"`, `"end"` or `"text"`).
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryField::Start => "start",
+            EntryField::End => "end",
+            EntryField::Text => "text",
+        }
+    }
+
+    fn from_name(s: &str) -> Option<EntryField> {
+        match s {
+            "start" => Some(EntryField::Start),
+            "end" => Some(EntryField::End),
+            "text" => Some(EntryField::Text),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field (times in milliseconds, `""` for entries without text).
+    fn get(self, entry: &SubtitleEntry) -> String {
+        match self {
+            EntryField::Start => entry.timespan.start.msecs().to_string(),
+            EntryField::End => entry.timespan.end.msecs().to_string(),
+            EntryField::Text => entry.line.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Sets the value of the field, returns `false` if the value is invalid.
+    fn set(self, entry: &mut SubtitleEntry, value: &str) -> bool {
+        match self {
+            EntryField::Start | EntryField::End => match value.parse() {
+                Ok(msecs) if self == EntryField::Start => entry.timespan.start = TimePoint::from_msecs(msecs),
+                Ok(msecs) => entry.timespan.end = TimePoint::from_msecs(msecs),
+                Err(_) => return false,
+            },
+            EntryField::Text => entry.line = Some(value.to_string()),
+        }
+        true
+    }
+}
+
+/// A single recorded edit. `entry_idx` is the position of the entry at the time of the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A field of an entry was changed.
+    Edit {
+        /// The position of the entry.
+        entry_idx: usize,
+
+        /// The changed field.
+        field: EntryField,
+
+        /// The value before the change.
+        old: String,
+
+        /// The value after the change.
+        new: String,
+    },
+
+    /// An entry was removed (the following entries move up by one).
+    Remove {
+        /// The position of the entry.
+        entry_idx: usize,
+    },
+}
+
+/// An ordered list of changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    changes: Vec<Change>,
+}
+
+impl Patch {
+    /// Creates a patch from a list of changes.
+    pub fn new(changes: Vec<Change>) -> Patch {
+        Patch { changes }
+    }
+
+    /// All changes in the order they were made.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Returns `true` if nothing was changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the patch as compact JSON (see the module documentation).
+    pub fn to_json(&self) -> String {
+        let changes = self.changes.iter().map(|change| match change {
+            Change::Edit { entry_idx, field, old, new } => Value::Array(vec![
+                Value::Number(*entry_idx as f64),
+                Value::String(field.name().to_string()),
+                Value::String(old.clone()),
+                Value::String(new.clone()),
+            ]),
+            Change::Remove { entry_idx } => Value::Array(vec![Value::Number(*entry_idx as f64), Value::String("remove".to_string())]),
+        });
+        Value::Array(changes.collect()).to_string()
+    }
+
+    /// Reads a patch that was written by `to_json()`.
+    pub fn from_json(s: &str) -> SubtitleParserResult<Patch> {
+        Ok(Self::from_json_inner(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    fn from_json_inner(s: &str) -> Result<Patch> {
+        let value = json::parse(s).map_err(|e| Error::from(InvalidJson { msg: e.to_string() }))?;
+        let changes = value.as_array().ok_or_else(|| {
+            Error::from(InvalidJson {
+                msg: "expected an array".to_string(),
+            })
+        })?;
+        let changes = changes.iter().enumerate().map(|(index, change)| {
+            let parse_change = || -> Option<Change> {
+                let parts = change.as_array()?;
+                let entry_idx = parts.first()?.as_f64().filter(|n| *n >= 0.0 && n.fract() == 0.0)? as usize;
+                match (parts.get(1)?.as_str()?, parts.get(2), parts.get(3)) {
+                    ("remove", None, None) => Some(Change::Remove { entry_idx }),
+                    (field, Some(old), Some(new)) => Some(Change::Edit {
+                        entry_idx,
+                        field: EntryField::from_name(field)?,
+                        old: old.as_str()?.to_string(),
+                        new: new.as_str()?.to_string(),
+                    }),
+                    _ => None,
+                }
+            };
+            parse_change().ok_or_else(|| Error::from(InvalidChange { index }))
+        });
+        Ok(Patch::new(changes.collect::<Result<Vec<Change>>>()?))
+    }
+
+    /// Applies all changes to a file. Nothing is changed if an old value doesn't match the file.
+    pub fn apply(&self, file: &mut SubtitleFile) -> SubtitleParserResult<()> {
+        let entries = file.get_subtitle_entries()?;
+        let (entries, keep) = self.apply_to_entries(entries).with_context(|_| crate::ErrorKind::CreationError)?;
+        file.update_subtitle_entries(&entries)?;
+        file.retain_entries_by_mask(&keep)
+    }
+
+    /// Returns the changed entries and which of them are not removed.
+    fn apply_to_entries(&self, mut entries: Vec<SubtitleEntry>) -> Result<(Vec<SubtitleEntry>, Vec<bool>)> {
+        // the remaining entries by their current position
+        let mut positions: Vec<usize> = (0..entries.len()).collect();
+        for (index, change) in self.changes.iter().enumerate() {
+            let entry_idx = match *change {
+                Change::Edit { entry_idx, .. } | Change::Remove { entry_idx } => entry_idx,
+            };
+            let out_of_range = || {
+                Error::from(EntryOutOfRange {
+                    entry_idx,
+                    count: positions.len(),
+                })
+            };
+            let position = *positions.get(entry_idx).ok_or_else(out_of_range)?;
+
+            match change {
+                Change::Edit { field, old, new, .. } => {
+                    let entry = entries.get_mut(position).ok_or_else(out_of_range)?;
+                    let found = field.get(entry);
+                    if found != *old {
+                        return Err(Conflict {
+                            entry_idx,
+                            field: field.name(),
+                            expected: old.clone(),
+                            found,
+                        }
+                        .into());
+                    }
+                    if !field.set(entry, new) {
+                        return Err(InvalidChange { index }.into());
+                    }
+                }
+                Change::Remove { .. } => {
+                    positions.remove(entry_idx);
+                }
+            }
+        }
+
+        let keep = (0..entries.len()).map(|i| positions.binary_search(&i).is_ok()).collect();
+        Ok((entries, keep))
+    }
+}
+
+/// A subtitle file that records all changes which are made through it.
+#[derive(Debug, Clone)]
+pub struct EditSession {
+    file: SubtitleFile,
+    patch: Patch,
+
+    /// The id of every current entry
+    cue_ids: Vec<CueId>,
+
+    annotations: Annotations,
+}
+
+impl EditSession {
+    /// Starts recording the changes of `file`. The entries get the ids `CueId(0)`, `CueId(1)`, ...
+    pub fn new(file: SubtitleFile) -> EditSession {
+        let count = file.get_subtitle_entries().map_or(0, |entries| entries.len());
+        EditSession {
+            file,
+            patch: Patch::default(),
+            cue_ids: (0..count as u64).map(CueId).collect(),
+            annotations: Annotations::new(),
+        }
+    }
+
+    /// The current state of the file.
+    pub fn file(&self) -> &SubtitleFile {
+        &self.file
+    }
+
+    /// Returns the changed file.
+    pub fn into_file(self) -> SubtitleFile {
+        self.file
+    }
+
+    /// All changes that were made so far.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    /// Returns the id of the entry at `entry_idx`.
+    pub fn cue_id(&self, entry_idx: usize) -> Option<CueId> {
+        self.cue_ids.get(entry_idx).copied()
+    }
+
+    /// Returns the current position of an entry (`None` if it was removed).
+    pub fn entry_idx(&self, cue: CueId) -> Option<usize> {
+        self.cue_ids.iter().position(|&id| id == cue)
+    }
+
+    /// The notes about the entries.
+    pub fn annotations(&self) -> &Annotations {
+        &self.annotations
+    }
+
+    /// The notes about the entries, for changes.
+    pub fn annotations_mut(&mut self) -> &mut Annotations {
+        &mut self.annotations
+    }
+
+    /// Replaces all notes (for example with `Annotations::from_json()`). Notes of removed entries are dropped.
+    pub fn set_annotations(&mut self, mut annotations: Annotations) {
+        let removed: Vec<CueId> = annotations.cues().filter(|cue| !self.cue_ids.contains(cue)).collect();
+        for cue in removed {
+            annotations.remove_cue(cue);
+        }
+        self.annotations = annotations;
+    }
+
+    /// Sets the note `label` of the entry at `entry_idx`, returns `false` if there is no such entry.
+    pub fn annotate(&mut self, entry_idx: usize, label: &str, value: AnnotationValue) -> bool {
+        match self.cue_id(entry_idx) {
+            Some(cue) => {
+                self.annotations.set(cue, label, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// See `SubtitleFile::get_subtitle_entries()`.
+    pub fn get_subtitle_entries(&self) -> SubtitleParserResult<Vec<SubtitleEntry>> {
+        self.file.get_subtitle_entries()
+    }
+
+    /// Sets the entries (see `SubtitleFile::update_subtitle_entries()`) and records every changed field.
+    pub fn update_subtitle_entries(&mut self, new_entries: &[SubtitleEntry]) -> SubtitleParserResult<()> {
+        let old_entries = self.file.get_subtitle_entries()?;
+        self.file.update_subtitle_entries(new_entries)?;
+
+        for (entry_idx, (old, new)) in old_entries.iter().zip(new_entries).enumerate() {
+            for &field in &[EntryField::Start, EntryField::End, EntryField::Text] {
+                // entries without a line keep their text
+                if field == EntryField::Text && new.line.is_none() {
+                    continue;
+                }
+                let (old_value, new_value) = (field.get(old), field.get(new));
+                if old_value != new_value {
+                    self.patch.changes.push(Change::Edit {
+                        entry_idx,
+                        field,
+                        old: old_value,
+                        new: new_value,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes entries (see `SubtitleFile::retain_entries_by_mask()`) and records the removals. The notes about
+    /// removed entries are dropped.
+    pub fn retain_entries_by_mask(&mut self, keep: &[bool]) -> SubtitleParserResult<()> {
+        self.file.retain_entries_by_mask(keep)?;
+
+        let mut keep_iter = keep.iter();
+        let annotations = &mut self.annotations;
+        self.cue_ids.retain(|&cue| {
+            let keep = keep_iter.next().cloned().unwrap_or(true);
+            if !keep {
+                annotations.remove_cue(cue);
+            }
+            keep
+        });
+
+        // removing from the back keeps the recorded positions valid when the changes are applied in order
+        let removed = keep.iter().enumerate().rev().filter(|(_, &keep)| !keep);
+        self.patch.changes.extend(removed.map(|(entry_idx, _)| Change::Remove { entry_idx }));
+        Ok(())
+    }
+
+    /// Removes every entry for which `f` returns `false` and records the removals.
+    pub fn retain_entries<F>(&mut self, f: F) -> SubtitleParserResult<()>
+    where
+        F: FnMut(&SubtitleEntry) -> bool,
+    {
+        let keep: Vec<bool> = self.file.get_subtitle_entries()?.iter().map(f).collect();
+        self.retain_entries_by_mask(&keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimeDelta;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn record_and_apply_patch() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHelo\n\n2\n00:00:03,000 --> 00:00:04,000\nb\n\n3\n00:00:05,000 --> 00:00:06,000\nc\n";
+        let original = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+
+        let mut session = EditSession::new(original.clone());
+        let mut entries = session.get_subtitle_entries().unwrap();
+        entries[0].line = Some("Hello".to_string());
+        entries[2].timespan += TimeDelta::from_msecs(200);
+        session.update_subtitle_entries(&entries).unwrap();
+        session.retain_entries(|e| e.line.as_deref() != Some("b")).unwrap();
+
+        let json = session.patch().to_json();
+        assert_eq!(
+            json,
+            r#"[[0,"text","Helo","Hello"],[2,"start","5000","5200"],[2,"end","6000","6200"],[1,"remove"]]"#
+        );
+
+        let mut copy = original.clone();
+        Patch::from_json(&json).unwrap().apply(&mut copy).unwrap();
+        assert_eq!(copy.to_data().unwrap(), session.file().to_data().unwrap());
+
+        // applying it twice conflicts with the already changed text
+        assert!(Patch::from_json(&json).unwrap().apply(&mut copy).is_err());
+    }
+
+    #[test]
+    fn annotations_follow_entries() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\na\n\n2\n00:00:03,000 --> 00:00:04,000\nb\n\n3\n00:00:05,000 --> 00:00:06,000\nc\n";
+        let original = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let mut session = EditSession::new(original.clone());
+        assert!(session.annotate(0, "qc.checked", AnnotationValue::Flag(true)));
+        assert!(session.annotate(1, "comment", AnnotationValue::Text("remove?".to_string())));
+        assert!(session.annotate(2, "cps", AnnotationValue::Number(23.5)));
+        assert!(!session.annotate(3, "comment", AnnotationValue::Text("none".to_string())));
+
+        session.retain_entries_by_mask(&[true, false, true]).unwrap();
+        assert_eq!(session.cue_id(1), Some(CueId(2)));
+        assert_eq!(session.entry_idx(CueId(1)), None);
+        let json = session.annotations().to_json();
+        assert_eq!(json, r#"{"0":{"qc.checked":true},"2":{"cps":23.5}}"#);
+
+        // the notes are not written to the file
+        assert_eq!(
+            String::from_utf8(session.file().to_data().unwrap()).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\na\n\n3\n00:00:05,000 --> 00:00:06,000\nc\n\n"
+        );
+
+        let mut other = EditSession::new(original);
+        other.set_annotations(Annotations::from_json(&json).unwrap());
+        assert_eq!(other.annotations().get(CueId(2), "cps"), Some(&AnnotationValue::Number(23.5)));
+        assert!(Annotations::from_json(r#"{"0":{"x":[1]}}"#).is_err());
+    }
+}
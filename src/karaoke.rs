@@ -0,0 +1,182 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Conversion between `.ass` karaoke and enhanced LRC files.
+//!
+//! Converting the subtitle entries of a file only keeps the time of every line, so the timing of the karaoke
+//! syllables (`{\k50}` tags in `.ass` files, `<00:12.50>` word tags in enhanced LRC files) is lost. These functions
+//! convert the syllables to words and back:
+//!
+//! ```
+//! use subparse::karaoke::ass_to_lrc;
+//! use subparse::SsaFile;
+//!
+//! let ass = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+//!            Dialogue: 0,0:00:12.00,0:00:14.00,Default,,0,0,0,,{\\k50}Never {\\k150}gonna";
+//! let lrc = ass_to_lrc(&SsaFile::parse(ass).unwrap()).unwrap();
+//! let data = String::from_utf8(subparse::SubtitleFileInterface::to_data(&lrc).unwrap()).unwrap();
+//! assert_eq!(data, "[00:12.00]<00:12.00>Never <00:12.50>gonna<00:14.00>\n");
+//! ```
+
+use crate::errors::Result;
+use crate::timetypes::{TimePoint, TimeSpan};
+use crate::{AssBlockItem, AssTag, AssTemplate, AssText, AssTextPart, LrcFile, LrcWord, SsaFile, SsaStyle, SubtitleFileInterface};
+
+/// Returns the visible text of an `.ass` text (without override blocks, `\N` and `\h` as spaces).
+fn plain_text(text: &str) -> String {
+    let text = AssText::parse(text)
+        .parts
+        .into_iter()
+        .filter_map(|part| match part {
+            AssTextPart::Text(text) => Some(text),
+            AssTextPart::Block(_) => None,
+        })
+        .collect::<String>();
+    text.replace("\\N", " ").replace("\\n", " ").replace("\\h", " ")
+}
+
+/// Returns the visible text before the first karaoke tag.
+fn intro_text(text: &str) -> String {
+    let mut result = String::new();
+    for part in AssText::parse(text).parts {
+        match part {
+            AssTextPart::Text(text) => result.push_str(&text),
+            AssTextPart::Block(items) => {
+                if items.iter().any(|item| matches!(item, AssBlockItem::Tag(AssTag::Karaoke { .. }))) {
+                    break;
+                }
+            }
+        }
+    }
+    plain_text(&result)
+}
+
+/// Converts the dialogue events of an `.ass` file into the lines of an enhanced LRC file.
+///
+/// Every karaoke syllable becomes a word which starts when the syllable is highlighted, and the end of the last
+/// syllable is written as a tag after the last word. The text before the first karaoke tag gets the start of the
+/// event, syllables without text (pauses) are left out. Events without karaoke tags become plain lines.
+pub fn ass_to_lrc(file: &SsaFile) -> Result<LrcFile> {
+    let events = file.get_events();
+    let mut lrc = LrcFile::create(events.iter().map(|event| (event.timespan, plain_text(&event.text))).collect())?;
+
+    for (idx, event) in events.iter().enumerate() {
+        let karaoke = event.karaoke();
+        if karaoke.syllables.is_empty() {
+            continue;
+        }
+
+        let start = event.timespan.start;
+        let mut words = Vec::new();
+        let intro = intro_text(&event.text);
+        if !intro.trim().is_empty() {
+            words.push(LrcWord {
+                timespan: TimeSpan::new(start, start),
+                text: intro,
+            });
+        }
+        for (syllable, timespan) in karaoke.syllables.iter().zip(karaoke.timespans(start)) {
+            let text = plain_text(&syllable.text);
+            if !text.is_empty() {
+                words.push(LrcWord { timespan, text });
+            } else if let Some(last) = words.last_mut() {
+                last.timespan.end = timespan.start;
+            }
+        }
+        lrc.set_words(idx, &words);
+    }
+
+    Ok(lrc)
+}
+
+/// Converts the lines of an enhanced LRC file into `\k` karaoke events of an `.ass` file (with the `Default` style).
+///
+/// The words become syllables in the order of the text; a gap before the first word becomes a syllable without
+/// text, and the event ends with the last word. The durations are rounded to centiseconds without accumulating a
+/// rounding error. Lines without word tags become events without karaoke tags (with the end times of
+/// `LrcFile::get_subtitle_entries()`).
+pub fn lrc_to_ass(file: &LrcFile) -> Result<SsaFile> {
+    let entries = file.get_subtitle_entries()?;
+    let mut template = AssTemplate::new(vec![SsaStyle::default()]);
+
+    for (entry, words) in entries.iter().zip(file.words()?) {
+        let start = entry.timespan.start;
+        let mut text = String::new();
+        let mut end_cs = 0;
+        let mut add_syllable = |text: &mut String, end: TimePoint, syllable: &str| {
+            let new_end_cs = ((end - start).msecs().max(0) as f64 / 10.0).round() as i64;
+            text.push_str(&format!("{{\\k{}}}{}", (new_end_cs - end_cs).max(0), syllable));
+            end_cs = new_end_cs.max(end_cs);
+        };
+        if let Some(first) = words.first().filter(|first| first.timespan.start > start) {
+            add_syllable(&mut text, first.timespan.start, "");
+        }
+        for word in &words {
+            add_syllable(&mut text, word.timespan.end, &word.text);
+        }
+
+        let end = words.last().map_or(entry.timespan.end, |last| last.timespan.end);
+        let line = if words.is_empty() {
+            entry.line.clone().unwrap_or_default()
+        } else {
+            text
+        };
+        template.add_event(TimeSpan::new(start, end), &line, "Default", None);
+    }
+
+    template.to_ssa_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::to_string;
+
+    const ASS: &str = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                       Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{\\an8}Oh {\\k40}Hel{\\kf60}lo{\\k50} {\\k50}\\Nworld\n\
+                       Dialogue: 0,0:00:04.00,0:00:05.00,Default,,0,0,0,,{\\i1}no karaoke";
+
+    #[test]
+    fn ass_karaoke_to_lrc_words() {
+        let lrc = ass_to_lrc(&SsaFile::parse(ASS).unwrap()).unwrap();
+        assert_eq!(
+            to_string(&lrc),
+            "[00:01.00]<00:01.00>Oh <00:01.00>Hel<00:01.40>lo<00:02.00> <00:02.50> world<00:03.00>\n[00:04.00]no karaoke\n"
+        );
+        let words = lrc.words().unwrap();
+        assert_eq!(
+            words[0].iter().map(|word| word.text.as_str()).collect::<Vec<_>>(),
+            vec!["Oh ", "Hel", "lo", " ", " world"]
+        );
+        assert!(words[1].is_empty());
+    }
+
+    #[test]
+    fn lrc_words_to_ass_karaoke() {
+        let lrc = LrcFile::parse("[00:01.00]<00:01.50>Never <00:01.83>gonna<00:03.00>\n[00:04.00]plain line\n[00:06.00]\n").unwrap();
+        let ass = lrc_to_ass(&lrc).unwrap();
+        let texts: Vec<(String, TimeSpan)> = ass.get_events().into_iter().map(|event| (event.text, event.timespan)).collect();
+        let span = |start: i64, end: i64| TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end));
+        assert_eq!(
+            texts,
+            vec![
+                ("{\\k50}{\\k33}Never {\\k117}gonna".to_string(), span(1000, 3000)),
+                ("plain line".to_string(), span(4000, 6000)),
+                (String::new(), span(6000, 11000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn karaoke_round_trip() {
+        let ass = SsaFile::parse(ASS).unwrap();
+        let converted = lrc_to_ass(&ass_to_lrc(&ass).unwrap()).unwrap();
+        let durations = |file: &SsaFile| -> Vec<i64> { file.get_events()[0].karaoke().syllables.iter().map(|s| s.duration.csecs()).collect() };
+        assert_eq!(durations(&ass), vec![40, 60, 50, 50]);
+
+        // the text before the first syllable becomes a syllable without duration
+        assert_eq!(durations(&converted), vec![0, 40, 60, 50, 50]);
+        assert_eq!(converted.get_events()[0].timespan, ass.get_events()[0].timespan);
+    }
+}
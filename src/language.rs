@@ -0,0 +1,224 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Per-entry language tags for tracks which mix multiple languages.
+//!
+//! Some formats store languages, which `get_subtitle_entries()` writes into `SubtitleEntry::language`: the
+//! inherited `xml:lang` of the paragraphs of TTML (and iTunes Timed Text) files, the language of the class of SAMI
+//! paragraphs, and the language of the whole file for the `Language` key of the `[Script Info]` section of
+//! `.ssa`/`.ass` files and a `Language:` header line of WebVTT files.
+//!
+//! For all other entries the language can be guessed from the text: the writing system decides for scripts
+//! which are (mostly) used by a single language, and a short list of very common words decides between some
+//! languages written in latin script. Everything else is tagged as undetermined with the script subtag (like
+//! `"und-Cyrl"`).
+
+use crate::SubtitleEntry;
+
+/// Writing systems which are recognized by `detect_language()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Thai,
+    Hangul,
+    Kana,
+    Han,
+}
+
+impl Script {
+    fn of(c: char) -> Option<Script> {
+        match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => Some(Script::Latin),
+            0x370..=0x3FF => Some(Script::Greek),
+            0x400..=0x52F => Some(Script::Cyrillic),
+            0x590..=0x5FF => Some(Script::Hebrew),
+            0x600..=0x6FF | 0x750..=0x77F => Some(Script::Arabic),
+            0xE00..=0xE7F => Some(Script::Thai),
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Some(Script::Hangul),
+            0x3040..=0x30FF => Some(Script::Kana),
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => Some(Script::Han),
+            _ => None,
+        }
+    }
+}
+
+/// Very common (short) words which are specific enough to tell these languages apart.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &["the", "and", "you", "is", "what", "this", "that", "are", "with", "have", "it's", "don't"],
+    ),
+    (
+        "de",
+        &["und", "ich", "nicht", "ist", "das", "du", "wir", "ein", "eine", "der", "die", "mit"],
+    ),
+    (
+        "fr",
+        &["le", "les", "et", "je", "vous", "est", "pas", "une", "c'est", "qui", "nous", "avec"],
+    ),
+    ("es", &["el", "los", "y", "que", "es", "está", "una", "por", "pero", "para", "qué", "yo"]),
+    (
+        "it",
+        &["il", "che", "non", "sono", "una", "per", "gli", "della", "questo", "è", "perché", "io"],
+    ),
+    ("pt", &["não", "que", "uma", "você", "está", "os", "em", "isso", "mas", "para", "eu", "é"]),
+    (
+        "nl",
+        &["het", "een", "niet", "ik", "je", "is", "van", "dat", "wat", "zijn", "maar", "ook"],
+    ),
+];
+
+/// Removes `{...}` (SubStation Alpha) and `<...>` (html-like) markup from a text.
+fn strip_markup(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut closing: Option<char> = None;
+    for c in text.chars() {
+        match closing {
+            Some(end) if c == end => closing = None,
+            Some(_) => {}
+            None if c == '{' => closing = Some('}'),
+            None if c == '<' => closing = Some('>'),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+/// Guesses the language of a text and returns it as BCP 47 tag.
+///
+/// Returns `None` if the text has no letters at all.
+pub fn detect_language(text: &str) -> Option<String> {
+    let text = strip_markup(text);
+
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in text.chars().filter_map(Script::of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+
+    // Japanese mixes kana with han characters, Korean can contain han characters
+    let has = |script: Script| counts.iter().any(|(s, _)| *s == script);
+    if has(Script::Kana) {
+        return Some("ja".to_string());
+    }
+    if has(Script::Hangul) {
+        return Some("ko".to_string());
+    }
+
+    let dominant = counts.iter().max_by_key(|(_, count)| *count).map(|(s, _)| *s)?;
+    let tag = match dominant {
+        Script::Latin => return Some(detect_latin_language(&text).unwrap_or("und-Latn").to_string()),
+        Script::Cyrillic => "und-Cyrl",
+        Script::Greek => "el",
+        Script::Arabic => "und-Arab",
+        Script::Hebrew => "he",
+        Script::Thai => "th",
+        Script::Han => "zh",
+        Script::Hangul => "ko",
+        Script::Kana => "ja",
+    };
+    Some(tag.to_string())
+}
+
+/// Decides between latin script languages by counting their most common words.
+fn detect_latin_language(text: &str) -> Option<&'static str> {
+    let lowercase = text.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !(c.is_alphabetic() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut best: Option<(&'static str, usize)> = None;
+    let mut tie = false;
+    for &(language, stopwords) in STOPWORDS {
+        let score = words.iter().filter(|w| stopwords.contains(w)).count();
+        match best {
+            _ if score == 0 => {}
+            Some((_, best_score)) if score == best_score => tie = true,
+            Some((_, best_score)) if score < best_score => {}
+            _ => {
+                best = Some((language, score));
+                tie = false;
+            }
+        }
+    }
+
+    if tie {
+        None
+    } else {
+        best.map(|(language, _)| language)
+    }
+}
+
+/// Returns `true` if the language tag `tag` belongs to the requested `language`.
+///
+/// The comparison ignores case, and a request without subtags (like `"pt"`) also accepts regional variants
+/// (like `"pt-BR"`).
+pub fn language_matches(tag: &str, language: &str) -> bool {
+    let tag = tag.to_lowercase();
+    let language = language.to_lowercase();
+    tag == language || (tag.starts_with(&language) && tag[language.len()..].starts_with('-'))
+}
+
+/// Returns the language of an entry: the stored tag if there is one, otherwise the detected language.
+pub fn entry_language(entry: &SubtitleEntry) -> Option<String> {
+    entry
+        .language
+        .clone()
+        .or_else(|| entry.line.as_ref().and_then(|text| detect_language(text)))
+}
+
+/// Sets the detected language on all entries which do not have a language yet.
+pub fn tag_languages(entries: &mut [SubtitleEntry]) {
+    for entry in entries.iter_mut() {
+        if entry.language.is_none() {
+            entry.language = entry.line.as_ref().and_then(|text| detect_language(text));
+        }
+    }
+}
+
+/// Returns (copies of) all entries which are in `language` (see `language_matches()`).
+pub fn filter_by_language(entries: &[SubtitleEntry], language: &str) -> Vec<SubtitleEntry> {
+    entries
+        .iter()
+        .filter(|entry| entry_language(entry).is_some_and(|tag| language_matches(&tag, language)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::{TimePoint, TimeSpan};
+
+    #[test]
+    fn detect_and_filter() {
+        assert_eq!(detect_language("What is this?").as_deref(), Some("en"));
+        assert_eq!(detect_language("{\\i1}Das ist nicht gut.{\\i0}").as_deref(), Some("de"));
+        assert_eq!(detect_language("Привет").as_deref(), Some("und-Cyrl"));
+        assert_eq!(detect_language("何ですか").as_deref(), Some("ja"));
+        assert_eq!(detect_language("안녕하세요").as_deref(), Some("ko"));
+        assert_eq!(detect_language("Okay!").as_deref(), Some("und-Latn"));
+        assert_eq!(detect_language("..."), None);
+
+        assert!(language_matches("pt-BR", "pt"));
+        assert!(!language_matches("pt", "pt-BR"));
+        assert!(!language_matches("ptx", "pt"));
+
+        let ts = TimeSpan::new(TimePoint::from_secs(0), TimePoint::from_secs(1));
+        let mut tagged = SubtitleEntry::new(ts, "Bonjour".to_string());
+        tagged.language = Some("fr-CA".to_string());
+        let entries = vec![SubtitleEntry::new(ts, "I don't know what this is.".to_string()), tagged];
+
+        let french = filter_by_language(&entries, "fr");
+        assert_eq!(french.len(), 1);
+        assert_eq!(french[0].line.as_deref(), Some("Bonjour"));
+    }
+}
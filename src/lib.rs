@@ -13,12 +13,53 @@
     unused_import_braces,
     unused_qualifications
 )]
+// the `Fail` derive of `failure` generates its impls inside of an anonymous const
+#![allow(non_local_definitions)]
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::indexing_slicing,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented
+    )
+)]
 
-//! This crate provides a common interface for popular subtitle formats (`.srt`, `.ssa`, `.ass`, `.idx`, `.sub`).
+//! This crate provides a common interface for popular subtitle formats (`.srt`, `.ssa`, `.ass`, `.idx`, `.sub`, `.vtt`, `.ttml`, `.dfxp`, `.sbv`, `.lrc`, `.scc`, `.stl`, `.pac`, `.txt`, `.mpl`, `.sup`, `.jss`, `.rt`, `.itt`, `.xml`, `.aqt`, `.psb`, `.srv3`, `.ytt`, `.smi`, `.sami`, `.dvbsub`).
+//! Some extensions are used by several formats (see `SubtitleFormat::extensions()`):
+//!
+//! ```
+//! use subparse::SubtitleFormat;
+//!
+//! let mut extensions: Vec<&str> = SubtitleFormat::ALL.iter().flat_map(|format| format.extensions().iter().copied()).collect();
+//! extensions.sort();
+//! extensions.dedup();
+//! # let crate_doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/lib.rs"));
+//! # let intro = crate_doc.lines().find(|line| line.starts_with("//! This crate provides")).unwrap();
+//! # let mut listed: Vec<&str> = intro.split('`').skip(1).step_by(2).map(|ext| ext.trim_start_matches('.')).collect();
+//! # let count = listed.len();
+//! # listed.sort();
+//! # listed.dedup();
+//! # assert_eq!(listed.len(), count, "an extension is listed twice");
+//! # assert_eq!(listed, extensions);
+//! assert!(extensions.contains(&"srt"));
+//! ```
 //!
 //! Files can be parsed, modified and saved again - some formats can be created from scratch.
 //! The focus is on non-destructive parsing, meaning that formatting and other information are preserved
 //! if not explicitely changed.
+//!
+//! # Untrusted input
+//!
+//! Parsing, changing and writing text subtitle files never panics, no matter how malformed the input is:
+//! invalid input results in an `Err`, and times which would overflow fail with `timetypes::TimestampOutOfRange` (in `.srt`,
+//! `.ssa`/`.ass`, `.idx` and `.vtt` files) or are saturated (in the other formats). This is enforced by
+//! denying `unwrap`, `expect`, `panic!` and unchecked indexing in the library code, and checked by the
+//! fuzz targets in the `fuzz/` directory (`cargo +nightly fuzz run parse_text`). The binary VobSub `.sub`
+//! data is decoded by the `vobsub` crate, which is not covered by this guarantee.
 
 extern crate combine;
 extern crate encoding_rs;
@@ -31,21 +72,98 @@ extern crate vobsub;
 pub mod errors;
 
 mod formats;
+mod json;
+#[cfg(test)]
+mod test_helpers;
+
+pub mod alignment;
+pub mod annotations;
+pub mod bitmaps;
+pub mod casing;
+pub mod cea708;
+pub mod chapters;
+pub mod conformance;
+pub mod conversion;
+pub mod delay;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+pub mod durations;
+pub mod escaping;
+pub mod filesets;
+pub mod fitting;
+pub mod flags;
+pub mod forced;
+pub mod gaps;
+pub mod hls;
+pub mod journal;
+pub mod karaoke;
+pub mod language;
+pub mod markers;
+pub mod matroska;
+pub mod metrics;
+pub mod model;
+pub mod music;
+pub mod newlines;
+pub mod pacing;
+pub mod pipeline;
+pub mod positioning;
+pub mod profiles;
+pub mod registry;
+pub mod remap;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod repair;
+pub mod retiming;
+pub mod saving;
+pub mod search;
+pub mod shared;
+pub mod splitting;
+pub mod timeline;
+pub mod warnings;
+pub mod words;
 
 /// Types that represent a time point, duration and time span.
 pub mod timetypes;
 
 use errors::*;
+pub use flags::EntryFlags;
+pub use formats::aqtitle::AqTitleFile;
+pub use formats::bdn::{BdnEvent, BdnFile, BdnGraphic};
+pub use formats::dvb::DvbFile;
 pub use formats::idx::IdxFile;
+pub use formats::itt::{IttFile, IttFrameRate};
+pub use formats::jacosub::JacoFile;
+pub use formats::lrc::{LrcFile, LrcWord};
 pub use formats::microdvd::MdvdFile;
-pub use formats::srt::SrtFile;
-pub use formats::ssa::SsaFile;
-pub use formats::vobsub::VobFile;
+pub use formats::mpl2::Mpl2File;
+pub use formats::pac::PacFile;
+pub use formats::pgs::PgsFile;
+pub use formats::powerdivx::PowerDivXFile;
+pub use formats::realtext::RealTextFile;
+pub use formats::sami::{SamiFile, SamiLanguage};
+pub use formats::sbv::SbvFile;
+pub use formats::scc::SccFile;
+pub use formats::spruce::SpruceStlFile;
+pub use formats::srt::{
+    SrtAlignmentMapping, SrtAssTagOutput, SrtAssTags, SrtFile, SrtNumbering, SrtStyleMapping, SrtTimestampFormat, SrtToAssOptions,
+};
+pub use formats::srv3::Srv3File;
+pub use formats::ssa::{
+    AssTemplate, AssTemplateEvent, SsaDialect, SsaEvent, SsaEventMut, SsaFile, SsaScriptInfo, SsaStyle, SsaStyleCleanup, SsaStyleConflict,
+};
+pub use formats::ssa_color::{AssAlpha, AssColor};
+pub use formats::ssa_override::{AssBlockItem, AssKaraoke, AssKaraokeKind, AssSyllable, AssTag, AssText, AssTextPart};
+pub use formats::stl::StlFile;
+pub use formats::subviewer::SubViewerFile;
+pub use formats::ttml::TtmlFile;
+pub use formats::vobsub::{validate_vobsub, SpuColors, VobFile, VobSubIssue};
+pub use formats::vplayer::VPlayerFile;
+pub use formats::vtt::{VttBlock, VttCueNode, VttCueStyle, VttCueText, VttFile, VttSettings, VttSpan, VttTag};
 pub use formats::{
     get_subtitle_format, get_subtitle_format_by_extension, get_subtitle_format_by_extension_err, get_subtitle_format_err,
-    is_valid_extension_for_subtitle_format, parse_bytes, parse_str,
+    is_valid_extension_for_subtitle_format, parse_auto, parse_bytes, parse_str, parse_str_with_warnings,
 };
-pub use formats::{SubtitleFile, SubtitleFormat};
+pub use formats::{FormatCapabilities, SubtitleFile, SubtitleFormat};
 use timetypes::TimeSpan;
 
 /// This trait represents the generic interface for reading and writing subtitle information across all subtitle formats.
@@ -58,8 +176,8 @@ pub trait SubtitleFileInterface {
     /// Set the entries from the subtitle entries from the `get_subtitle_entries()`.
     ///
     /// The length of the given input slice should always match the length of the vector length from
-    /// `get_subtitle_entries()`. This function can not delete/create new entries, but preserves
-    /// everything else in the file (formatting, authors, ...).
+    /// `get_subtitle_entries()` (otherwise `ErrorKind::EntryCountMismatch` is returned). This function can
+    /// not delete/create new entries, but preserves everything else in the file (formatting, authors, ...).
     ///
     /// If the input entry has `entry.line == None`, the line will not be overwritten.
     ///
@@ -71,10 +189,45 @@ pub trait SubtitleFileInterface {
     /// Returns a byte-stream in the respective format (.ssa, .srt, etc.) with the
     /// (probably) altered information.
     fn to_data(&self) -> Result<Vec<u8>>;
+
+    /// Removes every entry for which `keep[i]` is `false`, where `i` is the position of the entry in
+    /// `get_subtitle_entries()`.
+    ///
+    /// The length of `keep` has to match the number of entries (otherwise `ErrorKind::EntryCountMismatch` is
+    /// returned). All file parts which belong to a removed
+    /// entry are removed too (the whole `Dialogue:` line in `.ssa` files, the whole `timestamp: ..., filepos: ...`
+    /// line in `.idx` files), the rest of the file is preserved.
+    ///
+    /// All formats of this crate implement it; the default implementation returns `ErrorKind::UnsupportedOperation`.
+    fn retain_entries_by_mask(&mut self, _keep: &[bool]) -> Result<()> {
+        Err(ErrorKind::UnsupportedOperation.into())
+    }
+
+    /// Removes every entry for which `f` returns `false`.
+    fn retain_entries<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnMut(&SubtitleEntry) -> bool,
+        Self: Sized,
+    {
+        let keep: Vec<bool> = self.get_subtitle_entries()?.iter().map(f).collect();
+        self.retain_entries_by_mask(&keep)
+    }
+
+    /// Removes every entry which lies completely within `timespan`.
+    fn drop_entries_in(&mut self, timespan: TimeSpan) -> Result<()>
+    where
+        Self: Sized,
+    {
+        self.retain_entries(|entry| !timespan.contains(entry.timespan))
+    }
 }
 
 /// The data which can be read from/written to a subtitle file.
-#[derive(Debug)]
+///
+/// New fields can be added in minor versions, so create entries with `SubtitleEntry::new()` or
+/// `SubtitleEntry::from(timespan)` instead of struct literals.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct SubtitleEntry {
     /// The duration for which the current subtitle will be shown.
     pub timespan: TimeSpan,
@@ -84,20 +237,47 @@ pub struct SubtitleEntry {
     /// for example VobSub files (and any other image based format)
     /// will have `None` as value.
     pub line: Option<String>,
+
+    /// The language of this entry as BCP 47 tag (like `"en"` or `"pt-BR"`), if the format stores one (TTML, iTunes
+    /// Timed Text, SAMI, `.ssa`/`.ass` and WebVTT files, see `language`) or it was tagged with
+    /// `language::tag_languages()`.
+    ///
+    /// This is only metadata: `update_subtitle_entries()` ignores it.
+    pub language: Option<String>,
+
+    /// Common properties like whole-cue italics or SDH annotations (see `flags` on how they are detected).
+    ///
+    /// This is only metadata: `update_subtitle_entries()` ignores it.
+    pub flags: EntryFlags,
 }
 
 impl SubtitleEntry {
-    /// Create subtitle entry with text.
-    fn new(timespan: TimeSpan, line: String) -> SubtitleEntry {
+    /// Create subtitle entry with text (the `flags` are detected from the text).
+    ///
+    /// ```
+    /// use subparse::timetypes::{TimePoint, TimeSpan};
+    /// use subparse::SubtitleEntry;
+    ///
+    /// let entry = SubtitleEntry::new(TimeSpan::new(TimePoint::from_msecs(0), TimePoint::from_msecs(1500)), "<i>Hi</i>".to_string());
+    /// assert!(entry.flags.italic);
+    /// ```
+    pub fn new(timespan: TimeSpan, line: String) -> SubtitleEntry {
         SubtitleEntry {
-            timespan: timespan,
+            timespan,
+            flags: EntryFlags::from_text(&line),
             line: Some(line),
+            language: None,
         }
     }
 }
 
 impl From<TimeSpan> for SubtitleEntry {
     fn from(f: TimeSpan) -> SubtitleEntry {
-        SubtitleEntry { timespan: f, line: None }
+        SubtitleEntry {
+            timespan: f,
+            line: None,
+            language: None,
+            flags: EntryFlags::default(),
+        }
     }
 }
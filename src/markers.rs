@@ -0,0 +1,327 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Export cue boundaries as timeline markers for video editors, and import marker lists as empty cues.
+//!
+//! DaVinci Resolve imports markers from an EDL (`File > Import > Timeline Markers from EDL`) and Adobe Premiere
+//! exports and imports markers as CSV. Both use SMPTE timecodes like `01:00:02:12`, so the frame rate and the
+//! start timecode of the timeline are needed (see `MarkerOptions`).
+//!
+//! ```
+//! use subparse::markers::{from_premiere_csv, markers_from_entries, to_premiere_csv, MarkerOptions};
+//! use subparse::timetypes::{TimePoint, TimeSpan};
+//! use subparse::SubtitleEntry;
+//!
+//! let mut entry = SubtitleEntry::from(TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2400)));
+//! entry.line = Some("Hello".to_string());
+//! let markers = markers_from_entries(&[entry]);
+//! let csv = to_premiere_csv(&markers, &MarkerOptions::default());
+//! assert_eq!(from_premiere_csv(&csv, &MarkerOptions::default()).unwrap(), markers);
+//! ```
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::timetypes::{RoundingMode, TimeDelta, TimePoint, TimeSpan};
+use crate::SubtitleEntry;
+use failure::ResultExt;
+
+/// Errors specific to marker lists.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(Clone, Copy, PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "the frame rate has to be a positive number")]
+        InvalidFps,
+
+        #[fail(display = "expected a timecode like `01:00:02:12` at line {}", line_num)]
+        ExpectedTimecode { line_num: usize },
+
+        #[fail(display = "the marker list has no `In` column")]
+        MissingInColumn,
+    }
+}
+
+/// A marker on the timeline of a video editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    /// The start and end of the marker (a marker without duration is one frame long).
+    pub timespan: TimeSpan,
+
+    /// The name which is shown on the timeline.
+    pub name: String,
+}
+
+impl Marker {
+    /// Converts the marker into an entry without text.
+    pub fn to_empty_entry(&self) -> SubtitleEntry {
+        SubtitleEntry::new(self.timespan, String::new())
+    }
+}
+
+/// Settings for the timecodes of a marker list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerOptions {
+    /// The frame rate of the timeline.
+    pub fps: f64,
+
+    /// The timecode of the first frame of the timeline (Resolve uses `01:00:00:00` by default).
+    pub start: TimePoint,
+
+    /// The marker color of Resolve EDLs (like `ResolveColorBlue` or `ResolveColorRed`).
+    pub color: String,
+}
+
+impl Default for MarkerOptions {
+    fn default() -> MarkerOptions {
+        MarkerOptions {
+            fps: 25.0,
+            start: TimePoint::from_msecs(0),
+            color: "ResolveColorBlue".to_string(),
+        }
+    }
+}
+
+/// Creates a marker for every entry, named after the text of the entry (on a single line).
+pub fn markers_from_entries(entries: &[SubtitleEntry]) -> Vec<Marker> {
+    entries
+        .iter()
+        .map(|entry| Marker {
+            timespan: entry.timespan,
+            name: entry.line.as_deref().unwrap_or("").lines().collect::<Vec<_>>().join(" "),
+        })
+        .collect()
+}
+
+/// Returns the offset between the times of the entries and the timecodes of the timeline.
+fn start_offset(options: &MarkerOptions) -> TimeDelta {
+    options.start - TimePoint::from_msecs(0)
+}
+
+/// Returns the length of one frame.
+fn frame_delta(fps: f64) -> TimeDelta {
+    TimePoint::from_frame(1, fps, RoundingMode::Nearest) - TimePoint::from_msecs(0)
+}
+
+/// Formats a time of the timeline as a non-drop-frame timecode.
+fn timecode(t: TimePoint, options: &MarkerOptions) -> String {
+    let base = options.fps.round().max(1.0) as i64;
+    let frame = (t + start_offset(options)).to_frame(options.fps, RoundingMode::Nearest).max(0);
+    let (secs, frames) = (frame / base, frame % base);
+    format!("{:02}:{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60, frames)
+}
+
+/// Parses a timecode like `01:00:02:12` (or `01:00:02;12` for drop-frame) into a time of the timeline.
+fn parse_timecode(s: &str, options: &MarkerOptions) -> Option<TimePoint> {
+    let parts: Vec<i64> = s.trim().split([':', ';']).map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let (hours, mins, secs, frames) = match *parts.as_slice() {
+        [hours, mins, secs, frames] => (hours, mins, secs, frames),
+        _ => return None,
+    };
+    let base = options.fps.round().max(1.0) as i64;
+    let frame = hours
+        .checked_mul(60)?
+        .checked_add(mins)?
+        .checked_mul(60)?
+        .checked_add(secs)?
+        .checked_mul(base)?
+        .checked_add(frames)?;
+    Some(TimePoint::from_frame(frame, options.fps, RoundingMode::Nearest) - start_offset(options))
+}
+
+/// Returns the length of a marker in frames (at least one).
+fn duration_frames(timespan: TimeSpan, fps: f64) -> i64 {
+    (timespan.end.to_frame(fps, RoundingMode::Nearest) - timespan.start.to_frame(fps, RoundingMode::Nearest)).max(1)
+}
+
+/// Writes the markers as an EDL which DaVinci Resolve imports as timeline markers.
+pub fn to_resolve_edl(markers: &[Marker], title: &str, options: &MarkerOptions) -> String {
+    let mut result = format!("TITLE: {}\nFCM: NON-DROP FRAME\n\n", title);
+    for (i, marker) in markers.iter().enumerate() {
+        let (start, next) = (
+            timecode(marker.timespan.start, options),
+            timecode(marker.timespan.start + frame_delta(options.fps), options),
+        );
+        result.push_str(&format!(
+            "{:03}  001      V     C        {} {} {} {}  \n",
+            i + 1,
+            start,
+            next,
+            start,
+            next
+        ));
+        result.push_str(&format!(
+            " |C:{} |M:{} |D:{}\n\n",
+            options.color,
+            marker.name.replace('|', "/"),
+            duration_frames(marker.timespan, options.fps)
+        ));
+    }
+    result
+}
+
+/// Reads the markers of an EDL which was exported by DaVinci Resolve (or written by `to_resolve_edl()`).
+pub fn from_resolve_edl(edl: &str, options: &MarkerOptions) -> SubtitleParserResult<Vec<Marker>> {
+    Ok(from_resolve_edl_inner(edl, options).with_context(|_| crate::ErrorKind::ParsingError)?)
+}
+
+fn from_resolve_edl_inner(edl: &str, options: &MarkerOptions) -> Result<Vec<Marker>> {
+    check_fps(options.fps)?;
+    let mut result: Vec<Marker> = Vec::new();
+    for (line_num, line) in edl.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.first().is_some_and(|event| event.bytes().all(|b| b.is_ascii_digit())) && fields.len() >= 8 {
+            // the record in point of the event is the position on the timeline
+            let start = fields
+                .get(6)
+                .and_then(|tc| parse_timecode(tc, options))
+                .ok_or(ExpectedTimecode { line_num })?;
+            result.push(Marker {
+                timespan: TimeSpan::new(start, start + frame_delta(options.fps)),
+                name: String::new(),
+            });
+        } else if let (Some(marker), true) = (result.last_mut(), line.trim_start().starts_with('|')) {
+            for field in line.split('|').map(str::trim) {
+                if let Some(name) = field.strip_prefix("M:") {
+                    marker.name = name.to_string();
+                } else if let Some(frames) = field.strip_prefix("D:").and_then(|frames| frames.parse::<i64>().ok()) {
+                    let start_frame = marker.timespan.start.to_frame(options.fps, RoundingMode::Nearest);
+                    marker.timespan.end = TimePoint::from_frame(start_frame.saturating_add(frames.max(1)), options.fps, RoundingMode::Nearest);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Quotes a CSV field if necessary.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\t']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Splits a CSV line into its fields (quoted fields can contain the separator).
+fn split_csv_line(line: &str, separator: char) -> Vec<String> {
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Writes the markers as a CSV file in the layout of the marker list of Adobe Premiere.
+pub fn to_premiere_csv(markers: &[Marker], options: &MarkerOptions) -> String {
+    // the duration is a timecode too, but without the start of the timeline
+    let no_offset = MarkerOptions {
+        start: TimePoint::from_msecs(0),
+        ..options.clone()
+    };
+    let mut result = "Marker Name,Description,In,Out,Duration,Marker Type\n".to_string();
+    for marker in markers {
+        let duration = TimePoint::from_msecs(0) + marker.timespan.len();
+        result.push_str(&format!(
+            "{},,{},{},{},Comment\n",
+            csv_field(&marker.name),
+            timecode(marker.timespan.start, options),
+            timecode(marker.timespan.end, options),
+            timecode(duration, &no_offset)
+        ));
+    }
+    result
+}
+
+/// Reads a marker list of Adobe Premiere (comma- or tab-separated, which is how Premiere exports it).
+pub fn from_premiere_csv(csv: &str, options: &MarkerOptions) -> SubtitleParserResult<Vec<Marker>> {
+    Ok(from_premiere_csv_inner(csv, options).with_context(|_| crate::ErrorKind::ParsingError)?)
+}
+
+fn from_premiere_csv_inner(csv: &str, options: &MarkerOptions) -> Result<Vec<Marker>> {
+    check_fps(options.fps)?;
+    let (_, csv) = crate::formats::common::split_bom(csv);
+    let mut lines = csv.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let header = lines.next().map(|(_, header)| header).unwrap_or("");
+    let separator = if header.contains('\t') { '\t' } else { ',' };
+    let columns = split_csv_line(header, separator);
+    let column = |name: &str| columns.iter().position(|column| column.trim() == name);
+    let (name_column, in_column, out_column) = (column("Marker Name"), column("In").ok_or(MissingInColumn)?, column("Out"));
+
+    let mut result = Vec::new();
+    for (line_num, line) in lines {
+        let fields = split_csv_line(line, separator);
+        let time = |index: Option<usize>| index.and_then(|index| fields.get(index)).and_then(|tc| parse_timecode(tc, options));
+        let start = time(Some(in_column)).ok_or(ExpectedTimecode { line_num })?;
+        let end = time(out_column)
+            .filter(|end| *end > start)
+            .unwrap_or_else(|| start + frame_delta(options.fps));
+        result.push(Marker {
+            timespan: TimeSpan::new(start, end),
+            name: name_column.and_then(|index| fields.get(index)).cloned().unwrap_or_default(),
+        });
+    }
+    Ok(result)
+}
+
+fn check_fps(fps: f64) -> Result<()> {
+    if fps > 0.0 && fps.is_finite() {
+        Ok(())
+    } else {
+        Err(InvalidFps.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_round_trips() {
+        let entry = |start: i64, end: i64, text: &str| {
+            SubtitleEntry::new(TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end)), text.to_string())
+        };
+        let entries = [entry(1000, 2000, "Hello\nworld"), entry(61_040, 63_000, "Tom | \"Jerry\", again")];
+        let markers = markers_from_entries(&entries);
+        assert_eq!(markers[0].name, "Hello world");
+
+        let options = MarkerOptions {
+            start: TimePoint::from_hours(1),
+            ..MarkerOptions::default()
+        };
+        let edl = to_resolve_edl(&markers, "Subtitles", &options);
+        assert!(edl.contains(
+            "002  001      V     C        01:01:01:01 01:01:01:02 01:01:01:01 01:01:01:02  \n |C:ResolveColorBlue |M:Tom / \"Jerry\", again |D:49\n"
+        ));
+        let imported = from_resolve_edl(&edl, &options).unwrap();
+        assert_eq!(imported[0], markers[0]);
+        assert_eq!(
+            imported[1].timespan,
+            TimeSpan::new(TimePoint::from_msecs(61_040), TimePoint::from_msecs(63_000))
+        );
+
+        let csv = to_premiere_csv(&markers, &options);
+        assert!(csv.contains("\"Tom | \"\"Jerry\"\", again\",,01:01:01:01,01:01:03:00,00:00:01:24,Comment\n"));
+        assert_eq!(from_premiere_csv(&csv, &options).unwrap(), markers);
+        let tabs = "Marker Name\tDescription\tIn\tOut\tDuration\tMarker Type\nScene\t\t00:00:04:00\t00:00:04:00\t00:00:00:00\tComment\n";
+        let imported = from_premiere_csv(tabs, &MarkerOptions::default()).unwrap();
+        assert_eq!(imported[0].to_empty_entry(), entry(4000, 4040, ""));
+        assert!(from_premiere_csv("Name,Out\n", &options).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Writing subtitle files as tracks of a Matroska file.
+//!
+//! `to_matroska()` creates a Matroska file which only contains subtitle tracks (usually saved as `.mks`). `.ssa`
+//! files become `S_TEXT/ASS` tracks with their styles, all other text formats `S_TEXT/UTF8` tracks. The file can be
+//! played next to the video or merged into it (`mkvmerge -o out.mkv video.mkv subtitles.mks`).
+//!
+//! Writing into an existing Matroska file is out of scope: adding the tracks to a file with video would require
+//! rewriting all of its clusters, cues and seek heads, which is left to `mkvmerge`. Bitmap formats can't be written
+//! either (their entries have no text).
+//!
+//! ```
+//! use subparse::matroska::{to_matroska, MatroskaTrack};
+//! # let file = subparse::parse_str(subparse::SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nHello\n", 25.0).unwrap();
+//!
+//! let track = MatroskaTrack::new(&file, "eng").with_name("English (SDH)");
+//! let mks: Vec<u8> = to_matroska(&[track]).unwrap();
+//! # assert!(mks.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]));
+//! ```
+
+use crate::errors::Result;
+use crate::timetypes::TimeSpan;
+use crate::SubtitleFile;
+
+/// Element IDs of the Matroska specification.
+mod ids {
+    pub const EBML: u32 = 0x1A45_DFA3;
+    pub const EBML_VERSION: u32 = 0x4286;
+    pub const EBML_READ_VERSION: u32 = 0x42F7;
+    pub const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+    pub const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+    pub const DOC_TYPE: u32 = 0x4282;
+    pub const DOC_TYPE_VERSION: u32 = 0x4287;
+    pub const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+    pub const SEGMENT: u32 = 0x1853_8067;
+    pub const INFO: u32 = 0x1549_A966;
+    pub const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+    pub const MUXING_APP: u32 = 0x4D80;
+    pub const WRITING_APP: u32 = 0x5741;
+    pub const DURATION: u32 = 0x4489;
+    pub const TRACKS: u32 = 0x1654_AE6B;
+    pub const TRACK_ENTRY: u32 = 0xAE;
+    pub const TRACK_NUMBER: u32 = 0xD7;
+    pub const TRACK_UID: u32 = 0x73C5;
+    pub const TRACK_TYPE: u32 = 0x83;
+    pub const FLAG_DEFAULT: u32 = 0x88;
+    pub const FLAG_FORCED: u32 = 0x55AA;
+    pub const FLAG_LACING: u32 = 0x9C;
+    pub const LANGUAGE: u32 = 0x22_B59C;
+    pub const LANGUAGE_BCP47: u32 = 0x22_B59D;
+    pub const NAME: u32 = 0x536E;
+    pub const CODEC_ID: u32 = 0x86;
+    pub const CODEC_PRIVATE: u32 = 0x63A2;
+    pub const CLUSTER: u32 = 0x1F43_B675;
+    pub const TIMESTAMP: u32 = 0xE7;
+    pub const BLOCK_GROUP: u32 = 0xA0;
+    pub const BLOCK: u32 = 0xA1;
+    pub const BLOCK_DURATION: u32 = 0x9B;
+}
+
+/// The `TrackType` of subtitle tracks.
+const TRACK_TYPE_SUBTITLE: u64 = 0x11;
+
+/// A subtitle file together with the metadata of its track.
+#[derive(Debug, Clone, Copy)]
+pub struct MatroskaTrack<'a> {
+    /// The subtitles (any text format).
+    pub file: &'a SubtitleFile,
+
+    /// The language as ISO 639-2 code like `"eng"` (`"und"` if it is unknown).
+    ///
+    /// If all entries of the file have the same `SubtitleEntry::language`, it is written as BCP 47 tag too (which
+    /// newer players prefer).
+    pub language: &'a str,
+
+    /// The name of the track shown by players (like `"English (SDH)"`).
+    pub name: Option<&'a str>,
+
+    /// The track is selected by players if the user has no preference.
+    pub default: bool,
+
+    /// The track only contains forced subtitles (translations of signs, ...).
+    pub forced: bool,
+}
+
+impl<'a> MatroskaTrack<'a> {
+    /// Creates a track without name that is not the default track.
+    pub fn new(file: &'a SubtitleFile, language: &'a str) -> MatroskaTrack<'a> {
+        MatroskaTrack {
+            file,
+            language,
+            name: None,
+            default: false,
+            forced: false,
+        }
+    }
+
+    /// Sets the name of the track.
+    pub fn with_name(self, name: &'a str) -> MatroskaTrack<'a> {
+        MatroskaTrack { name: Some(name), ..self }
+    }
+}
+
+/// Appends an EBML variable length integer (the value with all bits set is reserved for "unknown").
+fn vint(out: &mut Vec<u8>, value: u64) {
+    let len = (1..8).find(|&len| value < (1 << (7 * len)) - 1).unwrap_or(8);
+    let marked = value | (1 << (7 * len));
+    out.extend(marked.to_be_bytes().iter().skip(8 - len as usize));
+}
+
+/// Appends an EBML element with the given payload.
+fn element(out: &mut Vec<u8>, id: u32, payload: &[u8]) {
+    out.extend(id.to_be_bytes().iter().skip_while(|&&b| b == 0));
+    vint(out, payload.len() as u64);
+    out.extend_from_slice(payload);
+}
+
+/// Appends an unsigned integer element.
+fn uint_element(out: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(7).take_while(|&&b| b == 0).count();
+    element(out, id, bytes.get(skip..).unwrap_or(&[]));
+}
+
+/// Returns the milliseconds of a time (Matroska can't store negative times).
+fn msecs(t: crate::timetypes::TimePoint) -> u64 {
+    t.msecs().max(0) as u64
+}
+
+/// Writes the subtitle files as tracks of a new Matroska file (see the module documentation).
+///
+/// Only new files are written: there is no way to add the tracks to an existing Matroska file. Returns
+/// `ErrorKind::TextFormatOnly` for bitmap formats (VobSub, PGS, BDN, ...).
+pub fn to_matroska(tracks: &[MatroskaTrack<'_>]) -> Result<Vec<u8>> {
+    let mut track_entries = Vec::new();
+    // (start, duration, track number, data)
+    let mut blocks: Vec<(u64, u64, u64, String)> = Vec::new();
+    for (track_number, track) in (1..).zip(tracks) {
+        // the entries of bitmap formats have no text
+        if track.file.format().is_some_and(|format| format.capabilities().bitmap) {
+            return Err(crate::ErrorKind::TextFormatOnly.into());
+        }
+        let (codec_id, codec_private, track_blocks): (&str, Option<String>, Vec<(TimeSpan, String)>) = match track.file {
+            SubtitleFile::SubStationAlpha(f) => {
+                let (header, events) = f.to_matroska_blocks()?;
+                ("S_TEXT/ASS", Some(header), events)
+            }
+            file => {
+                let entries = file.get_subtitle_entries()?;
+                let blocks = entries
+                    .into_iter()
+                    .map(|entry| (entry.timespan, entry.line.unwrap_or_default()))
+                    .collect();
+                ("S_TEXT/UTF8", None, blocks)
+            }
+        };
+
+        let languages: Vec<Option<String>> = track.file.get_subtitle_entries()?.into_iter().map(|entry| entry.language).collect();
+        let file_language = match languages.first() {
+            Some(Some(language)) if languages.iter().all(|other| other.as_ref() == Some(language)) => Some(language),
+            _ => None,
+        };
+
+        let mut entry = Vec::new();
+        uint_element(&mut entry, ids::TRACK_NUMBER, track_number);
+        uint_element(&mut entry, ids::TRACK_UID, track_number);
+        uint_element(&mut entry, ids::TRACK_TYPE, TRACK_TYPE_SUBTITLE);
+        uint_element(&mut entry, ids::FLAG_DEFAULT, u64::from(track.default));
+        uint_element(&mut entry, ids::FLAG_FORCED, u64::from(track.forced));
+        uint_element(&mut entry, ids::FLAG_LACING, 0);
+        element(&mut entry, ids::LANGUAGE, track.language.as_bytes());
+        if let Some(language) = file_language {
+            element(&mut entry, ids::LANGUAGE_BCP47, language.as_bytes());
+        }
+        if let Some(name) = track.name {
+            element(&mut entry, ids::NAME, name.as_bytes());
+        }
+        element(&mut entry, ids::CODEC_ID, codec_id.as_bytes());
+        if let Some(codec_private) = codec_private {
+            element(&mut entry, ids::CODEC_PRIVATE, codec_private.as_bytes());
+        }
+        element(&mut track_entries, ids::TRACK_ENTRY, &entry);
+
+        for (timespan, text) in track_blocks {
+            let start = msecs(timespan.start);
+            blocks.push((start, msecs(timespan.end).saturating_sub(start), track_number, text));
+        }
+    }
+
+    // the blocks of a cluster are ordered by time and their timestamps are relative to the cluster (16 bit)
+    blocks.sort_by_key(|&(start, _, track_number, _)| (start, track_number));
+    let mut clusters = Vec::new();
+    let mut cluster: Option<(u64, Vec<u8>)> = None;
+    for (start, duration, track_number, text) in &blocks {
+        let cluster_start = match cluster {
+            Some((cluster_start, _)) if start - cluster_start <= i16::MAX as u64 => cluster_start,
+            _ => {
+                if let Some((_, content)) = cluster.take() {
+                    element(&mut clusters, ids::CLUSTER, &content);
+                }
+                let mut content = Vec::new();
+                uint_element(&mut content, ids::TIMESTAMP, *start);
+                cluster = Some((*start, content));
+                *start
+            }
+        };
+
+        // track number, relative timestamp, flags, data
+        let mut block = Vec::new();
+        vint(&mut block, *track_number);
+        block.extend(((start - cluster_start) as i16).to_be_bytes().iter());
+        block.push(0);
+        block.extend(text.as_bytes());
+
+        let mut group = Vec::new();
+        element(&mut group, ids::BLOCK, &block);
+        uint_element(&mut group, ids::BLOCK_DURATION, *duration);
+        if let Some((_, ref mut content)) = cluster {
+            element(content, ids::BLOCK_GROUP, &group);
+        }
+    }
+    if let Some((_, content)) = cluster {
+        element(&mut clusters, ids::CLUSTER, &content);
+    }
+
+    let mut info = Vec::new();
+    uint_element(&mut info, ids::TIMESTAMP_SCALE, 1_000_000);
+    element(&mut info, ids::MUXING_APP, b"subparse");
+    element(&mut info, ids::WRITING_APP, b"subparse");
+    let duration = blocks.iter().map(|&(start, duration, _, _)| start + duration).max().unwrap_or(0);
+    element(&mut info, ids::DURATION, &(duration as f64).to_be_bytes());
+
+    let mut segment = Vec::new();
+    element(&mut segment, ids::INFO, &info);
+    element(&mut segment, ids::TRACKS, &track_entries);
+    segment.extend(clusters);
+
+    let mut header = Vec::new();
+    uint_element(&mut header, ids::EBML_VERSION, 1);
+    uint_element(&mut header, ids::EBML_READ_VERSION, 1);
+    uint_element(&mut header, ids::EBML_MAX_ID_LENGTH, 4);
+    uint_element(&mut header, ids::EBML_MAX_SIZE_LENGTH, 8);
+    element(&mut header, ids::DOC_TYPE, b"matroska");
+    uint_element(&mut header, ids::DOC_TYPE_VERSION, 4);
+    uint_element(&mut header, ids::DOC_TYPE_READ_VERSION, 2);
+
+    let mut result = Vec::new();
+    element(&mut result, ids::EBML, &header);
+    element(&mut result, ids::SEGMENT, &segment);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn write_matroska_tracks() {
+        let srt = crate::parse_str(
+            SubtitleFormat::SubRip,
+            "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:01:00,000 --> 00:01:01,000\nLater\n",
+            25.0,
+        )
+        .unwrap();
+        let ssa = "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\n\n\
+                   [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                   Dialogue: 1,0:00:01.50,0:00:03.00,Default,Bob,0,0,10,,{\\i1}Hi\n";
+        let ssa = crate::parse_str(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        let tracks = [MatroskaTrack::new(&srt, "eng").with_name("English"), MatroskaTrack::new(&ssa, "ger")];
+        let mkv = to_matroska(&tracks).unwrap();
+
+        // EBML header and a segment which spans the rest of the file
+        assert!(mkv.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]));
+        let header_len = 5 + mkv[4] as usize - 0x80;
+        assert_eq!(&mkv[header_len..header_len + 4], &[0x18, 0x53, 0x80, 0x67]);
+        let size_len = mkv[header_len + 4].leading_zeros() as usize + 1;
+        let size_bytes = &mkv[header_len + 4..header_len + 4 + size_len];
+        let segment_size = size_bytes.iter().fold(0, |size, &b| size << 8 | b as usize) & ((1 << (7 * size_len)) - 1);
+        assert_eq!(segment_size, mkv.len() - header_len - 4 - size_len);
+
+        let contains = |needle: &[u8]| mkv.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"S_TEXT/UTF8") && contains(b"S_TEXT/ASS") && contains(b"English"));
+        assert!(contains(
+            b"[V4+ Styles]\nFormat: Name, Fontname\nStyle: Default,Arial\n\n[Events]\nFormat: Layer, Start"
+        ));
+        assert!(contains(b"0,1,Default,Bob,0,0,10,,{\\i1}Hi"));
+        // the second entry is more than 32 seconds later, so it is in a new cluster
+        assert!(contains(&[0xE7, 0x82, 0xEA, 0x60]));
+    }
+
+    #[test]
+    fn reject_bitmap_formats() {
+        let idx = crate::parse_str(SubtitleFormat::VobSubIdx, "timestamp: 00:00:01:000, filepos: 000000000\n", 25.0).unwrap();
+        let bdn = "<BDN Version=\"0.93\"><Description><Format VideoFormat=\"1080p\" FrameRate=\"25\"/></Description><Events>\
+                   <Event InTC=\"00:00:01:00\" OutTC=\"00:00:02:00\"><Graphic>1.png</Graphic></Event></Events></BDN>";
+        let bdn = crate::parse_str(SubtitleFormat::Bdn, bdn, 25.0).unwrap();
+        for file in &[idx, bdn] {
+            let error = to_matroska(&[MatroskaTrack::new(file, "eng")]).unwrap_err();
+            assert_eq!(error.kind(), crate::ErrorKind::TextFormatOnly);
+        }
+    }
+
+    #[test]
+    fn write_language_of_entries() {
+        let ttml = "<tt xmlns=\"http://www.w3.org/ns/ttml\" xml:lang=\"pt-BR\"><body><div>\
+                    <p begin=\"1s\" end=\"2s\">Olá</p><p begin=\"3s\" end=\"4s\">Tchau</p></div></body></tt>";
+        let ttml = crate::parse_str(SubtitleFormat::Ttml, ttml, 25.0).unwrap();
+        let mkv = to_matroska(&[MatroskaTrack::new(&ttml, "por")]).unwrap();
+        let contains = |mkv: &[u8], needle: &[u8]| mkv.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(&mkv, &[0x22, 0xB5, 0x9C, 0x83, b'p', b'o', b'r']));
+        assert!(contains(&mkv, &[0x22, 0xB5, 0x9D, 0x85, b'p', b't', b'-', b'B', b'R']));
+
+        // files without languages only have the ISO 639-2 code
+        let srt = crate::parse_str(SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nOlá\n", 25.0).unwrap();
+        assert!(!contains(&to_matroska(&[MatroskaTrack::new(&srt, "por")]).unwrap(), &[0x22, 0xB5, 0x9D]));
+    }
+}
@@ -0,0 +1,146 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Metrics about how much of a file was recognized by the parser.
+//!
+//! The non-destructive parsers keep everything they don't understand as "filler", so a file can be parsed
+//! successfully although almost nothing was recognized: an `.idx` file with a typo in every `timestamp:` key has
+//! no entries, but no error either. `SubtitleFile::parse_metrics()` makes this visible:
+//!
+//! ```
+//! use subparse::{parse_str_with_warnings, SubtitleFormat};
+//!
+//! let idx = "# VobSub index file, v7\nsize: 720x480\ntimestmp: 00:00:01:000, filepos: 000000000\n";
+//! let (file, warnings) = parse_str_with_warnings(SubtitleFormat::VobSubIdx, idx, 25.0).unwrap();
+//! let metrics = file.parse_metrics().unwrap().with_warnings(&warnings);
+//! assert_eq!((metrics.entries, metrics.unrecognized_lines), (0, 3));
+//! assert!(metrics.is_suspicious());
+//! ```
+
+use crate::errors::Result;
+use crate::warnings::{WarningKind, Warnings};
+use crate::SubtitleFile;
+
+/// How much of a file was recognized (see the module documentation).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// The size of the file as written by `to_data()`.
+    pub total_bytes: usize,
+
+    /// The bytes which are kept without being interpreted (comments, headers, unknown lines, whitespace, ...).
+    ///
+    /// This is always `0` for formats which interpret or reject everything (like `.srt` or binary formats).
+    pub filler_bytes: usize,
+
+    /// The number of entries.
+    pub entries: usize,
+
+    /// The number of non-empty lines which only consist of filler (including headers and comments).
+    pub unrecognized_lines: usize,
+
+    /// The number of ignored lines and unknown header keys reported by the parser (see `with_warnings()`).
+    pub unknown_lines: usize,
+}
+
+impl ParseMetrics {
+    /// Counts the ignored lines and unknown header keys in the warnings of the parser.
+    pub fn with_warnings(mut self, warnings: &Warnings) -> ParseMetrics {
+        self.unknown_lines = warnings
+            .iter()
+            .filter(|warning| matches!(warning.kind, WarningKind::IgnoredLine { .. } | WarningKind::UnknownHeaderKey { .. }))
+            .count();
+        self
+    }
+
+    /// Returns the bytes which were interpreted (timestamps, texts, styles, ...).
+    pub fn typed_bytes(&self) -> usize {
+        self.total_bytes.saturating_sub(self.filler_bytes)
+    }
+
+    /// Returns the share of the interpreted bytes (between `0.0` and `1.0`, `1.0` for empty files).
+    pub fn typed_ratio(&self) -> f64 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.typed_bytes() as f64 / self.total_bytes as f64
+        }
+    }
+
+    /// Returns `true` if the file has content, but no entry was recognized.
+    pub fn is_suspicious(&self) -> bool {
+        self.entries == 0 && self.unrecognized_lines + self.unknown_lines > 0
+    }
+}
+
+/// Counts the filler bytes and the lines that only consist of filler.
+fn count_filler(parts: &[Option<&str>]) -> (usize, usize) {
+    let (mut bytes, mut lines) = (0, 0);
+    let (mut content, mut typed) = (false, false);
+    for part in parts {
+        let text = match *part {
+            Some(text) => text,
+            None => {
+                typed = true;
+                continue;
+            }
+        };
+        bytes += text.len();
+        let mut pieces = text.split('\n').peekable();
+        while let Some(piece) = pieces.next() {
+            content |= !piece.trim().is_empty();
+            if pieces.peek().is_some() {
+                // the piece ends with a line break
+                if content && !typed {
+                    lines += 1;
+                }
+                content = false;
+                typed = false;
+            }
+        }
+    }
+    if content && !typed {
+        lines += 1;
+    }
+    (bytes, lines)
+}
+
+impl SubtitleFile {
+    /// Returns how much of the file was recognized (see the module documentation).
+    pub fn parse_metrics(&self) -> Result<ParseMetrics> {
+        let (filler_bytes, unrecognized_lines) = count_filler(&self.filler_parts());
+        Ok(ParseMetrics {
+            total_bytes: self.to_data()?.len(),
+            filler_bytes,
+            entries: self.get_subtitle_entries()?.len(),
+            unrecognized_lines,
+            unknown_lines: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_str_with_warnings, SubtitleFormat};
+
+    #[test]
+    fn filler_metrics() {
+        let idx =
+            "# VobSub index file, v7\nsize: 720x480\n\ntimestamp: 00:00:01:000, filepos: 000000000\ntimestamp: 00:00:02:000, filepos: 000000800\n";
+        let file = crate::parse_str(SubtitleFormat::VobSubIdx, idx, 25.0).unwrap();
+        let metrics = file.parse_metrics().unwrap();
+        assert_eq!((metrics.total_bytes, metrics.entries, metrics.unrecognized_lines), (idx.len(), 2, 2));
+        assert_eq!(metrics.typed_bytes(), 24);
+        assert!(!metrics.is_suspicious());
+
+        let ssa = "[Events]\nFormat: Layer, Start, End, Style, Name, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,Hi\nDialog: broken\n";
+        let (file, warnings) = parse_str_with_warnings(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        let metrics = file.parse_metrics().unwrap().with_warnings(&warnings);
+        assert_eq!((metrics.entries, metrics.unrecognized_lines, metrics.unknown_lines), (1, 3, 1));
+        assert!(metrics.typed_ratio() > 0.0 && metrics.typed_ratio() < 1.0);
+
+        let srt = crate::parse_str(SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nHi\n", 25.0).unwrap();
+        let metrics = srt.parse_metrics().unwrap();
+        assert_eq!((metrics.filler_bytes, metrics.typed_ratio()), (0, 1.0));
+    }
+}
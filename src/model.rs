@@ -0,0 +1,242 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Stable types for the subtitles of a file, independent of the format modules.
+//!
+//! The types of the format modules (and `SubtitleEntry`) change whenever a format learns something new. The types
+//! of this module only change in a new version module: `model::v1` stays as it is when a `model::v2` is added, and
+//! `model` itself re-exports the newest version. New fields can be added to a version (the structs are
+//! `#[non_exhaustive]`), so use the constructors instead of struct literals.
+//!
+//! ```
+//! use std::convert::TryFrom;
+//! use subparse::model::v1::{Entry, TimeSpan, Track};
+//! use subparse::SubtitleFormat;
+//!
+//! let mut file = subparse::parse_str(SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i>\n", 25.0).unwrap();
+//! let mut track = Track::try_from(&file).unwrap();
+//! assert!(track.entries[0].styling.italic);
+//!
+//! track.entries[0] = Entry::new(TimeSpan::new(500, 2000), "Hi");
+//! track.apply_to(&mut file).unwrap();
+//! assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Hi"));
+//! ```
+
+pub use self::v1::*;
+
+/// The first version of the model types.
+pub mod v1 {
+    use crate::errors::Result;
+    use crate::flags::{is_styled, EntryFlags};
+    use crate::timetypes;
+    use crate::{SubtitleEntry, SubtitleFile, SubtitleFormat};
+    use std::convert::TryFrom;
+
+    /// A time span in milliseconds.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[non_exhaustive]
+    pub struct TimeSpan {
+        /// The start in milliseconds (can be negative after shifting).
+        pub start_ms: i64,
+
+        /// The end in milliseconds.
+        pub end_ms: i64,
+    }
+
+    impl TimeSpan {
+        /// Creates a time span from the start and the end in milliseconds.
+        pub fn new(start_ms: i64, end_ms: i64) -> TimeSpan {
+            TimeSpan { start_ms, end_ms }
+        }
+    }
+
+    impl From<timetypes::TimeSpan> for TimeSpan {
+        fn from(timespan: timetypes::TimeSpan) -> TimeSpan {
+            TimeSpan::new(timespan.start.msecs(), timespan.end.msecs())
+        }
+    }
+
+    impl From<TimeSpan> for timetypes::TimeSpan {
+        fn from(timespan: TimeSpan) -> timetypes::TimeSpan {
+            timetypes::TimeSpan::new(
+                timetypes::TimePoint::from_msecs(timespan.start_ms),
+                timetypes::TimePoint::from_msecs(timespan.end_ms),
+            )
+        }
+    }
+
+    /// The styles which apply to the whole text of an entry.
+    ///
+    /// The text keeps its own markup, so this is only a summary (which is recomputed from the text).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[non_exhaustive]
+    pub struct Styling {
+        /// The whole text is in italics.
+        pub italic: bool,
+
+        /// The whole text is bold.
+        pub bold: bool,
+
+        /// The whole text is underlined.
+        pub underline: bool,
+    }
+
+    impl Styling {
+        /// Detects the styles of a text with tags like `<i>` or `{\i1}`.
+        pub fn from_text(text: &str) -> Styling {
+            Styling {
+                italic: is_styled(text, 'i'),
+                bold: is_styled(text, 'b'),
+                underline: is_styled(text, 'u'),
+            }
+        }
+    }
+
+    /// A subtitle.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub struct Entry {
+        /// When the subtitle is shown.
+        pub timespan: TimeSpan,
+
+        /// The text with the markup of the format (`None` for images).
+        pub text: Option<String>,
+
+        /// The language as BCP 47 tag (like `"pt-BR"`), if known.
+        pub language: Option<String>,
+
+        /// The styles of the whole text.
+        pub styling: Styling,
+
+        /// The subtitle has to be shown even if subtitles are disabled.
+        pub forced: bool,
+    }
+
+    impl Entry {
+        /// Creates an entry with a text.
+        pub fn new(timespan: TimeSpan, text: &str) -> Entry {
+            Entry {
+                timespan,
+                text: Some(text.to_string()),
+                language: None,
+                styling: Styling::from_text(text),
+                forced: false,
+            }
+        }
+
+        /// Creates an entry without text (like an image).
+        pub fn without_text(timespan: TimeSpan) -> Entry {
+            Entry {
+                timespan,
+                text: None,
+                language: None,
+                styling: Styling::default(),
+                forced: false,
+            }
+        }
+    }
+
+    impl From<SubtitleEntry> for Entry {
+        fn from(entry: SubtitleEntry) -> Entry {
+            Entry {
+                timespan: entry.timespan.into(),
+                styling: entry.line.as_deref().map(Styling::from_text).unwrap_or_default(),
+                text: entry.line,
+                language: entry.language,
+                forced: entry.flags.forced,
+            }
+        }
+    }
+
+    impl From<Entry> for SubtitleEntry {
+        fn from(entry: Entry) -> SubtitleEntry {
+            let flags = EntryFlags {
+                forced: entry.forced,
+                ..entry.text.as_deref().map(EntryFlags::from_text).unwrap_or_default()
+            };
+            SubtitleEntry {
+                timespan: entry.timespan.into(),
+                line: entry.text,
+                language: entry.language,
+                flags,
+            }
+        }
+    }
+
+    /// The subtitles of a file.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+    #[non_exhaustive]
+    pub struct Track {
+        /// The language of all entries, if they have the same one.
+        pub language: Option<String>,
+
+        /// The subtitles in the order of the file.
+        pub entries: Vec<Entry>,
+    }
+
+    impl Track {
+        /// Creates a track from entries.
+        pub fn new(entries: Vec<Entry>) -> Track {
+            let mut languages = entries.iter().map(|entry| entry.language.as_deref());
+            let first = languages.next().flatten();
+            let language = if languages.all(|language| language == first) {
+                first.map(str::to_string)
+            } else {
+                None
+            };
+            Track { language, entries }
+        }
+
+        /// Writes the entries back into the file they were read from (the number of entries has to be the same).
+        pub fn apply_to(&self, file: &mut SubtitleFile) -> Result<()> {
+            let entries: Vec<SubtitleEntry> = self.entries.iter().cloned().map(SubtitleEntry::from).collect();
+            file.update_subtitle_entries(&entries)
+        }
+
+        /// Creates a file of a format from the entries with text (see `SubtitleFormat::create_file()`).
+        pub fn to_file(&self, format: SubtitleFormat, fps: f64) -> Result<SubtitleFile> {
+            let v = self
+                .entries
+                .iter()
+                .filter_map(|entry| entry.text.clone().map(|text| (entry.timespan.into(), text)))
+                .collect();
+            format.create_file(v, fps)
+        }
+    }
+
+    impl<'a> TryFrom<&'a SubtitleFile> for Track {
+        type Error = crate::errors::Error;
+
+        fn try_from(file: &'a SubtitleFile) -> Result<Track> {
+            Ok(Track::new(file.get_subtitle_entries()?.into_iter().map(Entry::from).collect()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn model_conversions() {
+        let ssa = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Forced,,0,0,0,,{\\b1}EXIT\n\
+                   Dialogue: 0,0:00:02.00,0:00:03.50,Default,,0,0,0,,<u>Hi</u> there";
+        let file = crate::parse_str(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        let track = Track::try_from(&file).unwrap();
+        assert_eq!(track.entries[0].timespan, TimeSpan::new(1000, 2000));
+        assert_eq!((track.entries[0].forced, track.entries[0].styling.bold), (true, true));
+        assert_eq!(track.entries[1].styling, Styling::default());
+
+        let entry = crate::SubtitleEntry::from(track.entries[0].clone());
+        assert!(entry.flags.forced);
+        assert_eq!(Entry::from(entry), track.entries[0]);
+
+        let srt = track.to_file(SubtitleFormat::SubRip, 25.0).unwrap();
+        assert_eq!(Track::try_from(&srt).unwrap().entries.len(), 2);
+        assert_eq!(Track::new(vec![Entry::without_text(TimeSpan::new(0, 1))]).language, None);
+    }
+}
@@ -0,0 +1,153 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Detection and normalization of music cues.
+//!
+//! Every source marks music differently: `♪` or `♫` around lyrics, `#` in captions from old character sets, and
+//! descriptions like `[music]` or `(MUSIC PLAYING)` for instrumental parts.
+
+use crate::errors::Result;
+use crate::SubtitleFile;
+
+/// Music notes which are always recognized as such.
+const MUSIC_NOTES: &[char] = &['♪', '♫', '♬', '♩'];
+
+/// Words of descriptions like `[music]` or `(MUSIC PLAYING)`.
+const MUSIC_WORDS: &[&str] = &["music", "musik", "musique", "música", "musica", "song", "singing"];
+
+/// Returns for every character whether it is part of a tag like `<i>` or `{\an8}`.
+pub(crate) fn tag_mask(line: &str) -> Vec<bool> {
+    let mut tag_end = None;
+    line.chars()
+        .map(|c| match tag_end {
+            Some(end) => {
+                if c == end {
+                    tag_end = None;
+                }
+                true
+            }
+            None if c == '<' || c == '{' => {
+                tag_end = Some(if c == '<' { '>' } else { '}' });
+                true
+            }
+            None => false,
+        })
+        .collect()
+}
+
+/// Replaces all music notes of a line by `symbol` (a `#` is only a note at the start or the end of the line).
+fn normalize_line(line: &str, symbol: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mask = tag_mask(line);
+    let visible: Vec<usize> = (0..chars.len())
+        .filter(|&i| mask.get(i) == Some(&false) && chars.get(i).is_some_and(|c| !c.is_whitespace()))
+        .collect();
+    let (first, last) = (visible.first().copied(), visible.last().copied());
+
+    let mut result = String::with_capacity(line.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let is_tag = mask.get(i) == Some(&true);
+        let is_note = !is_tag && (MUSIC_NOTES.contains(&c) || (c == '#' && (Some(i) == first || Some(i) == last)));
+        if is_note {
+            result.push_str(symbol);
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Replaces all music notes (`♪`, `♫`, `♬`, `♩` and `#` at the start or end of a line) by `symbol`.
+pub fn normalize_music_notes(text: &str, symbol: &str) -> String {
+    text.split('\n')
+        .map(|line| normalize_line(line, symbol))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Returns `true` if the text only marks music, like `♪♪`, `# #` or `[MUSIC PLAYING]` (but not `♪ la la ♪`).
+pub fn is_music_only(text: &str) -> bool {
+    let normalized = normalize_music_notes(text, "♪");
+    let mask = tag_mask(&normalized);
+    let visible: String = normalized.chars().zip(mask).filter(|&(_, is_tag)| !is_tag).map(|(c, _)| c).collect();
+    if !visible.contains('♪') && !visible.contains(['[', '(']) {
+        return false;
+    }
+
+    // remove descriptions and check that nothing but notes and punctuation remains
+    let mut rest = String::new();
+    let mut description = String::new();
+    let mut in_description = false;
+    for c in visible.chars() {
+        match c {
+            '[' | '(' => in_description = true,
+            ']' | ')' if in_description => {
+                in_description = false;
+                let lower = description.to_lowercase();
+                if !(MUSIC_WORDS.iter().any(|w| lower.contains(w)) || description.contains('♪')) {
+                    return false;
+                }
+                description.clear();
+            }
+            c if in_description => description.push(c),
+            c => rest.push(c),
+        }
+    }
+    !in_description && rest.chars().all(|c| c == '♪' || c.is_whitespace() || c.is_ascii_punctuation())
+}
+
+impl SubtitleFile {
+    /// Replaces all music notes by `symbol` (like `"♪"`) and returns the number of changed entries.
+    pub fn normalize_music_notes(&mut self, symbol: &str) -> Result<usize> {
+        let mut entries = self.get_subtitle_entries()?;
+        let mut changed = 0;
+        for entry in &mut entries {
+            if let Some(ref mut text) = entry.line {
+                let normalized = normalize_music_notes(text, symbol);
+                if normalized != *text {
+                    *text = normalized;
+                    changed += 1;
+                }
+            }
+        }
+        self.update_subtitle_entries(&entries)?;
+        Ok(changed)
+    }
+
+    /// Removes all entries which only mark music (see `is_music_only()`) and returns their number.
+    pub fn drop_music_only_entries(&mut self) -> Result<usize> {
+        let keep: Vec<bool> = self
+            .get_subtitle_entries()?
+            .iter()
+            .map(|entry| !entry.line.as_deref().is_some_and(is_music_only))
+            .collect();
+        self.retain_entries_by_mask(&keep)?;
+        Ok(keep.iter().filter(|&&keep| !keep).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn music_cues() {
+        assert_eq!(normalize_music_notes("<i># la la #</i>\n♫ la ♫", "♪"), "<i>♪ la la ♪</i>\n♪ la ♪");
+        assert_eq!(normalize_music_notes("track #1", "♪"), "track #1");
+        assert!(is_music_only("[MUSIC PLAYING]"));
+        assert!(is_music_only("{\\an8}♪ ♪"));
+        assert!(is_music_only("# #"));
+        assert!(!is_music_only("♪ la la ♪"));
+        assert!(!is_music_only("[door slams]"));
+
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n# la la #\n\n2\n00:00:03,000 --> 00:00:04,000\n(music)\n\n\
+                   3\n00:00:05,000 --> 00:00:06,000\nHello!\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        assert_eq!(file.normalize_music_notes("♪").unwrap(), 1);
+        assert_eq!(file.drop_music_only_entries().unwrap(), 1);
+        let texts: Vec<String> = file.get_subtitle_entries().unwrap().into_iter().map(|e| e.line.unwrap()).collect();
+        assert_eq!(texts, vec!["♪ la la ♪", "Hello!"]);
+    }
+}
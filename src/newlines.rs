@@ -0,0 +1,228 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Line breaks inside of cues.
+//!
+//! Every format has its own syntax for a line break inside of a cue: `\N` (and the "soft" break `\n`, which only
+//! breaks with `WrapStyle: 2`) in `.ssa`/`.ass` events, a real line feed in `.srt` and `.vtt` files, `|` in MicroDVD
+//! and MPL2 lines, `[br]` in SubViewer 2.0 and `<br>` in SAMI. A `LineBreakStyle` describes one of them, so texts
+//! can be split into their lines, converted between the styles, or forced onto a single line:
+//!
+//! ```
+//! use subparse::newlines::{convert_line_breaks, LineBreakStyle, NewlineOptions};
+//!
+//! let options = NewlineOptions::default();
+//! assert_eq!(convert_line_breaks("Hello\\Nworld", LineBreakStyle::Ass, LineBreakStyle::Pipe, &options), "Hello|world");
+//!
+//! let options = NewlineOptions::single_line();
+//! assert_eq!(convert_line_breaks("Hello\nworld", LineBreakStyle::Newline, LineBreakStyle::Newline, &options), "Hello world");
+//! ```
+//!
+//! The texts of a `SubtitleEntry` use line feeds in all formats except `.ssa`/`.ass` (see
+//! `LineBreakStyle::for_entries()`).
+
+use crate::errors::Result;
+use crate::{SubtitleFile, SubtitleFormat};
+
+/// A syntax for line breaks inside of a cue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBreakStyle {
+    /// A line feed (`\n` or `\r\n`), like in `.srt` and `.vtt` files.
+    Newline,
+
+    /// `\N` (and the soft break `\n`) of `.ssa`/`.ass` events.
+    Ass,
+
+    /// `|`, like in MicroDVD and MPL2 lines.
+    Pipe,
+
+    /// `[br]` (in any case), like in SubViewer 2.0 files.
+    BrTag,
+
+    /// `<br>`, `<br/>` or `<br />` (in any case), like in SAMI files.
+    Html,
+}
+
+impl LineBreakStyle {
+    /// Returns the line break style of the texts of `SubtitleEntry`s in the given format.
+    pub fn for_entries(format: SubtitleFormat) -> LineBreakStyle {
+        match format {
+            SubtitleFormat::SubStationAlpha => LineBreakStyle::Ass,
+            _ => LineBreakStyle::Newline,
+        }
+    }
+
+    /// Returns the line break which is written in this style.
+    pub fn separator(self) -> &'static str {
+        match self {
+            LineBreakStyle::Newline => "\n",
+            LineBreakStyle::Ass => "\\N",
+            LineBreakStyle::Pipe => "|",
+            LineBreakStyle::BrTag => "[br]",
+            LineBreakStyle::Html => "<br>",
+        }
+    }
+
+    /// Returns the length of the line break at the start of `s` and whether it is a soft break.
+    fn break_at(self, s: &str) -> Option<(usize, bool)> {
+        let starts_with_ignore_case = |prefix: &str| s.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+        match self {
+            LineBreakStyle::Newline if s.starts_with("\r\n") => Some((2, false)),
+            LineBreakStyle::Newline if s.starts_with('\n') => Some((1, false)),
+            LineBreakStyle::Ass if s.starts_with("\\N") => Some((2, false)),
+            LineBreakStyle::Ass if s.starts_with("\\n") => Some((2, true)),
+            LineBreakStyle::Pipe if s.starts_with('|') => Some((1, false)),
+            LineBreakStyle::BrTag if starts_with_ignore_case("[br]") => Some((4, false)),
+            LineBreakStyle::Html => ["<br>", "<br/>", "<br />"]
+                .iter()
+                .find(|tag| starts_with_ignore_case(tag))
+                .map(|tag| (tag.len(), false)),
+            _ => None,
+        }
+    }
+}
+
+/// How the soft line breaks (`\n`) of `.ssa`/`.ass` events are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftBreaks {
+    /// Soft breaks are line breaks (like with `WrapStyle: 2`).
+    Break,
+
+    /// Soft breaks are spaces (like with the other wrap styles).
+    Space,
+}
+
+/// Options for splitting and joining the lines of a cue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewlineOptions {
+    /// How the soft line breaks of `.ssa`/`.ass` events are treated.
+    pub soft_breaks: SoftBreaks,
+
+    /// Join all lines of a cue into a single line.
+    pub single_line: bool,
+
+    /// The separator between the lines if `single_line` is set.
+    pub joiner: String,
+}
+
+impl Default for NewlineOptions {
+    fn default() -> NewlineOptions {
+        NewlineOptions {
+            soft_breaks: SoftBreaks::Space,
+            single_line: false,
+            joiner: " ".to_string(),
+        }
+    }
+}
+
+impl NewlineOptions {
+    /// The default options, but all lines are joined with a space.
+    pub fn single_line() -> NewlineOptions {
+        NewlineOptions {
+            single_line: true,
+            ..NewlineOptions::default()
+        }
+    }
+}
+
+/// Splits a text into its lines.
+pub fn split_lines(text: &str, style: LineBreakStyle, options: &NewlineOptions) -> Vec<String> {
+    let (mut lines, mut line) = (Vec::new(), String::new());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = match style.break_at(rest) {
+            Some((len, true)) if options.soft_breaks == SoftBreaks::Space => {
+                line.push(' ');
+                len
+            }
+            Some((len, _)) => {
+                lines.push(std::mem::take(&mut line));
+                len
+            }
+            None => {
+                line.push(c);
+                c.len_utf8()
+            }
+        };
+        rest = rest.get(len..).unwrap_or("");
+    }
+    lines.push(line);
+    lines
+}
+
+/// Joins lines into a text (or into a single line, see `NewlineOptions::single_line`).
+pub fn join_lines(lines: &[String], style: LineBreakStyle, options: &NewlineOptions) -> String {
+    if options.single_line {
+        let words: Vec<&str> = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+        words.join(&options.joiner)
+    } else {
+        lines.join(style.separator())
+    }
+}
+
+/// Converts the line breaks of a text from one style into another.
+pub fn convert_line_breaks(text: &str, from: LineBreakStyle, to: LineBreakStyle, options: &NewlineOptions) -> String {
+    join_lines(&split_lines(text, from, options), to, options)
+}
+
+impl SubtitleFile {
+    /// Rewrites the line breaks in all entries with the options (for example to force single-line output).
+    ///
+    /// The texts keep the line break style of the format (see `LineBreakStyle::for_entries()`).
+    pub fn normalize_line_breaks(&mut self, options: &NewlineOptions) -> Result<()> {
+        let style = match *self {
+            SubtitleFile::SubStationAlpha(_) => LineBreakStyle::Ass,
+            _ => LineBreakStyle::Newline,
+        };
+        let mut entries = self.get_subtitle_entries()?;
+        for entry in &mut entries {
+            if let Some(ref mut text) = entry.line {
+                *text = convert_line_breaks(text, style, style, options);
+            }
+        }
+        self.update_subtitle_entries(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_break_styles() {
+        let options = NewlineOptions::default();
+        assert_eq!(split_lines("a\\Nb\\nc", LineBreakStyle::Ass, &options), vec!["a", "b c"]);
+        let soft = NewlineOptions {
+            soft_breaks: SoftBreaks::Break,
+            ..NewlineOptions::default()
+        };
+        assert_eq!(split_lines("a\\Nb\\nc", LineBreakStyle::Ass, &soft), vec!["a", "b", "c"]);
+        assert_eq!(
+            convert_line_breaks("a[BR]b<br />c", LineBreakStyle::BrTag, LineBreakStyle::Newline, &options),
+            "a\nb<br />c"
+        );
+        assert_eq!(
+            convert_line_breaks("a<Br/>b", LineBreakStyle::Html, LineBreakStyle::Ass, &options),
+            "a\\Nb"
+        );
+        assert_eq!(
+            convert_line_breaks("a\r\n\nb", LineBreakStyle::Newline, LineBreakStyle::Pipe, &options),
+            "a||b"
+        );
+
+        let joined = NewlineOptions {
+            joiner: " / ".to_string(),
+            ..NewlineOptions::single_line()
+        };
+        assert_eq!(
+            convert_line_breaks(" a |\n| b", LineBreakStyle::Pipe, LineBreakStyle::Pipe, &joined),
+            "a / b"
+        );
+
+        let ssa = "[Events]\nFormat: Layer, Start, End, Style, Name, Text\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,line1\\Nline2\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        file.normalize_line_breaks(&NewlineOptions::single_line()).unwrap();
+        assert_eq!(file.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("line1 line2"));
+    }
+}
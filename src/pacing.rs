@@ -0,0 +1,355 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Checking that captions fit into the bandwidth of broadcast channels.
+//!
+//! CEA-608 captions are sent as one byte pair per video frame and teletext subtitles as a few packets per field,
+//! so the data of a cue takes time to arrive: a pop-on caption has to be loaded completely before it is shown,
+//! and a teletext page has to be complete before it is replaced. Encoders drop the cues whose data doesn't arrive
+//! in time. `check_pacing()` simulates the channel for the timings of the entries and reports these cues, together
+//! with the rows and characters that don't fit into the caption screen.
+//!
+//! ```
+//! use subparse::pacing::{check_pacing, CaptionChannel, PacingIssueKind};
+//! use subparse::timetypes::TimeDelta;
+//! use subparse::SubtitleFormat;
+//!
+//! let srt = "1\n00:00:01,000 --> 00:00:01,100\nHi\n\n2\n00:00:01,100 --> 00:00:03,000\nA much longer second caption\n";
+//! let entries = subparse::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap().get_subtitle_entries().unwrap();
+//! let issues = check_pacing(&entries, CaptionChannel::Cea608);
+//! assert_eq!(issues[0].entry_idx, 1);
+//! assert_eq!(issues[0].kind, PacingIssueKind::Dropped { missed_by: TimeDelta::from_msecs(634) });
+//! ```
+
+use crate::formats::scc::{char_encoding, CharEncoding};
+use crate::music::tag_mask;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::SubtitleEntry;
+
+/// The visible characters of a row and whether an `<i>` or `</i>` comes before each of them.
+type Row = Vec<(char, bool)>;
+
+/// A broadcast channel for captions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionChannel {
+    /// CEA-608 pop-on captions in field 1 of 29.97 fps video (one byte pair per frame).
+    Cea608,
+
+    /// Teletext subtitles in 25 fps video (one packet per line and field).
+    Teletext {
+        /// The number of lines of the vertical blanking interval that carry subtitle packets in every field.
+        lines_per_field: u8,
+    },
+}
+
+impl CaptionChannel {
+    /// The number of byte pairs (CEA-608) or packets (teletext) that are sent per second.
+    pub fn rate(self) -> f64 {
+        match self {
+            CaptionChannel::Cea608 => 30_000.0 / 1001.0,
+            CaptionChannel::Teletext { lines_per_field } => 50.0 * f64::from(lines_per_field),
+        }
+    }
+
+    /// The maximum number of characters of a row.
+    ///
+    /// Teletext rows have 40 columns, but the boxed background of subtitles needs three control characters.
+    pub fn max_columns(self) -> usize {
+        match self {
+            CaptionChannel::Cea608 => 32,
+            CaptionChannel::Teletext { .. } => 37,
+        }
+    }
+
+    /// The maximum number of rows of a cue (teletext subtitles use double height rows).
+    pub fn max_rows(self) -> usize {
+        match self {
+            CaptionChannel::Cea608 => 15,
+            CaptionChannel::Teletext { .. } => 11,
+        }
+    }
+
+    /// Returns the number of byte pairs or packets which are needed to show the rows.
+    fn cue_cost(self, rows: &[Row]) -> usize {
+        match self {
+            // RCL and ENM before and EOC after the rows (all control codes are doubled)
+            CaptionChannel::Cea608 => 4 + rows.iter().map(|row| cea608_row_pairs(row)).sum::<usize>() + 2,
+            // the page header and one packet per row
+            CaptionChannel::Teletext { .. } => 1 + rows.len(),
+        }
+    }
+
+    /// Returns the number of byte pairs (a doubled EDM) or packets (an empty page header) to erase a cue.
+    fn erase_cost(self) -> usize {
+        match self {
+            CaptionChannel::Cea608 => 2,
+            CaptionChannel::Teletext { .. } => 1,
+        }
+    }
+}
+
+/// A problem of a cue in a caption channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacingIssueKind {
+    /// The data of the cue can't be sent in time, so an encoder would drop it.
+    Dropped {
+        /// How much later than needed the data would be complete.
+        missed_by: TimeDelta,
+    },
+
+    /// A row has more characters than the channel allows (encoders cut it off).
+    RowTooLong {
+        /// The row, starting at 0.
+        row: usize,
+
+        /// The number of characters of the row.
+        chars: usize,
+
+        /// See `CaptionChannel::max_columns()`.
+        max: usize,
+    },
+
+    /// The cue has more rows than the channel allows.
+    TooManyRows {
+        /// The number of rows of the cue.
+        rows: usize,
+
+        /// See `CaptionChannel::max_rows()`.
+        max: usize,
+    },
+
+    /// The character can't be encoded in CEA-608 captions.
+    UnsupportedCharacter(char),
+}
+
+/// A problem of an entry in a caption channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacingIssue {
+    /// The position of the entry in `get_subtitle_entries()`.
+    pub entry_idx: usize,
+
+    /// What is wrong.
+    pub kind: PacingIssueKind,
+}
+
+/// Returns the rows of a text (empty rows are left out).
+fn rows(text: &str) -> Vec<Row> {
+    let mut rows = vec![Vec::new()];
+    let mut tag = String::new();
+    let mut style_change = false;
+    for (c, is_tag) in text.chars().zip(tag_mask(text)) {
+        if is_tag {
+            tag.push(c);
+            if c == '>' || c == '}' {
+                style_change |= tag.eq_ignore_ascii_case("<i>") || tag.eq_ignore_ascii_case("</i>");
+                tag.clear();
+            }
+            continue;
+        }
+        match c {
+            '\r' => {}
+            '\n' => {
+                rows.push(Vec::new());
+                style_change = false;
+            }
+            c => {
+                if let Some(row) = rows.last_mut() {
+                    row.push((c, style_change));
+                }
+                style_change = false;
+            }
+        }
+    }
+    rows.retain(|row| !row.is_empty());
+    rows
+}
+
+/// Returns the number of byte pairs of a CEA-608 row: a PAC, the characters and a mid-row code for every
+/// change of italics (control code pairs are doubled and can't start in the middle of a pair).
+fn cea608_row_pairs(row: &[(char, bool)]) -> usize {
+    let mut pairs = 2;
+    let mut bytes = 0usize;
+    let control_code = |pairs: &mut usize, bytes: &mut usize| {
+        *pairs += (*bytes + 1) / 2 + 2;
+        *bytes = 0;
+    };
+    for &(c, style_change) in row {
+        if style_change {
+            control_code(&mut pairs, &mut bytes);
+        }
+        match char_encoding(c) {
+            // unsupported characters are replaced by a standard one
+            Some(CharEncoding::Basic) | None => bytes += 1,
+            Some(CharEncoding::Special) => control_code(&mut pairs, &mut bytes),
+            Some(CharEncoding::Extended) => {
+                bytes += 1;
+                control_code(&mut pairs, &mut bytes);
+            }
+        }
+    }
+    pairs + (bytes + 1) / 2
+}
+
+/// Returns the layout problems of the rows of a cue.
+fn layout_issues(rows: &[Row], channel: CaptionChannel) -> Vec<PacingIssueKind> {
+    let mut issues = Vec::new();
+    if rows.len() > channel.max_rows() {
+        issues.push(PacingIssueKind::TooManyRows {
+            rows: rows.len(),
+            max: channel.max_rows(),
+        });
+    }
+    for (row_idx, row) in rows.iter().enumerate() {
+        if row.len() > channel.max_columns() {
+            issues.push(PacingIssueKind::RowTooLong {
+                row: row_idx,
+                chars: row.len(),
+                max: channel.max_columns(),
+            });
+        }
+        if channel == CaptionChannel::Cea608 {
+            for &(c, _) in row {
+                if char_encoding(c).is_none() {
+                    issues.push(PacingIssueKind::UnsupportedCharacter(c));
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Returns the problems of the entries with text in a caption channel, ordered by entry.
+///
+/// The channel is simulated from `0:00:00` (or the first cue, if it starts earlier). A CEA-608 pop-on caption is
+/// loaded after the previous one is shown and has to be complete when it is shown; a teletext page is sent when
+/// it is shown and has to be complete before it ends (or the next cue starts). Cues that end before the next one
+/// starts are erased at their end, which also takes time of the channel. Dropped cues don't take any time, so the
+/// following cues are checked as the encoder would send them.
+pub fn check_pacing(entries: &[SubtitleEntry], channel: CaptionChannel) -> Vec<PacingIssue> {
+    let mut cues: Vec<(usize, TimeSpan, Vec<Row>)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(entry_idx, entry)| Some((entry_idx, entry.timespan, rows(entry.line.as_ref()?))))
+        .filter(|(_, _, rows)| !rows.is_empty())
+        .collect();
+    cues.sort_by_key(|&(entry_idx, timespan, _)| (timespan.start, entry_idx));
+
+    let mut issues: Vec<PacingIssue> = cues
+        .iter()
+        .flat_map(|(entry_idx, _, rows)| {
+            layout_issues(rows, channel)
+                .into_iter()
+                .map(move |kind| PacingIssue { entry_idx: *entry_idx, kind })
+        })
+        .collect();
+
+    let secs = |t: TimePoint| t.msecs() as f64 / 1000.0;
+    let rate = channel.rate().max(f64::MIN_POSITIVE);
+    let erase_time = channel.erase_cost() as f64 / rate;
+
+    // the time from which the channel is free, the time from which the next pop-on caption can be loaded and
+    // the end of the shown cue, which has to be erased
+    let mut free = cues.first().map_or(0.0, |&(_, timespan, _)| secs(timespan.start).min(0.0));
+    let mut loadable_from = f64::NEG_INFINITY;
+    let mut pending_erase: Option<f64> = None;
+
+    for (pos, (entry_idx, timespan, rows)) in cues.iter().enumerate() {
+        let start = secs(timespan.start);
+        let (earliest, deadline) = match channel {
+            CaptionChannel::Cea608 => (loadable_from, start),
+            CaptionChannel::Teletext { .. } => {
+                let next_start = cues.get(pos + 1).map_or(f64::INFINITY, |&(_, next, _)| secs(next.start));
+                (start, secs(timespan.end).min(next_start))
+            }
+        };
+
+        // a cue that is still shown is replaced instead of erased
+        let erase = pending_erase.filter(|&end| end < start);
+        let mut busy = free;
+        let mut erased = false;
+        if let Some(at) = erase {
+            if at <= busy.max(earliest) {
+                busy = busy.max(at) + erase_time;
+                erased = true;
+            }
+        }
+        let mut finish = busy.max(earliest) + channel.cue_cost(rows) as f64 / rate;
+        if erase.is_some_and(|at| !erased && at < finish) {
+            // the erasure is sent while the cue is loaded
+            finish += erase_time;
+            erased = true;
+        }
+
+        let missed_ms = ((finish - deadline) * 1000.0).round();
+        if missed_ms > 0.0 {
+            issues.push(PacingIssue {
+                entry_idx: *entry_idx,
+                kind: PacingIssueKind::Dropped {
+                    missed_by: TimeDelta::from_msecs(missed_ms as i64),
+                },
+            });
+            if erased && busy > free {
+                free = busy;
+                pending_erase = None;
+            }
+            continue;
+        }
+
+        free = match erase {
+            Some(at) if !erased => finish.max(at) + erase_time,
+            _ => finish,
+        };
+        loadable_from = start;
+        pending_erase = Some(secs(timespan.end));
+    }
+
+    issues.sort_by_key(|issue| issue.entry_idx);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn check_caption_pacing() {
+        let srt = "1\n00:00:01,000 --> 00:00:01,500\nHello\n\n\
+                   2\n00:00:01,600 --> 00:00:03,000\nThis row has thirty-two letters.\n<i>And this one is in italics</i>\n\n\
+                   3\n00:00:05,000 --> 00:00:06,000\n¿Tschüß? Łódź\n\n\
+                   4\n00:00:06,000 --> 00:00:08,000\nThis row is longer than thirty-two letters\n";
+        let entries = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0)
+            .unwrap()
+            .get_subtitle_entries()
+            .unwrap();
+        let kinds = |channel| {
+            check_pacing(&entries, channel)
+                .into_iter()
+                .map(|issue| (issue.entry_idx, issue.kind))
+                .collect::<Vec<_>>()
+        };
+
+        // the second cue is loaded from 1s on: 4 + (2 + 16) + (2 + 2 + 13) + 2 byte pairs and the erasure
+        // of the first cue at 1.5s
+        assert_eq!(
+            kinds(CaptionChannel::Cea608),
+            vec![
+                (
+                    1,
+                    PacingIssueKind::Dropped {
+                        missed_by: TimeDelta::from_msecs(835)
+                    }
+                ),
+                (2, PacingIssueKind::UnsupportedCharacter('Ł')),
+                (2, PacingIssueKind::UnsupportedCharacter('ź')),
+                (3, PacingIssueKind::RowTooLong { row: 0, chars: 42, max: 32 }),
+            ]
+        );
+        assert_eq!(
+            kinds(CaptionChannel::Teletext { lines_per_field: 1 }),
+            vec![(3, PacingIssueKind::RowTooLong { row: 0, chars: 42, max: 37 })]
+        );
+        assert_eq!(rows("<i>a</i>\n\nb"), vec![vec![('a', true)], vec![('b', false)]]);
+    }
+}
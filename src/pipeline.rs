@@ -0,0 +1,724 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Declarative conversion jobs, described by a small YAML file.
+//!
+//! A pipeline reads every input file, applies the operations in order and writes the result to the output file
+//! at the same position:
+//!
+//! ```
+//! use subparse::pipeline::{Operation, Pipeline};
+//!
+//! let pipeline = Pipeline::parse(
+//!     "
+//! fps: 25                            # for frame-based input files like MicroDVD
+//! inputs:
+//!   - episode01.srt
+//!   - episode02.srt
+//! operations:
+//!   - shift: -1.5                     # in seconds
+//!   - fps: {from: 25, to: 23.976}     # the video was sped up for PAL
+//!   - convert: vtt                    # an extension or a name like `MicroDVD`
+//!   - qc                              # collects suspicious timestamps
+//! outputs:
+//!   - out/episode01.vtt
+//!   - out/episode02.vtt
+//! ",
+//! )
+//! .unwrap();
+//! assert_eq!(pipeline.operations.len(), 4);
+//! assert_eq!(pipeline.operations[3], Operation::Qc);
+//! ```
+//!
+//! `Pipeline::run()` executes the jobs and returns a `JobReport` for every file. Only a subset of YAML is
+//! supported: maps, lists (`- item`), flow maps and lists (`{a: 1}`, `[a, b]`), quoted strings and comments.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::conversion::ConversionWarning;
+use crate::errors::Result as SubtitleParserResult;
+use crate::formats::{parse_auto, SubtitleFile, SubtitleFormat};
+use crate::retiming::{Retiming, TimeTransform};
+use crate::timetypes::{TimeDelta, TimeSpan};
+use crate::warnings::Warnings;
+use failure::ResultExt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Errors specific to reading pipeline files.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "invalid YAML in line {}: {}", line_num, msg)]
+        InvalidYaml { line_num: usize, msg: String },
+
+        #[fail(display = "the pipeline has no `{}`", name)]
+        MissingField { name: &'static str },
+
+        #[fail(display = "unknown field `{}` in the pipeline", name)]
+        UnknownField { name: String },
+
+        #[fail(display = "unknown operation `{}`", name)]
+        UnknownOperation { name: String },
+
+        #[fail(display = "invalid value `{}` for `{}`", value, name)]
+        InvalidValue { name: String, value: String },
+
+        #[fail(display = "the pipeline has {} inputs, but {} outputs", inputs, outputs)]
+        OutputCountMismatch { inputs: usize, outputs: usize },
+    }
+}
+
+/// A value of the supported YAML subset.
+#[derive(Debug, Clone, PartialEq)]
+enum Yaml {
+    Scalar(String),
+    List(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+}
+
+impl Yaml {
+    /// Describes the value for error messages.
+    fn describe(&self) -> String {
+        match *self {
+            Yaml::Scalar(ref text) => text.clone(),
+            Yaml::List(_) => "a list".to_string(),
+            Yaml::Map(_) => "a map".to_string(),
+        }
+    }
+}
+
+/// A non-empty line of a YAML file without its comment.
+#[derive(Debug)]
+struct YamlLine {
+    /// The line number (starting at 1).
+    line_num: usize,
+    indent: usize,
+    text: String,
+}
+
+fn yaml_error(line_num: usize, msg: &str) -> Error {
+    Error::from(InvalidYaml {
+        line_num,
+        msg: msg.to_string(),
+    })
+}
+
+/// Removes a comment (`#` at the start or after whitespace, outside of quotes) from a line.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                previous = c;
+                continue;
+            }
+            (Some(q), _) if c == q && !escaped => quote = None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '#') if previous.is_whitespace() => return line.get(..i).unwrap_or(line),
+            _ => {}
+        }
+        escaped = false;
+        previous = c;
+    }
+    line
+}
+
+/// Splits a YAML text into its non-empty lines.
+fn yaml_lines(s: &str) -> Result<Vec<YamlLine>> {
+    let mut result = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        let text = strip_comment(line.trim_start_matches('\u{feff}')).trim_end();
+        let content = text.trim_start_matches(' ');
+        if content.is_empty() || content == "---" {
+            continue;
+        }
+        if content.starts_with('\t') {
+            return Err(yaml_error(i + 1, "tabs can't be used for indentation"));
+        }
+        result.push(YamlLine {
+            line_num: i + 1,
+            indent: text.len() - content.len(),
+            text: content.to_string(),
+        });
+    }
+    Ok(result)
+}
+
+fn is_list_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Parses a quoted string at the start of `s` and returns the unquoted string and the rest.
+fn parse_quoted(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.char_indices().peekable();
+    let quote = chars.next().map(|(_, c)| c).filter(|&c| c == '"' || c == '\'')?;
+    let mut result = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            // `''` is a single quote in single-quoted strings
+            '\'' if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                chars.next();
+                result.push('\'');
+            }
+            c if c == quote => return Some((result, s.get(i + 1..)?)),
+            '\\' if quote == '"' => match chars.next()?.1 {
+                'n' => result.push('\n'),
+                't' => result.push('\t'),
+                other => result.push(other),
+            },
+            c => result.push(c),
+        }
+    }
+    None
+}
+
+/// Splits a line like `key: value` into the key and the (trimmed) value.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    let (key, rest) = if text.starts_with('"') || text.starts_with('\'') {
+        parse_quoted(text)?
+    } else if text.starts_with('{') || text.starts_with('[') {
+        return None;
+    } else {
+        let end = text.find(": ").or_else(|| text.strip_suffix(':').map(str::len))?;
+        (text.get(..end)?.trim().to_string(), text.get(end..)?)
+    };
+    let value = rest.trim_start().strip_prefix(':')?;
+    if key.is_empty() || !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// Parses a scalar, a flow list like `[a, b]` or a flow map like `{a: 1}` at the start of `s` and returns the rest.
+fn parse_flow_value(s: &str, in_flow: bool, line_num: usize) -> Result<(Yaml, &str)> {
+    let s = s.trim_start();
+    if s.starts_with('"') || s.starts_with('\'') {
+        let (text, rest) = parse_quoted(s).ok_or_else(|| yaml_error(line_num, "unterminated quoted string"))?;
+        return Ok((Yaml::Scalar(text), rest));
+    }
+    let (is_map, mut rest) = match (s.strip_prefix('{'), s.strip_prefix('[')) {
+        (Some(rest), _) => (true, rest),
+        (_, Some(rest)) => (false, rest),
+        (None, None) => {
+            let end = if in_flow { s.find([',', ']', '}']).unwrap_or(s.len()) } else { s.len() };
+            let (text, rest) = s.split_at(end);
+            return Ok((Yaml::Scalar(text.trim().to_string()), rest));
+        }
+    };
+
+    let close = if is_map { '}' } else { ']' };
+    let mut items = Vec::new();
+    let mut entries = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix(close) {
+            let value = if is_map { Yaml::Map(entries) } else { Yaml::List(items) };
+            return Ok((value, after));
+        }
+        if is_map {
+            let (key, after) = match parse_quoted(rest) {
+                Some(quoted) => quoted,
+                None => {
+                    let end = rest.find([':', ',', '}']).unwrap_or(rest.len());
+                    let (key, after) = rest.split_at(end);
+                    (key.trim().to_string(), after)
+                }
+            };
+            let after = after
+                .trim_start()
+                .strip_prefix(':')
+                .ok_or_else(|| yaml_error(line_num, "expected `key: value` in the flow map"))?;
+            let (value, after) = parse_flow_value(after, true, line_num)?;
+            entries.push((key, value));
+            rest = after;
+        } else {
+            let (value, after) = parse_flow_value(rest, true, line_num)?;
+            items.push(value);
+            rest = after;
+        }
+        rest = rest.trim_start();
+        match rest.strip_prefix(',') {
+            Some(after) => rest = after,
+            None if rest.starts_with(close) => {}
+            None => return Err(yaml_error(line_num, &format!("expected `,` or `{}`", close))),
+        }
+    }
+}
+
+/// Parses the value after a `key:` or `- ` (the rest of the line must be empty).
+fn parse_inline_value(s: &str, line_num: usize) -> Result<Yaml> {
+    let (value, rest) = parse_flow_value(s, false, line_num)?;
+    if !rest.trim().is_empty() {
+        return Err(yaml_error(line_num, &format!("unexpected `{}`", rest.trim())));
+    }
+    Ok(value)
+}
+
+/// Parses the block structure of YAML lines.
+struct YamlParser {
+    lines: Vec<YamlLine>,
+    pos: usize,
+}
+
+impl YamlParser {
+    fn parse(s: &str) -> Result<Yaml> {
+        let mut parser = YamlParser {
+            lines: yaml_lines(s)?,
+            pos: 0,
+        };
+        let indent = match parser.lines.first() {
+            Some(line) => line.indent,
+            None => return Ok(Yaml::Map(Vec::new())),
+        };
+        let result = parser.parse_block(indent)?;
+        match parser.lines.get(parser.pos) {
+            Some(line) => Err(yaml_error(line.line_num, "unexpected indentation")),
+            None => Ok(result),
+        }
+    }
+
+    fn parse_block(&mut self, indent: usize) -> Result<Yaml> {
+        match self.lines.get(self.pos) {
+            Some(line) if is_list_item(&line.text) => self.parse_list(indent),
+            _ => self.parse_map(indent),
+        }
+    }
+
+    /// Parses the value of a `key:` or `- ` which is written in the following lines.
+    fn parse_nested(&mut self, indent: usize, allow_list: bool) -> Result<Yaml> {
+        match self.lines.get(self.pos) {
+            Some(next) if next.indent > indent => self.parse_block(next.indent),
+            // a list can have the indentation of its key
+            Some(next) if allow_list && next.indent == indent && is_list_item(&next.text) => self.parse_list(indent),
+            _ => Ok(Yaml::Scalar(String::new())),
+        }
+    }
+
+    fn parse_list(&mut self, indent: usize) -> Result<Yaml> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get_mut(self.pos) {
+            if line.indent != indent || !is_list_item(&line.text) {
+                break;
+            }
+            let line_num = line.line_num;
+            let item = line.text.get(1..).unwrap_or("").trim_start().to_string();
+            if item.is_empty() {
+                self.pos += 1;
+                items.push(self.parse_nested(indent, false)?);
+            } else if split_key(&item).is_some() {
+                // a map which starts in the line of the dash
+                line.indent += line.text.len() - item.len();
+                line.text = item;
+                let map_indent = line.indent;
+                items.push(self.parse_map(map_indent)?);
+            } else {
+                self.pos += 1;
+                items.push(parse_inline_value(&item, line_num)?);
+            }
+        }
+        Ok(Yaml::List(items))
+    }
+
+    fn parse_map(&mut self, indent: usize) -> Result<Yaml> {
+        let mut entries: Vec<(String, Yaml)> = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || is_list_item(&line.text) {
+                break;
+            }
+            let line_num = line.line_num;
+            let (key, rest) = split_key(&line.text).ok_or_else(|| yaml_error(line_num, "expected `key: value`"))?;
+            let rest = rest.to_string();
+            self.pos += 1;
+            let value = if rest.is_empty() {
+                self.parse_nested(indent, true)?
+            } else {
+                parse_inline_value(&rest, line_num)?
+            };
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(yaml_error(line_num, &format!("duplicate key `{}`", key)));
+            }
+            entries.push((key, value));
+        }
+        Ok(Yaml::Map(entries))
+    }
+}
+
+/// A step of a pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    /// Moves all subtitles by the delta (`shift: 1.5`, in seconds).
+    Shift(TimeDelta),
+
+    /// Changes the timing from a video with the frame rate `from` to the same video with the frame rate `to`
+    /// (`fps: {from: 25, to: 23.976}`).
+    ChangeFps {
+        /// The frame rate of the video the subtitles were made for.
+        from: f64,
+
+        /// The frame rate of the target video.
+        to: f64,
+    },
+
+    /// Converts the file to another format (`convert: vtt`). The text and the timing are kept; see
+    /// `JobReport::conversion` for what is lost.
+    Convert(SubtitleFormat),
+
+    /// Checks the timing of the subtitles (`qc`), the issues are collected in `JobReport::qc`.
+    Qc,
+}
+
+/// The result of a job of a pipeline.
+#[derive(Debug, Clone)]
+pub struct JobReport {
+    /// The input file.
+    pub input: PathBuf,
+
+    /// The written output file.
+    pub output: PathBuf,
+
+    /// The timing issues found by `Operation::Qc`.
+    pub qc: Warnings,
+
+    /// The features which were lost by `Operation::Convert`.
+    pub conversion: Vec<ConversionWarning>,
+}
+
+/// A list of conversion jobs with the same operations (see the module documentation).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    /// The frame rate for frame-based input files (`fps: 25`, this is also the default).
+    pub fps: f64,
+
+    /// The input files.
+    pub inputs: Vec<PathBuf>,
+
+    /// The operations which are applied to every input file.
+    pub operations: Vec<Operation>,
+
+    /// The output files, one for every input file.
+    pub outputs: Vec<PathBuf>,
+}
+
+/// Parses a number which has to be positive, like a frame rate.
+fn positive_number(value: &Yaml, name: &str) -> Result<f64> {
+    match *value {
+        Yaml::Scalar(ref text) => text.parse::<f64>().ok().filter(|n| n.is_finite() && *n > 0.0),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        Error::from(InvalidValue {
+            name: name.to_string(),
+            value: value.describe(),
+        })
+    })
+}
+
+/// Parses a path or a list of paths.
+fn paths(value: Yaml, name: &str) -> Result<Vec<PathBuf>> {
+    let items = match value {
+        Yaml::List(items) => items,
+        Yaml::Scalar(ref text) if text.is_empty() => Vec::new(),
+        scalar @ Yaml::Scalar(_) => vec![scalar],
+        map @ Yaml::Map(_) => vec![map],
+    };
+    items
+        .into_iter()
+        .map(|item| match item {
+            Yaml::Scalar(ref text) if !text.is_empty() => Ok(PathBuf::from(text)),
+            other => Err(Error::from(InvalidValue {
+                name: name.to_string(),
+                value: other.describe(),
+            })),
+        })
+        .collect()
+}
+
+/// Finds a format by its name (like `MicroDVD`) or an extension which only one format uses (like `vtt`).
+fn find_format(name: &str) -> Option<SubtitleFormat> {
+    let name = name.trim_start_matches('.').to_lowercase();
+    let by_name = SubtitleFormat::ALL.iter().find(|format| {
+        let full_name = format.get_name();
+        let short_name = full_name.split_once('(').and_then(|(_, rest)| rest.strip_suffix(')'));
+        short_name.is_some_and(|short_name| short_name.to_lowercase() == name)
+    });
+    let mut by_extension = SubtitleFormat::ALL.iter().filter(|format| format.extensions().contains(&name.as_str()));
+    match (by_name, by_extension.next(), by_extension.next()) {
+        (Some(&format), _, _) | (None, Some(&format), None) => Some(format),
+        _ => None,
+    }
+}
+
+fn parse_operation(value: Yaml) -> Result<Operation> {
+    let (name, argument) = match value {
+        Yaml::Scalar(name) => (name, None),
+        Yaml::Map(mut entries) if entries.len() == 1 => match entries.pop() {
+            Some((name, argument)) => (name, Some(argument)),
+            None => return Err(UnknownOperation { name: String::new() }.into()),
+        },
+        other => {
+            return Err(InvalidValue {
+                name: "operations".to_string(),
+                value: other.describe(),
+            }
+            .into());
+        }
+    };
+    let invalid = |argument: &Option<Yaml>| {
+        Error::from(InvalidValue {
+            name: name.clone(),
+            value: argument.as_ref().map(Yaml::describe).unwrap_or_default(),
+        })
+    };
+    match (name.as_str(), &argument) {
+        ("shift", Some(Yaml::Scalar(secs))) => match secs.parse::<f64>() {
+            Ok(secs) if secs.is_finite() => Ok(Operation::Shift(TimeDelta::from_msecs((secs * 1000.0).round() as i64))),
+            _ => Err(invalid(&argument)),
+        },
+        ("fps", Some(Yaml::Map(entries))) => {
+            let field = |key: &str| entries.iter().find(|(k, _)| k == key).map(|(_, value)| positive_number(value, key));
+            match (field("from"), field("to"), entries.len()) {
+                (Some(from), Some(to), 2) => Ok(Operation::ChangeFps { from: from?, to: to? }),
+                _ => Err(invalid(&argument)),
+            }
+        }
+        ("convert", Some(Yaml::Scalar(format))) => find_format(format).map(Operation::Convert).ok_or_else(|| invalid(&argument)),
+        ("qc", None) => Ok(Operation::Qc),
+        ("shift", _) | ("fps", _) | ("convert", _) | ("qc", _) => Err(invalid(&argument)),
+        _ => Err(UnknownOperation { name: name.clone() }.into()),
+    }
+}
+
+impl Pipeline {
+    /// Parses the YAML description of a pipeline (see the module documentation).
+    pub fn parse(s: &str) -> SubtitleParserResult<Pipeline> {
+        Ok(Self::parse_inner(s).with_context(|_| crate::ErrorKind::ParsingError)?)
+    }
+
+    /// Reads a pipeline file; relative paths in the file are relative to its directory.
+    pub fn load(path: &Path) -> SubtitleParserResult<Pipeline> {
+        let text = fs::read_to_string(path).context(crate::ErrorKind::ReadingFileError)?;
+        let mut pipeline = Self::parse(&text)?;
+        if let Some(dir) = path.parent() {
+            for file in pipeline.inputs.iter_mut().chain(pipeline.outputs.iter_mut()) {
+                *file = dir.join(&file);
+            }
+        }
+        Ok(pipeline)
+    }
+
+    fn parse_inner(s: &str) -> Result<Pipeline> {
+        let entries = match YamlParser::parse(s)? {
+            Yaml::Map(entries) => entries,
+            _ => return Err(yaml_error(1, "the pipeline has to be a map")),
+        };
+
+        let mut pipeline = Pipeline {
+            fps: 25.0,
+            inputs: Vec::new(),
+            operations: Vec::new(),
+            outputs: Vec::new(),
+        };
+        for (key, value) in entries {
+            match key.as_str() {
+                "fps" => pipeline.fps = positive_number(&value, "fps")?,
+                "inputs" => pipeline.inputs = paths(value, "inputs")?,
+                "outputs" => pipeline.outputs = paths(value, "outputs")?,
+                "operations" => {
+                    pipeline.operations = match value {
+                        Yaml::List(items) => items.into_iter().map(parse_operation).collect::<Result<_>>()?,
+                        Yaml::Scalar(ref text) if text.is_empty() => Vec::new(),
+                        other => vec![parse_operation(other)?],
+                    }
+                }
+                _ => return Err(UnknownField { name: key }.into()),
+            }
+        }
+
+        if pipeline.inputs.is_empty() {
+            return Err(MissingField { name: "inputs" }.into());
+        }
+        if pipeline.inputs.len() != pipeline.outputs.len() {
+            return Err(OutputCountMismatch {
+                inputs: pipeline.inputs.len(),
+                outputs: pipeline.outputs.len(),
+            }
+            .into());
+        }
+        Ok(pipeline)
+    }
+
+    /// Runs the jobs in order and stops at the first error. Output directories are created if necessary.
+    pub fn run(&self) -> SubtitleParserResult<Vec<JobReport>> {
+        self.inputs
+            .iter()
+            .zip(&self.outputs)
+            .map(|(input, output)| self.run_job(input, output))
+            .collect()
+    }
+
+    fn run_job(&self, input: &Path, output: &Path) -> SubtitleParserResult<JobReport> {
+        let content = fs::read(input).context(crate::ErrorKind::ReadingFileError)?;
+        let mut file = parse_auto(input.extension(), &content, None, self.fps)?;
+        let mut report = JobReport {
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            qc: Warnings::new(),
+            conversion: Vec::new(),
+        };
+        for operation in &self.operations {
+            self.apply(operation, &mut file, &mut report)?;
+        }
+
+        if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).context(crate::ErrorKind::WritingFileError)?;
+        }
+        file.save_to(output)?;
+        Ok(report)
+    }
+
+    fn apply(&self, operation: &Operation, file: &mut SubtitleFile, report: &mut JobReport) -> SubtitleParserResult<()> {
+        let entries = file.get_subtitle_entries()?;
+        match *operation {
+            Operation::Shift(delta) => TimeTransform::shift(delta).retime_file(file),
+            Operation::ChangeFps { from, to } => TimeTransform::change_fps(from, to).retime_file(file),
+            Operation::Convert(format) if file.format() == Some(format) => Ok(()),
+            Operation::Convert(format) => {
+                report.conversion.extend(file.conversion_warnings(format)?);
+                let timespans: Vec<TimeSpan> = entries.iter().map(|entry| entry.timespan).collect();
+                let lines: Option<Vec<String>> = entries.into_iter().map(|entry| entry.line).collect();
+                let lines = lines.ok_or(crate::ErrorKind::TextFormatOnly)?;
+                let v = timespans.into_iter().zip(lines).collect();
+                *file = format.create_file(v, self.fps)?;
+                Ok(())
+            }
+            Operation::Qc => {
+                report.qc.check_timestamps(&entries);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::parse_error;
+    use crate::timetypes::TimePoint;
+
+    const JOBS: &str =
+        "inputs: [in.srt]   # one job\noperations:\n- shift: '-1'\n- \"fps\": {from: 25, to: 50}\n-   convert: vtt\n- qc\noutputs:\n  - out/in.vtt\n";
+
+    /// Returns a new directory with the pipeline file `jobs.yaml` and its input `in.srt`.
+    fn job_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("subparse-pipeline-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("in.srt"),
+            "1\n00:00:02,000 --> 00:00:04,000\n<i>Hello</i>\n\n2\n00:00:06,000 --> 00:00:05,000\nWorld\n",
+        )
+        .unwrap();
+        fs::write(dir.join("jobs.yaml"), JOBS).unwrap();
+        dir
+    }
+
+    #[test]
+    fn pipeline_parse() {
+        let pipeline = Pipeline::parse(JOBS).unwrap();
+        assert_eq!(pipeline.fps, 25.0);
+        assert_eq!(pipeline.inputs, vec![PathBuf::from("in.srt")]);
+        assert_eq!(pipeline.outputs, vec![PathBuf::from("out/in.vtt")]);
+        assert_eq!(
+            pipeline.operations,
+            vec![
+                Operation::Shift(TimeDelta::from_msecs(-1000)),
+                Operation::ChangeFps { from: 25.0, to: 50.0 },
+                Operation::Convert(SubtitleFormat::WebVtt),
+                Operation::Qc,
+            ]
+        );
+    }
+
+    #[test]
+    fn pipeline_load_relative_paths() {
+        let dir = job_dir("load");
+        let pipeline = Pipeline::load(&dir.join("jobs.yaml")).unwrap();
+        assert_eq!(pipeline.inputs, vec![dir.join("in.srt")]);
+        assert_eq!(pipeline.outputs, vec![dir.join("out/in.vtt")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pipeline_run() {
+        let dir = job_dir("run");
+        let reports = Pipeline::load(&dir.join("jobs.yaml")).unwrap().run().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].qc.len(), 1);
+        assert_eq!(reports[0].conversion.len(), 0);
+        assert_eq!(
+            fs::read_to_string(dir.join("out/in.vtt")).unwrap(),
+            "WEBVTT\n\n00:00:00.500 --> 00:00:01.500\n<i>Hello</i>\n\n00:00:02.500 --> 00:00:02.000\nWorld\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pipeline_run_missing_input() {
+        let pipeline = Pipeline::parse("inputs: missing.srt\noutputs: out.srt\n").unwrap();
+        assert_eq!(pipeline.run().unwrap_err().kind(), crate::ErrorKind::ReadingFileError);
+    }
+
+    #[test]
+    fn create_file_with_fps() {
+        let span = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        let mdvd = SubtitleFormat::MicroDVD.create_file(vec![(span, "a\nb".to_string())], 25.0).unwrap();
+        assert_eq!(String::from_utf8(mdvd.to_data().unwrap()).unwrap(), "{25}{50}a|b");
+    }
+
+    #[test]
+    fn pipeline_find_format() {
+        assert_eq!(find_format("microdvd"), Some(SubtitleFormat::MicroDVD));
+        assert_eq!(find_format(".ASS"), Some(SubtitleFormat::SubStationAlpha));
+        // `.sub` is ambiguous
+        assert_eq!(find_format("sub"), None);
+    }
+
+    #[test]
+    fn pipeline_parse_errors() {
+        assert_eq!(
+            parse_error(Pipeline::parse_inner, "inputs: a.srt\n").kind(),
+            &OutputCountMismatch { inputs: 1, outputs: 0 }
+        );
+        assert_eq!(
+            parse_error(Pipeline::parse_inner, "outputs: b.srt\n").kind(),
+            &MissingField { name: "inputs" }
+        );
+        assert_eq!(
+            parse_error(Pipeline::parse_inner, "inputs: a.srt\noutputs: b.srt\nformat: srt\n").kind(),
+            &UnknownField { name: "format".to_string() }
+        );
+        assert_eq!(
+            parse_error(Pipeline::parse_inner, "inputs: a.srt\noutputs: b.srt\noperations: [trim]\n").kind(),
+            &UnknownOperation { name: "trim".to_string() }
+        );
+    }
+
+    #[test]
+    fn pipeline_yaml_errors() {
+        let message = |s: &str| parse_error(Pipeline::parse_inner, s).kind().to_string();
+        assert_eq!(
+            message("inputs:\n  - a.srt\n    - b.srt\n"),
+            "invalid YAML in line 3: unexpected indentation"
+        );
+        assert_eq!(message("fps: {from: 1\n"), "invalid YAML in line 1: expected `,` or `}`");
+    }
+}
@@ -0,0 +1,388 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A format-independent description of where a subtitle is placed on the screen, and the conversions from and
+//! to the positioning systems of the different formats.
+//!
+//! | format       | alignment                                   | offsets                         |
+//! |--------------|---------------------------------------------|---------------------------------|
+//! | `.ssa`/`.ass`| `\an1`-`\an9` (numpad layout)               | `MarginL`/`MarginR`/`MarginV`, `\pos(x,y)` in script pixels |
+//! | `.srt`       | `{\an1}`-`{\an9}` at the start of the text  | -                               |
+//! | WebVTT       | `align:` and `line-align:`                  | `line:` and `position:` in percent |
+//! | TTML         | `tts:textAlign` and `tts:displayAlign`      | `tts:origin` and `tts:extent` of the region in percent |
+//!
+//! Offsets are stored as fractions of the video size, so they survive changes of the script resolution.
+//!
+//! If a format can't represent everything, the information is dropped in this order: first the explicit
+//! position, then the margins, then the horizontal alignment. The vertical alignment (top/middle/bottom) is
+//! kept wherever possible, because it's usually there to avoid covering on-screen text. Missing information
+//! falls back to the default of every format: bottom center without margins.
+
+use crate::formats::srt::SrtAssTags;
+
+/// The horizontal alignment of a subtitle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    /// Left aligned.
+    Left,
+
+    /// Centered.
+    Center,
+
+    /// Right aligned.
+    Right,
+}
+
+/// The vertical alignment of a subtitle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlign {
+    /// At the bottom of the screen (the default of all formats).
+    Bottom,
+
+    /// Vertically centered.
+    Middle,
+
+    /// At the top of the screen.
+    Top,
+}
+
+/// Where a subtitle is placed on the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// The horizontal alignment.
+    pub horizontal: HorizontalAlign,
+
+    /// The vertical alignment.
+    pub vertical: VerticalAlign,
+
+    /// Distance to the left/right/top-or-bottom border as fraction of the video size.
+    pub margins: Option<(f64, f64, f64)>,
+
+    /// The anchor point of the subtitle as fraction of the video size (overrides the margins).
+    pub position: Option<(f64, f64)>,
+}
+
+impl Default for Placement {
+    fn default() -> Placement {
+        Placement {
+            horizontal: HorizontalAlign::Center,
+            vertical: VerticalAlign::Bottom,
+            margins: None,
+            position: None,
+        }
+    }
+}
+
+/// Formats a fraction as percentage without unnecessary decimals.
+fn percent(fraction: f64) -> String {
+    let p = (fraction * 100.0 * 100.0).round() / 100.0;
+    format!("{}%", p)
+}
+
+fn parse_percent(s: &str) -> Option<f64> {
+    s.trim().strip_suffix('%')?.trim().parse::<f64>().ok().map(|p| p / 100.0)
+}
+
+impl Placement {
+    /// Creates a placement from an alignment in numpad notation (as in `\an1` to `\an9`).
+    pub fn from_an(an: u8) -> Option<Placement> {
+        let horizontal = match an {
+            1 | 4 | 7 => HorizontalAlign::Left,
+            2 | 5 | 8 => HorizontalAlign::Center,
+            3 | 6 | 9 => HorizontalAlign::Right,
+            _ => return None,
+        };
+        let vertical = match an {
+            1..=3 => VerticalAlign::Bottom,
+            4..=6 => VerticalAlign::Middle,
+            _ => VerticalAlign::Top,
+        };
+        Some(Placement {
+            horizontal,
+            vertical,
+            ..Placement::default()
+        })
+    }
+
+    /// Returns the alignment in numpad notation (as in `\an1` to `\an9`).
+    pub fn to_an(&self) -> u8 {
+        let row = match self.vertical {
+            VerticalAlign::Bottom => 0,
+            VerticalAlign::Middle => 3,
+            VerticalAlign::Top => 6,
+        };
+        let column = match self.horizontal {
+            HorizontalAlign::Left => 1,
+            HorizontalAlign::Center => 2,
+            HorizontalAlign::Right => 3,
+        };
+        row + column
+    }
+
+    /// Creates a placement from a legacy SubStation Alpha alignment (`\a1` to `\a11` and the `Alignment` of
+    /// `[V4 Styles]`): `1`-`3` are at the bottom, `+4` is at the top and `+8` is in the middle.
+    pub fn from_legacy_alignment(a: u8) -> Option<Placement> {
+        match a {
+            1..=3 => Placement::from_an(a),
+            5..=7 => Placement::from_an(a + 2),
+            9..=11 => Placement::from_an(a - 5),
+            _ => None,
+        }
+    }
+
+    /// Returns the legacy SubStation Alpha alignment (see `from_legacy_alignment()`).
+    pub fn to_legacy_alignment(&self) -> u8 {
+        let row = match self.vertical {
+            VerticalAlign::Bottom => 0,
+            VerticalAlign::Top => 4,
+            VerticalAlign::Middle => 8,
+        };
+        let column = match self.horizontal {
+            HorizontalAlign::Left => 1,
+            HorizontalAlign::Center => 2,
+            HorizontalAlign::Right => 3,
+        };
+        row + column
+    }
+
+    /// Creates a placement from the alignment, the margins and an optional `\pos(x,y)` of a `.ass` event
+    /// (all in pixels of the script resolution `play_res`).
+    pub fn from_ass(an: u8, margins: (i32, i32, i32), pos: Option<(f64, f64)>, play_res: (u32, u32)) -> Placement {
+        let (w, h) = (f64::from(play_res.0.max(1)), f64::from(play_res.1.max(1)));
+        Placement {
+            margins: Some((f64::from(margins.0) / w, f64::from(margins.1) / w, f64::from(margins.2) / h)),
+            position: pos.map(|(x, y)| (x / w, y / h)),
+            ..Placement::from_an(an).unwrap_or_default()
+        }
+    }
+
+    /// Returns the margins (`MarginL`, `MarginR`, `MarginV`) and the `\pos(x,y)` for a `.ass` event in pixels of `play_res`.
+    pub fn to_ass(&self, play_res: (u32, u32)) -> ((i32, i32, i32), Option<(i32, i32)>) {
+        let (w, h) = (f64::from(play_res.0), f64::from(play_res.1));
+        let margins = self.margins.map_or((0, 0, 0), |(l, r, v)| {
+            ((l * w).round() as i32, (r * w).round() as i32, (v * h).round() as i32)
+        });
+        let pos = self.position.map(|(x, y)| ((x * w).round() as i32, (y * h).round() as i32));
+        (margins, pos)
+    }
+
+    /// Creates a placement from the override tags at the start of a `.srt` text (like `{\an8}`).
+    pub fn from_srt_tags(tags: &SrtAssTags) -> Placement {
+        tags.alignment.and_then(Placement::from_an).unwrap_or_default()
+    }
+
+    /// Returns the tag (like `{\an8}`) that has to be put in front of a `.srt` text, or `""` for the default placement.
+    ///
+    /// `.srt` has no margins, so only the alignment is kept.
+    pub fn to_srt_prefix(&self) -> String {
+        match self.to_an() {
+            2 => String::new(),
+            an => format!("{{\\an{}}}", an),
+        }
+    }
+
+    /// Creates a placement from WebVTT cue settings like `"line:10% align:left position:5%"`.
+    ///
+    /// Line numbers (`line:-1` instead of percentages) only decide whether the cue is at the top or the bottom.
+    pub fn from_vtt_settings(settings: &str) -> Placement {
+        let mut result = Placement::default();
+        let (mut line, mut line_align, mut position) = (None, "start", None);
+        for (key, value) in settings.split_whitespace().filter_map(|s| s.split_once(':')) {
+            match key {
+                "align" => {
+                    result.horizontal = match value {
+                        "left" | "start" => HorizontalAlign::Left,
+                        "right" | "end" => HorizontalAlign::Right,
+                        _ => HorizontalAlign::Center,
+                    }
+                }
+                "line" => {
+                    let value = value.split(',').next().unwrap_or("");
+                    line = parse_percent(value).or_else(|| match value.trim().parse::<i64>() {
+                        Ok(n) if n < 0 => Some(1.0),
+                        Ok(_) => Some(0.0),
+                        Err(_) => None,
+                    });
+                    if let Some(align) = value.split(',').nth(1) {
+                        line_align = align;
+                    }
+                }
+                "line-align" => line_align = value,
+                "position" => position = parse_percent(value.split(',').next().unwrap_or("")),
+                _ => {}
+            }
+        }
+
+        if let Some(line) = line {
+            let (vertical, margin_v) = match line_align {
+                "center" | "middle" => (VerticalAlign::Middle, 0.0),
+                "end" => (VerticalAlign::Bottom, 1.0 - line),
+                _ if line >= 0.5 => (VerticalAlign::Bottom, (1.0 - line).max(0.0)),
+                _ => (VerticalAlign::Top, line),
+            };
+            result.vertical = vertical;
+            let (l, r) = match (result.horizontal, position) {
+                (HorizontalAlign::Left, Some(p)) => (p, 0.0),
+                (HorizontalAlign::Right, Some(p)) => (0.0, 1.0 - p),
+                _ => (0.0, 0.0),
+            };
+            result.margins = Some((l, r, margin_v));
+        }
+        result
+    }
+
+    /// Returns the WebVTT cue settings for this placement (the empty string for the default placement).
+    ///
+    /// WebVTT has no explicit anchor points, so `position` is converted into percentages of `line:` and `position:`.
+    pub fn to_vtt_settings(&self) -> String {
+        let mut settings = Vec::new();
+        let (margin_l, margin_r, margin_v) = self.margins.unwrap_or((0.0, 0.0, 0.0));
+
+        match (self.position, self.vertical) {
+            (Some((_, y)), _) => settings.push(format!("line:{}", percent(y))),
+            (None, VerticalAlign::Top) => settings.push(format!("line:{}", percent(margin_v))),
+            (None, VerticalAlign::Middle) => settings.push("line:50%".to_string()),
+            (None, VerticalAlign::Bottom) if margin_v > 0.0 => settings.push(format!("line:{}", percent(1.0 - margin_v))),
+            (None, VerticalAlign::Bottom) => {}
+        }
+        match (self.position.is_some(), self.vertical) {
+            (false, VerticalAlign::Top) | (false, VerticalAlign::Bottom) if settings.is_empty() => {}
+            (_, VerticalAlign::Top) => settings.push("line-align:start".to_string()),
+            (_, VerticalAlign::Middle) => settings.push("line-align:center".to_string()),
+            (_, VerticalAlign::Bottom) => settings.push("line-align:end".to_string()),
+        }
+
+        match self.horizontal {
+            HorizontalAlign::Left => settings.push("align:left".to_string()),
+            HorizontalAlign::Center => {}
+            HorizontalAlign::Right => settings.push("align:right".to_string()),
+        }
+        let position = match (self.position, self.horizontal) {
+            (Some((x, _)), _) => Some(x),
+            (None, HorizontalAlign::Left) if margin_l > 0.0 => Some(margin_l),
+            (None, HorizontalAlign::Right) if margin_r > 0.0 => Some(1.0 - margin_r),
+            _ => None,
+        };
+        if let Some(position) = position {
+            settings.push(format!("position:{}", percent(position)));
+        }
+
+        settings.join(" ")
+    }
+
+    /// Creates a placement from the styling attributes of a TTML region.
+    pub fn from_ttml_region(region: &TtmlRegion) -> Placement {
+        let horizontal = match region.text_align.as_str() {
+            "left" | "start" => HorizontalAlign::Left,
+            "right" | "end" => HorizontalAlign::Right,
+            _ => HorizontalAlign::Center,
+        };
+        let vertical = match region.display_align.as_str() {
+            "before" => VerticalAlign::Top,
+            "center" => VerticalAlign::Middle,
+            _ => VerticalAlign::Bottom,
+        };
+
+        let parse_pair = |s: &str| {
+            let mut iter = s.split_whitespace().map(parse_percent);
+            match (iter.next(), iter.next()) {
+                (Some(Some(a)), Some(Some(b))) => Some((a, b)),
+                _ => None,
+            }
+        };
+        let margins = match (parse_pair(&region.origin), parse_pair(&region.extent)) {
+            (Some((x, y)), Some((w, h))) => {
+                let margin_v = match vertical {
+                    VerticalAlign::Top => y,
+                    VerticalAlign::Middle => 0.0,
+                    VerticalAlign::Bottom => 1.0 - (y + h),
+                };
+                Some((x, (1.0 - (x + w)).max(0.0), margin_v.max(0.0)))
+            }
+            _ => None,
+        };
+
+        Placement {
+            horizontal,
+            vertical,
+            margins,
+            position: None,
+        }
+    }
+
+    /// Returns a TTML region that places the text like this placement.
+    ///
+    /// The region covers the whole video minus the margins, and the text is aligned within it. TTML regions
+    /// have no anchor points, so an explicit `position` is dropped.
+    pub fn to_ttml_region(&self) -> TtmlRegion {
+        let (l, r, v) = self.margins.unwrap_or((0.0, 0.0, 0.0));
+        let (top, bottom) = match self.vertical {
+            VerticalAlign::Top => (v, 0.0),
+            VerticalAlign::Middle => (0.0, 0.0),
+            VerticalAlign::Bottom => (0.0, v),
+        };
+        TtmlRegion {
+            origin: format!("{} {}", percent(l), percent(top)),
+            extent: format!("{} {}", percent((1.0 - l - r).max(0.0)), percent((1.0 - top - bottom).max(0.0))),
+            text_align: match self.horizontal {
+                HorizontalAlign::Left => "left",
+                HorizontalAlign::Center => "center",
+                HorizontalAlign::Right => "right",
+            }
+            .to_string(),
+            display_align: match self.vertical {
+                VerticalAlign::Top => "before",
+                VerticalAlign::Middle => "center",
+                VerticalAlign::Bottom => "after",
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// The positioning attributes of a TTML `<region>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtmlRegion {
+    /// `tts:origin` like `"10% 5%"`.
+    pub origin: String,
+
+    /// `tts:extent` like `"80% 90%"`.
+    pub extent: String,
+
+    /// `tts:textAlign` (`left`, `center`, `right`, `start` or `end`).
+    pub text_align: String,
+
+    /// `tts:displayAlign` (`before`, `center` or `after`).
+    pub display_align: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placement_conversions() {
+        let top_left = Placement::from_ass(7, (192, 0, 54), None, (1920, 1080));
+        assert_eq!(top_left.to_an(), 7);
+        assert_eq!(top_left.to_ass((1280, 720)), ((128, 0, 36), None));
+        assert_eq!(top_left.to_srt_prefix(), "{\\an7}");
+        assert_eq!(Placement::default().to_srt_prefix(), "");
+
+        let vtt = top_left.to_vtt_settings();
+        assert_eq!(vtt, "line:5% line-align:start align:left position:10%");
+        assert_eq!(Placement::from_vtt_settings(&vtt).to_an(), 7);
+        assert_eq!(Placement::from_vtt_settings("line:-1").to_an(), 2);
+        assert_eq!(Placement::from_vtt_settings("line:0 align:end").to_an(), 9);
+        assert_eq!(Placement::default().to_vtt_settings(), "");
+
+        let region = top_left.to_ttml_region();
+        assert_eq!((region.origin.as_str(), region.extent.as_str()), ("10% 5%", "90% 95%"));
+        let back = Placement::from_ttml_region(&region);
+        assert_eq!(back.to_an(), 7);
+        assert_eq!(back.to_ass((1920, 1080)).0, (192, 0, 54));
+
+        let srt = SrtAssTags::parse("{\\an8}Hello");
+        assert_eq!(Placement::from_srt_tags(&srt).vertical, VerticalAlign::Top);
+    }
+}
@@ -0,0 +1,253 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Profiles of the features that players support, and the downgrading of subtitles for them.
+//!
+//! Many hardware players only show a small subset of what a subtitle format can express: some show
+//! `<font>` tags as text, some can't handle override tags like `{\an8}`, and some only support a single
+//! code page. `SubtitleFile::sanitize_for()` removes everything a `PlayerProfile` does not support and
+//! reports every loss, so nothing is dropped silently.
+
+use crate::errors::Result;
+use crate::{SrtAssTags, SubtitleFile};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use std::fmt;
+
+/// The subset of features a player supports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerProfile {
+    /// A descriptive name.
+    pub name: &'static str,
+
+    /// The HTML-like tags which are supported (like `"i"` or `"font"`), all others are removed.
+    pub allowed_tags: &'static [&'static str],
+
+    /// Whether the `{\anN}` alignment tag is supported (all other override tags are always removed).
+    pub alignment: bool,
+
+    /// The maximum number of lines of an entry (more lines are joined into the last one).
+    pub max_lines: Option<usize>,
+
+    /// The only character set the player can show (other characters are replaced by `?`).
+    pub charset: Option<&'static Encoding>,
+}
+
+impl PlayerProfile {
+    /// Old standalone DivX players: plain text in Windows-1252 with at most two lines.
+    pub const OLD_DIVX: PlayerProfile = PlayerProfile {
+        name: "old DivX player",
+        allowed_tags: &[],
+        alignment: false,
+        max_lines: Some(2),
+        charset: Some(WINDOWS_1252),
+    };
+
+    /// Smart TVs (like the players of Samsung TVs): basic formatting and font colors, but no override tags.
+    pub const SAMSUNG_TV: PlayerProfile = PlayerProfile {
+        name: "Samsung TV",
+        allowed_tags: &["i", "b", "u", "font"],
+        alignment: false,
+        max_lines: Some(3),
+        charset: None,
+    };
+
+    /// The `<track>` element of web browsers: italic, bold and underline only.
+    pub const WEB_TRACK: PlayerProfile = PlayerProfile {
+        name: "web <track>",
+        allowed_tags: &["i", "b", "u"],
+        alignment: false,
+        max_lines: None,
+        charset: None,
+    };
+}
+
+/// What was lost when an entry was sanitized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Loss {
+    /// An unsupported HTML-like tag was removed (the name of the tag, like `"font"`).
+    RemovedTag(String),
+
+    /// Unsupported override tags (like `\pos(10,10)`) were removed (the tags without backslash).
+    RemovedOverrideTags(Vec<String>),
+
+    /// The entry had more lines than supported, so lines were joined (the original number of lines).
+    JoinedLines(usize),
+
+    /// Characters which are not in the character set were replaced by `?` (the number of characters).
+    ReplacedCharacters(usize),
+}
+
+/// A `Loss` of a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLoss {
+    /// The position of the entry in `get_subtitle_entries()`.
+    pub entry_idx: usize,
+
+    /// What was lost.
+    pub loss: Loss,
+}
+
+impl fmt::Display for EntryLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entry {}: ", self.entry_idx)?;
+        match self.loss {
+            Loss::RemovedTag(ref tag) => write!(f, "removed the <{}> tag", tag),
+            Loss::RemovedOverrideTags(ref tags) => write!(f, "removed the override tags \\{}", tags.join(" \\")),
+            Loss::JoinedLines(count) => write!(f, "joined {} lines", count),
+            Loss::ReplacedCharacters(count) => write!(f, "replaced {} unsupported characters", count),
+        }
+    }
+}
+
+/// Removes all HTML-like tags which are not allowed and returns the names of the removed tags.
+fn strip_tags(text: &str, allowed_tags: &[&str]) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut removed: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let (before, tag_start) = rest.split_at(start);
+        result.push_str(before);
+        let (tag, after) = match tag_start.find('>') {
+            Some(end) => tag_start.split_at(end + 1),
+            None => (tag_start, ""),
+        };
+        rest = after;
+
+        let inner = tag.trim_start_matches('<').trim_end_matches('>').trim().trim_start_matches('/');
+        let name = inner.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+        let is_tag = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric());
+        if !is_tag || allowed_tags.contains(&name.as_str()) {
+            result.push_str(tag);
+        } else if !removed.contains(&name) {
+            removed.push(name);
+        }
+    }
+    result.push_str(rest);
+    (result, removed)
+}
+
+/// Returns the sanitized text and all losses.
+fn sanitize_text(text: &str, profile: &PlayerProfile) -> (String, Vec<Loss>) {
+    let mut losses = Vec::new();
+
+    // override tags
+    let ass_tags = SrtAssTags::parse(text);
+    let removed: Vec<String> = ass_tags
+        .tags
+        .iter()
+        .filter(|tag| !(profile.alignment && tag.starts_with("an")))
+        .cloned()
+        .collect();
+    let mut result = match (profile.alignment, ass_tags.alignment) {
+        (true, Some(an)) if !ass_tags.tags.is_empty() => format!("{{\\an{}}}{}", an, ass_tags.text),
+        _ if ass_tags.tags.is_empty() => text.to_string(),
+        _ => ass_tags.text.clone(),
+    };
+    if !removed.is_empty() {
+        losses.push(Loss::RemovedOverrideTags(removed));
+    }
+
+    // HTML-like tags
+    let (stripped, removed_tags) = strip_tags(&result, profile.allowed_tags);
+    result = stripped;
+    losses.extend(removed_tags.into_iter().map(Loss::RemovedTag));
+
+    // number of lines
+    if let Some(max_lines) = profile.max_lines.map(|m| m.max(1)) {
+        let lines: Vec<&str> = result.lines().collect();
+        if lines.len() > max_lines {
+            let (kept, joined) = lines.split_at(max_lines - 1);
+            let joined = joined.iter().map(|line| line.trim()).collect::<Vec<&str>>().join(" ");
+            losses.push(Loss::JoinedLines(lines.len()));
+            result = kept
+                .iter()
+                .cloned()
+                .chain(std::iter::once(joined.as_str()))
+                .collect::<Vec<&str>>()
+                .join("\n");
+        }
+    }
+
+    // character set
+    if let Some(charset) = profile.charset {
+        let mut replaced = 0;
+        result = result
+            .chars()
+            .map(|c| {
+                let (_, _, unmappable) = charset.encode(c.encode_utf8(&mut [0; 4]));
+                if unmappable {
+                    replaced += 1;
+                    '?'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        if replaced > 0 {
+            losses.push(Loss::ReplacedCharacters(replaced));
+        }
+    }
+
+    (result, losses)
+}
+
+impl SubtitleFile {
+    /// Removes everything from the texts which is not supported by `profile` and returns all losses.
+    ///
+    /// Timings, the rest of the file and entries without text are not changed.
+    pub fn sanitize_for(&mut self, profile: &PlayerProfile) -> Result<Vec<EntryLoss>> {
+        let mut entries = self.get_subtitle_entries()?;
+        let mut losses = Vec::new();
+        for (entry_idx, entry) in entries.iter_mut().enumerate() {
+            if let Some(ref text) = entry.line {
+                let (sanitized, entry_losses) = sanitize_text(text, profile);
+                losses.extend(entry_losses.into_iter().map(|loss| EntryLoss { entry_idx, loss }));
+                entry.line = Some(sanitized);
+            }
+        }
+        self.update_subtitle_entries(&entries)?;
+        Ok(losses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn sanitize_for_profiles() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\n{\\an8\\pos(10,10)}<font color=\"red\">Crème</font> <i>brûlée</i>\nline 2\nline 3\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let mut tv_file = file.clone();
+
+        let losses = file.sanitize_for(&PlayerProfile::OLD_DIVX).unwrap();
+        let messages: Vec<String> = losses.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "entry 0: removed the override tags \\an8 \\pos(10,10)",
+                "entry 0: removed the <font> tag",
+                "entry 0: removed the <i> tag",
+                "entry 0: joined 3 lines",
+            ]
+        );
+        assert_eq!(
+            file.get_subtitle_entries().unwrap()[0].line.as_deref(),
+            Some("Crème brûlée\nline 2 line 3")
+        );
+
+        let losses = tv_file
+            .sanitize_for(&PlayerProfile {
+                alignment: true,
+                ..PlayerProfile::SAMSUNG_TV
+            })
+            .unwrap();
+        assert_eq!(losses.len(), 1);
+        assert_eq!(
+            tv_file.get_subtitle_entries().unwrap()[0].line.as_deref(),
+            Some("{\\an8}<font color=\"red\">Crème</font> <i>brûlée</i>\nline 2\nline 3")
+        );
+    }
+}
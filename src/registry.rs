@@ -0,0 +1,226 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Support for subtitle formats that are implemented outside of this crate.
+//!
+//! A crate implements `SubtitleFormatProvider` for its format and registers it once with
+//! `register_format_provider()`. Afterwards `parse_auto()` detects and parses files of this format too
+//! and returns them as `SubtitleFile::External`, which supports all operations of `SubtitleFile`
+//! (writing is done by `SubtitleFileInterface::to_data()` of the external file type).
+//!
+//! The built-in formats are always detected first, so a provider can not take over `.srt` or `.ass` files.
+
+use crate::errors::Result;
+use crate::SubtitleFileInterface;
+use encoding_rs::Encoding;
+use std::ffi::OsStr;
+use std::fmt;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// A subtitle file of an external format (implemented for every cloneable `SubtitleFileInterface` type).
+pub trait ExternalSubtitleFile: SubtitleFileInterface + fmt::Debug + Send + Sync {
+    /// Clones the file into a new box (so `SubtitleFile` can be cloned).
+    fn clone_box(&self) -> Box<dyn ExternalSubtitleFile>;
+}
+
+impl<T> ExternalSubtitleFile for T
+where
+    T: SubtitleFileInterface + Clone + fmt::Debug + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Box<dyn ExternalSubtitleFile> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn ExternalSubtitleFile> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Detects and parses the files of a subtitle format which is not built into this crate.
+pub trait SubtitleFormatProvider: Send + Sync {
+    /// A descriptive name for the format like `".vtt (WebVTT)"` (like `SubtitleFormat::get_name()`).
+    fn name(&self) -> &str;
+
+    /// The file extensions of the format (without the dot).
+    fn extensions(&self) -> &[&str];
+
+    /// Returns `true` if the file with the (optional) extension and the content is in this format.
+    ///
+    /// The default implementation only checks the extension.
+    fn detect(&self, extension: Option<&OsStr>, _content: &[u8]) -> bool {
+        extension.is_some_and(|extension| self.extensions().iter().any(|e| OsStr::new(e) == extension))
+    }
+
+    /// Parses the file (see `parse_bytes()` for the meaning of `encoding` and `fps`).
+    fn parse(&self, content: &[u8], encoding: Option<&'static Encoding>, fps: f64) -> Result<Box<dyn ExternalSubtitleFile>>;
+}
+
+/// A list of format providers, which are asked in the order of registration.
+#[derive(Clone, Default)]
+pub struct FormatRegistry {
+    providers: Vec<Arc<dyn SubtitleFormatProvider>>,
+}
+
+impl fmt::Debug for FormatRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.providers.iter().map(|p| p.name())).finish()
+    }
+}
+
+impl FormatRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> FormatRegistry {
+        FormatRegistry::default()
+    }
+
+    /// Adds a provider.
+    pub fn register(&mut self, provider: Arc<dyn SubtitleFormatProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// All providers in the order of registration.
+    pub fn providers(&self) -> &[Arc<dyn SubtitleFormatProvider>] {
+        &self.providers
+    }
+
+    /// Returns the first provider which detects the file.
+    pub fn detect(&self, extension: Option<&OsStr>, content: &[u8]) -> Option<Arc<dyn SubtitleFormatProvider>> {
+        self.providers.iter().find(|p| p.detect(extension, content)).cloned()
+    }
+
+    /// Returns the provider with the given name.
+    pub fn by_name(&self, name: &str) -> Option<Arc<dyn SubtitleFormatProvider>> {
+        self.providers.iter().find(|p| p.name() == name).cloned()
+    }
+}
+
+fn global_registry() -> &'static RwLock<FormatRegistry> {
+    static REGISTRY: OnceLock<RwLock<FormatRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(FormatRegistry::new()))
+}
+
+/// Registers a provider for the whole process (used by `parse_auto()`).
+pub fn register_format_provider<P: SubtitleFormatProvider + 'static>(provider: P) {
+    let mut registry = global_registry().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    registry.register(Arc::new(provider));
+}
+
+/// Returns a copy of the process-wide registry.
+pub fn registered_formats() -> FormatRegistry {
+    global_registry().read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::{TimePoint, TimeSpan};
+    use crate::{parse_auto, SubtitleEntry, SubtitleFile};
+
+    /// A format with one subtitle per line: `<start ms> <end ms> <text>`.
+    #[derive(Debug, Clone)]
+    struct LineFile(Vec<SubtitleEntry>);
+
+    impl SubtitleFileInterface for LineFile {
+        fn get_subtitle_entries(&self) -> Result<Vec<SubtitleEntry>> {
+            Ok(self.0.clone())
+        }
+
+        fn update_subtitle_entries(&mut self, i: &[SubtitleEntry]) -> Result<()> {
+            self.0 = i.to_vec();
+            Ok(())
+        }
+
+        fn to_data(&self) -> Result<Vec<u8>> {
+            let lines: Vec<String> = self
+                .0
+                .iter()
+                .map(|e| {
+                    format!(
+                        "{} {} {}\n",
+                        e.timespan.start.msecs(),
+                        e.timespan.end.msecs(),
+                        e.line.as_deref().unwrap_or("")
+                    )
+                })
+                .collect();
+            Ok(lines.concat().into_bytes())
+        }
+
+        fn retain_entries_by_mask(&mut self, keep: &[bool]) -> Result<()> {
+            self.0 = self.0.iter().zip(keep).filter(|(_, &k)| k).map(|(e, _)| e.clone()).collect();
+            Ok(())
+        }
+    }
+
+    struct LineFormat;
+
+    impl SubtitleFormatProvider for LineFormat {
+        fn name(&self) -> &str {
+            ".lines (test)"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["lines"]
+        }
+
+        fn parse(&self, content: &[u8], _: Option<&'static Encoding>, _: f64) -> Result<Box<dyn ExternalSubtitleFile>> {
+            let entries = String::from_utf8_lossy(content)
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(3, ' ');
+                    let start = parts.next()?.parse().ok()?;
+                    let end = parts.next()?.parse().ok()?;
+                    let timespan = TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end));
+                    Some(SubtitleEntry::new(timespan, parts.next().unwrap_or("").to_string()))
+                })
+                .collect();
+            Ok(Box::new(LineFile(entries)))
+        }
+    }
+
+    /// A file which only implements the required methods.
+    #[derive(Debug, Clone)]
+    struct FixedFile;
+
+    impl SubtitleFileInterface for FixedFile {
+        fn get_subtitle_entries(&self) -> Result<Vec<SubtitleEntry>> {
+            Ok(vec![SubtitleEntry::from(TimeSpan::new(
+                TimePoint::from_msecs(0),
+                TimePoint::from_msecs(1000),
+            ))])
+        }
+
+        fn update_subtitle_entries(&mut self, _: &[SubtitleEntry]) -> Result<()> {
+            Ok(())
+        }
+
+        fn to_data(&self) -> Result<Vec<u8>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn retaining_is_optional() {
+        let mut file = SubtitleFile::External(Box::new(FixedFile));
+        let error = file.retain_entries(|_| false).unwrap_err();
+        assert_eq!(error.kind(), crate::ErrorKind::UnsupportedOperation);
+    }
+
+    #[test]
+    fn external_format_provider() {
+        register_format_provider(LineFormat);
+        assert!(registered_formats().by_name(".lines (test)").is_some());
+
+        let mut file = parse_auto(Some(OsStr::new("lines")), b"1000 2000 a\n3000 4000 b\n", None, 25.0).unwrap();
+        assert!(matches!(file, SubtitleFile::External(_)));
+        file.retain_entries(|e| e.line.as_deref() == Some("b")).unwrap();
+        assert_eq!(file.clone().to_data().unwrap(), b"3000 4000 b\n");
+
+        // built-in formats are still detected first
+        let srt = parse_auto(Some(OsStr::new("srt")), b"1\n00:00:01,000 --> 00:00:02,000\na\n", None, 25.0).unwrap();
+        assert!(matches!(srt, SubtitleFile::SubRipFile(_)));
+    }
+}
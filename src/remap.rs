@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Piecewise remapping of the timeline, for videos where only some sections were sped up or slowed down.
+//!
+//! A `TimeRemap` consists of pairs of source and target spans. Times inside a source span are linearly
+//! interpolated into the target span. Times between two segments (or before the first/after the last
+//! segment) keep the offset of the closest preceding segment end (or of the first segment start), so the
+//! timeline stays continuous if the target spans are adjacent.
+
+use self::errors::ErrorKind::*;
+use self::errors::*;
+use crate::errors::Result as SubtitleParserResult;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::{SubtitleEntry, SubtitleFile};
+use failure::ResultExt;
+
+/// Errors specific to timeline remapping.
+#[allow(missing_docs)]
+pub mod errors {
+    pub type Result<T> = std::result::Result<T, Error>;
+
+    define_error!(Error, ErrorKind);
+
+    #[derive(Clone, Copy, PartialEq, Debug, Fail)]
+    pub enum ErrorKind {
+        #[fail(display = "remap segment {} has an empty or negative source span", index)]
+        EmptySourceSpan { index: usize },
+
+        #[fail(display = "remap segment {} has a negative target span", index)]
+        NegativeTargetSpan { index: usize },
+
+        #[fail(display = "the source span of remap segment {} overlaps with the previous segment", index)]
+        OverlappingSegments { index: usize },
+
+        #[fail(display = "the playback speed has to be a positive number")]
+        InvalidSpeed,
+    }
+}
+
+/// A single section of the timeline: `source` is stretched/squeezed into `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemapSegment {
+    /// The span in the original timeline.
+    pub source: TimeSpan,
+
+    /// The span in the new timeline.
+    pub target: TimeSpan,
+}
+
+/// A piecewise linear mapping from the original timeline to a new timeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeRemap {
+    /// Sorted by source start, without overlaps
+    segments: Vec<RemapSegment>,
+}
+
+impl TimeRemap {
+    /// Creates a remapping from `(source, target)` pairs.
+    ///
+    /// The segments can be given in any order, but their source spans must not overlap.
+    pub fn new(pairs: Vec<(TimeSpan, TimeSpan)>) -> SubtitleParserResult<TimeRemap> {
+        Ok(Self::new_inner(pairs).with_context(|_| crate::ErrorKind::CreationError)?)
+    }
+
+    fn new_inner(pairs: Vec<(TimeSpan, TimeSpan)>) -> Result<TimeRemap> {
+        let mut segments = Vec::with_capacity(pairs.len());
+        for (index, (source, target)) in pairs.into_iter().enumerate() {
+            if source.end <= source.start {
+                return Err(EmptySourceSpan { index }.into());
+            }
+            if target.end < target.start {
+                return Err(NegativeTargetSpan { index }.into());
+            }
+            segments.push((index, RemapSegment { source, target }));
+        }
+        segments.sort_by_key(|(_, segment)| segment.source.start);
+
+        for (prev, (index, segment)) in segments.iter().zip(segments.iter().skip(1)) {
+            if segment.source.start < prev.1.source.end {
+                return Err(OverlappingSegments { index: *index }.into());
+            }
+        }
+
+        Ok(TimeRemap {
+            segments: segments.into_iter().map(|(_, segment)| segment).collect(),
+        })
+    }
+
+    /// Creates a remapping where the section `source` is played with `speed` (like `25.0 / 23.976` for a PAL speedup).
+    ///
+    /// Everything after the section is shifted by the time that was gained or lost.
+    pub fn with_speed(source: TimeSpan, speed: f64) -> SubtitleParserResult<TimeRemap> {
+        Ok(Self::with_speed_inner(source, speed).with_context(|_| crate::ErrorKind::CreationError)?)
+    }
+
+    fn with_speed_inner(source: TimeSpan, speed: f64) -> Result<TimeRemap> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(InvalidSpeed.into());
+        }
+        let target_len = (source.len().msecs() as f64 / speed).round() as i64;
+        let target = TimeSpan::new(source.start, source.start + TimeDelta::from_msecs(target_len));
+        Self::new_inner(vec![(source, target)])
+    }
+
+    /// Returns the segments sorted by their source start.
+    pub fn segments(&self) -> &[RemapSegment] {
+        &self.segments
+    }
+
+    /// Maps a time of the original timeline to the new timeline.
+    pub fn map_point(&self, t: TimePoint) -> TimePoint {
+        // the last segment which starts at or before `t`
+        let segment = match self.segments.iter().rev().find(|segment| segment.source.start <= t) {
+            Some(segment) => segment,
+            None => match self.segments.first() {
+                Some(first) => return t + (first.target.start - first.source.start),
+                None => return t,
+            },
+        };
+
+        if t >= segment.source.end {
+            return t + (segment.target.end - segment.source.end);
+        }
+
+        let offset = (t - segment.source.start).msecs() as f64;
+        let factor = segment.target.len().msecs() as f64 / segment.source.len().msecs() as f64;
+        segment.target.start + TimeDelta::from_msecs((offset * factor).round() as i64)
+    }
+
+    /// Maps the start and end of a span.
+    pub fn map_span(&self, span: TimeSpan) -> TimeSpan {
+        TimeSpan::new(self.map_point(span.start), self.map_point(span.end))
+    }
+
+    /// Maps the timespans of all entries.
+    pub fn apply(&self, entries: &mut [SubtitleEntry]) {
+        for entry in entries {
+            entry.timespan = self.map_span(entry.timespan);
+        }
+    }
+
+    /// Maps the timespans of all entries in a subtitle file.
+    pub fn apply_to_file(&self, file: &mut SubtitleFile) -> SubtitleParserResult<()> {
+        let mut entries = file.get_subtitle_entries()?;
+        self.apply(&mut entries);
+        file.update_subtitle_entries(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: i64, end: i64) -> TimeSpan {
+        TimeSpan::new(TimePoint::from_secs(start), TimePoint::from_secs(end))
+    }
+
+    #[test]
+    fn remap_piecewise() {
+        // 10s..20s are slowed down to half the speed, 40s..50s are cut to 5 seconds
+        let remap = TimeRemap::new(vec![(span(40, 50), span(50, 55)), (span(10, 20), span(10, 30))]).unwrap();
+
+        let map = |secs: i64| remap.map_point(TimePoint::from_secs(secs)).msecs();
+        assert_eq!(map(5), 5000);
+        assert_eq!(map(15), 20000);
+        assert_eq!(map(30), 40000);
+        assert_eq!(map(45), 52500);
+        assert_eq!(map(60), 65000);
+
+        assert!(TimeRemap::new(vec![(span(0, 10), span(0, 10)), (span(5, 15), span(5, 15))]).is_err());
+        assert!(TimeRemap::new(vec![(span(10, 10), span(0, 10))]).is_err());
+
+        let pal = TimeRemap::with_speed(span(0, 25), 25.0 / 24.0).unwrap();
+        assert_eq!(pal.map_point(TimePoint::from_secs(25)).msecs(), 24000);
+        assert!(TimeRemap::with_speed(span(0, 25), 0.0).is_err());
+    }
+}
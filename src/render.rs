@@ -0,0 +1,472 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A minimal renderer for subtitle previews (requires the `render` feature).
+//!
+//! This draws the plain dialogue text with a built-in 5x7 pixel font onto an RGBA canvas. It is meant for
+//! quality-control preview strips and thumbnails where `libass` is not available - it ignores fonts, styles
+//! and override tags (except for the line breaks) and draws characters outside of ASCII as boxes.
+
+use crate::positioning::{HorizontalAlign, Placement, VerticalAlign};
+use crate::timetypes::TimePoint;
+use crate::SubtitleEntry;
+
+/// The glyphs for the characters `' '` to `'~'`. Every byte is a column (from left to right), the lowest bit is the top row.
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x01, 0x01],
+    [0x3E, 0x41, 0x41, 0x51, 0x32],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x04, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x7F, 0x20, 0x18, 0x20, 0x7F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x08, 0x14, 0x54, 0x54, 0x3C],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x00, 0x7F, 0x10, 0x28, 0x44],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+/// The glyph for characters that are not in the font.
+const UNKNOWN_GLYPH: [u8; 5] = [0x7F, 0x41, 0x41, 0x41, 0x7F];
+
+/// Width of a character cell (glyph and spacing) in font pixels.
+const CELL_WIDTH: u32 = 6;
+
+/// Height of a line (glyph and spacing) in font pixels.
+const LINE_HEIGHT: u32 = 9;
+
+fn glyph(c: char) -> [u8; 5] {
+    (c as usize)
+        .checked_sub(0x20)
+        .and_then(|i| FONT_5X7.get(i))
+        .cloned()
+        .unwrap_or(UNKNOWN_GLYPH)
+}
+
+/// An RGBA image (8 bits per channel, rows from top to bottom).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a canvas filled with the `background` color.
+    pub fn new(width: u32, height: u32, background: [u8; 4]) -> Canvas {
+        let pixel_count = width as usize * height as usize;
+        Canvas {
+            width,
+            height,
+            data: background.iter().cloned().cycle().take(pixel_count * 4).collect(),
+        }
+    }
+
+    /// The width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the color of a pixel (or `None` if it is outside of the canvas).
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width {
+            return None;
+        }
+        let idx = (y as usize * self.width as usize + x as usize) * 4;
+        match self.data.get(idx..idx + 4)? {
+            &[r, g, b, a] => Some([r, g, b, a]),
+            _ => None,
+        }
+    }
+
+    /// The raw RGBA data.
+    pub fn as_rgba(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the raw RGBA data.
+    pub fn into_rgba(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Draws `color` over a rectangle (blended by its alpha value). Parts outside of the canvas are clipped.
+    pub fn fill_rect(&mut self, x: i64, y: i64, width: u32, height: u32, color: [u8; 4]) {
+        let x_range = x.max(0)..(x + i64::from(width)).min(i64::from(self.width));
+        let y_range = y.max(0)..(y + i64::from(height)).min(i64::from(self.height));
+        for py in y_range {
+            for px in x_range.clone() {
+                let idx = (py as usize * self.width as usize + px as usize) * 4;
+                if let Some(pixel) = self.data.get_mut(idx..idx + 4) {
+                    blend(pixel, color);
+                }
+            }
+        }
+    }
+
+    /// Draws the plain text of a subtitle line (override tags are ignored, `\N` and `\n` are line breaks).
+    ///
+    /// Lines that are too wide for the canvas are wrapped at spaces.
+    pub fn draw_text(&mut self, text: &str, style: &RenderStyle) {
+        let scale = style.scale.max(1);
+        let (margin_l, margin_r, margin_v) = match style.placement.margins {
+            Some((l, r, v)) => (
+                (l * f64::from(self.width)) as i64,
+                (r * f64::from(self.width)) as i64,
+                (v * f64::from(self.height)) as i64,
+            ),
+            None => {
+                let margin = i64::from(LINE_HEIGHT * scale);
+                (margin, margin, margin)
+            }
+        };
+
+        let max_chars = ((i64::from(self.width) - margin_l - margin_r) / i64::from(CELL_WIDTH * scale)).max(1) as usize;
+        let lines = wrap_lines(&plain_text(text), max_chars);
+
+        let line_height = i64::from(LINE_HEIGHT * scale);
+        let block_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as i64 * i64::from(CELL_WIDTH * scale);
+        let block_height = lines.len() as i64 * line_height;
+
+        // the anchor point is on the side of the block given by the alignment
+        let (anchor_x, anchor_y) = match style.placement.position {
+            Some((x, y)) => ((x * f64::from(self.width)) as i64, (y * f64::from(self.height)) as i64),
+            None => (
+                match style.placement.horizontal {
+                    HorizontalAlign::Left => margin_l,
+                    HorizontalAlign::Center => (margin_l + i64::from(self.width) - margin_r) / 2,
+                    HorizontalAlign::Right => i64::from(self.width) - margin_r,
+                },
+                match style.placement.vertical {
+                    VerticalAlign::Top => margin_v,
+                    VerticalAlign::Middle => i64::from(self.height) / 2,
+                    VerticalAlign::Bottom => i64::from(self.height) - margin_v,
+                },
+            ),
+        };
+        let top = match style.placement.vertical {
+            VerticalAlign::Top => anchor_y,
+            VerticalAlign::Middle => anchor_y - block_height / 2,
+            VerticalAlign::Bottom => anchor_y - block_height,
+        };
+
+        // the outline is drawn first, so it doesn't cover the text of neighbouring characters
+        let mut passes = Vec::new();
+        if let Some(outline_color) = style.outline_color {
+            passes.push((i64::from(style.outline_width), outline_color));
+        }
+        passes.push((0, style.color));
+
+        for (grow, color) in passes {
+            for (line_idx, line) in lines.iter().enumerate() {
+                let line_width = line.chars().count() as i64 * i64::from(CELL_WIDTH * scale);
+                let left = match style.placement.horizontal {
+                    HorizontalAlign::Left => anchor_x,
+                    HorizontalAlign::Center => anchor_x - block_width / 2 + (block_width - line_width) / 2,
+                    HorizontalAlign::Right => anchor_x - line_width,
+                };
+                let y = top + line_idx as i64 * line_height;
+                for (char_idx, c) in line.chars().enumerate() {
+                    let x = left + char_idx as i64 * i64::from(CELL_WIDTH * scale);
+                    self.draw_glyph(glyph(c), x, y, scale, grow, color);
+                }
+            }
+        }
+    }
+
+    fn draw_glyph(&mut self, glyph: [u8; 5], x: i64, y: i64, scale: u32, grow: i64, color: [u8; 4]) {
+        let size = scale + 2 * grow as u32;
+        for (col, bits) in glyph.iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) != 0 {
+                    let px = x + col as i64 * i64::from(scale) - grow;
+                    let py = y + row * i64::from(scale) - grow;
+                    self.fill_rect(px, py, size, size, color);
+                }
+            }
+        }
+    }
+}
+
+/// Blends `color` over `pixel` ("source over" compositing).
+fn blend(pixel: &mut [u8], color: [u8; 4]) {
+    let alpha = u32::from(color[3]);
+    for (dst, src) in pixel.iter_mut().zip(color.iter().take(3)) {
+        *dst = ((u32::from(*src) * alpha + u32::from(*dst) * (255 - alpha)) / 255) as u8;
+    }
+    if let Some(dst_alpha) = pixel.get_mut(3) {
+        *dst_alpha = (alpha + u32::from(*dst_alpha) * (255 - alpha) / 255) as u8;
+    }
+}
+
+/// Removes override blocks (`{\an8}`) and HTML-like tags (`<i>`) and converts `\N`/`\n`/`\h`.
+fn plain_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut closing = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(end) = closing {
+            if c == end {
+                closing = None;
+            }
+            continue;
+        }
+        match c {
+            '{' => closing = Some('}'),
+            '<' => closing = Some('>'),
+            '\\' => match chars.peek() {
+                Some('N') | Some('n') => {
+                    chars.next();
+                    result.push('\n');
+                }
+                Some('h') => {
+                    chars.next();
+                    result.push(' ');
+                }
+                _ => result.push(c),
+            },
+            '\r' => {}
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Splits the text into lines and wraps them at spaces so they are at most `max_chars` long (if possible).
+fn wrap_lines(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+/// How the text is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStyle {
+    /// The size of a font pixel in canvas pixels (a line is `9 * scale` pixels high).
+    pub scale: u32,
+
+    /// The text color (RGBA).
+    pub color: [u8; 4],
+
+    /// The outline color (RGBA) or `None` for no outline.
+    pub outline_color: Option<[u8; 4]>,
+
+    /// The outline width in canvas pixels.
+    pub outline_width: u32,
+
+    /// The background of newly created canvases (RGBA).
+    pub background: [u8; 4],
+
+    /// Where the text is placed. Without margins, the distance to the border is one line height.
+    pub placement: Placement,
+}
+
+impl Default for RenderStyle {
+    fn default() -> RenderStyle {
+        RenderStyle {
+            scale: 2,
+            color: [255, 255, 255, 255],
+            outline_color: Some([0, 0, 0, 255]),
+            outline_width: 2,
+            background: [32, 32, 32, 255],
+            placement: Placement::default(),
+        }
+    }
+}
+
+/// Renders all entries that are shown at the time `t` (like a thumbnail of the video at this time).
+pub fn render_frame(entries: &[SubtitleEntry], t: TimePoint, width: u32, height: u32, style: &RenderStyle) -> Canvas {
+    let mut canvas = Canvas::new(width, height, style.background);
+    let text: Vec<&str> = entries
+        .iter()
+        .filter(|entry| entry.timespan.start <= t && t < entry.timespan.end)
+        .filter_map(|entry| entry.line.as_deref())
+        .collect();
+    canvas.draw_text(&text.join("\n"), style);
+    canvas
+}
+
+/// Renders every entry with text into its own frame and stacks the frames vertically (separated by a line
+/// of one pixel in the text color), for quickly checking all lines of a file.
+pub fn render_preview_strip(entries: &[SubtitleEntry], width: u32, frame_height: u32, style: &RenderStyle) -> Canvas {
+    let texts: Vec<&str> = entries.iter().filter_map(|entry| entry.line.as_deref()).collect();
+    let row_height = frame_height + 1;
+    let mut canvas = Canvas::new(width, (texts.len() as u32).saturating_mul(row_height), style.background);
+    for (idx, text) in texts.iter().enumerate() {
+        let mut frame = Canvas::new(width, frame_height, style.background);
+        frame.draw_text(text, style);
+
+        let offset = idx * row_height as usize * width as usize * 4;
+        if let Some(dst) = canvas.data.get_mut(offset..offset + frame.data.len()) {
+            dst.copy_from_slice(&frame.data);
+        }
+        canvas.fill_rect(0, (idx as i64 + 1) * i64::from(row_height) - 1, width, 1, style.color);
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A style which draws in the top left corner without margins on a black background.
+    fn corner_style() -> RenderStyle {
+        RenderStyle {
+            scale: 1,
+            outline_color: None,
+            background: [0, 0, 0, 255],
+            placement: Placement {
+                vertical: VerticalAlign::Top,
+                horizontal: HorizontalAlign::Left,
+                margins: Some((0.0, 0.0, 0.0)),
+                position: None,
+            },
+            ..RenderStyle::default()
+        }
+    }
+
+    fn entries() -> Vec<SubtitleEntry> {
+        vec![SubtitleEntry::new(
+            crate::timetypes::TimeSpan::new(TimePoint::from_secs(1), TimePoint::from_secs(2)),
+            "x".to_string(),
+        )]
+    }
+
+    #[test]
+    fn render_plain_text() {
+        assert_eq!(plain_text("{\\an8}<i>Hello</i>\\Nworld"), "Hello\nworld");
+        assert_eq!(wrap_lines("aa bb cc\ndd", 5), vec!["aa bb", "cc", "dd"]);
+    }
+
+    #[test]
+    fn render_glyph() {
+        let style = corner_style();
+        let mut canvas = Canvas::new(12, 9, style.background);
+        canvas.draw_text("I", &style);
+
+        // the glyph of `I` is a vertical bar with serifs at the top and the bottom
+        let white = Some([255, 255, 255, 255]);
+        assert_eq!((0..7).filter(|&y| canvas.pixel(2, y) == white).count(), 7);
+        assert_eq!(canvas.pixel(1, 0), white);
+        assert_eq!(canvas.pixel(1, 3), Some([0, 0, 0, 255]));
+        assert_eq!(
+            canvas.as_rgba().chunks(4).filter(|p| Some(*p) == white.as_ref().map(|w| &w[..])).count(),
+            11
+        );
+    }
+
+    #[test]
+    fn render_preview_strip_size() {
+        let strip = render_preview_strip(&entries(), 40, 30, &RenderStyle::default());
+        assert_eq!((strip.width(), strip.height()), (40, 31));
+    }
+
+    #[test]
+    fn render_frame_without_entry() {
+        assert!(render_frame(&entries(), TimePoint::from_secs(3), 40, 30, &corner_style())
+            .as_rgba()
+            .chunks(4)
+            .all(|p| p == [0, 0, 0, 255]));
+    }
+}
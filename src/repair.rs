@@ -0,0 +1,521 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! An opinionated "fix this broken file" entry point.
+//!
+//! `repair()` decodes the bytes (replacing invalid characters instead of failing), detects the format,
+//! skips text around the subtitles which belongs to another format (see `locate_payload()`), parses `.srt` files leniently (any line with a `-->` starts a new entry), and then fixes the timings,
+//! overlaps and unbalanced `<i>`/`<b>`/`<u>`/`<s>`/`<font>` tags. Everything that was changed is listed in
+//! the `RepairReport`.
+
+use crate::errors::Result;
+use crate::formats::{detect_encoding, parse_str_with_warnings};
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::warnings::Warnings;
+use crate::{SrtFile, SubtitleEntry, SubtitleFile, SubtitleFormat};
+use encoding_rs::{Encoding, UTF_8};
+use std::fmt;
+use std::ops::Range;
+
+/// The options of `repair()`.
+#[derive(Debug, Clone, Copy)]
+pub struct RepairOptions {
+    /// The format of the file (`None` to detect it from the content).
+    pub format: Option<SubtitleFormat>,
+
+    /// The character encoding (`None` to use UTF-8 if possible and detect it otherwise).
+    pub encoding: Option<&'static Encoding>,
+
+    /// The frame rate for `MicroDVD` files.
+    pub fps: f64,
+
+    /// The duration of entries which end before they start.
+    pub min_duration: TimeDelta,
+
+    /// Shorten entries which overlap with the next entry.
+    pub fix_overlaps: bool,
+
+    /// Close unclosed and remove unopened formatting tags in `.srt` files.
+    pub balance_tags: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> RepairOptions {
+        RepairOptions {
+            format: None,
+            encoding: None,
+            fps: 25.0,
+            min_duration: TimeDelta::from_secs(1),
+            fix_overlaps: true,
+            balance_tags: true,
+        }
+    }
+}
+
+/// A single fix that was applied by `repair()`. Entry indices refer to the repaired file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    /// The file was decoded with this encoding.
+    Decoded {
+        /// The name of the encoding.
+        encoding: &'static str,
+
+        /// Whether invalid byte sequences were replaced by `U+FFFD`.
+        replaced_invalid: bool,
+    },
+
+    /// The format was detected from the content.
+    DetectedFormat {
+        /// The detected format.
+        format: SubtitleFormat,
+    },
+
+    /// A line of a `.srt` file which does not belong to any entry was dropped.
+    DroppedLine {
+        /// The line number (starting at 0).
+        line_num: usize,
+
+        /// The content of the line.
+        line: String,
+    },
+
+    /// Lines before the subtitles which belong to another format (like an `.m3u8` playlist) were skipped.
+    SkippedPrefix {
+        /// The number of skipped lines.
+        line_count: usize,
+    },
+
+    /// Lines after the subtitles (like a repeated copy of the file) were skipped.
+    SkippedSuffix {
+        /// The line number of the first skipped line (starting at 0).
+        line_num: usize,
+
+        /// The number of skipped lines.
+        line_count: usize,
+    },
+
+    /// The `.srt` entries were sorted by start time.
+    Sorted,
+
+    /// The `.srt` entries were renumbered, because their indices were not `1, 2, 3, ...`.
+    Renumbered,
+
+    /// The start time was negative and was set to zero.
+    ClampedNegativeStart {
+        /// The position of the entry.
+        entry_idx: usize,
+    },
+
+    /// The entry ended before it started, so the end was set to `start + min_duration`.
+    FixedEndBeforeStart {
+        /// The position of the entry.
+        entry_idx: usize,
+    },
+
+    /// The entry overlapped with the next one and was shortened.
+    TrimmedOverlap {
+        /// The position of the entry.
+        entry_idx: usize,
+    },
+
+    /// Formatting tags of the entry were closed or removed.
+    BalancedTags {
+        /// The position of the entry.
+        entry_idx: usize,
+    },
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairAction::Decoded { encoding, replaced_invalid } => {
+                write!(f, "decoded as {}", encoding)?;
+                if *replaced_invalid {
+                    write!(f, " (invalid characters were replaced)")?;
+                }
+                Ok(())
+            }
+            RepairAction::DetectedFormat { format } => write!(f, "detected format {}", format.get_name()),
+            RepairAction::DroppedLine { line_num, line } => write!(f, "line {}: dropped '{}'", line_num, line),
+            RepairAction::SkippedPrefix { line_count } => write!(f, "skipped {} lines before the subtitles", line_count),
+            RepairAction::SkippedSuffix { line_num, line_count } => write!(f, "line {}: skipped {} lines after the subtitles", line_num, line_count),
+            RepairAction::Sorted => write!(f, "sorted the entries by start time"),
+            RepairAction::Renumbered => write!(f, "renumbered the entries"),
+            RepairAction::ClampedNegativeStart { entry_idx } => write!(f, "entry {}: moved the negative start to zero", entry_idx),
+            RepairAction::FixedEndBeforeStart { entry_idx } => write!(f, "entry {}: fixed the end before the start", entry_idx),
+            RepairAction::TrimmedOverlap { entry_idx } => write!(f, "entry {}: shortened to end before the next entry", entry_idx),
+            RepairAction::BalancedTags { entry_idx } => write!(f, "entry {}: balanced the formatting tags", entry_idx),
+        }
+    }
+}
+
+/// Everything that `repair()` found and changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// The applied fixes in the order they were applied.
+    pub actions: Vec<RepairAction>,
+
+    /// The warnings of the parser (for the formats that are not parsed leniently).
+    pub warnings: Warnings,
+}
+
+/// Decodes, parses and fixes a broken subtitle file (see the module documentation).
+///
+/// Returns an error only if the format can't be detected or the file can't be parsed at all.
+pub fn repair(bytes: &[u8], options: &RepairOptions) -> Result<(SubtitleFile, RepairReport)> {
+    let mut report = RepairReport::default();
+
+    let encoding = match options.encoding {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => detect_encoding(bytes)?,
+    };
+    let (text, _, replaced_invalid) = encoding.decode(bytes);
+    report.actions.push(RepairAction::Decoded {
+        encoding: encoding.name(),
+        replaced_invalid,
+    });
+
+    let format = match options.format {
+        Some(format) => format,
+        None => {
+            let format = detect_text_format(&text).ok_or(crate::ErrorKind::UnknownFileFormat)?;
+            report.actions.push(RepairAction::DetectedFormat { format });
+            format
+        }
+    };
+
+    let payload = locate_payload(&text, format);
+    let line_count = text.lines().count();
+    if payload.start > 0 {
+        report.actions.push(RepairAction::SkippedPrefix { line_count: payload.start });
+    }
+    if payload.end < line_count {
+        report.actions.push(RepairAction::SkippedSuffix {
+            line_num: payload.end,
+            line_count: line_count - payload.end,
+        });
+    }
+    let text: String = text.split_inclusive('\n').take(payload.end).skip(payload.start).collect();
+
+    let mut file = if format == SubtitleFormat::SubRip {
+        lenient_srt(&text, &mut report)?
+    } else {
+        let (file, warnings) = parse_str_with_warnings(format, &text, options.fps)?;
+        report.warnings = warnings;
+        file
+    };
+
+    let mut entries = file.get_subtitle_entries()?;
+    fix_timings(&mut entries, options, &mut report);
+    if options.balance_tags && format == SubtitleFormat::SubRip {
+        for (entry_idx, entry) in entries.iter_mut().enumerate() {
+            if let Some(balanced) = entry.line.as_deref().and_then(balance_tags) {
+                entry.line = Some(balanced);
+                report.actions.push(RepairAction::BalancedTags { entry_idx });
+            }
+        }
+    }
+    file.update_subtitle_entries(&entries)?;
+
+    Ok((file, report))
+}
+
+/// The section headers which can start a `.ssa` file.
+const SSA_SECTIONS: &[&str] = &["[Script Info]", "[V4 Styles]", "[V4+ Styles]", "[Events]"];
+
+/// Returns the lines (starting at 0) which contain the subtitles of `format`, without text of another format around
+/// them.
+///
+/// WebVTT files are found inside of other texts (like an `.m3u8` playlist), garbage before the first section of
+/// a `.ssa` file (from a bad mux) is skipped and repeated copies of a `.srt` file which was concatenated with itself
+/// are cut off. For other formats all lines are returned.
+pub fn locate_payload(text: &str, format: SubtitleFormat) -> Range<usize> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim_start_matches('\u{FEFF}').trim()).collect();
+    let start = match format {
+        SubtitleFormat::WebVtt => lines.iter().position(|line| line.starts_with("WEBVTT")),
+        SubtitleFormat::SubStationAlpha => lines
+            .iter()
+            .position(|line| SSA_SECTIONS.iter().any(|section| line.eq_ignore_ascii_case(section))),
+        _ => None,
+    }
+    .unwrap_or(0);
+    let end = match format {
+        SubtitleFormat::SubRip => repeated_srt_start(&lines),
+        _ => None,
+    }
+    .unwrap_or(lines.len());
+    start..end.max(start)
+}
+
+/// Returns the line where the first entry of a `.srt` file is repeated (with the same index, timing and text).
+fn repeated_srt_start(lines: &[&str]) -> Option<usize> {
+    let first_timing = lines.iter().position(|line| line.contains("-->"))?;
+    let first_start = first_timing.saturating_sub(1);
+    let first_entry = lines.get(first_start..=first_timing + 1)?;
+    (first_timing + 1..lines.len())
+        .filter(|&idx| lines.get(idx) == lines.get(first_timing))
+        .map(|idx| idx - (first_timing - first_start))
+        .find(|&start| lines.get(start..start + first_entry.len()) == Some(first_entry))
+}
+
+/// Guesses the text format from the content.
+fn detect_text_format(text: &str) -> Option<SubtitleFormat> {
+    let lines = || text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines().any(|line| line.trim_start_matches('\u{feff}').starts_with("WEBVTT")) {
+        Some(SubtitleFormat::WebVtt)
+    } else if lines()
+        .next()
+        .is_some_and(|line| line.trim_start_matches('\u{feff}').starts_with("Scenarist_SCC"))
+    {
+        Some(SubtitleFormat::Scc)
+    } else if text.contains("<tt") && text.contains("http://www.w3.org/ns/ttml") {
+        Some(SubtitleFormat::Ttml)
+    } else if crate::formats::srv3::looks_like_srv3(text) {
+        Some(SubtitleFormat::Srv3)
+    } else if crate::formats::sami::looks_like_sami(text) {
+        Some(SubtitleFormat::Sami)
+    } else if crate::formats::realtext::looks_like_realtext(text) {
+        Some(SubtitleFormat::RealText)
+    } else if crate::formats::spruce::looks_like_spruce(text) {
+        Some(SubtitleFormat::SpruceStl)
+    } else if crate::formats::aqtitle::looks_like_aqtitle(text) {
+        Some(SubtitleFormat::AqTitle)
+    } else if crate::formats::vplayer::looks_like_vplayer(text) {
+        Some(SubtitleFormat::VPlayer)
+    } else if lines().any(|line| line.eq_ignore_ascii_case("[Events]") || line.eq_ignore_ascii_case("[Script Info]")) {
+        Some(SubtitleFormat::SubStationAlpha)
+    } else if lines().any(|line| line.starts_with("timestamp:")) {
+        Some(SubtitleFormat::VobSubIdx)
+    } else if lines().any(|line| line.contains("-->")) {
+        Some(SubtitleFormat::SubRip)
+    } else if lines().filter(|line| crate::formats::lrc::starts_with_time_tag(line)).nth(1).is_some() {
+        Some(SubtitleFormat::Lrc)
+    } else if crate::formats::subviewer::looks_like_subviewer(text) {
+        Some(SubtitleFormat::SubViewer)
+    } else if lines()
+        .next()
+        .is_some_and(|line| crate::formats::sbv::parse_timing_line(line.trim_start_matches('\u{feff}')).is_some())
+    {
+        Some(SubtitleFormat::Sbv)
+    } else if crate::formats::mpl2::looks_like_mpl2(text) {
+        Some(SubtitleFormat::Mpl2)
+    } else if crate::formats::jacosub::looks_like_jacosub(text) {
+        Some(SubtitleFormat::JacoSub)
+    } else if crate::formats::powerdivx::looks_like_powerdivx(text) {
+        Some(SubtitleFormat::PowerDivX)
+    } else if lines().next().is_some_and(|line| line.starts_with('{') && line.contains("}{")) {
+        Some(SubtitleFormat::MicroDVD)
+    } else {
+        None
+    }
+}
+
+/// Parses timestamps like `01:02:03,456`, `1:2:3.4` or `02:03,456`.
+fn parse_loose_timestamp(s: &str) -> Option<TimePoint> {
+    let s = s.trim();
+    let (hms, fraction) = match s.find([',', '.']) {
+        Some(idx) => (s.get(..idx)?, s.get(idx + 1..)?),
+        None => (s, ""),
+    };
+    let mut parts = hms.split(':').map(|p| p.trim().parse::<i64>());
+    let (h, m, sec) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(h)), Some(Ok(m)), Some(Ok(s)), None) => (h, m, s),
+        (Some(Ok(m)), Some(Ok(s)), None, None) => (0, m, s),
+        _ => return None,
+    };
+    // `4` means 400ms, `45` means 450ms
+    let digits: String = fraction.chars().take_while(char::is_ascii_digit).take(3).collect();
+    let ms = if digits.is_empty() { 0 } else { format!("{:0<3}", digits).parse().ok()? };
+    TimePoint::try_from_components(h, m, sec, ms).ok()
+}
+
+/// Parses a line like `00:00:01,000 --> 00:00:02,000 X1:...` into a timespan.
+fn parse_loose_timespan(line: &str) -> Option<TimeSpan> {
+    let (start, end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+    Some(TimeSpan::new(parse_loose_timestamp(start)?, parse_loose_timestamp(end)?))
+}
+
+/// Reads every timestamp line and the text up to the next empty line (or the next timestamp line).
+fn lenient_srt(text: &str, report: &mut RepairReport) -> Result<SubtitleFile> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim_start_matches('\u{FEFF}').trim_end()).collect();
+    let is_timespan = |idx: usize| lines.get(idx).and_then(|line| parse_loose_timespan(line)).is_some();
+    let is_index = |idx: usize| {
+        lines
+            .get(idx)
+            .is_some_and(|line| !line.is_empty() && line.trim().chars().all(|c| c.is_ascii_digit()))
+    };
+
+    let mut entries: Vec<(TimeSpan, Vec<&str>)> = Vec::new();
+    let mut indices = Vec::new();
+    let mut in_text = false;
+    for (line_num, line) in lines.iter().enumerate() {
+        if let Some(timespan) = parse_loose_timespan(line) {
+            entries.push((timespan, Vec::new()));
+            in_text = true;
+        } else if is_index(line_num) && is_timespan(line_num + 1) {
+            indices.push(line.trim().parse::<i64>().unwrap_or(0));
+            in_text = false;
+        } else if line.is_empty() {
+            in_text = false;
+        } else if let (true, Some((_, texts))) = (in_text, entries.last_mut()) {
+            texts.push(line);
+        } else {
+            let line = line.to_string();
+            report.actions.push(RepairAction::DroppedLine { line_num, line });
+        }
+    }
+
+    if indices.len() != entries.len() || indices.iter().zip(1..).any(|(&index, expected)| index != expected) {
+        report.actions.push(RepairAction::Renumbered);
+    }
+    if entries.windows(2).any(|w| matches!(w, [a, b] if a.0.start > b.0.start)) {
+        entries.sort_by_key(|(timespan, _)| timespan.start);
+        report.actions.push(RepairAction::Sorted);
+    }
+
+    let entries = entries.into_iter().map(|(timespan, texts)| (timespan, texts.join("\n"))).collect();
+    Ok(SrtFile::create(entries)?.into())
+}
+
+fn fix_timings(entries: &mut [SubtitleEntry], options: &RepairOptions, report: &mut RepairReport) {
+    for (entry_idx, entry) in entries.iter_mut().enumerate() {
+        if entry.timespan.start.is_negative() {
+            entry.timespan.start = TimePoint::from_msecs(0);
+            report.actions.push(RepairAction::ClampedNegativeStart { entry_idx });
+        }
+        if entry.timespan.end < entry.timespan.start {
+            entry.timespan.end = entry.timespan.start + options.min_duration;
+            report.actions.push(RepairAction::FixedEndBeforeStart { entry_idx });
+        }
+    }
+
+    if !options.fix_overlaps {
+        return;
+    }
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries.get(i).map(|e| e.timespan.start));
+    for pair in order.windows(2) {
+        if let [current, next] = *pair {
+            let next_start = match entries.get(next) {
+                Some(next) => next.timespan.start,
+                None => continue,
+            };
+            if let Some(entry) = entries.get_mut(current) {
+                // entries with the same start are shown together on purpose
+                if entry.timespan.end > next_start && entry.timespan.start < next_start {
+                    entry.timespan.end = next_start;
+                    report.actions.push(RepairAction::TrimmedOverlap { entry_idx: current });
+                }
+            }
+        }
+    }
+}
+
+/// The formatting tags that are balanced by `balance_tags()`.
+const BALANCED_TAGS: &[&str] = &["i", "b", "u", "s", "font"];
+
+/// Closes unclosed tags at the end of the text and removes closing tags without an opening tag.
+/// Returns `None` if the tags are already balanced.
+fn balance_tags(text: &str) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut changed = false;
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let (before, tag_start) = rest.split_at(start);
+        result.push_str(before);
+        let end = match tag_start.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let (tag, after) = tag_start.split_at(end + 1);
+        rest = after;
+
+        let inner = tag.trim_start_matches('<').trim_end_matches('>').trim();
+        let (closing, name) = match inner.strip_prefix('/') {
+            Some(name) => (true, name.trim().to_ascii_lowercase()),
+            None => (false, inner.split_whitespace().next().unwrap_or("").to_ascii_lowercase()),
+        };
+        if !BALANCED_TAGS.contains(&name.as_str()) {
+            result.push_str(tag);
+        } else if !closing {
+            open.push(name);
+            result.push_str(tag);
+        } else if let Some(pos) = open.iter().rposition(|n| *n == name) {
+            // close the tags which were opened inside of this one first
+            for inner_name in open.drain(pos..).skip(1).rev() {
+                result.push_str(&format!("</{}>", inner_name));
+                changed = true;
+            }
+            result.push_str(tag);
+        } else {
+            changed = true;
+        }
+    }
+    result.push_str(rest);
+
+    for name in open.iter().rev() {
+        result.push_str(&format!("</{}>", name));
+        changed = true;
+    }
+    if changed {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repair_embedded_payloads() {
+        let messages = |input: &str| -> (Vec<String>, usize) {
+            let (file, report) = repair(input.as_bytes(), &RepairOptions::default()).unwrap();
+            let messages = report.actions.iter().skip(2).map(|a| a.to_string()).collect();
+            (messages, file.get_subtitle_entries().unwrap().len())
+        };
+
+        let m3u8 = "#EXTM3U\n#EXT-X-TARGETDURATION:10\nWEBVTT\nX-TIMESTAMP-MAP=LOCAL:00:00:00.000,MPEGTS:900000\n\n00:01.000 --> 00:02.000\nHi\n";
+        assert_eq!(messages(m3u8), (vec!["skipped 2 lines before the subtitles".to_string()], 1));
+
+        let ssa = "garbage from the muxer\n[Script Info]\nTitle: x\n\n[Events]\nFormat: Start, End, Text\nDialogue: 0:00:01.00,0:00:02.00,Hi\n";
+        assert_eq!(messages(ssa), (vec!["skipped 1 lines before the subtitles".to_string()], 1));
+
+        let srt = "1\n00:00:01,000 --> 00:00:02,000\nHi\n\n2\n00:00:03,000 --> 00:00:04,000\nthere\n\n";
+        let (messages, entries) = messages(&srt.repeat(3));
+        assert_eq!((messages, entries), (vec!["line 8: skipped 16 lines after the subtitles".to_string()], 2));
+    }
+
+    #[test]
+    fn repair_broken_srt() {
+        let input = "\u{FEFF}5\r\n00:00:03.5 --> 00:00:05,000\r\n<i>second\r\n\r\ngarbage\r\n\r\n1\r\n0:0:1,000 --> 00:00:04,000\r\nfirst</b>\r\n\r\n2\r\n00:00:07,000 --> 00:00:06,000\r\nthird\r\n";
+        let (file, report) = repair(input.as_bytes(), &RepairOptions::default()).unwrap();
+        let data = String::from_utf8(file.to_data().unwrap()).unwrap();
+        assert_eq!(
+            data,
+            "1\n00:00:01,000 --> 00:00:03,500\nfirst\n\n2\n00:00:03,500 --> 00:00:05,000\n<i>second</i>\n\n3\n00:00:07,000 --> 00:00:08,000\nthird\n\n"
+        );
+
+        let messages: Vec<String> = report.actions.iter().map(|a| a.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "decoded as UTF-8",
+                "detected format .srt (SubRip)",
+                "line 4: dropped 'garbage'",
+                "renumbered the entries",
+                "sorted the entries by start time",
+                "entry 2: fixed the end before the start",
+                "entry 0: shortened to end before the next entry",
+                "entry 0: balanced the formatting tags",
+                "entry 1: balanced the formatting tags",
+            ]
+        );
+    }
+}
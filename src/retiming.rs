@@ -0,0 +1,255 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Reusable strategies that change the timing of subtitles.
+//!
+//! A `Retiming` changes the timespans of a list of entries, `Retiming::retime_file()` applies it to a file and
+//! `SubtitleFile::preview_transform()` returns the changed entries without changing the file (for example for a
+//! live preview while a sync slider is dragged). `TimeTransform` shifts and scales the times,
+//! `remap::TimeRemap` maps the times to another cut of the video and `WordSnapping` moves the cue boundaries of
+//! speech recognizer output to the pauses between words.
+
+use crate::alignment::TimedWord;
+use crate::errors::Result as SubtitleParserResult;
+use crate::remap::TimeRemap;
+use crate::timetypes::{TimeDelta, TimePoint};
+use crate::{SubtitleEntry, SubtitleFile};
+
+/// A strategy that changes the timespans of subtitles.
+pub trait Retiming {
+    /// Changes the timespans of the entries.
+    fn retime(&self, entries: &mut [SubtitleEntry]);
+
+    /// Changes the timespans of all entries in a subtitle file.
+    fn retime_file(&self, file: &mut SubtitleFile) -> SubtitleParserResult<()> {
+        let mut entries = file.get_subtitle_entries()?;
+        self.retime(&mut entries);
+        file.update_subtitle_entries(&entries)
+    }
+}
+
+impl Retiming for TimeRemap {
+    fn retime(&self, entries: &mut [SubtitleEntry]) {
+        self.apply(entries);
+    }
+}
+
+/// A linear change of all times: every time `t` becomes `t * scale + shift` (rounded to milliseconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeTransform {
+    /// The factor for the times (`1.0` keeps the speed).
+    pub scale: f64,
+
+    /// The delay which is added after scaling.
+    pub shift: TimeDelta,
+}
+
+impl Default for TimeTransform {
+    fn default() -> TimeTransform {
+        TimeTransform::shift(TimeDelta::from_msecs(0))
+    }
+}
+
+impl TimeTransform {
+    /// Moves all times by `delta`.
+    pub fn shift(delta: TimeDelta) -> TimeTransform {
+        TimeTransform { scale: 1.0, shift: delta }
+    }
+
+    /// Multiplies all times by `factor`.
+    pub fn scale(factor: f64) -> TimeTransform {
+        TimeTransform {
+            scale: factor,
+            shift: TimeDelta::from_msecs(0),
+        }
+    }
+
+    /// Changes subtitles for a video with `from` frames per second to a video with `to` frames per second.
+    pub fn change_fps(from: f64, to: f64) -> TimeTransform {
+        TimeTransform::scale(from / to)
+    }
+
+    /// Returns the transform which maps `a.0` to `a.1` and `b.0` to `b.1` (two sync points) and interpolates
+    /// linearly between them (`None` if both sync points are at the same time).
+    pub fn from_sync_points(a: (TimePoint, TimePoint), b: (TimePoint, TimePoint)) -> Option<TimeTransform> {
+        let source_len = (b.0 - a.0).msecs();
+        if source_len == 0 {
+            return None;
+        }
+        let scale = (b.1 - a.1).msecs() as f64 / source_len as f64;
+        let shift = a.1.msecs() as f64 - a.0.msecs() as f64 * scale;
+        Some(TimeTransform {
+            scale,
+            shift: TimeDelta::from_msecs(shift.round() as i64),
+        })
+    }
+
+    /// Returns the transform which applies this transform and then `next`.
+    pub fn then(self, next: TimeTransform) -> TimeTransform {
+        TimeTransform {
+            scale: self.scale * next.scale,
+            shift: TimeDelta::from_msecs((self.shift.msecs() as f64 * next.scale).round() as i64) + next.shift,
+        }
+    }
+
+    /// Returns the changed time.
+    pub fn apply(&self, t: TimePoint) -> TimePoint {
+        let scaled = if self.scale == 1.0 {
+            t
+        } else {
+            TimePoint::from_msecs((t.msecs() as f64 * self.scale).round() as i64)
+        };
+        scaled + self.shift
+    }
+}
+
+impl Retiming for TimeTransform {
+    fn retime(&self, entries: &mut [SubtitleEntry]) {
+        for entry in entries {
+            entry.timespan.start = self.apply(entry.timespan.start);
+            entry.timespan.end = self.apply(entry.timespan.end);
+        }
+    }
+}
+
+/// Moves cue boundaries out of words into the closest pause between words.
+///
+/// Subtitles created by speech recognizers are often split by length, so a cue starts or ends while a word is
+/// still spoken. Every boundary inside a word is moved to the edge of the nearest pause (preferring the longer
+/// pause if two are equally near), so the next cue starts exactly where the previous one ends. Words with a
+/// confidence below `min_confidence` are treated like silence, because their timing is often wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordSnapping {
+    /// The recognized words (see `alignment::parse_whisper_words()`).
+    pub words: Vec<TimedWord>,
+
+    /// Boundaries are only moved if the nearest pause is at most this far away.
+    pub max_shift: TimeDelta,
+
+    /// Words with a lower confidence are ignored.
+    pub min_confidence: f64,
+
+    /// Shorter gaps between words are not pauses.
+    pub min_pause: TimeDelta,
+}
+
+impl WordSnapping {
+    /// Snaps to the given words with a maximum shift of half a second.
+    pub fn new(words: Vec<TimedWord>) -> WordSnapping {
+        WordSnapping {
+            words,
+            max_shift: TimeDelta::from_msecs(500),
+            min_confidence: 0.5,
+            min_pause: TimeDelta::from_msecs(0),
+        }
+    }
+
+    /// Returns the pauses between the reliable words (`None` for the time before the first and after the last word).
+    fn pauses(&self) -> Vec<(Option<TimePoint>, Option<TimePoint>)> {
+        let mut words: Vec<&TimedWord> = self.words.iter().filter(|word| word.confidence >= self.min_confidence).collect();
+        words.sort_by_key(|word| word.timespan.start);
+
+        let mut result = Vec::new();
+        let mut last_end = None;
+        for word in words {
+            let pause_end = word.timespan.start.max(last_end.unwrap_or(word.timespan.start));
+            if last_end.map_or(true, |end| pause_end - end >= self.min_pause) {
+                result.push((last_end, Some(pause_end)));
+            }
+            last_end = Some(last_end.map_or(word.timespan.end, |end: TimePoint| end.max(word.timespan.end)));
+        }
+        result.push((last_end, None));
+        result
+    }
+
+    /// Returns the boundary moved into the nearest pause (or `None` if it would move too far).
+    fn snap(&self, pauses: &[(Option<TimePoint>, Option<TimePoint>)], t: TimePoint) -> Option<TimePoint> {
+        let snapped = |&(start, end): &(Option<TimePoint>, Option<TimePoint>)| {
+            let t = start.map_or(t, |start| t.max(start));
+            end.map_or(t, |end| t.min(end))
+        };
+        let pause_len = |&(start, end): &(Option<TimePoint>, Option<TimePoint>)| match (start, end) {
+            (Some(start), Some(end)) => end - start,
+            _ => TimeDelta::from_msecs(i64::MAX),
+        };
+        pauses
+            .iter()
+            .map(|pause| (snapped(pause), pause_len(pause)))
+            .filter(|&(snapped, _)| (snapped - t).abs() <= self.max_shift)
+            .min_by_key(|&(snapped, len)| ((snapped - t).abs(), -len))
+            .map(|(snapped, _)| snapped)
+    }
+}
+
+impl Retiming for WordSnapping {
+    fn retime(&self, entries: &mut [SubtitleEntry]) {
+        let pauses = self.pauses();
+        for entry in entries {
+            let start = self.snap(&pauses, entry.timespan.start).unwrap_or(entry.timespan.start);
+            let end = self.snap(&pauses, entry.timespan.end).unwrap_or(entry.timespan.end);
+            if start < end {
+                entry.timespan.start = start;
+                entry.timespan.end = end;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alignment::parse_whisper_words;
+    use crate::timetypes::TimeSpan;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn preview_time_transform() {
+        let srt = "1\n00:00:10,000 --> 00:00:12,000\nHello\n\n2\n00:00:20,000 --> 00:00:21,000\nworld\n";
+        let file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let times =
+            |entries: Vec<SubtitleEntry>| -> Vec<(i64, i64)> { entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect() };
+
+        let transform = TimeTransform::change_fps(25.0, 50.0).then(TimeTransform::shift(TimeDelta::from_msecs(500)));
+        assert_eq!(times(file.preview_transform(&transform).unwrap()), vec![(5500, 6500), (10500, 11000)]);
+        assert_eq!(times(file.get_subtitle_entries().unwrap()), vec![(10000, 12000), (20000, 21000)]);
+
+        let t = TimePoint::from_msecs;
+        let synced = TimeTransform::from_sync_points((t(10000), t(11000)), (t(20000), t(22000))).unwrap();
+        assert_eq!(times(file.preview_transform(&synced).unwrap()), vec![(11000, 13200), (22000, 23100)]);
+        assert_eq!(TimeTransform::from_sync_points((t(0), t(1)), (t(0), t(2))), None);
+    }
+
+    #[test]
+    fn snap_to_word_gaps() {
+        let json = r#"{"segments": [
+            {"words": [{"word": " Hello", "start": 0.0, "end": 0.4, "probability": 0.9},
+                       {"word": " world.", "start": 0.45, "end": 0.9, "probability": 0.95}]},
+            {"words": [{"word": " This", "start": 1.5, "end": 1.8, "probability": 0.8},
+                       {"word": " is", "start": 1.85, "end": 2.0, "probability": 0.2},
+                       {"word": " fine.", "start": 2.1, "end": 2.6, "probability": 0.9},
+                       {"word": " [noise]"}]}
+        ]}"#;
+        let words = parse_whisper_words(json).unwrap();
+        assert_eq!(words.len(), 5);
+        assert_eq!(words[1].text, "world.");
+
+        let span = |start, end| TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end));
+        let mut entries = vec![
+            SubtitleEntry::new(span(100, 1650), "Hello world. Th-".to_string()),
+            SubtitleEntry::new(span(1650, 2500), "-is is fine.".to_string()),
+            SubtitleEntry::new(span(2900, 3500), "(silence)".to_string()),
+        ];
+        WordSnapping::new(words.clone()).retime(&mut entries);
+        let spans: Vec<(i64, i64)> = entries.iter().map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs())).collect();
+        // the boundary in "This" moves to the longer pause before it, the end of "fine." is moved after the word
+        assert_eq!(spans, vec![(0, 1500), (1500, 2600), (2900, 3500)]);
+
+        // boundaries that are too far from a pause stay
+        let mut snapping = WordSnapping::new(words);
+        snapping.max_shift = TimeDelta::from_msecs(50);
+        let mut entries = vec![SubtitleEntry::new(span(1650, 2500), "x".to_string())];
+        snapping.retime(&mut entries);
+        assert_eq!(entries[0].timespan, span(1650, 2500));
+    }
+}
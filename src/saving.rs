@@ -0,0 +1,323 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Safe in-place updates of subtitle files.
+//!
+//! `SubtitleFile::save_to()` writes the data into a new temporary file with a random name next to the target and
+//! renames it over the target afterwards, so the file is never left half-written. If the target already exists, the
+//! new data is written in its character encoding and with its BOM and line endings (the file itself only knows the
+//! UTF-8 text). XML files are written in the encoding of their `<?xml ... encoding="..."?>` declaration:
+//!
+//! ```no_run
+//! use subparse::saving::SaveOptions;
+//! use subparse::{parse_bytes, SubtitleFormat};
+//! use std::path::Path;
+//!
+//! let path = Path::new("movie.srt");
+//! let mut file = parse_bytes(SubtitleFormat::SubRip, &std::fs::read(path).unwrap(), None, 25.0).unwrap();
+//! file.drop_entries_in(subparse::timetypes::TimeSpan::new(
+//!     subparse::timetypes::TimePoint::from_msecs(0),
+//!     subparse::timetypes::TimePoint::from_msecs(5000),
+//! ))
+//! .unwrap();
+//! file.save_to_with_options(path, &SaveOptions::with_backup("~")).unwrap();
+//! ```
+
+use crate::errors::{ErrorKind, Result};
+use crate::formats::common::split_bom;
+use crate::formats::detect_encoding;
+use crate::SubtitleFile;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use failure::ResultExt;
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// How often `create_temp_file()` tries another name if the file already exists.
+const TEMP_FILE_ATTEMPTS: u32 = 100;
+
+/// Options for `SubtitleFile::save_to_with_options()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// Keep a copy of the replaced file with this suffix appended to its name (like `"~"` or `".bak"`).
+    pub backup_suffix: Option<String>,
+
+    /// The character encoding of text formats (`None` keeps the encoding of the replaced file, new files are UTF-8).
+    pub encoding: Option<&'static Encoding>,
+
+    /// Write the data as it is, instead of using the BOM and line endings of the replaced file.
+    pub keep_layout: bool,
+}
+
+impl SaveOptions {
+    /// The default options, but with a backup of the replaced file.
+    pub fn with_backup(suffix: &str) -> SaveOptions {
+        SaveOptions {
+            backup_suffix: Some(suffix.to_string()),
+            ..SaveOptions::default()
+        }
+    }
+}
+
+/// The encoding, BOM and line ending of an existing text file.
+struct TextLayout {
+    encoding: &'static Encoding,
+    bom: bool,
+    crlf: Option<bool>,
+}
+
+impl TextLayout {
+    fn of(data: &[u8]) -> TextLayout {
+        let (encoding, bom) = match Encoding::for_bom(data) {
+            Some((encoding, _)) => (encoding, true),
+            None => match xml_declaration_encoding(data) {
+                Some(encoding) => (encoding, false),
+                None if std::str::from_utf8(data).is_ok() => (UTF_8, false),
+                None => (detect_encoding(data).unwrap_or(UTF_8), false),
+            },
+        };
+        let (text, _, _) = encoding.decode(data);
+        let crlf = text.find('\n').map(|pos| text.get(..pos).is_some_and(|line| line.ends_with('\r')));
+        TextLayout { encoding, bom, crlf }
+    }
+}
+
+/// Returns the encoding of an XML declaration like `<?xml version="1.0" encoding="ISO-8859-1"?>` (TTML, iTunes Timed
+/// Text, ... files without BOM).
+fn xml_declaration_encoding(data: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(data.get(..data.len().min(256))?);
+    let declaration = head.strip_prefix("<?xml")?;
+    let declaration = declaration.get(..declaration.find("?>")?)?;
+    let (_, value) = declaration.split_once("encoding")?;
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let label = value.get(1..)?.split(quote).next()?;
+    // the declaration of a UTF-16 file without BOM can't be read as ASCII
+    Encoding::for_label(label.trim().as_bytes()).filter(|&encoding| encoding != UTF_16LE && encoding != UTF_16BE)
+}
+
+/// Converts all line endings of the text to `\r\n` or `\n`.
+fn convert_line_endings(text: &str, crlf: bool) -> String {
+    let lf = text.replace("\r\n", "\n");
+    if crlf {
+        lf.replace('\n', "\r\n")
+    } else {
+        lf
+    }
+}
+
+/// Encodes the text (`encoding_rs` only decodes UTF-16, so it is encoded here).
+fn encode(text: &str, encoding: &'static Encoding) -> Result<Vec<u8>> {
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        return Ok(text
+            .encode_utf16()
+            .flat_map(|unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect());
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        return Err(ErrorKind::EncodingError.into());
+    }
+    Ok(bytes.into_owned())
+}
+
+/// Creates a new temporary file next to `path`, with a random name so that concurrent writers never share a file.
+fn create_temp_file(path: &Path) -> Result<(PathBuf, fs::File)> {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let mut attempt = 0;
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        hasher.write_u32(attempt);
+        let temp_path = path.with_file_name(format!(".{}.{:016x}.tmp", name, hasher.finish()));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < TEMP_FILE_ATTEMPTS => attempt += 1,
+            Err(e) => return Err(e).context(ErrorKind::WritingFileError)?,
+        }
+    }
+}
+
+/// Returns the path with a suffix appended to the file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+impl SubtitleFile {
+    /// Writes the file to `path` with the default `SaveOptions` (see the module documentation).
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        self.save_to_with_options(path, &SaveOptions::default())
+    }
+
+    /// Writes the file to `path` by replacing it atomically (see the module documentation).
+    pub fn save_to_with_options(&self, path: &Path, options: &SaveOptions) -> Result<()> {
+        let data = self.to_data()?;
+        let old = match fs::read(path) {
+            Ok(old) => Some(old),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).context(ErrorKind::WritingFileError)?,
+        };
+
+        let binary = matches!(
+            *self,
+            SubtitleFile::VobSubSubFile(_)
+                | SubtitleFile::StlFile(_)
+                | SubtitleFile::PacFile(_)
+                | SubtitleFile::PgsFile(_)
+                | SubtitleFile::DvbFile(_)
+        );
+        let data = match std::str::from_utf8(&data) {
+            Ok(text) if !binary => {
+                let layout = old.as_ref().map(|old| TextLayout::of(old));
+                // the XML declaration of the new data has to match its encoding
+                let encoding = options
+                    .encoding
+                    .or_else(|| xml_declaration_encoding(text.as_bytes()))
+                    .or_else(|| layout.as_ref().map(|layout| layout.encoding))
+                    .unwrap_or(UTF_8);
+                let (bom, text) = split_bom(text);
+                let mut text = text.to_string();
+                let mut bom = !bom.is_empty();
+                if let (Some(layout), false) = (&layout, options.keep_layout) {
+                    bom = layout.bom;
+                    if let Some(crlf) = layout.crlf {
+                        text = convert_line_endings(&text, crlf);
+                    }
+                }
+                // a BOM can only be written in the Unicode encodings
+                if bom && (encoding == UTF_8 || encoding == UTF_16LE || encoding == UTF_16BE) {
+                    text.insert(0, '\u{feff}');
+                }
+                encode(&text, encoding)?
+            }
+            _ => data,
+        };
+
+        // the temporary file is in the same directory, so renaming it doesn't move data between file systems
+        let (temp_path, temp) = create_temp_file(path)?;
+        let result = write_replacing(path, &temp_path, temp, &data, old.is_some(), options);
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+/// Writes the data to the temporary file, makes the backup and renames the temporary file to `path`.
+fn write_replacing(path: &Path, temp_path: &Path, mut temp: fs::File, data: &[u8], exists: bool, options: &SaveOptions) -> Result<()> {
+    temp.write_all(data).context(ErrorKind::WritingFileError)?;
+    temp.sync_all().context(ErrorKind::WritingFileError)?;
+    drop(temp);
+
+    if exists {
+        let permissions = fs::metadata(path).context(ErrorKind::WritingFileError)?.permissions();
+        fs::set_permissions(temp_path, permissions).context(ErrorKind::WritingFileError)?;
+        if let Some(ref suffix) = options.backup_suffix {
+            fs::copy(path, with_suffix(path, suffix)).context(ErrorKind::WritingFileError)?;
+        }
+    }
+    fs::rename(temp_path, path).context(ErrorKind::WritingFileError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::{TimePoint, TimeSpan};
+    use crate::{parse_bytes, SrtFile, SubtitleFormat};
+
+    #[test]
+    fn save_preserves_layout() {
+        let dir = std::env::temp_dir().join(format!("subparse-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("movie.srt");
+
+        let old_text = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\nGrüße\r\n";
+        fs::write(&path, encode(old_text, UTF_16LE).unwrap()).unwrap();
+        let old = parse_bytes(SubtitleFormat::SubRip, &fs::read(&path).unwrap(), None, 25.0).unwrap();
+        assert_eq!(old.get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Grüße"));
+
+        let span = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(3000));
+        let new: SubtitleFile = SrtFile::create(vec![(span, "Hallo\nWelt".to_string())]).unwrap().into();
+        new.save_to_with_options(&path, &SaveOptions::with_backup(".bak")).unwrap();
+
+        let written = fs::read(&path).unwrap();
+        let expected = "\u{feff}1\r\n00:00:01,000 --> 00:00:03,000\r\nHallo\r\nWelt\r\n\r\n";
+        assert_eq!(written, encode(expected, UTF_16LE).unwrap());
+        assert_eq!(fs::read(dir.join("movie.srt.bak")).unwrap(), encode(old_text, UTF_16LE).unwrap());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        let options = SaveOptions {
+            encoding: Some(encoding_rs::WINDOWS_1252),
+            keep_layout: true,
+            ..SaveOptions::default()
+        };
+        old.save_to_with_options(&path, &options).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"1\n00:00:01,000 --> 00:00:02,000\nGr\xfc\xdfe\n\n".to_vec());
+        let options = SaveOptions {
+            encoding: Some(encoding_rs::ISO_8859_5),
+            ..SaveOptions::default()
+        };
+        assert_eq!(old.save_to_with_options(&path, &options).unwrap_err().kind(), ErrorKind::EncodingError);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn temp_files_are_new() {
+        let dir = std::env::temp_dir().join(format!("subparse-temp-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("movie.srt");
+        let (first, _) = create_temp_file(&path).unwrap();
+        let (second, _) = create_temp_file(&path).unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn layout_of_xml_declaration() {
+        let latin1 = TextLayout::of(b"<?xml version=\"1.0\" encoding='ISO-8859-1'?>\n<tt>Gr\xfc\xdfe</tt>\n");
+        assert_eq!(
+            (latin1.encoding, latin1.bom, latin1.crlf),
+            (encoding_rs::WINDOWS_1252, false, Some(false))
+        );
+        assert_eq!(TextLayout::of(b"<?xml version=\"1.0\"?>\n<tt/>").encoding, UTF_8);
+        assert_eq!(TextLayout::of(b"<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n<tt/>").encoding, UTF_8);
+    }
+
+    #[test]
+    fn save_in_declared_encoding() {
+        let dir = std::env::temp_dir().join(format!("subparse-xml-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("movie.ttml");
+        let old = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\"><body><div>\
+                   <p begin=\"00:00:01.000\" end=\"00:00:02.000\">Hello</p></div></body></tt>\n";
+        fs::write(&path, old).unwrap();
+
+        let mut file = parse_bytes(SubtitleFormat::Ttml, &fs::read(&path).unwrap(), None, 25.0).unwrap();
+        let mut entries = file.get_subtitle_entries().unwrap();
+        entries[0].line = Some("Grüße".to_string());
+        file.update_subtitle_entries(&entries).unwrap();
+        file.save_to(&path).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            old.replace("Hello", "Gr\u{fc}\u{df}e").chars().map(|c| c as u8).collect::<Vec<u8>>()
+        );
+
+        // the declaration of new data wins over the encoding of the replaced file
+        let span = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(3000));
+        SubtitleFile::from(crate::TtmlFile::create(vec![(span, "Grüße".to_string())]).unwrap())
+            .save_to(&path)
+            .unwrap();
+        assert!(String::from_utf8(fs::read(&path).unwrap()).unwrap().contains("Grüße"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Approximate search for phrases in subtitle texts.
+//!
+//! Texts are compared without tags, punctuation and case, and small differences (typos, a different translation
+//! of a word) are allowed by the tolerance, so a quote from a transcript or a script can be found even if the
+//! subtitles are slightly different:
+//!
+//! ```
+//! # let file = subparse::parse_str(subparse::SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\n<i>Where are</i>\nyou going?!\n", 25.0).unwrap();
+//! let matches = file.find_text("where are you goin", 0.1).unwrap();
+//! assert_eq!(matches[0].entry_idx, 0);
+//! ```
+
+use crate::errors::Result;
+use crate::music::tag_mask;
+use crate::timetypes::TimeSpan;
+use crate::{SubtitleEntry, SubtitleFile};
+
+/// An entry which contains the searched text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextMatch {
+    /// The position of the entry in `get_subtitle_entries()`.
+    pub entry_idx: usize,
+
+    /// The timing of the entry.
+    pub timespan: TimeSpan,
+
+    /// How well the text matches, from `0.0` (the allowed maximum of differences) to `1.0` (exact match).
+    pub score: f64,
+}
+
+/// Returns the text in lowercase without tags, punctuation and line breaks (`\N` in `.ssa` files) and with
+/// single spaces between words (apostrophes are removed, so "I'll" becomes "ill").
+fn normalize(text: &str) -> Vec<char> {
+    let text = text.replace("\\N", " ").replace("\\n", " ").replace("\\h", " ");
+    let visible: String = text.chars().zip(tag_mask(&text)).filter(|&(_, is_tag)| !is_tag).map(|(c, _)| c).collect();
+    let words: String = visible
+        .to_lowercase()
+        .chars()
+        .filter(|&c| c != '\'' && c != '’')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    words.split_whitespace().collect::<Vec<&str>>().join(" ").chars().collect()
+}
+
+/// Returns the smallest edit distance between `pattern` and any substring of `text`.
+fn substring_distance(pattern: &[char], text: &[char]) -> usize {
+    // the first row is zero, because a match can start anywhere in the text
+    let mut row = vec![0; text.len() + 1];
+    for (i, p) in pattern.iter().enumerate() {
+        let mut diagonal = i;
+        let mut next = Vec::with_capacity(row.len());
+        next.push(i + 1);
+        for (j, t) in text.iter().enumerate() {
+            let substitution = diagonal + usize::from(p != t);
+            let above = row.get(j + 1).map_or(usize::MAX, |d| d + 1);
+            let left = next.last().map_or(usize::MAX, |d| d + 1);
+            diagonal = row.get(j + 1).copied().unwrap_or(usize::MAX);
+            next.push(substitution.min(above).min(left));
+        }
+        row = next;
+    }
+    row.into_iter().min().unwrap_or(pattern.len())
+}
+
+/// Returns all entries that contain `query` with at most `tolerance` differences per character (`0.2` allows one
+/// typo in five characters), best matches first.
+///
+/// Case, punctuation and tags are ignored.
+pub fn find_text(entries: &[SubtitleEntry], query: &str, tolerance: f64) -> Vec<TextMatch> {
+    let pattern = normalize(query);
+    if pattern.is_empty() {
+        return Vec::new();
+    }
+    let max_distance = (pattern.len() as f64 * tolerance.max(0.0)).floor();
+
+    let mut result: Vec<TextMatch> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(entry_idx, entry)| {
+            let distance = substring_distance(&pattern, &normalize(entry.line.as_ref()?)) as f64;
+            let score = if max_distance > 0.0 {
+                1.0 - distance / (max_distance + 1.0)
+            } else {
+                1.0
+            };
+            Some(TextMatch {
+                entry_idx,
+                timespan: entry.timespan,
+                score,
+            })
+            .filter(|_| distance <= max_distance)
+        })
+        .collect();
+    result.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.entry_idx.cmp(&b.entry_idx)));
+    result
+}
+
+impl SubtitleFile {
+    /// Returns all entries that contain `query` (see `search::find_text()`), best matches first.
+    pub fn find_text(&self, query: &str, tolerance: f64) -> Result<Vec<TextMatch>> {
+        Ok(find_text(&self.get_subtitle_entries()?, query, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn fuzzy_text_search() {
+        let ssa = "[Events]\nFormat: Layer, Start, End, Style, Text\n\
+                   Dialogue: 0,0:00:01.00,0:00:02.00,Default,{\\i1}I'll be back,\\Nbaby.\n\
+                   Dialogue: 0,0:00:03.00,0:00:04.00,Default,I'll be bock!\n\
+                   Dialogue: 0,0:00:05.00,0:00:06.00,Default,Something else\n";
+        let file = crate::parse_str(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+
+        let exact = file.find_text("ill be back baby", 0.0).unwrap();
+        assert_eq!((exact.len(), exact[0].entry_idx, exact[0].score), (1, 0, 1.0));
+
+        let fuzzy = file.find_text("I'll be back", 0.2).unwrap();
+        let indices: Vec<usize> = fuzzy.iter().map(|m| m.entry_idx).collect();
+        assert_eq!(indices, vec![0, 1]);
+        assert!(fuzzy[0].score > fuzzy[1].score);
+        assert!(file.find_text("", 1.0).unwrap().is_empty());
+    }
+}
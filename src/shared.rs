@@ -0,0 +1,138 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Sharing a parsed file between threads.
+//!
+//! All file types are `Send + Sync`, so a `SharedSubtitleFile` can be handed to the decode, render and UI threads
+//! of a player. Cloning the handle only increments a reference count; a change through `make_mut()` copies the
+//! file once if other handles still exist, so the readers keep their consistent version:
+//!
+//! ```
+//! use subparse::shared::SharedSubtitleFile;
+//! use subparse::SubtitleFormat;
+//!
+//! let file = subparse::parse_str(SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nHi\n", 25.0).unwrap();
+//! let shared = SharedSubtitleFile::new(file);
+//!
+//! let reader = shared.clone();
+//! let count = std::thread::spawn(move || reader.get_subtitle_entries().unwrap().len()).join().unwrap();
+//! assert_eq!(count, 1);
+//! ```
+
+use crate::formats::SubtitleFile;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// A cheaply cloneable, thread-safe handle to a parsed file (see the module documentation).
+#[derive(Debug, Clone)]
+pub struct SharedSubtitleFile {
+    inner: Arc<SubtitleFile>,
+}
+
+impl SharedSubtitleFile {
+    /// Moves the file into a new handle.
+    pub fn new(file: SubtitleFile) -> SharedSubtitleFile {
+        SharedSubtitleFile { inner: Arc::new(file) }
+    }
+
+    /// Returns the file for changes, after copying it if other handles share it (copy-on-write).
+    pub fn make_mut(&mut self) -> &mut SubtitleFile {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// Returns the file (a copy if other handles still share it).
+    pub fn into_inner(self) -> SubtitleFile {
+        Arc::try_unwrap(self.inner).unwrap_or_else(|inner| (*inner).clone())
+    }
+
+    /// Returns `true` if both handles share the same file (no changes were made through one of them).
+    pub fn ptr_eq(&self, other: &SharedSubtitleFile) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// Returns the number of handles which share the file.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+}
+
+impl Deref for SharedSubtitleFile {
+    type Target = SubtitleFile;
+
+    fn deref(&self) -> &SubtitleFile {
+        &self.inner
+    }
+}
+
+impl From<SubtitleFile> for SharedSubtitleFile {
+    fn from(file: SubtitleFile) -> SharedSubtitleFile {
+        SharedSubtitleFile::new(file)
+    }
+}
+
+impl SubtitleFile {
+    /// Moves the file into a handle that can be shared between threads (see `shared::SharedSubtitleFile`).
+    pub fn into_shared(self) -> SharedSubtitleFile {
+        SharedSubtitleFile::new(self)
+    }
+}
+
+/// Fails to compile if one of the file types is not `Send + Sync` anymore.
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn check<T: Send + Sync>() {}
+    check::<SubtitleFile>();
+    check::<SharedSubtitleFile>();
+    check::<crate::SrtFile>();
+    check::<crate::SsaFile>();
+    check::<crate::IdxFile>();
+    check::<crate::VobFile>();
+    check::<crate::MdvdFile>();
+    check::<crate::VttFile>();
+    check::<crate::TtmlFile>();
+    check::<crate::SbvFile>();
+    check::<crate::LrcFile>();
+    check::<crate::SccFile>();
+    check::<crate::StlFile>();
+    check::<crate::PacFile>();
+    check::<crate::Mpl2File>();
+    check::<crate::PgsFile>();
+    check::<crate::JacoFile>();
+    check::<crate::SubViewerFile>();
+    check::<crate::RealTextFile>();
+    check::<crate::IttFile>();
+    check::<crate::BdnFile>();
+    check::<crate::SpruceStlFile>();
+    check::<crate::AqTitleFile>();
+    check::<crate::VPlayerFile>();
+    check::<crate::PowerDivXFile>();
+    check::<crate::Srv3File>();
+    check::<crate::SamiFile>();
+    check::<crate::DvbFile>();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn shared_file_copy_on_write() {
+        let file = crate::parse_str(SubtitleFormat::SubRip, "1\n00:00:01,000 --> 00:00:02,000\nHi\n", 25.0).unwrap();
+        let mut writer = file.into_shared();
+        let reader = writer.clone();
+        assert!(writer.ptr_eq(&reader));
+        assert_eq!(reader.handle_count(), 2);
+
+        let mut entries = writer.get_subtitle_entries().unwrap();
+        entries[0].line = Some("Changed".to_string());
+        writer.make_mut().update_subtitle_entries(&entries).unwrap();
+
+        // the reader keeps the old version, the writer now owns its copy
+        assert!(!writer.ptr_eq(&reader));
+        let handle = std::thread::spawn(move || reader.get_subtitle_entries().unwrap()[0].line.clone());
+        assert_eq!(handle.join().unwrap().as_deref(), Some("Hi"));
+        assert_eq!(writer.handle_count(), 1);
+        assert_eq!(writer.into_inner().get_subtitle_entries().unwrap()[0].line.as_deref(), Some("Changed"));
+    }
+}
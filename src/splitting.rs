@@ -0,0 +1,321 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Splitting of subtitles that are shown for too long, and re-segmentation of subtitles into sentences.
+//!
+//! Transcripts often have one cue per paragraph or chapter, which is unreadable as subtitle.
+//! `SubtitleFile::split_long_entries()` splits these cues at sentence boundaries and distributes the time by
+//! the length of the parts.
+//!
+//! Speech recognition output has the opposite problem: cues start and end in the middle of sentences.
+//! `SubtitleFile::resegment()` joins all texts, splits them into sentences and builds new cues which respect the
+//! limits of a `SegmentationLimits`.
+
+use crate::errors::{ErrorKind, Result};
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::SubtitleFile;
+use std::iter::once;
+
+/// Returns `true` for characters which end a sentence.
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+}
+
+/// Returns `true` if the word is the last word of a sentence (ignoring closing quotes and parentheses).
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', '»', '”'])
+        .chars()
+        .last()
+        .is_some_and(is_sentence_end)
+}
+
+/// Splits a text into sentences (or into words if there are fewer sentences than `pieces`).
+fn split_units(text: &str, pieces: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut sentences: Vec<String> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for word in &words {
+        current.push(word);
+        if ends_sentence(word) {
+            sentences.push(current.join(" "));
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        sentences.push(current.join(" "));
+    }
+
+    if sentences.len() >= pieces {
+        sentences
+    } else {
+        words.into_iter().map(str::to_string).collect()
+    }
+}
+
+/// Splits `text` into (at most) `pieces` parts of similar length.
+fn split_text(text: &str, pieces: usize) -> Vec<String> {
+    let units = split_units(text, pieces);
+    let total: usize = units.iter().map(|unit| unit.chars().count() + 1).sum();
+
+    let mut result: Vec<String> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut done = 0;
+    for unit in units {
+        done += unit.chars().count() + 1;
+        current.push(unit);
+        // a part ends as soon as it reaches its share of the whole text
+        if done * pieces >= total * (result.len() + 1) {
+            result.push(current.join(" "));
+            current.clear();
+        }
+    }
+    if !current.is_empty() {
+        result.push(current.join(" "));
+    }
+    result
+}
+
+/// Distributes `timespan` over the parts proportionally to their length.
+fn split_timespan(timespan: TimeSpan, parts: &[String]) -> Vec<TimeSpan> {
+    let lengths: Vec<i64> = parts.iter().map(|part| part.chars().count().max(1) as i64).collect();
+    let total: i64 = lengths.iter().sum();
+    let duration = timespan.len().msecs();
+
+    let mut done = 0;
+    let mut start = timespan.start;
+    lengths
+        .iter()
+        .map(|length| {
+            done += length;
+            let end = timespan.start + TimeDelta::from_msecs(duration * done / total.max(1));
+            let part = TimeSpan::new(start, if done == total { timespan.end } else { end });
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// The limits for the cues created by `SubtitleFile::resegment()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentationLimits {
+    /// The maximum number of characters per second (cues are extended into the following gap if they are too short).
+    pub max_cps: f64,
+
+    /// The maximum number of characters of a line.
+    pub max_line_len: usize,
+
+    /// The maximum number of lines of a cue.
+    pub max_lines: usize,
+}
+
+impl Default for SegmentationLimits {
+    fn default() -> SegmentationLimits {
+        SegmentationLimits {
+            max_cps: 17.0,
+            max_line_len: 42,
+            max_lines: 2,
+        }
+    }
+}
+
+/// Wraps the words into lines of at most `max_line_len` characters (longer words get their own line).
+fn wrap_words(words: &[&str], max_line_len: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= max_line_len => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Builds the cues of a single sentence from its timed words.
+fn segment_sentence(words: &[(TimeSpan, &str)], limits: &SegmentationLimits, result: &mut Vec<(TimeSpan, String)>) {
+    let mut current: Vec<(TimeSpan, &str)> = Vec::new();
+    let mut push_cue = |cue: &[(TimeSpan, &str)]| {
+        if let (Some(first), Some(last)) = (cue.first(), cue.last()) {
+            let texts: Vec<&str> = cue.iter().map(|&(_, word)| word).collect();
+            result.push((
+                TimeSpan::new(first.0.start, last.0.end),
+                wrap_words(&texts, limits.max_line_len).join("\n"),
+            ));
+        }
+    };
+    for &word in words {
+        current.push(word);
+        let texts: Vec<&str> = current.iter().map(|&(_, word)| word).collect();
+        if current.len() > 1 && wrap_words(&texts, limits.max_line_len).len() > limits.max_lines.max(1) {
+            current.pop();
+            push_cue(&current);
+            current = vec![word];
+        }
+    }
+    push_cue(&current);
+}
+
+impl SubtitleFile {
+    /// Splits every entry which is shown for longer than `max_duration` and returns the number of new entries.
+    ///
+    /// The text is split at sentence boundaries (or at word boundaries if there are not enough sentences) into
+    /// parts of similar length, and every part gets a share of the time that matches its length. Line breaks
+    /// inside the parts are replaced by spaces. Entries that are too long but consist of a single word stay
+    /// unchanged.
+    ///
+    /// This only works for `.srt` and MicroDVD files (other formats return `ErrorKind::InsertingEntriesNotSupported`).
+    pub fn split_long_entries(&mut self, max_duration: TimeDelta) -> Result<usize> {
+        if !self.can_insert_entries() {
+            return Err(ErrorKind::InsertingEntriesNotSupported.into());
+        }
+        let max_msecs = max_duration.msecs().max(1);
+
+        let mut entries = self.get_subtitle_entries()?;
+        let mut new_parts: Vec<(usize, Vec<(TimeSpan, String)>)> = Vec::new();
+        for (idx, entry) in entries.iter_mut().enumerate() {
+            let duration = entry.timespan.len().msecs();
+            let text = match entry.line {
+                Some(ref text) if duration > max_msecs => text,
+                _ => continue,
+            };
+            let pieces = ((duration + max_msecs - 1) / max_msecs) as usize;
+            let parts = split_text(text, pieces);
+            if parts.len() < 2 {
+                continue;
+            }
+
+            let mut timed_parts: Vec<(TimeSpan, String)> = split_timespan(entry.timespan, &parts).into_iter().zip(parts).collect();
+            let (first_timespan, first_text) = timed_parts.remove(0);
+            entry.timespan = first_timespan;
+            entry.line = Some(first_text);
+            new_parts.push((idx, timed_parts));
+        }
+        self.update_subtitle_entries(&entries)?;
+
+        // inserting from the back keeps the indices of the front valid
+        let mut count = 0;
+        for (idx, parts) in new_parts.into_iter().rev() {
+            for (offset, (timespan, text)) in parts.into_iter().enumerate() {
+                self.insert_entry(idx + 1 + offset, timespan, &text)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Rebuilds all entries so that every cue contains (a part of) a single sentence, and returns the new number
+    /// of entries.
+    ///
+    /// The texts of all entries are joined in the order of their start times and split into sentences. Every word
+    /// keeps the share of the time of its original entry that matches its length. Sentences which don't fit into
+    /// `limits.max_lines` lines of `limits.max_line_len` characters are split into multiple cues, and cues which
+    /// exceed `limits.max_cps` are extended into the gap before the next cue. Formatting of the texts is lost.
+    ///
+    /// This only works for `.srt` and MicroDVD files (other formats return `ErrorKind::InsertingEntriesNotSupported`).
+    pub fn resegment(&mut self, limits: &SegmentationLimits) -> Result<usize> {
+        if !self.can_insert_entries() {
+            return Err(ErrorKind::InsertingEntriesNotSupported.into());
+        }
+
+        let mut entries = self.get_subtitle_entries()?;
+        entries.sort_by_key(|entry| entry.timespan.start);
+        let texts: Vec<String> = entries.iter().map(|entry| entry.line.clone().unwrap_or_default()).collect();
+
+        // every word gets a share of the time of its entry
+        let mut words: Vec<(TimeSpan, &str)> = Vec::new();
+        for (entry, text) in entries.iter().zip(&texts) {
+            let entry_words: Vec<&str> = text.split_whitespace().collect();
+            let word_strings: Vec<String> = entry_words.iter().map(|word| word.to_string()).collect();
+            words.extend(split_timespan(entry.timespan, &word_strings).into_iter().zip(entry_words));
+        }
+
+        let mut cues: Vec<(TimeSpan, String)> = Vec::new();
+        let mut sentence_start = 0;
+        for (idx, &(_, word)) in words.iter().enumerate() {
+            if ends_sentence(word) {
+                segment_sentence(words.get(sentence_start..=idx).unwrap_or(&[]), limits, &mut cues);
+                sentence_start = idx + 1;
+            }
+        }
+        segment_sentence(words.get(sentence_start..).unwrap_or(&[]), limits, &mut cues);
+
+        // cues that are too fast to read are extended up to the next cue
+        let next_starts: Vec<Option<TimePoint>> = cues.iter().skip(1).map(|(timespan, _)| Some(timespan.start)).chain(once(None)).collect();
+        for ((timespan, text), next_start) in cues.iter_mut().zip(next_starts) {
+            let chars = text.chars().filter(|&c| c != '\n').count() as f64;
+            let needed = TimeDelta::from_msecs((chars / limits.max_cps.max(f64::MIN_POSITIVE) * 1000.0).ceil() as i64);
+            if timespan.len() < needed {
+                let end = timespan.start + needed;
+                timespan.end = next_start.map_or(end, |next_start| end.min(next_start).max(timespan.end));
+            }
+        }
+
+        let count = cues.len();
+        self.retain_entries_by_mask(&vec![false; entries.len()])?;
+        for (idx, (timespan, text)) in cues.into_iter().enumerate() {
+            self.insert_entry(idx, timespan, &text)?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SubtitleFormat;
+
+    #[test]
+    fn split_long_entries_at_sentences() {
+        let srt = "1\n00:00:00,000 --> 00:00:12,000\nThis is the first sentence. And a second one!\nThe end?\n\n\
+                   2\n00:00:20,000 --> 00:00:22,000\nshort\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        assert_eq!(file.split_long_entries(TimeDelta::from_secs(5)).unwrap(), 2);
+
+        let entries: Vec<(i64, i64, String)> = file
+            .get_subtitle_entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.line.unwrap()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (0, 6230, "This is the first sentence.".to_string()),
+                (6230, 10153, "And a second one!".to_string()),
+                (10153, 12000, "The end?".to_string()),
+                (20000, 22000, "short".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn resegment_into_sentences() {
+        let srt = "1\n00:00:00,000 --> 00:00:02,000\nso this is how\n\n2\n00:00:02,000 --> 00:00:04,000\nit works. and then\n\n\
+                   3\n00:00:05,000 --> 00:00:06,000\nwe continue with a longer sentence\n";
+        let mut file = crate::parse_str(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let limits = SegmentationLimits {
+            max_line_len: 16,
+            ..SegmentationLimits::default()
+        };
+        assert_eq!(file.resegment(&limits).unwrap(), 3);
+
+        let entries: Vec<(i64, i64, String)> = file
+            .get_subtitle_entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.timespan.start.msecs(), e.timespan.end.msecs(), e.line.unwrap()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (0, 3066, "so this is how\nit works.".to_string()),
+                (3066, 5517, "and then we\ncontinue with a".to_string()),
+                // extended to 17 characters per second
+                (5517, 6400, "longer sentence".to_string()),
+            ]
+        );
+    }
+}
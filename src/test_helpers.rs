@@ -0,0 +1,41 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Helpers for the tests of the subtitle formats.
+
+use crate::{ErrorKind, SubtitleFileInterface};
+use std::fmt::Debug;
+
+/// Returns the error of `parse(input)`, which has to fail.
+pub fn parse_error<I, T, E>(parse: impl FnOnce(&I) -> Result<T, E>, input: &I) -> E
+where
+    I: Debug + ?Sized,
+{
+    match parse(input) {
+        Err(error) => error,
+        Ok(_) => panic!("{:?} was parsed", input),
+    }
+}
+
+/// Returns the data of `file` as string.
+pub fn to_string(file: &impl SubtitleFileInterface) -> String {
+    String::from_utf8(file.to_data().unwrap()).unwrap()
+}
+
+/// Checks that `file` is written as `data` (also after updating it with its own entries) and that updates with
+/// the wrong number of entries are rejected.
+pub fn assert_round_trip(file: &mut impl SubtitleFileInterface, data: &[u8]) {
+    assert_eq!(file.to_data().unwrap(), data);
+    let entries = file.get_subtitle_entries().unwrap();
+    file.update_subtitle_entries(&entries).unwrap();
+    assert_eq!(file.to_data().unwrap(), data);
+
+    if let Some(entry) = entries.first() {
+        let mut too_many = entries.clone();
+        too_many.push(entry.clone());
+        let error = file.update_subtitle_entries(&too_many).unwrap_err();
+        let (expected, found) = (entries.len(), too_many.len());
+        assert_eq!(error.kind(), ErrorKind::EntryCountMismatch { expected, found });
+    }
+}
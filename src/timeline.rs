@@ -0,0 +1,161 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A compact overview of the subtitles of a file for timeline widgets.
+//!
+//! GUI applications can compute a `Timeline` once (instead of walking thousands of entries on every repaint) and
+//! draw the cue density, the gaps and the overlaps from it, or send it to a web frontend with `to_json()`.
+
+use crate::gaps::find_gaps;
+use crate::json::Value;
+use crate::timetypes::{TimeDelta, TimePoint, TimeSpan};
+use crate::SubtitleEntry;
+
+/// The cue density, gaps and overlaps of a list of subtitles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    /// The start of the first subtitle.
+    pub start: TimePoint,
+
+    /// The end of the last subtitle.
+    pub end: TimePoint,
+
+    /// The length of a bucket of `density`.
+    pub bucket_len: TimeDelta,
+
+    /// The number of subtitles that are (at least partly) shown in each bucket, beginning at `start`.
+    pub density: Vec<u32>,
+
+    /// The times between `start` and `end` without any subtitle.
+    pub gaps: Vec<TimeSpan>,
+
+    /// The times where two or more subtitles are shown.
+    pub overlaps: Vec<TimeSpan>,
+}
+
+impl Timeline {
+    /// Computes the timeline of `entries` (in any order) with at most `buckets` density buckets.
+    ///
+    /// Entries without duration are ignored.
+    pub fn new(entries: &[SubtitleEntry], buckets: usize) -> Timeline {
+        let timespans: Vec<TimeSpan> = entries.iter().map(|e| e.timespan).filter(|t| t.start < t.end).collect();
+        let start = timespans.iter().map(|t| t.start).min().unwrap_or(TimePoint::from_msecs(0));
+        let end = timespans.iter().map(|t| t.end).max().unwrap_or(TimePoint::from_msecs(0));
+
+        // every bucket is at least one millisecond long
+        let buckets = buckets.max(1) as i64;
+        let bucket_msecs = ((end - start).msecs() + buckets - 1) / buckets;
+        let bucket_len = TimeDelta::from_msecs(bucket_msecs.max(1));
+        let bucket_count = ((end - start).msecs() + bucket_len.msecs() - 1) / bucket_len.msecs();
+
+        // the density is the prefix sum of the starts and ends of the covered buckets
+        let mut changes = vec![0i64; bucket_count as usize + 1];
+        for timespan in &timespans {
+            let first = (timespan.start - start).msecs() / bucket_len.msecs();
+            let last = ((timespan.end - start).msecs() - 1) / bucket_len.msecs();
+            if let Some(change) = changes.get_mut(first as usize) {
+                *change += 1;
+            }
+            if let Some(change) = changes.get_mut(last as usize + 1) {
+                *change -= 1;
+            }
+        }
+        let mut active = 0;
+        let density = changes
+            .iter()
+            .take(bucket_count as usize)
+            .map(|change| {
+                active += change;
+                active.max(0) as u32
+            })
+            .collect();
+
+        let gap_entries: Vec<SubtitleEntry> = timespans.iter().cloned().map(SubtitleEntry::from).collect();
+        let gaps = find_gaps(&gap_entries, TimeDelta::from_msecs(1))
+            .into_iter()
+            .map(|(_, gap)| gap)
+            .collect();
+
+        Timeline {
+            start,
+            end,
+            bucket_len,
+            density,
+            gaps,
+            overlaps: find_overlaps(&timespans),
+        }
+    }
+
+    /// Returns the timeline as JSON object with all times in milliseconds:
+    ///
+    /// ```text
+    /// {"start":1000,"end":9000,"bucket":2000,"density":[1,1,2,1],"gaps":[[3000,4000]],"overlaps":[[5000,6000]]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let number = |n: i64| Value::Number(n as f64);
+        let spans = |spans: &[TimeSpan]| {
+            Value::Array(
+                spans
+                    .iter()
+                    .map(|span| Value::Array(vec![number(span.start.msecs()), number(span.end.msecs())]))
+                    .collect(),
+            )
+        };
+        Value::Object(vec![
+            ("start".to_string(), number(self.start.msecs())),
+            ("end".to_string(), number(self.end.msecs())),
+            ("bucket".to_string(), number(self.bucket_len.msecs())),
+            (
+                "density".to_string(),
+                Value::Array(self.density.iter().map(|&n| number(i64::from(n))).collect()),
+            ),
+            ("gaps".to_string(), spans(&self.gaps)),
+            ("overlaps".to_string(), spans(&self.overlaps)),
+        ])
+        .to_string()
+    }
+}
+
+/// Returns the (merged) times where at least two of the timespans are active.
+fn find_overlaps(timespans: &[TimeSpan]) -> Vec<TimeSpan> {
+    // ends sort before starts at the same time, so touching subtitles don't overlap
+    let mut events: Vec<(TimePoint, i32)> = timespans.iter().flat_map(|t| vec![(t.start, 1), (t.end, -1)]).collect();
+    events.sort();
+
+    let mut result = Vec::new();
+    let mut active = 0;
+    let mut overlap_start = None;
+    for (time, change) in events {
+        active += change;
+        match overlap_start {
+            None if active >= 2 => overlap_start = Some(time),
+            Some(start) if active < 2 => {
+                overlap_start = None;
+                match result.last_mut() {
+                    Some(TimeSpan { end, .. }) if *end == start => *end = time,
+                    _ => result.push(TimeSpan::new(start, time)),
+                }
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeline_json() {
+        let entry = |start: i64, end: i64| SubtitleEntry::from(TimeSpan::new(TimePoint::from_msecs(start), TimePoint::from_msecs(end)));
+        let entries = vec![entry(4000, 6000), entry(1000, 3000), entry(5000, 9000), entry(7000, 7000)];
+
+        let timeline = Timeline::new(&entries, 4);
+        assert_eq!(
+            timeline.to_json(),
+            r#"{"start":1000,"end":9000,"bucket":2000,"density":[1,1,2,1],"gaps":[[3000,4000]],"overlaps":[[5000,6000]]}"#
+        );
+    }
+}
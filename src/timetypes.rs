@@ -10,29 +10,40 @@ use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 struct Timing(i64 /* number of milliseconds */);
 
 /// The internal timing in `TimePoint` and `TimeDelta` (with all necessary functions and nice Debug information, etc.).
+///
+/// All arithmetic saturates at the limits of `i64`, so absurd values in a file can never cause an overflow panic.
 impl Timing {
-    fn from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> Timing {
-        Timing(ms + 1000 * (secs + 60 * (mins + 60 * hours)))
+    const fn from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> Timing {
+        let total_mins = mins.saturating_add(hours.saturating_mul(60));
+        let total_secs = secs.saturating_add(total_mins.saturating_mul(60));
+        Timing(ms.saturating_add(total_secs.saturating_mul(1000)))
     }
 
-    fn from_msecs(ms: i64) -> Timing {
+    /// Like `from_components()`, but returns `None` instead of saturating.
+    fn checked_from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> Option<Timing> {
+        let total_mins = mins.checked_add(hours.checked_mul(60)?)?;
+        let total_secs = secs.checked_add(total_mins.checked_mul(60)?)?;
+        Some(Timing(ms.checked_add(total_secs.checked_mul(1000)?)?))
+    }
+
+    const fn from_msecs(ms: i64) -> Timing {
         Timing(ms)
     }
 
-    fn from_csecs(cs: i64) -> Timing {
-        Timing(cs * 10)
+    const fn from_csecs(cs: i64) -> Timing {
+        Timing(cs.saturating_mul(10))
     }
 
-    fn from_secs(s: i64) -> Timing {
-        Timing(s * 1000)
+    const fn from_secs(s: i64) -> Timing {
+        Timing(s.saturating_mul(1000))
     }
 
-    fn from_mins(mins: i64) -> Timing {
-        Timing(mins * 1000 * 60)
+    const fn from_mins(mins: i64) -> Timing {
+        Timing(mins.saturating_mul(1000 * 60))
     }
 
-    fn from_hours(h: i64) -> Timing {
-        Timing(h * 1000 * 60 * 60)
+    const fn from_hours(h: i64) -> Timing {
+        Timing(h.saturating_mul(1000 * 60 * 60))
     }
 
     fn msecs(&self) -> i64 {
@@ -82,7 +93,7 @@ impl Timing {
 
 impl Debug for Timing {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "Timing({})", self.to_string())
+        write!(f, "Timing({})", self)
     }
 }
 
@@ -101,36 +112,73 @@ impl Display for Timing {
     }
 }
 
+impl Timing {
+    fn to_human(self, locale: &TimeLocale) -> String {
+        let t = if self.0 < 0 { -self } else { self };
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let (space, secs) = (locale.unit_separator, locale.secs);
+        if t.hours() > 0 {
+            format!(
+                "{}{}{}{} {:02}{}{} {:02}{}{}",
+                sign,
+                t.hours(),
+                space,
+                locale.hours,
+                t.mins_comp(),
+                space,
+                locale.mins,
+                t.secs_comp(),
+                space,
+                secs
+            )
+        } else if t.mins() > 0 {
+            format!("{}{}{}{} {:02}{}{}", sign, t.mins(), space, locale.mins, t.secs_comp(), space, secs)
+        } else {
+            format!("{}{}{}{}", sign, t.secs(), space, secs)
+        }
+    }
+
+    fn to_human_secs(self, decimals: usize, locale: &TimeLocale) -> String {
+        let number = format!("{:.*}", decimals, self.secs_f64());
+        format!(
+            "{}{}{}",
+            number.replace('.', &locale.decimal_separator.to_string()),
+            locale.unit_separator,
+            locale.secs
+        )
+    }
+}
+
 impl Add for Timing {
     type Output = Timing;
     fn add(self, rhs: Timing) -> Timing {
-        Timing(self.0 + rhs.0)
+        Timing(self.0.saturating_add(rhs.0))
     }
 }
 
 impl Sub for Timing {
     type Output = Timing;
     fn sub(self, rhs: Timing) -> Timing {
-        Timing(self.0 - rhs.0)
+        Timing(self.0.saturating_sub(rhs.0))
     }
 }
 
 impl AddAssign for Timing {
     fn add_assign(&mut self, r: Timing) {
-        self.0 += r.0;
+        *self = *self + r;
     }
 }
 
 impl SubAssign for Timing {
     fn sub_assign(&mut self, r: Timing) {
-        self.0 += r.0;
+        *self = *self - r;
     }
 }
 
 impl Neg for Timing {
     type Output = Timing;
     fn neg(self) -> Timing {
-        Timing(-self.0)
+        Timing(self.0.saturating_neg())
     }
 }
 
@@ -148,10 +196,26 @@ pub struct TimeDelta {
     intern: Timing,
 }
 
+/// The components of a timestamp are too large to be represented in milliseconds (like billions of hours).
+///
+/// The `.srt`, `.ssa`/`.ass`, `.idx` and `.vtt` parsers return this error as the cause of their format error
+/// instead of silently saturating the time. The other formats saturate such times at the limits of `i64`
+/// (or reject the timestamp as invalid).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimestampOutOfRange;
+
+impl Display for TimestampOutOfRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "the timestamp is out of range")
+    }
+}
+
+impl std::error::Error for TimestampOutOfRange {}
+
 macro_rules! create_time_type {
     ($i:ident) => {
         impl $i {
-            fn new(t: Timing) -> $i {
+            const fn new(t: Timing) -> $i {
                 $i { intern: t }
             }
 
@@ -159,32 +223,40 @@ macro_rules! create_time_type {
             ///
             /// The components can be negative and/or exceed the its natural limits without error.
             /// For example `from_components(0, 0, 3, -2000)` is the same as `from_components(0, 0, 1, 0)`.
-            pub fn from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> $i {
+            pub const fn from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> $i {
                 Self::new(Timing::from_components(hours, mins, secs, ms))
             }
 
+            /// Like `from_components()`, but fails with `TimestampOutOfRange` if the total number of milliseconds
+            /// would overflow (instead of saturating).
+            pub fn try_from_components(hours: i64, mins: i64, secs: i64, ms: i64) -> Result<$i, TimestampOutOfRange> {
+                Timing::checked_from_components(hours, mins, secs, ms)
+                    .map(Self::new)
+                    .ok_or(TimestampOutOfRange)
+            }
+
             /// Create the time type from a given number of milliseconds.
-            pub fn from_msecs(ms: i64) -> $i {
+            pub const fn from_msecs(ms: i64) -> $i {
                 Self::new(Timing::from_msecs(ms))
             }
 
             /// Create the time type from a given number of hundreth seconds (10 milliseconds).
-            pub fn from_csecs(ms: i64) -> $i {
+            pub const fn from_csecs(ms: i64) -> $i {
                 Self::new(Timing::from_csecs(ms))
             }
 
             /// Create the time type with a given number of seconds.
-            pub fn from_secs(ms: i64) -> $i {
+            pub const fn from_secs(ms: i64) -> $i {
                 Self::new(Timing::from_secs(ms))
             }
 
             /// Create the time type with a given number of minutes.
-            pub fn from_mins(mins: i64) -> $i {
+            pub const fn from_mins(mins: i64) -> $i {
                 Self::new(Timing::from_mins(mins))
             }
 
             /// Create the time type with a given number of hours.
-            pub fn from_hours(mins: i64) -> $i {
+            pub const fn from_hours(mins: i64) -> $i {
                 Self::new(Timing::from_hours(mins))
             }
 
@@ -243,6 +315,17 @@ macro_rules! create_time_type {
                 self.intern.is_negative()
             }
 
+            /// Formats the time for humans like `1 h 02 min 03 s` (leading zero units are left out and
+            /// milliseconds are cut off).
+            pub fn to_human(&self, locale: &TimeLocale) -> String {
+                self.intern.to_human(locale)
+            }
+
+            /// Formats the time in seconds for humans like `83.5 s` (with the given number of decimals).
+            pub fn to_human_secs(&self, decimals: usize, locale: &TimeLocale) -> String {
+                self.intern.to_human_secs(decimals, locale)
+            }
+
             /// Return the absolute value of the current time.
             pub fn abs(&self) -> $i {
                 if self.is_negative() {
@@ -268,6 +351,62 @@ macro_rules! create_time_type {
     };
 }
 
+/// Separators and unit names for formatting times for humans (see `TimePoint::to_human()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLocale {
+    /// The separator between the integer and the fractional part of a number (`.` or `,`).
+    pub decimal_separator: char,
+
+    /// The separator between a number and its unit (usually a space or a no-break space).
+    pub unit_separator: &'static str,
+
+    /// The unit name of hours.
+    pub hours: &'static str,
+
+    /// The unit name of minutes.
+    pub mins: &'static str,
+
+    /// The unit name of seconds.
+    pub secs: &'static str,
+}
+
+impl TimeLocale {
+    /// English formatting like `1 h 02 min 03 s` and `83.5 s`.
+    pub const ENGLISH: TimeLocale = TimeLocale {
+        decimal_separator: '.',
+        unit_separator: " ",
+        hours: "h",
+        mins: "min",
+        secs: "s",
+    };
+
+    /// Formatting with a decimal comma like `83,5 s` (German, French, Spanish, ...).
+    pub const DECIMAL_COMMA: TimeLocale = TimeLocale {
+        decimal_separator: ',',
+        ..TimeLocale::ENGLISH
+    };
+
+    /// Returns the formatting for a language tag like `de` or `pt-BR` (English if the language is unknown).
+    pub fn for_language(language: &str) -> TimeLocale {
+        const DECIMAL_COMMA_LANGUAGES: [&str; 24] = [
+            "bg", "cs", "da", "de", "el", "es", "et", "fi", "fr", "hr", "hu", "id", "it", "lt", "lv", "nb", "nl", "pl", "pt", "ro", "ru", "sk", "sv",
+            "tr",
+        ];
+        let primary = language.split(['-', '_']).next().unwrap_or("").to_ascii_lowercase();
+        if DECIMAL_COMMA_LANGUAGES.contains(&primary.as_str()) {
+            TimeLocale::DECIMAL_COMMA
+        } else {
+            TimeLocale::ENGLISH
+        }
+    }
+}
+
+impl Default for TimeLocale {
+    fn default() -> TimeLocale {
+        TimeLocale::ENGLISH
+    }
+}
+
 create_time_type! {TimePoint}
 create_time_type! {TimeDelta}
 
@@ -328,6 +467,95 @@ impl_add_assign!(TimePoint, TimeDelta);
 impl_sub_assign!(TimeDelta, TimeDelta);
 impl_sub_assign!(TimePoint, TimeDelta);
 
+/// Decides how times are rounded when they are converted to a coarser resolution (like the centiseconds
+/// of `.ssa` files or the frames of `.sub` files).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    /// Round towards negative infinity (the value is truncated for positive times).
+    Floor,
+
+    /// Round towards positive infinity.
+    Ceil,
+
+    /// Round to the nearest value, halfway cases away from zero.
+    Nearest,
+
+    /// Round to the nearest value, halfway cases to the even value ("banker's rounding").
+    Bankers,
+}
+
+impl Default for RoundingMode {
+    /// Rounding to the nearest value ensures that converting back and forth does not drift.
+    fn default() -> RoundingMode {
+        RoundingMode::Nearest
+    }
+}
+
+impl RoundingMode {
+    /// Rounds a floating point number to an integer.
+    pub fn round(self, x: f64) -> i64 {
+        let rounded = match self {
+            RoundingMode::Floor => x.floor(),
+            RoundingMode::Ceil => x.ceil(),
+            RoundingMode::Nearest => x.round(),
+            RoundingMode::Bankers => {
+                let r = x.round();
+                if (x - x.trunc()).abs() == 0.5 && r % 2.0 != 0.0 {
+                    r - x.signum()
+                } else {
+                    r
+                }
+            }
+        };
+        rounded as i64
+    }
+
+    /// Divides two integers (`d` has to be positive) and rounds the exact quotient.
+    pub fn div(self, n: i64, d: i64) -> i64 {
+        let q = n.div_euclid(d);
+        let r = n.rem_euclid(d);
+        if r == 0 {
+            return q;
+        }
+        match self {
+            RoundingMode::Floor => q,
+            RoundingMode::Ceil => q + 1,
+            RoundingMode::Nearest if 2 * r == d => {
+                if n < 0 {
+                    q
+                } else {
+                    q + 1
+                }
+            }
+            RoundingMode::Bankers if 2 * r == d => q + q.rem_euclid(2),
+            RoundingMode::Nearest | RoundingMode::Bankers => {
+                if 2 * r > d {
+                    q + 1
+                } else {
+                    q
+                }
+            }
+        }
+    }
+}
+
+impl TimePoint {
+    /// Converts the time point to centiseconds with the given rounding.
+    pub fn to_csecs_rounded(&self, mode: RoundingMode) -> i64 {
+        mode.div(self.msecs(), 10)
+    }
+
+    /// Converts the time point to a frame number with the given rounding.
+    pub fn to_frame(&self, fps: f64, mode: RoundingMode) -> i64 {
+        mode.round(self.msecs() as f64 * fps / 1000.0)
+    }
+
+    /// Creates the time point at which a frame begins (rounded to milliseconds).
+    pub fn from_frame(frame: i64, fps: f64, mode: RoundingMode) -> TimePoint {
+        TimePoint::from_msecs(mode.round(frame as f64 * 1000.0 / fps))
+    }
+}
+
 /// A time span (e.g. time in which a subtitle is shown).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TimeSpan {
@@ -341,13 +569,18 @@ pub struct TimeSpan {
 impl TimeSpan {
     /// Constructor of `TimeSpan`s.
     pub fn new(start: TimePoint, end: TimePoint) -> TimeSpan {
-        TimeSpan { start: start, end: end }
+        TimeSpan { start, end }
     }
 
     /// Get the length of the `TimeSpan` (can be negative).
     pub fn len(&self) -> TimeDelta {
         self.end - self.start
     }
+
+    /// Returns `true` if `other` lies completely within this time span.
+    pub fn contains(&self, other: TimeSpan) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
 }
 
 impl Add<TimeDelta> for TimeSpan {
@@ -380,6 +613,39 @@ impl SubAssign<TimeDelta> for TimeSpan {
 
 #[cfg(test)]
 mod tests {
+    use super::{RoundingMode, TimeDelta, TimePoint};
+
+    #[test]
+    fn test_rounding_modes() {
+        let modes = [RoundingMode::Floor, RoundingMode::Ceil, RoundingMode::Nearest, RoundingMode::Bankers];
+        let div = |n: i64| modes.iter().map(|m| m.div(n, 10)).collect::<Vec<_>>();
+        assert_eq!(div(1234), vec![123, 124, 123, 123]);
+        assert_eq!(div(1235), vec![123, 124, 124, 124]);
+        assert_eq!(div(1245), vec![124, 125, 125, 124]);
+        assert_eq!(div(-1235), vec![-124, -123, -124, -124]);
+
+        let round = |x: f64| modes.iter().map(|m| m.round(x)).collect::<Vec<_>>();
+        assert_eq!(round(2.5), vec![2, 3, 3, 2]);
+        assert_eq!(round(-2.5), vec![-3, -2, -3, -2]);
+
+        // arithmetic saturates instead of overflowing
+        let max = TimePoint::from_hours(i64::MAX);
+        assert_eq!(max.msecs(), i64::MAX);
+        assert_eq!((max + TimeDelta::from_secs(1) - max).msecs(), 0);
+        assert_eq!((-TimePoint::from_msecs(i64::MIN)).msecs(), i64::MAX);
+        assert_eq!(TimePoint::try_from_components(1, 2, 3, 4), Ok(TimePoint::from_components(1, 2, 3, 4)));
+        assert_eq!(
+            TimePoint::try_from_components(3_000_000_000_000, 0, 0, 0),
+            Err(super::TimestampOutOfRange)
+        );
+
+        // converting frames back and forth must not drift
+        for frame in 0..1000 {
+            let t = TimePoint::from_frame(frame, 23.976, RoundingMode::Nearest);
+            assert_eq!(t.to_frame(23.976, RoundingMode::Nearest), frame);
+        }
+    }
+
     #[test]
     fn test_timing_display() {
         let t = -super::Timing::from_components(12, 59, 29, 450);
@@ -388,4 +654,33 @@ mod tests {
         let t = super::Timing::from_msecs(0);
         assert_eq!(t.to_string(), "0:00:00.000".to_string());
     }
+
+    #[test]
+    fn test_sub_assign() {
+        use super::{TimeDelta, TimePoint, TimeSpan};
+
+        let mut t = TimePoint::from_msecs(5000);
+        t -= TimeDelta::from_msecs(1500);
+        assert_eq!(t, TimePoint::from_msecs(3500));
+
+        let mut span = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        span -= TimeDelta::from_msecs(250);
+        assert_eq!(span, TimeSpan::new(TimePoint::from_msecs(750), TimePoint::from_msecs(1750)));
+    }
+
+    #[test]
+    fn test_human_display() {
+        use super::TimeLocale;
+
+        let t = TimePoint::from_components(1, 2, 3, 999);
+        assert_eq!(t.to_human(&TimeLocale::ENGLISH), "1 h 02 min 03 s");
+        assert_eq!(TimeDelta::from_msecs(-83_500).to_human(&TimeLocale::ENGLISH), "-1 min 23 s");
+        assert_eq!(TimeDelta::from_msecs(2_400).to_human(&TimeLocale::default()), "2 s");
+        assert_eq!(TimeDelta::from_msecs(83_500).to_human_secs(1, &TimeLocale::ENGLISH), "83.5 s");
+        assert_eq!(
+            TimeDelta::from_msecs(83_500).to_human_secs(2, &TimeLocale::for_language("de-AT")),
+            "83,50 s"
+        );
+        assert_eq!(TimeLocale::for_language("en_US"), TimeLocale::ENGLISH);
+    }
 }
@@ -0,0 +1,174 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Non-fatal issues that were found while parsing a file.
+//!
+//! The `parse_with_warnings()` functions of the formats (and `parse_str_with_warnings()`) return these along
+//! with the successfully parsed file, so tools can show them to the user without rejecting the file.
+
+use crate::SubtitleEntry;
+use std::fmt;
+
+/// What is suspicious about the timing of an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampIssue {
+    /// The entry ends before it starts.
+    EndBeforeStart,
+
+    /// The entry starts before the beginning of the video.
+    Negative,
+}
+
+/// The kind of a `Warning`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A key in a header section (like `[Script Info]` in `.ssa` files) is not known to this library.
+    UnknownHeaderKey {
+        /// The header section.
+        section: String,
+
+        /// The unknown key.
+        key: String,
+    },
+
+    /// A line was skipped because it has no meaning in its position (it is still preserved if the format is non-destructive).
+    IgnoredLine {
+        /// The content of the line.
+        line: String,
+    },
+
+    /// The timing of an entry is suspicious.
+    SuspiciousTimestamp {
+        /// The position of the entry in `get_subtitle_entries()`.
+        entry_idx: usize,
+
+        /// What is wrong with the timing.
+        issue: TimestampIssue,
+    },
+
+    /// The index of a `.srt` entry does not continue the previous index.
+    UnexpectedIndex {
+        /// The index that was expected.
+        expected: i64,
+
+        /// The index in the file.
+        found: i64,
+    },
+}
+
+/// A non-fatal issue in a subtitle file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// The line of the issue (starting at 0), if it belongs to a single line.
+    pub line_num: Option<usize>,
+
+    /// What the issue is.
+    pub kind: WarningKind,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line_num) = self.line_num {
+            write!(f, "line {}: ", line_num)?;
+        }
+        match self.kind {
+            WarningKind::UnknownHeaderKey { ref section, ref key } => write!(f, "unknown key `{}` in section `[{}]`", key, section),
+            WarningKind::IgnoredLine { ref line } => write!(f, "ignored line '{}'", line),
+            WarningKind::SuspiciousTimestamp { entry_idx, issue } => match issue {
+                TimestampIssue::EndBeforeStart => write!(f, "subtitle entry {} ends before it starts", entry_idx),
+                TimestampIssue::Negative => write!(f, "subtitle entry {} starts at a negative time", entry_idx),
+            },
+            WarningKind::UnexpectedIndex { expected, found } => write!(f, "expected index {}, found {}", expected, found),
+        }
+    }
+}
+
+/// A collection of the warnings for a single file (in the order they were found).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Warnings {
+    v: Vec<Warning>,
+}
+
+impl Warnings {
+    /// Creates an empty collection.
+    pub fn new() -> Warnings {
+        Warnings::default()
+    }
+
+    /// Adds a warning.
+    pub fn push(&mut self, line_num: Option<usize>, kind: WarningKind) {
+        self.v.push(Warning { line_num, kind });
+    }
+
+    /// Returns `true` if no issues were found.
+    pub fn is_empty(&self) -> bool {
+        self.v.is_empty()
+    }
+
+    /// Returns the number of warnings.
+    pub fn len(&self) -> usize {
+        self.v.len()
+    }
+
+    /// Iterates over all warnings.
+    pub fn iter(&self) -> std::slice::Iter<'_, Warning> {
+        self.v.iter()
+    }
+
+    /// Adds `SuspiciousTimestamp` warnings for all entries with suspicious timings.
+    pub fn check_timestamps(&mut self, entries: &[SubtitleEntry]) {
+        for (entry_idx, entry) in entries.iter().enumerate() {
+            if entry.timespan.start.is_negative() {
+                let issue = TimestampIssue::Negative;
+                self.push(None, WarningKind::SuspiciousTimestamp { entry_idx, issue });
+            }
+            if entry.timespan.end < entry.timespan.start {
+                let issue = TimestampIssue::EndBeforeStart;
+                self.push(None, WarningKind::SuspiciousTimestamp { entry_idx, issue });
+            }
+        }
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.v.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Warnings {
+    type Item = &'a Warning;
+    type IntoIter = std::slice::Iter<'a, Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.v.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_str_with_warnings, SubtitleFormat};
+
+    #[test]
+    fn warnings_from_parsers() {
+        let srt = "1\n00:00:02,000 --> 00:00:01,000\na\n\n3\n00:00:03,000 --> 00:00:04,000\nb\n";
+        let (_, warnings) = parse_str_with_warnings(SubtitleFormat::SubRip, srt, 25.0).unwrap();
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec!["line 4: expected index 2, found 3", "subtitle entry 0 ends before it starts"]
+        );
+
+        let ssa = "[Script Info]\nTitle: x\nFoo: bar\n; comment\n\n[Events]\nFormat: Start, End, Text\nDialogue: 0:00:01.00,0:00:02.00,a\ngarbage\n";
+        let (_, warnings) = parse_str_with_warnings(SubtitleFormat::SubStationAlpha, ssa, 25.0).unwrap();
+        let messages: Vec<String> = warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec!["line 2: unknown key `Foo` in section `[Script Info]`", "line 8: ignored line 'garbage'"]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The words of a subtitle with the time when they are spoken.
+//!
+//! Karaoke tags (`{\k50}` in `.ssa` files, also `\kf`, `\ko` and `\K`) give every syllable a duration, so the words
+//! are timed by them. Without karaoke tags, the time of the subtitle is divided linearly by the number of characters
+//! of the words. This is good enough for a karaoke-style preview or a coarse alignment with a transcript, without
+//! running a forced aligner:
+//!
+//! ```
+//! use subparse::timetypes::{TimePoint, TimeSpan};
+//! use subparse::words::cue_words;
+//! use subparse::SubtitleEntry;
+//!
+//! let mut entry = SubtitleEntry::from(TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000)));
+//! entry.line = Some("{\\k30}Hel{\\k20}lo {\\k50}world".to_string());
+//! let words: Vec<(String, i64, i64)> = cue_words(&entry).map(|w| (w.text, w.timespan.start.msecs(), w.timespan.end.msecs())).collect();
+//! assert_eq!(words, vec![("Hello".to_string(), 1000, 1500), ("world".to_string(), 1500, 2000)]);
+//! ```
+
+use crate::formats::ssa_override::karaoke_durations;
+use crate::music::tag_mask;
+use crate::timetypes::{TimeDelta, TimeSpan};
+use crate::SubtitleEntry;
+use std::ops::Range;
+
+/// A word of a subtitle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueWord {
+    /// When the word is spoken.
+    pub timespan: TimeSpan,
+
+    /// The word without tags and surrounding whitespace.
+    pub text: String,
+
+    /// The timing is divided by the number of characters, because the subtitle has no karaoke tags.
+    pub interpolated: bool,
+}
+
+/// The visible characters of a subtitle text with the karaoke syllable they belong to.
+struct TimedText {
+    /// Every character and the index of its syllable (`None` before the first karaoke tag).
+    chars: Vec<(char, Option<usize>)>,
+
+    /// The offset from the start of the subtitle and the duration of every syllable.
+    syllables: Vec<(TimeDelta, TimeDelta)>,
+}
+
+/// Splits a subtitle text into its visible characters and karaoke syllables.
+fn timed_text(text: &str) -> TimedText {
+    let text = text.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " ");
+    let mut result = TimedText {
+        chars: Vec::new(),
+        syllables: Vec::new(),
+    };
+    let mut block: Option<String> = None;
+    for (c, is_tag) in text.chars().zip(tag_mask(&text)) {
+        match (is_tag, c, block.as_mut()) {
+            (false, _, _) => result.chars.push((c, result.syllables.len().checked_sub(1))),
+            (true, '{', _) => block = Some(String::new()),
+            (true, '}', Some(content)) => {
+                for duration in karaoke_durations(content) {
+                    let offset = result
+                        .syllables
+                        .last()
+                        .map_or(TimeDelta::from_msecs(0), |&(offset, duration)| offset + duration);
+                    result.syllables.push((offset, duration));
+                }
+                block = None;
+            }
+            (true, _, Some(content)) => content.push(c),
+            (true, _, None) => {}
+        }
+    }
+    result
+}
+
+/// Returns the words of a subtitle with their timing (nothing for subtitles without text).
+///
+/// The words are timed by the karaoke tags of the text; a word which consists of several syllables (like
+/// `Hel{\k20}lo`) lasts from the start of its first to the end of its last syllable. Without karaoke tags, every
+/// word gets a part of the subtitle time which is proportional to its number of characters, and is marked as
+/// `interpolated`.
+pub fn cue_words(entry: &SubtitleEntry) -> impl Iterator<Item = CueWord> {
+    let timed = timed_text(entry.line.as_deref().unwrap_or(""));
+
+    // the characters of every word and the number of word characters before it
+    let mut words: Vec<(Range<usize>, usize)> = Vec::new();
+    let mut char_count = 0;
+    for (idx, &(c, _)) in timed.chars.iter().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        match words.last_mut() {
+            Some((word, _)) if word.end == idx => word.end += 1,
+            _ => words.push((idx..idx + 1, char_count)),
+        }
+        char_count += 1;
+    }
+
+    let start = entry.timespan.start;
+    let duration = entry.timespan.len();
+    let interpolated = timed.syllables.is_empty();
+    let syllable_span = |syllable: Option<usize>| match syllable.and_then(|idx| timed.syllables.get(idx)) {
+        Some(&(offset, length)) => (start + offset, start + offset + length),
+        None => (start, start),
+    };
+    let result: Vec<CueWord> = words
+        .into_iter()
+        .map(|(range, chars_before)| {
+            let chars = timed.chars.get(range).unwrap_or(&[]);
+            let timespan = if interpolated {
+                let at = |count: usize| start + TimeDelta::from_msecs(duration.msecs() * count as i64 / char_count as i64);
+                TimeSpan::new(at(chars_before), at(chars_before + chars.len()))
+            } else {
+                let first = syllable_span(chars.first().and_then(|&(_, syllable)| syllable)).0;
+                let last = syllable_span(chars.last().and_then(|&(_, syllable)| syllable)).1;
+                TimeSpan::new(first, last.min(entry.timespan.end).max(first))
+            };
+            CueWord {
+                timespan,
+                text: chars.iter().map(|&(c, _)| c).collect(),
+                interpolated,
+            }
+        })
+        .collect();
+    result.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetypes::TimePoint;
+
+    fn words(text: &str) -> Vec<(String, i64, i64, bool)> {
+        let timespan = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        cue_words(&SubtitleEntry::new(timespan, text.to_string()))
+            .map(|word| (word.text, word.timespan.start.msecs(), word.timespan.end.msecs(), word.interpolated))
+            .collect()
+    }
+
+    #[test]
+    fn interpolated_words() {
+        assert_eq!(
+            words("<i>Good</i>\nmorning!"),
+            vec![("Good".to_string(), 1000, 1333, true), ("morning!".to_string(), 1333, 2000, true)]
+        );
+    }
+
+    #[test]
+    fn karaoke_words() {
+        // the pause before "One" has no text, the last syllable is cut at the end of the subtitle
+        assert_eq!(
+            words("{\\an8}{\\k20}{\\kf30}One\\N{\\K40}two {\\k90}three"),
+            vec![
+                ("One".to_string(), 1200, 1500, false),
+                ("two".to_string(), 1500, 1900, false),
+                ("three".to_string(), 1900, 2000, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_karaoke_tags_are_interpolated() {
+        assert_eq!(
+            words("{\\kabc}ab {\\t(\\k50)}cd"),
+            vec![("ab".to_string(), 1000, 1500, true), ("cd".to_string(), 1500, 2000, true)]
+        );
+    }
+
+    #[test]
+    fn entries_without_words() {
+        assert!(words("").is_empty());
+        assert!(words("{\\k50} ").is_empty());
+        let timespan = TimeSpan::new(TimePoint::from_msecs(1000), TimePoint::from_msecs(2000));
+        assert!(cue_words(&SubtitleEntry::from(timespan)).next().is_none());
+    }
+}